@@ -0,0 +1,217 @@
+//
+// imag - the personal information management suite for the commandline
+// Copyright (C) 2015, 2016 Matthias Beyer <mail@beyermatthias.de> and contributors
+//
+// This library is free software; you can redistribute it and/or
+// modify it under the terms of the GNU Lesser General Public
+// License as published by the Free Software Foundation; version
+// 2.1 of the License.
+//
+// This library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+// Lesser General Public License for more details.
+//
+// You should have received a copy of the GNU Lesser General Public
+// License along with this library; if not, write to the Free Software
+// Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA  02110-1301  USA
+//
+
+#![deny(
+    non_camel_case_types,
+    non_snake_case,
+    path_statements,
+    trivial_numeric_casts,
+    unstable_features,
+    unused_allocation,
+    unused_import_braces,
+    unused_imports,
+    unused_must_use,
+    unused_mut,
+    unused_qualifications,
+    while_true,
+)]
+
+extern crate clap;
+extern crate filters;
+#[macro_use] extern crate log;
+extern crate semver;
+#[macro_use] extern crate version;
+
+extern crate libimagbibliography;
+extern crate libimagentryfilter;
+extern crate libimagrt;
+extern crate libimagerror;
+extern crate libimagutil;
+
+use std::fs::File;
+use std::io::{Read, Write};
+use std::io::stdout;
+use std::path::PathBuf;
+use std::process::exit;
+
+use filters::filter::Filter;
+
+use libimagbibliography::citation::{Citation, entry_key, export_where};
+use libimagentryfilter::cli::get_filter;
+use libimagentryfilter::query::Query;
+use libimagrt::runtime::Runtime;
+use libimagrt::setup::generate_runtime_setup;
+use libimagerror::trace::{MapErrTrace, trace_error};
+use libimagutil::import::ImportSummary;
+use libimagutil::info_result::*;
+use libimagutil::warn_exit::warn_exit;
+
+mod ui;
+use ui::build_ui;
+
+fn main() {
+    let rt = generate_runtime_setup("imag-bib",
+                                    &version!()[..],
+                                    "Bibliography management with BibTeX import and export",
+                                    build_ui);
+
+    rt.cli()
+        .subcommand_name()
+        .map(|name| {
+            debug!("Call: {}", name);
+            match name {
+                "import" => import(&rt),
+                "export" => export(&rt),
+                "list"   => list(&rt),
+                "show"   => show(&rt),
+                "attach" => attach(&rt),
+                "link"   => link(&rt),
+                _        => {
+                    debug!("Unknown command"); // More error handling
+                },
+            };
+        });
+}
+
+fn key_from_cli<'a>(rt: &'a Runtime, subcmd: &str) -> &'a str {
+    rt.cli().subcommand_matches(subcmd).unwrap().value_of("key").unwrap() // enforced by clap
+}
+
+fn get_citation<'a>(rt: &'a Runtime, key: &str) -> Citation<'a> {
+    match Citation::get(rt.store(), key) {
+        Ok(Some(citation)) => citation,
+        Ok(None)           => warn_exit(&format!("No citation with key '{}'", key), 1),
+        Err(e)             => {
+            trace_error(&e);
+            exit(1)
+        },
+    }
+}
+
+fn import(rt: &Runtime) {
+    let scmd = rt.cli().subcommand_matches("import").unwrap();
+    let mut summary = ImportSummary::new();
+
+    for path in scmd.values_of("file").unwrap() { // enforced by clap
+        let mut text = String::new();
+        File::open(path)
+            .and_then(|mut f| f.read_to_string(&mut text))
+            .map_err_trace_exit(1)
+            .ok();
+
+        let imported = Citation::import_str(rt.store(), &text)
+            .map_err_trace_exit(1)
+            .unwrap(); // safe by map_err_trace_exit()
+
+        summary.add_all(imported);
+    }
+
+    println!("{}", summary);
+}
+
+fn export(rt: &Runtime) {
+    let scmd = rt.cli().subcommand_matches("export").unwrap();
+
+    let keys = scmd.values_of("key").map(|keys| keys.map(String::from).collect::<Vec<_>>());
+    let tags = scmd.values_of("tag")
+        .map(|tags| tags.map(|t| Query::parse(&format!("tag:{}", t))).collect::<Result<Vec<_>, _>>())
+        .unwrap_or(Ok(vec![]))
+        .map_err_trace_exit(1)
+        .unwrap(); // safe by map_err_trace_exit()
+    let query = get_filter(scmd).map_err_trace_exit(1).unwrap(); // safe by map_err_trace_exit()
+
+    let bibtex = export_where(rt.store(), |e| {
+        let key_matches = match keys {
+            Some(ref keys) => entry_key(e).map(|k| keys.contains(&k)).unwrap_or(false),
+            None           => true,
+        };
+
+        key_matches &&
+            tags.iter().all(|t| t.filter(e)) &&
+            query.as_ref().map(|q| q.filter(e)).unwrap_or(true)
+    });
+    let bibtex = bibtex.map_err_trace_exit(1).unwrap(); // safe by map_err_trace_exit()
+
+    let mut out : Box<Write> = match scmd.value_of("out") {
+        Some(path) => Box::new(File::create(path).map_err_trace_exit(1).unwrap()), // safe by map_err_trace_exit()
+        None       => Box::new(stdout()),
+    };
+
+    out.write_all(bibtex.as_bytes()).map_err_trace_exit(1).ok();
+}
+
+fn list(rt: &Runtime) {
+    let mut citations = Citation::all(rt.store())
+        .map_err_trace_exit(1)
+        .unwrap() // safe by map_err_trace_exit()
+        .filter_map(|c| c.map_err_trace().ok())
+        .filter_map(|c| c.to_bib_entry().map_err_trace().ok())
+        .collect::<Vec<_>>();
+
+    citations.sort_by(|a, b| a.key.cmp(&b.key));
+    for c in citations {
+        let get = |field: &str| c.fields.get(field).cloned().unwrap_or(String::new());
+        println!("{} | {} | {} | {}", c.key, get("author"), get("year"), get("title"));
+    }
+}
+
+fn show(rt: &Runtime) {
+    let citation = get_citation(rt, key_from_cli(rt, "show"));
+    let entry    = citation.to_bib_entry().map_err_trace_exit(1).unwrap(); // safe by map_err_trace_exit()
+
+    print!("{}", entry.to_bibtex());
+
+    for pdf in citation.pdfs(rt.store()).map_err_trace_exit(1).unwrap() { // safe by map_err_trace_exit()
+        println!("PDF:    {}", pdf.display());
+    }
+
+    for link in citation.linked_entries().map_err_trace_exit(1).unwrap() { // safe by map_err_trace_exit()
+        println!("Linked: {}", link);
+    }
+}
+
+fn attach(rt: &Runtime) {
+    let scmd         = rt.cli().subcommand_matches("attach").unwrap();
+    let file         = PathBuf::from(scmd.value_of("file").unwrap()); // enforced by clap
+    let mut citation = get_citation(rt, key_from_cli(rt, "attach"));
+
+    citation.attach_pdf(rt.store(), file)
+        .map_err_trace_exit(1)
+        .map_info_str("Ok")
+        .ok();
+}
+
+fn link(rt: &Runtime) {
+    let scmd         = rt.cli().subcommand_matches("link").unwrap();
+    let mut citation = get_citation(rt, key_from_cli(rt, "link"));
+
+    for id in scmd.values_of("entry").unwrap() { // enforced by clap
+        match rt.store().get(PathBuf::from(id)) {
+            Ok(Some(mut entry)) => {
+                citation.link_entry(&mut entry)
+                    .map_err_trace()
+                    .map_info_str("Ok")
+                    .ok();
+            },
+            Ok(None) => warn!("No entry '{}'", id),
+            Err(e)   => trace_error(&e),
+        }
+    }
+}
+
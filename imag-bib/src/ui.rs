@@ -0,0 +1,101 @@
+//
+// imag - the personal information management suite for the commandline
+// Copyright (C) 2015, 2016 Matthias Beyer <mail@beyermatthias.de> and contributors
+//
+// This library is free software; you can redistribute it and/or
+// modify it under the terms of the GNU Lesser General Public
+// License as published by the Free Software Foundation; version
+// 2.1 of the License.
+//
+// This library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+// Lesser General Public License for more details.
+//
+// You should have received a copy of the GNU Lesser General Public
+// License along with this library; if not, write to the Free Software
+// Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA  02110-1301  USA
+//
+
+use clap::{Arg, App, SubCommand};
+
+use libimagentryfilter::cli::filter_argument;
+
+fn key_arg<'a, 'b>(help: &'a str) -> Arg<'a, 'b> {
+    Arg::with_name("key")
+        .long("key")
+        .short("k")
+        .takes_value(true)
+        .required(true)
+        .help(help)
+        .value_name("KEY")
+}
+
+pub fn build_ui<'a>(app: App<'a, 'a>) -> App<'a, 'a> {
+    app
+        .subcommand(SubCommand::with_name("import")
+                   .about("Import BibTeX files. Citations with the same key are updated")
+                   .version("0.1")
+                   .arg(Arg::with_name("file")
+                        .index(1)
+                        .takes_value(true)
+                        .required(true)
+                        .multiple(true)
+                        .help("The BibTeX (.bib) files to import")
+                        .value_name("FILE")))
+
+        .subcommand(SubCommand::with_name("export")
+                   .about("Export citations to BibTeX. Without filters, all citations are exported")
+                   .version("0.1")
+                   .arg(key_arg("Export the citation with this key")
+                        .required(false)
+                        .multiple(true))
+                   .arg(Arg::with_name("tag")
+                        .long("tag")
+                        .short("t")
+                        .takes_value(true)
+                        .required(false)
+                        .multiple(true)
+                        .help("Only export citations with this tag")
+                        .value_name("TAG"))
+                   .arg(filter_argument())
+                   .arg(Arg::with_name("out")
+                        .long("out")
+                        .short("o")
+                        .takes_value(true)
+                        .required(false)
+                        .help("Write to this file instead of stdout")
+                        .value_name("FILE")))
+
+        .subcommand(SubCommand::with_name("list")
+                   .about("List all citations")
+                   .version("0.1"))
+
+        .subcommand(SubCommand::with_name("show")
+                   .about("Show a citation with its attached files and linked entries")
+                   .version("0.1")
+                   .arg(key_arg("Show the citation with this key")))
+
+        .subcommand(SubCommand::with_name("attach")
+                   .about("Attach a (PDF) file to a citation")
+                   .version("0.1")
+                   .arg(key_arg("Attach the file to the citation with this key"))
+                   .arg(Arg::with_name("file")
+                        .index(1)
+                        .takes_value(true)
+                        .required(true)
+                        .help("The file to attach")
+                        .value_name("FILE")))
+
+        .subcommand(SubCommand::with_name("link")
+                   .about("Link entries (like notes) to a citation")
+                   .version("0.1")
+                   .arg(key_arg("Link to the citation with this key"))
+                   .arg(Arg::with_name("entry")
+                        .index(1)
+                        .takes_value(true)
+                        .required(true)
+                        .multiple(true)
+                        .help("The ids of the entries to link, like 'notes/name'")
+                        .value_name("ENTRY")))
+}
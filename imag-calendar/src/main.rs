@@ -0,0 +1,262 @@
+//
+// imag - the personal information management suite for the commandline
+// Copyright (C) 2015, 2016 Matthias Beyer <mail@beyermatthias.de> and contributors
+//
+// This library is free software; you can redistribute it and/or
+// modify it under the terms of the GNU Lesser General Public
+// License as published by the Free Software Foundation; version
+// 2.1 of the License.
+//
+// This library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+// Lesser General Public License for more details.
+//
+// You should have received a copy of the GNU Lesser General Public
+// License along with this library; if not, write to the Free Software
+// Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA  02110-1301  USA
+//
+
+#![deny(
+    non_camel_case_types,
+    non_snake_case,
+    path_statements,
+    trivial_numeric_casts,
+    unstable_features,
+    unused_allocation,
+    unused_import_braces,
+    unused_imports,
+    unused_must_use,
+    unused_mut,
+    unused_qualifications,
+    while_true,
+)]
+
+extern crate chrono;
+extern crate clap;
+#[macro_use] extern crate log;
+extern crate semver;
+#[macro_use] extern crate version;
+
+extern crate libimagcalendar;
+extern crate libimagrt;
+extern crate libimagerror;
+extern crate libimagtimeui;
+extern crate libimagutil;
+
+use std::fs::File;
+use std::io::Write;
+use std::io::stdout;
+use std::path::PathBuf;
+use std::process::exit;
+
+use chrono::Datelike;
+use chrono::Duration;
+use chrono::naive::date::NaiveDate;
+use chrono::naive::datetime::NaiveDateTime;
+use chrono::offset::local::Local;
+
+use libimagcalendar::event::{Event, ImportMode, Occurrence, agenda, export};
+use libimagrt::runtime::Runtime;
+use libimagrt::setup::generate_runtime_setup;
+use libimagerror::trace::{MapErrTrace, trace_error};
+use libimagtimeui::date::Date;
+use libimagtimeui::parse::Parse;
+use libimagutil::import::ImportSummary;
+use libimagutil::warn_exit::warn_exit;
+
+mod ui;
+use ui::build_ui;
+
+fn main() {
+    let rt = generate_runtime_setup("imag-calendar",
+                                    &version!()[..],
+                                    "Calendar with iCalendar import and export",
+                                    build_ui);
+
+    rt.cli()
+        .subcommand_name()
+        .map(|name| {
+            debug!("Call: {}", name);
+            match name {
+                "import" => import(&rt),
+                "agenda" => show_agenda(&rt),
+                "list"   => list(&rt),
+                "show"   => show(&rt),
+                "export" => export_events(&rt),
+                _        => {
+                    debug!("Unknown command"); // More error handling
+                },
+            };
+        });
+}
+
+fn parse_date(s: &str) -> NaiveDate {
+    Date::parse(s)
+        .and_then(|d| NaiveDate::from_ymd_opt(d.year(), d.month(), d.day()))
+        .unwrap_or_else(|| warn_exit(&format!("Cannot parse date '{}', expected YYYY-MM-DD", s), 1))
+}
+
+fn get_event<'a>(rt: &'a Runtime, uid: &str) -> Event<'a> {
+    match Event::get(rt.store(), uid) {
+        Ok(Some(event)) => event,
+        Ok(None)        => warn_exit(&format!("No event with UID '{}'", uid), 1),
+        Err(e)          => {
+            trace_error(&e);
+            exit(1)
+        },
+    }
+}
+
+fn import(rt: &Runtime) {
+    let scmd = rt.cli().subcommand_matches("import").unwrap();
+    let mode = if scmd.is_present("ref") { ImportMode::Reference } else { ImportMode::Copy };
+    let mut summary = ImportSummary::new();
+
+    for path in scmd.values_of("file").unwrap() { // enforced by clap
+        let imported = Event::import_file(rt.store(), &PathBuf::from(path), mode)
+            .map_err_trace_exit(1)
+            .unwrap(); // safe by map_err_trace_exit()
+
+        summary.add_all(imported);
+    }
+
+    println!("{}", summary);
+}
+
+/// Get the first day of the week or month of `date` and the first day after it
+fn period(date: NaiveDate, week: bool, month: bool) -> (NaiveDate, NaiveDate) {
+    if week {
+        let monday = date - Duration::days(date.weekday().num_days_from_monday() as i64);
+        (monday, monday + Duration::weeks(1))
+    } else if month {
+        let first = NaiveDate::from_ymd(date.year(), date.month(), 1);
+        let next  = if date.month() == 12 {
+            NaiveDate::from_ymd(date.year() + 1, 1, 1)
+        } else {
+            NaiveDate::from_ymd(date.year(), date.month() + 1, 1)
+        };
+        (first, next)
+    } else {
+        (date, date + Duration::days(1))
+    }
+}
+
+fn format_occurrence(o: &Occurrence) -> String {
+    let time = if o.all_day {
+        String::from("all day")
+    } else if o.start == o.end {
+        o.start.format("%H:%M").to_string()
+    } else if o.start.date() == o.end.date() {
+        format!("{}-{}", o.start.format("%H:%M"), o.end.format("%H:%M"))
+    } else {
+        format!("{}-{}", o.start.format("%H:%M"), o.end.format("%Y-%m-%d %H:%M"))
+    };
+
+    let mut line = format!("  {:<12} ", time);
+    if o.todo {
+        line.push_str("[todo] ");
+    }
+    line.push_str(o.summary.as_ref().map(|s| &s[..]).unwrap_or("<no summary>"));
+    if let Some(ref location) = o.location {
+        line.push_str(&format!(" ({})", location));
+    }
+    line
+}
+
+fn show_agenda(rt: &Runtime) {
+    let scmd = rt.cli().subcommand_matches("agenda").unwrap();
+    let date = scmd.value_of("date")
+        .map(parse_date)
+        .unwrap_or_else(|| Local::today().naive_local());
+
+    let (from, to) = period(date, scmd.is_present("week"), scmd.is_present("month"));
+    let occurrences = agenda(rt.store(), from.and_hms(0, 0, 0), to.and_hms(0, 0, 0))
+        .map_err_trace_exit(1)
+        .unwrap(); // safe by map_err_trace_exit()
+
+    let mut current = None;
+    for o in occurrences {
+        // Events which started before the period are listed at its first day
+        let day = ::std::cmp::max(o.start.date(), from);
+        if current != Some(day) {
+            println!("{}", day.format("%a %Y-%m-%d"));
+            current = Some(day);
+        }
+        println!("{}", format_occurrence(&o));
+    }
+}
+
+fn print_event(event: &Event) {
+    let uid     = event.uid().map_err_trace().unwrap_or(String::from("<unknown>"));
+    let start   = event.start().map_err_trace().ok().and_then(|s| s)
+        .or(event.due().map_err_trace().ok().and_then(|d| d))
+        .map(|s| s.format("%Y-%m-%d %H:%M").to_string())
+        .unwrap_or(String::new());
+    let summary = event.summary().map_err_trace().ok().and_then(|s| s).unwrap_or(String::new());
+    println!("{} | {} | {}", uid, start, summary);
+}
+
+fn list(rt: &Runtime) {
+    Event::all(rt.store())
+        .map_err_trace_exit(1)
+        .unwrap() // safe by map_err_trace_exit()
+        .filter_map(|e| e.map_err_trace().ok())
+        .filter(|e| e.recurrence_id().map_err_trace().ok().and_then(|r| r).is_none())
+        .map(|e| print_event(&e))
+        .collect::<Vec<_>>();
+}
+
+fn show(rt: &Runtime) {
+    let uid   = rt.cli().subcommand_matches("show").unwrap().value_of("uid").unwrap(); // enforced by clap
+    let event = get_event(rt, uid);
+
+    let print_opt = |key: &str, value: Option<String>| if let Some(v) = value {
+        println!("{:<13} {}", key, v);
+    };
+    let fmt = |dt: Option<NaiveDateTime>| dt.map(|dt| dt.to_string());
+
+    println!("{:<13} {}", "UID:", uid);
+    print_opt("Summary:",     event.summary().map_err_trace_exit(1).unwrap());
+    print_opt("Location:",    event.location().map_err_trace_exit(1).unwrap());
+    print_opt("Status:",      event.status().map_err_trace_exit(1).unwrap());
+    print_opt("Start:",       fmt(event.start().map_err_trace_exit(1).unwrap()));
+    print_opt("End:",         fmt(event.end().map_err_trace_exit(1).unwrap()));
+    print_opt("Due:",         fmt(event.due().map_err_trace_exit(1).unwrap()));
+    print_opt("Recurrence:",  event.recurrence().map_err_trace().ok()
+              .and_then(|r| r)
+              .map(|r| format!("{:?}", r)));
+    print_opt("Source:",      event.source().map_err_trace_exit(1).unwrap().map(|s| s.to_string()));
+    print_opt("Description:", event.description().map_err_trace_exit(1).unwrap());
+}
+
+fn export_events(rt: &Runtime) {
+    let scmd = rt.cli().subcommand_matches("export").unwrap();
+
+    let mut events = match scmd.values_of("uid") {
+        Some(uids) => uids.map(|uid| get_event(rt, uid)).collect::<Vec<_>>(),
+        None => Event::all(rt.store())
+            .map_err_trace_exit(1)
+            .unwrap() // safe by map_err_trace_exit()
+            .filter_map(|e| e.map_err_trace().ok())
+            .collect(),
+    };
+
+    if let (Some(from), Some(to)) = (scmd.value_of("from"), scmd.value_of("to")) {
+        let from = parse_date(from).and_hms(0, 0, 0);
+        let to   = parse_date(to).and_hms(0, 0, 0) + Duration::days(1);
+        events.retain(|e| e.occurrences(from, to).map_err_trace().map(|o| !o.is_empty()).unwrap_or(false));
+    }
+
+    let calendar = export(rt.store(), events.iter())
+        .map_err_trace_exit(1)
+        .unwrap(); // safe by map_err_trace_exit()
+
+    let mut out : Box<Write> = match scmd.value_of("out") {
+        Some(path) => Box::new(File::create(path).map_err_trace_exit(1).unwrap()), // safe by map_err_trace_exit()
+        None       => Box::new(stdout()),
+    };
+
+    out.write_all(calendar.as_bytes()).map_err_trace_exit(1).ok();
+}
+
@@ -0,0 +1,111 @@
+//
+// imag - the personal information management suite for the commandline
+// Copyright (C) 2015, 2016 Matthias Beyer <mail@beyermatthias.de> and contributors
+//
+// This library is free software; you can redistribute it and/or
+// modify it under the terms of the GNU Lesser General Public
+// License as published by the Free Software Foundation; version
+// 2.1 of the License.
+//
+// This library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+// Lesser General Public License for more details.
+//
+// You should have received a copy of the GNU Lesser General Public
+// License along with this library; if not, write to the Free Software
+// Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA  02110-1301  USA
+//
+
+use clap::{Arg, App, SubCommand};
+
+pub fn build_ui<'a>(app: App<'a, 'a>) -> App<'a, 'a> {
+    app
+        .subcommand(SubCommand::with_name("import")
+                   .about("Import iCalendar files. Events with the same UID are updated")
+                   .version("0.1")
+                   .arg(Arg::with_name("file")
+                        .index(1)
+                        .takes_value(true)
+                        .required(true)
+                        .multiple(true)
+                        .help("The iCalendar (.ics) files to import")
+                        .value_name("FILE"))
+                   .arg(Arg::with_name("ref")
+                        .long("ref")
+                        .short("r")
+                        .takes_value(false)
+                        .required(false)
+                        .help("Do not copy the events but refer to the files")))
+
+        .subcommand(SubCommand::with_name("agenda")
+                   .about("Show the events of a day, week or month")
+                   .version("0.1")
+                   .arg(Arg::with_name("date")
+                        .index(1)
+                        .takes_value(true)
+                        .required(false)
+                        .help("Show the agenda for this date. Defaults to today")
+                        .value_name("YYYY-MM-DD"))
+                   .arg(Arg::with_name("week")
+                        .long("week")
+                        .short("w")
+                        .takes_value(false)
+                        .required(false)
+                        .conflicts_with("month")
+                        .help("Show the whole week of the date"))
+                   .arg(Arg::with_name("month")
+                        .long("month")
+                        .short("m")
+                        .takes_value(false)
+                        .required(false)
+                        .help("Show the whole month of the date")))
+
+        .subcommand(SubCommand::with_name("list")
+                   .about("List all events")
+                   .version("0.1"))
+
+        .subcommand(SubCommand::with_name("show")
+                   .about("Show an event")
+                   .version("0.1")
+                   .arg(Arg::with_name("uid")
+                        .long("uid")
+                        .short("u")
+                        .takes_value(true)
+                        .required(true)
+                        .help("Show the event with this UID")
+                        .value_name("UID")))
+
+        .subcommand(SubCommand::with_name("export")
+                   .about("Export events to an iCalendar file")
+                   .version("0.1")
+                   .arg(Arg::with_name("uid")
+                        .long("uid")
+                        .short("u")
+                        .takes_value(true)
+                        .required(false)
+                        .multiple(true)
+                        .help("Export the event with this UID. If not passed, all events are exported")
+                        .value_name("UID"))
+                   .arg(Arg::with_name("from")
+                        .long("from")
+                        .takes_value(true)
+                        .required(false)
+                        .requires("to")
+                        .help("Only export events which occur between this date and --to")
+                        .value_name("YYYY-MM-DD"))
+                   .arg(Arg::with_name("to")
+                        .long("to")
+                        .takes_value(true)
+                        .required(false)
+                        .requires("from")
+                        .help("Only export events which occur between --from and this date, inclusive")
+                        .value_name("YYYY-MM-DD"))
+                   .arg(Arg::with_name("out")
+                        .long("out")
+                        .short("o")
+                        .takes_value(true)
+                        .required(false)
+                        .help("Write to this file instead of stdout")
+                        .value_name("FILE")))
+}
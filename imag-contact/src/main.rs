@@ -0,0 +1,217 @@
+//
+// imag - the personal information management suite for the commandline
+// Copyright (C) 2015, 2016 Matthias Beyer <mail@beyermatthias.de> and contributors
+//
+// This library is free software; you can redistribute it and/or
+// modify it under the terms of the GNU Lesser General Public
+// License as published by the Free Software Foundation; version
+// 2.1 of the License.
+//
+// This library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+// Lesser General Public License for more details.
+//
+// You should have received a copy of the GNU Lesser General Public
+// License along with this library; if not, write to the Free Software
+// Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA  02110-1301  USA
+//
+
+#![deny(
+    non_camel_case_types,
+    non_snake_case,
+    path_statements,
+    trivial_numeric_casts,
+    unstable_features,
+    unused_allocation,
+    unused_import_braces,
+    unused_imports,
+    unused_must_use,
+    unused_mut,
+    unused_qualifications,
+    while_true,
+)]
+
+extern crate clap;
+#[macro_use] extern crate log;
+extern crate semver;
+#[macro_use] extern crate version;
+
+extern crate libimagcontact;
+extern crate libimagrt;
+extern crate libimagerror;
+extern crate libimagutil;
+
+use std::fs::File;
+use std::io::{Read, Write};
+use std::io::stdout;
+use std::path::PathBuf;
+use std::process::exit;
+
+use libimagcontact::contact::{Contact, link_mails};
+use libimagrt::runtime::Runtime;
+use libimagrt::setup::generate_runtime_setup;
+use libimagerror::trace::{MapErrTrace, trace_error};
+use libimagutil::import::ImportSummary;
+use libimagutil::info_result::*;
+use libimagutil::warn_exit::warn_exit;
+
+mod ui;
+use ui::build_ui;
+
+fn main() {
+    let rt = generate_runtime_setup("imag-contact",
+                                    &version!()[..],
+                                    "Contact management with vCard import and export",
+                                    build_ui);
+
+    rt.cli()
+        .subcommand_name()
+        .map(|name| {
+            debug!("Call: {}", name);
+            match name {
+                "import"     => import(&rt),
+                "export"     => export(&rt),
+                "list"       => list(&rt),
+                "search"     => search(&rt),
+                "show"       => show(&rt),
+                "link"       => link(&rt),
+                "link-mails" => link_all_mails(&rt),
+                _            => {
+                    debug!("Unknown command"); // More error handling
+                },
+            };
+        });
+}
+
+fn get_contact<'a>(rt: &'a Runtime, uid: &str) -> Contact<'a> {
+    match Contact::get(rt.store(), uid) {
+        Ok(Some(contact)) => contact,
+        Ok(None)          => warn_exit(&format!("No contact with UID '{}'", uid), 1),
+        Err(e)            => {
+            trace_error(&e);
+            exit(1)
+        },
+    }
+}
+
+fn import(rt: &Runtime) {
+    let scmd = rt.cli().subcommand_matches("import").unwrap();
+    let mut summary = ImportSummary::new();
+
+    for path in scmd.values_of("file").unwrap() { // enforced by clap
+        let mut text = String::new();
+        File::open(path)
+            .and_then(|mut f| f.read_to_string(&mut text))
+            .map_err_trace_exit(1)
+            .ok();
+
+        let imported = Contact::import_all(rt.store(), &text)
+            .map_err_trace_exit(1)
+            .unwrap(); // safe by map_err_trace_exit()
+
+        summary.add_all(imported);
+    }
+
+    println!("{}", summary);
+}
+
+fn export(rt: &Runtime) {
+    let scmd = rt.cli().subcommand_matches("export").unwrap();
+
+    let cards = match scmd.values_of("uid") {
+        Some(uids) => uids.map(|uid| get_contact(rt, uid).raw_vcard().clone()).collect::<Vec<_>>(),
+        None => Contact::all(rt.store())
+            .map_err_trace_exit(1)
+            .unwrap() // safe by map_err_trace_exit()
+            .filter_map(|c| c.map_err_trace().ok())
+            .map(|c| c.raw_vcard().clone())
+            .collect(),
+    };
+
+    let mut out : Box<Write> = match scmd.value_of("out") {
+        Some(path) => Box::new(File::create(path).map_err_trace_exit(1).unwrap()), // safe by map_err_trace_exit()
+        None       => Box::new(stdout()),
+    };
+
+    for card in cards {
+        out.write_all(card.as_bytes()).map_err_trace_exit(1).ok();
+    }
+}
+
+fn print_contact(contact: &Contact) {
+    let uid  = contact.uid().map_err_trace().unwrap_or(String::from("<unknown>"));
+    let name = contact.fullname().map_err_trace().ok().and_then(|n| n).unwrap_or(String::new());
+    let mail = contact.emails().map_err_trace().unwrap_or(vec![]).join(", ");
+    println!("{} | {} | {}", uid, name, mail);
+}
+
+fn list(rt: &Runtime) {
+    Contact::all(rt.store())
+        .map_err_trace_exit(1)
+        .unwrap() // safe by map_err_trace_exit()
+        .filter_map(|c| c.map_err_trace().ok())
+        .map(|c| print_contact(&c))
+        .collect::<Vec<_>>();
+}
+
+fn search(rt: &Runtime) {
+    let query = rt.cli().subcommand_matches("search").unwrap().value_of("query").unwrap(); // enforced by clap
+
+    Contact::all(rt.store())
+        .map_err_trace_exit(1)
+        .unwrap() // safe by map_err_trace_exit()
+        .filter_map(|c| c.map_err_trace().ok())
+        .filter(|c| c.matches(query).map_err_trace().unwrap_or(false))
+        .map(|c| print_contact(&c))
+        .collect::<Vec<_>>();
+}
+
+fn show(rt: &Runtime) {
+    let uid     = rt.cli().subcommand_matches("show").unwrap().value_of("uid").unwrap(); // enforced by clap
+    let contact = get_contact(rt, uid);
+
+    let print_opt = |key: &str, value: Option<String>| if let Some(v) = value {
+        println!("{:<13} {}", key, v);
+    };
+    let print_all = |key: &str, values: Vec<String>| for v in values {
+        println!("{:<13} {}", key, v);
+    };
+
+    println!("{:<13} {}", "UID:", uid);
+    print_opt("Name:",         contact.fullname().map_err_trace_exit(1).unwrap());
+    print_opt("Organization:", contact.organization().map_err_trace_exit(1).unwrap());
+    print_opt("Birthday:",     contact.birthday().map_err_trace_exit(1).unwrap());
+    print_all("Email:",        contact.emails().map_err_trace_exit(1).unwrap());
+    print_all("Phone:",        contact.phones().map_err_trace_exit(1).unwrap());
+    print_all("Address:",      contact.addresses().map_err_trace_exit(1).unwrap());
+
+    for link in contact.linked_entries().map_err_trace_exit(1).unwrap() { // safe by map_err_trace_exit()
+        println!("{:<13} {}", "Linked:", link);
+    }
+}
+
+fn link(rt: &Runtime) {
+    let scmd        = rt.cli().subcommand_matches("link").unwrap();
+    let uid         = scmd.value_of("uid").unwrap(); // enforced by clap
+    let mut contact = get_contact(rt, uid);
+
+    for id in scmd.values_of("entry").unwrap() { // enforced by clap
+        match rt.store().get(PathBuf::from(id)) {
+            Ok(Some(mut entry)) => {
+                contact.link_entry(&mut entry)
+                    .map_err_trace()
+                    .map_info_str("Ok")
+                    .ok();
+            },
+            Ok(None) => warn!("No entry '{}'", id),
+            Err(e)   => trace_error(&e),
+        }
+    }
+}
+
+fn link_all_mails(rt: &Runtime) {
+    let n = link_mails(rt.store()).map_err_trace_exit(1).unwrap(); // safe by map_err_trace_exit()
+    println!("{} links created", n);
+}
+
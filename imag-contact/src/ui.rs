@@ -0,0 +1,93 @@
+//
+// imag - the personal information management suite for the commandline
+// Copyright (C) 2015, 2016 Matthias Beyer <mail@beyermatthias.de> and contributors
+//
+// This library is free software; you can redistribute it and/or
+// modify it under the terms of the GNU Lesser General Public
+// License as published by the Free Software Foundation; version
+// 2.1 of the License.
+//
+// This library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+// Lesser General Public License for more details.
+//
+// You should have received a copy of the GNU Lesser General Public
+// License along with this library; if not, write to the Free Software
+// Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA  02110-1301  USA
+//
+
+use clap::{Arg, App, SubCommand};
+
+fn uid_arg<'a, 'b>(help: &'a str) -> Arg<'a, 'b> {
+    Arg::with_name("uid")
+        .long("uid")
+        .short("u")
+        .takes_value(true)
+        .required(true)
+        .help(help)
+        .value_name("UID")
+}
+
+pub fn build_ui<'a>(app: App<'a, 'a>) -> App<'a, 'a> {
+    app
+        .subcommand(SubCommand::with_name("import")
+                   .about("Import vCard files. Contacts with the same UID are updated")
+                   .version("0.1")
+                   .arg(Arg::with_name("file")
+                        .index(1)
+                        .takes_value(true)
+                        .required(true)
+                        .multiple(true)
+                        .help("The vCard files to import")
+                        .value_name("FILE")))
+
+        .subcommand(SubCommand::with_name("export")
+                   .about("Export contacts as vCard")
+                   .version("0.1")
+                   .arg(uid_arg("Export the contact with this UID. If not passed, all contacts are exported")
+                        .required(false)
+                        .multiple(true))
+                   .arg(Arg::with_name("out")
+                        .long("out")
+                        .short("o")
+                        .takes_value(true)
+                        .required(false)
+                        .help("Write to this file instead of stdout")
+                        .value_name("FILE")))
+
+        .subcommand(SubCommand::with_name("list")
+                   .about("List all contacts")
+                   .version("0.1"))
+
+        .subcommand(SubCommand::with_name("search")
+                   .about("List the contacts whose name, organization, emails, phones or addresses contain a string")
+                   .version("0.1")
+                   .arg(Arg::with_name("query")
+                        .index(1)
+                        .takes_value(true)
+                        .required(true)
+                        .help("The string to search for, case is ignored")
+                        .value_name("QUERY")))
+
+        .subcommand(SubCommand::with_name("show")
+                   .about("Show a contact and the entries linked to it")
+                   .version("0.1")
+                   .arg(uid_arg("Show the contact with this UID")))
+
+        .subcommand(SubCommand::with_name("link")
+                   .about("Link entries (like mails or diary entries) to a contact")
+                   .version("0.1")
+                   .arg(uid_arg("Link to the contact with this UID"))
+                   .arg(Arg::with_name("entry")
+                        .index(1)
+                        .takes_value(true)
+                        .required(true)
+                        .multiple(true)
+                        .help("The ids of the entries to link")
+                        .value_name("ENTRY")))
+
+        .subcommand(SubCommand::with_name("link-mails")
+                   .about("Link all mails to the contacts with the addresses from their From and To headers")
+                   .version("0.1"))
+}
@@ -75,6 +75,10 @@ fn main() {
                                     "Counter tool to count things",
                                     build_ui);
 
+    let _ = rt.store()
+        .register_schema(Counter::header_schema())
+        .map_err_trace();
+
     rt.cli()
         .subcommand_name()
         .map_or_else(|| {
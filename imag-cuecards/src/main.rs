@@ -0,0 +1,240 @@
+//
+// imag - the personal information management suite for the commandline
+// Copyright (C) 2015, 2016 Matthias Beyer <mail@beyermatthias.de> and contributors
+//
+// This library is free software; you can redistribute it and/or
+// modify it under the terms of the GNU Lesser General Public
+// License as published by the Free Software Foundation; version
+// 2.1 of the License.
+//
+// This library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+// Lesser General Public License for more details.
+//
+// You should have received a copy of the GNU Lesser General Public
+// License along with this library; if not, write to the Free Software
+// Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA  02110-1301  USA
+//
+
+#![deny(
+    non_camel_case_types,
+    non_snake_case,
+    path_statements,
+    trivial_numeric_casts,
+    unstable_features,
+    unused_allocation,
+    unused_import_braces,
+    unused_imports,
+    unused_must_use,
+    unused_mut,
+    unused_qualifications,
+    while_true,
+)]
+
+extern crate chrono;
+extern crate clap;
+#[macro_use] extern crate log;
+extern crate semver;
+#[macro_use] extern crate version;
+
+extern crate libimagcuecards;
+extern crate libimaginteraction;
+extern crate libimagrt;
+extern crate libimagerror;
+extern crate libimagutil;
+
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::Read;
+use std::process::exit;
+use std::str::FromStr;
+
+use chrono::naive::date::NaiveDate;
+use chrono::offset::local::Local;
+
+use libimagcuecards::card::{Card, DATE_FORMAT, decks, due_cards};
+use libimagcuecards::schedule::{MAX_GRADE, REPEAT_BELOW};
+use libimaginteraction::ask::{ask_bool, ask_uint};
+use libimaginteraction::error::InteractionError;
+use libimagrt::runtime::Runtime;
+use libimagrt::setup::generate_runtime_setup;
+use libimagerror::trace::{MapErrTrace, trace_error};
+use libimagutil::import::ImportSummary;
+use libimagutil::info_result::*;
+
+mod ui;
+use ui::build_ui;
+
+fn main() {
+    let rt = generate_runtime_setup("imag-cuecards",
+                                    &version!()[..],
+                                    "Learn with cuecards and spaced repetition",
+                                    build_ui);
+
+    rt.cli()
+        .subcommand_name()
+        .map(|name| {
+            debug!("Call: {}", name);
+            match name {
+                "import" => import(&rt),
+                "add"    => add(&rt),
+                "decks"  => list_decks(&rt),
+                "list"   => list(&rt),
+                "review" => review(&rt),
+                _        => {
+                    debug!("Unknown command"); // More error handling
+                },
+            };
+        });
+}
+
+fn today() -> NaiveDate {
+    Local::today().naive_local()
+}
+
+fn import(rt: &Runtime) {
+    let scmd = rt.cli().subcommand_matches("import").unwrap();
+    let deck = scmd.value_of("deck").unwrap(); // enforced by clap
+    let mut summary = ImportSummary::new();
+
+    for path in scmd.values_of("file").unwrap() { // enforced by clap
+        let mut text = String::new();
+        File::open(path)
+            .and_then(|mut f| f.read_to_string(&mut text))
+            .map_err_trace_exit(1)
+            .ok();
+
+        let delimiter = if scmd.is_present("tsv") || path.ends_with(".tsv") { '\t' } else { ',' };
+        let imported  = Card::import_delimited(rt.store(), deck, &text, delimiter, today())
+            .map_err_trace_exit(1)
+            .unwrap(); // safe by map_err_trace_exit()
+
+        summary.add_all(imported);
+    }
+
+    println!("{}", summary);
+}
+
+fn add(rt: &Runtime) {
+    let scmd  = rt.cli().subcommand_matches("add").unwrap();
+    let deck  = scmd.value_of("deck").unwrap();  // enforced by clap
+    let front = scmd.value_of("front").unwrap(); // enforced by clap
+    let back  = scmd.value_of("back").unwrap();  // enforced by clap
+
+    Card::import(rt.store(), deck, front, back, today())
+        .map_err_trace_exit(1)
+        .map_info(|&(_, action)| format!("{:?}", action))
+        .ok();
+}
+
+fn list_decks(rt: &Runtime) {
+    for (deck, (cards, due)) in decks(rt.store(), today()).map_err_trace_exit(1).unwrap() { // safe by map_err_trace_exit()
+        println!("{}: {} cards, {} due", deck, cards, due);
+    }
+}
+
+fn list(rt: &Runtime) {
+    let deck      = rt.cli().subcommand_matches("list").unwrap().value_of("deck");
+    let mut cards = vec![];
+
+    for card in Card::all(rt.store()).map_err_trace_exit(1).unwrap() { // safe by map_err_trace_exit()
+        let line = card.and_then(|card| {
+            let card_deck = try!(card.deck());
+            let due       = try!(card.schedule()).due;
+            Ok((card_deck, due, try!(card.front()), try!(card.back())))
+        });
+
+        match line {
+            Ok(line) => if deck.map(|d| d == line.0).unwrap_or(true) {
+                cards.push(line)
+            },
+            Err(e) => trace_error(&e),
+        }
+    }
+
+    cards.sort();
+    for (deck, due, front, back) in cards {
+        println!("{} | {} | {} | {}", deck, due.format(DATE_FORMAT), front, back);
+    }
+}
+
+/// Ask for a grade, fails if the input ends
+fn ask_grade() -> Result<u64, InteractionError> {
+    loop {
+        let grade = try!(ask_uint("Grade (0: forgotten - 5: perfect)", None));
+        if grade <= MAX_GRADE {
+            return Ok(grade);
+        }
+        warn!("The grade must be between 0 and {}", MAX_GRADE);
+    }
+}
+
+/// Review the due cards
+///
+/// Cards which are graded below 4 are asked again at the end of the session, only the first
+/// answer is used for scheduling the card.
+fn review(rt: &Runtime) {
+    let scmd  = rt.cli().subcommand_matches("review").unwrap();
+    let today = today();
+    let limit = match scmd.value_of("limit").map(usize::from_str) {
+        None         => None,
+        Some(Ok(n))  => Some(n),
+        Some(Err(_)) => {
+            error!("Not a number: {}", scmd.value_of("limit").unwrap());
+            exit(1);
+        },
+    };
+
+    let mut cards = due_cards(rt.store(), scmd.value_of("deck"), today)
+        .map_err_trace_exit(1)
+        .unwrap(); // safe by map_err_trace_exit()
+    if let Some(n) = limit {
+        cards.truncate(n);
+    }
+
+    if cards.is_empty() {
+        info!("No cards due");
+        return;
+    }
+
+    let mut queue    = cards.into_iter().map(|card| (card, true)).collect::<VecDeque<_>>();
+    let mut reviewed = 0;
+    while let Some((mut card, first)) = queue.pop_front() {
+        let (front, back) = match card.front().and_then(|f| card.back().map(|b| (f, b))) {
+            Ok(sides) => sides,
+            Err(e)    => {
+                trace_error(&e);
+                continue;
+            },
+        };
+
+        println!("\n{}\n", front);
+        if !ask_bool("Show the back side?", Some(true)) {
+            break;
+        }
+        println!("\n{}\n", back);
+
+        let grade = match ask_grade() {
+            Ok(grade) => grade,
+            Err(e)    => {
+                trace_error(&e);
+                break;
+            },
+        };
+        if first {
+            card.review(grade, today)
+                .map(|schedule| debug!("Next review of {} at {}", card.get_location(), schedule.due))
+                .map_err_trace()
+                .ok();
+            reviewed += 1;
+        }
+
+        if grade < REPEAT_BELOW {
+            queue.push_back((card, false));
+        }
+    }
+
+    println!("{} cards reviewed", reviewed);
+}
+
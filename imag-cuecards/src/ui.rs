@@ -0,0 +1,89 @@
+//
+// imag - the personal information management suite for the commandline
+// Copyright (C) 2015, 2016 Matthias Beyer <mail@beyermatthias.de> and contributors
+//
+// This library is free software; you can redistribute it and/or
+// modify it under the terms of the GNU Lesser General Public
+// License as published by the Free Software Foundation; version
+// 2.1 of the License.
+//
+// This library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+// Lesser General Public License for more details.
+//
+// You should have received a copy of the GNU Lesser General Public
+// License along with this library; if not, write to the Free Software
+// Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA  02110-1301  USA
+//
+
+use clap::{Arg, App, SubCommand};
+
+fn deck_arg<'a, 'b>(help: &'a str) -> Arg<'a, 'b> {
+    Arg::with_name("deck")
+        .long("deck")
+        .short("d")
+        .takes_value(true)
+        .required(true)
+        .help(help)
+        .value_name("DECK")
+}
+
+pub fn build_ui<'a>(app: App<'a, 'a>) -> App<'a, 'a> {
+    app
+        .subcommand(SubCommand::with_name("import")
+                   .about("Import cards from CSV or TSV files, with the front side in the first column and the back side in the second")
+                   .version("0.1")
+                   .arg(deck_arg("Import the cards into this deck"))
+                   .arg(Arg::with_name("tsv")
+                        .long("tsv")
+                        .short("t")
+                        .takes_value(false)
+                        .required(false)
+                        .help("The files are tab separated. This is the default for files ending in '.tsv'"))
+                   .arg(Arg::with_name("file")
+                        .index(1)
+                        .takes_value(true)
+                        .required(true)
+                        .multiple(true)
+                        .help("The files to import")
+                        .value_name("FILE")))
+
+        .subcommand(SubCommand::with_name("add")
+                   .about("Add a card")
+                   .version("0.1")
+                   .arg(deck_arg("Add the card to this deck"))
+                   .arg(Arg::with_name("front")
+                        .index(1)
+                        .takes_value(true)
+                        .required(true)
+                        .help("The front side of the card")
+                        .value_name("FRONT"))
+                   .arg(Arg::with_name("back")
+                        .index(2)
+                        .takes_value(true)
+                        .required(true)
+                        .help("The back side of the card")
+                        .value_name("BACK")))
+
+        .subcommand(SubCommand::with_name("decks")
+                   .about("List all decks with the number of cards and the number of due cards")
+                   .version("0.1"))
+
+        .subcommand(SubCommand::with_name("list")
+                   .about("List the cards with their next review")
+                   .version("0.1")
+                   .arg(deck_arg("Only list the cards of this deck").required(false)))
+
+        .subcommand(SubCommand::with_name("review")
+                   .about("Review the due cards")
+                   .version("0.1")
+                   .arg(deck_arg("Only review the cards of this deck").required(false))
+                   .arg(Arg::with_name("limit")
+                        .long("limit")
+                        .short("l")
+                        .takes_value(true)
+                        .required(false)
+                        .help("Review at most this many cards")
+                        .value_name("N")))
+}
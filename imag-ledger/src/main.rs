@@ -0,0 +1,211 @@
+//
+// imag - the personal information management suite for the commandline
+// Copyright (C) 2015, 2016 Matthias Beyer <mail@beyermatthias.de> and contributors
+//
+// This library is free software; you can redistribute it and/or
+// modify it under the terms of the GNU Lesser General Public
+// License as published by the Free Software Foundation; version
+// 2.1 of the License.
+//
+// This library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+// Lesser General Public License for more details.
+//
+// You should have received a copy of the GNU Lesser General Public
+// License along with this library; if not, write to the Free Software
+// Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA  02110-1301  USA
+//
+
+#![deny(
+    non_camel_case_types,
+    non_snake_case,
+    path_statements,
+    trivial_numeric_casts,
+    unstable_features,
+    unused_allocation,
+    unused_import_braces,
+    unused_imports,
+    unused_must_use,
+    unused_mut,
+    unused_qualifications,
+    while_true,
+)]
+
+extern crate clap;
+#[macro_use] extern crate log;
+extern crate semver;
+#[macro_use] extern crate version;
+
+extern crate libimagledger;
+extern crate libimagentrylist;
+extern crate libimagrt;
+extern crate libimagerror;
+extern crate libimagstore;
+extern crate libimagutil;
+
+use std::fs::File;
+use std::io::{Read, Write};
+use std::io::stdout;
+use std::process::exit;
+use std::str::FromStr;
+
+use libimagentrylist::listers::table::TableLister;
+use libimagledger::amount::Balance;
+use libimagledger::journal::to_journal;
+use libimagledger::ledger::{account_matches, balances, import_journal,
+                            monthly_register, transactions};
+use libimagledger::transaction::{DATE_FORMAT, Transaction};
+use libimagrt::runtime::Runtime;
+use libimagrt::setup::generate_runtime_setup;
+use libimagerror::trace::MapErrTrace;
+use libimagstore::store::FileLockEntry;
+use libimagutil::import::ImportSummary;
+
+mod ui;
+use ui::build_ui;
+
+fn main() {
+    let rt = generate_runtime_setup("imag-ledger",
+                                    &version!()[..],
+                                    "Plain-text accounting with ledger journal import and export",
+                                    build_ui);
+
+    rt.cli()
+        .subcommand_name()
+        .map(|name| {
+            debug!("Call: {}", name);
+            match name {
+                "import"   => import(&rt),
+                "export"   => export(&rt),
+                "balance"  => balance(&rt),
+                "register" => register(&rt),
+                _          => {
+                    debug!("Unknown command"); // More error handling
+                },
+            };
+        });
+}
+
+fn get_transactions(rt: &Runtime, account: Option<&str>) -> Vec<Transaction> {
+    transactions(rt.store())
+        .map_err_trace_exit(1)
+        .unwrap() // safe by map_err_trace_exit()
+        .into_iter()
+        .map(|(_, transaction)| transaction)
+        .filter(|t| {
+            account.map(|a| t.postings.iter().any(|p| account_matches(&p.account, a))).unwrap_or(true)
+        })
+        .collect()
+}
+
+fn list_table(header: Vec<&str>, rows: Vec<Vec<String>>) {
+    let _ = TableLister::new(|_: &FileLockEntry| vec![])
+        .with_header(header.into_iter().map(String::from).collect())
+        .with_idx(false)
+        .list_rows(rows.into_iter())
+        .map_err_trace_exit(1);
+}
+
+fn import(rt: &Runtime) {
+    let scmd = rt.cli().subcommand_matches("import").unwrap();
+    let mut summary = ImportSummary::new();
+
+    for path in scmd.values_of("file").unwrap() { // enforced by clap
+        let mut text = String::new();
+        File::open(path)
+            .and_then(|mut f| f.read_to_string(&mut text))
+            .map_err_trace_exit(1)
+            .ok();
+
+        let imported = import_journal(rt.store(), &text)
+            .map_err_trace_exit(1)
+            .unwrap(); // safe by map_err_trace_exit()
+
+        summary.add_all(imported);
+    }
+
+    println!("{} created, {} unchanged", summary.created, summary.unchanged);
+}
+
+fn export(rt: &Runtime) {
+    let scmd         = rt.cli().subcommand_matches("export").unwrap();
+    let transactions = get_transactions(rt, scmd.value_of("account"));
+
+    let mut out : Box<Write> = match scmd.value_of("out") {
+        Some(path) => Box::new(File::create(path).map_err_trace_exit(1).unwrap()), // safe by map_err_trace_exit()
+        None       => Box::new(stdout()),
+    };
+
+    out.write_all(to_journal(transactions.iter()).as_bytes()).map_err_trace_exit(1).ok();
+}
+
+fn balance(rt: &Runtime) {
+    let scmd    = rt.cli().subcommand_matches("balance").unwrap();
+    let account = scmd.value_of("account");
+    let depth   = match scmd.value_of("depth").map(usize::from_str) {
+        None         => None,
+        Some(Ok(n))  => Some(n),
+        Some(Err(_)) => {
+            error!("Not a number: {}", scmd.value_of("depth").unwrap());
+            exit(1);
+        },
+    };
+
+    let transactions = get_transactions(rt, account);
+    let mut total    = Balance::new();
+    let mut rows     = vec![];
+    let balances     = balances(transactions.iter(), depth)
+        .map_err_trace_exit(1)
+        .unwrap(); // safe by map_err_trace_exit()
+    for (name, balance) in balances {
+        if balance.is_zero() || !account.map(|a| account_matches(&name, a)).unwrap_or(true) {
+            continue;
+        }
+
+        total.add_balance(&balance).map_err_trace_exit(1).ok();
+        rows.push(vec![name, format!("{}", balance)]);
+    }
+    rows.push(vec![String::from("Total"), format!("{}", total)]);
+
+    list_table(vec!["Account", "Balance"], rows);
+}
+
+fn register(rt: &Runtime) {
+    let scmd         = rt.cli().subcommand_matches("register").unwrap();
+    let account      = scmd.value_of("account");
+    let transactions = get_transactions(rt, account);
+
+    if scmd.is_present("monthly") {
+        let rows = monthly_register(transactions.iter(), account)
+            .map_err_trace_exit(1)
+            .unwrap() // safe by map_err_trace_exit()
+            .into_iter()
+            .map(|(month, change, total)| vec![month, format!("{}", change), format!("{}", total)])
+            .collect();
+
+        return list_table(vec!["Month", "Change", "Total"], rows);
+    }
+
+    let mut total = Balance::new();
+    let mut rows  = vec![];
+    for transaction in transactions.iter() {
+        for posting in transaction.postings.iter() {
+            if !account.map(|a| account_matches(&posting.account, a)).unwrap_or(true) {
+                continue;
+            }
+
+            total.add(&posting.amount).map_err_trace_exit(1).ok();
+            rows.push(vec![
+                format!("{}", transaction.date.format(DATE_FORMAT)),
+                transaction.payee.clone(),
+                posting.account.clone(),
+                format!("{}", posting.amount),
+                format!("{}", total),
+            ]);
+        }
+    }
+
+    list_table(vec!["Date", "Payee", "Account", "Amount", "Total"], rows);
+}
+
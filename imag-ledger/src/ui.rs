@@ -0,0 +1,79 @@
+//
+// imag - the personal information management suite for the commandline
+// Copyright (C) 2015, 2016 Matthias Beyer <mail@beyermatthias.de> and contributors
+//
+// This library is free software; you can redistribute it and/or
+// modify it under the terms of the GNU Lesser General Public
+// License as published by the Free Software Foundation; version
+// 2.1 of the License.
+//
+// This library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+// Lesser General Public License for more details.
+//
+// You should have received a copy of the GNU Lesser General Public
+// License along with this library; if not, write to the Free Software
+// Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA  02110-1301  USA
+//
+
+use clap::{Arg, App, SubCommand};
+
+fn account_arg<'a, 'b>(help: &'a str) -> Arg<'a, 'b> {
+    Arg::with_name("account")
+        .long("account")
+        .short("a")
+        .takes_value(true)
+        .required(false)
+        .help(help)
+        .value_name("ACCOUNT")
+}
+
+pub fn build_ui<'a>(app: App<'a, 'a>) -> App<'a, 'a> {
+    app
+        .subcommand(SubCommand::with_name("import")
+                   .about("Import ledger/hledger journals. Transactions already in the store are skipped")
+                   .version("0.1")
+                   .arg(Arg::with_name("file")
+                        .index(1)
+                        .takes_value(true)
+                        .required(true)
+                        .multiple(true)
+                        .help("The journal files to import")
+                        .value_name("FILE")))
+
+        .subcommand(SubCommand::with_name("export")
+                   .about("Export transactions as ledger journal")
+                   .version("0.1")
+                   .arg(account_arg("Only export transactions with postings to this account or its subaccounts"))
+                   .arg(Arg::with_name("out")
+                        .long("out")
+                        .short("o")
+                        .takes_value(true)
+                        .required(false)
+                        .help("Write to this file instead of stdout")
+                        .value_name("FILE")))
+
+        .subcommand(SubCommand::with_name("balance")
+                   .about("Show account balances")
+                   .version("0.1")
+                   .arg(account_arg("Only show this account and its subaccounts"))
+                   .arg(Arg::with_name("depth")
+                        .long("depth")
+                        .short("d")
+                        .takes_value(true)
+                        .required(false)
+                        .help("Sum up subaccounts below this depth")
+                        .value_name("N")))
+
+        .subcommand(SubCommand::with_name("register")
+                   .about("Show postings with running total")
+                   .version("0.1")
+                   .arg(account_arg("Only show postings to this account and its subaccounts"))
+                   .arg(Arg::with_name("monthly")
+                        .long("monthly")
+                        .short("m")
+                        .takes_value(false)
+                        .required(false)
+                        .help("Sum up the postings per month")))
+}
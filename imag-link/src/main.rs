@@ -45,6 +45,7 @@ extern crate libimagstore;
 extern crate libimagerror;
 extern crate libimagutil;
 
+use std::collections::BTreeMap;
 use std::ops::Deref;
 
 use libimagrt::runtime::Runtime;
@@ -55,10 +56,14 @@ use libimagstore::store::FileLockEntry;
 use libimagstore::store::Store;
 use libimagerror::trace::{MapErrTrace, trace_error, trace_error_exit};
 use libimagentrylink::external::ExternalLinker;
+use libimagentrylink::external::{add_external_link_atomically, remove_external_link_atomically};
+use libimagentrylink::external::set_external_links_atomically;
+use libimagentrylink::internal::{link_entries, link_entries_typed, unlink_entries};
 use libimagutil::warn_result::*;
 use libimagutil::warn_exit::warn_exit;
 use libimagutil::info_result::*;
 use clap::ArgMatches;
+use toml::Value;
 use url::Url;
 
 mod ui;
@@ -77,6 +82,7 @@ fn main() {
             match name {
                 "internal" => handle_internal_linking(&rt),
                 "external" => handle_external_linking(&rt),
+                "graph"    => handle_graph(&rt),
                 _ => warn_exit("No commandline call", 1)
             }
         });
@@ -94,18 +100,32 @@ fn handle_internal_linking(rt: &Runtime) {
             debug!("Listing for '{}'", entry);
             match get_entry_by_name(rt, entry) {
                 Ok(Some(e)) => {
-                    e.get_internal_links()
+                    let links = match cmd.value_of("list-type") {
+                        Some(kind) => e.get_internal_links_by_type(kind),
+                        None       => e.get_typed_internal_links(),
+                    };
+
+                    links
                         .map(|links| {
                             let i = links
+                                .into_iter()
                                 .filter_map(|l| {
-                                    l.to_str()
+                                    l.get_link()
+                                        .to_str()
                                         .map_warn_err(|e| format!("Failed to convert StoreId to string: {:?}", e))
                                         .ok()
+                                        .map(|s| (s, l))
                                 })
                                 .enumerate();
 
-                            for (i, link) in i {
-                                println!("{: <3}: {}", i, link);
+                            for (i, (link, typed)) in i {
+                                match typed.get_kind() {
+                                    Some(kind) => {
+                                        let dir = if typed.is_incoming() { "<-" } else { "->" };
+                                        println!("{: <3}: {} ({} {})", i, link, dir, kind);
+                                    },
+                                    None => println!("{: <3}: {}", i, link),
+                                }
                             }
                         })
                         .map_err_trace()
@@ -125,7 +145,7 @@ fn handle_internal_linking(rt: &Runtime) {
         }
         debug!("Listing ready!");
     } else {
-        let mut from = {
+        let from = {
             let from = get_from_entry(&rt);
             if from.is_none() {
                 warn_exit("No 'from' entry", 1);
@@ -143,26 +163,45 @@ fn handle_internal_linking(rt: &Runtime) {
         };
         debug!("Link to = {:?}", to.iter().map(|f| f.deref()).collect::<Vec<&Entry>>());
 
-        match cmd.subcommand_name() {
+        // All entries are written in one transaction, so we never end up with a link which only
+        // exists on one side
+        let res = match cmd.subcommand_name() {
             Some("add") => {
-                for mut to_entry in to {
-                    if let Err(e) = to_entry.add_internal_link(&mut from) {
-                        trace_error_exit(&e, 1);
-                    }
-                }
-            },
+                let add_cmd = cmd.subcommand_matches("add").unwrap(); // safe by match
+                let meta    = get_link_meta(add_cmd);
 
-            Some("remove") => {
-                for mut to_entry in to {
-                    if let Err(e) = to_entry.remove_internal_link(&mut from) {
-                        trace_error_exit(&e, 1);
-                    }
+                match add_cmd.value_of("type") {
+                    Some(kind) => link_entries_typed(rt.store(), from, to, kind, meta),
+                    None       => link_entries(rt.store(), from, to),
                 }
             },
 
+            Some("remove") => unlink_entries(rt.store(), from, to),
+
             _ => unreachable!(),
         };
+
+        if let Err(e) = res {
+            trace_error_exit(&e, 1);
+        }
+    }
+}
+
+/// Parse the `--meta key=value` arguments into a table
+fn get_link_meta(matches: &ArgMatches) -> BTreeMap<String, Value> {
+    let mut meta = BTreeMap::new();
+    if let Some(values) = matches.values_of("meta") {
+        for kv in values {
+            let mut split = kv.splitn(2, '=');
+            match (split.next(), split.next()) {
+                (Some(k), Some(v)) if !k.is_empty() => {
+                    meta.insert(String::from(k), Value::String(String::from(v)));
+                },
+                _ => warn_exit(&format!("Metadata must be passed as key=value: {}", kv), 1),
+            }
+        }
     }
+    meta
 }
 
 fn get_from_entry<'a>(rt: &'a Runtime) -> Option<FileLockEntry<'a>> {
@@ -229,19 +268,19 @@ fn handle_external_linking(rt: &Runtime) {
 
     if scmd.is_present("add") {
         debug!("Adding link to entry!");
-        add_link_to_entry(rt.store(), scmd, &mut entry);
+        add_link_to_entry(rt.store(), scmd, entry);
         return;
     }
 
     if scmd.is_present("remove") {
         debug!("Removing link from entry!");
-        remove_link_from_entry(rt.store(), scmd, &mut entry);
+        remove_link_from_entry(rt.store(), scmd, entry);
         return;
     }
 
     if scmd.is_present("set") {
         debug!("Setting links in entry!");
-        set_links_for_entry(rt.store(), scmd, &mut entry);
+        set_links_for_entry(rt.store(), scmd, entry);
         return;
     }
 
@@ -254,21 +293,21 @@ fn handle_external_linking(rt: &Runtime) {
     panic!("Clap failed to enforce one of 'add', 'remove', 'set' or 'list'");
 }
 
-fn add_link_to_entry(store: &Store, matches: &ArgMatches, entry: &mut FileLockEntry) {
+fn add_link_to_entry<'a>(store: &'a Store, matches: &ArgMatches, entry: FileLockEntry<'a>) {
     Url::parse(matches.value_of("add").unwrap())
         .map_err_trace_exit(1)
-        .map(|link| entry.add_external_link(store, link).map_err_trace().map_info_str("Ok"))
+        .map(|link| add_external_link_atomically(store, entry, link).map_err_trace().map_info_str("Ok"))
         .ok();
 }
 
-fn remove_link_from_entry(store: &Store, matches: &ArgMatches, entry: &mut FileLockEntry) {
+fn remove_link_from_entry<'a>(store: &'a Store, matches: &ArgMatches, entry: FileLockEntry<'a>) {
     Url::parse(matches.value_of("remove").unwrap())
         .map_err_trace_exit(1)
-        .map(|link| entry.remove_external_link(store, link).map_err_trace().map_info_str("Ok"))
+        .map(|link| remove_external_link_atomically(store, entry, link).map_err_trace().map_info_str("Ok"))
         .ok();
 }
 
-fn set_links_for_entry(store: &Store, matches: &ArgMatches, entry: &mut FileLockEntry) {
+fn set_links_for_entry<'a>(store: &'a Store, matches: &ArgMatches, entry: FileLockEntry<'a>) {
     let links = matches
         .value_of("links")
         .map(String::from)
@@ -287,7 +326,7 @@ fn set_links_for_entry(store: &Store, matches: &ArgMatches, entry: &mut FileLock
         .filter_map(|x| x)
         .collect();
 
-    entry.set_external_links(store, links)
+    set_external_links_atomically(store, entry, links)
         .map_err_trace()
         .map_info_str("Ok")
         .ok();
@@ -306,3 +345,95 @@ fn list_links_for_entry(store: &Store, entry: &mut FileLockEntry) {
         .ok();
 }
 
+fn handle_graph(rt: &Runtime) {
+    use std::collections::BTreeSet;
+    use std::path::PathBuf;
+    use libimagentrylink::graph::LinkGraph;
+    use libimagstore::storeid::StoreId;
+
+    let scmd  = rt.cli().subcommand_matches("graph").unwrap();
+    let graph = LinkGraph::from_store(rt.store()).map_err_trace_exit(1).unwrap(); // safe by map_err_trace_exit()
+    let graph = if scmd.is_present("no-external") {
+        graph.filter(|id| !id.local().starts_with("links/external"))
+    } else {
+        graph
+    };
+
+    let to_id = |name: &str| -> StoreId {
+        let id = StoreId::new_baseless(PathBuf::from(name)).map_err_trace_exit(1).unwrap(); // safe by map_err_trace_exit()
+        if !graph.contains(&id) {
+            warn_exit(&format!("Entry not found: {}", name), 1);
+        }
+        id
+    };
+
+    let get_depth = |matches: &ArgMatches| -> Option<usize> {
+        matches.value_of("depth").map(|d| match d.parse::<usize>() {
+            Ok(d)  => d,
+            Err(_) => warn_exit(&format!("Not a number: {}", d), 1),
+        })
+    };
+
+    match scmd.subcommand() {
+        ("path", Some(m)) => {
+            let from = to_id(m.value_of("from").unwrap()); // enforced by clap
+            let to   = to_id(m.value_of("to").unwrap()); // enforced by clap
+            match graph.shortest_path(&from, &to) {
+                Some(path) => {
+                    for id in path {
+                        println!("{}", id);
+                    }
+                },
+                None => warn_exit("No path between the entries", 1),
+            }
+        },
+
+        ("neighbours", Some(m)) => {
+            let start = to_id(m.value_of("entry").unwrap()); // enforced by clap
+            let depth = get_depth(m).unwrap_or(1);
+            let mut neighbourhood = graph.neighbourhood(&start, depth).into_iter().collect::<Vec<_>>();
+            neighbourhood.sort_by(|a, b| a.1.cmp(&b.1));
+
+            for (id, dist) in neighbourhood {
+                println!("{: <3}: {}", dist, id);
+            }
+        },
+
+        ("components", _) => {
+            for (i, component) in graph.components().into_iter().enumerate() {
+                println!("{: <3}: {} entries", i, component.len());
+                for id in component {
+                    println!("     {}", id);
+                }
+            }
+        },
+
+        ("orphans", _) => {
+            for id in graph.orphans() {
+                println!("{}", id);
+            }
+        },
+
+        _ => {
+            let graph = match scmd.values_of("entry") {
+                Some(entries) => {
+                    let depth = get_depth(scmd).unwrap_or(graph.len());
+                    let mut nodes = BTreeSet::new();
+                    for entry in entries {
+                        nodes.extend(graph.neighbourhood(&to_id(entry), depth).into_iter().map(|(id, _)| id));
+                    }
+                    graph.subgraph(&nodes)
+                },
+                None => graph.clone(),
+            };
+
+            match scmd.value_of("format") {
+                Some("json") => match graph.to_json() {
+                    Ok(json) => println!("{}", json),
+                    Err(e)   => trace_error_exit(&e, 1),
+                },
+                _ => print!("{}", graph.to_dot()),
+            }
+        },
+    }
+}
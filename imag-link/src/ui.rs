@@ -42,6 +42,20 @@ pub fn build_ui<'a>(app: App<'a, 'a>) -> App<'a, 'a> {
                                      .multiple(true)
                                      .help("Link to this entries")
                                      .value_name("ENTRIES"))
+                                .arg(Arg::with_name("type")
+                                     .long("type")
+                                     .takes_value(true)
+                                     .required(false)
+                                     .help("The relation type of the link, for example 'references'")
+                                     .value_name("TYPE"))
+                                .arg(Arg::with_name("meta")
+                                     .long("meta")
+                                     .takes_value(true)
+                                     .required(false)
+                                     .multiple(true)
+                                     .requires("type")
+                                     .help("Metadata for the link, as key=value")
+                                     .value_name("KEY=VALUE"))
                                 )
 
                     .subcommand(SubCommand::with_name("remove")
@@ -71,6 +85,14 @@ pub fn build_ui<'a>(app: App<'a, 'a>) -> App<'a, 'a> {
                          .required(false)
                          .help("List links to this entry")
                          .value_name("ENTRY"))
+
+                    .arg(Arg::with_name("list-type")
+                         .long("type")
+                         .takes_value(true)
+                         .required(false)
+                         .requires("list")
+                         .help("Only list links of this relation type")
+                         .value_name("TYPE"))
                     )
         .subcommand(SubCommand::with_name("external")
                     .about("Add and remove external links")
@@ -119,4 +141,83 @@ pub fn build_ui<'a>(app: App<'a, 'a>) -> App<'a, 'a> {
                            .required(true))
 
                     )
+        .subcommand(SubCommand::with_name("graph")
+                    .about("Export the graph of internal links, or traverse it")
+                    .version("0.1")
+
+                    .arg(Arg::with_name("format")
+                         .long("format")
+                         .short("f")
+                         .takes_value(true)
+                         .required(false)
+                         .possible_values(&["dot", "json"])
+                         .help("The export format, defaults to dot")
+                         .value_name("FORMAT"))
+
+                    .arg(Arg::with_name("entry")
+                         .long("entry")
+                         .short("e")
+                         .takes_value(true)
+                         .required(false)
+                         .multiple(true)
+                         .help("Only export the part of the graph connected to these entries")
+                         .value_name("ENTRY"))
+
+                    .arg(depth_arg()
+                         .requires("entry")
+                         .help("Only export entries up to this many links away from the --entry entries"))
+
+                    .arg(Arg::with_name("no-external")
+                         .long("no-external")
+                         .takes_value(false)
+                         .required(false)
+                         .help("Leave out the entries which represent external links"))
+
+                    .subcommand(SubCommand::with_name("path")
+                                .about("Find the shortest path between two entries")
+                                .version("0.1")
+                                .arg(Arg::with_name("from")
+                                     .index(1)
+                                     .takes_value(true)
+                                     .required(true)
+                                     .help("Start at this entry")
+                                     .value_name("ENTRY"))
+                                .arg(Arg::with_name("to")
+                                     .index(2)
+                                     .takes_value(true)
+                                     .required(true)
+                                     .help("End at this entry")
+                                     .value_name("ENTRY"))
+                                )
+
+                    .subcommand(SubCommand::with_name("neighbours")
+                                .about("List the entries reachable from an entry")
+                                .version("0.1")
+                                .arg(Arg::with_name("entry")
+                                     .index(1)
+                                     .takes_value(true)
+                                     .required(true)
+                                     .help("Start at this entry")
+                                     .value_name("ENTRY"))
+                                .arg(depth_arg()
+                                     .help("Follow at most this many links, defaults to 1"))
+                                )
+
+                    .subcommand(SubCommand::with_name("components")
+                                .about("List the groups of entries which are connected by links")
+                                .version("0.1"))
+
+                    .subcommand(SubCommand::with_name("orphans")
+                                .about("List the entries without any internal link")
+                                .version("0.1"))
+                    )
+}
+
+fn depth_arg<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("depth")
+        .long("depth")
+        .short("d")
+        .takes_value(true)
+        .required(false)
+        .value_name("N")
 }
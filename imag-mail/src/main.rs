@@ -0,0 +1,234 @@
+//
+// imag - the personal information management suite for the commandline
+// Copyright (C) 2015, 2016 Matthias Beyer <mail@beyermatthias.de> and contributors
+//
+// This library is free software; you can redistribute it and/or
+// modify it under the terms of the GNU Lesser General Public
+// License as published by the Free Software Foundation; version
+// 2.1 of the License.
+//
+// This library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+// Lesser General Public License for more details.
+//
+// You should have received a copy of the GNU Lesser General Public
+// License along with this library; if not, write to the Free Software
+// Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA  02110-1301  USA
+//
+
+#![deny(
+    non_camel_case_types,
+    non_snake_case,
+    path_statements,
+    trivial_numeric_casts,
+    unstable_features,
+    unused_allocation,
+    unused_import_braces,
+    unused_imports,
+    unused_must_use,
+    unused_mut,
+    unused_qualifications,
+    while_true,
+)]
+
+#[macro_use] extern crate log;
+#[macro_use] extern crate version;
+extern crate clap;
+extern crate filters;
+extern crate toml;
+
+extern crate libimagstore;
+extern crate libimagrt;
+extern crate libimagerror;
+extern crate libimagmail;
+extern crate libimagref;
+extern crate libimagentryfilter;
+extern crate libimagentrylist;
+
+mod ui;
+use ui::build_ui;
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use toml::Value;
+
+use libimagerror::trace::{MapErrTrace, trace_error, trace_error_exit};
+use libimagmail::import::{Importer, find_mail_files};
+use libimagmail::mail::Mail;
+use libimagmail::thread::{get_thread, link_threads};
+use libimagref::reference::Ref;
+use libimagrt::setup::generate_runtime_setup;
+use libimagrt::runtime::Runtime;
+use libimagstore::store::FileLockEntry;
+
+fn main() {
+    let rt = generate_runtime_setup("imag-mail",
+                                    &version!()[..],
+                                    "Mail collection tool",
+                                    build_ui);
+
+    for schema in vec![Ref::header_schema(), Mail::header_schema()] {
+        let _ = rt.store().register_schema(schema).map_err_trace();
+    }
+
+    rt.cli()
+        .subcommand_name()
+        .map(|name| {
+            debug!("Call: {}", name);
+            match name {
+                "import" => import(&rt),
+                "list"   => list(&rt),
+                "thread" => thread(&rt),
+                _        => {
+                    debug!("Unknown command"); // More error handling
+                },
+            };
+        });
+}
+
+fn import(rt: &Runtime) {
+    let cmd      = rt.cli().subcommand_matches("import").unwrap();
+    let mbox_dir = cmd.value_of("mbox-dir")
+        .map(PathBuf::from)
+        .unwrap_or_else(|| {
+            let mut p = rt.rtp().clone();
+            p.push("mail");
+            p.push("mbox");
+            p
+        });
+
+    let mut importer = Importer::new(rt.store()).map_err_trace_exit(1).unwrap(); // safe by map_err_trace_exit()
+    let (mut imported, mut skipped) = (0, 0);
+
+    for path in cmd.values_of("path").unwrap() { // saved by clap
+        let files = match find_mail_files(&PathBuf::from(path), &mbox_dir) {
+            Ok(files) => files,
+            Err(e) => {
+                trace_error(&e);
+                warn!("Cannot find mails in {}, skipping", path);
+                continue;
+            },
+        };
+
+        for file in files {
+            match importer.import(&file) {
+                Ok(Some(_)) => imported += 1,
+                Ok(None)    => skipped += 1,
+                Err(e) => {
+                    trace_error(&e);
+                    warn!("Failed to import {:?}", file);
+                },
+            }
+        }
+    }
+
+    info!("Imported {} mails, skipped {} duplicates", imported, skipped);
+
+    if !cmd.is_present("no-thread") {
+        match link_threads(rt.store()) {
+            Ok(n)  => info!("Linked {} replies", n),
+            Err(e) => trace_error_exit(&e, 1),
+        }
+    }
+}
+
+fn header_string(fle: &FileLockEntry, field: &str) -> String {
+    match fle.get_header().read(field) {
+        Ok(Some(Value::String(s))) => s,
+        _ => String::from("<unknown>"),
+    }
+}
+
+fn mail_hash(fle: &FileLockEntry) -> String {
+    fle.get_location()
+        .local()
+        .file_name()
+        .and_then(|n| n.to_str())
+        .map(String::from)
+        .unwrap_or_else(|| String::from("<unknown>"))
+}
+
+fn list(rt: &Runtime) {
+    use filters::filter::Filter;
+    use libimagentryfilter::cli::get_filter;
+    use libimagentrylist::lister::Lister;
+    use libimagentrylist::listers::table::TableLister;
+
+    let cmd    = rt.cli().subcommand_matches("list").unwrap();
+    let filter = get_filter(cmd).map_err_trace_exit(1).unwrap(); // safe by map_err_trace_exit()
+
+    let mails = rt.store()
+        .retrieve_for_module("ref")
+        .map_err_trace_exit(1)
+        .unwrap() // safe by map_err_trace_exit()
+        .filter_map(|id| match rt.store().get(id) {
+            Ok(fle) => fle,
+            Err(e) => {
+                trace_error(&e);
+                None
+            },
+        })
+        .filter(|fle| Mail::is_mail(fle))
+        .filter(|fle| filter.as_ref().map(|f| f.filter(fle)).unwrap_or(true));
+
+    let _ = TableLister::new(|fle| {
+            vec![mail_hash(fle),
+                 header_string(fle, "mail.from"),
+                 header_string(fle, "mail.to"),
+                 header_string(fle, "mail.subject")]
+        })
+        .with_header(vec![String::from("Hash"), String::from("From"), String::from("To"),
+                          String::from("Subject")])
+        .with_idx(false)
+        .list(mails)
+        .map_err_trace_exit(1);
+}
+
+fn thread(rt: &Runtime) {
+    use std::iter::repeat;
+
+    use libimagentrylist::lister::Lister;
+    use libimagentrylist::listers::table::TableLister;
+
+    let cmd  = rt.cli().subcommand_matches("thread").unwrap();
+    let hash = cmd.value_of("hash").unwrap(); // saved by clap
+
+    let id = match Mail::open(rt.store(), hash) {
+        Ok(Some(mail)) => mail.get_ref().get_location().clone(),
+        Ok(None) => {
+            error!("No mail with hash {}", hash);
+            ::std::process::exit(1);
+        },
+        Err(e) => trace_error_exit(&e, 1),
+    };
+
+    let thread = get_thread(rt.store(), &id).map_err_trace_exit(1).unwrap(); // safe by map_err_trace_exit()
+    let depths = thread
+        .iter()
+        .map(|&(depth, ref mail)| (mail.id().local().clone(), depth))
+        .collect::<HashMap<_, _>>();
+
+    let mails = thread
+        .iter()
+        .filter_map(|&(_, ref mail)| match rt.store().get(mail.id().clone()) {
+            Ok(fle) => fle,
+            Err(e) => {
+                trace_error(&e);
+                None
+            },
+        });
+
+    let _ = TableLister::new(|fle| {
+            let depth   = depths.get(fle.get_location().local()).cloned().unwrap_or(0);
+            let indent  = repeat("  ").take(depth).collect::<String>();
+            let subject = format!("{}{}", indent, header_string(fle, "mail.subject"));
+            vec![mail_hash(fle), header_string(fle, "mail.from"), subject]
+        })
+        .with_header(vec![String::from("Hash"), String::from("From"), String::from("Subject")])
+        .with_idx(false)
+        .list(mails)
+        .map_err_trace_exit(1);
+}
+
@@ -0,0 +1,66 @@
+//
+// imag - the personal information management suite for the commandline
+// Copyright (C) 2015, 2016 Matthias Beyer <mail@beyermatthias.de> and contributors
+//
+// This library is free software; you can redistribute it and/or
+// modify it under the terms of the GNU Lesser General Public
+// License as published by the Free Software Foundation; version
+// 2.1 of the License.
+//
+// This library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+// Lesser General Public License for more details.
+//
+// You should have received a copy of the GNU Lesser General Public
+// License along with this library; if not, write to the Free Software
+// Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA  02110-1301  USA
+//
+
+use clap::{Arg, App, SubCommand};
+
+use libimagentryfilter::cli::filter_argument;
+
+pub fn build_ui<'a>(app: App<'a, 'a>) -> App<'a, 'a> {
+    app
+        .subcommand(SubCommand::with_name("import")
+                    .about("Import mails from Maildir folders, mbox files or single mail files")
+                    .version("0.1")
+                    .arg(Arg::with_name("path")
+                         .index(1)
+                         .takes_value(true)
+                         .multiple(true)
+                         .required(true)
+                         .help("Maildir folders, mbox files or mail files to import")
+                         .value_name("PATH"))
+                    .arg(Arg::with_name("mbox-dir")
+                         .long("mbox-dir")
+                         .takes_value(true)
+                         .required(false)
+                         .help("Where to put the mails of split mbox files. Default: $RTP/mail/mbox")
+                         .value_name("DIR"))
+                    .arg(Arg::with_name("no-thread")
+                         .long("no-thread")
+                         .takes_value(false)
+                         .required(false)
+                         .help("Do not link the imported mails into threads"))
+                    )
+
+        .subcommand(SubCommand::with_name("list")
+                    .about("List mails")
+                    .version("0.1")
+                    .arg(filter_argument())
+                    )
+
+        .subcommand(SubCommand::with_name("thread")
+                    .about("Show the thread of a mail")
+                    .version("0.1")
+                    .arg(Arg::with_name("hash")
+                         .index(1)
+                         .takes_value(true)
+                         .required(true)
+                         .help("The hash of the mail, as shown by 'list'")
+                         .value_name("HASH"))
+                    )
+}
+
@@ -22,10 +22,12 @@ extern crate clap;
 extern crate semver;
 #[macro_use] extern crate version;
 extern crate itertools;
+extern crate filters;
 
 extern crate libimagnotes;
 extern crate libimagrt;
 extern crate libimagentryedit;
+extern crate libimagentryfilter;
 extern crate libimagentrytag;
 extern crate libimagerror;
 extern crate libimagutil;
@@ -112,10 +114,19 @@ fn edit_entry(rt: &Runtime, name: String) -> bool {
 fn list(rt: &Runtime) {
     use std::cmp::Ordering;
 
+    use filters::filter::Filter;
+    use libimagentryfilter::cli::get_filter;
+
+    let filter = rt.cli()
+        .subcommand_matches("list")
+        .map(|matches| get_filter(matches).map_err_trace_exit(1).unwrap()) // safe by map_err_trace_exit()
+        .and_then(|filter| filter);
+
     Note::all_notes(rt.store())
         .map_err_trace_exit(1)
         .map(|iter| {
             let notes = iter.filter_map(|note| note.map_err_trace().ok())
+                .filter(|note| filter.as_ref().map(|f| f.filter(note)).unwrap_or(true))
                 .sort_by(|note_a, note_b| {
                     if let (Ok(a), Ok(b)) = (note_a.get_name(), note_b.get_name()) {
                         return a.cmp(&b)
@@ -19,6 +19,7 @@
 
 use clap::{Arg, App, SubCommand};
 
+use libimagentryfilter::cli::filter_argument;
 use libimagentrytag::ui::tag_argument;
 
 pub fn build_ui<'a>(app: App<'a, 'a>) -> App<'a, 'a> {
@@ -68,6 +69,7 @@ pub fn build_ui<'a>(app: App<'a, 'a>) -> App<'a, 'a> {
 
         .subcommand(SubCommand::with_name("list")
                    .about("List Notes")
-                   .version("0.1"))
+                   .version("0.1")
+                   .arg(filter_argument()))
 
 }
@@ -52,7 +52,7 @@ use std::path::PathBuf;
 
 use libimagref::reference::Ref;
 use libimagref::flags::RefFlags;
-use libimagerror::trace::trace_error;
+use libimagerror::trace::{MapErrTrace, trace_error};
 use libimagrt::setup::generate_runtime_setup;
 use libimagrt::runtime::Runtime;
 
@@ -61,6 +61,10 @@ fn main() {
                                     &version!()[..],
                                     "Reference files outside of the store",
                                     build_ui);
+
+    let _ = rt.store()
+        .register_schema(Ref::header_schema())
+        .map_err_trace();
     rt.cli()
         .subcommand_name()
         .map(|name| {
@@ -0,0 +1,159 @@
+//
+// imag - the personal information management suite for the commandline
+// Copyright (C) 2015, 2016 Matthias Beyer <mail@beyermatthias.de> and contributors
+//
+// This library is free software; you can redistribute it and/or
+// modify it under the terms of the GNU Lesser General Public
+// License as published by the Free Software Foundation; version
+// 2.1 of the License.
+//
+// This library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+// Lesser General Public License for more details.
+//
+// You should have received a copy of the GNU Lesser General Public
+// License along with this library; if not, write to the Free Software
+// Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA  02110-1301  USA
+//
+
+#![deny(
+    non_camel_case_types,
+    non_snake_case,
+    path_statements,
+    trivial_numeric_casts,
+    unstable_features,
+    unused_allocation,
+    unused_import_braces,
+    unused_imports,
+    unused_must_use,
+    unused_mut,
+    unused_qualifications,
+    while_true,
+)]
+
+#[macro_use] extern crate log;
+#[macro_use] extern crate version;
+extern crate clap;
+
+extern crate libimagstore;
+extern crate libimagrt;
+extern crate libimagerror;
+extern crate libimagsearch;
+extern crate libimagentrylist;
+
+mod ui;
+use ui::build_ui;
+
+use std::collections::HashMap;
+use std::process::exit;
+use std::str::FromStr;
+
+use libimagerror::trace::{MapErrTrace, trace_error};
+use libimagrt::setup::generate_runtime_setup;
+use libimagrt::runtime::Runtime;
+use libimagsearch::index::SearchIndex;
+use libimagsearch::query::Query;
+
+fn main() {
+    let rt = generate_runtime_setup("imag-search",
+                                    &version!()[..],
+                                    "Full-text search over the store",
+                                    build_ui);
+
+    rt.cli()
+        .subcommand_name()
+        .map(|name| {
+            debug!("Call: {}", name);
+            match name {
+                "query"   => query(&rt),
+                "rebuild" => rebuild(&rt),
+                _         => {
+                    debug!("Unknown command"); // More error handling
+                },
+            };
+        });
+}
+
+fn load_index(rt: &Runtime) -> SearchIndex {
+    SearchIndex::load(rt.store().backend(), SearchIndex::default_location(rt.store().path()))
+        .map_err_trace_exit(1)
+        .unwrap() // safe by map_err_trace_exit()
+}
+
+fn query(rt: &Runtime) {
+    use libimagentrylist::lister::Lister;
+    use libimagentrylist::listers::path::PathLister;
+    use libimagentrylist::listers::table::TableLister;
+
+    let cmd    = rt.cli().subcommand_matches("query").unwrap();
+    let module = cmd.value_of("module");
+    let limit  = match cmd.value_of("limit").map(usize::from_str) {
+        None         => None,
+        Some(Ok(n))  => Some(n),
+        Some(Err(_)) => {
+            error!("Not a number: {}", cmd.value_of("limit").unwrap());
+            exit(1);
+        },
+    };
+
+    let query = cmd.values_of("query")
+        .unwrap() // saved by clap
+        .collect::<Vec<_>>()
+        .join(" ");
+    let query = Query::parse(&query).map_err_trace_exit(1).unwrap(); // safe by map_err_trace_exit()
+
+    let mut results = load_index(rt).search(&query, module);
+    if let Some(n) = limit {
+        results.truncate(n);
+    }
+    debug!("Found {} results", results.len());
+
+    let scores = results
+        .iter()
+        .map(|r| (r.id().local().clone(), r.score()))
+        .collect::<HashMap<_, _>>();
+
+    let entries = results
+        .into_iter()
+        .filter_map(|r| match rt.store().get(r.id().clone()) {
+            Ok(Some(fle)) => Some(fle),
+            Ok(None) => {
+                warn!("Entry {} is in the search index but not in the store", r.id());
+                warn!("Use 'imag-search rebuild' to fix the index");
+                None
+            },
+            Err(e) => {
+                trace_error(&e);
+                None
+            },
+        });
+
+    let listed = if cmd.is_present("paths") {
+        PathLister::new(false).list(entries)
+    } else {
+        TableLister::new(|fle| {
+                let id = fle.get_location();
+                let score = scores.get(id.local()).cloned().unwrap_or(0.0);
+                vec![format!("{:.3}", score), format!("{}", id)]
+            })
+            .with_header(vec![String::from("Score"), String::from("Entry")])
+            .with_idx(false)
+            .list(entries)
+    };
+
+    let _ = listed.map_err_trace_exit(1);
+}
+
+fn rebuild(rt: &Runtime) {
+    let cmd    = rt.cli().subcommand_matches("rebuild").unwrap();
+    let module = cmd.value_of("module");
+
+    let mut index = load_index(rt);
+    let _ = index.rebuild(rt.store(), module)
+        .and_then(|_| index.save(rt.store().backend()))
+        .map_err_trace_exit(1);
+
+    info!("Indexed {} entries", index.len());
+}
+
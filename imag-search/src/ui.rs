@@ -0,0 +1,68 @@
+//
+// imag - the personal information management suite for the commandline
+// Copyright (C) 2015, 2016 Matthias Beyer <mail@beyermatthias.de> and contributors
+//
+// This library is free software; you can redistribute it and/or
+// modify it under the terms of the GNU Lesser General Public
+// License as published by the Free Software Foundation; version
+// 2.1 of the License.
+//
+// This library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+// Lesser General Public License for more details.
+//
+// You should have received a copy of the GNU Lesser General Public
+// License along with this library; if not, write to the Free Software
+// Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA  02110-1301  USA
+//
+
+use clap::{Arg, App, SubCommand};
+
+pub fn build_ui<'a>(app: App<'a, 'a>) -> App<'a, 'a> {
+    app
+        .subcommand(SubCommand::with_name("query")
+                    .about("Search the store")
+                    .version("0.1")
+                    .arg(Arg::with_name("query")
+                         .index(1)
+                         .multiple(true)
+                         .takes_value(true)
+                         .required(true)
+                         .help("The query. Terms are AND-ed, use OR, NOT/-term, \"phrases\" and parentheses for more complex queries")
+                         .value_name("QUERY"))
+                    .arg(Arg::with_name("module")
+                         .long("module")
+                         .short("m")
+                         .takes_value(true)
+                         .required(false)
+                         .help("Only search entries of this module (StoreId prefix)")
+                         .value_name("MODULE"))
+                    .arg(Arg::with_name("limit")
+                         .long("limit")
+                         .short("l")
+                         .takes_value(true)
+                         .required(false)
+                         .help("Show at most this many results")
+                         .value_name("N"))
+                    .arg(Arg::with_name("paths")
+                         .long("paths")
+                         .short("p")
+                         .takes_value(false)
+                         .required(false)
+                         .help("Print only the paths of the matching entries"))
+                    )
+
+        .subcommand(SubCommand::with_name("rebuild")
+                    .about("Rebuild the search index from the entries in the store")
+                    .version("0.1")
+                    .arg(Arg::with_name("module")
+                         .long("module")
+                         .short("m")
+                         .takes_value(true)
+                         .required(false)
+                         .help("Only reindex the entries of this module")
+                         .value_name("MODULE"))
+                    )
+}
+
@@ -42,13 +42,24 @@ extern crate libimagrt;
 extern crate libimagstore;
 extern crate libimagutil;
 #[macro_use] extern crate libimagerror;
+extern crate libimagcounter;
+extern crate libimagref;
+extern crate libimagtodo;
+extern crate libimagentrylink;
 
 use libimagrt::setup::generate_runtime_setup;
+use libimagerror::trace::MapErrTrace;
+use libimagcounter::counter::Counter;
+use libimagref::reference::Ref;
+use libimagtodo::task::Task;
 
 mod create;
 mod delete;
 mod error;
 mod get;
+mod migrate;
+mod reindex;
+mod relocate;
 mod retrieve;
 mod ui;
 mod update;
@@ -58,6 +69,9 @@ mod util;
 use create::create;
 use delete::delete;
 use get::get;
+use migrate::migrate;
+use reindex::reindex;
+use relocate::relocate;
 use retrieve::retrieve;
 use ui::build_ui;
 use update::update;
@@ -69,6 +83,11 @@ fn main() {
                                     "Direct interface to the store. Use with great care!",
                                     build_ui);
 
+    // Know the header schemas of the modules, so entries are validated on update and on verify
+    for schema in vec![Counter::header_schema(), Ref::header_schema(), Task::header_schema()] {
+        let _ = rt.store().register_schema(schema).map_err_trace();
+    }
+
     rt.cli()
         .subcommand_name()
         .map_or_else(
@@ -82,6 +101,9 @@ fn main() {
                     "create"   => create(&rt),
                     "delete"   => delete(&rt),
                     "get"      => get(&rt),
+                    "migrate"  => migrate(&rt),
+                    "move"     => relocate(&rt),
+                    "reindex"  => reindex(&rt),
                     "retrieve" => retrieve(&rt),
                     "update"   => update(&rt),
                     "verify"   => verify(&rt),
@@ -0,0 +1,43 @@
+//
+// imag - the personal information management suite for the commandline
+// Copyright (C) 2015, 2016 Matthias Beyer <mail@beyermatthias.de> and contributors
+//
+// This library is free software; you can redistribute it and/or
+// modify it under the terms of the GNU Lesser General Public
+// License as published by the Free Software Foundation; version
+// 2.1 of the License.
+//
+// This library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+// Lesser General Public License for more details.
+//
+// You should have received a copy of the GNU Lesser General Public
+// License along with this library; if not, write to the Free Software
+// Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA  02110-1301  USA
+//
+
+use libimagrt::runtime::Runtime;
+use libimagerror::trace::trace_error_exit;
+
+pub fn migrate(rt: &Runtime) {
+    let dry_run = rt.cli()
+        .subcommand_matches("migrate")
+        .map(|sub| sub.is_present("dry-run"))
+        .unwrap_or(false);
+
+    let steps = match rt.store().migrate(dry_run) {
+        Ok(steps) => steps,
+        Err(e)    => trace_error_exit(&e, 1),
+    };
+
+    for step in &steps {
+        if dry_run {
+            println!("Would migrate {}", step);
+        } else {
+            println!("Migrated {}", step);
+        }
+    }
+
+    info!("{} migration steps {}", steps.len(), if dry_run { "pending" } else { "applied" });
+}
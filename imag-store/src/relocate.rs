@@ -0,0 +1,47 @@
+//
+// imag - the personal information management suite for the commandline
+// Copyright (C) 2015, 2016 Matthias Beyer <mail@beyermatthias.de> and contributors
+//
+// This library is free software; you can redistribute it and/or
+// modify it under the terms of the GNU Lesser General Public
+// License as published by the Free Software Foundation; version
+// 2.1 of the License.
+//
+// This library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+// Lesser General Public License for more details.
+//
+// You should have received a copy of the GNU Lesser General Public
+// License along with this library; if not, write to the Free Software
+// Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA  02110-1301  USA
+//
+
+use std::path::PathBuf;
+
+use libimagrt::runtime::Runtime;
+use libimagerror::trace::MapErrTrace;
+use libimagentrylink::relocate::move_entry;
+use libimagstore::storeid::StoreId;
+use libimagutil::warn_exit::warn_exit;
+use libimagutil::warn_result::*;
+
+/// Move an entry and rewrite the links of all entries linked to it
+pub fn relocate(rt: &Runtime) {
+    let sub = rt.cli()
+        .subcommand_matches("move")
+        .unwrap_or_else(|| warn_exit("No subcommand 'move'. Will exit now", 1));
+
+    let to_id = |name: &str| {
+        StoreId::new(Some(rt.store().path().clone()), PathBuf::from(name))
+            .map_err_trace_exit(1)
+            .unwrap() // exited above on error
+    };
+    let from = to_id(sub.value_of("from").unwrap()); // enforced by clap
+    let to   = to_id(sub.value_of("to").unwrap()); // enforced by clap
+    debug!("Moving {:?} -> {:?}", from, to);
+
+    let _ = move_entry(rt.store(), from, to)
+        .map_warn_err(|e| format!("Error: {:?}", e))
+        .map_err_trace_exit(1);
+}
@@ -201,8 +201,43 @@ pub fn build_ui<'a>(app: App<'a, 'a>) -> App<'a, 'a> {
                         .value_name("PATH"))
                    )
 
+       .subcommand(SubCommand::with_name("move")
+                   .about("Move an entry, rewriting the links of all entries linked to it")
+                   .version("0.1")
+                   .arg(Arg::with_name("from")
+                        .long("from")
+                        .short("f")
+                        .takes_value(true)
+                        .required(true)
+                        .help("Move the Store Entry with this path")
+                        .value_name("PATH"))
+                   .arg(Arg::with_name("to")
+                        .long("to")
+                        .short("t")
+                        .takes_value(true)
+                        .required(true)
+                        .help("Move the Store Entry to this path")
+                        .value_name("PATH"))
+                   )
+
        .subcommand(SubCommand::with_name("verify")
                    .about("Verify the store")
                    .version("0.1")
                    )
+
+       .subcommand(SubCommand::with_name("reindex")
+                   .about("Rebuild the store index from all entries in the store")
+                   .version("0.1")
+                   )
+
+       .subcommand(SubCommand::with_name("migrate")
+                   .about("Migrate all entries to the current store format")
+                   .version("0.1")
+                   .arg(Arg::with_name("dry-run")
+                        .long("dry-run")
+                        .short("n")
+                        .takes_value(false)
+                        .required(false)
+                        .help("Only print which migrations would be applied"))
+                   )
 }
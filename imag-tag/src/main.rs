@@ -18,6 +18,7 @@
 //
 
 extern crate clap;
+extern crate filters;
 #[macro_use] extern crate log;
 extern crate semver;
 extern crate toml;
@@ -26,17 +27,26 @@ extern crate toml;
 extern crate libimagstore;
 extern crate libimagrt;
 extern crate libimagentrytag;
+extern crate libimagentryfilter;
 extern crate libimagerror;
 extern crate libimagutil;
 
 use std::process::exit;
 use std::path::PathBuf;
 
+use filters::filter::Filter;
+
 use libimagrt::runtime::Runtime;
 use libimagrt::setup::generate_runtime_setup;
+use libimagentryfilter::cli::get_filter;
+use libimagentryfilter::query::Query;
+use libimagentrytag::alias::TagAliases;
+use libimagentrytag::storewide::{add_tags_where, entries_where, merge_tags, remove_tags_where,
+                                 rename_tag, tag_counts};
+use libimagentrytag::util::is_tag;
 use libimagentrytag::tagable::Tagable;
 use libimagentrytag::tag::Tag;
-use libimagerror::trace::{trace_error, trace_error_exit};
+use libimagerror::trace::{MapErrTrace, trace_error, trace_error_exit};
 use libimagentrytag::ui::{get_add_tags, get_remove_tags};
 use libimagstore::storeid::StoreId;
 use libimagutil::warn_exit::warn_exit;
@@ -51,21 +61,24 @@ fn main() {
                                     "Direct interface to the store. Use with great care!",
                                     build_ui);
 
-    let id = rt.cli().value_of("id").unwrap(); // enforced by clap
     rt.cli()
         .subcommand_name()
         .map_or_else(
             || {
-                let id = PathBuf::from(id);
+                let id = get_id(&rt);
                 let add = get_add_tags(rt.cli());
                 let rem = get_remove_tags(rt.cli());
                 alter(&rt, id, add, rem);
             },
             |name| {
-                let id = PathBuf::from(id);
                 debug!("Call: {}", name);
                 match name {
-                    "list" => list(id, &rt),
+                    "list"    => list(get_id(&rt), &rt),
+                    "rename"  => rename(&rt),
+                    "merge"   => merge(&rt),
+                    "counts"  => counts(&rt),
+                    "entries" => entries(&rt),
+                    "bulk"    => bulk(&rt),
                     _ => {
                         warn!("Unknown command");
                         // More error handling
@@ -74,6 +87,19 @@ fn main() {
             });
 }
 
+fn get_id(rt: &Runtime) -> PathBuf {
+    rt.cli()
+        .value_of("id")
+        .map(PathBuf::from)
+        .unwrap_or_else(|| warn_exit("No entry specified, use --id", 1))
+}
+
+fn get_aliases(rt: &Runtime) -> TagAliases {
+    TagAliases::from_store(rt.store())
+        .map_err_trace_exit(1)
+        .unwrap() // safe by map_err_trace_exit()
+}
+
 fn alter(rt: &Runtime, id: PathBuf, add: Option<Vec<Tag>>, rem: Option<Vec<Tag>>) {
     let path = {
         match StoreId::new(Some(rt.store().path().clone()), id) {
@@ -170,3 +196,85 @@ fn list(id: PathBuf, rt: &Runtime) {
     }
 }
 
+
+fn rename(rt: &Runtime) {
+    let scmd = rt.cli().subcommand_matches("rename").unwrap(); // safe, we checked in main()
+    let from = scmd.value_of("from").unwrap(); // enforced by clap
+    let to   = scmd.value_of("to").unwrap(); // enforced by clap
+
+    let n = rename_tag(rt.store(), from, to).map_err_trace_exit(1).unwrap(); // safe by map_err_trace_exit()
+    info!("Renamed '{}' to '{}' in {} entries", from, to, n);
+}
+
+fn merge(rt: &Runtime) {
+    let scmd    = rt.cli().subcommand_matches("merge").unwrap(); // safe, we checked in main()
+    let target  = scmd.value_of("into").unwrap(); // enforced by clap
+    let sources = scmd
+        .values_of("tags")
+        .unwrap() // enforced by clap
+        .map(String::from)
+        .collect::<Vec<Tag>>();
+
+    let n = merge_tags(rt.store(), &sources, target).map_err_trace_exit(1).unwrap(); // safe by map_err_trace_exit()
+    info!("Merged {} into '{}' in {} entries", sources.join(", "), target, n);
+}
+
+fn counts(rt: &Runtime) {
+    let scmd    = rt.cli().subcommand_matches("counts").unwrap(); // safe, we checked in main()
+    let parents = scmd.is_present("parents");
+
+    let counts = tag_counts(rt.store(), parents).map_err_trace_exit(1).unwrap(); // safe by map_err_trace_exit()
+    for (tag, count) in counts {
+        println!("{:>6} {}", count, tag);
+    }
+}
+
+fn entries(rt: &Runtime) {
+    let scmd  = rt.cli().subcommand_matches("entries").unwrap(); // safe, we checked in main()
+    let expr  = scmd.value_of("expression").unwrap(); // enforced by clap
+
+    // A single tag is a shortcut for the "tag:<name>" query
+    let query = if is_tag(expr) {
+        Query::parse(&format!("tag:{}", expr))
+    } else {
+        Query::parse(expr)
+    };
+    let query = query.map_err_trace_exit(1).unwrap(); // safe by map_err_trace_exit()
+
+    let ids = entries_where(rt.store(), |e| query.filter(e))
+        .map_err_trace_exit(1)
+        .unwrap(); // safe by map_err_trace_exit()
+
+    for id in ids {
+        println!("{}", id);
+    }
+}
+
+fn bulk(rt: &Runtime) {
+    let scmd  = rt.cli().subcommand_matches("bulk").unwrap(); // safe, we checked in main()
+    let query = get_filter(scmd)
+        .map_err_trace_exit(1)
+        .unwrap() // safe by map_err_trace_exit()
+        .unwrap_or_else(|| warn_exit("No filter query specified", 1));
+
+    let add = get_add_tags(scmd);
+    let rem = get_remove_tags(scmd);
+    if add.is_none() && rem.is_none() {
+        warn_exit("Nothing to do, pass tags to add or remove", 1);
+    }
+
+    if let Some(tags) = add {
+        let aliases = get_aliases(rt);
+        let n = add_tags_where(rt.store(), |e| query.filter(e), &tags, &aliases)
+            .map_err_trace_exit(1)
+            .unwrap(); // safe by map_err_trace_exit()
+        info!("Added tags to {} entries", n);
+    }
+
+    if let Some(tags) = rem {
+        let n = remove_tags_where(rt.store(), |e| query.filter(e), &tags)
+            .map_err_trace_exit(1)
+            .unwrap(); // safe by map_err_trace_exit()
+        info!("Removed tags from {} entries", n);
+    }
+}
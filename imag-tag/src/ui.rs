@@ -19,15 +19,17 @@
 
 use clap::{Arg, App, ArgGroup, SubCommand};
 
+use libimagentryfilter::cli::filter_argument;
 use libimagentrytag::ui::{tag_add_arg, tag_remove_arg};
+use libimagutil::cli_validators::is_tag;
 
 pub fn build_ui<'a>(app: App<'a, 'a>) -> App<'a, 'a> {
     app.arg(Arg::with_name("id")
                 .long("id")
                 .short("i")
                 .takes_value(true)
-                .required(true)
-                .help("Use this entry")
+                .required(false)
+                .help("Use this entry (required for altering and listing tags)")
                 .value_name("ID"))
 
         .arg(tag_add_arg())
@@ -72,4 +74,74 @@ pub fn build_ui<'a>(app: App<'a, 'a>) -> App<'a, 'a> {
                           .required(true))
                    )
 
+       .subcommand(SubCommand::with_name("rename")
+                   .about("Rename a tag and its subtags in all entries of the store")
+                   .version("0.1")
+                   .arg(Arg::with_name("from")
+                        .index(1)
+                        .takes_value(true)
+                        .required(true)
+                        .validator(is_tag)
+                        .help("The tag to rename")
+                        .value_name("OLD"))
+                   .arg(Arg::with_name("to")
+                        .index(2)
+                        .takes_value(true)
+                        .required(true)
+                        .validator(is_tag)
+                        .help("The new name of the tag")
+                        .value_name("NEW"))
+                   )
+
+       .subcommand(SubCommand::with_name("merge")
+                   .about("Merge tags into one tag in all entries of the store")
+                   .version("0.1")
+                   .arg(Arg::with_name("tags")
+                        .index(1)
+                        .takes_value(true)
+                        .required(true)
+                        .multiple(true)
+                        .validator(is_tag)
+                        .help("The tags to merge")
+                        .value_name("TAGS"))
+                   .arg(Arg::with_name("into")
+                        .long("into")
+                        .short("I")
+                        .takes_value(true)
+                        .required(true)
+                        .validator(is_tag)
+                        .help("The tag to merge into")
+                        .value_name("TAG"))
+                   )
+
+       .subcommand(SubCommand::with_name("counts")
+                   .about("List all tags in the store with the number of entries carrying them")
+                   .version("0.1")
+                   .arg(Arg::with_name("parents")
+                        .long("parents")
+                        .short("p")
+                        .takes_value(false)
+                        .required(false)
+                        .help("Also count entries for the parents of their tags"))
+                   )
+
+       .subcommand(SubCommand::with_name("entries")
+                   .about("List all entries matching a tag or a filter query like 'tag:a and not tag:b'")
+                   .version("0.1")
+                   .arg(Arg::with_name("expression")
+                        .index(1)
+                        .takes_value(true)
+                        .required(true)
+                        .help("The tag or filter query")
+                        .value_name("EXPRESSION"))
+                   )
+
+       .subcommand(SubCommand::with_name("bulk")
+                   .about("Add or remove tags on all entries matching a filter query")
+                   .version("0.1")
+                   .arg(filter_argument().required(true))
+                   .arg(tag_add_arg())
+                   .arg(tag_remove_arg())
+                   )
+
 }
@@ -1,4 +1,4 @@
 #/!usr/bin/env bash
 
-imag todo tw-hook --delete
+imag todo tw-hook --modify
 
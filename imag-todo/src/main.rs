@@ -32,14 +32,20 @@ extern crate libimagstore;
 extern crate libimagerror;
 extern crate libimagtodo;
 
-use std::process::{Command, Stdio};
+use std::collections::BTreeMap;
+use std::process::exit;
 use std::io::stdin;
+use std::str::FromStr;
 
-use toml::Value;
+use clap::ArgMatches;
 
 use libimagrt::runtime::Runtime;
 use libimagrt::setup::generate_runtime_setup;
+use libimagtodo::data::{self, Priority, Status, TaskData};
+use libimagtodo::recurrence::Recurrence;
 use libimagtodo::task::Task;
+use libimagtodo::taskwarrior;
+use libimagtodo::sync::{self, ConflictKind, Resolution};
 use libimagerror::trace::{MapErrTrace, trace_error, trace_error_exit};
 
 mod ui;
@@ -48,12 +54,21 @@ use ui::build_ui;
 fn main() {
     let rt = generate_runtime_setup("imag-todo",
                                     &version!()[..],
-                                    "Interface with taskwarrior",
+                                    "Manage tasks, optionally synchronized with taskwarrior",
                                     build_ui);
 
+    let _ = rt.store()
+        .register_schema(Task::header_schema())
+        .map_err_trace();
+
     match rt.cli().subcommand_name() {
         Some("tw-hook") => tw_hook(&rt),
         Some("list") => list(&rt),
+        Some("add") => add(&rt),
+        Some("done") => done(&rt),
+        Some("show") => show(&rt),
+        Some("modify") => modify(&rt),
+        Some("sync") => sync(&rt),
         None => {
             warn!("No command");
         },
@@ -76,6 +91,10 @@ fn tw_hook(rt: &Runtime) {
         // per usage und wants one (the second one) back.
         let stdin         = stdin();
         Task::delete_by_imports(rt.store(), stdin.lock()).map_err_trace().ok();
+    } else if subcmd.is_present("modify") {
+        // Same as above, but all modifications are mirrored into the store
+        let stdin         = stdin();
+        Task::modify_by_imports(rt.store(), stdin.lock()).map_err_trace().ok();
     } else {
         // Should not be possible, as one argument is required via
         // ArgGroup
@@ -86,53 +105,348 @@ fn tw_hook(rt: &Runtime) {
 fn list(rt: &Runtime) {
     let subcmd  = rt.cli().subcommand_matches("list").unwrap();
     let verbose = subcmd.is_present("verbose");
+    let all     = subcmd.is_present("all");
+    let status  = subcmd.value_of("status").map(|s| Status::from_str(s).unwrap()); // safe by clap
+    let project = subcmd.value_of("project");
+    let tag     = subcmd.value_of("tag").map(String::from);
 
-    let res = Task::all(rt.store()) // get all tasks
-        .map(|iter| { // and if this succeeded
-            // filter out the ones were we can read the uuid
-            let uuids : Vec<_> = iter.filter_map(|t| match t {
-                Ok(v) => match v.get_header().read("todo.uuid") {
-                    Ok(Some(Value::String(ref u))) => Some(u.clone()),
-                    Ok(Some(_)) => {
-                        warn!("Header type error");
-                        None
-                    },
-                    Ok(None) => None,
-                    Err(e) => {
-                        trace_error(&e);
-                        None
-                    }
-                },
-                Err(e) => {
-                    trace_error(&e);
-                    None
-                }
-            })
-            .collect();
-
-            // compose a `task` call with them, ...
-            let outstring = if verbose { // ... if verbose
-                let output = Command::new("task")
-                    .stdin(Stdio::null())
-                    .args(&uuids)
-                    .spawn()
-                    .unwrap_or_else(|e| {
-                        trace_error(&e);
-                        panic!("Failed to execute `task` on the commandline. I'm dying now.");
-                    })
-                    .wait_with_output()
-                    .unwrap_or_else(|e| panic!("failed to unwrap output: {}", e));
-
-                String::from_utf8(output.stdout)
-                    .unwrap_or_else(|e| panic!("failed to execute: {}", e))
-            } else { // ... else just join them
-                uuids.join("\n")
-            };
-
-            // and then print that
-            println!("{}", outstring);
+    let tasks = Task::all(rt.store())
+        .map_err_trace_exit(1)
+        .unwrap() // safe by map_err_trace_exit()
+        .filter_map(|t| match t.and_then(|t| t.data()) {
+            Ok(data) => Some((data.uuid.clone(), data)),
+            Err(e)   => {
+                trace_error(&e);
+                None
+            },
+        })
+        .collect::<BTreeMap<String, TaskData>>();
+
+    let mut listed = tasks.values()
+        .filter(|t| match status {
+            Some(s) => t.current_status() == s,
+            None    => all || t.current_status() == Status::Pending,
+        })
+        .filter(|t| project.map(|p| t.project.as_ref().map(|tp| tp == p).unwrap_or(false)).unwrap_or(true))
+        .filter(|t| tag.as_ref().map(|tag| t.tags.contains(tag)).unwrap_or(true))
+        .collect::<Vec<_>>();
+
+    // Tasks with a due date first, the earliest first, then by priority
+    listed.sort_by(|a, b| {
+        let due = |t: &TaskData| t.due.clone().unwrap_or(String::from("~"));
+        let prio = |t: &TaskData| t.priority.map(|p| p as u8).unwrap_or(3);
+        (due(a), prio(a), a.entry.clone()).cmp(&(due(b), prio(b), b.entry.clone()))
+    });
+
+    for task in listed {
+        if verbose {
+            let blocked = task.depends
+                .iter()
+                .any(|dep| tasks.get(dep).map(|d| d.status.is_open()).unwrap_or(false));
+
+            let tags = task.tags
+                .iter()
+                .map(|t| format!(" +{}", t))
+                .collect::<Vec<_>>()
+                .join("");
+
+            println!("{} [{}]{}{} {}{}{}{}",
+                     task.uuid,
+                     task.current_status(),
+                     task.priority.map(|p| format!(" ({})", p)).unwrap_or(String::new()),
+                     task.project.as_ref().map(|p| format!(" {}:", p)).unwrap_or(String::new()),
+                     task.description,
+                     task.due.as_ref().map(|d| format!(" due:{}", d)).unwrap_or(String::new()),
+                     tags,
+                     if blocked { " (blocked)" } else { "" });
+        } else {
+            let short = task.uuid.chars().take(8).collect::<String>();
+            println!("{} {}", short, task.description);
+        }
+    }
+}
+
+fn add(rt: &Runtime) {
+    let subcmd      = rt.cli().subcommand_matches("add").unwrap();
+    let binary      = subcmd.value_of("task-binary").unwrap_or("task");
+    let description = subcmd.values_of("description")
+        .unwrap() // enforced by clap
+        .collect::<Vec<_>>()
+        .join(" ");
+
+    let mut data = TaskData::new(description);
+    data.tags    = subcmd.values_of("tag")
+        .map(|tags| tags.map(String::from).collect())
+        .unwrap_or(vec![]);
+    set_attributes(subcmd, &mut data);
+
+    let data = {
+        let task = if subcmd.is_present("push") {
+            Task::create_for_taskwarrior(rt.store(), &data)
+        } else {
+            Task::create(rt.store(), &data)
+        };
+        let mut task = task.map_err_trace_exit(1).unwrap(); // safe by map_err_trace_exit()
+        if let Some(ids) = subcmd.values_of("depends") {
+            for id in ids {
+                let mut other = find_or_exit(rt, id);
+                let _ = task.add_dependency(&mut other).map_err_trace_exit(1);
+            }
+        }
+        task.data().map_err_trace_exit(1).unwrap() // safe by map_err_trace_exit()
+    };
+
+    if subcmd.is_present("push") {
+        let res = taskwarrior::import(binary, &[data.clone()])
+            .and_then(|_| taskwarrior::export(binary))
+            .and_then(|tasks| match tasks.into_iter().find(|t| t.uuid == data.uuid) {
+                // Read back what taskwarrior made of the task, so both sides are in sync
+                Some(t) => Task::store_synced(rt.store(), &t).map(|_| ()),
+                None    => Ok(()),
+            });
+
+        if let Err(e) = res {
+            trace_error(&e);
+            warn!("Task {} will be passed to taskwarrior on the next sync", data.uuid);
+        }
+    }
+
+    println!("{}", data.uuid);
+}
+
+fn done(rt: &Runtime) {
+    let subcmd = rt.cli().subcommand_matches("done").unwrap();
+
+    for id in subcmd.values_of("id").unwrap() { // enforced by clap
+        let mut task = find_or_exit(rt, id);
+        match task.done(rt.store()) {
+            Ok(Some(next)) => {
+                let _ = next.data()
+                    .map(|d| info!("Next instance of the task: {}", d.uuid))
+                    .map_err_trace();
+            },
+            Ok(None) => { },
+            Err(e)   => trace_error_exit(&e, 1),
+        }
+    }
+}
+
+fn show(rt: &Runtime) {
+    let subcmd = rt.cli().subcommand_matches("show").unwrap();
+    let id     = subcmd.value_of("id").unwrap(); // enforced by clap
+    let data   = find_or_exit(rt, id).data().map_err_trace_exit(1).unwrap(); // safe by map_err_trace_exit()
+
+    println!("UUID:        {}", data.uuid);
+    println!("Description: {}", data.description);
+    println!("Status:      {}", data.current_status());
+    println!("Entered:     {}", data.entry);
+
+    {
+        let priority = data.priority.map(|p| format!("{}", p));
+        let optionals = [
+            ("Modified:    ", &data.modified),
+            ("Ended:       ", &data.end),
+            ("Project:     ", &data.project),
+            ("Priority:    ", &priority),
+            ("Due:         ", &data.due),
+            ("Scheduled:   ", &data.scheduled),
+            ("Wait:        ", &data.wait),
+            ("Recurrence:  ", &data.recur),
+        ];
+
+        for &(name, value) in optionals.iter() {
+            if let Some(ref v) = *value {
+                println!("{}{}", name, v);
+            }
+        }
+    }
+
+    if !data.tags.is_empty() {
+        println!("Tags:        {}", data.tags.join(", "));
+    }
+
+    for dep in data.depends.iter() {
+        match Task::find(rt.store(), dep).and_then(|o| match o {
+            Some(t) => t.data().map(Some),
+            None    => Ok(None),
+        }) {
+            Ok(Some(d)) => println!("Depends on:  {} [{}] {}", d.uuid, d.current_status(), d.description),
+            Ok(None)    => println!("Depends on:  {} (not in the store)", dep),
+            Err(e)      => trace_error(&e),
+        }
+    }
+
+    for ann in data.annotations.iter() {
+        println!("Annotation:  {} {}", ann.entry, ann.description);
+    }
+}
+
+fn modify(rt: &Runtime) {
+    let subcmd   = rt.cli().subcommand_matches("modify").unwrap();
+    let id       = subcmd.value_of("id").unwrap(); // enforced by clap
+    let mut task = find_or_exit(rt, id);
+    let mut data = task.data().map_err_trace_exit(1).unwrap(); // safe by map_err_trace_exit()
+
+    if let Some(d) = subcmd.value_of("description") {
+        data.description = String::from(d);
+    }
+
+    if let Some(s) = subcmd.value_of("status") {
+        data.status = Status::from_str(s).unwrap(); // safe by clap
+        data.end = if data.status.is_open() {
+            None
+        } else {
+            Some(data::now())
+        };
+    }
+
+    if let Some(tags) = subcmd.values_of("add-tag") {
+        for tag in tags {
+            if !data.tags.iter().any(|t| t == tag) {
+                data.tags.push(String::from(tag));
+            }
+        }
+    }
+
+    if let Some(tags) = subcmd.values_of("remove-tag") {
+        let tags = tags.collect::<Vec<_>>();
+        data.tags.retain(|t| !tags.contains(&&t[..]));
+    }
+
+    if let Some(text) = subcmd.value_of("annotate") {
+        data.annotations.push(data::Annotation {
+            entry: data::now(),
+            description: String::from(text),
         });
+    }
 
-    res.map_err_trace().ok();
+    set_attributes(subcmd, &mut data);
+
+    let _ = task.modify(|d| *d = data).map_err_trace_exit(1);
+
+    if let Some(ids) = subcmd.values_of("depends") {
+        for id in ids {
+            let mut other = find_or_exit(rt, id);
+            let _ = task.add_dependency(&mut other).map_err_trace_exit(1);
+        }
+    }
+
+    if let Some(ids) = subcmd.values_of("no-depends") {
+        for id in ids {
+            let mut other = find_or_exit(rt, id);
+            let _ = task.remove_dependency(&mut other).map_err_trace_exit(1);
+        }
+    }
 }
 
+/// Apply the attribute arguments shared by `add` and `modify`.
+///
+/// Empty values remove the attribute. Exits on invalid values.
+fn set_attributes(matches: &ArgMatches, data: &mut TaskData) {
+    fn date(matches: &ArgMatches, name: &str, old: Option<String>) -> Option<String> {
+        match matches.value_of(name) {
+            None     => old,
+            Some("") => None,
+            Some(s)  => match data::parse_date(s) {
+                Some(d) => Some(d),
+                None    => {
+                    error!("Cannot parse date: {}", s);
+                    exit(1)
+                },
+            },
+        }
+    }
+
+    data.due       = date(matches, "due", data.due.take());
+    data.scheduled = date(matches, "scheduled", data.scheduled.take());
+
+    let old_wait = data.wait.take();
+    data.wait    = date(matches, "wait", old_wait.clone());
+    if data.wait != old_wait {
+        match (data.status, data.wait.is_some()) {
+            (Status::Pending, true)  => data.status = Status::Waiting,
+            (Status::Waiting, false) => data.status = Status::Pending,
+            _                        => { },
+        }
+    }
+
+    match matches.value_of("project") {
+        None     => { },
+        Some("") => data.project = None,
+        Some(p)  => data.project = Some(String::from(p)),
+    }
+
+    match matches.value_of("priority") {
+        None     => { },
+        Some("") => data.priority = None,
+        Some(p)  => data.priority = Some(Priority::from_str(p).map_err_trace_exit(1).unwrap()), // safe by map_err_trace_exit()
+    }
+
+    match matches.value_of("recur") {
+        None     => { },
+        Some("") => data.recur = None,
+        Some(r)  => {
+            let r = Recurrence::from_str(r).map_err_trace_exit(1).unwrap(); // safe by map_err_trace_exit()
+            data.recur = Some(format!("{}", r));
+        },
+    }
+
+    if data.recur.is_some() && data.due.is_none() {
+        error!("A recurring task needs a due date");
+        exit(1);
+    }
+}
+
+fn find_or_exit<'a>(rt: &'a Runtime, id: &str) -> Task<'a> {
+    match Task::find(rt.store(), id) {
+        Ok(Some(task)) => task,
+        Ok(None)       => {
+            error!("No task found for '{}'", id);
+            exit(1)
+        },
+        Err(e)         => trace_error_exit(&e, 1),
+    }
+}
+
+fn sync(rt: &Runtime) {
+    let subcmd     = rt.cli().subcommand_matches("sync").unwrap();
+    let binary     = subcmd.value_of("task-binary").unwrap_or("task");
+    let dry_run    = subcmd.is_present("dry-run");
+    let resolution = match subcmd.value_of("prefer") {
+        Some("imag")        => Resolution::PreferImag,
+        Some("taskwarrior") => Resolution::PreferTaskwarrior,
+        _                   => Resolution::Report,
+    };
+
+    let report = match sync::sync(rt.store(), binary, resolution, dry_run) {
+        Ok(report) => report,
+        Err(e)     => trace_error_exit(&e, 1),
+    };
+
+    for uuid in report.pulled.iter() {
+        println!("Pulled {}", uuid);
+    }
+    for uuid in report.pushed.iter() {
+        println!("Pushed {}", uuid);
+    }
+    for uuid in report.removed.iter() {
+        println!("Removed {}", uuid);
+    }
+    for conflict in report.conflicts.iter() {
+        let reason = match conflict.kind {
+            ConflictKind::BothModified         => "modified in imag and taskwarrior",
+            ConflictKind::MissingInTaskwarrior => "missing in taskwarrior",
+        };
+        println!("Conflict {} ({}): {}", conflict.uuid, conflict.description, reason);
+    }
+
+    info!("{} pulled, {} pushed, {} removed, {} conflicts",
+          report.pulled.len(),
+          report.pushed.len(),
+          report.removed.len(),
+          report.conflicts.len());
+
+    if resolution == Resolution::Report && !report.conflicts.is_empty() {
+        exit(1);
+    }
+}
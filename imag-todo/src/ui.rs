@@ -39,15 +39,23 @@ pub fn build_ui<'a>(app: App<'a, 'a>) -> App<'a, 'a> {
                          .required(false)
                          .help("For use in an on-delete hook"))
 
+                    .arg(Arg::with_name("modify")
+                         .long("modify")
+                         .short("m")
+                         .takes_value(false)
+                         .required(false)
+                         .help("For use in an on-modify hook, mirrors all modifications"))
+
                     .group(ArgGroup::with_name("taskwarrior hooks")
                            .args(&[ "add",
                                  "delete",
+                                 "modify",
                            ])
                            .required(true))
                     )
 
         .subcommand(SubCommand::with_name("list")
-                    .about("List all tasks")
+                    .about("List tasks")
                     .version("0.1")
 
                     .arg(Arg::with_name("verbose")
@@ -55,7 +63,253 @@ pub fn build_ui<'a>(app: App<'a, 'a>) -> App<'a, 'a> {
                          .short("v")
                          .takes_value(false)
                          .required(false)
-                         .help("Asks taskwarrior for all the details")
+                         .help("Show the details of the tasks")
                         )
+
+                    .arg(Arg::with_name("all")
+                         .long("all")
+                         .short("a")
+                         .takes_value(false)
+                         .required(false)
+                         .help("Also list completed and deleted tasks"))
+
+                    .arg(Arg::with_name("status")
+                         .long("status")
+                         .short("s")
+                         .takes_value(true)
+                         .required(false)
+                         .possible_values(&STATUS_VALUES)
+                         .help("Only list tasks with this status")
+                         .value_name("STATUS"))
+
+                    .arg(Arg::with_name("project")
+                         .long("project")
+                         .short("p")
+                         .takes_value(true)
+                         .required(false)
+                         .help("Only list tasks of this project")
+                         .value_name("PROJECT"))
+
+                    .arg(Arg::with_name("tag")
+                         .long("tag")
+                         .short("t")
+                         .takes_value(true)
+                         .required(false)
+                         .help("Only list tasks with this tag")
+                         .value_name("TAG"))
                    )
+
+        .subcommand(attribute_args(SubCommand::with_name("add")
+                    .about("Add a task")
+                    .version("0.1")
+
+                    .arg(Arg::with_name("description")
+                         .index(1)
+                         .multiple(true)
+                         .takes_value(true)
+                         .required(true)
+                         .help("The description of the task")
+                         .value_name("DESCRIPTION"))
+
+                    .arg(Arg::with_name("tag")
+                         .long("tag")
+                         .short("t")
+                         .takes_value(true)
+                         .multiple(true)
+                         .required(false)
+                         .help("Tag the task")
+                         .value_name("TAG"))
+
+                    .arg(Arg::with_name("depends")
+                         .long("depends")
+                         .takes_value(true)
+                         .multiple(true)
+                         .required(false)
+                         .help("The task depends on the task with this ID")
+                         .value_name("ID"))
+
+                    .arg(Arg::with_name("push")
+                         .long("push")
+                         .takes_value(false)
+                         .required(false)
+                         .help("Link the task to taskwarrior and pass it to taskwarrior now"))
+
+                    .arg(task_binary_arg())
+                   ))
+
+        .subcommand(SubCommand::with_name("done")
+                    .about("Mark tasks as completed")
+                    .version("0.1")
+
+                    .arg(Arg::with_name("id")
+                         .index(1)
+                         .multiple(true)
+                         .takes_value(true)
+                         .required(true)
+                         .help("The UUIDs of the tasks, or unique prefixes of them")
+                         .value_name("ID"))
+                   )
+
+        .subcommand(SubCommand::with_name("show")
+                    .about("Show a task")
+                    .version("0.1")
+
+                    .arg(Arg::with_name("id")
+                         .index(1)
+                         .takes_value(true)
+                         .required(true)
+                         .help("The UUID of the task, or a unique prefix of it")
+                         .value_name("ID"))
+                   )
+
+        .subcommand(attribute_args(SubCommand::with_name("modify")
+                    .about("Modify a task. Pass an empty value to remove an attribute")
+                    .version("0.1")
+
+                    .arg(Arg::with_name("id")
+                         .index(1)
+                         .takes_value(true)
+                         .required(true)
+                         .help("The UUID of the task, or a unique prefix of it")
+                         .value_name("ID"))
+
+                    .arg(Arg::with_name("description")
+                         .long("description")
+                         .takes_value(true)
+                         .required(false)
+                         .help("The new description")
+                         .value_name("DESCRIPTION"))
+
+                    .arg(Arg::with_name("status")
+                         .long("status")
+                         .short("s")
+                         .takes_value(true)
+                         .required(false)
+                         .possible_values(&STATUS_VALUES)
+                         .help("The new status")
+                         .value_name("STATUS"))
+
+                    .arg(Arg::with_name("add-tag")
+                         .long("add-tag")
+                         .takes_value(true)
+                         .multiple(true)
+                         .required(false)
+                         .help("Add a tag")
+                         .value_name("TAG"))
+
+                    .arg(Arg::with_name("remove-tag")
+                         .long("remove-tag")
+                         .takes_value(true)
+                         .multiple(true)
+                         .required(false)
+                         .help("Remove a tag")
+                         .value_name("TAG"))
+
+                    .arg(Arg::with_name("depends")
+                         .long("depends")
+                         .takes_value(true)
+                         .multiple(true)
+                         .required(false)
+                         .help("Add a dependency on the task with this ID")
+                         .value_name("ID"))
+
+                    .arg(Arg::with_name("no-depends")
+                         .long("no-depends")
+                         .takes_value(true)
+                         .multiple(true)
+                         .required(false)
+                         .help("Remove the dependency on the task with this ID")
+                         .value_name("ID"))
+
+                    .arg(Arg::with_name("annotate")
+                         .long("annotate")
+                         .takes_value(true)
+                         .required(false)
+                         .help("Add an annotation")
+                         .value_name("TEXT"))
+                   ))
+
+        .subcommand(SubCommand::with_name("sync")
+                    .about("Synchronize the tasks in the store with taskwarrior")
+                    .version("0.1")
+
+                    .arg(Arg::with_name("dry-run")
+                         .long("dry-run")
+                         .short("n")
+                         .takes_value(false)
+                         .required(false)
+                         .help("Only report what would be done"))
+
+                    .arg(Arg::with_name("prefer")
+                         .long("prefer")
+                         .takes_value(true)
+                         .required(false)
+                         .possible_values(&["imag", "taskwarrior"])
+                         .help("Resolve conflicts by preferring one side. Conflicts are only reported otherwise")
+                         .value_name("SIDE"))
+
+                    .arg(task_binary_arg())
+                   )
+}
+
+const STATUS_VALUES : [&'static str; 5] = ["pending", "waiting", "recurring", "completed", "deleted"];
+
+/// The arguments to set the attributes of a task, shared by `add` and `modify`
+fn attribute_args<'a, 'b>(app: App<'a, 'b>) -> App<'a, 'b> {
+    app
+        .arg(Arg::with_name("project")
+             .long("project")
+             .short("p")
+             .takes_value(true)
+             .required(false)
+             .help("The project of the task")
+             .value_name("PROJECT"))
+
+        .arg(Arg::with_name("priority")
+             .long("priority")
+             .short("P")
+             .takes_value(true)
+             .required(false)
+             .help("The priority of the task (H, M or L)")
+             .value_name("PRIORITY"))
+
+        .arg(Arg::with_name("due")
+             .long("due")
+             .short("d")
+             .takes_value(true)
+             .required(false)
+             .help("The due date of the task (YYYY-MM-DD)")
+             .value_name("DATE"))
+
+        .arg(Arg::with_name("scheduled")
+             .long("scheduled")
+             .takes_value(true)
+             .required(false)
+             .help("The date the work on the task should start (YYYY-MM-DD)")
+             .value_name("DATE"))
+
+        .arg(Arg::with_name("wait")
+             .long("wait")
+             .short("w")
+             .takes_value(true)
+             .required(false)
+             .help("Hide the task until this date (YYYY-MM-DD)")
+             .value_name("DATE"))
+
+        .arg(Arg::with_name("recur")
+             .long("recur")
+             .short("r")
+             .takes_value(true)
+             .required(false)
+             .help("Let the task recur (daily, weekly, monthly, yearly, or for example 2w). Needs a due date")
+             .value_name("RECURRENCE"))
+}
+
+fn task_binary_arg<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("task-binary")
+        .long("task-binary")
+        .takes_value(true)
+        .required(false)
+        .help("The taskwarrior binary to call, defaults to 'task'")
+        .value_name("BINARY")
 }
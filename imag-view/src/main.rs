@@ -42,13 +42,23 @@ extern crate libimagrt;
 extern crate libimagstore;
 extern crate libimagentryview;
 #[macro_use] extern crate libimagerror;
+extern crate libimagutil;
 
 use std::process::exit;
 use std::path::PathBuf;
 
+use toml::Value;
+
+use libimagrt::runtime::Runtime;
 use libimagrt::setup::generate_runtime_setup;
 use libimagerror::trace::trace_error_exit;
+use libimagentryview::builtin::custom::CustomViewer;
+use libimagentryview::builtin::html::HtmlViewer;
 use libimagentryview::builtin::stdout::StdoutViewer;
+use libimagentryview::site::Site;
+use libimagerror::trace::MapErrTrace;
+use libimagstore::storeid::StoreId;
+use libimagutil::warn_exit::warn_exit;
 use libimagentryview::viewer::Viewer;
 
 mod ui;
@@ -63,7 +73,13 @@ fn main() {
                                      "View entries (readonly)",
                                      build_ui);
 
-    let entry_id     = rt.cli().value_of("id").unwrap(); // enforced by clap
+    if rt.cli().subcommand_matches("compile").is_some() {
+        return compile(&rt);
+    }
+
+    let entry_id     = rt.cli()
+        .value_of("id")
+        .unwrap_or_else(|| warn_exit("No entry specified, use --id", 1));
     let view_header  = rt.cli().is_present("view-header");
     let view_content = rt.cli().is_present("view-content");
 
@@ -83,25 +99,36 @@ fn main() {
             None => {
                 debug!("No commandline call");
                 debug!("Assuming to view in cli (stdout)");
+                StdoutViewer::new(view_header, view_content).view_entry(&entry)
             },
             Some(s) => {
                 if s.is_present("view-in-stdout") {
+                    StdoutViewer::new(view_header, view_content).view_entry(&entry)
                 } else if s.is_present("view-in-ui") {
                     warn!("Viewing in UI is currently not supported, switch to stdout");
+                    StdoutViewer::new(view_header, view_content).view_entry(&entry)
                 } else if s.is_present("view-in-browser") {
-                    warn!("Viewing in browser is currently not supported, switch to stdout");
+                    let browser = s.value_of("view-in-browser").map(String::from);
+                    HtmlViewer::new(rt.store(), browser).view_entry(&entry)
                 } else if s.is_present("view-in-texteditor") {
                     if let Err(e) = Editor::new(&rt, &entry).show() {
                         error!("Cannot view in editor: {}", e);
                         trace_error_exit(&e, 1);
                     }
+                    Ok(())
                 } else if s.is_present("view-in-custom") {
-                    warn!("Viewing in custom is currently not supported, switch to stdout");
+                    let template = s.value_of("view-in-custom")
+                        .map(String::from)
+                        .or_else(|| get_custom_template(&rt))
+                        .unwrap_or_else(|| {
+                            warn_exit("No command passed and no 'view.custom' configured", 1)
+                        });
+                    CustomViewer::new(rt.store(), template).view_entry(&entry)
+                } else {
+                    StdoutViewer::new(view_header, view_content).view_entry(&entry)
                 }
             },
-        };
-
-        StdoutViewer::new(view_header, view_content).view_entry(&entry)
+        }
     };
 
     if let Err(e) = res {
@@ -109,3 +136,52 @@ fn main() {
     }
 }
 
+/// Get the command template for custom viewing from the "view.custom" configuration setting
+fn get_custom_template(rt: &Runtime) -> Option<String> {
+    rt.config()
+        .map(|config| config.config())
+        .and_then(|config| config.lookup("view.custom"))
+        .and_then(|template| match *template {
+            Value::String(ref s) => Some(s.clone()),
+            _ => {
+                warn!("Setting 'view.custom' is not a string, ignoring it");
+                None
+            },
+        })
+}
+
+fn compile(rt: &Runtime) {
+    let scmd   = rt.cli().subcommand_matches("compile").unwrap(); // safe, we checked in main()
+    let outdir = PathBuf::from(scmd.value_of("out").unwrap()); // enforced by clap
+
+    // Only markdown to HTML is supported, which is enforced by clap
+    debug!("Compiling from {:?} to {:?}", scmd.value_of("from"), scmd.value_of("to"));
+
+    let mut site = Site::new(rt.store());
+    if let Some(modules) = scmd.values_of("module") {
+        for module in modules {
+            site.add_module(module);
+        }
+    }
+
+    let ids = scmd
+        .values_of("entry")
+        .map(|ids| ids.collect::<Vec<_>>())
+        .unwrap_or_else(Vec::new)
+        .into_iter()
+        .chain(rt.cli().value_of("id").into_iter());
+
+    for id in ids {
+        let id = StoreId::new_baseless(PathBuf::from(id))
+            .map_err_trace_exit(1)
+            .unwrap(); // safe by map_err_trace_exit()
+        site.add_entry(id);
+    }
+
+    if site.is_empty() {
+        warn_exit("No entries selected, pass --module or --entry", 1);
+    }
+
+    let n = site.write(&outdir).map_err_trace_exit(1).unwrap(); // safe by map_err_trace_exit()
+    info!("Rendered {} entries to {}", n, outdir.display());
+}
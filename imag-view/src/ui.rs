@@ -25,8 +25,8 @@ pub fn build_ui<'a>(app: App<'a, 'a>) -> App<'a, 'a> {
             .long("id")
             .short("i")
             .takes_value(true)
-            .required(true)
-            .help("View this entry at this store path")
+            .required(false)
+            .help("View this entry at this store path (required unless compiling)")
             .value_name("ID"))
 
         .arg(Arg::with_name("view-header")
@@ -64,8 +64,9 @@ pub fn build_ui<'a>(app: App<'a, 'a>) -> App<'a, 'a> {
                         .long("browser")
                         .short("b")
                         .takes_value(true) // optional, which browser
+                        .min_values(0)
                         .required(false)
-                        .help("View content in $BROWSER (fails if no env variable $BROWSER)")
+                        .help("View rendered entry in BROWSER or $BROWSER (fails if neither is set)")
                         .value_name("BROWSER"))
 
                    .arg(Arg::with_name("view-in-texteditor")
@@ -78,9 +79,10 @@ pub fn build_ui<'a>(app: App<'a, 'a>) -> App<'a, 'a> {
                    .arg(Arg::with_name("view-in-custom")
                         .long("custom")
                         .short("c")
-                        .takes_value(true) // non-optional, call-string
+                        .takes_value(true) // optional, call-string, defaults to "view.custom" from the config
+                        .min_values(0)
                         .required(false)
-                        .help("View content in custom program, for example 'libreoffice %e', replace '%e' with entry path")
+                        .help("View content in custom program, for example 'libreoffice %e'. '%e' is replaced with the entry path, '%i' with the id, '%h' with the path of the rendered HTML. Defaults to 'view.custom' from the configuration")
                         .value_name("PROGRAM"))
 
                    .group(ArgGroup::with_name("viewer")
@@ -94,22 +96,52 @@ pub fn build_ui<'a>(app: App<'a, 'a>) -> App<'a, 'a> {
                    )
 
         .subcommand(SubCommand::with_name("compile")
-                   .about("Compile content to other format for viewing, implies that the entry gets copied to /tmp")
+                   .about("Compile entries to a static HTML site")
                    .version("0.1")
                    .arg(Arg::with_name("from")
                         .long("from")
                         .short("f")
-                        .takes_value(true) // "markdown" or "textile" or "restructuredtex"
-                        .required(true)
+                        .takes_value(true)
+                        .required(false)
+                        .possible_values(&["markdown"])
+                        .default_value("markdown")
                         .help("Compile from")
                         .value_name("FORMAT"))
 
                    .arg(Arg::with_name("to")
                         .long("to")
                         .short("t")
-                        .takes_value(true) // "html" or "HTML" or ... json maybe?
-                        .required(true)
+                        .takes_value(true)
+                        .required(false)
+                        .possible_values(&["html"])
+                        .default_value("html")
                         .help("Compile to")
                         .value_name("FORMAT"))
+
+                   .arg(Arg::with_name("out")
+                        .long("out")
+                        .short("o")
+                        .takes_value(true)
+                        .required(true)
+                        .help("Write the site to this directory")
+                        .value_name("DIR"))
+
+                   .arg(Arg::with_name("module")
+                        .long("module")
+                        .short("m")
+                        .takes_value(true)
+                        .multiple(true)
+                        .required(false)
+                        .help("Compile all entries of this module, for example 'notes' or 'diary'")
+                        .value_name("MODULE"))
+
+                   .arg(Arg::with_name("entry")
+                        .long("entry")
+                        .short("e")
+                        .takes_value(true)
+                        .multiple(true)
+                        .required(false)
+                        .help("Compile this entry")
+                        .value_name("ID"))
                    )
 }
@@ -0,0 +1,175 @@
+//
+// imag - the personal information management suite for the commandline
+// Copyright (C) 2015, 2016 Matthias Beyer <mail@beyermatthias.de> and contributors
+//
+// This library is free software; you can redistribute it and/or
+// modify it under the terms of the GNU Lesser General Public
+// License as published by the Free Software Foundation; version
+// 2.1 of the License.
+//
+// This library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+// Lesser General Public License for more details.
+//
+// You should have received a copy of the GNU Lesser General Public
+// License along with this library; if not, write to the Free Software
+// Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA  02110-1301  USA
+//
+
+#![deny(
+    non_camel_case_types,
+    non_snake_case,
+    path_statements,
+    trivial_numeric_casts,
+    unstable_features,
+    unused_allocation,
+    unused_import_braces,
+    unused_imports,
+    unused_must_use,
+    unused_mut,
+    unused_qualifications,
+    while_true,
+)]
+
+extern crate clap;
+#[macro_use] extern crate log;
+extern crate semver;
+#[macro_use] extern crate version;
+
+extern crate libimagwiki;
+extern crate libimagrt;
+extern crate libimagentryedit;
+extern crate libimagerror;
+extern crate libimagutil;
+
+use std::process::exit;
+
+use libimagentryedit::edit::Edit;
+use libimagrt::runtime::Runtime;
+use libimagrt::setup::generate_runtime_setup;
+use libimagwiki::hook::register_wiki_hook;
+use libimagwiki::page::WikiPage;
+use libimagerror::trace::{MapErrTrace, trace_error};
+use libimagutil::info_result::*;
+use libimagutil::warn_exit::warn_exit;
+
+mod ui;
+use ui::build_ui;
+
+fn main() {
+    let mut rt = generate_runtime_setup("imag-wiki",
+                                    &version!()[..],
+                                    "Personal wiki",
+                                    build_ui);
+
+    register_wiki_hook(rt.store_mut());
+
+    rt.cli()
+        .subcommand_name()
+        .map(|name| {
+            debug!("Call: {}", name);
+            match name {
+                "create"       => create(&rt),
+                "delete"       => delete(&rt),
+                "edit"         => edit(&rt),
+                "show"         => show(&rt),
+                "backlinks"    => backlinks(&rt),
+                "list"         => list(&rt),
+                "update-links" => update_links(&rt),
+                _              => {
+                    debug!("Unknown command"); // More error handling
+                },
+            };
+        });
+}
+
+fn name_from_cli(rt: &Runtime, subcmd: &str) -> String {
+    rt.cli().subcommand_matches(subcmd).unwrap().value_of("name").map(String::from).unwrap()
+}
+
+fn get_page<'a>(rt: &'a Runtime, name: String) -> WikiPage<'a> {
+    match WikiPage::get(rt.store(), name) {
+        Ok(Some(page)) => page,
+        Ok(None)       => warn_exit("No such wiki page", 1),
+        Err(e)         => {
+            trace_error(&e);
+            exit(1)
+        },
+    }
+}
+
+fn create(rt: &Runtime) {
+    let name = name_from_cli(rt, "create");
+    WikiPage::new(rt.store(), name.clone(), String::new())
+        .map_err_trace_exit(1)
+        .map_info_str("Ok")
+        .ok();
+
+    if rt.cli().subcommand_matches("create").unwrap().is_present("edit") {
+        edit_page(rt, name);
+    }
+}
+
+fn delete(rt: &Runtime) {
+    WikiPage::delete(rt.store(), name_from_cli(rt, "delete"))
+        .map_err_trace_exit(1)
+        .map_info_str("Ok")
+        .ok();
+}
+
+fn edit(rt: &Runtime) {
+    edit_page(rt, name_from_cli(rt, "edit"));
+}
+
+fn edit_page(rt: &Runtime, name: String) {
+    let mut page = get_page(rt, name);
+    page.edit_content(rt).map_err_trace_exit(1).ok();
+    page.update_links(rt.store()).map_err_trace_exit(1).ok();
+}
+
+fn show(rt: &Runtime) {
+    let page = get_page(rt, name_from_cli(rt, "show"));
+    println!("{}", page.get_text());
+
+    let backlinks = page.backlinks(rt.store()).map_err_trace_exit(1).unwrap(); // safe by map_err_trace_exit()
+    if !backlinks.is_empty() {
+        println!("Backlinks:");
+        for link in backlinks {
+            println!("  {}", link);
+        }
+    }
+}
+
+fn backlinks(rt: &Runtime) {
+    let page = get_page(rt, name_from_cli(rt, "backlinks"));
+    for link in page.backlinks(rt.store()).map_err_trace_exit(1).unwrap() { // safe by map_err_trace_exit()
+        println!("{}", link);
+    }
+}
+
+fn list(rt: &Runtime) {
+    let mut names = WikiPage::all_pages(rt.store())
+        .map_err_trace_exit(1)
+        .unwrap() // safe by map_err_trace_exit()
+        .filter_map(|page| page.map_err_trace().ok())
+        .filter_map(|page| page.get_name().map_err_trace().ok())
+        .collect::<Vec<_>>();
+
+    names.sort();
+    for name in names {
+        println!("{}", name);
+    }
+}
+
+fn update_links(rt: &Runtime) {
+    let pages = WikiPage::all_pages(rt.store())
+        .map_err_trace_exit(1)
+        .unwrap(); // safe by map_err_trace_exit()
+
+    for page in pages {
+        page.and_then(|mut page| page.update_links(rt.store()))
+            .map_err_trace()
+            .ok();
+    }
+}
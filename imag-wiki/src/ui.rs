@@ -0,0 +1,73 @@
+//
+// imag - the personal information management suite for the commandline
+// Copyright (C) 2015, 2016 Matthias Beyer <mail@beyermatthias.de> and contributors
+//
+// This library is free software; you can redistribute it and/or
+// modify it under the terms of the GNU Lesser General Public
+// License as published by the Free Software Foundation; version
+// 2.1 of the License.
+//
+// This library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+// Lesser General Public License for more details.
+//
+// You should have received a copy of the GNU Lesser General Public
+// License along with this library; if not, write to the Free Software
+// Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA  02110-1301  USA
+//
+
+use clap::{Arg, App, SubCommand};
+
+fn name_arg<'a, 'b>(help: &'a str) -> Arg<'a, 'b> {
+    Arg::with_name("name")
+        .long("name")
+        .short("n")
+        .takes_value(true)
+        .required(true)
+        .help(help)
+        .value_name("NAME")
+}
+
+pub fn build_ui<'a>(app: App<'a, 'a>) -> App<'a, 'a> {
+    app
+        .subcommand(SubCommand::with_name("create")
+                   .about("Create a wiki page")
+                   .version("0.1")
+                   .arg(name_arg("Create the page with this name"))
+                   .arg(Arg::with_name("edit")
+                        .long("edit")
+                        .short("e")
+                        .takes_value(false)
+                        .required(false)
+                        .help("Edit after creating"))
+                   )
+
+        .subcommand(SubCommand::with_name("delete")
+                   .about("Delete a wiki page")
+                   .version("0.1")
+                   .arg(name_arg("Delete the page with this name")))
+
+        .subcommand(SubCommand::with_name("edit")
+                   .about("Edit a wiki page")
+                   .version("0.1")
+                   .arg(name_arg("Edit the page with this name")))
+
+        .subcommand(SubCommand::with_name("show")
+                   .about("Print a wiki page and the pages linking to it")
+                   .version("0.1")
+                   .arg(name_arg("Show the page with this name")))
+
+        .subcommand(SubCommand::with_name("backlinks")
+                   .about("List the pages linking to a wiki page")
+                   .version("0.1")
+                   .arg(name_arg("List the backlinks of the page with this name")))
+
+        .subcommand(SubCommand::with_name("list")
+                   .about("List all wiki pages")
+                   .version("0.1"))
+
+        .subcommand(SubCommand::with_name("update-links")
+                   .about("Update the links of all wiki pages from their text, for example after editing pages with other tools")
+                   .version("0.1"))
+}
@@ -0,0 +1,433 @@
+//
+// imag - the personal information management suite for the commandline
+// Copyright (C) 2015, 2016 Matthias Beyer <mail@beyermatthias.de> and contributors
+//
+// This library is free software; you can redistribute it and/or
+// modify it under the terms of the GNU Lesser General Public
+// License as published by the Free Software Foundation; version
+// 2.1 of the License.
+//
+// This library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+// Lesser General Public License for more details.
+//
+// You should have received a copy of the GNU Lesser General Public
+// License along with this library; if not, write to the Free Software
+// Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA  02110-1301  USA
+//
+
+//! A parser and writer for BibTeX files
+//!
+//! `@string` definitions are expanded and values concatenated with `#` are joined, so each
+//! parsed entry is self-contained. Only values which are one of the predefined month strings,
+//! like `month = may`, are remembered and written as such again. The order of the fields is kept.
+//!
+//! `@comment`, `@preamble` and all text outside of entries are skipped, as they do not belong to
+//! any entry. They are therefore not written again either.
+
+use std::collections::BTreeMap;
+
+use error::BibliographyErrorKind as BEK;
+use result::Result;
+
+use libimagerror::into::IntoError;
+
+/// A BibTeX entry, like `@article{knuth1984, author = {Donald E. Knuth}, ...}`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BibEntry {
+    /// The type of the entry, lowercased, like "article"
+    pub kind: String,
+
+    /// The citation key
+    pub key: String,
+
+    /// The fields, with lowercased names
+    ///
+    /// The values are stored without their outer braces or quotes, inner braces are kept.
+    pub fields: BTreeMap<String, String>,
+
+    /// The names of the fields, in the order they were written
+    ///
+    /// Fields which are not listed here are written after these, sorted by name.
+    pub order: Vec<String>,
+
+    /// The fields whose value is a predefined string, like "may" for `month = may`
+    pub macros: BTreeMap<String, String>,
+}
+
+impl BibEntry {
+
+    pub fn new(kind: String, key: String) -> BibEntry {
+        BibEntry {
+            kind: kind,
+            key: key,
+            fields: BTreeMap::new(),
+            order: vec![],
+            macros: BTreeMap::new(),
+        }
+    }
+
+    /// Parse all entries in `text`
+    pub fn parse_all(text: &str) -> Result<Vec<BibEntry>> {
+        Parser::new(text).parse()
+    }
+
+    /// Write the entry as BibTeX
+    pub fn to_bibtex(&self) -> String {
+        let predefined = predefined_strings();
+        let names      = self.order
+            .iter()
+            .filter(|name| self.fields.contains_key(*name))
+            .chain(self.fields.keys().filter(|name| !self.order.contains(*name)));
+
+        let mut s = format!("@{}{{{},\n", self.kind, self.key);
+        for name in names {
+            let value = &self.fields[name];
+
+            // A predefined string is only written if it still has the value of the field
+            match self.macros.get(name) {
+                Some(m) if predefined.get(m) == Some(value) => {
+                    s.push_str(&format!("  {} = {},\n", name, m))
+                },
+                _ => s.push_str(&format!("  {} = {{{}}},\n", name, value)),
+            }
+        }
+        s.push_str("}\n");
+        s
+    }
+
+}
+
+/// Write all entries as BibTeX, separated by empty lines
+pub fn to_bibtex<'a, I: Iterator<Item = &'a BibEntry>>(entries: I) -> String {
+    entries.map(BibEntry::to_bibtex).collect::<Vec<_>>().join("\n")
+}
+
+/// The strings which are predefined by BibTeX
+fn predefined_strings() -> BTreeMap<String, String> {
+    let months = [
+        ("jan", "January"), ("feb", "February"), ("mar", "March"), ("apr", "April"),
+        ("may", "May"), ("jun", "June"), ("jul", "July"), ("aug", "August"),
+        ("sep", "September"), ("oct", "October"), ("nov", "November"), ("dec", "December"),
+    ];
+
+    months.iter().map(|&(k, v)| (String::from(k), String::from(v))).collect()
+}
+
+struct Parser {
+    chars: Vec<char>,
+    pos: usize,
+    strings: BTreeMap<String, String>,
+}
+
+impl Parser {
+
+    fn new(text: &str) -> Parser {
+        Parser {
+            chars: text.chars().collect(),
+            pos: 0,
+            strings: predefined_strings(),
+        }
+    }
+
+    fn parse(mut self) -> Result<Vec<BibEntry>> {
+        let mut entries = vec![];
+
+        // Everything outside of entries is a comment
+        while self.skip_to('@') {
+            self.pos += 1;
+            let kind = self.identifier().to_lowercase();
+            self.skip_whitespace();
+
+            // An "@" which does not start an entry, like in a mail address, is part of a comment
+            let close = match self.peek() {
+                Some('{') if !kind.is_empty() => '}',
+                Some('(') if !kind.is_empty() => ')',
+                _                             => continue,
+            };
+            self.pos += 1;
+
+            match &kind[..] {
+                "comment" | "preamble" => if close == '}' {
+                    self.pos -= 1;
+                    try!(self.delimited());
+                } else if self.skip_to(close) {
+                    self.pos += 1;
+                },
+                "string" => {
+                    let (name, value, _) = try!(self.field());
+                    self.strings.insert(name, value);
+                    try!(self.expect(close));
+                },
+                _ => entries.push(try!(self.entry(kind, close))),
+            }
+        }
+
+        Ok(entries)
+    }
+
+    fn entry(&mut self, kind: String, close: char) -> Result<BibEntry> {
+        self.skip_whitespace();
+        let start = self.pos;
+        while self.peek().map(|c| c != ',' && c != close && !c.is_whitespace()).unwrap_or(false) {
+            self.pos += 1;
+        }
+        let key = self.chars[start..self.pos].iter().cloned().collect::<String>();
+        if key.is_empty() {
+            return Err(BEK::BibTeXParseError.into_error());
+        }
+
+        let mut entry = BibEntry::new(kind, key);
+        loop {
+            self.skip_whitespace();
+            match self.peek() {
+                Some(',') => self.pos += 1,
+                Some(c) if c == close => {
+                    self.pos += 1;
+                    return Ok(entry);
+                },
+                _ => return Err(BEK::BibTeXParseError.into_error()),
+            }
+
+            self.skip_whitespace();
+            if self.peek() == Some(close) {
+                continue;
+            }
+
+            let (name, value, m) = try!(self.field());
+            if !entry.order.contains(&name) {
+                entry.order.push(name.clone());
+            }
+            match m {
+                Some(m) => entry.macros.insert(name.clone(), m),
+                None    => entry.macros.remove(&name),
+            };
+            entry.fields.insert(name, value);
+        }
+    }
+
+    /// Parse `name = value # value ...`
+    ///
+    /// Returns the name of the predefined string as well, if the value is only this string.
+    fn field(&mut self) -> Result<(String, String, Option<String>)> {
+        self.skip_whitespace();
+        let name = self.identifier().to_lowercase();
+        if name.is_empty() {
+            return Err(BEK::BibTeXParseError.into_error());
+        }
+
+        try!(self.expect('='));
+
+        // The names of the strings of the parts of the value, None for literal parts
+        let mut value = String::new();
+        let mut parts = vec![];
+        loop {
+            self.skip_whitespace();
+            match self.peek() {
+                Some('{') => {
+                    value.push_str(&try!(self.delimited()));
+                    parts.push(None);
+                },
+                Some('"') => {
+                    value.push_str(&try!(self.quoted()));
+                    parts.push(None);
+                },
+                Some(c) if c.is_digit(10) => {
+                    while self.peek().map(|c| c.is_digit(10)).unwrap_or(false) {
+                        value.push(self.chars[self.pos]);
+                        self.pos += 1;
+                    }
+                    parts.push(None);
+                },
+                Some(_) => {
+                    let string = self.identifier().to_lowercase();
+                    match self.strings.get(&string) {
+                        Some(s) => value.push_str(s),
+                        None    => return Err(BEK::UndefinedString.into_error()),
+                    }
+                    parts.push(Some(string));
+                },
+                None => return Err(BEK::BibTeXParseError.into_error()),
+            }
+
+            self.skip_whitespace();
+            if self.peek() == Some('#') {
+                self.pos += 1;
+            } else {
+                let single = if parts.len() == 1 { parts.pop().and_then(|p| p) } else { None };
+                let single = match single {
+                    Some(s) => if predefined_strings().contains_key(&s) { Some(s) } else { None },
+                    None    => None,
+                };
+                return Ok((name, value, single));
+            }
+        }
+    }
+
+    /// Parse `{...}` with balanced braces, returns the text between the outer braces
+    fn delimited(&mut self) -> Result<String> {
+        try!(self.expect('{'));
+        let start     = self.pos;
+        let mut depth = 1;
+        while depth > 0 {
+            match self.next() {
+                Some('{') => depth += 1,
+                Some('}') => depth -= 1,
+                Some(_)   => {},
+                None      => return Err(BEK::BibTeXParseError.into_error()),
+            }
+        }
+        Ok(self.chars[start..(self.pos - 1)].iter().cloned().collect())
+    }
+
+    /// Parse `"..."`, quotes inside of braces do not end the value
+    fn quoted(&mut self) -> Result<String> {
+        try!(self.expect('"'));
+        let start     = self.pos;
+        let mut depth = 0;
+        loop {
+            match self.next() {
+                Some('{') => depth += 1,
+                Some('}') => depth -= 1,
+                Some('"') if depth == 0 => break,
+                Some(_)   => {},
+                None      => return Err(BEK::BibTeXParseError.into_error()),
+            }
+        }
+        Ok(self.chars[start..(self.pos - 1)].iter().cloned().collect())
+    }
+
+    fn identifier(&mut self) -> String {
+        let start = self.pos;
+        while self.peek().map(|c| c.is_alphanumeric() || "_-:.+/".contains(c)).unwrap_or(false) {
+            self.pos += 1;
+        }
+        self.chars[start..self.pos].iter().cloned().collect()
+    }
+
+    fn expect(&mut self, c: char) -> Result<()> {
+        self.skip_whitespace();
+        if self.next() == Some(c) {
+            Ok(())
+        } else {
+            Err(BEK::BibTeXParseError.into_error())
+        }
+    }
+
+    /// Move to the next `c`, returns false if there is none
+    fn skip_to(&mut self, c: char) -> bool {
+        while let Some(x) = self.peek() {
+            if x == c {
+                return true;
+            }
+            self.pos += 1;
+        }
+        false
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.peek().map(char::is_whitespace).unwrap_or(false) {
+            self.pos += 1;
+        }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).cloned()
+    }
+
+    fn next(&mut self) -> Option<char> {
+        let c = self.peek();
+        if c.is_some() {
+            self.pos += 1;
+        }
+        c
+    }
+
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const BIB : &'static str = r#"
+This is a comment outside of any entry, by someone@example.com.
+
+@comment{ this {is} ignored }
+@string{ tugboat = "TUGboat" }
+
+@Article{knuth1984,
+  Author    = {Donald E. Knuth},
+  title     = "Literate Programming",
+  journal   = tugboat # { Journal},
+  year      = 1984,
+  month     = may,
+  note      = {The {\TeX} book "quoted"},
+}
+
+@book(lamport1994, title={\LaTeX: A Document Preparation System})
+"#;
+
+    #[test]
+    fn test_parse() {
+        let entries = BibEntry::parse_all(BIB).unwrap();
+        assert_eq!(entries.len(), 2);
+
+        let knuth = &entries[0];
+        assert_eq!(knuth.kind, "article");
+        assert_eq!(knuth.key, "knuth1984");
+        assert_eq!(knuth.fields.get("author").unwrap(), "Donald E. Knuth");
+        assert_eq!(knuth.fields.get("title").unwrap(), "Literate Programming");
+        assert_eq!(knuth.fields.get("journal").unwrap(), "TUGboat Journal");
+        assert_eq!(knuth.fields.get("year").unwrap(), "1984");
+        assert_eq!(knuth.fields.get("month").unwrap(), "May");
+        assert_eq!(knuth.fields.get("note").unwrap(), "The {\\TeX} book \"quoted\"");
+
+        let lamport = &entries[1];
+        assert_eq!(lamport.kind, "book");
+        assert_eq!(lamport.fields.get("title").unwrap(), "\\LaTeX: A Document Preparation System");
+    }
+
+    #[test]
+    fn test_roundtrip() {
+        let entries  = BibEntry::parse_all(BIB).unwrap();
+        let bibtex   = to_bibtex(entries.iter());
+        let reparsed = BibEntry::parse_all(&bibtex).unwrap();
+        assert_eq!(entries, reparsed);
+    }
+
+    #[test]
+    fn test_to_bibtex_keeps_order_and_months() {
+        let entries = BibEntry::parse_all(BIB).unwrap();
+        let knuth   = &entries[0];
+        assert_eq!(knuth.order, vec!["author", "title", "journal", "year", "month", "note"]);
+        assert_eq!(knuth.macros.get("month").unwrap(), "may");
+
+        let bibtex = knuth.to_bibtex();
+        assert!(bibtex.starts_with("@article{knuth1984,\n  author = {Donald E. Knuth},\n  title = "));
+        assert!(bibtex.contains("  month = may,\n"));
+
+        let mut changed = knuth.clone();
+        changed.fields.insert(String::from("month"), String::from("June"));
+        changed.fields.insert(String::from("abstract"), String::from("..."));
+        let bibtex = changed.to_bibtex();
+        assert!(bibtex.contains("  month = {June},\n"));
+        assert!(bibtex.ends_with("  abstract = {...},\n}\n"));
+    }
+
+    #[test]
+    fn test_stray_at_is_a_comment() {
+        let entries = BibEntry::parse_all("Mail me at me@example.com @ any time\n@misc{foo, title = {x}}").unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].key, "foo");
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert!(BibEntry::parse_all("@article{foo, title = {unbalanced}").is_err());
+        assert!(BibEntry::parse_all("@article{foo, title = undefined}").is_err());
+        assert!(BibEntry::parse_all("@article{, title = {x}}").is_err());
+        assert!(BibEntry::parse_all("@article{foo, title}").is_err());
+    }
+
+}
@@ -0,0 +1,384 @@
+//
+// imag - the personal information management suite for the commandline
+// Copyright (C) 2015, 2016 Matthias Beyer <mail@beyermatthias.de> and contributors
+//
+// This library is free software; you can redistribute it and/or
+// modify it under the terms of the GNU Lesser General Public
+// License as published by the Free Software Foundation; version
+// 2.1 of the License.
+//
+// This library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+// Lesser General Public License for more details.
+//
+// You should have received a copy of the GNU Lesser General Public
+// License along with this library; if not, write to the Free Software
+// Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA  02110-1301  USA
+//
+
+use std::collections::BTreeMap;
+use std::ops::Deref;
+use std::path::PathBuf;
+
+use toml::Value;
+
+use libimagstore::store::{Entry, FileLockEntry, Store};
+use libimagstore::storeid::{IntoStoreId, StoreId, StoreIdIterator};
+use libimagentrylink::internal::InternalLinker;
+use libimagref::flags::RefFlags;
+use libimagref::reference::Ref;
+use libimagerror::into::IntoError;
+use libimagutil::import::ImportAction;
+
+use module_path::ModuleEntryPath;
+use bibtex::BibEntry;
+use result::Result;
+use error::BibliographyErrorKind as BEK;
+use error::MapErrInto;
+
+/// The type of the internal links between citations and other entries, like notes
+pub const LINK_TYPE : &'static str = "citation";
+
+/// The type of the internal links between citations and the references to their PDF files
+pub const PDF_LINK_TYPE : &'static str = "pdf";
+
+/// A citation, imported from a BibTeX entry
+///
+/// The type, key and fields of the BibTeX entry are stored in the "bibliography" section of the
+/// header. The content is free for notes on the citation.
+#[derive(Debug)]
+pub struct Citation<'a>(FileLockEntry<'a>);
+
+fn entry_to_header(entry: &BibEntry) -> Value {
+    let fields = entry.fields
+        .iter()
+        .map(|(k, v)| (k.clone(), Value::String(v.clone())))
+        .collect();
+
+    let mut section = BTreeMap::new();
+    section.insert(String::from("key"), Value::String(entry.key.clone()));
+    section.insert(String::from("type"), Value::String(entry.kind.clone()));
+    section.insert(String::from("fields"), Value::Table(fields));
+
+    // Only needed to write the entry as it was imported
+    if !entry.order.is_empty() {
+        let order = entry.order.iter().cloned().map(Value::String).collect();
+        section.insert(String::from("order"), Value::Array(order));
+    }
+    if !entry.macros.is_empty() {
+        let macros = entry.macros
+            .iter()
+            .map(|(k, v)| (k.clone(), Value::String(v.clone())))
+            .collect();
+        section.insert(String::from("macros"), Value::Table(macros));
+    }
+
+    Value::Table(section)
+}
+
+impl<'a> Citation<'a> {
+
+    /// Import a BibTeX entry
+    ///
+    /// Citations are identified by their key: if there is a citation with the same key already,
+    /// its type and fields are replaced, unless they did not change.
+    pub fn import(store: &'a Store, entry: &BibEntry) -> Result<(Citation<'a>, ImportAction)> {
+        let header = entry_to_header(entry);
+        let id     = try!(ModuleEntryPath::new(entry.key.replace("/", "_"))
+                          .into_storeid()
+                          .map_err_into(BEK::StoreWriteError));
+
+        let (mut fle, action) = match try!(store.get(id.clone()).map_err_into(BEK::StoreReadError)) {
+            Some(fle) => {
+                if fle.get_header().read("bibliography").ok() == Some(Some(header.clone())) {
+                    return Ok((Citation(fle), ImportAction::Unchanged));
+                }
+                (fle, ImportAction::Updated)
+            },
+            None => (try!(store.create(id).map_err_into(BEK::StoreWriteError)), ImportAction::Created),
+        };
+
+        try!(fle.get_header_mut().set("bibliography", header).map_err_into(BEK::HeaderWriteError));
+        Ok((Citation(fle), action))
+    }
+
+    /// Import all entries in the BibTeX text `text`, returns the ids of the citations and what
+    /// happened to them
+    pub fn import_str(store: &Store, text: &str) -> Result<Vec<(StoreId, ImportAction)>> {
+        let entries = try!(BibEntry::parse_all(text));
+        let mut imported = vec![];
+
+        for entry in entries {
+            let (citation, action) = try!(Citation::import(store, &entry));
+            imported.push((citation.get_location().clone().without_base(), action));
+        }
+
+        Ok(imported)
+    }
+
+    /// Get a citation by its key
+    pub fn get(store: &'a Store, key: &str) -> Result<Option<Citation<'a>>> {
+        ModuleEntryPath::new(key.replace("/", "_"))
+            .into_storeid()
+            .and_then(|id| store.get(id))
+            .map_err_into(BEK::StoreReadError)
+            .map(|o| o.map(Citation))
+    }
+
+    pub fn all(store: &'a Store) -> Result<CitationIterator<'a>> {
+        store.retrieve_for_module("bibliography")
+            .map(|iter| CitationIterator::new(store, iter))
+            .map_err_into(BEK::StoreReadError)
+    }
+
+    fn read_string(&self, field: &str) -> Result<String> {
+        match self.0.get_header().read(field) {
+            Ok(Some(Value::String(s))) => Ok(s),
+            Ok(_)                      => Err(BEK::HeaderTypeError.into_error()),
+            Err(e)                     => Err(BEK::HeaderReadError.into_error_with_cause(Box::new(e))),
+        }
+    }
+
+    /// The citation key
+    pub fn key(&self) -> Result<String> {
+        self.read_string("bibliography.key")
+    }
+
+    /// The BibTeX entry type, like "article"
+    pub fn kind(&self) -> Result<String> {
+        self.read_string("bibliography.type")
+    }
+
+    fn read_table(&self, field: &str) -> Result<BTreeMap<String, String>> {
+        match self.0.get_header().read(field) {
+            Ok(Some(Value::Table(t))) => t.into_iter()
+                .map(|(k, v)| match v {
+                    Value::String(s) => Ok((k, s)),
+                    _                => Err(BEK::HeaderTypeError.into_error()),
+                })
+                .collect(),
+            Ok(None) => Ok(BTreeMap::new()),
+            Ok(_)    => Err(BEK::HeaderTypeError.into_error()),
+            Err(e)   => Err(BEK::HeaderReadError.into_error_with_cause(Box::new(e))),
+        }
+    }
+
+    pub fn fields(&self) -> Result<BTreeMap<String, String>> {
+        self.read_table("bibliography.fields")
+    }
+
+    /// The names of the fields in the order of the imported BibTeX entry
+    pub fn field_order(&self) -> Result<Vec<String>> {
+        match self.0.get_header().read("bibliography.order") {
+            Ok(Some(Value::Array(a))) => a.into_iter()
+                .map(|v| match v {
+                    Value::String(s) => Ok(s),
+                    _                => Err(BEK::HeaderTypeError.into_error()),
+                })
+                .collect(),
+            Ok(None) => Ok(vec![]),
+            Ok(_)    => Err(BEK::HeaderTypeError.into_error()),
+            Err(e)   => Err(BEK::HeaderReadError.into_error_with_cause(Box::new(e))),
+        }
+    }
+
+    pub fn field(&self, name: &str) -> Result<Option<String>> {
+        self.fields().map(|mut fields| fields.remove(&name.to_lowercase()))
+    }
+
+    /// Build the BibTeX entry of this citation
+    pub fn to_bib_entry(&self) -> Result<BibEntry> {
+        let mut entry = BibEntry::new(try!(self.kind()), try!(self.key()));
+        entry.fields  = try!(self.fields());
+        entry.order   = try!(self.field_order());
+        entry.macros  = try!(self.read_table("bibliography.macros"));
+        Ok(entry)
+    }
+
+    /// Attach the (PDF) file at `path` to this citation
+    ///
+    /// The file is referenced with libimagref, the reference is linked to the citation.
+    pub fn attach_pdf(&mut self, store: &Store, path: PathBuf) -> Result<()> {
+        let mut reference = match try!(Ref::get_by_path(store, path.clone()).map_err_into(BEK::RefError)) {
+            Some(r) => r,
+            None    => {
+                let flags = RefFlags::default().with_content_hashing(true);
+                try!(Ref::create(store, path, flags).map_err_into(BEK::RefError))
+            },
+        };
+
+        self.0
+            .add_typed_internal_link(&mut reference, PDF_LINK_TYPE, BTreeMap::new())
+            .map_err_into(BEK::LinkError)
+    }
+
+    /// Get the paths of the files attached to this citation
+    pub fn pdfs(&self, store: &Store) -> Result<Vec<PathBuf>> {
+        let links = try!(self.0.get_internal_links_by_type(PDF_LINK_TYPE).map_err_into(BEK::LinkError));
+
+        links.into_iter()
+            .map(|link| {
+                Ref::get(store, link.get_link().clone())
+                    .and_then(|r| r.fs_file())
+                    .map_err_into(BEK::RefError)
+            })
+            .collect()
+    }
+
+    /// Link an entry (like a note) to this citation
+    pub fn link_entry(&mut self, entry: &mut Entry) -> Result<()> {
+        self.0
+            .add_typed_internal_link(entry, LINK_TYPE, BTreeMap::new())
+            .map_err_into(BEK::LinkError)
+    }
+
+    /// Get the ids of all entries linked to this citation
+    pub fn linked_entries(&self) -> Result<Vec<StoreId>> {
+        self.0
+            .get_internal_links_by_type(LINK_TYPE)
+            .map(|links| links.into_iter().map(|l| l.get_link().clone()).collect())
+            .map_err_into(BEK::LinkError)
+    }
+
+}
+
+impl<'a> Deref for Citation<'a> {
+
+    type Target = FileLockEntry<'a>;
+
+    fn deref(&self) -> &FileLockEntry<'a> {
+        &self.0
+    }
+
+}
+
+#[derive(Debug)]
+pub struct CitationIterator<'a> {
+    store: &'a Store,
+    iditer: StoreIdIterator,
+}
+
+impl<'a> CitationIterator<'a> {
+
+    pub fn new(store: &'a Store, iditer: StoreIdIterator) -> CitationIterator<'a> {
+        CitationIterator {
+            store: store,
+            iditer: iditer,
+        }
+    }
+
+}
+
+impl<'a> Iterator for CitationIterator<'a> {
+    type Item = Result<Citation<'a>>;
+
+    fn next(&mut self) -> Option<Result<Citation<'a>>> {
+        self.iditer
+            .next()
+            .map(|id| self.store.retrieve(id).map(Citation).map_err_into(BEK::StoreReadError))
+    }
+
+}
+
+/// Get the citation key of `entry`, if it is a citation
+pub fn entry_key(entry: &Entry) -> Option<String> {
+    match entry.get_header().read("bibliography.key") {
+        Ok(Some(Value::String(key))) => Some(key),
+        _                            => None,
+    }
+}
+
+/// Export all citations for which `f` returns true to BibTeX, sorted by key
+pub fn export_where<F>(store: &Store, f: F) -> Result<String>
+    where F: Fn(&Entry) -> bool
+{
+    let mut entries = vec![];
+    for citation in try!(Citation::all(store)) {
+        let citation = try!(citation);
+        if f(&citation) {
+            entries.push(try!(citation.to_bib_entry()));
+        }
+    }
+
+    entries.sort_by(|a, b| a.key.cmp(&b.key));
+    Ok(::bibtex::to_bibtex(entries.iter()))
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::PathBuf;
+
+    use libimagstore::store::Store;
+    use libimagstore::file_abstraction::InMemoryBackend;
+
+    use super::*;
+
+    fn get_store() -> Store {
+        Store::new(PathBuf::from("/"), None, Box::new(InMemoryBackend::new())).unwrap()
+    }
+
+    const BIB : &'static str = "@article{knuth1984, author = {Donald E. Knuth}, title = {Literate Programming}}\n\
+                                @book{lamport1994, author = {Leslie Lamport}, title = {\\LaTeX}}\n";
+
+    #[test]
+    fn test_import_deduplicates_by_key() {
+        let store = get_store();
+
+        let imported = Citation::import_str(&store, BIB).unwrap();
+        assert_eq!(imported.len(), 2);
+        assert!(imported.iter().all(|&(_, a)| a == ImportAction::Created));
+
+        let imported = Citation::import_str(&store, BIB).unwrap();
+        assert!(imported.iter().all(|&(_, a)| a == ImportAction::Unchanged));
+
+        let changed  = BIB.replace("Literate Programming", "Literate programming");
+        let imported = Citation::import_str(&store, &changed).unwrap();
+        assert_eq!(imported.iter().filter(|&&(_, a)| a == ImportAction::Updated).count(), 1);
+
+        let knuth = Citation::get(&store, "knuth1984").unwrap().unwrap();
+        assert_eq!(knuth.kind().unwrap(), "article");
+        assert_eq!(knuth.field("Title").unwrap(), Some(String::from("Literate programming")));
+    }
+
+    #[test]
+    fn test_export_where() {
+        let store = get_store();
+        let _     = Citation::import_str(&store, BIB).unwrap();
+
+        let all = export_where(&store, |_| true).unwrap();
+        assert_eq!(BibEntry::parse_all(&all).unwrap(), BibEntry::parse_all(BIB).unwrap());
+
+        let books = export_where(&store, |e| {
+            e.get_header().read("bibliography.type").ok() == Some(Some(Value::String(String::from("book"))))
+        }).unwrap();
+        let books = BibEntry::parse_all(&books).unwrap();
+        assert_eq!(books.len(), 1);
+        assert_eq!(books[0].key, "lamport1994");
+    }
+
+    #[test]
+    fn test_export_keeps_order_and_months() {
+        let store = get_store();
+        let _     = Citation::import_str(&store, "@article{knuth1984, title = {Literate Programming}, \
+                                                  month = may, author = {Donald E. Knuth}}").unwrap();
+
+        let exported = export_where(&store, |_| true).unwrap();
+        assert_eq!(exported, "@article{knuth1984,\n  title = {Literate Programming},\n  month = may,\n  \
+                              author = {Donald E. Knuth},\n}\n");
+    }
+
+    #[test]
+    fn test_link_entry() {
+        let store     = get_store();
+        let _         = Citation::import_str(&store, BIB).unwrap();
+        let mut note  = store.create(PathBuf::from("notes/literate")).unwrap();
+        let mut knuth = Citation::get(&store, "knuth1984").unwrap().unwrap();
+
+        knuth.link_entry(&mut note).unwrap();
+        assert_eq!(knuth.linked_entries().unwrap(),
+                   vec![StoreId::new_baseless(PathBuf::from("notes/literate")).unwrap()]);
+        assert!(knuth.pdfs(&store).unwrap().is_empty());
+    }
+
+}
@@ -0,0 +1,42 @@
+//
+// imag - the personal information management suite for the commandline
+// Copyright (C) 2015, 2016 Matthias Beyer <mail@beyermatthias.de> and contributors
+//
+// This library is free software; you can redistribute it and/or
+// modify it under the terms of the GNU Lesser General Public
+// License as published by the Free Software Foundation; version
+// 2.1 of the License.
+//
+// This library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+// Lesser General Public License for more details.
+//
+// You should have received a copy of the GNU Lesser General Public
+// License along with this library; if not, write to the Free Software
+// Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA  02110-1301  USA
+//
+
+generate_error_module!(
+    generate_error_types!(CalendarError, CalendarErrorKind,
+        ICalParseError        => "Error parsing iCalendar data",
+        DateTimeParseError    => "Error parsing date or time",
+        RecurrenceParseError  => "Error parsing recurrence rule",
+        UnsupportedRecurrence => "Recurrence rule is not supported",
+        UnknownTimeZone       => "Unknown time zone",
+        MissingUid            => "Event has no UID",
+        IOError               => "IO Error",
+        StoreReadError        => "Error reading store",
+        StoreWriteError       => "Error writing store",
+        HeaderReadError       => "Error reading header",
+        HeaderWriteError      => "Error writing header",
+        HeaderTypeError       => "Header type error",
+        RefError              => "Error handling reference to calendar file",
+        EventNotFound         => "Event does not exist"
+    );
+);
+
+pub use self::error::CalendarError;
+pub use self::error::CalendarErrorKind;
+pub use self::error::MapErrInto;
+
@@ -0,0 +1,691 @@
+//
+// imag - the personal information management suite for the commandline
+// Copyright (C) 2015, 2016 Matthias Beyer <mail@beyermatthias.de> and contributors
+//
+// This library is free software; you can redistribute it and/or
+// modify it under the terms of the GNU Lesser General Public
+// License as published by the Free Software Foundation; version
+// 2.1 of the License.
+//
+// This library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+// Lesser General Public License for more details.
+//
+// You should have received a copy of the GNU Lesser General Public
+// License along with this library; if not, write to the Free Software
+// Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA  02110-1301  USA
+//
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs::File;
+use std::io::Read;
+use std::ops::Deref;
+use std::path::{Path, PathBuf};
+
+use chrono::Duration;
+use chrono::naive::datetime::NaiveDateTime;
+use toml::Value;
+
+use libimagstore::store::{FileLockEntry, Store};
+use libimagstore::storeid::{IntoStoreId, StoreId, StoreIdIterator};
+use libimagref::flags::RefFlags;
+use libimagref::reference::Ref;
+use libimagerror::into::IntoError;
+use libimagutil::import::ImportAction;
+
+use module_path::ModuleEntryPath;
+use ical::{Component, parse_duration};
+use recurrence::Recurrence;
+use timezone::TimeZones;
+use result::Result;
+use error::CalendarErrorKind as CEK;
+use error::MapErrInto;
+
+/// The format of date-times in the header
+pub const DATETIME_FORMAT : &'static str = "%Y-%m-%dT%H:%M:%S";
+
+/// How events are imported
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImportMode {
+    /// The text of each event is copied into the content of its entry
+    Copy,
+
+    /// The file is referenced with libimagref, the entries of the events point to the reference
+    Reference,
+}
+
+/// An event (VEVENT) or todo (VTODO), imported from an iCalendar file
+///
+/// The fields needed for agenda views are stored in the "calendar" section of the header. The
+/// original text of the event is either the content of the entry or, if the event was imported
+/// by reference, read from the referenced file.
+#[derive(Debug)]
+pub struct Event<'a>(FileLockEntry<'a>);
+
+/// A single occurrence of an event
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Occurrence {
+    pub id: StoreId,
+    pub summary: Option<String>,
+    pub location: Option<String>,
+    pub start: NaiveDateTime,
+    pub end: NaiveDateTime,
+    pub all_day: bool,
+    pub todo: bool,
+}
+
+fn format_datetime(dt: &NaiveDateTime) -> String {
+    dt.format(DATETIME_FORMAT).to_string()
+}
+
+/// The part of the store id of an event after "calendar/"
+///
+/// This is the UID of the event. Events which override a single occurrence of a recurring event
+/// (which have a RECURRENCE-ID) get the date-time of this occurrence appended.
+fn component_name(component: &Component, zones: &TimeZones) -> Result<String> {
+    let uid = try!(component.get_text("UID").ok_or(CEK::MissingUid.into_error())).replace("/", "_");
+    match component.get("RECURRENCE-ID") {
+        Some(p) => p.datetime(zones).map(|(dt, _)| format!("{}@{}", uid, format_datetime(&dt))),
+        None    => Ok(uid),
+    }
+}
+
+fn component_to_header(component: &Component, zones: &TimeZones, source: Option<&StoreId>)
+    -> Result<Value>
+{
+    let mut section = BTreeMap::new();
+    let todo = component.name() == "VTODO";
+
+    {
+        let mut insert = |key: &str, value: Option<String>| if let Some(v) = value {
+            section.insert(String::from(key), Value::String(v));
+        };
+
+        insert("uid", component.get_text("UID"));
+        insert("kind", Some(String::from(if todo { "todo" } else { "event" })));
+        insert("summary", component.get_text("SUMMARY"));
+        insert("description", component.get_text("DESCRIPTION"));
+        insert("location", component.get_text("LOCATION"));
+        insert("status", component.get_text("STATUS"));
+        insert("rrule", component.get("RRULE").map(|p| p.value.clone()));
+        insert("source", source.map(|id| id.to_string()));
+    }
+
+    let start = match component.get("DTSTART") {
+        Some(p) => Some(try!(p.datetime(zones))),
+        None if todo => None,
+        None => return Err(CEK::ICalParseError.into_error()),
+    };
+
+    if let Some((start, all_day)) = start {
+        let end = match (component.get("DTEND"), component.get("DURATION")) {
+            (Some(p), _)    => try!(p.datetime(zones)).0,
+            (None, Some(p)) => start + try!(parse_duration(&p.value)),
+            (None, None)    => if all_day { start + Duration::days(1) } else { start },
+        };
+
+        section.insert(String::from("start"), Value::String(format_datetime(&start)));
+        section.insert(String::from("end"), Value::String(format_datetime(&end)));
+        section.insert(String::from("all_day"), Value::Boolean(all_day));
+    }
+
+    if let Some(p) = component.get("DUE") {
+        let (due, _) = try!(p.datetime(zones));
+        section.insert(String::from("due"), Value::String(format_datetime(&due)));
+    }
+
+    if let Some(p) = component.get("RECURRENCE-ID") {
+        let (recurrence_id, _) = try!(p.datetime(zones));
+        section.insert(String::from("recurrence_id"), Value::String(format_datetime(&recurrence_id)));
+    }
+
+    let mut exdates = vec![];
+    for p in component.get_all("EXDATE") {
+        exdates.extend(try!(p.datetimes(zones)).iter().map(format_datetime).map(Value::String));
+    }
+    if !exdates.is_empty() {
+        section.insert(String::from("exdates"), Value::Array(exdates));
+    }
+
+    // Kept for the export, as the event can only be read again with its time zones
+    let timezones = zones.raws_for(component);
+    if !timezones.is_empty() {
+        let timezones = timezones.into_iter().map(Value::String).collect();
+        section.insert(String::from("timezones"), Value::Array(timezones));
+    }
+
+    Ok(Value::Table(section))
+}
+
+fn read_file(path: &Path) -> Result<String> {
+    let mut text = String::new();
+    File::open(path)
+        .and_then(|mut file| file.read_to_string(&mut text))
+        .map(|_| text)
+        .map_err_into(CEK::IOError)
+}
+
+impl<'a> Event<'a> {
+
+    /// Import a VEVENT or VTODO component
+    ///
+    /// Events are identified by their UID: if there is an event with the same UID already, it is
+    /// replaced, unless it did not change. If `source` is passed, it is the id of the reference
+    /// to the file the event was read from and the text of the event is not copied. Date-times
+    /// with a time zone are resolved with `zones`, the time zones of the calendar.
+    pub fn import(store: &'a Store, component: &Component, zones: &TimeZones, source: Option<&StoreId>)
+        -> Result<(Event<'a>, ImportAction)>
+    {
+        let name    = try!(component_name(component, zones));
+        let header  = try!(component_to_header(component, zones, source));
+        let content = if source.is_some() { String::new() } else { String::from(component.raw()) };
+        let id      = try!(ModuleEntryPath::new(name).into_storeid().map_err_into(CEK::StoreWriteError));
+
+        let (mut entry, action) = match try!(store.get(id.clone()).map_err_into(CEK::StoreReadError)) {
+            Some(entry) => {
+                let unchanged = *entry.get_content() == content &&
+                    entry.get_header().read("calendar").ok() == Some(Some(header.clone()));
+                if unchanged {
+                    return Ok((Event(entry), ImportAction::Unchanged));
+                }
+                (entry, ImportAction::Updated)
+            },
+            None => (try!(store.create(id).map_err_into(CEK::StoreWriteError)), ImportAction::Created),
+        };
+
+        try!(entry.get_header_mut().set("calendar", header).map_err_into(CEK::HeaderWriteError));
+        *entry.get_content_mut() = content;
+
+        Ok((Event(entry), action))
+    }
+
+    /// Import all events in the iCalendar text `text`, returns the ids of the events and what
+    /// happened to them
+    pub fn import_str(store: &Store, text: &str, source: Option<&StoreId>)
+        -> Result<Vec<(StoreId, ImportAction)>>
+    {
+        let mut imported = vec![];
+        for calendar in try!(Component::parse_all(text)) {
+            let zones = TimeZones::from_calendar(&calendar);
+            for component in calendar.events() {
+                let (event, action) = try!(Event::import(store, component, &zones, source));
+                imported.push((event.get_location().clone().without_base(), action));
+            }
+        }
+        Ok(imported)
+    }
+
+    /// Import all events in the iCalendar file at `path`
+    pub fn import_file(store: &Store, path: &Path, mode: ImportMode) -> Result<Vec<(StoreId, ImportAction)>> {
+        let text = try!(read_file(path));
+
+        match mode {
+            ImportMode::Copy      => Event::import_str(store, &text, None),
+            ImportMode::Reference => {
+                let pb        = PathBuf::from(path);
+                let reference = match try!(Ref::get_by_path(store, pb.clone()).map_err_into(CEK::RefError)) {
+                    Some(r) => r,
+                    None    => try!(Ref::create(store, pb, RefFlags::default()).map_err_into(CEK::RefError)),
+                };
+                let source = reference.get_location().clone().without_base();
+
+                Event::import_str(store, &text, Some(&source))
+            },
+        }
+    }
+
+    /// Get an event by its UID
+    pub fn get(store: &'a Store, uid: &str) -> Result<Option<Event<'a>>> {
+        ModuleEntryPath::new(uid.replace("/", "_"))
+            .into_storeid()
+            .and_then(|id| store.get(id))
+            .map_err_into(CEK::StoreReadError)
+            .map(|o| o.map(Event))
+    }
+
+    pub fn all(store: &'a Store) -> Result<EventIterator<'a>> {
+        store.retrieve_for_module("calendar")
+            .map(|iter| EventIterator::new(store, iter))
+            .map_err_into(CEK::StoreReadError)
+    }
+
+    fn read_string(&self, field: &str) -> Result<Option<String>> {
+        match self.0.get_header().read(field) {
+            Ok(Some(Value::String(s))) => Ok(Some(s)),
+            Ok(None)                   => Ok(None),
+            Ok(_)                      => Err(CEK::HeaderTypeError.into_error()),
+            Err(e)                     => Err(CEK::HeaderReadError.into_error_with_cause(Box::new(e))),
+        }
+    }
+
+    fn read_datetime(&self, field: &str) -> Result<Option<NaiveDateTime>> {
+        match try!(self.read_string(field)) {
+            Some(s) => NaiveDateTime::parse_from_str(&s, DATETIME_FORMAT)
+                .map(Some)
+                .map_err(|e| CEK::DateTimeParseError.into_error_with_cause(Box::new(e))),
+            None => Ok(None),
+        }
+    }
+
+    pub fn uid(&self) -> Result<String> {
+        self.read_string("calendar.uid")
+            .and_then(|uid| uid.ok_or(CEK::MissingUid.into_error()))
+    }
+
+    /// Whether this is a todo (VTODO) rather than an event
+    pub fn is_todo(&self) -> Result<bool> {
+        self.read_string("calendar.kind").map(|kind| kind.map(|k| k == "todo").unwrap_or(false))
+    }
+
+    pub fn summary(&self) -> Result<Option<String>> {
+        self.read_string("calendar.summary")
+    }
+
+    pub fn description(&self) -> Result<Option<String>> {
+        self.read_string("calendar.description")
+    }
+
+    pub fn location(&self) -> Result<Option<String>> {
+        self.read_string("calendar.location")
+    }
+
+    pub fn status(&self) -> Result<Option<String>> {
+        self.read_string("calendar.status")
+    }
+
+    pub fn start(&self) -> Result<Option<NaiveDateTime>> {
+        self.read_datetime("calendar.start")
+    }
+
+    pub fn end(&self) -> Result<Option<NaiveDateTime>> {
+        self.read_datetime("calendar.end")
+    }
+
+    pub fn due(&self) -> Result<Option<NaiveDateTime>> {
+        self.read_datetime("calendar.due")
+    }
+
+    /// The occurrence of a recurring event which is overridden by this event
+    pub fn recurrence_id(&self) -> Result<Option<NaiveDateTime>> {
+        self.read_datetime("calendar.recurrence_id")
+    }
+
+    pub fn is_all_day(&self) -> Result<bool> {
+        match self.0.get_header().read("calendar.all_day") {
+            Ok(Some(Value::Boolean(b))) => Ok(b),
+            Ok(None)                    => Ok(false),
+            Ok(_)                       => Err(CEK::HeaderTypeError.into_error()),
+            Err(e)                      => Err(CEK::HeaderReadError.into_error_with_cause(Box::new(e))),
+        }
+    }
+
+    pub fn recurrence(&self) -> Result<Option<Recurrence>> {
+        match try!(self.read_string("calendar.rrule")) {
+            Some(rule) => Recurrence::parse(&rule).map(Some),
+            None       => Ok(None),
+        }
+    }
+
+    pub fn exdates(&self) -> Result<Vec<NaiveDateTime>> {
+        match self.0.get_header().read("calendar.exdates") {
+            Ok(Some(Value::Array(a))) => a.into_iter()
+                .map(|v| match v {
+                    Value::String(s) => NaiveDateTime::parse_from_str(&s, DATETIME_FORMAT)
+                        .map_err(|e| CEK::DateTimeParseError.into_error_with_cause(Box::new(e))),
+                    _ => Err(CEK::HeaderTypeError.into_error()),
+                })
+                .collect(),
+            Ok(None) => Ok(vec![]),
+            Ok(_)    => Err(CEK::HeaderTypeError.into_error()),
+            Err(e)   => Err(CEK::HeaderReadError.into_error_with_cause(Box::new(e))),
+        }
+    }
+
+    /// The original text of the VTIMEZONE components of the time zones the event uses
+    pub fn timezones(&self) -> Result<Vec<String>> {
+        match self.0.get_header().read("calendar.timezones") {
+            Ok(Some(Value::Array(a))) => a.into_iter()
+                .map(|v| match v {
+                    Value::String(s) => Ok(s),
+                    _                => Err(CEK::HeaderTypeError.into_error()),
+                })
+                .collect(),
+            Ok(None) => Ok(vec![]),
+            Ok(_)    => Err(CEK::HeaderTypeError.into_error()),
+            Err(e)   => Err(CEK::HeaderReadError.into_error_with_cause(Box::new(e))),
+        }
+    }
+
+    /// The id of the reference to the file the event was imported from, if it was imported by
+    /// reference
+    pub fn source(&self) -> Result<Option<StoreId>> {
+        match try!(self.read_string("calendar.source")) {
+            Some(s) => StoreId::new_baseless(PathBuf::from(s)).map(Some).map_err_into(CEK::StoreReadError),
+            None    => Ok(None),
+        }
+    }
+
+    /// The original text of the event
+    ///
+    /// For events which were imported by reference, the referenced file is read.
+    pub fn raw_component(&self, store: &Store) -> Result<String> {
+        let source = match try!(self.source()) {
+            Some(source) => source,
+            None         => return Ok(self.0.get_content().clone()),
+        };
+
+        let path = try!(Ref::get(store, source)
+                        .and_then(|r| r.fs_file())
+                        .map_err_into(CEK::RefError));
+        let text = try!(read_file(&path));
+        let name = try!(self.0
+                        .get_location()
+                        .local()
+                        .file_name()
+                        .and_then(|n| n.to_str())
+                        .map(String::from)
+                        .ok_or(CEK::StoreReadError.into_error()));
+
+        for calendar in try!(Component::parse_all(&text)) {
+            let zones = TimeZones::from_calendar(&calendar);
+            for component in calendar.events() {
+                if try!(component_name(component, &zones)) == name {
+                    return Ok(String::from(component.raw()));
+                }
+            }
+        }
+
+        Err(CEK::EventNotFound.into_error())
+    }
+
+    /// Get all occurrences of the event which overlap the time from `from` to `to`
+    ///
+    /// Todos occur at their due date, or at their start if they have no due date.
+    pub fn occurrences(&self, from: NaiveDateTime, to: NaiveDateTime) -> Result<Vec<Occurrence>> {
+        let all_day = try!(self.is_all_day());
+        let todo    = try!(self.is_todo());
+
+        let occurrence = |start: NaiveDateTime, end: NaiveDateTime| -> Result<Occurrence> {
+            Ok(Occurrence {
+                id: self.0.get_location().clone().without_base(),
+                summary: try!(self.summary()),
+                location: try!(self.location()),
+                start: start,
+                end: end,
+                all_day: all_day,
+                todo: todo,
+            })
+        };
+
+        if todo {
+            let date = match try!(self.due()) {
+                Some(due) => Some(due),
+                None      => try!(self.start()),
+            };
+
+            return match date {
+                Some(date) if from <= date && date < to => occurrence(date, date).map(|o| vec![o]),
+                _ => Ok(vec![]),
+            };
+        }
+
+        let start = match try!(self.start()) {
+            Some(start) => start,
+            None        => return Ok(vec![]),
+        };
+        let duration = try!(self.end()).unwrap_or(start) - start;
+        let exdates  = try!(self.exdates());
+
+        let starts = match try!(self.recurrence()) {
+            Some(recurrence) => recurrence.occurrences(start, to),
+            None             => vec![start],
+        };
+
+        starts.into_iter()
+            .filter(|s| !exdates.iter().any(|ex| *ex == *s || (all_day && ex.date() == s.date())))
+            .filter(|s| *s < to && (*s >= from || *s + duration > from))
+            .map(|s| occurrence(s, s + duration))
+            .collect()
+    }
+
+}
+
+impl<'a> Deref for Event<'a> {
+
+    type Target = FileLockEntry<'a>;
+
+    fn deref(&self) -> &FileLockEntry<'a> {
+        &self.0
+    }
+
+}
+
+#[derive(Debug)]
+pub struct EventIterator<'a> {
+    store: &'a Store,
+    iditer: StoreIdIterator,
+}
+
+impl<'a> EventIterator<'a> {
+
+    pub fn new(store: &'a Store, iditer: StoreIdIterator) -> EventIterator<'a> {
+        EventIterator {
+            store: store,
+            iditer: iditer,
+        }
+    }
+
+}
+
+impl<'a> Iterator for EventIterator<'a> {
+    type Item = Result<Event<'a>>;
+
+    fn next(&mut self) -> Option<Result<Event<'a>>> {
+        self.iditer
+            .next()
+            .map(|id| self.store.retrieve(id).map(Event).map_err_into(CEK::StoreReadError))
+    }
+
+}
+
+/// Get all occurrences of all events which overlap the time from `from` to `to`, sorted by
+/// their start
+///
+/// Occurrences of recurring events which are overridden by another event (with a RECURRENCE-ID)
+/// are left out. Events with recurrence rules which are not supported are left out, too.
+pub fn agenda(store: &Store, from: NaiveDateTime, to: NaiveDateTime) -> Result<Vec<Occurrence>> {
+    let mut occurrences = vec![];
+    let mut overridden  = BTreeSet::new();
+
+    for event in try!(Event::all(store)) {
+        let event = try!(event);
+        if let Some(recurrence_id) = try!(event.recurrence_id()) {
+            overridden.insert((try!(event.uid()), recurrence_id));
+        }
+
+        match event.occurrences(from, to) {
+            Ok(o)  => occurrences.push((try!(event.uid()), try!(event.recurrence_id()).is_some(), o)),
+            Err(e) => warn!("Cannot compute the occurrences of {}: {}", event.get_location(), e),
+        }
+    }
+
+    let mut occurrences = occurrences
+        .into_iter()
+        .flat_map(|(uid, is_override, o)| {
+            let overridden = &overridden;
+            o.into_iter().filter(move |o| is_override || !overridden.contains(&(uid.clone(), o.start)))
+        })
+        .collect::<Vec<_>>();
+
+    occurrences.sort_by(|a, b| (a.start, &a.summary).cmp(&(b.start, &b.summary)));
+    Ok(occurrences)
+}
+
+/// Export the events to an iCalendar file
+pub fn export<'a, 'b: 'a, I: Iterator<Item = &'a Event<'b>>>(store: &Store, events: I) -> Result<String> {
+    let mut zones = BTreeSet::new();
+    let mut raws  = vec![];
+    for event in events {
+        zones.extend(try!(event.timezones()));
+        raws.push(try!(event.raw_component(store)));
+    }
+    Ok(::ical::to_calendar(zones.iter().chain(raws.iter()).map(|s| &s[..])))
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::PathBuf;
+
+    use chrono::TimeZone;
+    use chrono::naive::date::NaiveDate;
+    use chrono::naive::datetime::NaiveDateTime;
+    use chrono::offset::local::Local;
+    use chrono::offset::utc::UTC;
+
+    use libimagstore::store::Store;
+    use libimagstore::file_abstraction::InMemoryBackend;
+
+    use super::*;
+
+    fn get_store() -> Store {
+        Store::new(PathBuf::from("/"), None, Box::new(InMemoryBackend::new())).unwrap()
+    }
+
+    fn dt(d: u32, h: u32) -> NaiveDateTime {
+        NaiveDate::from_ymd(2016, 10, d).and_hms(h, 0, 0)
+    }
+
+    const CALENDAR : &'static str = "BEGIN:VCALENDAR\n\
+VERSION:2.0\n\
+BEGIN:VEVENT\n\
+UID:standup\n\
+SUMMARY:Standup\n\
+DTSTART:20161017T090000\n\
+DTEND:20161017T091500\n\
+RRULE:FREQ=DAILY;COUNT=5\n\
+EXDATE:20161018T090000\n\
+END:VEVENT\n\
+BEGIN:VEVENT\n\
+UID:standup\n\
+RECURRENCE-ID:20161019T090000\n\
+SUMMARY:Late standup\n\
+DTSTART:20161019T110000\n\
+DURATION:PT15M\n\
+END:VEVENT\n\
+BEGIN:VEVENT\n\
+UID:holiday\n\
+SUMMARY:Holiday\n\
+DTSTART;VALUE=DATE:20161020\n\
+END:VEVENT\n\
+BEGIN:VTODO\n\
+UID:report\n\
+SUMMARY:Write report\n\
+DUE:20161021T170000\n\
+END:VTODO\n\
+END:VCALENDAR\n";
+
+    #[test]
+    fn test_import_deduplicates_by_uid() {
+        let store = get_store();
+
+        let imported = Event::import_str(&store, CALENDAR, None).unwrap();
+        assert_eq!(imported.len(), 4);
+        assert!(imported.iter().all(|&(_, a)| a == ImportAction::Created));
+
+        let imported = Event::import_str(&store, CALENDAR, None).unwrap();
+        assert!(imported.iter().all(|&(_, a)| a == ImportAction::Unchanged));
+
+        let changed  = CALENDAR.replace("SUMMARY:Holiday", "SUMMARY:Vacation");
+        let imported = Event::import_str(&store, &changed, None).unwrap();
+        assert_eq!(imported.iter().filter(|&&(_, a)| a == ImportAction::Updated).count(), 1);
+
+        let holiday = Event::get(&store, "holiday").unwrap().unwrap();
+        assert_eq!(holiday.summary().unwrap(), Some(String::from("Vacation")));
+        assert!(holiday.is_all_day().unwrap());
+        assert_eq!(holiday.end().unwrap(), Some(dt(21, 0)));
+    }
+
+    #[test]
+    fn test_agenda() {
+        let store = get_store();
+        let _     = Event::import_str(&store, CALENDAR, None).unwrap();
+
+        let occurrences = agenda(&store, dt(17, 0), dt(24, 0)).unwrap();
+        let starts      = occurrences.iter().map(|o| (o.start, o.summary.clone().unwrap())).collect::<Vec<_>>();
+        assert_eq!(starts, vec![
+            (dt(17, 9), String::from("Standup")),
+            (dt(19, 11), String::from("Late standup")),
+            (dt(20, 0), String::from("Holiday")),
+            (dt(20, 9), String::from("Standup")),
+            (dt(21, 9), String::from("Standup")),
+            (dt(21, 17), String::from("Write report")),
+        ]);
+
+        let occurrences = agenda(&store, dt(20, 12), dt(21, 0)).unwrap();
+        assert_eq!(occurrences.len(), 1);
+        assert_eq!(occurrences[0].summary, Some(String::from("Holiday")));
+    }
+
+    #[test]
+    fn test_export() {
+        let store = get_store();
+        let _     = Event::import_str(&store, CALENDAR, None).unwrap();
+
+        let holiday  = Event::get(&store, "holiday").unwrap().unwrap();
+        let exported = export(&store, vec![&holiday].into_iter()).unwrap();
+        let imported = Event::import_str(&store, &exported, None).unwrap();
+        assert_eq!(imported.len(), 1);
+        assert_eq!(imported[0].1, ImportAction::Unchanged);
+    }
+
+    const ZONED : &'static str = "BEGIN:VCALENDAR\n\
+VERSION:2.0\n\
+BEGIN:VTIMEZONE\n\
+TZID:Europe/Berlin\n\
+BEGIN:DAYLIGHT\n\
+TZOFFSETFROM:+0100\n\
+TZOFFSETTO:+0200\n\
+DTSTART:19810329T020000\n\
+RRULE:FREQ=YEARLY;BYMONTH=3;BYDAY=-1SU\n\
+END:DAYLIGHT\n\
+BEGIN:STANDARD\n\
+TZOFFSETFROM:+0200\n\
+TZOFFSETTO:+0100\n\
+DTSTART:19961027T030000\n\
+RRULE:FREQ=YEARLY;BYMONTH=10;BYDAY=-1SU\n\
+END:STANDARD\n\
+END:VTIMEZONE\n\
+BEGIN:VEVENT\n\
+UID:meeting\n\
+SUMMARY:Meeting\n\
+DTSTART;TZID=Europe/Berlin:20161017T090000\n\
+DURATION:PT1H\n\
+END:VEVENT\n\
+END:VCALENDAR\n";
+
+    #[test]
+    fn test_import_resolves_tzid() {
+        let store = get_store();
+        let _     = Event::import_str(&store, ZONED, None).unwrap();
+
+        let utc     = NaiveDate::from_ymd(2016, 10, 17).and_hms(7, 0, 0);
+        let meeting = Event::get(&store, "meeting").unwrap().unwrap();
+        let local   = UTC.from_utc_datetime(&utc).with_timezone(&Local).naive_local();
+        assert_eq!(meeting.start().unwrap(), Some(local));
+
+        // The time zone is exported with the event, so it can be imported again
+        let exported = export(&store, vec![&meeting].into_iter()).unwrap();
+        assert!(exported.contains("BEGIN:VTIMEZONE"));
+        let imported = Event::import_str(&store, &exported, None).unwrap();
+        assert_eq!(imported[0].1, ImportAction::Unchanged);
+    }
+
+    #[test]
+    fn test_import_unknown_tzid_fails() {
+        let store   = get_store();
+        let unknown = ZONED.replace("DTSTART;TZID=Europe/Berlin", "DTSTART;TZID=Mars/Olympus");
+        assert!(Event::import_str(&store, &unknown, None).is_err());
+    }
+
+}
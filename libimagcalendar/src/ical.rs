@@ -0,0 +1,479 @@
+//
+// imag - the personal information management suite for the commandline
+// Copyright (C) 2015, 2016 Matthias Beyer <mail@beyermatthias.de> and contributors
+//
+// This library is free software; you can redistribute it and/or
+// modify it under the terms of the GNU Lesser General Public
+// License as published by the Free Software Foundation; version
+// 2.1 of the License.
+//
+// This library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+// Lesser General Public License for more details.
+//
+// You should have received a copy of the GNU Lesser General Public
+// License along with this library; if not, write to the Free Software
+// Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA  02110-1301  USA
+//
+
+//! A small parser for iCalendar (RFC 5545) files
+//!
+//! Only the structure of the file is parsed: components with their properties and
+//! subcomponents. The original text of each component is kept, so it can be exported again
+//! without changes.
+
+use chrono::Duration;
+use chrono::TimeZone;
+use chrono::naive::date::NaiveDate;
+use chrono::naive::datetime::NaiveDateTime;
+use chrono::offset::local::Local;
+use chrono::offset::utc::UTC;
+
+use error::CalendarErrorKind as CEK;
+use result::Result;
+use timezone::TimeZones;
+
+use libimagerror::into::IntoError;
+use libimagutil::quoted::{find_unquoted, split_unquoted};
+
+/// A property (content line) of a component, as in `DTSTART;TZID=Europe/Berlin:20161017T090000`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Property {
+    pub name: String,
+    pub params: Vec<(String, String)>,
+    pub value: String,
+}
+
+impl Property {
+
+    /// Parse an unfolded content line
+    pub fn parse(line: &str) -> Result<Property> {
+        let colon = try!(find_unquoted(line, ':').ok_or(CEK::ICalParseError.into_error()));
+        let (head, value) = (&line[..colon], &line[(colon + 1)..]);
+
+        let mut parts = split_unquoted(head, ';').into_iter();
+        let name      = try!(parts.next().ok_or(CEK::ICalParseError.into_error()));
+        if name.is_empty() {
+            return Err(CEK::ICalParseError.into_error());
+        }
+
+        let mut params = vec![];
+        for param in parts {
+            let i = try!(param.find('=').ok_or(CEK::ICalParseError.into_error()));
+            params.push((param[..i].to_uppercase(), String::from(param[(i + 1)..].trim_matches('"'))));
+        }
+
+        Ok(Property {
+            name: name.to_uppercase(),
+            params: params,
+            value: String::from(value),
+        })
+    }
+
+    /// Get the value of the parameter `name`
+    pub fn param(&self, name: &str) -> Option<&str> {
+        self.params
+            .iter()
+            .find(|&&(ref n, _)| n == name)
+            .map(|&(_, ref v)| &v[..])
+    }
+
+    /// The value as plain text
+    pub fn text(&self) -> String {
+        unescape(&self.value)
+    }
+
+    /// The value as date-time, see `parse_datetime()`
+    ///
+    /// If the property has a TZID parameter, the value is converted from this time zone to local
+    /// time. The zone has to be one of `zones`.
+    pub fn datetime(&self, zones: &TimeZones) -> Result<(NaiveDateTime, bool)> {
+        match self.param("TZID") {
+            Some(tzid) => parse_zoned_datetime(&self.value, tzid, zones),
+            None       => parse_datetime(&self.value),
+        }
+    }
+
+    /// The values of a property with a list of date-times, like EXDATE
+    pub fn datetimes(&self, zones: &TimeZones) -> Result<Vec<NaiveDateTime>> {
+        self.value
+            .split(',')
+            .map(|v| match self.param("TZID") {
+                Some(tzid) => parse_zoned_datetime(v, tzid, zones),
+                None       => parse_datetime(v),
+            })
+            .map(|r| r.map(|(dt, _)| dt))
+            .collect()
+    }
+
+}
+
+/// A component, like VCALENDAR, VEVENT or VTODO, together with its original text
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Component {
+    name: String,
+    raw: String,
+    properties: Vec<Property>,
+    components: Vec<Component>,
+}
+
+impl Component {
+
+    /// Parse all top level components in `text`, usually one VCALENDAR
+    ///
+    /// The original text of each component, from its "BEGIN" line up to and including the line
+    /// ending after its "END" line, is kept.
+    pub fn parse_all(text: &str) -> Result<Vec<Component>> {
+        // (component, offset of the BEGIN line)
+        let mut open : Vec<(Component, usize)> = vec![];
+        let mut top  = vec![];
+
+        for (start, end, line) in logical_lines(text) {
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            let property = try!(Property::parse(&line));
+            let name     = property.name.clone();
+            match &name[..] {
+                "BEGIN" => {
+                    let component = Component {
+                        name: property.value.trim().to_uppercase(),
+                        raw: String::new(),
+                        properties: vec![],
+                        components: vec![],
+                    };
+                    open.push((component, start));
+                },
+
+                "END" => {
+                    let (mut component, begin) = try!(open.pop().ok_or(CEK::ICalParseError.into_error()));
+                    if component.name != property.value.trim().to_uppercase() {
+                        return Err(CEK::ICalParseError.into_error());
+                    }
+
+                    component.raw = String::from(&text[begin..end]);
+                    match open.last_mut() {
+                        Some(&mut (ref mut parent, _)) => parent.components.push(component),
+                        None                           => top.push(component),
+                    }
+                },
+
+                _ => match open.last_mut() {
+                    Some(&mut (ref mut component, _)) => component.properties.push(property),
+                    None => return Err(CEK::ICalParseError.into_error()),
+                },
+            }
+        }
+
+        if !open.is_empty() {
+            return Err(CEK::ICalParseError.into_error());
+        }
+
+        Ok(top)
+    }
+
+    /// The name of the component, uppercased
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The original text of the component
+    pub fn raw(&self) -> &str {
+        &self.raw
+    }
+
+    pub fn properties(&self) -> &Vec<Property> {
+        &self.properties
+    }
+
+    pub fn components(&self) -> &Vec<Component> {
+        &self.components
+    }
+
+    /// Get the first property with the name `name`
+    pub fn get(&self, name: &str) -> Option<&Property> {
+        self.properties.iter().find(|p| p.name == name)
+    }
+
+    /// Get all properties with the name `name`
+    pub fn get_all(&self, name: &str) -> Vec<&Property> {
+        self.properties.iter().filter(|p| p.name == name).collect()
+    }
+
+    /// Get the value of the first property with the name `name` as plain text
+    pub fn get_text(&self, name: &str) -> Option<String> {
+        self.get(name).map(Property::text)
+    }
+
+    /// Get all VEVENT and VTODO components, searching recursively
+    pub fn events(&self) -> Vec<&Component> {
+        let mut events = vec![];
+        for component in &self.components {
+            match component.name() {
+                "VEVENT" | "VTODO" => events.push(component),
+                _                  => events.extend(component.events()),
+            }
+        }
+        events
+    }
+
+}
+
+/// Split `text` into unfolded lines, returns the offsets of the original text of each line
+/// (including the line ending) together with the unfolded line
+fn logical_lines(text: &str) -> Vec<(usize, usize, String)> {
+    let mut lines : Vec<(usize, usize, String)> = vec![];
+    let mut pos = 0;
+
+    for line in text.split('\n') {
+        let end  = ::std::cmp::min(pos + line.len() + 1, text.len());
+        let trim = line.trim_right_matches('\r');
+
+        if (trim.starts_with(" ") || trim.starts_with("\t")) && !lines.is_empty() {
+            let last = lines.len() - 1;
+            lines[last].1 = end;
+            lines[last].2.push_str(&trim[1..]);
+        } else {
+            lines.push((pos, end, String::from(trim)));
+        }
+
+        pos = end;
+    }
+
+    lines
+}
+
+/// Parse a DATE or DATE-TIME value without evaluating its time zone
+///
+/// Returns the date-time, whether the value was a DATE (an all day value) and whether it was a
+/// UTC value (with a trailing "Z").
+pub fn parse_datetime_value(value: &str) -> Result<(NaiveDateTime, bool, bool)> {
+    let value = value.trim();
+
+    if value.len() == 8 {
+        return NaiveDate::parse_from_str(value, "%Y%m%d")
+            .map(|d| (d.and_hms(0, 0, 0), true, false))
+            .map_err(|e| CEK::DateTimeParseError.into_error_with_cause(Box::new(e)));
+    }
+
+    let (value, utc) = if value.ends_with("Z") {
+        (&value[..(value.len() - 1)], true)
+    } else {
+        (value, false)
+    };
+
+    NaiveDateTime::parse_from_str(value, "%Y%m%dT%H%M%S")
+        .map(|dt| (dt, false, utc))
+        .map_err(|e| CEK::DateTimeParseError.into_error_with_cause(Box::new(e)))
+}
+
+/// Parse a DATE or DATE-TIME value
+///
+/// Returns the date-time and whether the value was a DATE (an all day value). UTC values (with
+/// a trailing "Z") are converted to local time, all other values are floating and taken as they
+/// are. Values with a TZID parameter have to be parsed with `Property::datetime()`.
+pub fn parse_datetime(value: &str) -> Result<(NaiveDateTime, bool)> {
+    parse_datetime_value(value).map(|(dt, all_day, utc)| if utc {
+        (utc_to_local(dt), all_day)
+    } else {
+        (dt, all_day)
+    })
+}
+
+/// Parse a DATE or DATE-TIME value in the time zone `tzid` and convert it to local time
+///
+/// DATE values and UTC values are not affected by the time zone.
+fn parse_zoned_datetime(value: &str, tzid: &str, zones: &TimeZones) -> Result<(NaiveDateTime, bool)> {
+    let (dt, all_day, utc) = try!(parse_datetime_value(value));
+    if all_day {
+        Ok((dt, true))
+    } else if utc {
+        Ok((utc_to_local(dt), false))
+    } else {
+        zones.to_utc(tzid, dt).map(|dt| (utc_to_local(dt), false))
+    }
+}
+
+fn utc_to_local(dt: NaiveDateTime) -> NaiveDateTime {
+    UTC.from_utc_datetime(&dt).with_timezone(&Local).naive_local()
+}
+
+/// Parse a DURATION value, like "PT1H30M", "P1D" or "-P1W"
+pub fn parse_duration(value: &str) -> Result<Duration> {
+    let value = value.trim();
+    let (negative, value) = match value.chars().next() {
+        Some('-') => (true, &value[1..]),
+        Some('+') => (false, &value[1..]),
+        _         => (false, value),
+    };
+
+    if !value.starts_with("P") || value.len() < 3 {
+        return Err(CEK::DateTimeParseError.into_error());
+    }
+
+    let mut duration = Duration::zero();
+    let mut number   = String::new();
+    for c in value[1..].chars() {
+        if c.is_digit(10) {
+            number.push(c);
+            continue;
+        }
+
+        if c == 'T' {
+            continue;
+        }
+
+        let n : i64 = try!(number.parse().map_err(|_| CEK::DateTimeParseError.into_error()));
+        duration = duration + match c {
+            'W' => Duration::weeks(n),
+            'D' => Duration::days(n),
+            'H' => Duration::hours(n),
+            'M' => Duration::minutes(n),
+            'S' => Duration::seconds(n),
+            _   => return Err(CEK::DateTimeParseError.into_error()),
+        };
+        number.clear();
+    }
+
+    if !number.is_empty() {
+        return Err(CEK::DateTimeParseError.into_error());
+    }
+
+    Ok(if negative { -duration } else { duration })
+}
+
+/// Unescape a text value: `\n`, `\,`, `\;` and `\\`
+pub fn unescape(s: &str) -> String {
+    let mut result = String::with_capacity(s.len());
+    let mut chars  = s.chars();
+    while let Some(c) = chars.next() {
+        if c == '\\' {
+            match chars.next() {
+                Some('n') | Some('N') => result.push('\n'),
+                Some(c)               => result.push(c),
+                None                  => result.push('\\'),
+            }
+        } else {
+            result.push(c);
+        }
+    }
+    result
+}
+
+/// Wrap the components in `raws` into a VCALENDAR
+pub fn to_calendar<'a, I: Iterator<Item = &'a str>>(raws: I) -> String {
+    let mut calendar = String::from("BEGIN:VCALENDAR\r\nVERSION:2.0\r\nPRODID:-//imag//imag-calendar//EN\r\n");
+    for raw in raws {
+        calendar.push_str(raw);
+        if !raw.ends_with("\n") {
+            calendar.push_str("\r\n");
+        }
+    }
+    calendar.push_str("END:VCALENDAR\r\n");
+    calendar
+}
+
+#[cfg(test)]
+mod test {
+    use chrono::Duration;
+    use chrono::naive::date::NaiveDate;
+
+    use timezone::TimeZones;
+
+    use super::*;
+
+    const CALENDAR : &'static str = "BEGIN:VCALENDAR\r\n\
+VERSION:2.0\r\n\
+BEGIN:VEVENT\r\n\
+UID:standup@example.com\r\n\
+DTSTART;TZID=\"Europe/Berlin\":20161017T090000\r\n\
+DURATION:PT15M\r\n\
+SUMMARY:Standup\\, daily\r\n\
+DESCRIPTION:A very long description which is folded \r\n over two lines\r\n\
+RRULE:FREQ=DAILY;COUNT=5\r\n\
+BEGIN:VALARM\r\n\
+ACTION:DISPLAY\r\n\
+END:VALARM\r\n\
+END:VEVENT\r\n\
+BEGIN:VTODO\r\n\
+UID:todo@example.com\r\n\
+DUE;VALUE=DATE:20161020\r\n\
+END:VTODO\r\n\
+END:VCALENDAR\r\n";
+
+    #[test]
+    fn test_parse() {
+        let top = Component::parse_all(CALENDAR).unwrap();
+        assert_eq!(top.len(), 1);
+        assert_eq!(top[0].name(), "VCALENDAR");
+        assert_eq!(top[0].raw(), CALENDAR);
+
+        let events = top[0].events();
+        assert_eq!(events.len(), 2);
+
+        let standup = events[0];
+        assert_eq!(standup.name(), "VEVENT");
+        assert_eq!(standup.get_text("SUMMARY"), Some(String::from("Standup, daily")));
+        assert_eq!(standup.get_text("DESCRIPTION"),
+                   Some(String::from("A very long description which is folded over two lines")));
+        assert_eq!(standup.get("DTSTART").unwrap().param("TZID"), Some("Europe/Berlin"));
+        assert_eq!(standup.components().len(), 1);
+        assert!(standup.raw().starts_with("BEGIN:VEVENT\r\n"));
+        assert!(standup.raw().ends_with("END:VEVENT\r\n"));
+
+        assert_eq!(events[1].name(), "VTODO");
+    }
+
+    #[test]
+    fn test_parse_unbalanced() {
+        assert!(Component::parse_all("BEGIN:VCALENDAR\nBEGIN:VEVENT\nEND:VCALENDAR\n").is_err());
+        assert!(Component::parse_all("BEGIN:VCALENDAR\n").is_err());
+        assert!(Component::parse_all("UID:foo\n").is_err());
+    }
+
+    #[test]
+    fn test_parse_datetime() {
+        let (dt, all_day) = parse_datetime("20161017").unwrap();
+        assert!(all_day);
+        assert_eq!(dt, NaiveDate::from_ymd(2016, 10, 17).and_hms(0, 0, 0));
+
+        let (dt, all_day) = parse_datetime("20161017T093000").unwrap();
+        assert!(!all_day);
+        assert_eq!(dt, NaiveDate::from_ymd(2016, 10, 17).and_hms(9, 30, 0));
+
+        assert!(parse_datetime("2016-10-17").is_err());
+    }
+
+    #[test]
+    fn test_datetime_with_tzid() {
+        let zones = TimeZones::empty();
+
+        let p = Property::parse("DTSTART;TZID=UTC:20161017T093000").unwrap();
+        assert_eq!(p.datetime(&zones).unwrap(), parse_datetime("20161017T093000Z").unwrap());
+
+        let p = Property::parse("DTSTART;TZID=Europe/Berlin;VALUE=DATE:20161017").unwrap();
+        assert_eq!(p.datetime(&zones).unwrap(), (NaiveDate::from_ymd(2016, 10, 17).and_hms(0, 0, 0), true));
+
+        let p = Property::parse("DTSTART;TZID=Europe/Berlin:20161017T093000").unwrap();
+        assert!(p.datetime(&zones).is_err());
+    }
+
+    #[test]
+    fn test_parse_duration() {
+        assert_eq!(parse_duration("PT1H30M").unwrap(), Duration::minutes(90));
+        assert_eq!(parse_duration("P1D").unwrap(), Duration::days(1));
+        assert_eq!(parse_duration("-P2W").unwrap(), -Duration::weeks(2));
+        assert!(parse_duration("1H").is_err());
+        assert!(parse_duration("PT1").is_err());
+    }
+
+    #[test]
+    fn test_to_calendar() {
+        let calendar = to_calendar(vec!["BEGIN:VEVENT\r\nUID:a\r\nEND:VEVENT"].into_iter());
+        let top = Component::parse_all(&calendar).unwrap();
+        assert_eq!(top[0].events().len(), 1);
+    }
+
+}
@@ -0,0 +1,283 @@
+//
+// imag - the personal information management suite for the commandline
+// Copyright (C) 2015, 2016 Matthias Beyer <mail@beyermatthias.de> and contributors
+//
+// This library is free software; you can redistribute it and/or
+// modify it under the terms of the GNU Lesser General Public
+// License as published by the Free Software Foundation; version
+// 2.1 of the License.
+//
+// This library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+// Lesser General Public License for more details.
+//
+// You should have received a copy of the GNU Lesser General Public
+// License along with this library; if not, write to the Free Software
+// Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA  02110-1301  USA
+//
+
+use std::str::FromStr;
+
+use chrono::Datelike;
+use chrono::Duration;
+use chrono::Weekday;
+use chrono::naive::date::NaiveDate;
+use chrono::naive::datetime::NaiveDateTime;
+
+use ical::parse_datetime;
+use error::CalendarErrorKind as CEK;
+use result::Result;
+
+use libimagerror::into::IntoError;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Frequency {
+    Daily,
+    Weekly,
+    Monthly,
+    Yearly,
+}
+
+/// A recurrence rule (RRULE)
+///
+/// Supported are the frequencies DAILY, WEEKLY, MONTHLY and YEARLY together with INTERVAL,
+/// COUNT, UNTIL and, for weekly rules, BYDAY. Other rules result in an
+/// `UnsupportedRecurrence` error.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Recurrence {
+    frequency: Frequency,
+    interval: u32,
+    count: Option<u32>,
+    until: Option<NaiveDateTime>,
+    by_day: Vec<Weekday>,
+}
+
+impl Recurrence {
+
+    /// Parse the value of a RRULE property, like "FREQ=WEEKLY;BYDAY=MO,WE;COUNT=10"
+    pub fn parse(rule: &str) -> Result<Recurrence> {
+        let mut frequency = None;
+        let mut interval  = 1;
+        let mut count     = None;
+        let mut until     = None;
+        let mut by_day    = vec![];
+
+        for part in rule.trim().split(';').filter(|p| !p.is_empty()) {
+            let i = try!(part.find('=').ok_or(CEK::RecurrenceParseError.into_error()));
+            let (key, value) = (part[..i].to_uppercase(), &part[(i + 1)..]);
+
+            match &key[..] {
+                "FREQ" => frequency = Some(match &value.to_uppercase()[..] {
+                    "DAILY"   => Frequency::Daily,
+                    "WEEKLY"  => Frequency::Weekly,
+                    "MONTHLY" => Frequency::Monthly,
+                    "YEARLY"  => Frequency::Yearly,
+                    _         => return Err(CEK::UnsupportedRecurrence.into_error()),
+                }),
+                "INTERVAL" => interval = try!(parse_number(value)),
+                "COUNT"    => count    = Some(try!(parse_number(value))),
+                "UNTIL"    => {
+                    let (dt, all_day) = try!(parse_datetime(value));
+                    // An UNTIL date includes the whole day
+                    until = Some(if all_day { dt.date().and_hms(23, 59, 59) } else { dt });
+                },
+                "BYDAY" => for day in value.split(',') {
+                    by_day.push(try!(parse_weekday(day)));
+                },
+                "WKST" => {},
+                _      => return Err(CEK::UnsupportedRecurrence.into_error()),
+            }
+        }
+
+        let frequency = try!(frequency.ok_or(CEK::RecurrenceParseError.into_error()));
+        if interval == 0 {
+            return Err(CEK::RecurrenceParseError.into_error());
+        }
+        if !by_day.is_empty() && frequency != Frequency::Weekly {
+            return Err(CEK::UnsupportedRecurrence.into_error());
+        }
+        by_day.sort_by_key(|d| d.num_days_from_monday());
+        by_day.dedup();
+
+        Ok(Recurrence {
+            frequency: frequency,
+            interval: interval,
+            count: count,
+            until: until,
+            by_day: by_day,
+        })
+    }
+
+    pub fn frequency(&self) -> Frequency {
+        self.frequency
+    }
+
+    pub fn interval(&self) -> u32 {
+        self.interval
+    }
+
+    pub fn count(&self) -> Option<u32> {
+        self.count
+    }
+
+    pub fn until(&self) -> Option<NaiveDateTime> {
+        self.until
+    }
+
+    /// Get the starts of all occurrences of an event starting at `start` which begin before `end`
+    pub fn occurrences(&self, start: NaiveDateTime, end: NaiveDateTime) -> Vec<NaiveDateTime> {
+        let mut occurrences = vec![];
+        let mut period      = 0;
+
+        loop {
+            let (anchor, candidates) = self.period(start, period);
+            if anchor.and_hms(0, 0, 0) >= end || self.until.map(|u| anchor.and_hms(0, 0, 0) > u).unwrap_or(false) {
+                return occurrences;
+            }
+
+            for candidate in candidates.into_iter().filter(|c| *c >= start) {
+                if candidate >= end || self.until.map(|u| candidate > u).unwrap_or(false) {
+                    return occurrences;
+                }
+
+                occurrences.push(candidate);
+                if self.count.map(|c| occurrences.len() >= c as usize).unwrap_or(false) {
+                    return occurrences;
+                }
+            }
+
+            period += 1;
+        }
+    }
+
+    /// Get the first day of the `n`th period of the rule together with the occurrences in it
+    fn period(&self, start: NaiveDateTime, n: i64) -> (NaiveDate, Vec<NaiveDateTime>) {
+        let step = n * self.interval as i64;
+        let date = start.date();
+        let time = start.time();
+
+        match self.frequency {
+            Frequency::Daily => {
+                let day = date + Duration::days(step);
+                (day, vec![day.and_time(time)])
+            },
+
+            Frequency::Weekly => {
+                let monday = date - Duration::days(date.weekday().num_days_from_monday() as i64);
+                let week   = monday + Duration::weeks(step);
+                let days   = if self.by_day.is_empty() {
+                    vec![date.weekday()]
+                } else {
+                    self.by_day.clone()
+                };
+
+                let occurrences = days
+                    .into_iter()
+                    .map(|d| (week + Duration::days(d.num_days_from_monday() as i64)).and_time(time))
+                    .collect();
+                (week, occurrences)
+            },
+
+            Frequency::Monthly => {
+                let index = date.year() as i64 * 12 + date.month0() as i64 + step;
+                let (year, month) = ((index / 12) as i32, (index % 12) as u32 + 1);
+
+                // Months without this day are skipped
+                let occurrences = NaiveDate::from_ymd_opt(year, month, date.day())
+                    .map(|d| d.and_time(time))
+                    .into_iter()
+                    .collect();
+                (NaiveDate::from_ymd(year, month, 1), occurrences)
+            },
+
+            Frequency::Yearly => {
+                let year = date.year() + step as i32;
+
+                // February 29th only occurs in leap years
+                let occurrences = NaiveDate::from_ymd_opt(year, date.month(), date.day())
+                    .map(|d| d.and_time(time))
+                    .into_iter()
+                    .collect();
+                (NaiveDate::from_ymd(year, 1, 1), occurrences)
+            },
+        }
+    }
+
+}
+
+fn parse_number(s: &str) -> Result<u32> {
+    FromStr::from_str(s).map_err(|_| CEK::RecurrenceParseError.into_error())
+}
+
+/// Parse a weekday, like "MO"
+pub fn parse_weekday(s: &str) -> Result<Weekday> {
+    match &s.trim().to_uppercase()[..] {
+        "MO" => Ok(Weekday::Mon),
+        "TU" => Ok(Weekday::Tue),
+        "WE" => Ok(Weekday::Wed),
+        "TH" => Ok(Weekday::Thu),
+        "FR" => Ok(Weekday::Fri),
+        "SA" => Ok(Weekday::Sat),
+        "SU" => Ok(Weekday::Sun),
+        // Like "1MO" for "the first monday"
+        _    => Err(CEK::UnsupportedRecurrence.into_error()),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use chrono::naive::date::NaiveDate;
+    use chrono::naive::datetime::NaiveDateTime;
+
+    use super::*;
+
+    fn dt(y: i32, m: u32, d: u32) -> NaiveDateTime {
+        NaiveDate::from_ymd(y, m, d).and_hms(9, 0, 0)
+    }
+
+    #[test]
+    fn test_daily_count() {
+        let r = Recurrence::parse("FREQ=DAILY;INTERVAL=2;COUNT=3").unwrap();
+        assert_eq!(r.occurrences(dt(2016, 10, 1), dt(2017, 1, 1)),
+                   vec![dt(2016, 10, 1), dt(2016, 10, 3), dt(2016, 10, 5)]);
+    }
+
+    #[test]
+    fn test_weekly_by_day() {
+        // 2016-10-17 is a monday
+        let r = Recurrence::parse("FREQ=WEEKLY;BYDAY=WE,MO").unwrap();
+        assert_eq!(r.occurrences(dt(2016, 10, 17), dt(2016, 10, 27)),
+                   vec![dt(2016, 10, 17), dt(2016, 10, 19), dt(2016, 10, 24), dt(2016, 10, 26)]);
+
+        // Days before the start are not included
+        assert_eq!(r.occurrences(dt(2016, 10, 18), dt(2016, 10, 25)),
+                   vec![dt(2016, 10, 19), dt(2016, 10, 24)]);
+    }
+
+    #[test]
+    fn test_monthly_skips_missing_days() {
+        let r = Recurrence::parse("FREQ=MONTHLY;COUNT=3").unwrap();
+        assert_eq!(r.occurrences(dt(2016, 1, 31), dt(2017, 1, 1)),
+                   vec![dt(2016, 1, 31), dt(2016, 3, 31), dt(2016, 5, 31)]);
+    }
+
+    #[test]
+    fn test_yearly_until() {
+        let r = Recurrence::parse("FREQ=YEARLY;UNTIL=20180101").unwrap();
+        assert_eq!(r.occurrences(dt(2016, 2, 29), dt(2030, 1, 1)), vec![dt(2016, 2, 29)]);
+
+        let r = Recurrence::parse("FREQ=YEARLY;UNTIL=20181017").unwrap();
+        assert_eq!(r.occurrences(dt(2016, 10, 17), dt(2030, 1, 1)),
+                   vec![dt(2016, 10, 17), dt(2017, 10, 17), dt(2018, 10, 17)]);
+    }
+
+    #[test]
+    fn test_unsupported() {
+        assert!(Recurrence::parse("FREQ=MONTHLY;BYDAY=1MO").is_err());
+        assert!(Recurrence::parse("FREQ=DAILY;BYHOUR=9").is_err());
+        assert!(Recurrence::parse("FREQ=SECONDLY").is_err());
+        assert!(Recurrence::parse("COUNT=3").is_err());
+    }
+
+}
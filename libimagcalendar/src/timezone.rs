@@ -0,0 +1,353 @@
+//
+// imag - the personal information management suite for the commandline
+// Copyright (C) 2015, 2016 Matthias Beyer <mail@beyermatthias.de> and contributors
+//
+// This library is free software; you can redistribute it and/or
+// modify it under the terms of the GNU Lesser General Public
+// License as published by the Free Software Foundation; version
+// 2.1 of the License.
+//
+// This library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+// Lesser General Public License for more details.
+//
+// You should have received a copy of the GNU Lesser General Public
+// License along with this library; if not, write to the Free Software
+// Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA  02110-1301  USA
+//
+
+//! Time zones defined by the VTIMEZONE components of a calendar
+//!
+//! A date-time with a TZID parameter is a local time of the time zone with this id. RFC 5545
+//! requires a calendar to contain a VTIMEZONE component for every TZID it uses, so the zones are
+//! resolved from these components rather than from a time zone database. The observances of a
+//! zone may either have a single onset or a yearly one on the nth weekday of a month, which is
+//! what daylight saving time rules look like. Other rules and unknown zones are errors.
+
+use std::collections::BTreeMap;
+
+use chrono::Datelike;
+use chrono::Duration;
+use chrono::Weekday;
+use chrono::naive::date::NaiveDate;
+use chrono::naive::datetime::NaiveDateTime;
+
+use ical::{Component, parse_datetime_value};
+use recurrence::parse_weekday;
+use error::CalendarErrorKind as CEK;
+use result::Result;
+
+use libimagerror::into::IntoError;
+
+/// Time zone ids which are UTC, even if the calendar does not define them
+const UTC_IDS : &'static [&'static str] = &["UTC", "GMT", "Etc/UTC", "Etc/GMT", "Z"];
+
+/// The VTIMEZONE components of a calendar, by their TZID
+#[derive(Debug, Clone, Default)]
+pub struct TimeZones(BTreeMap<String, Component>);
+
+impl TimeZones {
+
+    /// No time zones, only UTC can be resolved
+    pub fn empty() -> TimeZones {
+        TimeZones::default()
+    }
+
+    /// Get the time zones defined in the VCALENDAR component `calendar`
+    pub fn from_calendar(calendar: &Component) -> TimeZones {
+        let zones = calendar.components()
+            .iter()
+            .filter(|c| c.name() == "VTIMEZONE")
+            .filter_map(|c| c.get_text("TZID").map(|id| (id, c.clone())))
+            .collect();
+
+        TimeZones(zones)
+    }
+
+    /// Get the original text of the VTIMEZONE components of the zones `component` refers to
+    pub fn raws_for(&self, component: &Component) -> Vec<String> {
+        let mut ids = component.properties()
+            .iter()
+            .filter_map(|p| p.param("TZID"))
+            .collect::<Vec<_>>();
+        ids.sort();
+        ids.dedup();
+
+        ids.into_iter()
+            .filter_map(|id| self.0.get(id))
+            .map(|zone| String::from(zone.raw()))
+            .collect()
+    }
+
+    /// Convert `dt`, a local time in the zone `tzid`, to UTC
+    pub fn to_utc(&self, tzid: &str, dt: NaiveDateTime) -> Result<NaiveDateTime> {
+        if UTC_IDS.iter().any(|id| *id == tzid) {
+            return Ok(dt);
+        }
+
+        let zone = try!(self.0.get(tzid).ok_or(CEK::UnknownTimeZone.into_error()));
+
+        // (onset, offset) of the observance in effect at `dt` and of the first observance
+        let mut current : Option<(NaiveDateTime, i32)> = None;
+        let mut first   : Option<(NaiveDateTime, i32)> = None;
+
+        for component in zone.components() {
+            match component.name() {
+                "STANDARD" | "DAYLIGHT" => {},
+                _                       => continue,
+            }
+
+            let observance = try!(Observance::parse(component));
+            if first.map(|(start, _)| observance.start < start).unwrap_or(true) {
+                first = Some((observance.start, observance.offset_from));
+            }
+
+            if let Some(onset) = observance.last_onset(dt) {
+                if current.map(|(o, _)| onset > o).unwrap_or(true) {
+                    current = Some((onset, observance.offset_to));
+                }
+            }
+        }
+
+        // Before the first onset, the offset before this onset is in effect
+        let offset = match (current, first) {
+            (Some((_, offset)), _)    => offset,
+            (None, Some((_, offset))) => offset,
+            (None, None)              => return Err(CEK::UnknownTimeZone.into_error()),
+        };
+
+        Ok(dt - Duration::seconds(offset as i64))
+    }
+
+}
+
+/// A STANDARD or DAYLIGHT component of a time zone
+#[derive(Debug)]
+struct Observance {
+    /// The first onset, in the local time before the onset
+    start: NaiveDateTime,
+    /// UTC offsets before and after each onset, in seconds
+    offset_from: i32,
+    offset_to: i32,
+    rule: Option<YearlyRule>,
+}
+
+/// An onset every year on the nth weekday of a month, like "FREQ=YEARLY;BYMONTH=3;BYDAY=-1SU"
+#[derive(Debug)]
+struct YearlyRule {
+    month: u32,
+    nth: i64,
+    weekday: Weekday,
+    /// In UTC
+    until: Option<NaiveDateTime>,
+}
+
+impl Observance {
+
+    fn parse(component: &Component) -> Result<Observance> {
+        let start  = try!(component.get("DTSTART").ok_or(CEK::ICalParseError.into_error()));
+        let offset = |name: &str| component.get(name)
+            .ok_or(CEK::ICalParseError.into_error())
+            .and_then(|p| parse_offset(&p.value));
+
+        let rule = match component.get("RRULE") {
+            Some(p) => Some(try!(YearlyRule::parse(&p.value))),
+            None    => None,
+        };
+
+        Ok(Observance {
+            start: try!(parse_datetime_value(&start.value)).0,
+            offset_from: try!(offset("TZOFFSETFROM")),
+            offset_to: try!(offset("TZOFFSETTO")),
+            rule: rule,
+        })
+    }
+
+    /// Get the last onset of this observance at or before the local time `dt`
+    fn last_onset(&self, dt: NaiveDateTime) -> Option<NaiveDateTime> {
+        if dt < self.start {
+            return None;
+        }
+
+        let rule = match self.rule {
+            Some(ref rule) => rule,
+            None           => return Some(self.start),
+        };
+
+        let time = self.start.time();
+        (0..2)
+            .filter_map(|back| nth_weekday(dt.year() - back, rule.month, rule.nth, rule.weekday))
+            .map(|date| date.and_time(time))
+            .filter(|onset| *onset >= self.start && *onset <= dt)
+            .filter(|onset| rule.until
+                    .map(|until| *onset - Duration::seconds(self.offset_from as i64) <= until)
+                    .unwrap_or(true))
+            .max()
+            .or(Some(self.start))
+    }
+
+}
+
+impl YearlyRule {
+
+    fn parse(rule: &str) -> Result<YearlyRule> {
+        let mut yearly = false;
+        let mut month  = None;
+        let mut day    = None;
+        let mut until  = None;
+
+        for part in rule.trim().split(';').filter(|p| !p.is_empty()) {
+            let i = try!(part.find('=').ok_or(CEK::RecurrenceParseError.into_error()));
+            let (key, value) = (part[..i].to_uppercase(), &part[(i + 1)..]);
+
+            match &key[..] {
+                "FREQ" if value.to_uppercase() == "YEARLY" => yearly = true,
+                "INTERVAL" if value == "1" => {},
+                "BYMONTH" => month = Some(try!(value.parse::<u32>()
+                    .map_err(|_| CEK::RecurrenceParseError.into_error()))),
+                "BYDAY"   => day   = Some(try!(parse_nth_weekday(value))),
+                "UNTIL"   => until = Some(try!(parse_datetime_value(value)).0),
+                _         => return Err(CEK::UnsupportedRecurrence.into_error()),
+            }
+        }
+
+        match (yearly, month, day) {
+            (true, Some(month), Some((nth, weekday))) => Ok(YearlyRule {
+                month: month,
+                nth: nth,
+                weekday: weekday,
+                until: until,
+            }),
+            _ => Err(CEK::UnsupportedRecurrence.into_error()),
+        }
+    }
+
+}
+
+/// Parse a UTC offset, like "+0100", "-0500" or "+053000", into seconds
+fn parse_offset(value: &str) -> Result<i32> {
+    let value = value.trim();
+    let sign  = match value.chars().next() {
+        Some('+') => 1,
+        Some('-') => -1,
+        _         => return Err(CEK::DateTimeParseError.into_error()),
+    };
+
+    let digits = &value[1..];
+    if !(digits.len() == 4 || digits.len() == 6) || !digits.chars().all(|c| c.is_digit(10)) {
+        return Err(CEK::DateTimeParseError.into_error());
+    }
+
+    // only ASCII digits, so slicing is safe
+    let number  = |s: &str| s.parse::<i32>().map_err(|_| CEK::DateTimeParseError.into_error());
+    let hours   = try!(number(&digits[0..2]));
+    let minutes = try!(number(&digits[2..4]));
+    let seconds = if digits.len() == 6 { try!(number(&digits[4..6])) } else { 0 };
+
+    Ok(sign * (hours * 3600 + minutes * 60 + seconds))
+}
+
+/// Parse a weekday with an ordinal, like "-1SU" (the last sunday) or "2MO" (the second monday)
+fn parse_nth_weekday(value: &str) -> Result<(i64, Weekday)> {
+    let value = value.trim();
+    if value.len() < 3 || !value.bytes().all(|b| b < 128) {
+        return Err(CEK::UnsupportedRecurrence.into_error());
+    }
+
+    let (nth, day) = value.split_at(value.len() - 2);
+    let nth = try!(nth.parse::<i64>().map_err(|_| CEK::UnsupportedRecurrence.into_error()));
+    if nth == 0 || nth.abs() > 5 {
+        return Err(CEK::RecurrenceParseError.into_error());
+    }
+
+    parse_weekday(day).map(|weekday| (nth, weekday))
+}
+
+/// Get the `nth` `weekday` of a month, counted from the end of the month if `nth` is negative
+fn nth_weekday(year: i32, month: u32, nth: i64, weekday: Weekday) -> Option<NaiveDate> {
+    let first = match NaiveDate::from_ymd_opt(year, month, 1) {
+        Some(date) => date,
+        None       => return None,
+    };
+    let next = if month == 12 {
+        NaiveDate::from_ymd(year + 1, 1, 1)
+    } else {
+        NaiveDate::from_ymd(year, month + 1, 1)
+    };
+
+    let day  = |d: Weekday| d.num_days_from_monday() as i64;
+    let date = if nth > 0 {
+        first + Duration::days((7 + day(weekday) - day(first.weekday())) % 7 + (nth - 1) * 7)
+    } else {
+        let last = next - Duration::days(1);
+        last - Duration::days((7 + day(last.weekday()) - day(weekday)) % 7 + (-nth - 1) * 7)
+    };
+
+    if date >= first && date < next {
+        Some(date)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use chrono::Duration;
+    use chrono::Weekday;
+    use chrono::naive::date::NaiveDate;
+
+    use ical::Component;
+
+    use super::*;
+    use super::nth_weekday;
+
+    const CALENDAR : &'static str = "BEGIN:VCALENDAR\r\n\
+VERSION:2.0\r\n\
+BEGIN:VTIMEZONE\r\n\
+TZID:Europe/Berlin\r\n\
+BEGIN:DAYLIGHT\r\n\
+TZOFFSETFROM:+0100\r\n\
+TZOFFSETTO:+0200\r\n\
+DTSTART:19810329T020000\r\n\
+RRULE:FREQ=YEARLY;BYMONTH=3;BYDAY=-1SU\r\n\
+END:DAYLIGHT\r\n\
+BEGIN:STANDARD\r\n\
+TZOFFSETFROM:+0200\r\n\
+TZOFFSETTO:+0100\r\n\
+DTSTART:19961027T030000\r\n\
+RRULE:FREQ=YEARLY;BYMONTH=10;BYDAY=-1SU\r\n\
+END:STANDARD\r\n\
+END:VTIMEZONE\r\n\
+END:VCALENDAR\r\n";
+
+    fn zones() -> TimeZones {
+        TimeZones::from_calendar(&Component::parse_all(CALENDAR).unwrap()[0])
+    }
+
+    #[test]
+    fn test_nth_weekday() {
+        assert_eq!(nth_weekday(2016, 3, -1, Weekday::Sun), Some(NaiveDate::from_ymd(2016, 3, 27)));
+        assert_eq!(nth_weekday(2016, 10, 2, Weekday::Mon), Some(NaiveDate::from_ymd(2016, 10, 10)));
+        assert_eq!(nth_weekday(2016, 2, 5, Weekday::Mon), None);
+    }
+
+    #[test]
+    fn test_to_utc() {
+        let zones  = zones();
+        let summer = NaiveDate::from_ymd(2016, 10, 17).and_hms(9, 0, 0);
+        let winter = NaiveDate::from_ymd(2016, 12, 1).and_hms(9, 0, 0);
+
+        assert_eq!(zones.to_utc("Europe/Berlin", summer).unwrap(), summer - Duration::hours(2));
+        assert_eq!(zones.to_utc("Europe/Berlin", winter).unwrap(), winter - Duration::hours(1));
+        assert_eq!(zones.to_utc("UTC", winter).unwrap(), winter);
+    }
+
+    #[test]
+    fn test_unknown_zone() {
+        let dt = NaiveDate::from_ymd(2016, 10, 17).and_hms(9, 0, 0);
+        assert!(zones().to_utc("America/New_York", dt).is_err());
+        assert!(TimeZones::empty().to_utc("Europe/Berlin", dt).is_err());
+    }
+
+}
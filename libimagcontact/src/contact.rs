@@ -0,0 +1,453 @@
+//
+// imag - the personal information management suite for the commandline
+// Copyright (C) 2015, 2016 Matthias Beyer <mail@beyermatthias.de> and contributors
+//
+// This library is free software; you can redistribute it and/or
+// modify it under the terms of the GNU Lesser General Public
+// License as published by the Free Software Foundation; version
+// 2.1 of the License.
+//
+// This library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+// Lesser General Public License for more details.
+//
+// You should have received a copy of the GNU Lesser General Public
+// License along with this library; if not, write to the Free Software
+// Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA  02110-1301  USA
+//
+
+use std::collections::BTreeMap;
+use std::ops::Deref;
+
+use crypto::digest::Digest;
+use crypto::sha1::Sha1;
+use toml::Value;
+
+use libimagstore::store::{Entry, FileLockEntry, Store, StoreObject};
+use libimagstore::storeid::{IntoStoreId, StoreId, StoreIdIterator};
+use libimagentrylink::internal::InternalLinker;
+use libimagerror::into::IntoError;
+use libimagmail::mail::Mail;
+use libimagutil::import::ImportAction;
+
+use module_path::ModuleEntryPath;
+use result::Result;
+use error::ContactErrorKind as CEK;
+use error::MapErrInto;
+use vcard::VCard;
+
+/// The type of the internal links between contacts and other entries
+pub const LINK_TYPE : &'static str = "contact";
+
+/// A contact, imported from a vCard
+///
+/// The original card is the content of the entry, so it can be exported again without any
+/// changes. The most important fields are copied to the "contact" section of the header.
+#[derive(Debug)]
+pub struct Contact<'a>(FileLockEntry<'a>);
+
+/// The part of the store id of a contact after "contact/"
+///
+/// This is the UID of the card, or the SHA1 hash of the card if it has no UID.
+fn contact_name(card: &VCard) -> String {
+    match card.uid() {
+        Some(uid) => uid.replace("/", "_"),
+        None => {
+            let mut hasher = Sha1::new();
+            hasher.input_str(card.raw());
+            hasher.result_str()
+        },
+    }
+}
+
+fn card_to_header(card: &VCard, uid: String) -> Value {
+    let mut section = BTreeMap::new();
+    let strings = |v: Vec<String>| Value::Array(v.into_iter().map(Value::String).collect());
+
+    section.insert(String::from("uid"), Value::String(uid));
+    section.insert(String::from("emails"), strings(card.emails()));
+    section.insert(String::from("phones"), strings(card.phones()));
+    section.insert(String::from("addresses"), strings(card.addresses()));
+
+    {
+        let mut insert = |key: &str, value: Option<String>| if let Some(v) = value {
+            section.insert(String::from(key), Value::String(v));
+        };
+
+        insert("version", card.version());
+        insert("fullname", card.fullname());
+        insert("birthday", card.birthday());
+        insert("organization", card.organization());
+    }
+
+    if let Some(components) = card.name() {
+        let keys = ["family", "given", "additional", "prefix", "suffix"];
+        let name = keys
+            .iter()
+            .zip(components.into_iter())
+            .filter(|&(_, ref value)| !value.is_empty())
+            .map(|(key, value)| (String::from(*key), Value::String(value)))
+            .collect::<BTreeMap<_, _>>();
+
+        if !name.is_empty() {
+            section.insert(String::from("name"), Value::Table(name));
+        }
+    }
+
+    Value::Table(section)
+}
+
+impl<'a> Contact<'a> {
+
+    /// Import a card
+    ///
+    /// Cards are identified by their UID: if there is a contact with the same UID already, it is
+    /// replaced by the card, unless the card did not change.
+    pub fn import(store: &'a Store, card: &VCard) -> Result<(Contact<'a>, ImportAction)> {
+        let name = contact_name(card);
+        let id   = try!(ModuleEntryPath::new(name.clone()).into_storeid().map_err_into(CEK::StoreWriteError));
+
+        let (mut entry, action) = match try!(store.get(id.clone()).map_err_into(CEK::StoreReadError)) {
+            Some(entry) => {
+                if entry.get_content() == card.raw() {
+                    return Ok((Contact(entry), ImportAction::Unchanged));
+                }
+                (entry, ImportAction::Updated)
+            },
+            None => (try!(store.create(id).map_err_into(CEK::StoreWriteError)), ImportAction::Created),
+        };
+
+        let uid = card.uid().unwrap_or(name);
+        try!(entry.get_header_mut()
+             .set("contact", card_to_header(card, uid))
+             .map_err_into(CEK::HeaderWriteError));
+        *entry.get_content_mut() = String::from(card.raw());
+
+        Ok((Contact(entry), action))
+    }
+
+    /// Import all cards in `text`, returns the ids of the contacts and what happened to them
+    pub fn import_all(store: &Store, text: &str) -> Result<Vec<(StoreId, ImportAction)>> {
+        let cards = try!(VCard::parse_all(text));
+        let mut imported = vec![];
+
+        for card in cards {
+            let (contact, action) = try!(Contact::import(store, &card));
+            imported.push((contact.get_location().clone().without_base(), action));
+        }
+
+        Ok(imported)
+    }
+
+    /// Get a contact by its UID
+    pub fn get(store: &'a Store, uid: &str) -> Result<Option<Contact<'a>>> {
+        ModuleEntryPath::new(uid.replace("/", "_"))
+            .into_storeid()
+            .and_then(|id| store.get(id))
+            .map_err_into(CEK::StoreReadError)
+            .map(|o| o.map(Contact))
+    }
+
+    pub fn all(store: &'a Store) -> Result<ContactIterator<'a>> {
+        store.retrieve_for_module("contact")
+            .map(|iter| ContactIterator::new(store, iter))
+            .map_err_into(CEK::StoreReadError)
+    }
+
+    fn read_string(&self, field: &str) -> Result<Option<String>> {
+        match self.0.get_header().read(field) {
+            Ok(Some(Value::String(s))) => Ok(Some(s)),
+            Ok(None)                   => Ok(None),
+            Ok(_)                      => Err(CEK::HeaderTypeError.into_error()),
+            Err(e)                     => Err(CEK::HeaderReadError.into_error_with_cause(Box::new(e))),
+        }
+    }
+
+    fn read_strings(&self, field: &str) -> Result<Vec<String>> {
+        match self.0.get_header().read(field) {
+            Ok(Some(Value::Array(a))) => {
+                a.into_iter()
+                    .map(|v| match v {
+                        Value::String(s) => Ok(s),
+                        _                => Err(CEK::HeaderTypeError.into_error()),
+                    })
+                    .collect()
+            },
+            Ok(None) => Ok(vec![]),
+            Ok(_)    => Err(CEK::HeaderTypeError.into_error()),
+            Err(e)   => Err(CEK::HeaderReadError.into_error_with_cause(Box::new(e))),
+        }
+    }
+
+    pub fn uid(&self) -> Result<String> {
+        self.read_string("contact.uid")
+            .and_then(|uid| uid.ok_or(CEK::HeaderTypeError.into_error()))
+    }
+
+    pub fn fullname(&self) -> Result<Option<String>> {
+        self.read_string("contact.fullname")
+    }
+
+    pub fn birthday(&self) -> Result<Option<String>> {
+        self.read_string("contact.birthday")
+    }
+
+    pub fn organization(&self) -> Result<Option<String>> {
+        self.read_string("contact.organization")
+    }
+
+    pub fn emails(&self) -> Result<Vec<String>> {
+        self.read_strings("contact.emails")
+    }
+
+    pub fn phones(&self) -> Result<Vec<String>> {
+        self.read_strings("contact.phones")
+    }
+
+    pub fn addresses(&self) -> Result<Vec<String>> {
+        self.read_strings("contact.addresses")
+    }
+
+    /// The original vCard of the contact
+    pub fn raw_vcard(&self) -> &String {
+        self.0.get_content()
+    }
+
+    pub fn vcard(&self) -> Result<VCard> {
+        VCard::parse(self.raw_vcard())
+    }
+
+    /// Check whether the name, organization, emails, phones or addresses contain `query`,
+    /// ignoring case
+    pub fn matches(&self, query: &str) -> Result<bool> {
+        let query = query.to_lowercase();
+        let mut fields = vec![];
+        fields.extend(try!(self.fullname()));
+        fields.extend(try!(self.organization()));
+        fields.extend(try!(self.emails()));
+        fields.extend(try!(self.phones()));
+        fields.extend(try!(self.addresses()));
+
+        Ok(fields.iter().any(|f| f.to_lowercase().contains(&query)))
+    }
+
+    /// Check whether the contact has the email address `email`, ignoring case
+    pub fn has_email(&self, email: &str) -> Result<bool> {
+        let email = email.to_lowercase();
+        self.emails().map(|emails| emails.iter().any(|e| e.to_lowercase() == email))
+    }
+
+    /// Link an entry (like a mail or a diary entry) to this contact
+    pub fn link_entry(&mut self, entry: &mut Entry) -> Result<()> {
+        self.0
+            .add_typed_internal_link(entry, LINK_TYPE, BTreeMap::new())
+            .map_err_into(CEK::LinkError)
+    }
+
+    /// Get the ids of all entries linked to this contact
+    pub fn linked_entries(&self) -> Result<Vec<StoreId>> {
+        self.0
+            .get_internal_links_by_type(LINK_TYPE)
+            .map(|links| links.into_iter().map(|l| l.get_link().clone()).collect())
+            .map_err_into(CEK::LinkError)
+    }
+
+}
+
+impl<'a> Deref for Contact<'a> {
+
+    type Target = FileLockEntry<'a>;
+
+    fn deref(&self) -> &FileLockEntry<'a> {
+        &self.0
+    }
+
+}
+
+#[derive(Debug)]
+pub struct ContactIterator<'a> {
+    store: &'a Store,
+    iditer: StoreIdIterator,
+}
+
+impl<'a> ContactIterator<'a> {
+
+    pub fn new(store: &'a Store, iditer: StoreIdIterator) -> ContactIterator<'a> {
+        ContactIterator {
+            store: store,
+            iditer: iditer,
+        }
+    }
+
+}
+
+impl<'a> Iterator for ContactIterator<'a> {
+    type Item = Result<Contact<'a>>;
+
+    fn next(&mut self) -> Option<Result<Contact<'a>>> {
+        self.iditer
+            .next()
+            .map(|id| self.store.retrieve(id).map(Contact).map_err_into(CEK::StoreReadError))
+    }
+
+}
+
+/// Get the ids of all contacts with the email address `email`
+pub fn find_by_email(store: &Store, email: &str) -> Result<Vec<StoreId>> {
+    let mut ids = vec![];
+    for contact in try!(Contact::all(store)) {
+        let contact = try!(contact);
+        if try!(contact.has_email(email)) {
+            ids.push(contact.get_location().clone().without_base());
+        }
+    }
+    Ok(ids)
+}
+
+/// Extract the email addresses from an address list header like
+/// `"Doe, John" <john@example.com>, jane@example.com`
+pub fn extract_addresses(s: &str) -> Vec<String> {
+    let mut parts  = vec![String::new()];
+    let mut quoted = false;
+    for c in s.chars() {
+        match c {
+            '"' => quoted = !quoted,
+            ',' if !quoted => parts.push(String::new()),
+            c => parts.last_mut().unwrap().push(c), // there is always at least one element
+        }
+    }
+
+    parts.into_iter()
+        .map(|part| match (part.rfind('<'), part.rfind('>')) {
+            (Some(start), Some(end)) if start < end => String::from(&part[(start + 1)..end]),
+            _ => String::from(part.trim()),
+        })
+        .map(|address| address.trim().to_lowercase())
+        .filter(|address| address.contains('@'))
+        .collect()
+}
+
+/// Link all mails in the store to the contacts with the addresses from their "From" and "To"
+/// headers, returns the number of created links
+pub fn link_mails(store: &Store) -> Result<usize> {
+    let mut by_email : BTreeMap<String, Vec<StoreId>> = BTreeMap::new();
+    for contact in try!(Contact::all(store)) {
+        let contact = try!(contact);
+        for email in try!(contact.emails()) {
+            by_email.entry(email.to_lowercase())
+                .or_insert_with(Vec::new)
+                .push(contact.get_location().clone().without_base());
+        }
+    }
+
+    let mut linked = 0;
+    for object in store.walk("ref") {
+        let id = match object {
+            StoreObject::Id(id)        => id,
+            StoreObject::Collection(_) => continue,
+        };
+
+        let mut mail = match try!(store.get(id).map_err_into(CEK::StoreReadError)) {
+            Some(entry) => entry,
+            None        => continue,
+        };
+
+        if !Mail::is_mail(&mail) {
+            continue;
+        }
+
+        let mut addresses = vec![];
+        for field in &["mail.from", "mail.to"] {
+            if let Ok(Some(Value::String(s))) = mail.get_header().read(field) {
+                addresses.extend(extract_addresses(&s));
+            }
+        }
+
+        let existing = try!(mail.get_internal_links().map_err_into(CEK::LinkError)).collect::<Vec<_>>();
+        let contacts = addresses.iter().filter_map(|a| by_email.get(a)).flat_map(|ids| ids.iter());
+        for contact_id in contacts {
+            if existing.contains(contact_id) {
+                continue;
+            }
+
+            if let Some(mut contact) = try!(store.get(contact_id.clone()).map_err_into(CEK::StoreReadError)) {
+                try!(contact
+                     .add_typed_internal_link(&mut mail, LINK_TYPE, BTreeMap::new())
+                     .map_err_into(CEK::LinkError));
+                linked += 1;
+            }
+        }
+    }
+
+    Ok(linked)
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::PathBuf;
+
+    use libimagstore::store::Store;
+    use libimagstore::file_abstraction::InMemoryBackend;
+
+    use super::*;
+
+    fn get_store() -> Store {
+        Store::new(PathBuf::from("/"), None, Box::new(InMemoryBackend::new())).unwrap()
+    }
+
+    const JOHN : &'static str = "BEGIN:VCARD\nVERSION:3.0\nUID:john\nFN:John Doe\nEMAIL:John@Example.com\nEND:VCARD\n";
+
+    #[test]
+    fn test_import_deduplicates_by_uid() {
+        let store = get_store();
+
+        let imported = Contact::import_all(&store, JOHN).unwrap();
+        assert_eq!(imported.len(), 1);
+        assert_eq!(imported[0].1, ImportAction::Created);
+
+        let imported = Contact::import_all(&store, JOHN).unwrap();
+        assert_eq!(imported[0].1, ImportAction::Unchanged);
+
+        let changed  = JOHN.replace("FN:John Doe", "FN:Johnny Doe");
+        let imported = Contact::import_all(&store, &changed).unwrap();
+        assert_eq!(imported[0].1, ImportAction::Updated);
+
+        let john = Contact::get(&store, "john").unwrap().unwrap();
+        assert_eq!(john.fullname().unwrap(), Some(String::from("Johnny Doe")));
+        assert_eq!(john.raw_vcard(), &changed);
+        assert!(john.has_email("john@example.com").unwrap());
+        assert!(john.matches("johnny").unwrap());
+        assert!(!john.matches("jane").unwrap());
+    }
+
+    #[test]
+    fn test_import_without_uid() {
+        let store = get_store();
+        let card  = "BEGIN:VCARD\nVERSION:4.0\nFN:Jane\nEND:VCARD\n";
+
+        let first  = Contact::import_all(&store, card).unwrap();
+        let second = Contact::import_all(&store, card).unwrap();
+        assert_eq!(first[0].0, second[0].0);
+        assert_eq!(second[0].1, ImportAction::Unchanged);
+    }
+
+    #[test]
+    fn test_extract_addresses() {
+        let addresses = extract_addresses("\"Doe, John\" <John@Example.com>, jane@example.com, nobody");
+        assert_eq!(addresses, vec!["john@example.com", "jane@example.com"]);
+    }
+
+    #[test]
+    fn test_link_entry() {
+        let store = get_store();
+        let _ = Contact::import_all(&store, JOHN).unwrap();
+        let mut entry = store.create(PathBuf::from("diary/2016/10/01")).unwrap();
+
+        let mut john = Contact::get(&store, "john").unwrap().unwrap();
+        john.link_entry(&mut entry).unwrap();
+        assert_eq!(john.linked_entries().unwrap(),
+                   vec![StoreId::new_baseless(PathBuf::from("diary/2016/10/01")).unwrap()]);
+    }
+
+}
@@ -0,0 +1,352 @@
+//
+// imag - the personal information management suite for the commandline
+// Copyright (C) 2015, 2016 Matthias Beyer <mail@beyermatthias.de> and contributors
+//
+// This library is free software; you can redistribute it and/or
+// modify it under the terms of the GNU Lesser General Public
+// License as published by the Free Software Foundation; version
+// 2.1 of the License.
+//
+// This library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+// Lesser General Public License for more details.
+//
+// You should have received a copy of the GNU Lesser General Public
+// License along with this library; if not, write to the Free Software
+// Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA  02110-1301  USA
+//
+
+//! A minimal vCard (RFC 2426 and RFC 6350) parser
+//!
+//! Only the parts needed for importing contacts are implemented: cards are split, lines are
+//! unfolded and properties are parsed into their group, name, parameters and value. Values are
+//! kept as they are in the card, `unescape()` turns them into plain text.
+//!
+
+use error::ContactErrorKind as CEK;
+use result::Result;
+
+use libimagerror::into::IntoError;
+use libimagutil::quoted::{find_unquoted, split_unquoted};
+
+/// A property (content line) of a vCard, as in `item1.EMAIL;TYPE=work:john@example.com`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Property {
+    pub group: Option<String>,
+    pub name: String,
+    pub params: Vec<(String, String)>,
+    pub value: String,
+}
+
+impl Property {
+
+    /// Parse an unfolded content line
+    pub fn parse(line: &str) -> Result<Property> {
+        let colon = try!(find_unquoted(line, ':').ok_or(CEK::VCardParseError.into_error()));
+        let (head, value) = (&line[..colon], &line[(colon + 1)..]);
+
+        let mut parts = split_unquoted(head, ';').into_iter();
+        let name      = try!(parts.next().ok_or(CEK::VCardParseError.into_error()));
+        let (group, name) = match name.rfind('.') {
+            Some(i) => (Some(String::from(&name[..i])), String::from(&name[(i + 1)..])),
+            None    => (None, name),
+        };
+
+        if name.is_empty() {
+            return Err(CEK::VCardParseError.into_error());
+        }
+
+        let params = parts
+            .map(|param| match param.find('=') {
+                Some(i) => {
+                    let value = param[(i + 1)..].trim_matches('"');
+                    (param[..i].to_uppercase(), String::from(value))
+                },
+                // vCard 2.1 style parameters without name, like "EMAIL;HOME:..."
+                None => (String::from("TYPE"), param),
+            })
+            .collect();
+
+        Ok(Property {
+            group: group,
+            name: name.to_uppercase(),
+            params: params,
+            value: String::from(value),
+        })
+    }
+
+    /// Get the values of the TYPE parameters, lowercased
+    pub fn types(&self) -> Vec<String> {
+        self.params
+            .iter()
+            .filter(|&&(ref name, _)| name == "TYPE")
+            .flat_map(|&(_, ref value)| value.split(',').map(|t| t.to_lowercase()).collect::<Vec<_>>())
+            .collect()
+    }
+
+    /// The value as plain text
+    pub fn text(&self) -> String {
+        unescape(&self.value)
+    }
+
+    /// The components of a structured value (like N or ADR), as plain text
+    pub fn components(&self) -> Vec<String> {
+        split_escaped(&self.value, ';').iter().map(|c| unescape(c)).collect()
+    }
+
+}
+
+/// A parsed vCard together with its original text
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VCard {
+    raw: String,
+    properties: Vec<Property>,
+}
+
+impl VCard {
+
+    /// Parse all cards in `text`
+    ///
+    /// The original text of each card, from "BEGIN:VCARD" up to and including the line ending
+    /// after "END:VCARD", is kept, so the card can be exported without changes.
+    pub fn parse_all(text: &str) -> Result<Vec<VCard>> {
+        let mut cards = vec![];
+        let mut start = None;
+        let mut pos   = 0;
+
+        for line in text.split('\n') {
+            let next    = pos + line.len() + 1;
+            let trimmed = line.trim().to_uppercase();
+
+            if trimmed == "BEGIN:VCARD" {
+                if start.is_some() {
+                    return Err(CEK::VCardParseError.into_error());
+                }
+                start = Some(pos);
+            } else if trimmed == "END:VCARD" {
+                let begin = try!(start.take().ok_or(CEK::VCardParseError.into_error()));
+                let end   = ::std::cmp::min(next, text.len());
+                cards.push(try!(VCard::parse(&text[begin..end])));
+            }
+
+            pos = next;
+        }
+
+        if start.is_some() {
+            return Err(CEK::VCardParseError.into_error());
+        }
+
+        Ok(cards)
+    }
+
+    /// Parse a single card
+    pub fn parse(raw: &str) -> Result<VCard> {
+        let mut properties = vec![];
+        for line in unfold(raw) {
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            let property = try!(Property::parse(&line));
+            if property.name != "BEGIN" && property.name != "END" {
+                properties.push(property);
+            }
+        }
+
+        Ok(VCard {
+            raw: String::from(raw),
+            properties: properties,
+        })
+    }
+
+    /// The original text of the card
+    pub fn raw(&self) -> &str {
+        &self.raw
+    }
+
+    pub fn properties(&self) -> &Vec<Property> {
+        &self.properties
+    }
+
+    /// Get all properties with this name
+    pub fn get(&self, name: &str) -> Vec<&Property> {
+        let name = name.to_uppercase();
+        self.properties.iter().filter(|p| p.name == name).collect()
+    }
+
+    /// Get the plain text value of the first property with this name
+    pub fn get_text(&self, name: &str) -> Option<String> {
+        self.get(name).into_iter().next().map(Property::text).filter_empty()
+    }
+
+    pub fn version(&self) -> Option<String> {
+        self.get_text("VERSION")
+    }
+
+    pub fn uid(&self) -> Option<String> {
+        self.get_text("UID")
+    }
+
+    pub fn fullname(&self) -> Option<String> {
+        self.get_text("FN")
+    }
+
+    pub fn birthday(&self) -> Option<String> {
+        self.get_text("BDAY")
+    }
+
+    pub fn organization(&self) -> Option<String> {
+        self.get("ORG")
+            .into_iter()
+            .next()
+            .map(|p| p.components().into_iter().filter(|c| !c.is_empty()).collect::<Vec<_>>().join(", "))
+            .filter_empty()
+    }
+
+    /// The components of the N property: family name, given name, additional names, honorific
+    /// prefixes and suffixes
+    pub fn name(&self) -> Option<Vec<String>> {
+        self.get("N").into_iter().next().map(Property::components)
+    }
+
+    pub fn emails(&self) -> Vec<String> {
+        self.get("EMAIL").into_iter().map(Property::text).filter(|s| !s.is_empty()).collect()
+    }
+
+    pub fn phones(&self) -> Vec<String> {
+        self.get("TEL").into_iter().map(Property::text).filter(|s| !s.is_empty()).collect()
+    }
+
+    /// The addresses of the card, with the non-empty components joined by ", "
+    pub fn addresses(&self) -> Vec<String> {
+        self.get("ADR")
+            .into_iter()
+            .map(|p| p.components().into_iter().filter(|c| !c.is_empty()).collect::<Vec<_>>().join(", "))
+            .filter(|s| !s.is_empty())
+            .collect()
+    }
+
+}
+
+trait FilterEmpty {
+    fn filter_empty(self) -> Self;
+}
+
+impl FilterEmpty for Option<String> {
+    fn filter_empty(self) -> Option<String> {
+        match self {
+            Some(ref s) if s.is_empty() => None,
+            other => other,
+        }
+    }
+}
+
+/// Unfold the lines of a card: lines starting with a space or tab continue the previous line
+pub fn unfold(text: &str) -> Vec<String> {
+    let mut lines : Vec<String> = vec![];
+    for line in text.split('\n') {
+        let line = line.trim_right_matches('\r');
+        if (line.starts_with(" ") || line.starts_with("\t")) && !lines.is_empty() {
+            let last = lines.len() - 1;
+            lines[last].push_str(&line[1..]);
+        } else {
+            lines.push(String::from(line));
+        }
+    }
+    lines
+}
+
+/// Unescape a text value: `\n`, `\,`, `\;` and `\\`
+pub fn unescape(s: &str) -> String {
+    let mut result = String::with_capacity(s.len());
+    let mut chars  = s.chars();
+    while let Some(c) = chars.next() {
+        if c == '\\' {
+            match chars.next() {
+                Some('n') | Some('N') => result.push('\n'),
+                Some(c)               => result.push(c),
+                None                  => result.push('\\'),
+            }
+        } else {
+            result.push(c);
+        }
+    }
+    result
+}
+
+/// Split at `sep`, but not at escaped separators
+fn split_escaped(s: &str, sep: char) -> Vec<String> {
+    let mut parts   = vec![String::new()];
+    let mut escaped = false;
+    for c in s.chars() {
+        if c == sep && !escaped {
+            parts.push(String::new());
+        } else {
+            parts.last_mut().unwrap().push(c); // there is always at least one element
+        }
+        escaped = c == '\\' && !escaped;
+    }
+    parts
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const CARDS : &'static str = "BEGIN:VCARD\r\n\
+VERSION:3.0\r\n\
+UID:urn:uuid:4fbe8971-0bc3-424c-9c26-36c3e1eff6b1\r\n\
+FN:John Doe\r\n\
+N:Doe;John;;Dr.;\r\n\
+EMAIL;TYPE=work,pref:john@example.com\r\n\
+item1.EMAIL:john.doe@home.example.com\r\n\
+TEL;TYPE=cell:+49 123 456\r\n\
+ADR;TYPE=home:;;Main Street 1;Springfield;;12345;\r\n USA\r\n\
+NOTE:Met at the conference\\, nice guy\\nCall again\r\n\
+END:VCARD\r\n\
+BEGIN:VCARD\n\
+VERSION:4.0\n\
+FN:Jane\n\
+BDAY:19850412\n\
+ORG:ACME;Research\n\
+END:VCARD\n";
+
+    #[test]
+    fn test_parse_all() {
+        let cards = VCard::parse_all(CARDS).unwrap();
+        assert_eq!(cards.len(), 2);
+        assert!(cards[0].raw().starts_with("BEGIN:VCARD\r\n"));
+        assert!(cards[0].raw().ends_with("END:VCARD\r\n"));
+        assert_eq!(format!("{}{}", cards[0].raw(), cards[1].raw()), CARDS);
+    }
+
+    #[test]
+    fn test_card_fields() {
+        let cards = VCard::parse_all(CARDS).unwrap();
+        let john  = &cards[0];
+
+        assert_eq!(john.version(), Some(String::from("3.0")));
+        assert_eq!(john.uid(), Some(String::from("urn:uuid:4fbe8971-0bc3-424c-9c26-36c3e1eff6b1")));
+        assert_eq!(john.fullname(), Some(String::from("John Doe")));
+        assert_eq!(john.name().unwrap()[..2], [String::from("Doe"), String::from("John")]);
+        assert_eq!(john.emails(), vec!["john@example.com", "john.doe@home.example.com"]);
+        assert_eq!(john.phones(), vec!["+49 123 456"]);
+        assert_eq!(john.addresses(), vec!["Main Street 1, Springfield, 12345, USA"]);
+        assert_eq!(john.get_text("NOTE"), Some(String::from("Met at the conference, nice guy\nCall again")));
+        assert_eq!(john.get("EMAIL")[0].types(), vec!["work", "pref"]);
+        assert_eq!(john.get("EMAIL")[1].group, Some(String::from("item1")));
+
+        let jane = &cards[1];
+        assert_eq!(jane.uid(), None);
+        assert_eq!(jane.birthday(), Some(String::from("19850412")));
+        assert_eq!(jane.organization(), Some(String::from("ACME, Research")));
+    }
+
+    #[test]
+    fn test_unbalanced_cards() {
+        assert!(VCard::parse_all("BEGIN:VCARD\nFN:x\n").is_err());
+        assert!(VCard::parse_all("FN:x\nEND:VCARD\n").is_err());
+    }
+
+}
@@ -30,6 +30,7 @@ use libimagstore::storeid::StoreIdIterator;
 use libimagstore::store::FileLockEntry;
 use libimagstore::storeid::StoreId;
 use libimagstore::storeid::IntoStoreId;
+use libimagstore::schema::{FieldType, Schema};
 use libimagerror::into::IntoError;
 
 use module_path::ModuleEntryPath;
@@ -202,6 +203,15 @@ impl<'a> Counter<'a> {
             .map_err_into(CEK::StoreReadError)
     }
 
+    /// The schema of the "counter" header section, to be registered with
+    /// `Store::register_schema()`
+    pub fn header_schema() -> Schema {
+        Schema::new("counter")
+            .required("name", FieldType::String)
+            .required("value", FieldType::Integer)
+            .optional("unit", FieldType::String)
+    }
+
 }
 
 trait FromStoreId {
@@ -0,0 +1,388 @@
+//
+// imag - the personal information management suite for the commandline
+// Copyright (C) 2015, 2016 Matthias Beyer <mail@beyermatthias.de> and contributors
+//
+// This library is free software; you can redistribute it and/or
+// modify it under the terms of the GNU Lesser General Public
+// License as published by the Free Software Foundation; version
+// 2.1 of the License.
+//
+// This library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+// Lesser General Public License for more details.
+//
+// You should have received a copy of the GNU Lesser General Public
+// License along with this library; if not, write to the Free Software
+// Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA  02110-1301  USA
+//
+
+use std::collections::BTreeMap;
+use std::ops::Deref;
+
+use chrono::naive::date::NaiveDate;
+use toml::Value;
+use uuid::Uuid;
+
+use libimagstore::store::{FileLockEntry, Store};
+use libimagstore::storeid::{IntoStoreId, StoreId, StoreIdIterator};
+use libimagerror::into::IntoError;
+use libimagutil::import::ImportAction;
+
+use delimited::parse_delimited;
+use module_path::ModuleEntryPath;
+use schedule::Schedule;
+use result::Result;
+use error::CuecardsErrorKind as CEK;
+use error::MapErrInto;
+
+pub const DATE_FORMAT : &'static str = "%Y-%m-%d";
+
+/// A cuecard, with the front and the back side and the schedule for reviewing it
+///
+/// Cards are stored as `cuecards/<deck>/<uuid>`, with everything in the "cuecard" section of the
+/// header. The id is assigned when the card is created and does not depend on the sides, so it
+/// does not change if the card is edited. The content of the entry is free for notes on the card.
+#[derive(Debug)]
+pub struct Card<'a>(FileLockEntry<'a>);
+
+/// Get the ids of the cards of `deck`, by their front side
+fn cards_by_front(store: &Store, deck: &str) -> Result<BTreeMap<String, StoreId>> {
+    let mut cards = BTreeMap::new();
+    for card in try!(Card::all(store)) {
+        let card = try!(card);
+        if try!(card.deck()) == deck {
+            cards.insert(try!(card.front()), card.get_location().clone());
+        }
+    }
+    Ok(cards)
+}
+
+impl<'a> Card<'a> {
+
+    /// Import a card into `deck`
+    ///
+    /// Cards are identified by their deck and their front side: if there is such a card already,
+    /// its back side is updated and its schedule is kept. New cards are due at `today`.
+    pub fn import(store: &'a Store, deck: &str, front: &str, back: &str, today: NaiveDate)
+        -> Result<(Card<'a>, ImportAction)>
+    {
+        let mut cards = try!(cards_by_front(store, deck));
+        Card::import_into(store, &mut cards, deck, front, back, today)
+    }
+
+    /// Import a card into `deck`, whose cards are `cards`
+    ///
+    /// A new card is added to `cards`.
+    fn import_into(store: &'a Store,
+                   cards: &mut BTreeMap<String, StoreId>,
+                   deck: &str,
+                   front: &str,
+                   back: &str,
+                   today: NaiveDate)
+        -> Result<(Card<'a>, ImportAction)>
+    {
+        if let Some(id) = cards.get(front) {
+            let entry    = try!(store.retrieve(id.clone()).map_err_into(CEK::StoreReadError));
+            let mut card = Card(entry);
+            if try!(card.back()) == back {
+                return Ok((card, ImportAction::Unchanged));
+            }
+
+            try!(card.0
+                 .get_header_mut()
+                 .set("cuecard.back", Value::String(String::from(back)))
+                 .map_err_into(CEK::HeaderWriteError));
+            return Ok((card, ImportAction::Updated));
+        }
+
+        let mut section = BTreeMap::new();
+        section.insert(String::from("deck"), Value::String(String::from(deck)));
+        section.insert(String::from("front"), Value::String(String::from(front)));
+        section.insert(String::from("back"), Value::String(String::from(back)));
+
+        let id = try!(ModuleEntryPath::new(format!("{}/{}", deck, Uuid::new_v4()))
+                      .into_storeid()
+                      .map_err_into(CEK::StoreWriteError));
+        let entry = try!(store.create(id).map_err_into(CEK::StoreWriteError));
+        cards.insert(String::from(front), entry.get_location().clone());
+        let mut card = Card(entry);
+        try!(card.0
+             .get_header_mut()
+             .set("cuecard", Value::Table(section))
+             .map_err_into(CEK::HeaderWriteError));
+        try!(card.set_schedule(&Schedule::new(today)));
+
+        Ok((card, ImportAction::Created))
+    }
+
+    /// Import the cards from CSV or TSV text, with the front side in the first column and the
+    /// back side in the second. Further columns are ignored.
+    pub fn import_delimited(store: &Store, deck: &str, text: &str, delimiter: char, today: NaiveDate)
+        -> Result<Vec<(StoreId, ImportAction)>>
+    {
+        let mut imported = vec![];
+        let mut cards    = try!(cards_by_front(store, deck));
+        for row in try!(parse_delimited(text, delimiter)) {
+            if row.len() < 2 {
+                return Err(CEK::MissingColumn.into_error());
+            }
+
+            let (front, back)  = (row[0].trim(), row[1].trim());
+            let (card, action) = try!(Card::import_into(store, &mut cards, deck, front, back, today));
+            imported.push((card.get_location().clone().without_base(), action));
+        }
+
+        Ok(imported)
+    }
+
+    pub fn all(store: &'a Store) -> Result<CardIterator<'a>> {
+        store.retrieve_for_module("cuecards")
+            .map(|iter| CardIterator::new(store, iter))
+            .map_err_into(CEK::StoreReadError)
+    }
+
+    fn read(&self, field: &str) -> Result<Value> {
+        match self.0.get_header().read(field) {
+            Ok(Some(v)) => Ok(v),
+            Ok(None)    => Err(CEK::HeaderTypeError.into_error()),
+            Err(e)      => Err(CEK::HeaderReadError.into_error_with_cause(Box::new(e))),
+        }
+    }
+
+    fn read_string(&self, field: &str) -> Result<String> {
+        match try!(self.read(field)) {
+            Value::String(s) => Ok(s),
+            _                => Err(CEK::HeaderTypeError.into_error()),
+        }
+    }
+
+    fn read_uint(&self, field: &str) -> Result<u64> {
+        match try!(self.read(field)) {
+            Value::Integer(i) if i >= 0 => Ok(i as u64),
+            _                           => Err(CEK::HeaderTypeError.into_error()),
+        }
+    }
+
+    pub fn deck(&self) -> Result<String> {
+        self.read_string("cuecard.deck")
+    }
+
+    pub fn front(&self) -> Result<String> {
+        self.read_string("cuecard.front")
+    }
+
+    pub fn back(&self) -> Result<String> {
+        self.read_string("cuecard.back")
+    }
+
+    pub fn schedule(&self) -> Result<Schedule> {
+        let ease = match try!(self.read("cuecard.ease")) {
+            Value::Float(f)   => f,
+            Value::Integer(i) => i as f64,
+            _                 => return Err(CEK::HeaderTypeError.into_error()),
+        };
+
+        let due = try!(self.read_string("cuecard.due")
+            .and_then(|d| {
+                NaiveDate::parse_from_str(&d, DATE_FORMAT)
+                    .map_err(|e| CEK::DateParseError.into_error_with_cause(Box::new(e)))
+            }));
+
+        Ok(Schedule {
+            ease: ease,
+            interval: try!(self.read_uint("cuecard.interval")),
+            repetitions: try!(self.read_uint("cuecard.repetitions")),
+            due: due,
+        })
+    }
+
+    pub fn set_schedule(&mut self, schedule: &Schedule) -> Result<()> {
+        let header = self.0.get_header_mut();
+        let values = vec![
+            ("cuecard.ease", Value::Float(schedule.ease)),
+            ("cuecard.interval", Value::Integer(schedule.interval as i64)),
+            ("cuecard.repetitions", Value::Integer(schedule.repetitions as i64)),
+            ("cuecard.due", Value::String(schedule.due.format(DATE_FORMAT).to_string())),
+        ];
+
+        for (field, value) in values {
+            try!(header.set(field, value).map_err_into(CEK::HeaderWriteError));
+        }
+        Ok(())
+    }
+
+    /// Grade a review of this card at `today` and schedule the next review
+    pub fn review(&mut self, grade: u64, today: NaiveDate) -> Result<Schedule> {
+        let schedule = try!(try!(self.schedule()).review(grade, today));
+        try!(self.set_schedule(&schedule));
+        Ok(schedule)
+    }
+
+}
+
+impl<'a> Deref for Card<'a> {
+    type Target = FileLockEntry<'a>;
+
+    fn deref(&self) -> &FileLockEntry<'a> {
+        &self.0
+    }
+}
+
+#[derive(Debug)]
+pub struct CardIterator<'a> {
+    store: &'a Store,
+    iditer: StoreIdIterator,
+}
+
+impl<'a> CardIterator<'a> {
+
+    pub fn new(store: &'a Store, iditer: StoreIdIterator) -> CardIterator<'a> {
+        CardIterator {
+            store: store,
+            iditer: iditer,
+        }
+    }
+
+}
+
+impl<'a> Iterator for CardIterator<'a> {
+    type Item = Result<Card<'a>>;
+
+    fn next(&mut self) -> Option<Result<Card<'a>>> {
+        self.iditer
+            .next()
+            .map(|id| self.store.retrieve(id).map(Card).map_err_into(CEK::StoreReadError))
+    }
+
+}
+
+/// Get all cards of `deck` (or of all decks) which are due at `today`, the longest overdue first
+pub fn due_cards<'a>(store: &'a Store, deck: Option<&str>, today: NaiveDate) -> Result<Vec<Card<'a>>> {
+    let mut due = vec![];
+    for card in try!(Card::all(store)) {
+        let card = try!(card);
+        if let Some(deck) = deck {
+            if try!(card.deck()) != deck {
+                continue;
+            }
+        }
+
+        let schedule = try!(card.schedule());
+        if schedule.is_due(today) {
+            due.push((schedule.due, card));
+        }
+    }
+
+    due.sort_by(|a, b| a.0.cmp(&b.0));
+    Ok(due.into_iter().map(|(_, card)| card).collect())
+}
+
+/// Get the names of all decks, with the number of cards and the number of cards due at `today`
+pub fn decks(store: &Store, today: NaiveDate) -> Result<BTreeMap<String, (usize, usize)>> {
+    let mut decks = BTreeMap::new();
+    for card in try!(Card::all(store)) {
+        let card   = try!(card);
+        let is_due = try!(card.schedule()).is_due(today);
+        let counts = decks.entry(try!(card.deck())).or_insert((0, 0));
+
+        counts.0 += 1;
+        if is_due {
+            counts.1 += 1;
+        }
+    }
+    Ok(decks)
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::PathBuf;
+
+    use chrono::naive::date::NaiveDate;
+
+    use libimagstore::store::Store;
+    use libimagstore::file_abstraction::InMemoryBackend;
+
+    use super::*;
+
+    fn get_store() -> Store {
+        Store::new(PathBuf::from("/"), None, Box::new(InMemoryBackend::new())).unwrap()
+    }
+
+    fn day(d: u32) -> NaiveDate {
+        NaiveDate::from_ymd(2016, 10, d)
+    }
+
+    #[test]
+    fn test_import() {
+        let store    = get_store();
+        let imported = Card::import_delimited(&store, "spanish", "hola,hello\nadiós,bye\n", ',', day(1))
+            .unwrap();
+        assert!(imported.iter().all(|&(_, a)| a == ImportAction::Created));
+
+        let imported = Card::import_delimited(&store, "spanish", "hola\thello\nadiós\tgoodbye\n", '\t', day(2))
+            .unwrap();
+        let actions  = imported.iter().map(|&(_, a)| a).collect::<Vec<_>>();
+        assert_eq!(actions, vec![ImportAction::Unchanged, ImportAction::Updated]);
+
+        let cards = Card::all(&store).unwrap().map(|c| c.unwrap()).collect::<Vec<_>>();
+        assert_eq!(cards.len(), 2);
+
+        let card = cards.iter().find(|c| c.front().unwrap() == "adiós").unwrap();
+        assert_eq!(card.back().unwrap(), "goodbye");
+        assert_eq!(card.deck().unwrap(), "spanish");
+        assert_eq!(card.schedule().unwrap(), Schedule::new(day(1)));
+    }
+
+    #[test]
+    fn test_id_does_not_change_with_the_front() {
+        let store = get_store();
+        let id    = {
+            let (mut card, _) = Card::import(&store, "spanish", "hola", "hello", day(1)).unwrap();
+            card.0.get_header_mut().set("cuecard.front", Value::String(String::from("¡hola!"))).unwrap();
+            card.get_location().clone()
+        };
+
+        let (card, action) = Card::import(&store, "spanish", "¡hola!", "hello", day(2)).unwrap();
+        assert_eq!(action, ImportAction::Unchanged);
+        assert_eq!(card.get_location(), &id);
+        drop(card);
+
+        let (_, action) = Card::import(&store, "german", "¡hola!", "hallo", day(2)).unwrap();
+        assert_eq!(action, ImportAction::Created);
+        assert_eq!(Card::all(&store).unwrap().count(), 2);
+    }
+
+    #[test]
+    fn test_missing_column() {
+        let store = get_store();
+        assert!(Card::import_delimited(&store, "spanish", "hola\n", ',', day(1)).is_err());
+    }
+
+    #[test]
+    fn test_review() {
+        let store = get_store();
+        let _ = Card::import(&store, "spanish", "hola", "hello", day(1)).unwrap();
+        let _ = Card::import(&store, "german", "hallo", "hello", day(1)).unwrap();
+        let _ = Card::import(&store, "german", "tschüss", "bye", day(3)).unwrap();
+
+        {
+            let mut due = due_cards(&store, Some("german"), day(3)).unwrap();
+            assert_eq!(due.len(), 2);
+            assert_eq!(due[0].front().unwrap(), "hallo");
+
+            let schedule = due[0].review(5, day(3)).unwrap();
+            assert_eq!(schedule.due, day(4));
+            assert!(due[1].review(6, day(3)).is_err());
+        }
+
+        let due = due_cards(&store, None, day(3)).unwrap();
+        let due = due.iter().map(|c| c.front().unwrap()).collect::<Vec<_>>();
+        assert_eq!(due, vec!["hola", "tschüss"]);
+
+        let decks = decks(&store, day(3)).unwrap();
+        assert_eq!(decks.get("german"), Some(&(2, 1)));
+        assert_eq!(decks.get("spanish"), Some(&(1, 1)));
+    }
+
+}
@@ -0,0 +1,124 @@
+//
+// imag - the personal information management suite for the commandline
+// Copyright (C) 2015, 2016 Matthias Beyer <mail@beyermatthias.de> and contributors
+//
+// This library is free software; you can redistribute it and/or
+// modify it under the terms of the GNU Lesser General Public
+// License as published by the Free Software Foundation; version
+// 2.1 of the License.
+//
+// This library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+// Lesser General Public License for more details.
+//
+// You should have received a copy of the GNU Lesser General Public
+// License along with this library; if not, write to the Free Software
+// Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA  02110-1301  USA
+//
+
+use std::mem;
+
+use error::CuecardsErrorKind as CEK;
+use libimagerror::into::IntoError;
+use result::Result;
+
+/// Parse delimiter separated values, like CSV (with `,`) or TSV (with a tab)
+///
+/// Fields can be quoted with `"`. Quoted fields can contain the delimiter, newlines and quotes,
+/// which are written as `""`. Empty lines and lines starting with `#` are skipped.
+pub fn parse_delimited(text: &str, delimiter: char) -> Result<Vec<Vec<String>>> {
+    let mut rows       = vec![];
+    let mut row        = vec![];
+    let mut field      = String::new();
+    let mut quoted     = false;
+    let mut line_start = true;
+    let mut chars      = text.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if quoted {
+            match c {
+                '"' if chars.peek() == Some(&'"') => {
+                    chars.next();
+                    field.push('"');
+                },
+                '"' => quoted = false,
+                c   => field.push(c),
+            }
+            continue;
+        }
+
+        if line_start && c == '#' {
+            for c in chars.by_ref() {
+                if c == '\n' {
+                    break;
+                }
+            }
+            continue;
+        }
+        line_start = false;
+
+        match c {
+            '"' if field.is_empty()               => quoted = true,
+            '\r' if chars.peek() == Some(&'\n')   => {},
+            '\n'                                  => {
+                row.push(mem::replace(&mut field, String::new()));
+                push_row(&mut rows, mem::replace(&mut row, vec![]));
+                line_start = true;
+            },
+            c if c == delimiter                   => row.push(mem::replace(&mut field, String::new())),
+            c                                     => field.push(c),
+        }
+    }
+
+    if quoted {
+        return Err(CEK::DelimitedParseError.into_error());
+    }
+
+    if !line_start {
+        row.push(field);
+        push_row(&mut rows, row);
+    }
+
+    Ok(rows)
+}
+
+fn push_row(rows: &mut Vec<Vec<String>>, row: Vec<String>) {
+    if !(row.len() == 1 && row[0].trim().is_empty()) {
+        rows.push(row);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::parse_delimited;
+
+    fn strings(v: &[&[&str]]) -> Vec<Vec<String>> {
+        v.iter().map(|row| row.iter().map(|s| String::from(*s)).collect()).collect()
+    }
+
+    #[test]
+    fn test_csv() {
+        let text = "hola,hello\n\"adiós, amigo\",\"goodbye, \"\"friend\"\"\"\r\n\n# comment, skipped\nuno,one";
+        assert_eq!(parse_delimited(text, ',').unwrap(), strings(&[
+            &["hola", "hello"],
+            &["adiós, amigo", "goodbye, \"friend\""],
+            &["uno", "one"],
+        ]));
+    }
+
+    #[test]
+    fn test_tsv() {
+        let text = "#separator:tab\nhola\thello, world\t\n\"two\nlines\"\tzwei\n";
+        assert_eq!(parse_delimited(text, '\t').unwrap(), strings(&[
+            &["hola", "hello, world", ""],
+            &["two\nlines", "zwei"],
+        ]));
+    }
+
+    #[test]
+    fn test_unterminated_quote() {
+        assert!(parse_delimited("\"hola,hello\n", ',').is_err());
+    }
+
+}
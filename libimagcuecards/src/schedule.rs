@@ -0,0 +1,170 @@
+//
+// imag - the personal information management suite for the commandline
+// Copyright (C) 2015, 2016 Matthias Beyer <mail@beyermatthias.de> and contributors
+//
+// This library is free software; you can redistribute it and/or
+// modify it under the terms of the GNU Lesser General Public
+// License as published by the Free Software Foundation; version
+// 2.1 of the License.
+//
+// This library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+// Lesser General Public License for more details.
+//
+// You should have received a copy of the GNU Lesser General Public
+// License along with this library; if not, write to the Free Software
+// Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA  02110-1301  USA
+//
+
+//! The SM-2 algorithm by Piotr Wozniak, as used in SuperMemo 2
+//!
+//! After each review, the card is graded from 0 (complete blackout) to 5 (perfect response).
+//! Cards graded 3 or better are scheduled for an interval which grows with the ease factor of
+//! the card, the others start again with an interval of one day.
+
+use chrono::Duration;
+use chrono::naive::date::NaiveDate;
+
+use error::CuecardsErrorKind as CEK;
+use libimagerror::into::IntoError;
+use result::Result;
+
+/// The ease factor of new cards
+pub const DEFAULT_EASE : f64 = 2.5;
+
+/// The ease factor never drops below this value, so cards don't show up too often
+pub const MIN_EASE : f64 = 1.3;
+
+/// The best grade
+pub const MAX_GRADE : u64 = 5;
+
+/// Cards with a grade below this are reviewed again in the same session
+pub const REPEAT_BELOW : u64 = 4;
+
+/// The scheduling state of a card
+#[derive(Debug, Clone, PartialEq)]
+pub struct Schedule {
+    /// The ease factor, how fast the interval grows
+    pub ease: f64,
+
+    /// The number of days until the next review
+    pub interval: u64,
+
+    /// The number of successful reviews in a row
+    pub repetitions: u64,
+
+    /// The date of the next review
+    pub due: NaiveDate,
+}
+
+impl Schedule {
+
+    /// The schedule of a new card, which is due at `today`
+    pub fn new(today: NaiveDate) -> Schedule {
+        Schedule {
+            ease: DEFAULT_EASE,
+            interval: 0,
+            repetitions: 0,
+            due: today,
+        }
+    }
+
+    pub fn is_due(&self, today: NaiveDate) -> bool {
+        self.due <= today
+    }
+
+    /// Compute the schedule after a review at `today` with the grade `grade`
+    pub fn review(&self, grade: u64, today: NaiveDate) -> Result<Schedule> {
+        if grade > MAX_GRADE {
+            return Err(CEK::InvalidGrade.into_error());
+        }
+
+        let (interval, repetitions, ease) = if grade < 3 {
+            (1, 0, self.ease)
+        } else {
+            let interval = match self.repetitions {
+                0 => 1,
+                1 => 6,
+                _ => (self.interval as f64 * self.ease).round() as u64,
+            };
+
+            let q    = (MAX_GRADE - grade) as f64;
+            let ease = self.ease + (0.1 - q * (0.08 + q * 0.02));
+            (interval, self.repetitions + 1, if ease < MIN_EASE { MIN_EASE } else { ease })
+        };
+
+        Ok(Schedule {
+            ease: ease,
+            interval: interval,
+            repetitions: repetitions,
+            due: today + Duration::days(interval as i64),
+        })
+    }
+
+}
+
+#[cfg(test)]
+mod test {
+    use chrono::naive::date::NaiveDate;
+
+    use super::*;
+
+    fn day(d: u32) -> NaiveDate {
+        NaiveDate::from_ymd(2016, 10, d)
+    }
+
+    #[test]
+    fn test_intervals() {
+        let s = Schedule::new(day(1));
+        assert!(s.is_due(day(1)));
+
+        let s = s.review(4, day(1)).unwrap();
+        assert_eq!(s.interval, 1);
+        assert_eq!(s.due, day(2));
+        assert!(!s.is_due(day(1)));
+
+        let s = s.review(4, day(2)).unwrap();
+        assert_eq!(s.interval, 6);
+        assert_eq!(s.due, day(8));
+
+        let s = s.review(5, day(8)).unwrap();
+        assert_eq!(s.repetitions, 3);
+        assert_eq!(s.interval, 15); // 6 * 2.5
+        assert_eq!(s.due, day(23));
+        assert!((s.ease - 2.6).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_ease() {
+        let s = Schedule::new(day(1));
+        assert!((s.review(5, day(1)).unwrap().ease - 2.6).abs() < 1e-9);
+        assert!((s.review(4, day(1)).unwrap().ease - 2.5).abs() < 1e-9);
+        assert!((s.review(3, day(1)).unwrap().ease - 2.36).abs() < 1e-9);
+
+        let mut s = s;
+        for _ in 0..20 {
+            s = s.review(3, day(1)).unwrap();
+        }
+        assert!((s.ease - MIN_EASE).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_lapse() {
+        let s = Schedule::new(day(1))
+            .review(5, day(1)).unwrap()
+            .review(5, day(2)).unwrap()
+            .review(1, day(8)).unwrap();
+
+        assert_eq!(s.repetitions, 0);
+        assert_eq!(s.interval, 1);
+        assert_eq!(s.due, day(9));
+        assert!((s.ease - 2.7).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_invalid_grade() {
+        assert!(Schedule::new(day(1)).review(6, day(1)).is_err());
+    }
+
+}
@@ -39,9 +39,35 @@ impl FieldExists {
 impl Filter<Entry> for FieldExists {
 
     fn filter(&self, e: &Entry) -> bool {
-        e.get_header().read(&self.header_field_path[..]).is_ok()
+        e.get_header()
+            .read(&self.header_field_path[..])
+            .map(|v| v.is_some())
+            .unwrap_or(false)
     }
 
 }
 
+#[cfg(test)]
+mod test {
+    use filters::filter::Filter;
 
+    use super::FieldExists;
+    use test_util::entry;
+
+    fn exists(path: &str) -> bool {
+        FieldExists::new(String::from(path)).filter(&entry())
+    }
+
+    #[test]
+    fn test_field_exists() {
+        assert!(exists("test.int"));
+        assert!(exists("test"));
+    }
+
+    #[test]
+    fn test_missing_field_does_not_exist() {
+        assert!(!exists("test.missing"));
+        assert!(!exists("missing.int"));
+    }
+
+}
@@ -36,15 +36,15 @@ impl Predicate for EqGt {
         match self.comp {
             Value::Integer(i) => {
                 match v {
-                    Value::Integer(j) => i > j,
-                    Value::Float(f) => (i as f64) > f,
+                    Value::Integer(j) => j > i,
+                    Value::Float(f) => f > (i as f64),
                     _ => false,
                 }
             },
             Value::Float(f) => {
                 match v {
-                    Value::Integer(i) => f > (i as f64),
-                    Value::Float(d) => f > d,
+                    Value::Integer(i) => (i as f64) > f,
+                    Value::Float(d) => d > f,
                     _ => false,
                 }
             },
@@ -54,7 +54,7 @@ impl Predicate for EqGt {
 
 }
 
-/// Check whether certain header field in a entry is equal to a value
+/// Check whether certain header field in a entry is greater than a value
 pub struct FieldGt {
     filter: FieldPredicate<EqGt>,
 }
@@ -77,3 +77,33 @@ impl Filter<Entry> for FieldGt {
 
 }
 
+#[cfg(test)]
+mod test {
+    use filters::filter::Filter;
+    use toml::Value;
+
+    use super::FieldGt;
+    use test_util::entry;
+
+    fn gt(path: &str, v: Value) -> bool {
+        FieldGt::new(String::from(path), v).filter(&entry())
+    }
+
+    #[test]
+    fn test_field_is_greater_than_value() {
+        assert!(gt("test.int", Value::Integer(3)));
+        assert!(gt("test.int", Value::Float(4.5)));
+        assert!(gt("test.float", Value::Integer(5)));
+        assert!(gt("test.float", Value::Float(5.0)));
+    }
+
+    #[test]
+    fn test_field_is_not_greater_than_value() {
+        assert!(!gt("test.int", Value::Integer(5)));
+        assert!(!gt("test.int", Value::Integer(7)));
+        assert!(!gt("test.int", Value::Float(5.5)));
+        assert!(!gt("test.float", Value::Integer(6)));
+        assert!(!gt("test.missing", Value::Integer(0)));
+    }
+
+}
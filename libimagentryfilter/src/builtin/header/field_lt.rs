@@ -36,15 +36,15 @@ impl Predicate for EqLt {
         match self.comp {
             Value::Integer(i) => {
                 match v {
-                    Value::Integer(j) => i < j,
-                    Value::Float(f) => (i as f64) < f,
+                    Value::Integer(j) => j < i,
+                    Value::Float(f) => f < (i as f64),
                     _ => false,
                 }
             },
             Value::Float(f) => {
                 match v {
-                    Value::Integer(i) => f < (i as f64),
-                    Value::Float(d) => f < d,
+                    Value::Integer(i) => (i as f64) < f,
+                    Value::Float(d) => d < f,
                     _ => false,
                 }
             },
@@ -54,7 +54,7 @@ impl Predicate for EqLt {
 
 }
 
-/// Check whether certain header field in a entry is equal to a value
+/// Check whether certain header field in a entry is less than a value
 pub struct FieldLt {
     filter: FieldPredicate<EqLt>,
 }
@@ -77,3 +77,33 @@ impl Filter<Entry> for FieldLt {
 
 }
 
+#[cfg(test)]
+mod test {
+    use filters::filter::Filter;
+    use toml::Value;
+
+    use super::FieldLt;
+    use test_util::entry;
+
+    fn lt(path: &str, v: Value) -> bool {
+        FieldLt::new(String::from(path), v).filter(&entry())
+    }
+
+    #[test]
+    fn test_field_is_less_than_value() {
+        assert!(lt("test.int", Value::Integer(7)));
+        assert!(lt("test.int", Value::Float(5.5)));
+        assert!(lt("test.float", Value::Integer(6)));
+        assert!(lt("test.float", Value::Float(6.0)));
+    }
+
+    #[test]
+    fn test_field_is_not_less_than_value() {
+        assert!(!lt("test.int", Value::Integer(5)));
+        assert!(!lt("test.int", Value::Integer(3)));
+        assert!(!lt("test.int", Value::Float(4.5)));
+        assert!(!lt("test.float", Value::Integer(5)));
+        assert!(!lt("test.missing", Value::Integer(10)));
+    }
+
+}
@@ -17,3 +17,45 @@
 // Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA  02110-1301  USA
 //
 
+use clap::{Arg, ArgMatches};
+
+use query::Query;
+use result::Result;
+
+/// Generates a `clap::Arg` which can be integrated into the commandline-ui builder for building a
+/// "--filter" argument, which takes a filter query (see the `query` module for the syntax)
+pub fn filter_argument<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name(filter_argument_name())
+        .long("filter")
+        .takes_value(true)
+        .multiple(false)
+        .value_name("QUERY")
+        .validator(is_filter_query)
+        .help("Only use entries matching this query, e.g. 'header.counter.value > 3 and tag:work'")
+}
+
+pub fn filter_argument_name() -> &'static str {
+    "entry-filter"
+}
+
+/// Get the filter query from the commandline
+///
+/// Returns `Ok(None)` if the argument was not specified
+pub fn get_filter(matches: &ArgMatches) -> Result<Option<Query>> {
+    match matches.value_of(filter_argument_name()) {
+        Some(q) => Query::parse(q).map(Some),
+        None    => Ok(None),
+    }
+}
+
+fn is_filter_query(s: String) -> ::std::result::Result<(), String> {
+    use std::error::Error;
+
+    Query::parse(&s)
+        .map(|_| ())
+        .map_err(|e| {
+            let reason = e.cause().map(|c| format!("{}", c)).unwrap_or_else(|| format!("{}", e));
+            format!("Not a valid filter query: '{}' {}", s, reason)
+        })
+}
+
@@ -34,6 +34,7 @@
 
 #[macro_use] extern crate log;
 
+extern crate clap;
 extern crate filters;
 extern crate itertools;
 extern crate regex;
@@ -42,12 +43,19 @@ extern crate toml;
 
 extern crate libimagstore;
 extern crate libimagentrytag;
+#[macro_use] extern crate libimagerror;
 
 // core functionality modules of the crate,
 // these depend only on libimagstore
 
 pub mod cli;
 pub mod builtin;
+pub mod error;
+pub mod query;
+pub mod result;
+
+#[cfg(test)]
+mod test_util;
 
 // extended functionality of the crate
 // these depend on other internal libraries than libimagstore and use the upper core modules for
@@ -0,0 +1,488 @@
+//
+// imag - the personal information management suite for the commandline
+// Copyright (C) 2015, 2016 Matthias Beyer <mail@beyermatthias.de> and contributors
+//
+// This library is free software; you can redistribute it and/or
+// modify it under the terms of the GNU Lesser General Public
+// License as published by the Free Software Foundation; version
+// 2.1 of the License.
+//
+// This library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+// Lesser General Public License for more details.
+//
+// You should have received a copy of the GNU Lesser General Public
+// License along with this library; if not, write to the Free Software
+// Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA  02110-1301  USA
+//
+
+//! A small query language for combining the builtin filters
+//!
+//! A query is a boolean expression over predicates:
+//!
+//! ```text
+//! header.counter.value > 3 and tag:work and not content ~ "draft"
+//! ```
+//!
+//! The predicates are:
+//!
+//! * `header.<path> == <value>`, `!=`, `<`, `>`, `<=`, `>=` - compare a header field
+//! * `header.<path> ~ <regex>` - match a string header field against a regex
+//! * `header.<path> exists`, `header.<path> is empty`, `header.<path> is <type>`, where type is
+//!   one of `array`, `boolean`, `float`, `integer`, `string` or `table`
+//! * `content ~ <regex>` - match the content against a regex
+//! * `content.length <op> <number>` - compare the length of the content
+//! * `version <op> <version>` - compare the version of the entry (`imag.version`)
+//! * `tag:<name>` - the entry has the tag `name`
+//! * `true`, `false`
+//!
+//! Values are integers, floats, `true`, `false` or strings. Strings which contain whitespace or
+//! operator characters have to be put in double quotes.
+//!
+//! Predicates can be combined with `and`, `or` and `not` and grouped with parentheses. `and` binds
+//! stronger than `or`. Note that `!=` also matches entries which do not have the header field at
+//! all.
+//!
+
+use std::iter::Peekable;
+use std::vec::IntoIter;
+
+use filters::filter::Filter;
+use regex::Regex;
+use semver::Version;
+use toml::Value;
+
+use libimagerror::into::IntoError;
+use libimagstore::store::Entry;
+
+use builtin::bool_filter::BoolFilter;
+use builtin::content::grep::ContentGrep;
+use builtin::content::length::is_over::ContentLengthIsOver;
+use builtin::content::length::is_under::ContentLengthIsUnder;
+use builtin::header::field_eq::FieldEq;
+use builtin::header::field_exists::FieldExists;
+use builtin::header::field_grep::FieldGrep;
+use builtin::header::field_gt::FieldGt;
+use builtin::header::field_isempty::FieldIsEmpty;
+use builtin::header::field_istype::{FieldIsType, Type};
+use builtin::header::field_lt::FieldLt;
+use builtin::header::version::eq::VersionEq;
+use builtin::header::version::gt::VersionGt;
+use builtin::header::version::lt::VersionLt;
+use error::FilterErrorKind as FEK;
+use error::MapErrInto;
+use result::Result;
+use tags::HasTag;
+
+/// A parsed filter query
+///
+/// The leafs of the expression are the builtin filters of this crate.
+pub enum Query {
+    Predicate(Box<Filter<Entry>>),
+    And(Box<Query>, Box<Query>),
+    Or(Box<Query>, Box<Query>),
+    Not(Box<Query>),
+}
+
+impl Query {
+
+    /// Parse a query string
+    pub fn parse(s: &str) -> Result<Query> {
+        parse_query(s).map_err_into(FEK::QueryParseError)
+    }
+
+    fn predicate<F: Filter<Entry> + 'static>(f: F) -> Query {
+        Query::Predicate(Box::new(f))
+    }
+
+    fn and(a: Query, b: Query) -> Query {
+        Query::And(Box::new(a), Box::new(b))
+    }
+
+    fn or(a: Query, b: Query) -> Query {
+        Query::Or(Box::new(a), Box::new(b))
+    }
+
+    fn not(q: Query) -> Query {
+        Query::Not(Box::new(q))
+    }
+
+}
+
+impl Filter<Entry> for Query {
+
+    fn filter(&self, e: &Entry) -> bool {
+        match *self {
+            Query::Predicate(ref f) => f.filter(e),
+            Query::And(ref a, ref b) => a.filter(e) && b.filter(e),
+            Query::Or(ref a, ref b)  => a.filter(e) || b.filter(e),
+            Query::Not(ref q)        => !q.filter(e),
+        }
+    }
+
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Op {
+    Eq,
+    Ne,
+    Lt,
+    Gt,
+    Le,
+    Ge,
+    Match,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+    Word(String),
+    Str(String),
+    Op(Op),
+    Open,
+    Close,
+}
+
+fn is_op_char(c: char) -> bool {
+    c == '=' || c == '!' || c == '<' || c == '>' || c == '~'
+}
+
+fn lex(s: &str) -> Result<Vec<Token>> {
+    let mut tokens = vec![];
+    let mut chars  = s.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            c if c.is_whitespace() => continue,
+            '(' => tokens.push(Token::Open),
+            ')' => tokens.push(Token::Close),
+            '"' => {
+                let mut string = String::new();
+                loop {
+                    match chars.next() {
+                        Some('"')  => break,
+                        Some('\\') => match chars.next() {
+                            Some(c) => string.push(c),
+                            None    => return Err(FEK::UnexpectedEnd.into_error()),
+                        },
+                        Some(c)    => string.push(c),
+                        None       => return Err(FEK::UnexpectedEnd.into_error()),
+                    }
+                }
+                tokens.push(Token::Str(string));
+            },
+            c if is_op_char(c) => {
+                let second = match chars.peek() {
+                    Some(&'=') => true,
+                    _          => false,
+                };
+                if second {
+                    chars.next();
+                }
+
+                let op = match (c, second) {
+                    ('=', true)  => Op::Eq,
+                    ('!', true)  => Op::Ne,
+                    ('<', true)  => Op::Le,
+                    ('>', true)  => Op::Ge,
+                    ('<', false) => Op::Lt,
+                    ('>', false) => Op::Gt,
+                    ('~', false) => Op::Match,
+                    _ => {
+                        debug!("Unknown operator starting with '{}'", c);
+                        return Err(FEK::UnexpectedToken.into_error());
+                    },
+                };
+                tokens.push(Token::Op(op));
+            },
+            c => {
+                let mut word = String::new();
+                word.push(c);
+                loop {
+                    let ends_word = match chars.peek() {
+                        Some(&c) => c.is_whitespace() || c == '(' || c == ')' || c == '"' ||
+                                    is_op_char(c),
+                        None     => true,
+                    };
+                    if ends_word {
+                        break;
+                    }
+                    word.push(chars.next().unwrap());
+                }
+                tokens.push(Token::Word(word));
+            },
+        }
+    }
+
+    Ok(tokens)
+}
+
+type Tokens = Peekable<IntoIter<Token>>;
+
+fn parse_query(s: &str) -> Result<Query> {
+    let mut tokens = try!(lex(s)).into_iter().peekable();
+    if tokens.peek().is_none() {
+        return Err(FEK::EmptyQuery.into_error());
+    }
+
+    let query = try!(parse_or(&mut tokens));
+    match tokens.next() {
+        None    => Ok(query),
+        Some(t) => {
+            debug!("Unexpected token in filter query: {:?}", t);
+            Err(FEK::UnexpectedToken.into_error())
+        },
+    }
+}
+
+fn is_keyword(t: Option<&Token>, kw: &str) -> bool {
+    match t {
+        Some(&Token::Word(ref w)) => w.to_lowercase() == kw,
+        _ => false,
+    }
+}
+
+fn parse_or(tokens: &mut Tokens) -> Result<Query> {
+    let mut query = try!(parse_and(tokens));
+    while is_keyword(tokens.peek(), "or") {
+        tokens.next();
+        let rhs = try!(parse_and(tokens));
+        query = Query::or(query, rhs);
+    }
+    Ok(query)
+}
+
+fn parse_and(tokens: &mut Tokens) -> Result<Query> {
+    let mut query = try!(parse_unary(tokens));
+    while is_keyword(tokens.peek(), "and") {
+        tokens.next();
+        let rhs = try!(parse_unary(tokens));
+        query = Query::and(query, rhs);
+    }
+    Ok(query)
+}
+
+fn parse_unary(tokens: &mut Tokens) -> Result<Query> {
+    if is_keyword(tokens.peek(), "not") {
+        tokens.next();
+        return parse_unary(tokens).map(Query::not);
+    }
+
+    match tokens.next() {
+        Some(Token::Open) => {
+            let query = try!(parse_or(tokens));
+            match tokens.next() {
+                Some(Token::Close) => Ok(query),
+                Some(_) => Err(FEK::UnexpectedToken.into_error()),
+                None    => Err(FEK::UnexpectedEnd.into_error()),
+            }
+        },
+        Some(Token::Word(w)) => parse_predicate(w, tokens),
+        Some(t) => {
+            debug!("Unexpected token in filter query: {:?}", t);
+            Err(FEK::UnexpectedToken.into_error())
+        },
+        None => Err(FEK::UnexpectedEnd.into_error()),
+    }
+}
+
+fn parse_predicate(word: String, tokens: &mut Tokens) -> Result<Query> {
+    match &word[..] {
+        "true"  => return Ok(Query::predicate(BoolFilter::new(true))),
+        "false" => return Ok(Query::predicate(BoolFilter::new(false))),
+        "content" => {
+            try!(expect_op(tokens, &[Op::Match]));
+            let regex = try!(expect_value(tokens));
+            return ContentGrep::new(&regex[..])
+                .map(Query::predicate)
+                .map_err_into(FEK::InvalidRegex);
+        },
+        "content.length" => {
+            let op  = try!(expect_op(tokens, &[Op::Eq, Op::Ne, Op::Lt, Op::Gt, Op::Le, Op::Ge]));
+            let len = try!(expect_value(tokens)
+                           .and_then(|v| v.parse::<usize>().map_err_into(FEK::InvalidValue)));
+            let over  = || Query::predicate(ContentLengthIsOver::new(len));
+            let under = || Query::predicate(ContentLengthIsUnder::new(len));
+            return Ok(match op {
+                Op::Gt => over(),
+                Op::Lt => under(),
+                Op::Ge => Query::not(under()),
+                Op::Le => Query::not(over()),
+                Op::Eq => Query::and(Query::not(over()), Query::not(under())),
+                _      => Query::or(over(), under()),
+            });
+        },
+        "version" => {
+            let op      = try!(expect_op(tokens, &[Op::Eq, Op::Ne, Op::Lt, Op::Gt, Op::Le, Op::Ge]));
+            let version = try!(expect_value(tokens)
+                               .and_then(|v| Version::parse(&v[..])
+                                         .map_err(|_| FEK::InvalidVersion.into_error())));
+            let eq = || Query::predicate(VersionEq::new(version.clone()));
+            let gt = || Query::predicate(VersionGt::new(version.clone()));
+            let lt = || Query::predicate(VersionLt::new(version.clone()));
+            return Ok(match op {
+                Op::Eq => eq(),
+                Op::Ne => Query::not(eq()),
+                Op::Gt => gt(),
+                Op::Lt => lt(),
+                Op::Ge => Query::or(gt(), eq()),
+                _      => Query::or(lt(), eq()),
+            });
+        },
+        _ => { /* handled below */ },
+    }
+
+    if word.starts_with("tag:") {
+        let tag = String::from(&word[4..]);
+        if tag.is_empty() {
+            return Err(FEK::InvalidValue.into_error());
+        }
+        return Ok(Query::predicate(HasTag::new(tag)));
+    }
+
+    if word.starts_with("header.") && word.len() > "header.".len() {
+        return parse_header_predicate(String::from(&word["header.".len()..]), tokens);
+    }
+
+    debug!("Unknown predicate in filter query: {}", word);
+    Err(FEK::UnexpectedToken.into_error())
+}
+
+fn parse_header_predicate(path: String, tokens: &mut Tokens) -> Result<Query> {
+    if is_keyword(tokens.peek(), "exists") {
+        tokens.next();
+        return Ok(Query::predicate(FieldExists::new(path)));
+    }
+
+    if is_keyword(tokens.peek(), "is") {
+        tokens.next();
+        let ty = match &try!(expect_value(tokens)).to_lowercase()[..] {
+            "empty"   => return Ok(Query::predicate(FieldIsEmpty::new(path))),
+            "array"   => Type::Array,
+            "boolean" => Type::Boolean,
+            "float"   => Type::Float,
+            "integer" => Type::Integer,
+            "string"  => Type::String,
+            "table"   => Type::Table,
+            _         => return Err(FEK::InvalidValue.into_error()),
+        };
+        return Ok(Query::predicate(FieldIsType::new(path, ty)));
+    }
+
+    let op    = try!(expect_op(tokens, &[Op::Eq, Op::Ne, Op::Lt, Op::Gt, Op::Le, Op::Ge, Op::Match]));
+    let value = try!(expect_token_value(tokens));
+
+    if op == Op::Match {
+        let regex = match value {
+            Value::String(s) => s,
+            _ => return Err(FEK::InvalidRegex.into_error()),
+        };
+        return Regex::new(&regex[..])
+            .map_err_into(FEK::InvalidRegex)
+            .map(|re| Query::predicate(FieldGrep::new(path, re)));
+    }
+
+    let eq = || Query::predicate(FieldEq::new(path.clone(), value.clone()));
+    let gt = || Query::predicate(FieldGt::new(path.clone(), value.clone()));
+    let lt = || Query::predicate(FieldLt::new(path.clone(), value.clone()));
+    Ok(match op {
+        Op::Eq => eq(),
+        Op::Ne => Query::not(eq()),
+        Op::Gt => gt(),
+        Op::Lt => lt(),
+        Op::Ge => Query::or(gt(), eq()),
+        _      => Query::or(lt(), eq()),
+    })
+}
+
+fn expect_op(tokens: &mut Tokens, allowed: &[Op]) -> Result<Op> {
+    match tokens.next() {
+        Some(Token::Op(op)) => if allowed.contains(&op) {
+            Ok(op)
+        } else {
+            debug!("Operator {:?} not allowed here", op);
+            Err(FEK::UnexpectedToken.into_error())
+        },
+        Some(_) => Err(FEK::UnexpectedToken.into_error()),
+        None    => Err(FEK::UnexpectedEnd.into_error()),
+    }
+}
+
+/// Get the next word or string as a plain string
+fn expect_value(tokens: &mut Tokens) -> Result<String> {
+    match tokens.next() {
+        Some(Token::Word(s)) |
+        Some(Token::Str(s))  => Ok(s),
+        Some(_) => Err(FEK::UnexpectedToken.into_error()),
+        None    => Err(FEK::UnexpectedEnd.into_error()),
+    }
+}
+
+/// Get the next word or string as TOML value
+///
+/// Quoted strings are always strings, words are converted to integers, floats or booleans if
+/// possible.
+fn expect_token_value(tokens: &mut Tokens) -> Result<Value> {
+    match tokens.next() {
+        Some(Token::Str(s))  => Ok(Value::String(s)),
+        Some(Token::Word(w)) => Ok({
+            if let Ok(i) = w.parse::<i64>() {
+                Value::Integer(i)
+            } else if let Ok(f) = w.parse::<f64>() {
+                Value::Float(f)
+            } else if let Ok(b) = w.parse::<bool>() {
+                Value::Boolean(b)
+            } else {
+                Value::String(w)
+            }
+        }),
+        Some(_) => Err(FEK::UnexpectedToken.into_error()),
+        None    => Err(FEK::UnexpectedEnd.into_error()),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use filters::filter::Filter;
+
+    use super::Query;
+    use test_util::entry;
+
+    fn matches(q: &str) -> bool {
+        Query::parse(q).unwrap().filter(&entry())
+    }
+
+    #[test]
+    fn test_header_comparisons() {
+        assert!(matches("header.counter.value > 3"));
+        assert!(!matches("header.counter.value < 3"));
+        assert!(matches("header.counter.value >= 5"));
+        assert!(matches("header.counter.name == coffee"));
+        assert!(matches("header.counter.name != \"tea\""));
+        assert!(matches("header.counter.name ~ \"^cof\""));
+        assert!(matches("header.counter.value is integer"));
+        assert!(matches("header.counter exists"));
+        assert!(!matches("header.counter.unit exists"));
+    }
+
+    #[test]
+    fn test_boolean_operators() {
+        assert!(matches("header.counter.value > 3 and content ~ \"draft\""));
+        assert!(!matches("header.counter.value > 3 and not content ~ \"draft\""));
+        assert!(matches("false or (true and not false)"));
+        assert!(matches("content.length > 3 and content.length < 100"));
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert!(Query::parse("").is_err());
+        assert!(Query::parse("header.a >").is_err());
+        assert!(Query::parse("(true").is_err());
+        assert!(Query::parse("true false").is_err());
+        assert!(Query::parse("content ~ \"(\"").is_err());
+        assert!(Query::parse("version > foo").is_err());
+        assert!(Query::parse("unknown == 1").is_err());
+    }
+
+}
+
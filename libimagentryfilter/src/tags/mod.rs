@@ -24,6 +24,8 @@ use libimagentrytag::tag::Tag;
 use filters::filter::Filter;
 
 /// Check whether an Entry has a certain tag
+///
+/// Tags are hierarchical, so filtering for `work` also matches entries tagged `work/review`.
 pub struct HasTag {
     tag: Tag,
 }
@@ -0,0 +1,45 @@
+//
+// imag - the personal information management suite for the commandline
+// Copyright (C) 2015, 2016 Matthias Beyer <mail@beyermatthias.de> and contributors
+//
+// This library is free software; you can redistribute it and/or
+// modify it under the terms of the GNU Lesser General Public
+// License as published by the Free Software Foundation; version
+// 2.1 of the License.
+//
+// This library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+// Lesser General Public License for more details.
+//
+// You should have received a copy of the GNU Lesser General Public
+// License along with this library; if not, write to the Free Software
+// Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA  02110-1301  USA
+//
+
+//! Fixtures for the tests of the filters
+
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+use toml::Value;
+
+use libimagstore::store::Entry;
+use libimagstore::storeid::StoreId;
+
+/// An entry with a "test" table holding an integer and a float, a "counter" table holding an
+/// integer and a string and the content "This is a draft"
+pub fn entry() -> Entry {
+    let mut e = Entry::new(StoreId::new_baseless(PathBuf::from("test/entry")).unwrap());
+    {
+        let hdr = e.get_header_mut();
+        hdr.insert("test", Value::Table(BTreeMap::new())).unwrap();
+        hdr.insert("test.int", Value::Integer(5)).unwrap();
+        hdr.insert("test.float", Value::Float(5.5)).unwrap();
+        hdr.insert("counter", Value::Table(BTreeMap::new())).unwrap();
+        hdr.insert("counter.value", Value::Integer(5)).unwrap();
+        hdr.insert("counter.name", Value::String(String::from("coffee"))).unwrap();
+    }
+    *e.get_content_mut() = String::from("This is a draft");
+    e
+}
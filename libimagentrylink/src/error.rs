@@ -27,7 +27,8 @@ generate_error_module!(
         InvalidUri              => "URI is not valid",
         StoreReadError          => "Store read error",
         StoreWriteError         => "Store write error",
-        StoreIdError            => "StoreId handling error"
+        StoreIdError            => "StoreId handling error",
+        EntryMoveError          => "Error while moving an entry"
     );
 );
 
@@ -36,6 +36,7 @@ use std::collections::BTreeMap;
 use libimagstore::store::Entry;
 use libimagstore::store::FileLockEntry;
 use libimagstore::store::Store;
+use libimagstore::store::Transaction;
 use libimagstore::storeid::StoreId;
 use libimagstore::storeid::IntoStoreId;
 use libimagutil::debug_result::*;
@@ -122,6 +123,70 @@ fn get_external_link_from_file(entry: &FileLockEntry) -> Result<Url> {
         .ok_or(LE::new(LEK::StoreReadError, None))
 }
 
+/// Implement `ExternalLinker` for `Entry`, hiding the fact that there is no such thing as an external
+/// link in an entry, but internal links to other entries which serve as external links, as one
+/// entry in the store can only have one external link.
+impl ExternalLinker for Entry {
+
+    /// Get the external links from the implementor object
+    fn get_external_links(&self, store: &Store) -> Result<Vec<Url>> {
+        // Iterate through all internal links and filter for FileLockEntries which live in
+        // /link/external/<SHA> -> load these files and get the external link from their headers,
+        // put them into the return vector.
+        self.get_internal_links()
+            .map(|iter| {
+                debug!("Getting external links");
+                iter.filter(|l| is_external_link_storeid(l))
+                    .map(|id| {
+                        debug!("Retrieving entry for id: '{:?}'", id);
+                        match store.retrieve(id.clone()) {
+                            Ok(f) => {
+                                debug!("Store::retrieve({:?}) succeeded", id);
+                                debug!("getting external link from file now");
+                                get_external_link_from_file(&f)
+                                    .map_err(|e| { debug!("URL -> Err = {:?}", e); e })
+                            },
+                            Err(e) => {
+                                debug!("Retrieving entry for id: '{:?}' failed", id);
+                                Err(LE::new(LEK::StoreReadError, Some(Box::new(e))))
+                            }
+                        }
+                    })
+                    .filter_map(|x| x.ok()) // TODO: Do not ignore error here
+                    .collect()
+            })
+            .map_err(|e| LE::new(LEK::StoreReadError, Some(Box::new(e))))
+    }
+
+    /// Set the external links for the implementor object
+    ///
+    /// The link entries are written in one transaction, the implementor object is written when it
+    /// is dropped. Use `set_external_links_atomically()` to write it in the same transaction.
+    fn set_external_links(&mut self, store: &Store, links: Vec<Url>) -> Result<()> {
+        let mut tx = store.transaction();
+        try!(add_link_entries(store, &mut tx, self, links));
+        tx.commit().map_err_into(LEK::StoreWriteError)
+    }
+
+    /// Add an external link to the implementor object
+    fn add_external_link(&mut self, store: &Store, link: Url) -> Result<()>;
+
+    /// Remove an external link from the implementor object
+    fn remove_external_link(&mut self, store: &Store, link: Url) -> Result<()>;
+
+}
+
+/// Check whether the StoreId starts with `/link/external/`
+pub fn is_external_link_storeid(id: &StoreId) -> bool {
+    debug!("Checking whether this is a 'links/external/': '{:?}'", id);
+    id.local().starts_with("links/external")
+}
+
+fn get_external_link_from_file(entry: &FileLockEntry) -> Result<Url> {
+    Link::get_link_uri_from_filelockentry(entry) // TODO: Do not hide error by using this function
+        .ok_or(LE::new(LEK::StoreReadError, None))
+}
+
 /// Implement `ExternalLinker` for `Entry`, hiding the fact that there is no such thing as an external
 /// link in an entry, but internal links to other entries which serve as external links, as one
 /// entry in the store can only have one external link.
@@ -256,3 +321,115 @@ impl ExternalLinker for Entry {
 
 }
 
+/// Set the external links of `entry` and write it and all link entries in one transaction
+pub fn set_external_links_atomically<'a>(store: &'a Store, mut entry: FileLockEntry<'a>, links: Vec<Url>)
+    -> Result<()>
+{
+    let mut tx = store.transaction();
+    let res    = add_link_entries(store, &mut tx, &mut entry, links);
+    tx.add(entry);
+    try!(res);
+    tx.commit().map_err_into(LEK::StoreWriteError)
+}
+
+/// Add an external link to `entry`, writing it and all link entries in one transaction
+pub fn add_external_link_atomically<'a>(store: &'a Store, entry: FileLockEntry<'a>, link: Url)
+    -> Result<()>
+{
+    let mut links = try!(entry.get_external_links(store));
+    debug!("Adding link = '{:?}' to links = {:?}", link, links);
+    links.push(link);
+    set_external_links_atomically(store, entry, links)
+}
+
+/// Remove an external link from `entry`, writing it and all link entries in one transaction
+pub fn remove_external_link_atomically<'a>(store: &'a Store, entry: FileLockEntry<'a>, link: Url)
+    -> Result<()>
+{
+    let links = try!(entry.get_external_links(store))
+        .into_iter()
+        .filter(|l| l.as_str() != link.as_str())
+        .collect();
+    debug!("Removing link = '{:?}' from links", link);
+    set_external_links_atomically(store, entry, links)
+}
+
+/// Create (or retrieve) the link entry for each of the `links`, link it with `entry` and add it
+/// to the transaction `tx`
+///
+/// Each link entry is added to the transaction even if linking it fails, so it is rolled back with
+/// the transaction.
+fn add_link_entries<'a>(store: &'a Store, tx: &mut Transaction<'a>, entry: &mut Entry, links: Vec<Url>)
+    -> Result<()>
+{
+    // Take all the links, generate a SHA sum out of each one, filter out the already existing
+    // store entries and store the other URIs in the header of one FileLockEntry each, in
+    // the path /link/external/<SHA of the URL>
+
+    debug!("Iterating {} links = {:?}", links.len(), links);
+    for link in links { // for all links
+        let hash = {
+            let mut s = Sha1::new();
+            s.input_str(&link.as_str()[..]);
+            s.result_str()
+        };
+        let file_id = try!(
+            ModuleEntryPath::new(format!("external/{}", hash)).into_storeid()
+                .map_err_into(LEK::StoreWriteError)
+                .map_dbg_err(|_| {
+                    format!("Failed to build StoreId for this hash '{:?}'", hash)
+                })
+            );
+
+        debug!("Link    = '{:?}'", link);
+        debug!("Hash    = '{:?}'", hash);
+        debug!("StoreId = '{:?}'", file_id);
+
+        // retrieve the file from the store, which implicitely creates the entry if it does not
+        // exist
+        let mut file = try!(store
+            .retrieve(file_id.clone())
+            .map_err_into(LEK::StoreWriteError)
+            .map_dbg_err(|_| {
+                format!("Failed to create or retrieve an file for this link '{:?}'", link)
+            }));
+
+        // then add an internal link to the new file or return an error if this fails
+        let res = set_link_url(file.deref_mut(), link)
+            .and_then(|_| {
+                entry.add_internal_link(file.deref_mut())
+                    .map_dbg_err_str("Error adding internal link")
+                    .map_err(|e| LE::new(LEK::StoreWriteError, Some(Box::new(e))))
+            });
+        tx.add(file);
+        try!(res);
+    }
+    debug!("Ready iterating");
+    Ok(())
+}
+
+/// Write `link` to the header of the link entry `file`
+fn set_link_url(file: &mut Entry, link: Url) -> Result<()> {
+    debug!("Generating header content!");
+    let hdr = file.get_header_mut();
+
+    let mut table = match hdr.read("imag.content") {
+        Ok(Some(Value::Table(table))) => table,
+        Ok(Some(_)) => {
+            warn!("There is a value at 'imag.content' which is not a table.");
+            warn!("Going to override this value");
+            BTreeMap::new()
+        },
+        Ok(None) => BTreeMap::new(),
+        Err(e)   => return Err(LE::new(LEK::StoreWriteError, Some(Box::new(e)))),
+    };
+
+    let v = Value::String(link.into_string());
+
+    debug!("setting URL = '{:?}", v);
+    table.insert(String::from("url"), v);
+
+    hdr.set("imag.content", Value::Table(table))
+        .map(|_| debug!("Setting URL worked"))
+        .map_err(|e| LE::new(LEK::StoreWriteError, Some(Box::new(e))))
+}
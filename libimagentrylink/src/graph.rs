@@ -0,0 +1,394 @@
+//
+// imag - the personal information management suite for the commandline
+// Copyright (C) 2015, 2016 Matthias Beyer <mail@beyermatthias.de> and contributors
+//
+// This library is free software; you can redistribute it and/or
+// modify it under the terms of the GNU Lesser General Public
+// License as published by the Free Software Foundation; version
+// 2.1 of the License.
+//
+// This library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+// Lesser General Public License for more details.
+//
+// You should have received a copy of the GNU Lesser General Public
+// License along with this library; if not, write to the Free Software
+// Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA  02110-1301  USA
+//
+
+//! Operations on the graph of internal links
+//!
+//! Internal links are always bidirectional, so the entries of the store and the internal links
+//! between them form an undirected graph. `LinkGraph` holds this graph in memory and offers the
+//! usual graph operations on it, as well as an export to Graphviz DOT and JSON.
+
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+use std::collections::btree_map::Keys;
+
+use serde_json;
+use serde_json::Value as JsonValue;
+
+use libimagstore::store::Store;
+use libimagstore::storeid::StoreId;
+
+use error::LinkErrorKind as LEK;
+use error::MapErrInto;
+use internal::InternalLinker;
+use result::Result;
+
+/// The undirected graph formed by entries and the internal links between them.
+///
+/// All nodes are `StoreId`s without base, like they are stored in the links themselves.
+#[derive(Debug, Clone, Default)]
+pub struct LinkGraph {
+    adjacency: BTreeMap<StoreId, BTreeSet<StoreId>>,
+}
+
+impl LinkGraph {
+
+    pub fn new() -> LinkGraph {
+        LinkGraph { adjacency: BTreeMap::new() }
+    }
+
+    /// Build the link graph of the whole store.
+    ///
+    /// Every entry of the store is a node, also if it has no links. Links to entries which do not
+    /// exist (anymore) are part of the graph as well.
+    pub fn from_store(store: &Store) -> Result<LinkGraph> {
+        let mut graph = LinkGraph::new();
+        let ids = try!(store.retrieve_for_module("").map_err_into(LEK::StoreReadError));
+
+        for id in ids {
+            let entry = try!(store.retrieve_copy(id.clone()).map_err_into(LEK::StoreReadError));
+            let node  = id.without_base();
+
+            graph.add_node(node.clone());
+            for link in try!(entry.get_internal_links()) {
+                graph.add_link(node.clone(), link);
+            }
+        }
+
+        Ok(graph)
+    }
+
+    /// Add a node without links. Does nothing if the node is already in the graph.
+    pub fn add_node(&mut self, id: StoreId) {
+        self.adjacency.entry(id).or_insert_with(BTreeSet::new);
+    }
+
+    /// Add a link between two nodes, adding the nodes if necessary
+    pub fn add_link(&mut self, a: StoreId, b: StoreId) {
+        if a == b {
+            self.add_node(a);
+            return;
+        }
+
+        self.adjacency.entry(a.clone()).or_insert_with(BTreeSet::new).insert(b.clone());
+        self.adjacency.entry(b).or_insert_with(BTreeSet::new).insert(a);
+    }
+
+    /// Number of nodes in the graph
+    pub fn len(&self) -> usize {
+        self.adjacency.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.adjacency.is_empty()
+    }
+
+    pub fn contains(&self, id: &StoreId) -> bool {
+        self.adjacency.contains_key(id)
+    }
+
+    /// Iterate over all nodes, in order
+    pub fn nodes(&self) -> Keys<StoreId, BTreeSet<StoreId>> {
+        self.adjacency.keys()
+    }
+
+    /// All links of the graph. Each link is contained once, with the smaller node first.
+    pub fn links(&self) -> Vec<(&StoreId, &StoreId)> {
+        self.adjacency
+            .iter()
+            .flat_map(|(a, neighbours)| {
+                neighbours.iter().filter(move |b| a < *b).map(move |b| (a, b))
+            })
+            .collect()
+    }
+
+    /// The nodes directly linked to `id`, or `None` if `id` is not in the graph
+    pub fn neighbours(&self, id: &StoreId) -> Option<&BTreeSet<StoreId>> {
+        self.adjacency.get(id)
+    }
+
+    /// All nodes reachable from `start` over at most `depth` links, with their distance to
+    /// `start`. `start` itself is contained with distance 0.
+    pub fn neighbourhood(&self, start: &StoreId, depth: usize) -> BTreeMap<StoreId, usize> {
+        let mut distances = BTreeMap::new();
+        if !self.contains(start) {
+            return distances;
+        }
+
+        let mut queue = VecDeque::new();
+        distances.insert(start.clone(), 0);
+        queue.push_back((start.clone(), 0));
+
+        while let Some((node, dist)) = queue.pop_front() {
+            if dist >= depth {
+                continue;
+            }
+
+            for next in self.adjacency.get(&node).into_iter().flat_map(|n| n.iter()) {
+                if !distances.contains_key(next) {
+                    distances.insert(next.clone(), dist + 1);
+                    queue.push_back((next.clone(), dist + 1));
+                }
+            }
+        }
+
+        distances
+    }
+
+    /// The shortest path from `from` to `to`, including both ends, or `None` if there is no path.
+    pub fn shortest_path(&self, from: &StoreId, to: &StoreId) -> Option<Vec<StoreId>> {
+        if !self.contains(from) || !self.contains(to) {
+            return None;
+        }
+
+        let mut predecessors : BTreeMap<StoreId, StoreId> = BTreeMap::new();
+        let mut visited = BTreeSet::new();
+        let mut queue   = VecDeque::new();
+
+        visited.insert(from.clone());
+        queue.push_back(from.clone());
+
+        while let Some(node) = queue.pop_front() {
+            if node == *to {
+                let mut path = vec![node];
+                loop {
+                    let pred = match predecessors.get(path.last().unwrap()) { // path is never empty
+                        Some(p) => p.clone(),
+                        None    => break,
+                    };
+                    path.push(pred);
+                }
+                path.reverse();
+                return Some(path);
+            }
+
+            for next in self.adjacency.get(&node).into_iter().flat_map(|n| n.iter()) {
+                if visited.insert(next.clone()) {
+                    predecessors.insert(next.clone(), node.clone());
+                    queue.push_back(next.clone());
+                }
+            }
+        }
+
+        None
+    }
+
+    /// The connected components of the graph, the largest first
+    pub fn components(&self) -> Vec<BTreeSet<StoreId>> {
+        let mut seen       = BTreeSet::new();
+        let mut components = vec![];
+
+        for node in self.adjacency.keys() {
+            if seen.contains(node) {
+                continue;
+            }
+
+            let component = self.neighbourhood(node, self.len())
+                .into_iter()
+                .map(|(id, _)| id)
+                .collect::<BTreeSet<StoreId>>();
+
+            for id in component.iter() {
+                seen.insert(id.clone());
+            }
+            components.push(component);
+        }
+
+        components.sort_by(|a, b| b.len().cmp(&a.len()));
+        components
+    }
+
+    /// All nodes without any link
+    pub fn orphans(&self) -> Vec<&StoreId> {
+        self.adjacency
+            .iter()
+            .filter(|&(_, neighbours)| neighbours.is_empty())
+            .map(|(id, _)| id)
+            .collect()
+    }
+
+    /// The graph restricted to the nodes for which `f` returns true
+    pub fn filter<F: Fn(&StoreId) -> bool>(&self, f: F) -> LinkGraph {
+        let adjacency = self.adjacency
+            .iter()
+            .filter(|&(id, _)| f(id))
+            .map(|(id, neighbours)| {
+                let neighbours = neighbours.iter().filter(|n| f(*n)).cloned().collect();
+                (id.clone(), neighbours)
+            })
+            .collect();
+
+        LinkGraph { adjacency: adjacency }
+    }
+
+    /// The graph restricted to the passed nodes
+    pub fn subgraph(&self, nodes: &BTreeSet<StoreId>) -> LinkGraph {
+        self.filter(|id| nodes.contains(id))
+    }
+
+    /// Export the graph in the Graphviz DOT format
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::from("graph imag {\n");
+
+        for node in self.nodes() {
+            dot.push_str(&format!("    {};\n", dot_id(node)));
+        }
+
+        for (a, b) in self.links() {
+            dot.push_str(&format!("    {} -- {};\n", dot_id(a), dot_id(b)));
+        }
+
+        dot.push_str("}\n");
+        dot
+    }
+
+    /// Export the graph as JSON object with a `nodes` array of ids and a `links` array of
+    /// `[a, b]` pairs
+    pub fn to_json(&self) -> Result<String> {
+        let nodes = self.nodes()
+            .map(|n| JsonValue::String(format!("{}", n)))
+            .collect();
+
+        let links = self.links()
+            .into_iter()
+            .map(|(a, b)| {
+                JsonValue::Array(vec![
+                    JsonValue::String(format!("{}", a)),
+                    JsonValue::String(format!("{}", b)),
+                ])
+            })
+            .collect();
+
+        let mut obj = BTreeMap::new();
+        obj.insert(String::from("nodes"), JsonValue::Array(nodes));
+        obj.insert(String::from("links"), JsonValue::Array(links));
+
+        serde_json::to_string_pretty(&JsonValue::Object(obj))
+            .map_err_into(LEK::InternalConversionError)
+    }
+
+}
+
+fn dot_id(id: &StoreId) -> String {
+    format!("\"{}\"", format!("{}", id).replace("\\", "\\\\").replace("\"", "\\\""))
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::BTreeSet;
+    use std::path::PathBuf;
+
+    use libimagstore::store::Store;
+    use libimagstore::storeid::StoreId;
+    use libimagstore::file_abstraction::InMemoryBackend;
+
+    use internal::InternalLinker;
+    use super::LinkGraph;
+
+    fn id(s: &str) -> StoreId {
+        StoreId::new_baseless(PathBuf::from(s)).unwrap()
+    }
+
+    // a - b - c - d    e - f    g
+    fn example() -> LinkGraph {
+        let mut graph = LinkGraph::new();
+        graph.add_link(id("a"), id("b"));
+        graph.add_link(id("b"), id("c"));
+        graph.add_link(id("c"), id("d"));
+        graph.add_link(id("e"), id("f"));
+        graph.add_node(id("g"));
+        graph
+    }
+
+    #[test]
+    fn test_neighbourhood() {
+        let graph = example();
+
+        let n = graph.neighbourhood(&id("b"), 1);
+        assert_eq!(vec![&id("a"), &id("b"), &id("c")], n.keys().collect::<Vec<_>>());
+
+        let n = graph.neighbourhood(&id("a"), 2);
+        assert_eq!(Some(&2), n.get(&id("c")));
+        assert!(!n.contains_key(&id("d")));
+
+        assert!(graph.neighbourhood(&id("x"), 2).is_empty());
+    }
+
+    #[test]
+    fn test_shortest_path() {
+        let mut graph = example();
+        assert_eq!(Some(vec![id("a"), id("b"), id("c"), id("d")]),
+                   graph.shortest_path(&id("a"), &id("d")));
+
+        graph.add_link(id("a"), id("d"));
+        assert_eq!(Some(vec![id("a"), id("d")]), graph.shortest_path(&id("a"), &id("d")));
+        assert_eq!(Some(vec![id("a")]), graph.shortest_path(&id("a"), &id("a")));
+        assert_eq!(None, graph.shortest_path(&id("a"), &id("e")));
+    }
+
+    #[test]
+    fn test_components_and_orphans() {
+        let graph      = example();
+        let components = graph.components();
+
+        assert_eq!(3, components.len());
+        assert_eq!(4, components[0].len());
+        assert_eq!(vec![&id("g")], graph.orphans());
+    }
+
+    #[test]
+    fn test_subgraph() {
+        let graph = example();
+        let nodes = vec![id("b"), id("c"), id("e")].into_iter().collect::<BTreeSet<_>>();
+        let sub   = graph.subgraph(&nodes);
+
+        assert_eq!(3, sub.len());
+        assert_eq!(1, sub.links().len());
+        assert_eq!(vec![&id("e")], sub.orphans());
+    }
+
+    #[test]
+    fn test_export() {
+        let mut graph = LinkGraph::new();
+        graph.add_link(id("notes/a"), id("todo/b"));
+
+        assert_eq!("graph imag {\n    \"notes/a\";\n    \"todo/b\";\n    \"notes/a\" -- \"todo/b\";\n}\n",
+                   graph.to_dot());
+
+        let json = graph.to_json().unwrap();
+        assert!(json.contains("\"nodes\""));
+        assert!(json.contains("\"notes/a\""));
+    }
+
+    #[test]
+    fn test_from_store() {
+        let store = Store::new(PathBuf::from("/"), None, Box::new(InMemoryBackend::new())).unwrap();
+        {
+            let mut a = store.create(PathBuf::from("a")).unwrap();
+            let mut b = store.create(PathBuf::from("b")).unwrap();
+            let _     = store.create(PathBuf::from("c")).unwrap();
+            assert!(a.add_internal_link(&mut b).is_ok());
+        }
+
+        let graph = LinkGraph::from_store(&store).unwrap();
+        assert_eq!(3, graph.len());
+        assert_eq!(vec![(&id("a"), &id("b"))], graph.links());
+        assert_eq!(vec![&id("c")], graph.orphans());
+    }
+
+}
+
@@ -17,33 +17,194 @@
 // Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA  02110-1301  USA
 //
 
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
 use libimagstore::storeid::StoreId;
 use libimagstore::store::Entry;
 use libimagstore::store::EntryHeader;
-use libimagstore::store::Result as StoreResult;
+use libimagstore::store::FileLockEntry;
+use libimagstore::store::Store;
 use libimagerror::into::IntoError;
 
 use error::LinkErrorKind as LEK;
 use error::MapErrInto;
 use result::Result;
 use self::iter::LinkIter;
-use self::iter::IntoValues;
 
 use toml::Value;
 
 pub type Link = StoreId;
 
+/// An internal link together with its relation type and metadata.
+///
+/// All links are stored as plain strings in the `imag.links` header array, like they always
+/// were, so `imag.links` can be queried (for example with `Store::query_header_contains()`). The
+/// type and metadata of typed links are stored in the separate `imag.typed_links` array of tables:
+///
+/// ```ignore
+/// [imag]
+/// links = [ "notes/plain", "todo/taskwarrior/a0e4..." ]
+///
+/// [[imag.typed_links]]
+/// link = "todo/taskwarrior/a0e4..."
+/// type = "references"
+/// meta = { note = "see" }
+/// ```
+///
+/// As links are two-way links, the linked entry gets the same link back, marked with
+/// `incoming = true`. So if a note references a task, the note has an outgoing "references" link
+/// to the task and the task an incoming "references" link to the note.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TypedLink {
+    link: Link,
+    kind: Option<String>,
+    incoming: bool,
+    meta: BTreeMap<String, Value>,
+}
+
+impl TypedLink {
+
+    /// A new outgoing link without type and metadata
+    pub fn new(link: Link) -> TypedLink {
+        TypedLink {
+            link: link.without_base(),
+            kind: None,
+            incoming: false,
+            meta: BTreeMap::new(),
+        }
+    }
+
+    pub fn with_kind(mut self, kind: String) -> TypedLink {
+        self.kind = Some(kind);
+        self
+    }
+
+    pub fn with_meta(mut self, meta: BTreeMap<String, Value>) -> TypedLink {
+        self.meta = meta;
+        self
+    }
+
+    pub fn get_link(&self) -> &Link {
+        &self.link
+    }
+
+    /// The relation type of the link, if any
+    pub fn get_kind(&self) -> Option<&str> {
+        self.kind.as_ref().map(|s| &s[..])
+    }
+
+    /// Whether the link was added from the linked entry to this one
+    pub fn is_incoming(&self) -> bool {
+        self.incoming
+    }
+
+    pub fn get_meta(&self) -> &BTreeMap<String, Value> {
+        &self.meta
+    }
+
+    /// The same link seen from the other side, pointing to `target`
+    fn reversed(&self, target: Link) -> TypedLink {
+        TypedLink {
+            link: target.without_base(),
+            kind: self.kind.clone(),
+            incoming: !self.incoming,
+            meta: self.meta.clone(),
+        }
+    }
+
+    fn is_plain(&self) -> bool {
+        self.kind.is_none() && self.meta.is_empty()
+    }
+
+    fn link_str(&self) -> Result<String> {
+        self.link.clone().without_base().to_str().map_err_into(LEK::InternalConversionError)
+    }
+
+    /// The table for the `imag.typed_links` array
+    fn to_table(&self) -> Result<Value> {
+        let mut table = BTreeMap::new();
+        table.insert(String::from("link"), Value::String(try!(self.link_str())));
+        if let Some(ref kind) = self.kind {
+            table.insert(String::from("type"), Value::String(kind.clone()));
+        }
+        if self.incoming {
+            table.insert(String::from("incoming"), Value::Boolean(true));
+        }
+        if !self.meta.is_empty() {
+            table.insert(String::from("meta"), Value::Table(self.meta.clone()));
+        }
+        Ok(Value::Table(table))
+    }
+
+    /// Read a link from an element of `imag.links` or `imag.typed_links`
+    ///
+    /// Tables are accepted in `imag.links` as well, as they were stored there by earlier versions.
+    fn from_value(v: Value) -> Result<TypedLink> {
+        let to_link = |s: String| StoreId::new_baseless(PathBuf::from(s)).map_err_into(LEK::StoreIdError);
+
+        match v {
+            Value::String(s) => to_link(s).map(TypedLink::new),
+            Value::Table(mut t) => {
+                let link = match t.remove("link") {
+                    Some(Value::String(s)) => try!(to_link(s)),
+                    _ => return Err(LEK::ExistingLinkTypeWrong.into_error()),
+                };
+
+                let kind = match t.remove("type") {
+                    Some(Value::String(s)) => Some(s),
+                    None                   => None,
+                    Some(_)                => return Err(LEK::ExistingLinkTypeWrong.into_error()),
+                };
+
+                let incoming = match t.remove("incoming") {
+                    Some(Value::Boolean(b)) => b,
+                    None                    => false,
+                    Some(_)                 => return Err(LEK::ExistingLinkTypeWrong.into_error()),
+                };
+
+                let meta = match t.remove("meta") {
+                    Some(Value::Table(m)) => m,
+                    None                  => BTreeMap::new(),
+                    Some(_)               => return Err(LEK::ExistingLinkTypeWrong.into_error()),
+                };
+
+                Ok(TypedLink { link: link, kind: kind, incoming: incoming, meta: meta })
+            },
+            _ => Err(LEK::ExistingLinkTypeWrong.into_error()),
+        }
+    }
+
+}
+
+/// Linking entries in memory
+///
+/// The functions of this trait alter the entries in memory only, each entry is written when it
+/// is dropped. Use `link_entries()`, `link_entries_typed()` and `unlink_entries()` to write both
+/// sides of a link in one transaction.
 pub trait InternalLinker {
 
     /// Get the internal links from the implementor object
     fn get_internal_links(&self) -> Result<LinkIter>;
 
+    /// Get the internal links from the implementor object, with their type and metadata
+    fn get_typed_internal_links(&self) -> Result<Vec<TypedLink>>;
+
+    /// Get the internal links of the relation type `kind`, in both directions
+    fn get_internal_links_by_type(&self, kind: &str) -> Result<Vec<TypedLink>>;
+
     /// Set the internal links for the implementor object
     fn set_internal_links(&mut self, links: Vec<&mut Entry>) -> Result<LinkIter>;
 
     /// Add an internal link to the implementor object
     fn add_internal_link(&mut self, link: &mut Entry) -> Result<()>;
 
+    /// Add an internal link with a relation type and metadata to the implementor object.
+    ///
+    /// If the entries are linked already, the type and metadata of the link are replaced.
+    fn add_typed_internal_link(&mut self, link: &mut Entry, kind: &str, meta: BTreeMap<String, Value>)
+        -> Result<()>;
+
     /// Remove an internal link from the implementor object
     fn remove_internal_link(&mut self, link: &mut Entry) -> Result<()>;
 
@@ -109,51 +270,56 @@ pub mod iter {
 impl InternalLinker for Entry {
 
     fn get_internal_links(&self) -> Result<LinkIter> {
-        process_rw_result(self.get_header().read("imag.links"))
+        read_typed_links(self.get_header())
+            .map(|links| LinkIter::new(links.into_iter().map(|l| l.link).collect()))
+    }
+
+    fn get_typed_internal_links(&self) -> Result<Vec<TypedLink>> {
+        read_typed_links(self.get_header())
+    }
+
+    fn get_internal_links_by_type(&self, kind: &str) -> Result<Vec<TypedLink>> {
+        read_typed_links(self.get_header())
+            .map(|links| links.into_iter().filter(|l| l.get_kind() == Some(kind)).collect())
     }
 
     /// Set the links in a header and return the old links, if any.
+    ///
+    /// Links which exist already keep their type and metadata.
     fn set_internal_links(&mut self, links: Vec<&mut Entry>) -> Result<LinkIter> {
-        use internal::iter::IntoValues;
-
         let self_location = self.get_location().clone();
+        let old_links     = try!(read_typed_links(self.get_header()));
         let mut new_links = vec![];
 
         for link in links {
-            if let Err(e) = add_foreign_link(link, self_location.clone()) {
-                return Err(e);
-            }
-            let link = link.get_location().clone();
-            new_links.push(link);
+            let target = link.get_location().clone().without_base();
+            let existing = old_links.iter().find(|l| l.link == target).cloned();
+            let typed    = existing.unwrap_or_else(|| TypedLink::new(target));
+
+            try!(add_foreign_link(link, typed.reversed(self_location.clone()), false));
+            new_links.push(typed);
         }
 
-        let new_links = try!(LinkIter::new(new_links)
-                             .into_values()
-                             .fold(Ok(vec![]), |acc, elem| {
-                                acc.and_then(move |mut v| {
-                                    elem.map_err_into(LEK::InternalConversionError)
-                                        .map(|e| {
-                                            v.push(e);
-                                            v
-                                        })
-                                })
-                            }));
-        process_rw_result(self.get_header_mut().set("imag.links", Value::Array(new_links)))
+        try!(write_typed_links(self.get_header_mut(), new_links));
+        Ok(LinkIter::new(old_links.into_iter().map(|l| l.link).collect()))
     }
 
     fn add_internal_link(&mut self, link: &mut Entry) -> Result<()> {
-        let new_link = link.get_location().clone();
+        let typed = TypedLink::new(link.get_location().clone());
 
-        debug!("Adding internal link from {:?} to {:?}", self.get_location(), new_link);
+        debug!("Adding internal link from {:?} to {:?}", self.get_location(), typed.link);
+        add_link(self, link, typed, false)
+    }
 
-        add_foreign_link(link, self.get_location().clone())
-            .and_then(|_| {
-                self.get_internal_links()
-                    .and_then(|links| {
-                        let links = links.chain(LinkIter::new(vec![new_link]));
-                        rewrite_links(self.get_header_mut(), links)
-                    })
-            })
+    fn add_typed_internal_link(&mut self, link: &mut Entry, kind: &str, meta: BTreeMap<String, Value>)
+        -> Result<()>
+    {
+        let typed = TypedLink::new(link.get_location().clone())
+            .with_kind(String::from(kind))
+            .with_meta(meta);
+
+        debug!("Adding internal link of type '{}' from {:?} to {:?}", kind, self.get_location(), typed.link);
+        add_link(self, link, typed, true)
     }
 
     fn remove_internal_link(&mut self, link: &mut Entry) -> Result<()> {
@@ -162,74 +328,153 @@ impl InternalLinker for Entry {
 
         debug!("Removing internal link from {:?} to {:?}", own_loc, other_loc);
 
-        link.get_internal_links()
+        read_typed_links(link.get_header())
             .and_then(|links| {
                 debug!("Rewriting own links for {:?}, without {:?}", other_loc, own_loc);
-                rewrite_links(link.get_header_mut(), links.filter(|l| *l != own_loc))
+                let links = links.into_iter().filter(|l| l.link != own_loc).collect();
+                write_typed_links(link.get_header_mut(), links)
             })
             .and_then(|_| {
-                self.get_internal_links()
+                read_typed_links(self.get_header())
                     .and_then(|links| {
                         debug!("Rewriting own links for {:?}, without {:?}", own_loc, other_loc);
-                        rewrite_links(self.get_header_mut(), links.filter(|l| *l != other_loc))
+                        let links = links.into_iter().filter(|l| l.link != other_loc).collect();
+                        write_typed_links(self.get_header_mut(), links)
                     })
             })
     }
 
 }
 
-fn rewrite_links<I: Iterator<Item = Link>>(header: &mut EntryHeader, links: I) -> Result<()> {
-    let links = try!(links.into_values()
-                     .fold(Ok(vec![]), |acc, elem| {
-                        acc.and_then(move |mut v| {
-                            elem.map_err_into(LEK::InternalConversionError)
-                                .map(|e| {
-                                    v.push(e);
-                                    v
-                                })
-                        })
-                     }));
-
-    debug!("Setting new link array: {:?}", links);
-    let process = header.set("imag.links", Value::Array(links));
-    process_rw_result(process).map(|_| ())
+/// Replace the link to `from` in the links of `entry` with a link to `to`.
+///
+/// Only the links of `entry` are altered. This is meant to be used after the entry `from` was moved
+/// to `to`, as then the links of the moved entry itself are still valid. The type and metadata of
+/// the link are kept.
+pub fn replace_internal_link(entry: &mut Entry, from: &StoreId, to: &StoreId) -> Result<()> {
+    let from = from.clone().without_base();
+    let to   = to.clone().without_base();
+
+    debug!("Replacing link {:?} with {:?} in {:?}", from, to, entry.get_location());
+    read_typed_links(entry.get_header())
+        .and_then(|links| {
+            let links = links
+                .into_iter()
+                .map(|mut l| {
+                    if l.link == from {
+                        l.link = to.clone();
+                    }
+                    l
+                })
+                .collect();
+            write_typed_links(entry.get_header_mut(), links)
+        })
 }
 
-/// When Linking A -> B, the specification wants us to link back B -> A.
-/// This is a helper function which does this.
-fn add_foreign_link(target: &mut Entry, from: StoreId) -> Result<()> {
-    debug!("Linking back from {:?} to {:?}", target.get_location(), from);
-    target.get_internal_links()
-        .and_then(|links| {
-            let links = try!(links
-                             .chain(LinkIter::new(vec![from]))
-                             .into_values()
-                             .fold(Ok(vec![]), |acc, elem| {
-                                acc.and_then(move |mut v| {
-                                    elem.map_err_into(LEK::InternalConversionError)
-                                        .map(|e| {
-                                            v.push(e);
-                                            v
-                                        })
-                                })
-                             }));
-            debug!("Setting links in {:?}: {:?}", target.get_location(), links);
-            process_rw_result(target.get_header_mut().set("imag.links", Value::Array(links)))
-                .map(|_| ())
+/// Link `from` with each entry in `to` and write all entries in one transaction
+///
+/// If linking or writing fails, none of the entries is altered, so a link never exists on one
+/// side only.
+pub fn link_entries<'a>(store: &'a Store, from: FileLockEntry<'a>, to: Vec<FileLockEntry<'a>>)
+    -> Result<()>
+{
+    commit_linked(store, from, to, |from, to| from.add_internal_link(to))
+}
+
+/// Like `link_entries()`, but with a relation type and metadata for the links
+pub fn link_entries_typed<'a>(store: &'a Store,
+                              from: FileLockEntry<'a>,
+                              to: Vec<FileLockEntry<'a>>,
+                              kind: &str,
+                              meta: BTreeMap<String, Value>)
+    -> Result<()>
+{
+    commit_linked(store, from, to, |from, to| from.add_typed_internal_link(to, kind, meta.clone()))
+}
+
+/// Remove the links between `from` and each entry in `to` and write all entries in one
+/// transaction
+pub fn unlink_entries<'a>(store: &'a Store, from: FileLockEntry<'a>, to: Vec<FileLockEntry<'a>>)
+    -> Result<()>
+{
+    commit_linked(store, from, to, |from, to| from.remove_internal_link(to))
+}
+
+/// Call `f` for `from` and each entry in `to` and commit all entries in one transaction
+///
+/// All entries are added to the transaction before anything can fail, so on failure the
+/// transaction is rolled back and no entry is written.
+fn commit_linked<'a, F>(store: &'a Store, from: FileLockEntry<'a>, to: Vec<FileLockEntry<'a>>, f: F)
+    -> Result<()>
+    where F: Fn(&mut Entry, &mut Entry) -> Result<()>
+{
+    let from_id = from.get_location().clone();
+    let mut tx  = store.transaction();
+    tx.add(from);
+
+    for mut entry in to {
+        let res = match tx.get_mut(&from_id) {
+            Some(from) => f(&mut **from, &mut *entry),
+            None       => Err(LEK::StoreWriteError.into_error()),
+        };
+        tx.add(entry);
+        try!(res);
+    }
+
+    tx.commit().map_err_into(LEK::StoreWriteError)
+}
+
+/// Add `typed` to the links of `from` and the reversed link to the links of `to`.
+fn add_link(from: &mut Entry, to: &mut Entry, typed: TypedLink, replace: bool) -> Result<()> {
+    let from_location = from.get_location().clone();
+
+    add_foreign_link(to, typed.reversed(from_location), replace)
+        .and_then(|_| add_foreign_link(from, typed, replace))
+}
+
+/// Add a link to the links of `target`.
+///
+/// If `target` links to the same entry already, the existing link is kept, unless `replace` is
+/// set.
+fn add_foreign_link(target: &mut Entry, link: TypedLink, replace: bool) -> Result<()> {
+    debug!("Adding link in {:?}: {:?}", target.get_location(), link);
+    read_typed_links(target.get_header())
+        .and_then(|mut links| {
+            let existing = links.iter().position(|l| l.link == link.link);
+            match existing {
+                Some(i) => if replace {
+                    links[i] = link;
+                },
+                None => links.push(link),
+            }
+            write_typed_links(target.get_header_mut(), links)
         })
 }
 
-fn process_rw_result(links: StoreResult<Option<Value>>) -> Result<LinkIter> {
-    use std::path::PathBuf;
+/// Read all links from the `imag.links` header field, together with their type and metadata from
+/// the `imag.typed_links` header field
+fn read_typed_links(header: &EntryHeader) -> Result<Vec<TypedLink>> {
+    let mut links = try!(read_link_array(header, "imag.links"));
+
+    for typed in try!(read_link_array(header, "imag.typed_links")) {
+        match links.iter().position(|l| l.link == typed.link) {
+            Some(i) => links[i] = typed,
+            None    => links.push(typed),
+        }
+    }
+
+    Ok(links)
+}
 
-    let links = match links {
+fn read_link_array(header: &EntryHeader, path: &str) -> Result<Vec<TypedLink>> {
+    let links = match header.read(path) {
         Err(e) => {
             debug!("RW action on store failed. Generating LinkError");
             return Err(LEK::EntryHeaderReadError.into_error_with_cause(Box::new(e)))
         },
         Ok(None) => {
             debug!("We got no value from the header!");
-            return Ok(LinkIter::new(vec![]))
+            return Ok(vec![])
         },
         Ok(Some(Value::Array(l))) => l,
         Ok(Some(_)) => {
@@ -238,41 +483,60 @@ fn process_rw_result(links: StoreResult<Option<Value>>) -> Result<LinkIter> {
         }
     };
 
-    if !links.iter().all(|l| is_match!(*l, Value::String(_))) {
-        debug!("At least one of the Values which were expected in the Array of links is a non-String!");
-        debug!("Generating LinkError");
-        return Err(LEK::ExistingLinkTypeWrong.into());
-    }
+    links.into_iter().map(TypedLink::from_value).collect()
+}
 
-    let links : Vec<Link> = try!(links.into_iter()
-        .map(|link| {
-            match link {
-                Value::String(s) => StoreId::new_baseless(PathBuf::from(s))
-                    .map_err_into(LEK::StoreIdError),
-                _ => unreachable!(),
-            }
-        })
-        .collect());
+/// Write the links to the `imag.links` header field, sorted and without duplicates, and the type
+/// and metadata of the typed ones to the `imag.typed_links` header field
+fn write_typed_links(header: &mut EntryHeader, links: Vec<TypedLink>) -> Result<()> {
+    let mut sorted : BTreeMap<String, TypedLink> = BTreeMap::new();
+    for link in links {
+        let key = try!(link.link_str());
+        if !sorted.contains_key(&key) {
+            sorted.insert(key, link);
+        }
+    }
 
-    debug!("Ok, the RW action was successful, returning link vector now!");
-    Ok(LinkIter::new(links))
+    let plain = sorted.keys().cloned().map(Value::String).collect();
+    let typed = try!(sorted
+                     .values()
+                     .filter(|l| !l.is_plain())
+                     .map(|l| l.to_table())
+                     .collect::<Result<Vec<Value>>>());
+
+    debug!("Setting new link array: {:?}", plain);
+    try!(header.set("imag.links", Value::Array(plain))
+         .map_err(|e| LEK::EntryHeaderWriteError.into_error_with_cause(Box::new(e))));
+
+    let res = if typed.is_empty() {
+        header.delete("imag.typed_links").map(|_| ())
+    } else {
+        debug!("Setting new typed link array: {:?}", typed);
+        header.set("imag.typed_links", Value::Array(typed)).map(|_| ())
+    };
+    res.map_err(|e| LEK::EntryHeaderWriteError.into_error_with_cause(Box::new(e)))
 }
 
 #[cfg(test)]
 mod test {
+    use std::collections::BTreeMap;
     use std::path::PathBuf;
 
     use libimagstore::store::Store;
+    use libimagstore::file_abstraction::InMemoryBackend;
 
     use super::InternalLinker;
 
+    use toml::Value;
+
     fn setup_logging() {
         use env_logger;
         let _ = env_logger::init().unwrap_or(());
     }
 
     pub fn get_store() -> Store {
-        Store::new(PathBuf::from("/"), None).unwrap()
+        let backend = Box::new(InMemoryBackend::new());
+        Store::new(PathBuf::from("/"), None, backend).unwrap()
     }
 
     #[test]
@@ -403,5 +667,112 @@ mod test {
 
     }
 
-}
+    #[test]
+    fn test_typed_links() {
+        setup_logging();
+        let store = get_store();
+
+        let mut note = store.retrieve(PathBuf::from("note")).unwrap();
+        let mut task = store.retrieve(PathBuf::from("task")).unwrap();
+        let mut other = store.retrieve(PathBuf::from("other")).unwrap();
+
+        let mut meta = BTreeMap::new();
+        meta.insert(String::from("comment"), Value::String(String::from("see")));
+
+        assert!(note.add_typed_internal_link(&mut task, "references", meta.clone()).is_ok());
+        assert!(note.add_internal_link(&mut other).is_ok());
+
+        let links = note.get_typed_internal_links().unwrap();
+        assert_eq!(links.len(), 2);
+
+        let refs = note.get_internal_links_by_type("references").unwrap();
+        assert_eq!(refs.len(), 1);
+        assert_eq!(refs[0].get_link().to_str().unwrap(), "task");
+        assert!(!refs[0].is_incoming());
+        assert_eq!(refs[0].get_meta(), &meta);
+
+        let back = task.get_internal_links_by_type("references").unwrap();
+        assert_eq!(back.len(), 1);
+        assert_eq!(back[0].get_link().to_str().unwrap(), "note");
+        assert!(back[0].is_incoming());
+
+        // All links are stored as plain strings, the types separately
+        let header_links = note.get_header().read("imag.links").unwrap().unwrap();
+        assert_eq!(header_links, Value::Array(vec![Value::String(String::from("other")),
+                                                   Value::String(String::from("task"))]));
+        match note.get_header().read("imag.typed_links").unwrap().unwrap() {
+            Value::Array(a) => assert_eq!(a.len(), 1),
+            _ => panic!("Typed links are not an array"),
+        }
+
+        // Adding an untyped link again keeps the type
+        assert!(note.add_internal_link(&mut task).is_ok());
+        assert_eq!(note.get_internal_links_by_type("references").unwrap().len(), 1);
+        assert_eq!(note.get_internal_links().unwrap().collect::<Vec<_>>().len(), 2);
+
+        assert!(note.remove_internal_link(&mut task).is_ok());
+        assert_eq!(note.get_internal_links_by_type("references").unwrap().len(), 0);
+        assert_eq!(task.get_internal_links().unwrap().collect::<Vec<_>>().len(), 0);
+    }
+
+    #[test]
+    fn test_link_entries() {
+        use super::{link_entries, unlink_entries};
+
+        setup_logging();
+        let store = get_store();
+        let a     = store.create(PathBuf::from("test_link_entries_a")).unwrap();
+        let b     = store.create(PathBuf::from("test_link_entries_b")).unwrap();
+        let c     = store.create(PathBuf::from("test_link_entries_c")).unwrap();
+
+        assert!(link_entries(&store, a, vec![b, c]).is_ok());
+
+        let links_of = |name: &str| {
+            store.retrieve_copy(PathBuf::from(name))
+                .unwrap()
+                .get_internal_links()
+                .unwrap()
+                .collect::<Vec<_>>()
+                .len()
+        };
+        assert_eq!(links_of("test_link_entries_a"), 2);
+        assert_eq!(links_of("test_link_entries_b"), 1);
+        assert_eq!(links_of("test_link_entries_c"), 1);
+
+        let a = store.retrieve(PathBuf::from("test_link_entries_a")).unwrap();
+        let b = store.retrieve(PathBuf::from("test_link_entries_b")).unwrap();
+        assert!(unlink_entries(&store, a, vec![b]).is_ok());
+
+        assert_eq!(links_of("test_link_entries_a"), 1);
+        assert_eq!(links_of("test_link_entries_b"), 0);
+        assert_eq!(links_of("test_link_entries_c"), 1);
+    }
+
+    #[test]
+    fn test_mixed_links_roundtrip() {
+        use libimagstore::store::Entry;
+
+        setup_logging();
+        let store     = get_store();
+        let mut note  = store.create(PathBuf::from("test_roundtrip_note")).unwrap();
+        let mut task  = store.create(PathBuf::from("test_roundtrip_task")).unwrap();
+        let mut other = store.create(PathBuf::from("test_roundtrip_other")).unwrap();
 
+        let mut meta = BTreeMap::new();
+        meta.insert(String::from("comment"), Value::String(String::from("see")));
+
+        assert!(note.add_typed_internal_link(&mut task, "references", meta.clone()).is_ok());
+        assert!(note.add_internal_link(&mut other).is_ok());
+
+        for entry in vec![&note, &task] {
+            let serialized = entry.to_str();
+            let parsed     = Entry::from_str(entry.get_location().clone(), &serialized);
+            assert!(parsed.is_ok(), "Cannot parse serialized entry:\n{}", serialized);
+
+            let parsed = parsed.unwrap();
+            assert_eq!(parsed.get_typed_internal_links().unwrap(),
+                       entry.get_typed_internal_links().unwrap());
+        }
+    }
+
+}
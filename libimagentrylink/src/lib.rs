@@ -37,6 +37,7 @@ extern crate toml;
 extern crate semver;
 extern crate url;
 extern crate crypto;
+extern crate serde_json;
 
 #[cfg(test)]
 extern crate env_logger;
@@ -49,6 +50,8 @@ module_entry_path_mod!("links");
 
 pub mod error;
 pub mod external;
+pub mod graph;
 pub mod internal;
+pub mod relocate;
 pub mod result;
 
@@ -0,0 +1,226 @@
+//
+// imag - the personal information management suite for the commandline
+// Copyright (C) 2015, 2016 Matthias Beyer <mail@beyermatthias.de> and contributors
+//
+// This library is free software; you can redistribute it and/or
+// modify it under the terms of the GNU Lesser General Public
+// License as published by the Free Software Foundation; version
+// 2.1 of the License.
+//
+// This library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+// Lesser General Public License for more details.
+//
+// You should have received a copy of the GNU Lesser General Public
+// License along with this library; if not, write to the Free Software
+// Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA  02110-1301  USA
+//
+
+//! Moving entries while keeping the link graph intact
+//!
+//! `Store::move_by_id` and `Store::save_as` only move the entry itself. As links are always two-way
+//! links, every entry the moved entry links to still points to the old location afterwards. The
+//! functions in this module move the entry and rewrite the links in all linked entries (including
+//! the helper entries for external links), so the link graph stays intact.
+
+use libimagstore::store::FileLockEntry;
+use libimagstore::store::Store;
+use libimagstore::store::Transaction;
+use libimagstore::storeid::StoreId;
+use libimagerror::into::IntoError;
+use libimagerror::trace::trace_error;
+
+use error::LinkErrorKind as LEK;
+use error::MapErrInto;
+use internal::InternalLinker;
+use internal::Link;
+use internal::replace_internal_link;
+use result::Result;
+
+/// Move the entry `from` to `to` and rewrite the links of all entries which are linked to it.
+///
+/// This is the link-aware version of `Store::move_by_id`. The linked entries are written in one
+/// transaction after the entry was moved. If that fails, the entry is moved back.
+pub fn move_entry(store: &Store, from: StoreId, to: StoreId) -> Result<()> {
+    let exists = try!(store.exists(from.clone())
+                      .map_err_into(LEK::StoreReadError)
+                      .map_err_into(LEK::EntryMoveError));
+    if !exists {
+        return Err(LEK::LinkTargetDoesNotExist.into_error()).map_err_into(LEK::EntryMoveError);
+    }
+
+    let links = try!(store.retrieve_copy(from.clone())
+                     .map_err_into(LEK::StoreReadError)
+                     .and_then(|entry| entry.get_internal_links())
+                     .map_err_into(LEK::EntryMoveError))
+        .collect::<Vec<Link>>();
+
+    let mut tx = store.transaction();
+    let self_linked = try!(rewrite_foreign_links(store, &mut tx, links, &from, &to));
+
+    try!(store.move_by_id(from.clone(), to.clone())
+         .map_err_into(LEK::StoreWriteError)
+         .map_err_into(LEK::EntryMoveError));
+
+    finish_relocation(store, tx, self_linked, &from, &to)
+        .map_err(|e| {
+            if let Err(e) = store.move_by_id(to.clone(), from.clone()) {
+                warn!("Moving {:?} back to {:?} failed", to, from);
+                trace_error(&e);
+            }
+            e
+        })
+}
+
+/// Save the `entry` as `to`, remove the old entry and rewrite the links of all entries which are
+/// linked to it.
+///
+/// This is the link-aware version of `Store::save_as`. The linked entries are written in one
+/// transaction after the entry was saved.
+pub fn save_entry_as<'a>(store: &'a Store, entry: FileLockEntry<'a>, to: StoreId) -> Result<()> {
+    let from  = entry.get_location().clone();
+    let links = try!(entry.get_internal_links().map_err_into(LEK::EntryMoveError))
+        .collect::<Vec<Link>>();
+
+    let mut tx = store.transaction();
+    let self_linked = try!(rewrite_foreign_links(store, &mut tx, links, &from, &to));
+
+    try!(store.save_as(entry, to.clone())
+         .map_err_into(LEK::StoreWriteError)
+         .map_err_into(LEK::EntryMoveError));
+
+    finish_relocation(store, tx, self_linked, &from, &to)
+}
+
+/// Rewrite the links to `from` in all entries in `links` to point to `to` and add the entries to
+/// the transaction `tx`
+///
+/// The entry `from` itself cannot be altered before it was moved, so if it links to itself, this
+/// returns `true` and the caller has to rewrite the link after the move.
+fn rewrite_foreign_links<'a>(store: &'a Store,
+                             tx: &mut Transaction<'a>,
+                             links: Vec<Link>,
+                             from: &StoreId,
+                             to: &StoreId)
+    -> Result<bool>
+{
+    let baseless_from = from.clone().without_base();
+    let mut self_linked = false;
+
+    for link in links {
+        if link == baseless_from {
+            self_linked = true;
+            continue;
+        }
+
+        debug!("Rewriting link {:?} -> {:?} in {:?}", from, to, link);
+        let mut entry = try!(store.retrieve(link)
+                             .map_err_into(LEK::StoreReadError)
+                             .map_err_into(LEK::EntryMoveError));
+
+        let res = replace_internal_link(&mut entry, from, to).map_err_into(LEK::EntryMoveError);
+        tx.add(entry);
+        try!(res);
+    }
+
+    Ok(self_linked)
+}
+
+/// Rewrite the link of the moved entry to itself, if any, and commit the transaction
+fn finish_relocation<'a>(store: &'a Store,
+                         mut tx: Transaction<'a>,
+                         self_linked: bool,
+                         from: &StoreId,
+                         to: &StoreId)
+    -> Result<()>
+{
+    if self_linked {
+        let mut entry = try!(store.retrieve(to.clone())
+                             .map_err_into(LEK::StoreReadError)
+                             .map_err_into(LEK::EntryMoveError));
+
+        let res = replace_internal_link(&mut entry, from, to).map_err_into(LEK::EntryMoveError);
+        tx.add(entry);
+        try!(res);
+    }
+
+    tx.commit()
+        .map_err_into(LEK::StoreWriteError)
+        .map_err_into(LEK::EntryMoveError)
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::PathBuf;
+
+    use libimagstore::store::Store;
+    use libimagstore::storeid::StoreId;
+    use libimagstore::file_abstraction::InMemoryBackend;
+
+    use internal::InternalLinker;
+    use super::move_entry;
+    use super::save_entry_as;
+
+    pub fn get_store() -> Store {
+        let backend = Box::new(InMemoryBackend::new());
+        Store::new(PathBuf::from("/"), None, backend).unwrap()
+    }
+
+    fn links_of(store: &Store, name: &str) -> Vec<StoreId> {
+        store.get(PathBuf::from(name))
+            .unwrap()
+            .unwrap()
+            .get_internal_links()
+            .unwrap()
+            .collect()
+    }
+
+    fn id(name: &str) -> StoreId {
+        StoreId::new_baseless(PathBuf::from(name)).unwrap()
+    }
+
+    #[test]
+    fn test_move_entry_rewrites_links() {
+        let store = get_store();
+
+        {
+            let mut e1 = store.create(PathBuf::from("test_move_entry1")).unwrap();
+            let mut e2 = store.create(PathBuf::from("test_move_entry2")).unwrap();
+            assert!(e1.add_internal_link(&mut e2).is_ok());
+        }
+
+        assert!(move_entry(&store, id("test_move_entry2"), id("test_move_entry3")).is_ok());
+
+        assert_eq!(links_of(&store, "test_move_entry1"), vec![id("test_move_entry3")]);
+        assert_eq!(links_of(&store, "test_move_entry3"), vec![id("test_move_entry1")]);
+        assert!(match store.get(PathBuf::from("test_move_entry2")) { Ok(None) => true, _ => false });
+    }
+
+    #[test]
+    fn test_move_nonexistent_entry() {
+        let store = get_store();
+        assert!(move_entry(&store, id("test_move_nonexistent1"), id("test_move_nonexistent2")).is_err());
+    }
+
+    #[test]
+    fn test_save_entry_as_rewrites_links() {
+        let store = get_store();
+
+        {
+            let mut e1 = store.create(PathBuf::from("test_save_entry_as1")).unwrap();
+            let mut e2 = store.create(PathBuf::from("test_save_entry_as2")).unwrap();
+            assert!(e1.add_internal_link(&mut e2).is_ok());
+        }
+
+        {
+            let e2 = store.retrieve(PathBuf::from("test_save_entry_as2")).unwrap();
+            assert!(save_entry_as(&store, e2, id("test_save_entry_as3")).is_ok());
+        }
+
+        assert_eq!(links_of(&store, "test_save_entry_as1"), vec![id("test_save_entry_as3")]);
+        assert_eq!(links_of(&store, "test_save_entry_as3"), vec![id("test_save_entry_as1")]);
+        assert!(match store.get(PathBuf::from("test_save_entry_as2")) { Ok(None) => true, _ => false });
+    }
+
+}
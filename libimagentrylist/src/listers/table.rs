@@ -57,11 +57,8 @@ impl<F: Fn(&FileLockEntry) -> Vec<String>> TableLister<F> {
         self
     }
 
-}
-
-impl<F: Fn(&FileLockEntry) -> Vec<String>> Lister for TableLister<F> {
-
-    fn list<'b, I: Iterator<Item = FileLockEntry<'b>>>(&self, entries: I) -> Result<()> {
+    /// List rows which are not generated from entries, like the lines of an aggregated report
+    pub fn list_rows<I: Iterator<Item = Vec<String>>>(&self, rows: I) -> Result<()> {
         use error::ListErrorKind as LEK;
 
         let mut table = Table::new();
@@ -81,9 +78,8 @@ impl<F: Fn(&FileLockEntry) -> Vec<String>> Lister for TableLister<F> {
             },
         }
 
-        entries.fold(Ok(table), |table, entry| {
+        rows.fold(Ok(table), |table, mut v| {
             table.and_then(|mut table| {
-                let mut v = (self.line_generator)(&entry);
                 {
                     let v_len = v.len();
                     if header_len.is_none() {
@@ -108,3 +104,11 @@ impl<F: Fn(&FileLockEntry) -> Vec<String>> Lister for TableLister<F> {
     }
 
 }
+
+impl<F: Fn(&FileLockEntry) -> Vec<String>> Lister for TableLister<F> {
+
+    fn list<'b, I: Iterator<Item = FileLockEntry<'b>>>(&self, entries: I) -> Result<()> {
+        self.list_rows(entries.map(|entry| (self.line_generator)(&entry)))
+    }
+
+}
@@ -0,0 +1,223 @@
+//
+// imag - the personal information management suite for the commandline
+// Copyright (C) 2015, 2016 Matthias Beyer <mail@beyermatthias.de> and contributors
+//
+// This library is free software; you can redistribute it and/or
+// modify it under the terms of the GNU Lesser General Public
+// License as published by the Free Software Foundation; version
+// 2.1 of the License.
+//
+// This library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+// Lesser General Public License for more details.
+//
+// You should have received a copy of the GNU Lesser General Public
+// License along with this library; if not, write to the Free Software
+// Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA  02110-1301  USA
+//
+
+//! Tag aliases
+//!
+//! Aliases map synonyms to a canonical tag, so `wrk` and `job` can both end up as `work`. They
+//! are configured in the `tag.aliases` table of the store configuration:
+//!
+//! ```toml
+//! [store.tag.aliases]
+//! wrk = "work"
+//! job = "work"
+//! "work/px" = "work/projectx"
+//! ```
+//!
+//! An alias also applies to the descendants of the aliased tag, so `wrk/review` is normalised
+//! to `work/review`.
+//!
+//! `Tagable::add_tag()` on a `FileLockEntry` normalises the tag with the aliases of its store.
+//! `Entry` and `EntryHeader` do not know their store, use `Tagable::add_tag_with_aliases()` for
+//! them.
+//!
+
+use std::collections::BTreeMap;
+
+use toml::Value;
+
+use libimagstore::store::Store;
+use libimagerror::into::IntoError;
+
+use error::TagErrorKind as TEK;
+use result::Result;
+use tag::{Tag, TagSlice};
+use util::{is_tag, tag_matches};
+
+/// A table of tag aliases, mapping an alias to the tag it stands for
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TagAliases(BTreeMap<Tag, Tag>);
+
+impl TagAliases {
+
+    pub fn new() -> TagAliases {
+        TagAliases(BTreeMap::new())
+    }
+
+    /// Read the aliases of a store from the `tag.aliases` table of its configuration
+    ///
+    /// A store without configuration has no aliases.
+    pub fn from_store(store: &Store) -> Result<TagAliases> {
+        store.config()
+            .map(TagAliases::from_config)
+            .unwrap_or_else(|| Ok(TagAliases::new()))
+    }
+
+    /// Read the aliases from the `tag.aliases` table of the store configuration
+    ///
+    /// A missing table results in an empty alias table.
+    pub fn from_config(config: &Value) -> Result<TagAliases> {
+        let mut aliases = TagAliases::new();
+
+        match config.lookup("tag.aliases") {
+            Some(&Value::Table(ref table)) => {
+                for (alias, tag) in table {
+                    match *tag {
+                        Value::String(ref tag) => try!(aliases.insert(alias.clone(), tag.clone())),
+                        _ => {
+                            debug!("Alias '{}' does not map to a string", alias);
+                            return Err(TEK::AliasConfigError.into_error());
+                        },
+                    }
+                }
+            },
+            Some(_) => return Err(TEK::AliasConfigError.into_error()),
+            None    => debug!("No tag aliases configured"),
+        }
+
+        Ok(aliases)
+    }
+
+    /// Add an alias, fails if either the alias or the tag is not a valid tag
+    pub fn insert(&mut self, alias: Tag, tag: Tag) -> Result<()> {
+        if !is_tag(&alias) || !is_tag(&tag) {
+            debug!("Not a valid alias: '{}' -> '{}'", alias, tag);
+            return Err(TEK::NotATag.into_error());
+        }
+
+        self.0.insert(alias, tag);
+        Ok(())
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Normalise a tag by replacing aliases with the tags they stand for
+    ///
+    /// The longest aliased ancestor of the tag is replaced. Chains of aliases are followed, an
+    /// alias cycle stops after every alias was applied once.
+    pub fn normalize(&self, tag: TagSlice) -> Tag {
+        let mut current = String::from(tag);
+
+        for _ in 0..self.0.len() {
+            let replaced = self.0
+                .iter()
+                .filter(|&(alias, _)| tag_matches(&current, alias))
+                .max_by_key(|&(alias, _)| alias.len())
+                .map(|(alias, target)| format!("{}{}", target, &current[alias.len()..]));
+
+            match replaced {
+                Some(ref r) if *r != current => current = r.clone(),
+                _ => break,
+            }
+        }
+
+        current
+    }
+
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::PathBuf;
+
+    use toml::Parser;
+    use toml::Value;
+
+    use libimagstore::file_abstraction::InMemoryBackend;
+    use libimagstore::store::Store;
+
+    use tagable::Tagable;
+    use super::TagAliases;
+
+    fn aliases() -> TagAliases {
+        let config = Parser::new(r#"
+            [tag.aliases]
+            wrk = "work"
+            job = "wrk"
+            "work/px" = "work/projectx"
+            a = "b"
+            b = "a"
+        "#).parse().unwrap();
+
+        TagAliases::from_config(&Value::Table(config)).unwrap()
+    }
+
+    #[test]
+    fn test_normalize() {
+        let aliases = aliases();
+        assert_eq!(aliases.normalize("wrk"), "work");
+        assert_eq!(aliases.normalize("job"), "work");
+        assert_eq!(aliases.normalize("wrk/review"), "work/review");
+        assert_eq!(aliases.normalize("wrk/px/review"), "work/projectx/review");
+        assert_eq!(aliases.normalize("wrkshop"), "wrkshop");
+        assert_eq!(aliases.normalize("home"), "home");
+    }
+
+    #[test]
+    fn test_normalize_cycle_terminates() {
+        let aliases = aliases();
+        let a = aliases.normalize("a");
+        assert!(a == "a" || a == "b");
+    }
+
+    #[test]
+    fn test_invalid_config() {
+        let config = Parser::new(r#"
+            [tag.aliases]
+            wrk = 1
+        "#).parse().unwrap();
+
+        assert!(TagAliases::from_config(&Value::Table(config)).is_err());
+        assert!(TagAliases::from_config(&Value::Table(Default::default())).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_add_tag_normalizes_with_store_aliases() {
+        let config = Parser::new(r#"
+            store-unload-hook-aspects  = []
+            pre-create-hook-aspects    = []
+            post-create-hook-aspects   = []
+            pre-retrieve-hook-aspects  = []
+            post-retrieve-hook-aspects = []
+            pre-update-hook-aspects    = []
+            post-update-hook-aspects   = []
+            pre-delete-hook-aspects    = []
+            post-delete-hook-aspects   = []
+
+            [hooks]
+            [aspects]
+
+            [tag.aliases]
+            wrk = "work"
+        "#).parse().unwrap();
+
+        let backend = Box::new(InMemoryBackend::new());
+        let store   = Store::new(PathBuf::from("/"), Some(Value::Table(config)), backend).unwrap();
+
+        let mut entry = store.create(PathBuf::from("a")).unwrap();
+        entry.add_tag(String::from("wrk/review")).unwrap();
+        assert_eq!(entry.get_tags().unwrap(), vec![String::from("work/review")]);
+    }
+
+}
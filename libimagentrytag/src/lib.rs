@@ -41,9 +41,11 @@ extern crate libimagstore;
 #[macro_use] extern crate libimagerror;
 #[macro_use] extern crate libimagutil;
 
+pub mod alias;
 pub mod error;
 pub mod exec;
 pub mod result;
+pub mod storewide;
 pub mod tag;
 pub mod tagable;
 pub mod util;
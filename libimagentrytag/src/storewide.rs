@@ -0,0 +1,360 @@
+//
+// imag - the personal information management suite for the commandline
+// Copyright (C) 2015, 2016 Matthias Beyer <mail@beyermatthias.de> and contributors
+//
+// This library is free software; you can redistribute it and/or
+// modify it under the terms of the GNU Lesser General Public
+// License as published by the Free Software Foundation; version
+// 2.1 of the License.
+//
+// This library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+// Lesser General Public License for more details.
+//
+// You should have received a copy of the GNU Lesser General Public
+// License along with this library; if not, write to the Free Software
+// Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA  02110-1301  USA
+//
+
+//! Tag operations on the whole store
+//!
+//! All functions in this module walk the whole store with `Store::walk()` and load every entry.
+//! The functions which only read tags work on copies of the entries, so they neither lock the
+//! entries nor write them back. Entries whose tags cannot be read are skipped with a warning.
+
+use std::collections::BTreeMap;
+use std::ops::Deref;
+
+use libimagstore::store::{Entry, FileLockEntry, Store, StoreObject};
+use libimagstore::storeid::StoreId;
+use libimagerror::into::IntoError;
+use libimagerror::trace::trace_error;
+
+use alias::TagAliases;
+use error::TagErrorKind as TEK;
+use result::Result;
+use tag::Tag;
+use tagable::Tagable;
+use util::{ancestors, is_tag, tag_matches};
+
+/// Call `f` with a copy of each entry in the store and its tags
+fn for_each_entry_copy<F>(store: &Store, mut f: F) -> Result<()>
+    where F: FnMut(&Entry, Vec<Tag>) -> Result<()>
+{
+    for object in store.walk("") {
+        let id = match object {
+            StoreObject::Id(id)         => id,
+            StoreObject::Collection(_)  => continue,
+        };
+
+        let entry = match store.retrieve_copy(id.clone()) {
+            Ok(entry) => entry,
+            Err(e)    => return Err(TEK::StoreReadError.into_error_with_cause(Box::new(e))),
+        };
+
+        match entry.get_tags() {
+            Ok(tags) => try!(f(&entry, tags)),
+            Err(e) => {
+                warn!("Cannot read tags of {}, skipping", id);
+                trace_error(&e);
+            },
+        }
+    }
+
+    Ok(())
+}
+
+/// Call `f` with each entry in the store and its tags
+fn for_each_entry<F>(store: &Store, mut f: F) -> Result<()>
+    where F: FnMut(&mut FileLockEntry, Vec<Tag>) -> Result<()>
+{
+    for object in store.walk("") {
+        let id = match object {
+            StoreObject::Id(id)         => id,
+            StoreObject::Collection(_)  => continue,
+        };
+
+        let mut entry = match store.get(id.clone()) {
+            Ok(Some(entry)) => entry,
+            Ok(None)        => continue,
+            Err(e)          => return Err(TEK::StoreReadError.into_error_with_cause(Box::new(e))),
+        };
+
+        match entry.get_tags() {
+            Ok(tags) => try!(f(&mut entry, tags)),
+            Err(e) => {
+                warn!("Cannot read tags of {}, skipping", id);
+                trace_error(&e);
+            },
+        }
+    }
+
+    Ok(())
+}
+
+/// Count how many entries carry each tag
+///
+/// If `with_ancestors` is true, an entry tagged `work/review` is counted for `work`, too.
+pub fn tag_counts(store: &Store, with_ancestors: bool) -> Result<BTreeMap<Tag, usize>> {
+    let mut counts = BTreeMap::new();
+
+    try!(for_each_entry_copy(store, |_, tags| {
+        let mut seen = tags.clone();
+        if with_ancestors {
+            for tag in &tags {
+                seen.extend(ancestors(tag));
+            }
+        }
+        seen.sort();
+        seen.dedup();
+
+        for tag in seen {
+            *counts.entry(tag).or_insert(0) += 1;
+        }
+        Ok(())
+    }));
+
+    Ok(counts)
+}
+
+/// Get the ids of all entries for which `f` returns true
+pub fn entries_where<F>(store: &Store, f: F) -> Result<Vec<StoreId>>
+    where F: Fn(&Entry) -> bool
+{
+    let mut ids = vec![];
+
+    try!(for_each_entry_copy(store, |entry, _| {
+        if f(entry) {
+            ids.push(entry.get_location().clone());
+        }
+        Ok(())
+    }));
+
+    Ok(ids)
+}
+
+/// Add `tags` to all entries for which `f` returns true
+///
+/// The tags are normalised with `aliases` before they are added. Returns the number of entries
+/// which were changed.
+pub fn add_tags_where<F>(store: &Store, f: F, tags: &[Tag], aliases: &TagAliases) -> Result<usize>
+    where F: Fn(&Entry) -> bool
+{
+    if tags.iter().any(|t| !is_tag(t)) {
+        return Err(TEK::NotATag.into_error());
+    }
+
+    let mut changed = 0;
+    try!(for_each_entry(store, |entry, existing| {
+        if !f(entry.deref()) {
+            return Ok(());
+        }
+
+        for tag in tags {
+            try!(entry.add_tag_with_aliases(tag.clone(), aliases));
+        }
+
+        if try!(entry.get_tags()) != existing {
+            changed += 1;
+        }
+        Ok(())
+    }));
+
+    Ok(changed)
+}
+
+/// Remove `tags` from all entries for which `f` returns true
+///
+/// Descendants of the tags are removed, too: removing `work` removes `work/review` as well.
+/// Returns the number of entries which were changed.
+pub fn remove_tags_where<F>(store: &Store, f: F, tags: &[Tag]) -> Result<usize>
+    where F: Fn(&Entry) -> bool
+{
+    if tags.iter().any(|t| !is_tag(t)) {
+        return Err(TEK::NotATag.into_error());
+    }
+
+    let mut changed = 0;
+    try!(for_each_entry(store, |entry, existing| {
+        if !f(entry.deref()) {
+            return Ok(());
+        }
+
+        let remaining = existing
+            .iter()
+            .filter(|t| !tags.iter().any(|tag| tag_matches(t, tag)))
+            .cloned()
+            .collect::<Vec<Tag>>();
+
+        if remaining != existing {
+            try!(entry.set_tags(&remaining));
+            changed += 1;
+        }
+        Ok(())
+    }));
+
+    Ok(changed)
+}
+
+/// Rename the tag `from` to `to` in all entries of the store
+///
+/// Descendants of `from` are renamed, too: renaming `work` to `job` turns `work/review` into
+/// `job/review`. Returns the number of entries which were changed.
+pub fn rename_tag(store: &Store, from: &str, to: &str) -> Result<usize> {
+    merge_tags(store, &[String::from(from)], to)
+}
+
+/// Merge the tags `sources` into the tag `target` in all entries of the store
+///
+/// Each of the source tags (and its descendants) is replaced by `target`. Returns the number of
+/// entries which were changed.
+pub fn merge_tags(store: &Store, sources: &[Tag], target: &str) -> Result<usize> {
+    if !is_tag(target) || sources.iter().any(|s| !is_tag(s)) {
+        return Err(TEK::NotATag.into_error());
+    }
+
+    let mut changed = 0;
+    try!(for_each_entry(store, |entry, tags| {
+        let retagged = tags
+            .iter()
+            .map(|tag| {
+                sources
+                    .iter()
+                    .filter(|source| tag_matches(tag, source))
+                    .max_by_key(|source| source.len())
+                    .map(|source| format!("{}{}", target, &tag[source.len()..]))
+                    .unwrap_or_else(|| tag.clone())
+            })
+            .collect::<Vec<Tag>>();
+
+        if retagged != tags {
+            debug!("Retagging {}: {:?} -> {:?}", entry.get_location(), tags, retagged);
+            try!(entry.set_tags(&retagged));
+            changed += 1;
+        }
+        Ok(())
+    }));
+
+    Ok(changed)
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::PathBuf;
+
+    use libimagstore::file_abstraction::InMemoryBackend;
+
+    use libimagstore::store::{Entry, Store};
+
+    use alias::TagAliases;
+    use tagable::Tagable;
+    use super::*;
+
+    fn get_store() -> Store {
+        Store::new(PathBuf::from("/"), None, Box::new(InMemoryBackend::new())).unwrap()
+    }
+
+    fn tag(store: &Store, name: &str, tags: &[&str]) {
+        let mut entry = store.create(PathBuf::from(name)).unwrap();
+        let tags = tags.iter().map(|t| String::from(*t)).collect::<Vec<_>>();
+        entry.set_tags(&tags).unwrap();
+    }
+
+    fn tags_of(store: &Store, name: &str) -> Vec<String> {
+        store.get(PathBuf::from(name)).unwrap().unwrap().get_tags().unwrap()
+    }
+
+    #[test]
+    fn test_has_tag_hierarchical() {
+        let store = get_store();
+        tag(&store, "a", &["work/projectx/review"]);
+
+        let entry = store.get(PathBuf::from("a")).unwrap().unwrap();
+        assert!(entry.has_tag("work").unwrap());
+        assert!(entry.has_tag("work/projectx").unwrap());
+        assert!(!entry.has_tag("work/project").unwrap());
+        assert!(!entry.has_tag("home").unwrap());
+    }
+
+    #[test]
+    fn test_rename_tag() {
+        let store = get_store();
+        tag(&store, "a", &["work", "home"]);
+        tag(&store, "b", &["work/review"]);
+        tag(&store, "c", &["workshop"]);
+
+        assert_eq!(rename_tag(&store, "work", "job").unwrap(), 2);
+        assert_eq!(tags_of(&store, "a"), vec!["job", "home"]);
+        assert_eq!(tags_of(&store, "b"), vec!["job/review"]);
+        assert_eq!(tags_of(&store, "c"), vec!["workshop"]);
+    }
+
+    #[test]
+    fn test_merge_tags() {
+        let store = get_store();
+        tag(&store, "a", &["wrk", "job", "home"]);
+        tag(&store, "b", &["job/review"]);
+
+        let sources = vec![String::from("wrk"), String::from("job")];
+        assert_eq!(merge_tags(&store, &sources, "work").unwrap(), 2);
+        assert_eq!(tags_of(&store, "a"), vec!["work", "home"]);
+        assert_eq!(tags_of(&store, "b"), vec!["work/review"]);
+        assert!(merge_tags(&store, &sources, "not a tag").is_err());
+    }
+
+    #[test]
+    fn test_tag_counts() {
+        let store = get_store();
+        tag(&store, "a", &["work/review", "work/projectx"]);
+        tag(&store, "b", &["work", "home"]);
+
+        let counts = tag_counts(&store, false).unwrap();
+        assert_eq!(counts.len(), 4);
+        assert_eq!(counts.get("work"), Some(&1));
+        assert_eq!(counts.get("work/review"), Some(&1));
+
+        let counts = tag_counts(&store, true).unwrap();
+        assert_eq!(counts.get("work"), Some(&2));
+        assert_eq!(counts.get("home"), Some(&1));
+    }
+
+    #[test]
+    fn test_add_and_remove_tags_where() {
+        let store = get_store();
+        tag(&store, "a", &["work/review"]);
+        tag(&store, "b", &["home"]);
+        tag(&store, "c", &["work", "urgent"]);
+
+        let mut aliases = TagAliases::new();
+        aliases.insert(String::from("asap"), String::from("urgent")).unwrap();
+
+        let is_work = |e: &Entry| e.has_tag("work").unwrap_or(false);
+        let asap    = vec![String::from("asap")];
+        assert_eq!(add_tags_where(&store, &is_work, &asap, &aliases).unwrap(), 1);
+        assert_eq!(tags_of(&store, "a"), vec!["work/review", "urgent"]);
+        assert_eq!(tags_of(&store, "b"), vec!["home"]);
+
+        let urgent = entries_where(&store, |e| e.has_tag("urgent").unwrap_or(false)).unwrap();
+        assert_eq!(urgent.len(), 2);
+
+        let tags = vec![String::from("urgent")];
+        assert_eq!(remove_tags_where(&store, |_| true, &tags).unwrap(), 2);
+        assert_eq!(tags_of(&store, "c"), vec!["work"]);
+    }
+
+    #[test]
+    fn test_remove_tags_where_hierarchical() {
+        let store = get_store();
+        tag(&store, "a", &["work/review", "home"]);
+        tag(&store, "b", &["work"]);
+        tag(&store, "c", &["workshop"]);
+
+        let tags = vec![String::from("work")];
+        assert_eq!(remove_tags_where(&store, |_| true, &tags).unwrap(), 2);
+        assert_eq!(tags_of(&store, "a"), vec!["home"]);
+        assert!(tags_of(&store, "b").is_empty());
+        assert_eq!(tags_of(&store, "c"), vec!["workshop"]);
+    }
+
+}
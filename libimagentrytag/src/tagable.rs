@@ -29,7 +29,8 @@ use error::TagErrorKind;
 use error::MapErrInto;
 use result::Result;
 use tag::{Tag, TagSlice};
-use util::is_tag;
+use util::{is_tag, tag_matches};
+use alias::TagAliases;
 
 use toml::Value;
 
@@ -41,9 +42,19 @@ pub trait Tagable {
     fn add_tag(&mut self, t: Tag) -> Result<()>;
     fn remove_tag(&mut self, t: Tag) -> Result<()>;
 
+    /// Check whether the object has the tag `t` or a descendant of it
+    ///
+    /// An entry tagged with `work/projectx/review` has the tags `work`, `work/projectx` and
+    /// `work/projectx/review`.
     fn has_tag(&self, t: TagSlice) -> Result<bool>;
     fn has_tags(&self, ts: &[Tag]) -> Result<bool>;
 
+    /// Add a tag after normalising it with the alias table
+    fn add_tag_with_aliases(&mut self, t: Tag, aliases: &TagAliases) -> Result<()> {
+        let t = aliases.normalize(&t);
+        self.add_tag(t)
+    }
+
 }
 
 impl Tagable for EntryHeader {
@@ -120,20 +131,7 @@ impl Tagable for EntryHeader {
     }
 
     fn has_tag(&self, t: TagSlice) -> Result<bool> {
-        let tags = try!(self.read("imag.tags").map_err_into(TagErrorKind::HeaderReadError));
-
-        if !tags.iter().all(|t| is_match!(*t, Value::String(_))) {
-            return Err(TagErrorKind::TagTypeError.into());
-        }
-
-        Ok(tags
-           .iter()
-           .any(|tag| {
-               match *tag {
-                   Value::String(ref s) => { s == t },
-                   _ => unreachable!()
-               }
-           }))
+        self.get_tags().map(|tags| tags.iter().any(|tag| tag_matches(tag, t)))
     }
 
     fn has_tags(&self, tags: &[Tag]) -> Result<bool> {
@@ -185,8 +183,10 @@ impl<'a> Tagable for FileLockEntry<'a> {
         self.deref_mut().set_tags(ts)
     }
 
+    /// Add a tag after normalising it with the aliases of the store
     fn add_tag(&mut self, t: Tag) -> Result<()> {
-        self.deref_mut().add_tag(t)
+        let aliases = try!(TagAliases::from_store(self.store()));
+        self.deref_mut().add_tag_with_aliases(t, &aliases)
     }
 
     fn remove_tag(&mut self, t: Tag) -> Result<()> {
@@ -19,6 +19,71 @@
 
 use regex::Regex;
 
+/// The separator between the segments of a hierarchical tag, as in `work/projectx/review`
+pub const TAG_SEPARATOR : char = '/';
+
+/// Check whether a string is a valid tag
+///
+/// A tag consists of one or more segments, separated by `TAG_SEPARATOR`. Each segment starts with
+/// a letter and continues with letters, digits, `_` or `-`.
 pub fn is_tag(s: &str) -> bool {
-    Regex::new("^[a-zA-Z]([a-zA-Z0-9_-]*)$").unwrap().captures(s).is_some()
+    Regex::new("^[a-zA-Z][a-zA-Z0-9_-]*(/[a-zA-Z][a-zA-Z0-9_-]*)*$").unwrap().captures(s).is_some()
+}
+
+/// Check whether `tag` is `query` or a descendant of `query`
+///
+/// `work/projectx/review` matches the queries `work/projectx/review`, `work/projectx` and `work`,
+/// but not `work/project`.
+pub fn tag_matches(tag: &str, query: &str) -> bool {
+    tag == query || is_descendant_of(tag, query)
+}
+
+/// Check whether `tag` is a (transitive) child of `parent`
+pub fn is_descendant_of(tag: &str, parent: &str) -> bool {
+    tag.len() > parent.len()
+        && tag.starts_with(parent)
+        && tag[parent.len()..].starts_with(TAG_SEPARATOR)
+}
+
+/// Get the ancestors of a tag, from the root to the direct parent
+///
+/// `work/projectx/review` has the ancestors `work` and `work/projectx`.
+pub fn ancestors(tag: &str) -> Vec<String> {
+    tag.match_indices(TAG_SEPARATOR)
+        .map(|(i, _)| String::from(&tag[..i]))
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_is_tag() {
+        assert!(is_tag("work"));
+        assert!(is_tag("work/projectx/review"));
+        assert!(is_tag("a_b-c/d1"));
+        assert!(!is_tag(""));
+        assert!(!is_tag("/work"));
+        assert!(!is_tag("work/"));
+        assert!(!is_tag("work//review"));
+        assert!(!is_tag("work/1st"));
+    }
+
+    #[test]
+    fn test_tag_matches() {
+        assert!(tag_matches("work/projectx/review", "work"));
+        assert!(tag_matches("work/projectx/review", "work/projectx"));
+        assert!(tag_matches("work/projectx/review", "work/projectx/review"));
+        assert!(!tag_matches("work/projectx/review", "work/project"));
+        assert!(!tag_matches("work", "work/projectx"));
+        assert!(!tag_matches("workshop", "work"));
+    }
+
+    #[test]
+    fn test_ancestors() {
+        assert_eq!(ancestors("work/projectx/review"), vec!["work", "work/projectx"]);
+        assert!(ancestors("work").is_empty());
+    }
+
 }
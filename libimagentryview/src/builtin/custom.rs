@@ -0,0 +1,90 @@
+//
+// imag - the personal information management suite for the commandline
+// Copyright (C) 2015, 2016 Matthias Beyer <mail@beyermatthias.de> and contributors
+//
+// This library is free software; you can redistribute it and/or
+// modify it under the terms of the GNU Lesser General Public
+// License as published by the Free Software Foundation; version
+// 2.1 of the License.
+//
+// This library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+// Lesser General Public License for more details.
+//
+// You should have received a copy of the GNU Lesser General Public
+// License along with this library; if not, write to the Free Software
+// Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA  02110-1301  USA
+//
+
+use std::process::Command;
+
+use libimagstore::store::{Entry, Store};
+use libimagerror::into::IntoError;
+
+use builtin::html::HtmlViewer;
+use viewer::Viewer;
+use result::Result;
+use error::ViewErrorKind as VEK;
+use error::MapErrInto;
+
+/// A viewer which runs a custom command
+///
+/// The command is built from a template, where these placeholders are replaced:
+///
+/// * `%e` - the path of the entry file in the store
+/// * `%i` - the id of the entry
+/// * `%h` - the path of the entry rendered to HTML (see `HtmlViewer`)
+///
+/// For example `libreoffice %e` or `firefox --new-window %h`.
+pub struct CustomViewer<'a> {
+    store: &'a Store,
+    template: String,
+}
+
+impl<'a> CustomViewer<'a> {
+
+    pub fn new(store: &'a Store, template: String) -> CustomViewer<'a> {
+        CustomViewer {
+            store: store,
+            template: template,
+        }
+    }
+
+}
+
+impl<'a> Viewer for CustomViewer<'a> {
+
+    fn view_entry(&self, e: &Entry) -> Result<()> {
+        let path = try!(e.get_location().clone().into_pathbuf().map_err_into(VEK::ViewError));
+        let id   = format!("{}", e.get_location());
+        let html = if self.template.contains("%h") {
+            Some(try!(HtmlViewer::new(self.store, None).render(e)))
+        } else {
+            None
+        };
+
+        let mut parts = self.template.split_whitespace().map(|part| {
+            let mut part = part.replace("%e", &path.display().to_string()).replace("%i", &id);
+            if let Some(ref html) = html {
+                part = part.replace("%h", &html.display().to_string());
+            }
+            part
+        });
+
+        let program = try!(parts.next().ok_or(VEK::CustomCommandError.into_error()));
+        debug!("Calling '{}' for viewing {}", self.template, id);
+
+        let status = try!(Command::new(program)
+            .args(&parts.collect::<Vec<_>>())
+            .status()
+            .map_err_into(VEK::CustomCommandError));
+
+        if status.success() {
+            Ok(())
+        } else {
+            Err(VEK::CustomCommandError.into_error())
+        }
+    }
+
+}
@@ -0,0 +1,377 @@
+//
+// imag - the personal information management suite for the commandline
+// Copyright (C) 2015, 2016 Matthias Beyer <mail@beyermatthias.de> and contributors
+//
+// This library is free software; you can redistribute it and/or
+// modify it under the terms of the GNU Lesser General Public
+// License as published by the Free Software Foundation; version
+// 2.1 of the License.
+//
+// This library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+// Lesser General Public License for more details.
+//
+// You should have received a copy of the GNU Lesser General Public
+// License along with this library; if not, write to the Free Software
+// Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA  02110-1301  USA
+//
+
+//! Rendering entries to HTML
+//!
+//! The header of an entry is rendered as a definition table, the content is rendered as markdown
+//! with libimagentrymarkdown. Links to other entries (from the content as well as from
+//! `imag.links`) are translated to hyperlinks by a resolver function, which maps a `StoreId` to
+//! the location of the rendered entry, if there is one.
+
+use std::collections::BTreeSet;
+use std::env;
+use std::fs::{create_dir_all, File};
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::Command;
+#[cfg(unix)] use std::fs::{symlink_metadata, DirBuilder};
+#[cfg(unix)] use std::io::ErrorKind;
+#[cfg(unix)] use std::os::unix::fs::{DirBuilderExt, PermissionsExt};
+
+use regex::{Captures, Regex};
+use toml::Value;
+
+use libimagstore::store::{Entry, Store};
+use libimagstore::storeid::StoreId;
+use libimagentrylink::internal::InternalLinker;
+use libimagentrymarkdown::html::to_html;
+use libimagentrymarkdown::link::extract_links;
+use libimagerror::into::IntoError;
+use libimagerror::trace::trace_error;
+
+use viewer::Viewer;
+use result::Result;
+use error::ViewErrorKind as VEK;
+use error::MapErrInto;
+
+pub type HTML = String;
+
+/// Escape a string for use in HTML text and attribute values
+pub fn escape(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&'  => escaped.push_str("&amp;"),
+            '<'  => escaped.push_str("&lt;"),
+            '>'  => escaped.push_str("&gt;"),
+            '"'  => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            c    => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Render a header value, tables become definition lists and arrays become lists
+pub fn header_to_html(v: &Value) -> HTML {
+    match *v {
+        Value::Table(ref t) => {
+            let mut html = String::from("<dl>\n");
+            for (key, value) in t {
+                html.push_str(&format!("<dt>{}</dt>\n<dd>{}</dd>\n", escape(key), header_to_html(value)));
+            }
+            html.push_str("</dl>");
+            html
+        },
+        Value::Array(ref a) => {
+            let mut html = String::from("<ul>\n");
+            for value in a {
+                html.push_str(&format!("<li>{}</li>\n", header_to_html(value)));
+            }
+            html.push_str("</ul>");
+            html
+        },
+        Value::String(ref s) => escape(s),
+        ref other => escape(&format!("{}", other)),
+    }
+}
+
+/// Get the id an entry-local link points to, if it is not an URL or an anchor
+fn link_to_storeid(link: &str) -> Option<StoreId> {
+    if link.is_empty() || link.starts_with("#") || link.contains("://") || link.starts_with("mailto:") {
+        return None;
+    }
+
+    StoreId::new_baseless(PathBuf::from(link)).ok()
+}
+
+/// Render the content of an entry as markdown
+///
+/// Hyperlinks which point to store ids are replaced by the location `resolve` returns for them.
+/// Links to entries which are not resolved are left untouched.
+pub fn content_to_html<F>(e: &Entry, resolve: &F) -> Result<HTML>
+    where F: Fn(&StoreId) -> Option<String>
+{
+    let html = try!(to_html(e.get_content()).map_err_into(VEK::RenderError));
+    let href = Regex::new("href=\"([^\"]*)\"").unwrap();
+
+    Ok(href.replace_all(&html, |caps: &Captures| {
+        let link = caps.at(1).unwrap_or("");
+        match link_to_storeid(link).and_then(|id| resolve(&id)) {
+            Some(target) => format!("href=\"{}\"", escape(&target)),
+            None         => format!("href=\"{}\"", link),
+        }
+    }))
+}
+
+/// Render a list of links to entries
+///
+/// Links which `resolve` returns a location for become hyperlinks, all others are plain text.
+pub fn links_to_html<F>(links: &[StoreId], resolve: &F) -> HTML
+    where F: Fn(&StoreId) -> Option<String>
+{
+    let mut html = String::from("<ul>\n");
+    for link in links {
+        let name = escape(&format!("{}", link));
+        match resolve(link) {
+            Some(target) => html.push_str(&format!("<li><a href=\"{}\">{}</a></li>\n", escape(&target), name)),
+            None         => html.push_str(&format!("<li>{}</li>\n", name)),
+        }
+    }
+    html.push_str("</ul>");
+    html
+}
+
+/// Wrap `body` into a HTML page
+pub fn page(title: &str, body: &str) -> HTML {
+    format!("<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{title}</title>\n</head>\n<body>\n<h1>{title}</h1>\n{body}\n</body>\n</html>\n",
+            title = escape(title),
+            body = body)
+}
+
+/// Get the ids of all entries an entry links to, internal links first, then content links
+pub fn linked_ids(e: &Entry) -> Result<Vec<StoreId>> {
+    let mut ids = try!(e.get_internal_links().map_err_into(VEK::LinkError)).collect::<Vec<_>>();
+
+    for link in extract_links(e.get_content()) {
+        if let Some(id) = link_to_storeid(&link.link) {
+            if !ids.contains(&id) {
+                ids.push(id);
+            }
+        }
+    }
+
+    Ok(ids)
+}
+
+/// Render an entry to the body of a HTML page: header, content and links
+pub fn entry_body<F>(e: &Entry, resolve: &F) -> Result<HTML>
+    where F: Fn(&StoreId) -> Option<String>
+{
+    let content = try!(content_to_html(e, resolve));
+    let links   = try!(e.get_internal_links().map_err_into(VEK::LinkError)).collect::<Vec<_>>();
+
+    let mut body = String::new();
+    body.push_str("<h2>Header</h2>\n");
+    body.push_str(&header_to_html(e.get_header().header()));
+    body.push_str("\n<h2>Content</h2>\n<div class=\"content\">\n");
+    body.push_str(&content);
+    body.push_str("</div>\n");
+    if !links.is_empty() {
+        body.push_str("<h2>Links</h2>\n");
+        body.push_str(&links_to_html(&links, resolve));
+    }
+
+    Ok(body)
+}
+
+/// Render an entry to a full HTML page
+pub fn render_entry<F>(e: &Entry, resolve: &F) -> Result<HTML>
+    where F: Fn(&StoreId) -> Option<String>
+{
+    let id = e.get_location().clone().without_base();
+    entry_body(e, resolve).map(|body| page(&format!("{}", id), &body))
+}
+
+/// Write `html` to `path`, creating all parent directories
+pub fn write_html(path: &PathBuf, html: &str) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        try!(create_dir_all(parent).map_err_into(VEK::IOError));
+    }
+
+    File::create(path)
+        .and_then(|mut file| file.write_all(html.as_bytes()))
+        .map_err_into(VEK::IOError)
+}
+
+/// Get the temporary directory of the current user for rendered entries
+///
+/// The directory is `imag-view-$USER` in the temporary directory of the system. It is created
+/// with mode 0700, an existing directory is only used if it is no symlink and not accessible by
+/// other users.
+#[cfg(unix)]
+fn user_temp_dir() -> Result<PathBuf> {
+    let user = try!(env::var("USER")
+        .or_else(|_| env::var("LOGNAME"))
+        .map_err_into(VEK::TempDirError));
+    let dir = env::temp_dir().join(format!("imag-view-{}", user));
+
+    match DirBuilder::new().mode(0o700).create(&dir) {
+        Ok(_) => return Ok(dir),
+        Err(ref e) if e.kind() == ErrorKind::AlreadyExists => {},
+        Err(e) => return Err(VEK::TempDirError.into_error_with_cause(Box::new(e))),
+    }
+
+    let meta = try!(symlink_metadata(&dir).map_err_into(VEK::TempDirError));
+    if !meta.is_dir() || meta.permissions().mode() & 0o077 != 0 {
+        warn!("{:?} is no directory or accessible by other users, not using it", dir);
+        return Err(VEK::TempDirError.into_error());
+    }
+
+    Ok(dir)
+}
+
+#[cfg(not(unix))]
+fn user_temp_dir() -> Result<PathBuf> {
+    let dir = env::temp_dir().join("imag-view");
+    try!(create_dir_all(&dir).map_err_into(VEK::TempDirError));
+    Ok(dir)
+}
+
+/// A viewer which renders the entry to HTML and opens it in a browser
+///
+/// The entry is written to a temporary directory of the user, together with all entries it links
+/// to, so the links between them can be followed in the browser.
+pub struct HtmlViewer<'a> {
+    store: &'a Store,
+    browser: Option<String>,
+    outdir: Option<PathBuf>,
+}
+
+impl<'a> HtmlViewer<'a> {
+
+    /// Create a new `HtmlViewer`, if `browser` is `None`, `$BROWSER` is used
+    pub fn new(store: &'a Store, browser: Option<String>) -> HtmlViewer<'a> {
+        HtmlViewer {
+            store: store,
+            browser: browser,
+            outdir: None,
+        }
+    }
+
+    /// Write the rendered entries to `outdir` instead of the temporary directory
+    pub fn with_outdir(mut self, outdir: PathBuf) -> HtmlViewer<'a> {
+        self.outdir = Some(outdir);
+        self
+    }
+
+    /// Render the entry and the entries it links to, returns the path of the rendered entry
+    pub fn render(&self, e: &Entry) -> Result<PathBuf> {
+        let outdir = match self.outdir {
+            Some(ref outdir) => outdir.clone(),
+            None             => try!(user_temp_dir()),
+        };
+        let path_for = |id: &StoreId| {
+            PathBuf::from(format!("{}.html", outdir.join(id.local()).display()))
+        };
+
+        let id     = e.get_location().clone().without_base();
+        let linked = try!(linked_ids(e));
+
+        let mut rendered = BTreeSet::new();
+        rendered.insert(id.clone());
+        let mut others = vec![];
+        for link in linked {
+            if rendered.contains(&link) {
+                continue;
+            }
+
+            match self.store.exists(link.clone()) {
+                Ok(true) => {},
+                Ok(false) => {
+                    debug!("Linked entry {} does not exist, not rendering it", link);
+                    continue;
+                },
+                Err(e) => {
+                    warn!("Cannot get linked entry {}, not rendering it", link);
+                    trace_error(&e);
+                    continue;
+                },
+            }
+
+            match self.store.retrieve_copy(link.clone()) {
+                Ok(entry) => {
+                    rendered.insert(link);
+                    others.push(entry);
+                },
+                Err(e) => {
+                    warn!("Cannot get linked entry {}, not rendering it", link);
+                    trace_error(&e);
+                },
+            }
+        }
+
+        let resolve = |link: &StoreId| {
+            if rendered.contains(link) {
+                path_for(link).to_str().map(String::from)
+            } else {
+                None
+            }
+        };
+
+        for other in &others {
+            let path = path_for(&other.get_location().clone().without_base());
+            try!(render_entry(other, &resolve).and_then(|html| write_html(&path, &html)));
+        }
+
+        let path = path_for(&id);
+        try!(render_entry(e, &resolve).and_then(|html| write_html(&path, &html)));
+        Ok(path)
+    }
+
+}
+
+impl<'a> Viewer for HtmlViewer<'a> {
+
+    fn view_entry(&self, e: &Entry) -> Result<()> {
+        let browser = try!(self.browser
+            .clone()
+            .or_else(|| env::var("BROWSER").ok())
+            .ok_or(VEK::BrowserNotSet.into_error()));
+
+        let path = try!(self.render(e));
+        debug!("Opening {:?} in '{}'", path, browser);
+
+        let status = try!(Command::new(&browser).arg(&path).status().map_err_into(VEK::ViewError));
+        if status.success() {
+            Ok(())
+        } else {
+            Err(VEK::ViewError.into_error())
+        }
+    }
+
+}
+
+#[cfg(test)]
+mod test {
+    use toml::Parser;
+    use toml::Value;
+
+    use super::{escape, header_to_html};
+
+    #[test]
+    fn test_escape() {
+        assert_eq!(escape("<a href=\"x\">&</a>"), "&lt;a href=&quot;x&quot;&gt;&amp;&lt;/a&gt;");
+    }
+
+    #[test]
+    fn test_header_to_html() {
+        let header = Parser::new(r#"
+            [imag]
+            tags = ["a", "<b>"]
+            version = "0.2.0"
+        "#).parse().unwrap();
+
+        let html = header_to_html(&Value::Table(header));
+        assert!(html.contains("<dt>imag</dt>"));
+        assert!(html.contains("<li>&lt;b&gt;</li>"));
+        assert!(html.contains("<dt>version</dt>\n<dd>0.2.0</dd>"));
+    }
+
+}
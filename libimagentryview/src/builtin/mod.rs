@@ -17,6 +17,8 @@
 // Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA  02110-1301  USA
 //
 
+pub mod custom;
 pub mod editor;
+pub mod html;
 pub mod plain;
 pub mod stdout;
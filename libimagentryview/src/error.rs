@@ -23,7 +23,15 @@ generate_error_module!(
         GlobError            => "Error while glob()ing",
         PatternError         => "Error in glob() pattern",
         PatternBuildingError => "Could not build glob() pattern",
-        ViewError            => "Failed to start viewer"
+        ViewError            => "Failed to start viewer",
+        RenderError          => "Failed to render entry",
+        IOError              => "IO Error",
+        LinkError            => "Error while reading links of entry",
+        TagError             => "Error while reading tags of entry",
+        StoreError           => "Store error",
+        BrowserNotSet        => "No browser passed and $BROWSER is not set",
+        TempDirError         => "Cannot use the temporary directory for rendering",
+        CustomCommandError   => "Custom viewer command failed"
     );
 );
 
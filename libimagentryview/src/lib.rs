@@ -35,15 +35,21 @@
 
 #[macro_use] extern crate log;
 extern crate glob;
+extern crate regex;
 extern crate toml;
+#[cfg(test)] extern crate tempdir;
 
 extern crate libimagstore;
 extern crate libimagrt;
 #[macro_use] extern crate libimagerror;
 extern crate libimagentryedit;
+extern crate libimagentrylink;
+extern crate libimagentrymarkdown;
+extern crate libimagentrytag;
 
 pub mod error;
 pub mod builtin;
 pub mod result;
+pub mod site;
 pub mod viewer;
 
@@ -0,0 +1,327 @@
+//
+// imag - the personal information management suite for the commandline
+// Copyright (C) 2015, 2016 Matthias Beyer <mail@beyermatthias.de> and contributors
+//
+// This library is free software; you can redistribute it and/or
+// modify it under the terms of the GNU Lesser General Public
+// License as published by the Free Software Foundation; version
+// 2.1 of the License.
+//
+// This library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+// Lesser General Public License for more details.
+//
+// You should have received a copy of the GNU Lesser General Public
+// License along with this library; if not, write to the Free Software
+// Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA  02110-1301  USA
+//
+
+//! Static HTML site export
+//!
+//! A `Site` renders a selection of entries to a directory of static HTML pages:
+//!
+//! * `entries/<id>.html` for each entry, with its header, content, links, backlinks and tags
+//! * `modules/<module>.html` for each module, listing the entries of the module
+//! * `tags/<tag>.html` for each tag, listing the entries carrying the tag
+//! * `index.html` listing all modules and `tags.html` listing all tags
+//!
+//! Entries, modules and tags are written to their own directories, so their pages never collide,
+//! whatever the entries and tags are named.
+//!
+//! All hyperlinks between the pages are relative, so the output directory can be published as it
+//! is. Links to entries which are not part of the site are not rendered as hyperlinks.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::PathBuf;
+
+use libimagstore::store::{Entry, Store, StoreObject};
+use libimagstore::storeid::StoreId;
+use libimagentrylink::internal::InternalLinker;
+use libimagentrytag::tag::Tag;
+use libimagentrytag::tagable::Tagable;
+use libimagentrytag::util::tag_matches;
+
+use builtin::html::{entry_body, escape, links_to_html, page, write_html};
+use error::ViewErrorKind as VEK;
+use error::MapErrInto;
+use result::Result;
+
+/// The directory (relative to the output directory) where the entry pages are written to
+const ENTRY_DIR : &'static str = "entries";
+
+/// The directory (relative to the output directory) where the module pages are written to
+const MODULE_DIR : &'static str = "modules";
+
+/// The directory (relative to the output directory) where the tag pages are written to
+const TAG_DIR : &'static str = "tags";
+
+/// The page listing all tags, relative to the output directory
+const TAG_INDEX : &'static str = "tags.html";
+
+/// An entry of the site, collected before rendering
+struct SiteEntry {
+    entry: Entry,
+    tags: Vec<Tag>,
+    links: Vec<StoreId>,
+}
+
+/// A selection of entries to be rendered to a static site
+pub struct Site<'a> {
+    store: &'a Store,
+    ids: BTreeSet<StoreId>,
+}
+
+/// The path of the page of an entry, relative to the output directory
+fn entry_page(id: &StoreId) -> String {
+    format!("{}/{}.html", ENTRY_DIR, id.local().display())
+}
+
+/// The path of the page of a module, relative to the output directory
+fn module_page(module: &str) -> String {
+    format!("{}/{}.html", MODULE_DIR, module)
+}
+
+/// The path of the page of a tag, relative to the output directory
+fn tag_page(tag: &str) -> String {
+    format!("{}/{}.html", TAG_DIR, tag)
+}
+
+fn module_of(id: &StoreId) -> String {
+    id.local()
+        .components()
+        .next()
+        .and_then(|c| c.as_os_str().to_str())
+        .map(String::from)
+        .unwrap_or_else(String::new)
+}
+
+/// The relative hyperlink from the page `from` to the page `to`
+///
+/// Both pages are given relative to the output directory.
+pub fn relative_link(from: &str, to: &str) -> String {
+    let mut link = String::new();
+    for _ in 0..from.matches('/').count() {
+        link.push_str("../");
+    }
+    link.push_str(to);
+    link
+}
+
+/// A list of hyperlinks from the page `from` to the pages of `ids`
+fn entry_list<'b, I>(from: &str, ids: I) -> String
+    where I: Iterator<Item = &'b StoreId>
+{
+    ids.map(|id| format!("<li><a href=\"{}\">{}</a></li>\n",
+                         escape(&relative_link(from, &entry_page(id))),
+                         escape(&format!("{}", id))))
+        .collect()
+}
+
+impl<'a> Site<'a> {
+
+    pub fn new(store: &'a Store) -> Site<'a> {
+        Site {
+            store: store,
+            ids: BTreeSet::new(),
+        }
+    }
+
+    /// Add an entry to the site
+    pub fn add_entry(&mut self, id: StoreId) {
+        self.ids.insert(id.without_base());
+    }
+
+    /// Add all entries of a module (like "notes" or "diary") to the site
+    pub fn add_module(&mut self, module: &str) {
+        for object in self.store.walk(module) {
+            if let StoreObject::Id(id) = object {
+                self.add_entry(id);
+            }
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.ids.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ids.is_empty()
+    }
+
+    /// Read copies of all entries of the site, the entries are not locked while rendering
+    fn collect(&self) -> Result<BTreeMap<StoreId, SiteEntry>> {
+        let mut entries = BTreeMap::new();
+
+        for id in &self.ids {
+            if !try!(self.store.exists(id.clone()).map_err_into(VEK::StoreError)) {
+                warn!("Entry {} does not exist, not rendering it", id);
+                continue;
+            }
+
+            let entry = try!(self.store.retrieve_copy(id.clone()).map_err_into(VEK::StoreError));
+            let tags  = try!(entry.get_tags().map_err_into(VEK::TagError));
+            let links = try!(entry.get_internal_links().map_err_into(VEK::LinkError)).collect();
+            entries.insert(id.clone(), SiteEntry { entry: entry, tags: tags, links: links });
+        }
+
+        Ok(entries)
+    }
+
+    /// Render the site into `outdir`, returns the number of rendered entries
+    pub fn write(&self, outdir: &PathBuf) -> Result<usize> {
+        let entries = try!(self.collect());
+
+        let mut backlinks : BTreeMap<&StoreId, Vec<StoreId>> = BTreeMap::new();
+        let mut modules   : BTreeMap<String, Vec<StoreId>>   = BTreeMap::new();
+        let mut tags      : BTreeSet<&Tag>                  = BTreeSet::new();
+        for (id, entry) in &entries {
+            for link in &entry.links {
+                if link != id && entries.contains_key(link) {
+                    backlinks.entry(link).or_insert_with(Vec::new).push(id.clone());
+                }
+            }
+            modules.entry(module_of(id)).or_insert_with(Vec::new).push(id.clone());
+            tags.extend(entry.tags.iter());
+        }
+
+        for (id, entry) in &entries {
+            let from    = entry_page(id);
+            let resolve = |target: &StoreId| {
+                let target = target.clone().without_base();
+                if entries.contains_key(&target) {
+                    Some(relative_link(&from, &entry_page(&target)))
+                } else {
+                    None
+                }
+            };
+
+            let mut body = format!("<p><a href=\"{}\">Index</a></p>\n",
+                                   relative_link(&from, "index.html"));
+            body.push_str(&try!(entry_body(&entry.entry, &resolve)));
+
+            if let Some(sources) = backlinks.get(id) {
+                body.push_str("\n<h2>Backlinks</h2>\n");
+                body.push_str(&links_to_html(sources, &resolve));
+            }
+
+            if !entry.tags.is_empty() {
+                body.push_str("\n<h2>Tags</h2>\n<ul>\n");
+                for tag in &entry.tags {
+                    body.push_str(&format!("<li><a href=\"{}\">{}</a></li>\n",
+                                           escape(&relative_link(&from, &tag_page(tag))),
+                                           escape(tag)));
+                }
+                body.push_str("</ul>");
+            }
+
+            let html = page(&format!("{}", id), &body);
+            try!(write_html(&outdir.join(&from), &html));
+        }
+
+        for (module, ids) in &modules {
+            let from = module_page(module);
+            let body = format!("<p><a href=\"{}\">Index</a></p>\n<ul>\n{}</ul>",
+                               relative_link(&from, "index.html"), entry_list(&from, ids.iter()));
+            try!(write_html(&outdir.join(&from), &page(module, &body)));
+        }
+
+        for tag in &tags {
+            let from = tag_page(tag);
+            let ids  = entries
+                .iter()
+                .filter(|&(_, entry)| entry.tags.iter().any(|t| tag_matches(t, tag)))
+                .map(|(id, _)| id);
+            let body = format!("<p><a href=\"{}\">Index</a></p>\n<ul>\n{}</ul>",
+                               relative_link(&from, "index.html"), entry_list(&from, ids));
+            try!(write_html(&outdir.join(&from), &page(&format!("Tag: {}", tag), &body)));
+        }
+
+        let tag_list = tags
+            .iter()
+            .map(|tag| format!("<li><a href=\"{}\">{}</a></li>\n", escape(&tag_page(tag)), escape(tag)))
+            .collect::<String>();
+        let body = format!("<p><a href=\"index.html\">Index</a></p>\n<ul>\n{}</ul>", tag_list);
+        try!(write_html(&outdir.join(TAG_INDEX), &page("Tags", &body)));
+
+        let module_list = modules
+            .iter()
+            .map(|(module, ids)| format!("<li><a href=\"{}\">{}</a> ({})</li>\n",
+                                         escape(&module_page(module)), escape(module), ids.len()))
+            .collect::<String>();
+        let body = format!("<h2>Modules</h2>\n<ul>\n{}</ul>\n<p><a href=\"{}\">Tags</a></p>",
+                           module_list, TAG_INDEX);
+        try!(write_html(&outdir.join("index.html"), &page("imag", &body)));
+
+        Ok(entries.len())
+    }
+
+}
+
+#[cfg(test)]
+mod test {
+    use std::fs::File;
+    use std::io::Read;
+    use std::path::PathBuf;
+
+    use tempdir::TempDir;
+
+    use libimagstore::file_abstraction::InMemoryBackend;
+    use libimagstore::store::Store;
+    use libimagentrylink::internal::InternalLinker;
+    use libimagentrytag::tagable::Tagable;
+
+    use super::{relative_link, Site};
+
+    fn read(path: PathBuf) -> String {
+        let mut s = String::new();
+        File::open(path).unwrap().read_to_string(&mut s).unwrap();
+        s
+    }
+
+    #[test]
+    fn test_relative_link() {
+        assert_eq!(relative_link("index.html", "notes/a.html"), "notes/a.html");
+        assert_eq!(relative_link("notes/a.html", "diary/2016/b.html"), "../diary/2016/b.html");
+        assert_eq!(relative_link("tags/work/review.html", "index.html"), "../../index.html");
+    }
+
+    #[test]
+    fn test_site_write() {
+        let store = Store::new(PathBuf::from("/"), None, Box::new(InMemoryBackend::new())).unwrap();
+        {
+            let mut a     = store.create(PathBuf::from("notes/a")).unwrap();
+            let mut index = store.create(PathBuf::from("notes/index")).unwrap();
+            a.add_tag(String::from("index")).unwrap();
+            a.add_internal_link(&mut index).unwrap();
+        }
+        let _ = store.create(PathBuf::from("diary/b")).unwrap();
+
+        let mut site = Site::new(&store);
+        site.add_module("notes");
+        let outdir = TempDir::new("imag-view-site").unwrap();
+        assert_eq!(site.write(&outdir.path().to_path_buf()).unwrap(), 2);
+
+        let out = outdir.path();
+        let a   = read(out.join("entries/notes/a.html"));
+        assert!(a.contains("<a href=\"../../index.html\">Index</a>"));
+        assert!(a.contains("<a href=\"../../entries/notes/index.html\">notes/index</a>"));
+        assert!(a.contains("<a href=\"../../tags/index.html\">index</a>"));
+
+        let index = read(out.join("entries/notes/index.html"));
+        assert!(index.contains("<h2>Backlinks</h2>"));
+        assert!(index.contains("<a href=\"../../entries/notes/a.html\">notes/a</a>"));
+
+        let module = read(out.join("modules/notes.html"));
+        assert!(module.contains("<a href=\"../entries/notes/a.html\">notes/a</a>"));
+        assert!(module.contains("<a href=\"../entries/notes/index.html\">notes/index</a>"));
+
+        let tag = read(out.join("tags/index.html"));
+        assert!(tag.contains("<a href=\"../entries/notes/a.html\">notes/a</a>"));
+
+        assert!(read(out.join("tags.html")).contains("<a href=\"tags/index.html\">index</a>"));
+        assert!(read(out.join("index.html")).contains("<a href=\"modules/notes.html\">notes</a> (2)"));
+        assert!(!out.join("entries/diary/b.html").exists());
+    }
+
+}
@@ -67,26 +67,31 @@ fn ask_bool_<R: BufRead>(s: &str, default: Option<bool>, input: &mut R) -> bool
 }
 
 /// Ask the user for an unsigned number. Optionally provide a default value. If none is provided,
-/// this keeps loop{}ing
-pub fn ask_uint(s: &str, default: Option<u64>) -> u64 {
+/// this keeps loop{}ing until the input ends, which is an error then
+pub fn ask_uint(s: &str, default: Option<u64>) -> Result<u64> {
     ask_uint_(s, default, &mut BufReader::new(stdin()))
 }
 
-fn ask_uint_<R: BufRead>(s: &str, default: Option<u64>, input: &mut R) -> u64 {
+fn ask_uint_<R: BufRead>(s: &str, default: Option<u64>, input: &mut R) -> Result<u64> {
     use std::str::FromStr;
 
     loop {
         ask_question(s, false);
 
         let mut s = String::new();
-        let _     = input.read_line(&mut s);
+        match input.read_line(&mut s) {
+            Ok(0) | Err(_) => {
+                return default.ok_or(InteractionError::new(InteractionErrorKind::EndOfInputError, None));
+            },
+            Ok(_) => {},
+        }
 
-        let u : RResult<u64, _> = FromStr::from_str(&s[..]);
+        let u : RResult<u64, _> = FromStr::from_str(s.trim());
         match u {
-            Ok(u)  => { return u; },
+            Ok(u)  => { return Ok(u); },
             Err(_) => {
                 if default.is_some() {
-                    return default.unwrap();
+                    return Ok(default.unwrap());
                 } // else keep looping
             }
         }
@@ -299,7 +304,16 @@ mod test {
         let default  = None;
         let answers  = "1";
 
-        assert!(1 == ask_uint_(question, default, &mut BufReader::new(answers.as_bytes())));
+        assert!(1 == ask_uint_(question, default, &mut BufReader::new(answers.as_bytes())).unwrap());
+    }
+
+    #[test]
+    fn test_ask_uint_nodefault_nl() {
+        let question = "Is this 1";
+        let default  = None;
+        let answers  = "1\n";
+
+        assert!(1 == ask_uint_(question, default, &mut BufReader::new(answers.as_bytes())).unwrap());
     }
 
     #[test]
@@ -308,7 +322,7 @@ mod test {
         let default  = Some(1);
         let answers  = "1";
 
-        assert!(1 == ask_uint_(question, default, &mut BufReader::new(answers.as_bytes())));
+        assert!(1 == ask_uint_(question, default, &mut BufReader::new(answers.as_bytes())).unwrap());
     }
 
     #[test]
@@ -317,7 +331,7 @@ mod test {
         let default  = Some(2);
         let answers  = "1";
 
-        assert!(1 == ask_uint_(question, default, &mut BufReader::new(answers.as_bytes())));
+        assert!(1 == ask_uint_(question, default, &mut BufReader::new(answers.as_bytes())).unwrap());
     }
 
     #[test]
@@ -326,7 +340,7 @@ mod test {
         let default  = Some(2);
         let answers  = "\n";
 
-        assert!(2 == ask_uint_(question, default, &mut BufReader::new(answers.as_bytes())));
+        assert!(2 == ask_uint_(question, default, &mut BufReader::new(answers.as_bytes())).unwrap());
     }
 
     #[test]
@@ -335,7 +349,7 @@ mod test {
         let default  = Some(2);
         let answers  = "\n\n\n\n";
 
-        assert!(2 == ask_uint_(question, default, &mut BufReader::new(answers.as_bytes())));
+        assert!(2 == ask_uint_(question, default, &mut BufReader::new(answers.as_bytes())).unwrap());
     }
 
     #[test]
@@ -344,7 +358,16 @@ mod test {
         let default  = Some(2);
         let answers  = "\n\n\nasfb\nsakjf\naskjf\n-2";
 
-        assert!(2 == ask_uint_(question, default, &mut BufReader::new(answers.as_bytes())));
+        assert!(2 == ask_uint_(question, default, &mut BufReader::new(answers.as_bytes())).unwrap());
+    }
+
+    #[test]
+    fn test_ask_uint_nodefault_end_of_input() {
+        let question = "Is this 1";
+        let default  = None;
+
+        assert!(ask_uint_(question, default, &mut BufReader::new("".as_bytes())).is_err());
+        assert!(ask_uint_(question, default, &mut BufReader::new("a\n\nb\n".as_bytes())).is_err());
     }
 
 }
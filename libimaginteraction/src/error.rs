@@ -29,7 +29,8 @@ generate_error_module!(
         ConfigTypeError     => "Config Type Error",
         NoConfigError       => "No configuration",
         ReadlineHistoryFileCreationError => "Could not create history file for readline",
-        ReadlineError       => "Readline error"
+        ReadlineError       => "Readline error",
+        EndOfInputError     => "Input ended without an answer"
     );
 );
 
@@ -0,0 +1,364 @@
+//
+// imag - the personal information management suite for the commandline
+// Copyright (C) 2015, 2016 Matthias Beyer <mail@beyermatthias.de> and contributors
+//
+// This library is free software; you can redistribute it and/or
+// modify it under the terms of the GNU Lesser General Public
+// License as published by the Free Software Foundation; version
+// 2.1 of the License.
+//
+// This library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+// Lesser General Public License for more details.
+//
+// You should have received a copy of the GNU Lesser General Public
+// License along with this library; if not, write to the Free Software
+// Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA  02110-1301  USA
+//
+
+use std::collections::BTreeMap;
+use std::fmt::{Display, Formatter, Error as FmtError};
+use std::result::Result as RResult;
+
+use error::LedgerErrorKind as LEK;
+use result::Result;
+
+use libimagerror::into::IntoError;
+
+/// The maximum number of decimal places, so the scale factor of an amount fits into an `i64`
+const MAX_SCALE : u32 = 18;
+
+/// An exact decimal amount of a commodity, like "12.50 EUR" or "$3"
+///
+/// The quantity is stored as an integer together with the number of decimal places, so there
+/// are no rounding errors when checking whether a transaction balances.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Amount {
+    value: i64,
+    scale: u32,
+    commodity: String,
+}
+
+impl Amount {
+
+    pub fn new(value: i64, scale: u32, commodity: String) -> Amount {
+        Amount {
+            value: value,
+            scale: scale,
+            commodity: commodity,
+        }
+    }
+
+    /// Parse an amount with the commodity before or after the quantity, like "12.50 EUR",
+    /// "-$1,000.00", "$-3" or "EUR 12"
+    ///
+    /// The decimal separator is the dot, commas are taken as thousands separators.
+    pub fn parse(s: &str) -> Result<Amount> {
+        // Commodities with digits or whitespace are quoted, like "ACME 1"
+        let (s, quoted) = match (s.find('"'), s.rfind('"')) {
+            (Some(a), Some(b)) if a < b => {
+                (format!("{} {}", &s[..a], &s[(b + 1)..]), Some(String::from(&s[(a + 1)..b])))
+            },
+            _ => (String::from(s), None),
+        };
+
+        let s     = s.trim();
+        let first = try!(s.find(|c: char| c.is_digit(10)).ok_or(LEK::AmountParseError.into_error()));
+        let last  = try!(s.rfind(|c: char| c.is_digit(10)).ok_or(LEK::AmountParseError.into_error()));
+
+        let number = &s[first..(last + 1)];
+        if !number.chars().all(|c| c.is_digit(10) || c == '.' || c == ',') {
+            return Err(LEK::AmountParseError.into_error());
+        }
+
+        // The sign may be in front of the commodity or between the commodity and the number
+        let before = s[..first].trim_right();
+        let (negative, before) = if before.ends_with("-") {
+            (true, before[..(before.len() - 1)].trim())
+        } else if before.starts_with("-") {
+            (true, before[1..].trim())
+        } else {
+            (false, before.trim())
+        };
+        let after = s[(last + 1)..].trim();
+
+        let is_quoted = quoted.is_some();
+        let commodity = match (before.is_empty(), after.is_empty(), quoted) {
+            (true, true, Some(quoted)) => quoted,
+            (_, true, None)            => String::from(before),
+            (true, false, None)        => String::from(after),
+            _                          => return Err(LEK::AmountParseError.into_error()),
+        };
+        if commodity.starts_with("-") || (!is_quoted && commodity.chars().any(char::is_whitespace)) {
+            return Err(LEK::AmountParseError.into_error());
+        }
+
+        let number = number.replace(",", "");
+        let mut parts = number.split('.');
+        let int   = parts.next().unwrap_or("");
+        let frac  = parts.next().unwrap_or("");
+        if parts.next().is_some() || frac.len() as u32 > MAX_SCALE {
+            return Err(LEK::AmountParseError.into_error());
+        }
+
+        let value = try!(format!("{}{}", int, frac)
+                         .parse::<i64>()
+                         .map_err(|_| LEK::AmountParseError.into_error()));
+
+        let value = if negative {
+            try!(value.checked_neg().ok_or(LEK::AmountOverflow.into_error()))
+        } else {
+            value
+        };
+
+        Ok(Amount {
+            value: value,
+            scale: frac.len() as u32,
+            commodity: commodity,
+        })
+    }
+
+    pub fn zero(commodity: String) -> Amount {
+        Amount::new(0, 0, commodity)
+    }
+
+    /// The quantity as integer, see `scale()`
+    pub fn value(&self) -> i64 {
+        self.value
+    }
+
+    /// The number of decimal places of the quantity
+    pub fn scale(&self) -> u32 {
+        self.scale
+    }
+
+    pub fn commodity(&self) -> &str {
+        &self.commodity
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.value == 0
+    }
+
+    pub fn is_negative(&self) -> bool {
+        self.value < 0
+    }
+
+    /// The quantity with `scale` decimal places, which must not be less than `self.scale`
+    fn rescaled(&self, scale: u32) -> Result<i64> {
+        (self.scale..scale)
+            .fold(Some(self.value), |value, _| value.and_then(|v| v.checked_mul(10)))
+            .ok_or(LEK::AmountOverflow.into_error())
+    }
+
+    /// Add `other`, which has to be of the same commodity
+    pub fn checked_add(&self, other: &Amount) -> Result<Amount> {
+        if self.commodity != other.commodity {
+            return Err(LEK::CommodityMismatch.into_error());
+        }
+
+        let scale = ::std::cmp::max(self.scale, other.scale);
+        let value = try!(try!(self.rescaled(scale))
+                         .checked_add(try!(other.rescaled(scale)))
+                         .ok_or(LEK::AmountOverflow.into_error()));
+        Ok(Amount::new(value, scale, self.commodity.clone()))
+    }
+
+    /// Multiply the quantity of this amount with the (unit) price `price`, the result is an
+    /// amount of the commodity of the price
+    pub fn times(&self, price: &Amount) -> Result<Amount> {
+        let scale = self.scale + price.scale;
+        if scale > MAX_SCALE {
+            return Err(LEK::AmountOverflow.into_error());
+        }
+
+        let value = try!(self.value.checked_mul(price.value).ok_or(LEK::AmountOverflow.into_error()));
+        Ok(Amount::new(value, scale, price.commodity.clone()))
+    }
+
+    /// The negated amount, fails if the quantity is the smallest `i64`
+    pub fn checked_neg(&self) -> Result<Amount> {
+        self.value
+            .checked_neg()
+            .map(|value| Amount::new(value, self.scale, self.commodity.clone()))
+            .ok_or(LEK::AmountOverflow.into_error())
+    }
+
+    /// The quantity without commodity, like "-12.50"
+    pub fn quantity(&self) -> String {
+        let sign = if self.value < 0 { "-" } else { "" };
+        // The absolute value of the smallest i64 does not fit into an i64
+        let abs  = if self.value < 0 {
+            (self.value as u64).wrapping_neg()
+        } else {
+            self.value as u64
+        };
+        if self.scale == 0 {
+            return format!("{}{}", sign, abs);
+        }
+
+        // If the factor does not fit into an u64, it is larger than any quantity
+        let factor = (0..self.scale).fold(Some(1u64), |f, _| f.and_then(|f| f.checked_mul(10)));
+        let (int, frac) = match factor {
+            Some(factor) => (abs / factor, abs % factor),
+            None         => (0, abs),
+        };
+        format!("{}{}.{:0width$}", sign, int, frac, width = self.scale as usize)
+    }
+
+}
+
+impl Display for Amount {
+
+    /// Symbols like "$" are written in front of the quantity, other commodities after it
+    fn fmt(&self, fmt: &mut Formatter) -> RResult<(), FmtError> {
+        let is_symbol = self.commodity.chars().count() == 1 &&
+            !self.commodity.chars().all(char::is_alphanumeric);
+
+        if self.commodity.is_empty() {
+            write!(fmt, "{}", self.quantity())
+        } else if is_symbol {
+            let quantity = self.quantity();
+            if quantity.starts_with("-") {
+                write!(fmt, "-{}{}", self.commodity, &quantity[1..])
+            } else {
+                write!(fmt, "{}{}", self.commodity, quantity)
+            }
+        } else if self.commodity.chars().all(char::is_alphabetic) {
+            write!(fmt, "{} {}", self.quantity(), self.commodity)
+        } else {
+            write!(fmt, "{} \"{}\"", self.quantity(), self.commodity)
+        }
+    }
+
+}
+
+/// The sum of amounts of possibly different commodities
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Balance(BTreeMap<String, Amount>);
+
+impl Balance {
+
+    pub fn new() -> Balance {
+        Balance(BTreeMap::new())
+    }
+
+    /// Add `amount`, fails if the sum of its commodity overflows
+    pub fn add(&mut self, amount: &Amount) -> Result<()> {
+        let sum = match self.0.get(amount.commodity()) {
+            Some(a) => try!(a.checked_add(amount)),
+            None    => amount.clone(),
+        };
+        self.0.insert(String::from(amount.commodity()), sum);
+        Ok(())
+    }
+
+    pub fn add_balance(&mut self, other: &Balance) -> Result<()> {
+        for amount in other.0.values() {
+            try!(self.add(amount));
+        }
+        Ok(())
+    }
+
+    /// Whether all amounts are zero
+    pub fn is_zero(&self) -> bool {
+        self.0.values().all(Amount::is_zero)
+    }
+
+    /// The amounts which are not zero, ordered by commodity
+    pub fn amounts(&self) -> Vec<&Amount> {
+        self.0.values().filter(|a| !a.is_zero()).collect()
+    }
+
+}
+
+impl Display for Balance {
+
+    fn fmt(&self, fmt: &mut Formatter) -> RResult<(), FmtError> {
+        let amounts = self.amounts();
+        if amounts.is_empty() {
+            return write!(fmt, "0");
+        }
+
+        let amounts = amounts.iter().map(|a| format!("{}", a)).collect::<Vec<_>>();
+        write!(fmt, "{}", amounts.join(", "))
+    }
+
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(Amount::parse("12.50 EUR").unwrap(), Amount::new(1250, 2, String::from("EUR")));
+        assert_eq!(Amount::parse("EUR -3").unwrap(), Amount::new(-3, 0, String::from("EUR")));
+        assert_eq!(Amount::parse("-$1,000.5").unwrap(), Amount::new(-10005, 1, String::from("$")));
+        assert_eq!(Amount::parse("$-2").unwrap(), Amount::new(-2, 0, String::from("$")));
+        assert_eq!(Amount::parse("10 \"ACME 1\"").unwrap(), Amount::new(10, 0, String::from("ACME 1")));
+        assert_eq!(Amount::parse("42").unwrap(), Amount::new(42, 0, String::new()));
+
+        assert!(Amount::parse("EUR").is_err());
+        assert!(Amount::parse("EUR 1 USD").is_err());
+        assert!(Amount::parse("1.2.3 EUR").is_err());
+        assert!(Amount::parse("0.0000000000000000001 EUR").is_err());
+    }
+
+    #[test]
+    fn test_display_roundtrip() {
+        for s in &["12.50 EUR", "-$1000.05", "$3", "0.001 BTC", "5 \"ACME 1\"", "7"] {
+            let amount = Amount::parse(s).unwrap();
+            assert_eq!(&format!("{}", amount), s);
+            assert_eq!(Amount::parse(&format!("{}", amount)).unwrap(), amount);
+        }
+    }
+
+    #[test]
+    fn test_arithmetic() {
+        let a = Amount::parse("12.5 EUR").unwrap();
+        let b = Amount::parse("-12.50 EUR").unwrap();
+        assert!(a.checked_add(&b).unwrap().is_zero());
+        assert!(a.checked_add(&Amount::parse("1 USD").unwrap()).is_err());
+
+        let price = Amount::parse("$1.10").unwrap();
+        assert_eq!(format!("{}", a.times(&price).unwrap()), "$13.750");
+    }
+
+    #[test]
+    fn test_overflow() {
+        let max = Amount::new(::std::i64::MAX, 0, String::from("EUR"));
+        assert!(max.checked_add(&Amount::parse("1 EUR").unwrap()).is_err());
+        assert!(max.checked_add(&Amount::parse("0.1 EUR").unwrap()).is_err());
+        assert!(max.times(&Amount::parse("$2").unwrap()).is_err());
+
+        let small = Amount::parse("0.000000001 EUR").unwrap();
+        assert!(small.times(&Amount::parse("$0.0000000001").unwrap()).is_err());
+
+        let min = Amount::new(::std::i64::MIN, 0, String::from("EUR"));
+        assert!(min.checked_neg().is_err());
+        assert_eq!(min.quantity(), "-9223372036854775808");
+        assert_eq!(Amount::new(::std::i64::MIN, 20, String::new()).quantity(),
+                   "-0.09223372036854775808");
+
+        let mut balance = Balance::new();
+        balance.add(&max).unwrap();
+        assert!(balance.add(&Amount::parse("1 EUR").unwrap()).is_err());
+    }
+
+    #[test]
+    fn test_balance() {
+        let mut balance = Balance::new();
+        balance.add(&Amount::parse("10 EUR").unwrap()).unwrap();
+        balance.add(&Amount::parse("$5").unwrap()).unwrap();
+        balance.add(&Amount::parse("-10.00 EUR").unwrap()).unwrap();
+        assert_eq!(format!("{}", balance), "$5");
+        assert!(!balance.is_zero());
+
+        balance.add(&Amount::parse("-$5").unwrap()).unwrap();
+        assert!(balance.is_zero());
+        assert_eq!(format!("{}", balance), "0");
+    }
+
+}
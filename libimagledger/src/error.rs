@@ -0,0 +1,42 @@
+//
+// imag - the personal information management suite for the commandline
+// Copyright (C) 2015, 2016 Matthias Beyer <mail@beyermatthias.de> and contributors
+//
+// This library is free software; you can redistribute it and/or
+// modify it under the terms of the GNU Lesser General Public
+// License as published by the Free Software Foundation; version
+// 2.1 of the License.
+//
+// This library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+// Lesser General Public License for more details.
+//
+// You should have received a copy of the GNU Lesser General Public
+// License along with this library; if not, write to the Free Software
+// Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA  02110-1301  USA
+//
+
+generate_error_module!(
+    generate_error_types!(LedgerError, LedgerErrorKind,
+        AmountParseError      => "Error parsing amount",
+        CommodityMismatch     => "Cannot add amounts of different commodities",
+        AmountOverflow        => "Amount is too large",
+        DateParseError        => "Error parsing date",
+        JournalParseError     => "Error parsing journal",
+        AmbiguousPosting      => "More than one posting without amount",
+        NoPostings            => "Transaction has no postings",
+        UnbalancedTransaction => "Postings of transaction do not balance",
+        NotATransaction       => "Entry is not a transaction",
+        StoreReadError        => "Error reading store",
+        StoreWriteError       => "Error writing store",
+        HeaderReadError       => "Error reading header",
+        HeaderWriteError      => "Error writing header",
+        HeaderTypeError       => "Header type error"
+    );
+);
+
+pub use self::error::LedgerError;
+pub use self::error::LedgerErrorKind;
+pub use self::error::MapErrInto;
+
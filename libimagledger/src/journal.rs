@@ -0,0 +1,314 @@
+//
+// imag - the personal information management suite for the commandline
+// Copyright (C) 2015, 2016 Matthias Beyer <mail@beyermatthias.de> and contributors
+//
+// This library is free software; you can redistribute it and/or
+// modify it under the terms of the GNU Lesser General Public
+// License as published by the Free Software Foundation; version
+// 2.1 of the License.
+//
+// This library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+// Lesser General Public License for more details.
+//
+// You should have received a copy of the GNU Lesser General Public
+// License along with this library; if not, write to the Free Software
+// Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA  02110-1301  USA
+//
+
+//! Reading and writing the journal syntax of ledger and hledger
+//!
+//! Only transactions are read. Directives (like "account" or "P"), periodic and automated
+//! transactions and comments are skipped, balance assertions are not checked.
+
+use chrono::naive::date::NaiveDate;
+
+use libimagerror::into::IntoError;
+
+use amount::{Amount, Balance};
+use transaction::{Posting, Status, Transaction};
+use error::LedgerErrorKind as LEK;
+use result::Result;
+
+/// A posting as written in the journal: the amount may be left out
+type RawPosting = (String, Option<(Amount, Option<Amount>)>);
+
+/// Parse all transactions of a journal
+///
+/// Amounts which are left out are inferred and all transactions have to balance.
+pub fn parse_journal(text: &str) -> Result<Vec<Transaction>> {
+    let mut transactions = vec![];
+    let mut current : Option<(Transaction, Vec<RawPosting>)> = None;
+    let mut skipping = false;
+
+    for (n, line) in text.lines().enumerate() {
+        let result = parse_line(line, &mut current, &mut skipping, &mut transactions);
+        if result.is_err() {
+            warn!("Cannot parse line {} of the journal: '{}'", n + 1, line);
+            return result.map(|_| vec![]);
+        }
+    }
+
+    if let Some((transaction, postings)) = current {
+        transactions.push(try!(finish(transaction, postings)));
+    }
+
+    Ok(transactions)
+}
+
+fn parse_line(line: &str,
+              current: &mut Option<(Transaction, Vec<RawPosting>)>,
+              skipping: &mut bool,
+              transactions: &mut Vec<Transaction>)
+    -> Result<()>
+{
+    if line.trim().is_empty() {
+        *skipping = false;
+        if let Some((transaction, postings)) = current.take() {
+            transactions.push(try!(finish(transaction, postings)));
+        }
+        return Ok(());
+    }
+
+    if line.starts_with(" ") || line.starts_with("\t") {
+        let trimmed = line.trim();
+        if *skipping || trimmed.starts_with(";") || trimmed.starts_with("#") {
+            return Ok(());
+        }
+
+        return match *current {
+            Some((_, ref mut postings)) => parse_posting(trimmed).map(|p| postings.push(p)),
+            None                        => Err(LEK::JournalParseError.into_error()),
+        };
+    }
+
+    if let Some((transaction, postings)) = current.take() {
+        transactions.push(try!(finish(transaction, postings)));
+    }
+
+    if line.chars().next().map(|c| c.is_digit(10)).unwrap_or(false) {
+        *skipping = false;
+        *current  = Some((try!(parse_transaction_line(line)), vec![]));
+    } else {
+        // Comments and directives, including the lines which belong to them
+        *skipping = true;
+    }
+
+    Ok(())
+}
+
+/// Parse a line like "2016/10/17=2016/10/18 * (1234) Payee  ; note"
+fn parse_transaction_line(line: &str) -> Result<Transaction> {
+    let (line, note) = split_comment(line);
+
+    let mut parts = line.trim().splitn(2, char::is_whitespace);
+    let date      = try!(parts.next().ok_or(LEK::JournalParseError.into_error()));
+    // The auxiliary date is ignored
+    let date      = try!(parse_date(date.split('=').next().unwrap_or(date)));
+    let mut rest  = parts.next().unwrap_or("").trim();
+
+    let mut transaction = Transaction::new(date, String::new());
+    transaction.note    = note;
+
+    if rest.starts_with("*") {
+        transaction.status = Status::Cleared;
+        rest = rest[1..].trim();
+    } else if rest.starts_with("!") {
+        transaction.status = Status::Pending;
+        rest = rest[1..].trim();
+    }
+
+    if rest.starts_with("(") {
+        let end = try!(rest.find(')').ok_or(LEK::JournalParseError.into_error()));
+        transaction.code = Some(String::from(&rest[1..end]));
+        rest = rest[(end + 1)..].trim();
+    }
+
+    transaction.payee = String::from(rest);
+    Ok(transaction)
+}
+
+/// Parse a date like "2016-10-17", "2016/10/17" or "2016.10.17"
+pub fn parse_date(s: &str) -> Result<NaiveDate> {
+    NaiveDate::parse_from_str(&s.replace("/", "-").replace(".", "-"), "%Y-%m-%d")
+        .map_err(|e| LEK::DateParseError.into_error_with_cause(Box::new(e)))
+}
+
+/// Parse a posting like "Assets:Checking  -10 AAPL @ $100 = -20 AAPL  ; note"
+fn parse_posting(line: &str) -> Result<RawPosting> {
+    let (line, _) = split_comment(line);
+    let line = line.trim();
+    let line = if line.starts_with("* ") || line.starts_with("! ") { line[2..].trim() } else { line };
+
+    // The account ends at two spaces or a tab
+    let (account, rest) = match (line.find("  "), line.find('\t')) {
+        (Some(a), Some(b)) => (&line[..::std::cmp::min(a, b)], &line[::std::cmp::min(a, b)..]),
+        (Some(i), None) | (None, Some(i)) => (&line[..i], &line[i..]),
+        (None, None) => (line, ""),
+    };
+
+    let account = String::from(account.trim());
+    if account.is_empty() {
+        return Err(LEK::JournalParseError.into_error());
+    }
+
+    // Balance assertions are not checked
+    let rest = rest.split('=').next().unwrap_or("").trim();
+    if rest.is_empty() {
+        return Ok((account, None));
+    }
+
+    let amount = if let Some(i) = rest.find("@@") {
+        let amount = try!(Amount::parse(&rest[..i]));
+        let total  = try!(Amount::parse(&rest[(i + 2)..]));
+        // The total cost is written without sign
+        let cost   = if total.is_negative() != amount.is_negative() {
+            try!(total.checked_neg())
+        } else {
+            total
+        };
+        (amount, Some(cost))
+    } else if let Some(i) = rest.find('@') {
+        let amount = try!(Amount::parse(&rest[..i]));
+        let price  = try!(Amount::parse(&rest[(i + 1)..]));
+        let cost   = try!(amount.times(&price));
+        (amount, Some(cost))
+    } else {
+        (try!(Amount::parse(rest)), None)
+    };
+
+    Ok((account, Some(amount)))
+}
+
+/// Fill in the amount which was left out and check that the transaction balances
+fn finish(mut transaction: Transaction, postings: Vec<RawPosting>) -> Result<Transaction> {
+    let mut balance = Balance::new();
+    let mut elided  = None;
+
+    for (i, &(ref account, ref amount)) in postings.iter().enumerate() {
+        match *amount {
+            Some((ref amount, ref cost)) => {
+                let mut posting = Posting::new(account.clone(), amount.clone());
+                posting.cost    = cost.clone();
+                if !posting.is_virtual() {
+                    try!(balance.add(posting.weight()));
+                }
+            },
+            None if elided.is_none() => elided = Some(i),
+            None                     => return Err(LEK::AmbiguousPosting.into_error()),
+        }
+    }
+
+    for (i, (account, amount)) in postings.into_iter().enumerate() {
+        match amount {
+            Some((amount, cost)) => {
+                let mut posting = Posting::new(account, amount);
+                posting.cost    = cost;
+                transaction.postings.push(posting);
+            },
+            None => {
+                debug_assert!(elided == Some(i));
+                let amounts = balance.amounts();
+                if amounts.is_empty() {
+                    transaction.postings.push(Posting::new(account, Amount::zero(String::new())));
+                }
+
+                // One posting per commodity
+                for amount in amounts {
+                    let amount = try!(amount.checked_neg());
+                    transaction.postings.push(Posting::new(account.clone(), amount));
+                }
+            },
+        }
+    }
+
+    try!(transaction.validate());
+    Ok(transaction)
+}
+
+/// Split a line at the first ";", returns the part before it and the comment
+fn split_comment(line: &str) -> (&str, Option<String>) {
+    match line.find(';') {
+        Some(i) => {
+            let comment = line[(i + 1)..].trim();
+            (&line[..i], if comment.is_empty() { None } else { Some(String::from(comment)) })
+        },
+        None => (line, None),
+    }
+}
+
+/// Write transactions in journal syntax, separated by empty lines
+pub fn to_journal<'a, I: Iterator<Item = &'a Transaction>>(transactions: I) -> String {
+    transactions.map(Transaction::to_journal).collect::<Vec<_>>().join("\n")
+}
+
+#[cfg(test)]
+mod test {
+    use amount::Amount;
+    use transaction::Status;
+
+    use super::*;
+
+    const JOURNAL : &'static str = "; A journal
+account Expenses:Food
+    note Everything to eat
+
+2016/10/17 * (42) Grocery  ; weekly
+    Expenses:Food           12.50 EUR
+    ; a comment
+    Assets:Cash
+
+2016-10-18 ! Broker
+    Assets:Stocks     10 AAPL @ $100.5
+    Assets:Bank\t-$1,005 = $0
+    (Budget:Stocks)   $-1005
+
+~ monthly
+    Expenses:Rent  500 EUR
+    Assets:Bank
+";
+
+    #[test]
+    fn test_parse_journal() {
+        let transactions = parse_journal(JOURNAL).unwrap();
+        assert_eq!(transactions.len(), 2);
+
+        let grocery = &transactions[0];
+        assert_eq!(grocery.payee, "Grocery");
+        assert_eq!(grocery.status, Status::Cleared);
+        assert_eq!(grocery.code, Some(String::from("42")));
+        assert_eq!(grocery.note, Some(String::from("weekly")));
+        assert_eq!(grocery.postings[1].account, "Assets:Cash");
+        assert_eq!(grocery.postings[1].amount, Amount::parse("-12.50 EUR").unwrap());
+
+        let broker = &transactions[1];
+        assert_eq!(broker.status, Status::Pending);
+        assert_eq!(broker.postings.len(), 3);
+        assert_eq!(broker.postings[0].cost, Some(Amount::parse("$1005.0").unwrap()));
+        assert!(broker.postings[2].is_virtual());
+    }
+
+    #[test]
+    fn test_unbalanced() {
+        assert!(parse_journal("2016-10-17 x\n    A  1 EUR\n    B  -2 EUR\n").is_err());
+        assert!(parse_journal("2016-10-17 x\n    A  1 EUR\n    B\n    C\n").is_err());
+        assert!(parse_journal("2016-10-17 x\n").is_err());
+        assert!(parse_journal("    A  1 EUR\n").is_err());
+    }
+
+    #[test]
+    fn test_multiple_commodities_elided() {
+        let t = parse_journal("2016-10-17 x\n    A  1 EUR\n    B  $2\n    C\n").unwrap();
+        assert_eq!(t[0].postings.len(), 4);
+        assert!(t[0].validate().is_ok());
+    }
+
+    #[test]
+    fn test_roundtrip() {
+        let transactions = parse_journal(JOURNAL).unwrap();
+        let journal      = to_journal(transactions.iter());
+        assert_eq!(parse_journal(&journal).unwrap(), transactions);
+    }
+
+}
@@ -0,0 +1,235 @@
+//
+// imag - the personal information management suite for the commandline
+// Copyright (C) 2015, 2016 Matthias Beyer <mail@beyermatthias.de> and contributors
+//
+// This library is free software; you can redistribute it and/or
+// modify it under the terms of the GNU Lesser General Public
+// License as published by the Free Software Foundation; version
+// 2.1 of the License.
+//
+// This library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+// Lesser General Public License for more details.
+//
+// You should have received a copy of the GNU Lesser General Public
+// License along with this library; if not, write to the Free Software
+// Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA  02110-1301  USA
+//
+
+use std::collections::BTreeMap;
+
+use crypto::digest::Digest;
+use crypto::sha1::Sha1;
+
+use libimagstore::store::{FileLockEntry, Store};
+use libimagstore::storeid::{IntoStoreId, StoreId};
+use libimagutil::import::ImportAction;
+
+use amount::Balance;
+use journal::parse_journal;
+use module_path::ModuleEntryPath;
+use transaction::Transaction;
+use error::LedgerErrorKind as LEK;
+use error::MapErrInto;
+use result::Result;
+
+/// Import all transactions of the journal `text`
+///
+/// Each transaction is stored as `ledger/<year>/<month>/<day>/<hash>`, where the hash is computed
+/// from the transaction. Importing the same journal again does therefore not create duplicates,
+/// while identical transactions within one journal get a counter appended to the hash.
+pub fn import_journal(store: &Store, text: &str) -> Result<Vec<(StoreId, ImportAction)>> {
+    let mut imported = vec![];
+    let mut seen : BTreeMap<String, usize> = BTreeMap::new();
+
+    for transaction in try!(parse_journal(text)) {
+        let mut hasher = Sha1::new();
+        hasher.input_str(&transaction.to_journal());
+        let hash = String::from(&hasher.result_str()[..16]);
+
+        let count = {
+            let count = seen.entry(hash.clone()).or_insert(0);
+            *count += 1;
+            *count
+        };
+
+        let name = if count == 1 { hash } else { format!("{}-{}", hash, count) };
+        let path = format!("{}/{}", transaction.date.format("%Y/%m/%d"), name);
+        let id   = try!(ModuleEntryPath::new(path).into_storeid().map_err_into(LEK::StoreWriteError));
+
+        let action = match try!(store.get(id.clone()).map_err_into(LEK::StoreReadError)) {
+            Some(_) => ImportAction::Unchanged,
+            None    => {
+                let mut entry = try!(store.create(id.clone()).map_err_into(LEK::StoreWriteError));
+                try!(entry.get_header_mut()
+                     .set("ledger", transaction.to_header())
+                     .map_err_into(LEK::HeaderWriteError));
+                ImportAction::Created
+            },
+        };
+
+        imported.push((id.without_base(), action));
+    }
+
+    Ok(imported)
+}
+
+/// Get all transaction entries together with their transactions, sorted by date
+pub fn transactions(store: &Store) -> Result<Vec<(FileLockEntry, Transaction)>> {
+    let mut transactions = vec![];
+    for id in try!(store.retrieve_for_module("ledger").map_err_into(LEK::StoreReadError)) {
+        let entry       = try!(store.retrieve(id).map_err_into(LEK::StoreReadError));
+        let transaction = try!(Transaction::from_entry(&entry));
+        transactions.push((entry, transaction));
+    }
+
+    transactions.sort_by(|a, b| a.1.date.cmp(&b.1.date));
+    Ok(transactions)
+}
+
+/// Check whether `account` is `pattern` or a subaccount of it
+pub fn account_matches(account: &str, pattern: &str) -> bool {
+    account == pattern || (account.starts_with(pattern) && account[pattern.len()..].starts_with(":"))
+}
+
+/// Cut the account name after `depth` levels, "Expenses:Food:Fruit" becomes "Expenses:Food"
+/// with a depth of 2
+pub fn truncate_account(account: &str, depth: usize) -> String {
+    account.split(':').take(depth).collect::<Vec<_>>().join(":")
+}
+
+/// Sum up the postings per account
+///
+/// If `depth` is passed, the postings of subaccounts below this depth are summed up in their
+/// parent account.
+pub fn balances<'a, I>(transactions: I, depth: Option<usize>) -> Result<BTreeMap<String, Balance>>
+    where I: Iterator<Item = &'a Transaction>
+{
+    let mut balances : BTreeMap<String, Balance> = BTreeMap::new();
+    for transaction in transactions {
+        for posting in transaction.postings.iter() {
+            let account = match depth {
+                Some(depth) => truncate_account(&posting.account, depth),
+                None        => posting.account.clone(),
+            };
+
+            try!(balances.entry(account).or_insert_with(Balance::new).add(&posting.amount));
+        }
+    }
+    Ok(balances)
+}
+
+/// Sum up the postings of the accounts matching `pattern` (or all accounts) per month
+///
+/// Returns the month (as "YYYY-MM"), the change in this month and the running total.
+pub fn monthly_register<'a, I>(transactions: I, pattern: Option<&str>)
+    -> Result<Vec<(String, Balance, Balance)>>
+    where I: Iterator<Item = &'a Transaction>
+{
+    let mut months : BTreeMap<String, Balance> = BTreeMap::new();
+    for transaction in transactions {
+        let month = transaction.date.format("%Y-%m").to_string();
+        let change = months.entry(month).or_insert_with(Balance::new);
+        for posting in transaction.postings.iter() {
+            if pattern.map(|p| account_matches(&posting.account, p)).unwrap_or(true) {
+                try!(change.add(&posting.amount));
+            }
+        }
+    }
+
+    let mut total    = Balance::new();
+    let mut register = vec![];
+    for (month, change) in months {
+        try!(total.add_balance(&change));
+        register.push((month, change, total.clone()));
+    }
+    Ok(register)
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::PathBuf;
+
+    use libimagstore::store::Store;
+    use libimagstore::file_abstraction::InMemoryBackend;
+
+    use super::*;
+    use journal::to_journal;
+
+    fn get_store() -> Store {
+        Store::new(PathBuf::from("/"), None, Box::new(InMemoryBackend::new())).unwrap()
+    }
+
+    const JOURNAL : &'static str = "2016-09-30 Salary
+    Assets:Bank        1000 EUR
+    Income:Salary
+
+2016-10-01 Coffee
+    Expenses:Food:Coffee  2.50 EUR
+    Assets:Cash
+
+2016-10-01 Coffee
+    Expenses:Food:Coffee  2.50 EUR
+    Assets:Cash
+
+2016-10-02 Grocery
+    Expenses:Food      10 EUR
+    Assets:Bank
+";
+
+    #[test]
+    fn test_import() {
+        let store    = get_store();
+        let imported = import_journal(&store, JOURNAL).unwrap();
+        assert_eq!(imported.len(), 4);
+        assert!(imported.iter().all(|&(_, a)| a == ImportAction::Created));
+        assert!(imported[1].0 != imported[2].0);
+
+        let imported = import_journal(&store, JOURNAL).unwrap();
+        assert!(imported.iter().all(|&(_, a)| a == ImportAction::Unchanged));
+
+        let transactions = transactions(&store).unwrap();
+        assert_eq!(transactions.len(), 4);
+
+        let transactions = transactions.into_iter().map(|(_, t)| t).collect::<Vec<_>>();
+        let exported     = to_journal(transactions.iter());
+        assert_eq!(parse_journal(&exported).unwrap(), parse_journal(JOURNAL).unwrap());
+    }
+
+    #[test]
+    fn test_balances() {
+        let transactions = parse_journal(JOURNAL).unwrap();
+
+        let balances = balances(transactions.iter(), Some(2)).unwrap();
+        let balances = balances.iter().map(|(a, b)| (a.clone(), format!("{}", b))).collect::<Vec<_>>();
+        assert_eq!(balances, vec![
+            (String::from("Assets:Bank"), String::from("990 EUR")),
+            (String::from("Assets:Cash"), String::from("-5.00 EUR")),
+            (String::from("Expenses:Food"), String::from("15.00 EUR")),
+            (String::from("Income:Salary"), String::from("-1000 EUR")),
+        ]);
+    }
+
+    #[test]
+    fn test_monthly_register() {
+        let transactions = parse_journal(JOURNAL).unwrap();
+        let register     = monthly_register(transactions.iter(), Some("Expenses:Food")).unwrap();
+        let register     = register.iter()
+            .map(|&(ref m, ref c, ref t)| (m.clone(), format!("{}", c), format!("{}", t)))
+            .collect::<Vec<_>>();
+
+        assert_eq!(register, vec![
+            (String::from("2016-09"), String::from("0"), String::from("0")),
+            (String::from("2016-10"), String::from("15.00 EUR"), String::from("15.00 EUR")),
+        ]);
+    }
+
+    #[test]
+    fn test_account_matches() {
+        assert!(account_matches("Expenses:Food", "Expenses"));
+        assert!(account_matches("Expenses", "Expenses"));
+        assert!(!account_matches("ExpensesX", "Expenses"));
+    }
+
+}
@@ -0,0 +1,328 @@
+//
+// imag - the personal information management suite for the commandline
+// Copyright (C) 2015, 2016 Matthias Beyer <mail@beyermatthias.de> and contributors
+//
+// This library is free software; you can redistribute it and/or
+// modify it under the terms of the GNU Lesser General Public
+// License as published by the Free Software Foundation; version
+// 2.1 of the License.
+//
+// This library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+// Lesser General Public License for more details.
+//
+// You should have received a copy of the GNU Lesser General Public
+// License along with this library; if not, write to the Free Software
+// Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA  02110-1301  USA
+//
+
+use std::collections::BTreeMap;
+use std::iter::repeat;
+
+use chrono::naive::date::NaiveDate;
+use toml::Value;
+
+use libimagstore::store::Entry;
+use libimagerror::into::IntoError;
+
+use amount::{Amount, Balance};
+use error::LedgerErrorKind as LEK;
+use result::Result;
+
+/// The format of the date in the header
+pub const DATE_FORMAT : &'static str = "%Y-%m-%d";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Status {
+    Uncleared,
+    Pending,
+    Cleared,
+}
+
+/// A posting of a transaction: an amount which is moved to or from an account
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Posting {
+    pub account: String,
+    pub amount: Amount,
+
+    /// The total cost of the amount in another commodity, as in "10 AAPL @@ $1000"
+    pub cost: Option<Amount>,
+}
+
+impl Posting {
+
+    pub fn new(account: String, amount: Amount) -> Posting {
+        Posting {
+            account: account,
+            amount: amount,
+            cost: None,
+        }
+    }
+
+    /// Whether this is a virtual posting which does not have to balance, like "(Budget:Food)"
+    pub fn is_virtual(&self) -> bool {
+        self.account.starts_with("(") && self.account.ends_with(")")
+    }
+
+    /// The amount which counts when balancing the transaction: the cost if there is one,
+    /// otherwise the amount
+    pub fn weight(&self) -> &Amount {
+        self.cost.as_ref().unwrap_or(&self.amount)
+    }
+
+}
+
+/// A transaction, as in a ledger journal
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Transaction {
+    pub date: NaiveDate,
+    pub status: Status,
+    pub code: Option<String>,
+    pub payee: String,
+    pub note: Option<String>,
+    pub postings: Vec<Posting>,
+}
+
+impl Transaction {
+
+    pub fn new(date: NaiveDate, payee: String) -> Transaction {
+        Transaction {
+            date: date,
+            status: Status::Uncleared,
+            code: None,
+            payee: payee,
+            note: None,
+            postings: vec![],
+        }
+    }
+
+    /// The sum of the weights of all postings which are not virtual
+    pub fn balance(&self) -> Result<Balance> {
+        let mut balance = Balance::new();
+        for posting in self.postings.iter().filter(|p| !p.is_virtual()) {
+            try!(balance.add(posting.weight()));
+        }
+        Ok(balance)
+    }
+
+    /// Check that the transaction has postings and that they balance
+    pub fn validate(&self) -> Result<()> {
+        if self.postings.is_empty() {
+            return Err(LEK::NoPostings.into_error());
+        }
+
+        if !try!(self.balance()).is_zero() {
+            return Err(LEK::UnbalancedTransaction.into_error());
+        }
+
+        Ok(())
+    }
+
+    /// Write the transaction in journal syntax
+    pub fn to_journal(&self) -> String {
+        let mut s = self.date.format(DATE_FORMAT).to_string();
+        match self.status {
+            Status::Cleared   => s.push_str(" *"),
+            Status::Pending   => s.push_str(" !"),
+            Status::Uncleared => {},
+        }
+        if let Some(ref code) = self.code {
+            s.push_str(&format!(" ({})", code));
+        }
+        if !self.payee.is_empty() {
+            s.push_str(&format!(" {}", self.payee));
+        }
+        if let Some(ref note) = self.note {
+            s.push_str(&format!("  ; {}", note));
+        }
+        s.push('\n');
+
+        let width = self.postings.iter().map(|p| p.account.chars().count()).max().unwrap_or(0);
+        for posting in self.postings.iter() {
+            let padding = repeat(' ').take(width - posting.account.chars().count()).collect::<String>();
+            s.push_str(&format!("    {}{}  {}", posting.account, padding, posting.amount));
+            if let Some(ref cost) = posting.cost {
+                let cost = if cost.is_negative() { -cost.clone() } else { cost.clone() };
+                s.push_str(&format!(" @@ {}", cost));
+            }
+            s.push('\n');
+        }
+
+        s
+    }
+
+    /// Build the "ledger" section of the header
+    pub fn to_header(&self) -> Value {
+        let mut section = BTreeMap::new();
+        section.insert(String::from("date"), Value::String(self.date.format(DATE_FORMAT).to_string()));
+        section.insert(String::from("payee"), Value::String(self.payee.clone()));
+
+        match self.status {
+            Status::Cleared   => { section.insert(String::from("status"), Value::String(String::from("cleared"))); },
+            Status::Pending   => { section.insert(String::from("status"), Value::String(String::from("pending"))); },
+            Status::Uncleared => {},
+        }
+
+        if let Some(ref code) = self.code {
+            section.insert(String::from("code"), Value::String(code.clone()));
+        }
+        if let Some(ref note) = self.note {
+            section.insert(String::from("note"), Value::String(note.clone()));
+        }
+
+        let postings = self.postings
+            .iter()
+            .map(|p| {
+                let mut posting = BTreeMap::new();
+                posting.insert(String::from("account"), Value::String(p.account.clone()));
+                posting.insert(String::from("amount"), Value::String(p.amount.quantity()));
+                posting.insert(String::from("commodity"), Value::String(String::from(p.amount.commodity())));
+                if let Some(ref cost) = p.cost {
+                    posting.insert(String::from("cost_amount"), Value::String(cost.quantity()));
+                    posting.insert(String::from("cost_commodity"), Value::String(String::from(cost.commodity())));
+                }
+                Value::Table(posting)
+            })
+            .collect();
+        section.insert(String::from("postings"), Value::Array(postings));
+
+        Value::Table(section)
+    }
+
+    /// Read the transaction from the "ledger" section of the header of `entry`
+    pub fn from_entry(entry: &Entry) -> Result<Transaction> {
+        let section = match entry.get_header().read("ledger") {
+            Ok(Some(Value::Table(t))) => t,
+            Ok(Some(_))               => return Err(LEK::HeaderTypeError.into_error()),
+            Ok(None)                  => return Err(LEK::NotATransaction.into_error()),
+            Err(e)                    => return Err(LEK::HeaderReadError.into_error_with_cause(Box::new(e))),
+        };
+
+        let date = try!(get_string(&section, "date")
+            .and_then(|d| d.ok_or(LEK::HeaderTypeError.into_error()))
+            .and_then(|d| {
+                NaiveDate::parse_from_str(&d, DATE_FORMAT)
+                    .map_err(|e| LEK::DateParseError.into_error_with_cause(Box::new(e)))
+            }));
+
+        let status = match try!(get_string(&section, "status")) {
+            Some(ref s) if s == "cleared" => Status::Cleared,
+            Some(ref s) if s == "pending" => Status::Pending,
+            _                             => Status::Uncleared,
+        };
+
+        let postings = match section.get("postings") {
+            Some(&Value::Array(ref a)) => try!(a.iter().map(posting_from_header).collect()),
+            _                          => return Err(LEK::HeaderTypeError.into_error()),
+        };
+
+        Ok(Transaction {
+            date: date,
+            status: status,
+            code: try!(get_string(&section, "code")),
+            payee: try!(get_string(&section, "payee")).unwrap_or(String::new()),
+            note: try!(get_string(&section, "note")),
+            postings: postings,
+        })
+    }
+
+}
+
+fn get_string(table: &BTreeMap<String, Value>, key: &str) -> Result<Option<String>> {
+    match table.get(key) {
+        Some(&Value::String(ref s)) => Ok(Some(s.clone())),
+        Some(_)                     => Err(LEK::HeaderTypeError.into_error()),
+        None                        => Ok(None),
+    }
+}
+
+fn amount_from_header(table: &BTreeMap<String, Value>, amount: &str, commodity: &str) -> Result<Option<Amount>> {
+    match (try!(get_string(table, amount)), try!(get_string(table, commodity))) {
+        (Some(a), Some(c)) => {
+            let a = try!(Amount::parse(&a));
+            Ok(Some(Amount::new(a.value(), a.scale(), c)))
+        },
+        (None, None) => Ok(None),
+        _            => Err(LEK::HeaderTypeError.into_error()),
+    }
+}
+
+fn posting_from_header(value: &Value) -> Result<Posting> {
+    match *value {
+        Value::Table(ref t) => {
+            let account = try!(get_string(t, "account").and_then(|a| a.ok_or(LEK::HeaderTypeError.into_error())));
+            let amount  = try!(amount_from_header(t, "amount", "commodity")
+                               .and_then(|a| a.ok_or(LEK::HeaderTypeError.into_error())));
+
+            Ok(Posting {
+                account: account,
+                amount: amount,
+                cost: try!(amount_from_header(t, "cost_amount", "cost_commodity")),
+            })
+        },
+        _ => Err(LEK::HeaderTypeError.into_error()),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::PathBuf;
+
+    use chrono::naive::date::NaiveDate;
+
+    use libimagstore::store::Store;
+    use libimagstore::file_abstraction::InMemoryBackend;
+
+    use amount::Amount;
+    use super::*;
+
+    fn transaction() -> Transaction {
+        let mut t  = Transaction::new(NaiveDate::from_ymd(2016, 10, 17), String::from("Grocery"));
+        t.status   = Status::Cleared;
+        t.note     = Some(String::from("weekly"));
+        t.postings = vec![
+            Posting::new(String::from("Expenses:Food"), Amount::parse("12.50 EUR").unwrap()),
+            Posting::new(String::from("Assets:Cash"), Amount::parse("-12.50 EUR").unwrap()),
+        ];
+        t
+    }
+
+    #[test]
+    fn test_validate() {
+        let mut t = transaction();
+        assert!(t.validate().is_ok());
+
+        t.postings[1].amount = Amount::parse("-12 EUR").unwrap();
+        assert!(t.validate().is_err());
+
+        t.postings[1].amount = Amount::parse("-$13.75").unwrap();
+        assert!(t.validate().is_err());
+        t.postings[0].cost = Some(Amount::parse("$13.75").unwrap());
+        assert!(t.validate().is_ok());
+
+        t.postings.clear();
+        assert!(t.validate().is_err());
+    }
+
+    #[test]
+    fn test_to_journal() {
+        let mut t = transaction();
+        t.postings[0].cost = Some(Amount::parse("-$1").unwrap());
+        assert_eq!(t.to_journal(), "2016-10-17 * Grocery  ; weekly\n    \
+                                    Expenses:Food  12.50 EUR @@ $1\n    \
+                                    Assets:Cash    -12.50 EUR\n");
+    }
+
+    #[test]
+    fn test_header_roundtrip() {
+        let store     = Store::new(PathBuf::from("/"), None, Box::new(InMemoryBackend::new())).unwrap();
+        let mut entry = store.create(PathBuf::from("ledger/test")).unwrap();
+        let mut t     = transaction();
+        t.postings[0].cost = Some(Amount::parse("$13.75").unwrap());
+
+        entry.get_header_mut().set("ledger", t.to_header()).unwrap();
+        assert_eq!(Transaction::from_entry(&entry).unwrap(), t);
+    }
+
+}
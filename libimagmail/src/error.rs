@@ -6,7 +6,14 @@ generate_error_module!(
 
         FetchByHashError => "Error fetching mail from Store by hash",
         FetchError       => "Error fetching mail from Store",
-        IOError => "IO Error"
+        IOError => "IO Error",
+
+        HeaderWriteError => "Error while writing the mail header section",
+        StoreReadError   => "Store read error",
+        StoreWriteError  => "Store write error",
+        LinkingError     => "Error while linking mails",
+        ImportError      => "Error while importing mails",
+        NotAMail         => "Entry is not a mail"
     );
 );
 
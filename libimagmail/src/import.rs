@@ -0,0 +1,225 @@
+//! Bulk import of Maildir folders and mbox files
+//!
+//! A Ref always points to one file, so mbox files are split into one file per mail before they
+//! are imported. Mails are deduplicated by the hash of the `MailHasher`, so importing the same
+//! mail twice (for example because it was moved from "new" to "cur" in a Maildir) does not create
+//! a second entry.
+//!
+
+use std::collections::HashSet;
+use std::fs::{File, create_dir_all};
+use std::io::{BufRead, BufReader, Read, Write};
+use std::path::{Path, PathBuf};
+
+use toml::Value;
+use walkdir::WalkDir;
+
+use libimagerror::trace::trace_error;
+use libimagref::hasher::Hasher;
+use libimagstore::store::Store;
+
+use error::{MapErrInto, MailErrorKind as MEK};
+use hasher::MailHasher;
+use mail::Mail;
+use result::Result;
+
+/// Check whether the file at `path` is a mbox file
+///
+/// A mbox file starts with a "From " line.
+pub fn is_mbox(path: &Path) -> bool {
+    File::open(path)
+        .ok()
+        .and_then(|f| BufReader::new(f).lines().next())
+        .and_then(|line| line.ok())
+        .map(|line| line.starts_with("From "))
+        .unwrap_or(false)
+}
+
+/// Find all mail files below `path`
+///
+/// * Files in the "cur" and "new" directories of a Maildir are mails
+/// * Files ending with ".eml" are mails
+/// * mbox files are split into `mbox_dir`, the split files are mails
+///
+/// If `path` is a file, it is either a mbox file or a single mail.
+pub fn find_mail_files(path: &Path, mbox_dir: &Path) -> Result<Vec<PathBuf>> {
+    if path.is_file() {
+        return if is_mbox(path) {
+            split_mbox(path, mbox_dir)
+        } else {
+            Ok(vec![PathBuf::from(path)])
+        };
+    }
+
+    let mut files = vec![];
+    for entry in WalkDir::new(path).follow_links(false).into_iter() {
+        let entry = try!(entry.map_err_into(MEK::IOError));
+        if !entry.file_type().is_file() {
+            continue;
+        }
+
+        let in_maildir = entry.path()
+            .parent()
+            .and_then(|p| p.file_name())
+            .map(|n| n == "cur" || n == "new")
+            .unwrap_or(false);
+        let is_eml = entry.path()
+            .extension()
+            .map(|e| e == "eml")
+            .unwrap_or(false);
+
+        if in_maildir || is_eml {
+            files.push(PathBuf::from(entry.path()));
+        } else if is_mbox(entry.path()) {
+            files.append(&mut try!(split_mbox(entry.path(), mbox_dir)));
+        } else {
+            debug!("Not a mail, skipping: {:?}", entry.path());
+        }
+    }
+
+    Ok(files)
+}
+
+/// Split the mbox file `mbox` into one file per mail
+///
+/// The files are written to a directory named like the mbox file inside `target`. Splitting the
+/// same mbox twice results in the same files, so re-importing a mbox does not create duplicates.
+///
+/// "From " lines in the mail bodies, which are escaped as ">From " in the mbox, are unescaped.
+pub fn split_mbox(mbox: &Path, target: &Path) -> Result<Vec<PathBuf>> {
+    let mut content = vec![];
+    try!(File::open(mbox)
+         .and_then(|mut f| f.read_to_end(&mut content))
+         .map_err_into(MEK::IOError));
+    let content = String::from_utf8_lossy(&content);
+
+    let mut dir = PathBuf::from(target);
+    dir.push(mbox.file_name().unwrap_or(mbox.as_os_str()));
+    try!(create_dir_all(&dir).map_err_into(MEK::IOError));
+
+    let mut files = vec![];
+    for (n, mail) in split_mbox_content(&content).into_iter().enumerate() {
+        let mut path = dir.clone();
+        path.push(format!("{:06}.eml", n));
+
+        try!(File::create(&path)
+             .and_then(|mut f| f.write_all(mail.as_bytes()))
+             .map_err_into(MEK::IOError));
+        files.push(path);
+    }
+
+    debug!("Split {:?} into {} mails", mbox, files.len());
+    Ok(files)
+}
+
+fn split_mbox_content(content: &str) -> Vec<String> {
+    let mut mails   = vec![];
+    let mut current : Option<String> = None;
+
+    for line in content.lines() {
+        if line.starts_with("From ") {
+            if let Some(mail) = current.take() {
+                mails.push(mail);
+            }
+            current = Some(String::new());
+            continue;
+        }
+
+        if let Some(ref mut mail) = current {
+            let unescaped = line.trim_left_matches('>');
+            if line.starts_with('>') && unescaped.starts_with("From ") {
+                mail.push_str(&line[1..]);
+            } else {
+                mail.push_str(line);
+            }
+            mail.push('\n');
+        }
+    }
+
+    if let Some(mail) = current {
+        mails.push(mail);
+    }
+
+    mails
+}
+
+/// Imports mails, skipping the ones which are already in the store
+pub struct Importer<'a> {
+    store: &'a Store,
+    known_hashes: HashSet<String>,
+}
+
+impl<'a> Importer<'a> {
+
+    /// Create a new Importer, reading the hashes of all mails which are already in the store
+    pub fn new(store: &'a Store) -> Result<Importer<'a>> {
+        let field = format!("ref.content_hash.{}", MailHasher::new().hash_name());
+        let ids   = try!(store.retrieve_for_module("ref").map_err_into(MEK::StoreReadError));
+
+        let mut known_hashes = HashSet::new();
+        for id in ids {
+            match store.retrieve_copy(id) {
+                Ok(entry) => if let Ok(Some(Value::String(hash))) = entry.get_header().read(&field) {
+                    known_hashes.insert(hash);
+                },
+                Err(e) => trace_error(&e),
+            }
+        }
+
+        debug!("{} mails already in the store", known_hashes.len());
+        Ok(Importer {
+            store: store,
+            known_hashes: known_hashes,
+        })
+    }
+
+    /// Import the mail at `path`
+    ///
+    /// Returns `Ok(None)` if the mail is already in the store.
+    pub fn import<P: AsRef<Path>>(&mut self, path: P) -> Result<Option<Mail<'a>>> {
+        let path = PathBuf::from(path.as_ref());
+        let hash = try!(File::open(&path)
+                        .map_err_into(MEK::IOError)
+                        .and_then(|mut f| {
+                            MailHasher::new()
+                                .create_hash(&path, &mut f)
+                                .map_err_into(MEK::RefHandlingError)
+                        }));
+
+        if self.known_hashes.contains(&hash) {
+            debug!("Mail {:?} is already in the store", path);
+            return Ok(None);
+        }
+
+        Mail::import_from_path(self.store, &path)
+            .map_err_into(MEK::ImportError)
+            .map(|mail| {
+                self.known_hashes.insert(hash);
+                Some(mail)
+            })
+    }
+
+}
+
+#[cfg(test)]
+mod test {
+    use super::split_mbox_content;
+
+    #[test]
+    fn test_split_mbox_content() {
+        let mbox = "From a@example.com Mon Jan  1 00:00:00 2016\n\
+                    Subject: one\n\
+                    \n\
+                    >From here on\n\
+                    From b@example.com Mon Jan  1 00:00:00 2016\n\
+                    Subject: two\n\
+                    \n\
+                    body\n";
+
+        let mails = split_mbox_content(mbox);
+        assert_eq!(mails.len(), 2);
+        assert_eq!(mails[0], "Subject: one\n\nFrom here on\n");
+        assert_eq!(mails[1], "Subject: two\n\nbody\n");
+    }
+
+}
@@ -3,14 +3,18 @@ extern crate mailparse;
 extern crate semver;
 extern crate toml;
 extern crate filters;
+extern crate walkdir;
 
 #[macro_use] extern crate libimagerror;
 extern crate libimagstore;
 extern crate libimagref;
+extern crate libimagentrylink;
 
 pub mod error;
 pub mod hasher;
+pub mod import;
 pub mod iter;
 pub mod mail;
 pub mod result;
+pub mod thread;
 
@@ -1,12 +1,17 @@
+use std::collections::BTreeMap;
 use std::result::Result as RResult;
 use std::path::Path;
 use std::path::PathBuf;
 use std::fs::File;
 use std::io::Read;
 
-use libimagstore::store::{FileLockEntry, Store};
+use toml::Value;
+
+use libimagstore::schema::{FieldType, Schema};
+use libimagstore::store::{Entry, Store};
 use libimagref::reference::Ref;
 use libimagref::flags::RefFlags;
+use libimagref::hasher::Hasher;
 
 use mailparse::{MailParseError, ParsedMail, parse_mail};
 
@@ -32,7 +37,27 @@ pub struct Mail<'a>(Ref<'a>, Buffer);
 
 impl<'a> Mail<'a> {
 
+    /// The schema of the "mail" header section, to be registered with `Store::register_schema()`
+    pub fn header_schema() -> Schema {
+        Schema::new("mail")
+            .optional("message_id", FieldType::String)
+            .optional("in_reply_to", FieldType::String)
+            .optional("references", FieldType::Array)
+            .optional("from", FieldType::String)
+            .optional("to", FieldType::String)
+            .optional("subject", FieldType::String)
+    }
+
+    /// Check whether `entry` is a mail, as in "a Ref which was hashed with the `MailHasher`"
+    pub fn is_mail(entry: &Entry) -> bool {
+        let field = format!("ref.content_hash.{}", MailHasher::new().hash_name());
+        entry.get_header().read(&field).map(|v| v.is_some()).unwrap_or(false)
+    }
+
     /// Imports a mail from the Path passed
+    ///
+    /// The headers which are needed for listing and threading are copied to the "mail" section of
+    /// the entry header, so they can be read without parsing the mail file.
     pub fn import_from_path<P: AsRef<Path>>(store: &Store, p: P) -> Result<Mail> {
         let h = MailHasher::new();
         let f = RefFlags::default().with_content_hashing(true).with_permission_tracking(false);
@@ -53,6 +78,54 @@ impl<'a> Mail<'a> {
                     .map(Buffer::from)
                     .map(|buffer| Mail(reference, buffer))
             })
+            .and_then(|mut mail| mail.cache_header_fields().map(|_| mail))
+    }
+
+    /// Copy the headers of the mail file to the "mail" section of the entry header
+    pub fn cache_header_fields(&mut self) -> Result<()> {
+        let mut section = BTreeMap::new();
+
+        {
+            let mut insert = |key: &str, value: Option<String>| if let Some(v) = value {
+                section.insert(String::from(key), Value::String(v));
+            };
+
+            insert("message_id", try!(self.get_message_id()).map(|s| normalize_message_id(&s)));
+            insert("in_reply_to", try!(self.get_in_reply_to())
+                   .and_then(|s| parse_message_ids(&s).into_iter().next()));
+            insert("from", try!(self.get_from()));
+            insert("to", try!(self.get_to()));
+            insert("subject", try!(self.get_subject()));
+        }
+
+        let references = try!(self.get_references());
+        if !references.is_empty() {
+            let references = references.into_iter().map(Value::String).collect();
+            section.insert(String::from("references"), Value::Array(references));
+        }
+
+        self.0
+            .get_header_mut()
+            .set("mail", Value::Table(section))
+            .map(|_| ())
+            .map_err_into(MEK::HeaderWriteError)
+    }
+
+    /// Get the hash of the mail, as computed by the `MailHasher`
+    pub fn get_hash(&self) -> Result<String> {
+        self.0
+            .get_stored_hash_with_hasher(&MailHasher::new())
+            .map_err_into(MEK::RefHandlingError)
+    }
+
+    /// Get the `Ref` object of this mail
+    pub fn get_ref(&self) -> &Ref<'a> {
+        &self.0
+    }
+
+    /// Turn the mail into its `Ref` object
+    pub fn into_ref(self) -> Ref<'a> {
+        self.0
     }
 
     /// Opens a mail by the passed hash
@@ -91,7 +164,11 @@ impl<'a> Mail<'a> {
             .map(|parsed| {
                 parsed.headers
                     .iter()
-                    .filter(|hdr| hdr.get_key().map(|n| n == field).unwrap_or(false))
+                    .filter(|hdr| {
+                        hdr.get_key()
+                            .map(|n| n.to_lowercase() == field.to_lowercase())
+                            .unwrap_or(false)
+                    })
                     .next()
                     .and_then(|field| field.get_value().ok())
             })
@@ -117,4 +194,65 @@ impl<'a> Mail<'a> {
         self.get_field("In-Reply-To")
     }
 
+    /// Get the message ids from the "References" header, oldest first
+    pub fn get_references(&self) -> Result<Vec<String>> {
+        self.get_field("References")
+            .map(|refs| refs.map(|s| parse_message_ids(&s)).unwrap_or_else(Vec::new))
+    }
+
+}
+
+/// Extract all message ids (`<...>`) from a header value
+///
+/// If the value contains no angle brackets, it is split at whitespace instead.
+pub fn parse_message_ids(s: &str) -> Vec<String> {
+    let mut ids = vec![];
+    let mut rest = s;
+
+    while let Some(start) = rest.find('<') {
+        match rest[start..].find('>') {
+            Some(len) => {
+                ids.push(String::from(&rest[start..(start + len + 1)]));
+                rest = &rest[(start + len + 1)..];
+            },
+            None => break,
+        }
+    }
+
+    if ids.is_empty() {
+        ids = s.split_whitespace().map(normalize_message_id).collect();
+    }
+
+    ids
+}
+
+/// Bring a message id into the `<...>` form
+pub fn normalize_message_id(s: &str) -> String {
+    let s = s.trim();
+    if s.starts_with('<') && s.ends_with('>') {
+        String::from(s)
+    } else {
+        format!("<{}>", s.trim_matches(|c| c == '<' || c == '>'))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{normalize_message_id, parse_message_ids};
+
+    #[test]
+    fn test_parse_message_ids() {
+        assert_eq!(parse_message_ids("<a@example.com> <b@example.com>"),
+                   vec!["<a@example.com>", "<b@example.com>"]);
+        assert_eq!(parse_message_ids("  <a@example.com>\n\t<b@x>"), vec!["<a@example.com>", "<b@x>"]);
+        assert_eq!(parse_message_ids("a@example.com"), vec!["<a@example.com>"]);
+        assert!(parse_message_ids("").is_empty());
+    }
+
+    #[test]
+    fn test_normalize_message_id() {
+        assert_eq!(normalize_message_id(" <a@b> "), "<a@b>");
+        assert_eq!(normalize_message_id("a@b"), "<a@b>");
+    }
+
 }
@@ -0,0 +1,351 @@
+//! Conversation threads
+//!
+//! Mails are threaded by their "Message-ID", "In-Reply-To" and "References" headers, which are
+//! cached in the "mail" section of the entry header on import. Mails which belong to the same
+//! thread are connected with internal links by `link_threads()`. The thread of a mail is the set
+//! of mails which can be reached from it via replies, so other links of a mail (for example to a
+//! contact or a note) do not pull unrelated mails into the thread.
+//!
+
+use std::collections::{BTreeSet, HashMap, VecDeque};
+use std::path::PathBuf;
+
+use toml::Value;
+
+use libimagentrylink::internal::{InternalLinker, link_entries};
+use libimagerror::into::IntoError;
+use libimagerror::trace::trace_error;
+use libimagstore::store::{Entry, FileLockEntry, Store};
+use libimagstore::storeid::StoreId;
+
+use error::{MapErrInto, MailErrorKind as MEK};
+use mail::Mail;
+use result::Result;
+
+/// The header data of a mail, as cached in the "mail" header section
+#[derive(Debug, Clone)]
+pub struct MailInfo {
+    id: StoreId,
+    message_id: Option<String>,
+    in_reply_to: Option<String>,
+    references: Vec<String>,
+    from: Option<String>,
+    subject: Option<String>,
+    links: Vec<PathBuf>,
+}
+
+impl MailInfo {
+
+    /// Read the mail information from an entry
+    ///
+    /// Returns `None` if the entry is not a mail.
+    pub fn from_entry(entry: &Entry) -> Option<MailInfo> {
+        if !Mail::is_mail(entry) {
+            return None;
+        }
+
+        let read_string = |field: &str| match entry.get_header().read(field) {
+            Ok(Some(Value::String(s))) => Some(s),
+            _ => None,
+        };
+
+        let references = match entry.get_header().read("mail.references") {
+            Ok(Some(Value::Array(a))) => a.into_iter()
+                .filter_map(|v| match v {
+                    Value::String(s) => Some(s),
+                    _ => None,
+                })
+                .collect(),
+            _ => vec![],
+        };
+
+        let links = entry.get_internal_links()
+            .map(|links| links.map(|l| l.local().clone()).collect())
+            .unwrap_or_else(|_| vec![]);
+
+        Some(MailInfo {
+            id: entry.get_location().clone().without_base(),
+            message_id: read_string("mail.message_id"),
+            in_reply_to: read_string("mail.in_reply_to"),
+            references: references,
+            from: read_string("mail.from"),
+            subject: read_string("mail.subject"),
+            links: links,
+        })
+    }
+
+    pub fn id(&self) -> &StoreId {
+        &self.id
+    }
+
+    pub fn message_id(&self) -> Option<&String> {
+        self.message_id.as_ref()
+    }
+
+    pub fn from(&self) -> Option<&String> {
+        self.from.as_ref()
+    }
+
+    pub fn subject(&self) -> Option<&String> {
+        self.subject.as_ref()
+    }
+
+    /// The message ids this mail replies to, the direct parent first
+    pub fn parents(&self) -> Vec<&String> {
+        let mut parents : Vec<&String> = self.in_reply_to.iter().collect();
+        parents.extend(self.references.iter().rev().filter(|r| Some(*r) != self.in_reply_to.as_ref()));
+        parents
+    }
+
+    fn is_linked_to(&self, id: &StoreId) -> bool {
+        self.links.iter().any(|l| l == id.local())
+    }
+
+}
+
+/// Read the information of all mails in the store
+pub fn all_mails(store: &Store) -> Result<Vec<MailInfo>> {
+    let ids = try!(store.retrieve_for_module("ref").map_err_into(MEK::StoreReadError));
+
+    Ok(ids
+       .filter_map(|id| match store.retrieve_copy(id) {
+           Ok(entry) => MailInfo::from_entry(&entry),
+           Err(e) => {
+               trace_error(&e);
+               None
+           },
+       })
+       .collect())
+}
+
+/// Link all mails in the store to the mails they reply to
+///
+/// Mails which are already linked are skipped. Returns the number of new links.
+pub fn link_threads(store: &Store) -> Result<usize> {
+    let mails = try!(all_mails(store));
+    let by_message_id = mails
+        .iter()
+        .filter_map(|m| m.message_id().map(|mid| (mid.clone(), m.id().clone())))
+        .collect::<HashMap<String, StoreId>>();
+
+    let mut count = 0;
+    for mail in mails.iter() {
+        for parent in mail.parents() {
+            let parent_id = match by_message_id.get(parent) {
+                Some(id) if id != mail.id() && !mail.is_linked_to(id) => id,
+                _ => continue,
+            };
+
+            try!(link_mails(store, mail.id(), parent_id));
+            count += 1;
+        }
+    }
+
+    Ok(count)
+}
+
+fn link_mails(store: &Store, a: &StoreId, b: &StoreId) -> Result<()> {
+    debug!("Linking mails {} <-> {}", a, b);
+
+    let entry_a = try!(get_entry(store, a));
+    let entry_b = try!(get_entry(store, b));
+    link_entries(store, entry_a, vec![entry_b]).map_err_into(MEK::LinkingError)
+}
+
+fn get_entry<'a>(store: &'a Store, id: &StoreId) -> Result<FileLockEntry<'a>> {
+    match store.get(id.clone()) {
+        Ok(Some(fle)) => Ok(fle),
+        Ok(None)      => Err(MEK::FetchError.into_error()),
+        Err(e)        => Err(MEK::StoreReadError.into_error_with_cause(Box::new(e))),
+    }
+}
+
+/// Get the thread `id` belongs to
+///
+/// Returns the mails of the thread in thread order, each with its depth in the reply tree. Mails
+/// whose parent is not part of the thread are at depth 0.
+pub fn get_thread(store: &Store, id: &StoreId) -> Result<Vec<(usize, MailInfo)>> {
+    let mails = try!(all_mails(store))
+        .into_iter()
+        .map(|m| (m.id().local().clone(), m))
+        .collect::<HashMap<PathBuf, MailInfo>>();
+
+    let start = id.local().clone();
+    if !mails.contains_key(&start) {
+        return Err(MEK::NotAMail.into_error());
+    }
+
+    Ok(thread_of(&mails, start))
+}
+
+fn thread_of(mails: &HashMap<PathBuf, MailInfo>, start: PathBuf) -> Vec<(usize, MailInfo)> {
+    let by_message_id = mails
+        .values()
+        .filter_map(|m| m.message_id().map(|mid| (mid.clone(), m.id().local().clone())))
+        .collect::<HashMap<String, PathBuf>>();
+
+    // The parent of each mail, and the mails replying to each mail
+    let mut replies : HashMap<PathBuf, Vec<PathBuf>> = HashMap::new();
+    for (id, mail) in mails.iter() {
+        for parent in mail.parents().into_iter().filter_map(|p| by_message_id.get(p)) {
+            if parent != id {
+                replies.entry(id.clone()).or_insert_with(Vec::new).push(parent.clone());
+                replies.entry(parent.clone()).or_insert_with(Vec::new).push(id.clone());
+            }
+        }
+    }
+
+    // All mails reachable via replies
+    let mut members = BTreeSet::new();
+    let mut queue   = VecDeque::new();
+    queue.push_back(start.clone());
+    while let Some(current) = queue.pop_front() {
+        if !members.insert(current.clone()) {
+            continue;
+        }
+        if let Some(related) = replies.get(&current) {
+            queue.extend(related.iter().cloned());
+        }
+    }
+
+    let mut parent_of = HashMap::new();
+    let mut children : HashMap<PathBuf, Vec<PathBuf>> = HashMap::new();
+    let mut roots = vec![];
+    for id in members.iter() {
+        let parent = mails[id]
+            .parents()
+            .into_iter()
+            .filter_map(|p| by_message_id.get(p))
+            .find(|p| *p != id && members.contains(*p))
+            .cloned();
+
+        match parent {
+            Some(p) => {
+                children.entry(p.clone()).or_insert_with(Vec::new).push(id.clone());
+                parent_of.insert(id.clone(), p);
+            },
+            None    => roots.push(id.clone()),
+        }
+    }
+
+    // Mails in a reply cycle (and the replies to them) cannot be reached from a root. They are
+    // printed after the rest of the thread, starting with the cycle the requested mail belongs to.
+    let in_cycle = |id: &PathBuf| {
+        let mut seen    = BTreeSet::new();
+        let mut current = id.clone();
+        while seen.insert(current.clone()) {
+            match parent_of.get(&current) {
+                Some(p) => current = p.clone(),
+                None    => break,
+            }
+        }
+        current
+    };
+    let unrooted = Some(start)
+        .into_iter()
+        .chain(members.iter().cloned())
+        .collect::<Vec<_>>();
+
+    let mut thread  = vec![];
+    let mut visited = BTreeSet::new();
+    let starts = roots
+        .into_iter()
+        .map(|r| (r, false))
+        .chain(unrooted.into_iter().map(|u| (u, true)))
+        .collect::<Vec<_>>();
+
+    for (first, is_unrooted) in starts {
+        if visited.contains(&first) {
+            continue;
+        }
+
+        let first = if is_unrooted { in_cycle(&first) } else { first };
+        let mut stack = vec![(0, first)];
+        while let Some((depth, current)) = stack.pop() {
+            if !visited.insert(current.clone()) {
+                continue;
+            }
+            if let Some(cs) = children.get(&current) {
+                for c in cs.iter().rev() {
+                    stack.push((depth + 1, c.clone()));
+                }
+            }
+            thread.push((depth, mails[&current].clone()));
+        }
+    }
+
+    thread
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+    use std::path::PathBuf;
+
+    use libimagstore::storeid::StoreId;
+
+    use super::{MailInfo, thread_of};
+
+    fn mail(id: &str, in_reply_to: Option<&str>, links: Vec<&str>) -> (PathBuf, MailInfo) {
+        let info = MailInfo {
+            id: StoreId::new_baseless(PathBuf::from(id)).unwrap(),
+            message_id: Some(format!("<{}>", id)),
+            in_reply_to: in_reply_to.map(|r| format!("<{}>", r)),
+            references: vec![],
+            from: None,
+            subject: None,
+            links: links.into_iter().map(PathBuf::from).collect(),
+        };
+        (PathBuf::from(id), info)
+    }
+
+    fn thread(mails: Vec<(PathBuf, MailInfo)>, start: &str) -> Vec<(usize, String)> {
+        thread_of(&mails.into_iter().collect::<HashMap<_, _>>(), PathBuf::from(start))
+            .into_iter()
+            .map(|(depth, m)| (depth, format!("{}", m.id())))
+            .collect()
+    }
+
+    #[test]
+    fn test_thread_follows_replies_only() {
+        let mails = vec![
+            mail("ref/a", None, vec!["ref/x"]),
+            mail("ref/b", Some("ref/a"), vec!["ref/a"]),
+            mail("ref/c", Some("ref/b"), vec!["ref/b"]),
+            mail("ref/x", None, vec!["ref/a"]),
+        ];
+
+        assert_eq!(thread(mails, "ref/c"), vec![
+            (0, String::from("ref/a")),
+            (1, String::from("ref/b")),
+            (2, String::from("ref/c")),
+        ]);
+    }
+
+    #[test]
+    fn test_thread_with_cycle_contains_all_mails() {
+        let mails = vec![
+            mail("ref/r", None, vec![]),
+            mail("ref/s", Some("ref/r"), vec![]),
+            mail("ref/a", Some("ref/b"), vec![]),
+            mail("ref/b", Some("ref/a"), vec![]),
+            mail("ref/c", Some("ref/a"), vec![]),
+            mail("ref/d", Some("ref/s"), vec![]),
+        ];
+
+        let t = thread(mails.clone(), "ref/s");
+        assert_eq!(t, vec![
+            (0, String::from("ref/r")),
+            (1, String::from("ref/s")),
+            (2, String::from("ref/d")),
+        ]);
+
+        let t = thread(mails, "ref/c");
+        assert_eq!(t, vec![
+            (0, String::from("ref/a")),
+            (1, String::from("ref/b")),
+            (1, String::from("ref/c")),
+        ]);
+    }
+
+}
@@ -33,6 +33,7 @@ use libimagstore::store::FileLockEntry;
 use libimagstore::storeid::StoreId;
 use libimagstore::storeid::IntoStoreId;
 use libimagstore::store::Store;
+use libimagstore::schema::{FieldType, Schema};
 use libimagerror::into::IntoError;
 use libimagerror::trace::MapErrTrace;
 
@@ -50,6 +51,14 @@ pub struct Ref<'a>(FileLockEntry<'a>);
 
 impl<'a> Ref<'a> {
 
+    /// The schema of the "ref" header section, to be registered with `Store::register_schema()`
+    pub fn header_schema() -> Schema {
+        Schema::new("ref")
+            .required("path", FieldType::String)
+            .optional("content_hash", FieldType::Table)
+            .optional("permissions", FieldType::Table)
+    }
+
     /// Try to build a Ref object based on an existing FileLockEntry object
     pub fn from_filelockentry(fle: FileLockEntry<'a>) -> Result<Ref<'a>> {
         Ref::read_reference(&fle).map(|_| Ref(fle))
@@ -76,6 +85,17 @@ impl<'a> Ref<'a> {
             .map_err(|e| REK::StoreReadError.into_error_with_cause(e))
     }
 
+    /// Get the Ref to the file at `pb` from the store.
+    ///
+    /// Returns None if there is no Ref to this file.
+    pub fn get_by_path(store: &'a Store, pb: PathBuf) -> Result<Option<Ref<'a>>> {
+        pb.canonicalize()
+            .map_err(Box::new)
+            .map_err(|e| REK::PathCanonicalizationError.into_error_with_cause(e))
+            .and_then(|can| Ref::hash_path(&can))
+            .and_then(|hash| Ref::get_by_hash(store, hash))
+    }
+
     /// Delete a ref by hash
     ///
     /// If the returned Result contains an error, the ref might not be deleted.
@@ -45,6 +45,7 @@ extern crate toml;
 
 extern crate libimagstore;
 extern crate libimagstorestdhook;
+#[cfg(feature = "search")] extern crate libimagsearch;
 extern crate libimagutil;
 #[macro_use] extern crate libimagerror;
 
@@ -61,8 +61,11 @@ impl<'a> Runtime<'a> {
         use libimagstore::hook::position::HookPosition as HP;
         use libimagstore::hook::Hook;
         use libimagstore::error::StoreErrorKind;
+        use libimagstore::file_abstraction::FileSystemBackend;
         use libimagstorestdhook::debug::DebugHook;
+        use libimagstorestdhook::index::IndexHook;
         use libimagstorestdhook::vcs::git::delete::DeleteHook as GitDeleteHook;
+        use libimagstorestdhook::vcs::git::move_entry::MoveHook as GitMoveHook;
         use libimagstorestdhook::vcs::git::update::UpdateHook as GitUpdateHook;
         use libimagstorestdhook::vcs::git::store_unload::StoreUnloadHook as GitStoreUnloadHook;
         use libimagerror::trace::trace_error;
@@ -129,7 +132,9 @@ impl<'a> Runtime<'a> {
             write!(stderr(), "Store-config: {:?}\n", store_config).ok();
         }
 
-        Store::new(storepath.clone(), store_config).map(|mut store| {
+        let backend = Box::new(FileSystemBackend::new());
+
+        Store::new(storepath.clone(), store_config, backend).map(|mut store| {
             // If we are debugging, generate hooks for all positions
             if is_debugging {
                 let hooks : Vec<(Box<Hook>, &str, HP)> = vec![
@@ -141,6 +146,8 @@ impl<'a> Runtime<'a> {
                     (Box::new(DebugHook::new(HP::PostUpdate))         , "debug", HP::PostUpdate),
                     (Box::new(DebugHook::new(HP::PreDelete))          , "debug", HP::PreDelete),
                     (Box::new(DebugHook::new(HP::PostDelete))         , "debug", HP::PostDelete),
+                    (Box::new(DebugHook::new(HP::PreMove))            , "debug", HP::PreMove),
+                    (Box::new(DebugHook::new(HP::PostMove))           , "debug", HP::PostMove),
                 ];
 
                 // If hook registration fails, trace the error and warn, but continue.
@@ -160,6 +167,7 @@ impl<'a> Runtime<'a> {
 
             let hooks : Vec<(Box<Hook>, &str, HP)> = vec![
                 (Box::new(GitDeleteHook::new(sp.clone(), HP::PostDelete)), "vcs", HP::PostDelete),
+                (Box::new(GitMoveHook::new(sp.clone(), HP::PostMove)),     "vcs", HP::PostMove),
                 (Box::new(GitUpdateHook::new(sp.clone(), HP::PostUpdate)), "vcs", HP::PostUpdate),
                 (Box::new(GitStoreUnloadHook::new(sp)),                    "vcs", HP::StoreUnload),
             ];
@@ -175,6 +183,29 @@ impl<'a> Runtime<'a> {
                 }
             }
 
+            if let Some(index) = store.index() {
+                let hooks : Vec<(Box<Hook>, &str, HP)> = vec![
+                    (Box::new(IndexHook::new(HP::PostCreate, index.clone())), "index", HP::PostCreate),
+                    (Box::new(IndexHook::new(HP::PostUpdate, index.clone())), "index", HP::PostUpdate),
+                    (Box::new(IndexHook::new(HP::PostDelete, index.clone())), "index", HP::PostDelete),
+                    (Box::new(IndexHook::new(HP::PostMove, index)),           "index", HP::PostMove),
+                ];
+
+                for (hook, aspectname, position) in hooks {
+                    if let Err(e) = store.register_hook(position, &String::from(aspectname), hook) {
+                        if e.err_type() == StoreErrorKind::HookRegisterError {
+                            trace_error_dbg(&e);
+                            warn!("Registering index hook with store failed");
+                        } else {
+                            trace_error(&e);
+                        };
+                    }
+                }
+            }
+
+            #[cfg(feature = "search")]
+            ::libimagsearch::hook::register_search_hooks(&mut store);
+
             Runtime {
                 cli_matches: matches,
                 configuration: cfg,
@@ -374,6 +405,13 @@ impl<'a> Runtime<'a> {
         &self.store
     }
 
+    /**
+     * Get the store object mutably, for registering the hooks of a module
+     */
+    pub fn store_mut(&mut self) -> &mut Store {
+        &mut self.store
+    }
+
     pub fn editor(&self) -> Option<Command> {
         self.cli()
             .value_of("editor")
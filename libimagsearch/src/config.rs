@@ -0,0 +1,34 @@
+//
+// imag - the personal information management suite for the commandline
+// Copyright (C) 2015, 2016 Matthias Beyer <mail@beyermatthias.de> and contributors
+//
+// This library is free software; you can redistribute it and/or
+// modify it under the terms of the GNU Lesser General Public
+// License as published by the Free Software Foundation; version
+// 2.1 of the License.
+//
+// This library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+// Lesser General Public License for more details.
+//
+// You should have received a copy of the GNU Lesser General Public
+// License along with this library; if not, write to the Free Software
+// Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA  02110-1301  USA
+//
+
+use toml::Value;
+
+/// Checks whether the store configuration has a table "search" with a key "enabled" which maps
+/// to `true`. If not, the search index is not kept up to date by imag.
+pub fn config_search_enabled(config: Option<&Value>) -> bool {
+    match config.and_then(|c| c.lookup("search.enabled")) {
+        Some(&Value::Boolean(b)) => b,
+        Some(_) => {
+            warn!("Key 'search.enabled' does not contain a Boolean value");
+            false
+        },
+        None => false,
+    }
+}
+
@@ -0,0 +1,83 @@
+//
+// imag - the personal information management suite for the commandline
+// Copyright (C) 2015, 2016 Matthias Beyer <mail@beyermatthias.de> and contributors
+//
+// This library is free software; you can redistribute it and/or
+// modify it under the terms of the GNU Lesser General Public
+// License as published by the Free Software Foundation; version
+// 2.1 of the License.
+//
+// This library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+// Lesser General Public License for more details.
+//
+// You should have received a copy of the GNU Lesser General Public
+// License along with this library; if not, write to the Free Software
+// Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA  02110-1301  USA
+//
+
+//! Registration of the hooks which keep the search index up to date
+//!
+//! The runtime does not know about the search index. Binaries which want the index to follow
+//! their changes call `register_search_hooks()` on their store (libimagrt does so if it is built
+//! with the "search" feature).
+
+use std::sync::{Arc, Mutex};
+
+use libimagerror::trace::{trace_error, trace_error_dbg};
+use libimagstore::error::StoreErrorKind;
+use libimagstore::hook::Hook;
+use libimagstore::hook::position::HookPosition as HP;
+use libimagstore::index::StoreIndex;
+use libimagstore::store::Store;
+use libimagstorestdhook::index::IndexHook;
+
+use config::config_search_enabled;
+use index::SearchIndex;
+
+/// Load the search index and register the index hooks for it in the "search" aspect
+///
+/// Does nothing if the search index is not enabled in the store configuration. Errors are traced
+/// and not returned, a store without an up to date search index is still usable.
+pub fn register_search_hooks(store: &mut Store) {
+    if !config_search_enabled(store.config()) {
+        return;
+    }
+
+    let location = SearchIndex::default_location(store.path());
+    debug!("Loading search index from {:?}", location);
+
+    let search_index = match SearchIndex::load(store.backend(), location) {
+        Ok(search_index) => search_index,
+        Err(e) => {
+            warn!("Cannot load search index, it is not kept up to date");
+            trace_error(&e);
+            return;
+        },
+    };
+
+    let search_index : Arc<Mutex<StoreIndex>> = Arc::new(Mutex::new(search_index));
+    let hooks : Vec<(Box<Hook>, &str, HP)> = vec![
+        (Box::new(IndexHook::new(HP::PostCreate, search_index.clone())), "search", HP::PostCreate),
+        (Box::new(IndexHook::new(HP::PostUpdate, search_index.clone())), "search", HP::PostUpdate),
+        (Box::new(IndexHook::new(HP::PostDelete, search_index.clone())), "search", HP::PostDelete),
+        (Box::new(IndexHook::new(HP::PostMove, search_index.clone())),   "search", HP::PostMove),
+    ];
+
+    for (hook, aspectname, position) in hooks {
+        if let Err(e) = store.register_hook(position, &String::from(aspectname), hook) {
+            if e.err_type() == StoreErrorKind::HookRegisterError {
+                trace_error_dbg(&e);
+                warn!("Registering search hook with store failed");
+            } else {
+                trace_error(&e);
+            };
+        }
+    }
+
+    if let Err(e) = store.register_index(search_index) {
+        warn!("Registering search index with store failed");
+        trace_error(&e);
+    }
+}
@@ -0,0 +1,567 @@
+//
+// imag - the personal information management suite for the commandline
+// Copyright (C) 2015, 2016 Matthias Beyer <mail@beyermatthias.de> and contributors
+//
+// This library is free software; you can redistribute it and/or
+// modify it under the terms of the GNU Lesser General Public
+// License as published by the Free Software Foundation; version
+// 2.1 of the License.
+//
+// This library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+// Lesser General Public License for more details.
+//
+// You should have received a copy of the GNU Lesser General Public
+// License along with this library; if not, write to the Free Software
+// Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA  02110-1301  USA
+//
+
+//! The inverted index
+//!
+//! For each term, the index knows which entries contain the term and at which positions. The
+//! positions are needed to answer phrase queries.
+//!
+//! The index is persisted as plain text: one line per entry, holding the local part of the
+//! `StoreId` and the token stream of the entry, separated by a tab. The inverted lists are built
+//! when the index is loaded.
+//!
+//! The index is read and written through the `StorageBackend` of the store. Like the store index,
+//! it only remembers what changed and applies these changes to the index on disk while holding
+//! its lock file when it is saved, so several imag processes can update it at the same time.
+//!
+
+use std::cmp::{Ordering, max};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::ffi::OsString;
+use std::path::PathBuf;
+
+use libimagerror::into::IntoError;
+use libimagerror::trace::trace_error;
+use libimagstore::error::StoreError;
+use libimagstore::error::StoreErrorKind;
+use libimagstore::file_abstraction::StorageBackend;
+use libimagstore::index::{StoreIndex, lock_index, lock_path};
+use libimagstore::store::{Entry, Store};
+use libimagstore::store::Result as StoreResult;
+use libimagstore::storeid::StoreId;
+
+use error::SearchError;
+use error::SearchErrorKind as SEK;
+use error::MapErrInto;
+use query::Query;
+use result::Result;
+use tokenizer::{FIELD_SEPARATOR, tokenize_entry};
+
+#[derive(Debug, Clone)]
+pub struct SearchResult {
+    id: StoreId,
+    score: f64,
+}
+
+impl SearchResult {
+
+    /// The id of the matching entry, without the store base
+    pub fn id(&self) -> &StoreId {
+        &self.id
+    }
+
+    /// The relevance of the hit. Higher is better.
+    pub fn score(&self) -> f64 {
+        self.score
+    }
+
+}
+
+#[derive(Debug)]
+pub struct SearchIndex {
+    location: PathBuf,
+
+    /// local id -> token stream
+    documents: BTreeMap<String, Vec<String>>,
+
+    /// term -> local id -> positions
+    postings: HashMap<String, BTreeMap<String, Vec<usize>>>,
+
+    /// The changes since the index was loaded or saved: the new token stream of an entry, or
+    /// `None` if the entry was removed
+    changes: BTreeMap<String, Option<Vec<String>>>,
+
+    /// Whether the index was cleared since it was loaded or saved
+    cleared: bool,
+}
+
+impl SearchIndex {
+
+    /// Create a new, empty index which will be persisted at `location`
+    pub fn new(location: PathBuf) -> SearchIndex {
+        SearchIndex {
+            location: location,
+            documents: BTreeMap::new(),
+            postings: HashMap::new(),
+            changes: BTreeMap::new(),
+            cleared: false,
+        }
+    }
+
+    /// The default location of the search index for the store at `store_path`
+    ///
+    /// The index lives next to the store directory, so it does not show up as an entry.
+    pub fn default_location(store_path: &PathBuf) -> PathBuf {
+        let mut name = store_path
+            .file_name()
+            .map(|n| n.to_os_string())
+            .unwrap_or(OsString::from("store"));
+        name.push(".search");
+        store_path.with_file_name(name)
+    }
+
+    /// Load the index from `location`
+    ///
+    /// If there is no index at `location` yet, an empty one is returned.
+    pub fn load(backend: &StorageBackend, location: PathBuf) -> Result<SearchIndex> {
+        let mut index = SearchIndex::new(location);
+        for (id, tokens) in try!(read_documents(backend, &index.location)) {
+            index.insert_document(id, tokens);
+        }
+        Ok(index)
+    }
+
+    /// Write the changes to the index to its location, if it was altered since it was loaded
+    ///
+    /// The index file is locked meanwhile, and the changes are applied to the index as it is on
+    /// disk right now, so changes other processes saved in the meantime are kept.
+    pub fn save(&mut self, backend: &StorageBackend) -> Result<()> {
+        if !self.cleared && self.changes.is_empty() {
+            debug!("Search index not altered, not writing it");
+            return Ok(());
+        }
+
+        let lock = lock_path(&self.location);
+        try!(lock_index(backend, &lock).map_err_into(SEK::IndexLockError));
+        let res = self.merge_and_write(backend);
+        try!(backend.unlock(&lock).map_err_into(SEK::IndexLockError));
+        res
+    }
+
+    fn merge_and_write(&mut self, backend: &StorageBackend) -> Result<()> {
+        let mut documents = if self.cleared {
+            BTreeMap::new()
+        } else {
+            try!(read_documents(backend, &self.location))
+        };
+
+        for (id, change) in self.changes.iter() {
+            match *change {
+                Some(ref tokens) => { documents.insert(id.clone(), tokens.clone()); },
+                None             => { documents.remove(id); },
+            }
+        }
+
+        let serialized = documents
+            .iter()
+            .map(|(id, tokens)| format!("{}\t{}\n", id, tokens.join(" ")))
+            .collect::<String>();
+
+        // The index is written to a temporary file first, which is then moved over the old
+        // index, so processes which read the index meanwhile never see a half-written one
+        let mut tmp = self.location.clone().into_os_string();
+        tmp.push(".tmp");
+        let tmp = PathBuf::from(tmp);
+
+        try!(backend.write(&tmp, serialized.as_bytes()).map_err_into(SEK::IndexWriteError));
+        try!(backend.rename(&tmp, &self.location).map_err_into(SEK::IndexWriteError));
+
+        self.documents.clear();
+        self.postings.clear();
+        for (id, tokens) in documents {
+            self.insert_document(id, tokens);
+        }
+        self.changes.clear();
+        self.cleared = false;
+        Ok(())
+    }
+
+    /// Get the path where this index is persisted
+    pub fn location(&self) -> &PathBuf {
+        &self.location
+    }
+
+    /// The number of entries in the index
+    pub fn len(&self) -> usize {
+        self.documents.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.documents.is_empty()
+    }
+
+    /// Remove all entries from the index
+    pub fn clear(&mut self) {
+        self.documents.clear();
+        self.postings.clear();
+        self.changes.clear();
+        self.cleared = true;
+    }
+
+    /// Add `entry` to the index, replacing the old version of it
+    pub fn update(&mut self, entry: &Entry) -> Result<()> {
+        let key    = try!(index_key(entry.get_location()));
+        let tokens = tokenize_entry(entry);
+        debug!("Updating search index for {}", key);
+        self.remove_document(&key);
+        self.insert_document(key.clone(), tokens.clone());
+        self.changes.insert(key, Some(tokens));
+        Ok(())
+    }
+
+    /// Remove the entry `id` from the index
+    pub fn remove(&mut self, id: &StoreId) -> Result<()> {
+        let key = try!(index_key(id));
+        debug!("Removing {} from search index", key);
+        self.remove_document(&key);
+        self.changes.insert(key, None);
+        Ok(())
+    }
+
+    /// Move the index data of `from` to `to`
+    pub fn rename(&mut self, from: &StoreId, to: &StoreId) -> Result<()> {
+        let from = try!(index_key(from));
+        let to   = try!(index_key(to));
+
+        if let Some(tokens) = self.remove_document(&from) {
+            debug!("Moving {} -> {} in search index", from, to);
+            self.remove_document(&to);
+            self.insert_document(to.clone(), tokens.clone());
+            self.changes.insert(to, Some(tokens));
+        }
+        self.changes.insert(from, None);
+        Ok(())
+    }
+
+    /// Rebuild the index from all entries of `module`, or from the whole store if `module` is
+    /// `None`
+    ///
+    /// Entries which cannot be read are skipped.
+    pub fn rebuild(&mut self, store: &Store, module: Option<&str>) -> Result<()> {
+        let ids = try!(store
+                       .retrieve_for_module(module.unwrap_or(""))
+                       .map_err_into(SEK::StoreReadError));
+
+        match module {
+            None => self.clear(),
+            Some(m) => {
+                let keys = self.documents
+                    .keys()
+                    .filter(|k| PathBuf::from(k).starts_with(m))
+                    .cloned()
+                    .collect::<Vec<_>>();
+                for key in keys {
+                    self.remove_document(&key);
+                    self.changes.insert(key, None);
+                }
+            },
+        }
+
+        for id in ids {
+            match store.retrieve_copy(id) {
+                Ok(entry) => try!(self.update(&entry)),
+                Err(e) => {
+                    warn!("Cannot index entry, skipping it");
+                    trace_error(&e);
+                },
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Search the index
+    ///
+    /// If `module` is given, only entries whose `StoreId` starts with `module` are returned.
+    /// The results are ranked by TF-IDF over the positive terms of the query, best first.
+    pub fn search(&self, query: &Query, module: Option<&str>) -> Vec<SearchResult> {
+        let terms  = query.positive_terms();
+        let n_docs = self.documents.len() as f64;
+
+        let mut results = self.matches(query)
+            .into_iter()
+            .filter(|id| module.map(|m| PathBuf::from(id).starts_with(m)).unwrap_or(true))
+            .filter_map(|id| {
+                let len   = max(1, self.documents.get(&id).map(|d| d.len()).unwrap_or(0)) as f64;
+                let score = terms.iter()
+                    .filter_map(|t| self.postings.get(*t))
+                    .map(|docs| {
+                        let tf  = docs.get(&id).map(|p| p.len()).unwrap_or(0) as f64;
+                        let idf = (1.0 + n_docs / docs.len() as f64).ln();
+                        tf * idf
+                    })
+                    .fold(0.0, |acc, s| acc + s) / len.sqrt();
+
+                StoreId::new_baseless(PathBuf::from(id))
+                    .map(|id| SearchResult { id: id, score: score })
+                    .ok()
+            })
+            .collect::<Vec<_>>();
+
+        results.sort_by(|a, b| {
+            match b.score.partial_cmp(&a.score) {
+                Some(Ordering::Equal) | None => a.id.local().cmp(b.id.local()),
+                Some(ord) => ord,
+            }
+        });
+        results
+    }
+
+    /// Get the ids of all entries matching `query`
+    fn matches(&self, query: &Query) -> BTreeSet<String> {
+        match *query {
+            Query::Term(ref t) => self.postings
+                .get(t)
+                .map(|docs| docs.keys().cloned().collect())
+                .unwrap_or_else(BTreeSet::new),
+
+            Query::Phrase(ref ts) => self.phrase_matches(ts),
+
+            Query::And(ref a, ref b) => self.matches(a)
+                .intersection(&self.matches(b))
+                .cloned()
+                .collect(),
+
+            Query::Or(ref a, ref b) => self.matches(a)
+                .union(&self.matches(b))
+                .cloned()
+                .collect(),
+
+            Query::Not(ref q) => {
+                let excluded = self.matches(q);
+                self.documents
+                    .keys()
+                    .filter(|id| !excluded.contains(*id))
+                    .cloned()
+                    .collect()
+            },
+        }
+    }
+
+    fn phrase_matches(&self, terms: &[String]) -> BTreeSet<String> {
+        let postings = terms.iter().map(|t| self.postings.get(t)).collect::<Option<Vec<_>>>();
+        let postings = match postings {
+            Some(p) => p,
+            None    => return BTreeSet::new(), // one of the terms is not in the index at all
+        };
+
+        postings[0]
+            .iter()
+            .filter(|&(id, first_positions)| {
+                first_positions.iter().any(|start| {
+                    postings[1..].iter().enumerate().all(|(i, docs)| {
+                        docs.get(id)
+                            .map(|ps| ps.binary_search(&(start + i + 1)).is_ok())
+                            .unwrap_or(false)
+                    })
+                })
+            })
+            .map(|(id, _)| id.clone())
+            .collect()
+    }
+
+    fn insert_document(&mut self, id: String, tokens: Vec<String>) {
+        for (pos, token) in tokens.iter().enumerate() {
+            if token == FIELD_SEPARATOR {
+                continue;
+            }
+
+            self.postings
+                .entry(token.clone())
+                .or_insert_with(BTreeMap::new)
+                .entry(id.clone())
+                .or_insert_with(Vec::new)
+                .push(pos);
+        }
+        self.documents.insert(id, tokens);
+    }
+
+    fn remove_document(&mut self, id: &str) -> Option<Vec<String>> {
+        let tokens = match self.documents.remove(id) {
+            Some(t) => t,
+            None    => return None,
+        };
+
+        for token in tokens.iter() {
+            let now_empty = match self.postings.get_mut(token) {
+                Some(docs) => {
+                    docs.remove(id);
+                    docs.is_empty()
+                },
+                None => false,
+            };
+            if now_empty {
+                self.postings.remove(token);
+            }
+        }
+
+        Some(tokens)
+    }
+
+}
+
+impl StoreIndex for SearchIndex {
+
+    fn update(&mut self, entry: &Entry) -> StoreResult<()> {
+        SearchIndex::update(self, entry).map_err(into_store_error)
+    }
+
+    fn remove(&mut self, id: &StoreId) -> StoreResult<()> {
+        SearchIndex::remove(self, id).map_err(into_store_error)
+    }
+
+    fn rename(&mut self, from: &StoreId, to: &StoreId) -> StoreResult<()> {
+        SearchIndex::rename(self, from, to).map_err(into_store_error)
+    }
+
+    fn save(&mut self, backend: &StorageBackend) -> StoreResult<()> {
+        SearchIndex::save(self, backend).map_err(into_store_error)
+    }
+
+}
+
+fn into_store_error(e: SearchError) -> StoreError {
+    StoreErrorKind::IndexWriteError.into_error_with_cause(Box::new(e))
+}
+
+fn read_documents(backend: &StorageBackend, location: &PathBuf)
+    -> Result<BTreeMap<String, Vec<String>>>
+{
+    let mut documents = BTreeMap::new();
+    if !backend.is_file(location) {
+        debug!("No search index at {:?}, starting with an empty one", location);
+        return Ok(documents);
+    }
+
+    let buf = try!(backend.read(location).map_err_into(SEK::IndexReadError));
+    let s   = try!(String::from_utf8(buf).map_err_into(SEK::EncodingError)
+                   .map_err_into(SEK::IndexReadError));
+
+    for line in s.lines() {
+        let mut parts = line.splitn(2, '\t');
+        match (parts.next(), parts.next()) {
+            (Some(id), Some(tokens)) => {
+                let tokens = tokens.split(' ').filter(|t| !t.is_empty()).map(String::from);
+                documents.insert(String::from(id), tokens.collect());
+            },
+            _ => return Err(SEK::IndexFormatError.into_error()),
+        }
+    }
+
+    Ok(documents)
+}
+
+fn index_key(id: &StoreId) -> Result<String> {
+    id.local()
+        .to_str()
+        .map(String::from)
+        .ok_or(SEK::EncodingError.into_error())
+        .and_then(|s| if s.contains('\t') || s.contains('\n') {
+            Err(SEK::EncodingError.into_error())
+        } else {
+            Ok(s)
+        })
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::PathBuf;
+
+    use libimagstore::file_abstraction::{InMemoryBackend, StorageBackend};
+    use libimagstore::store::Entry;
+    use libimagstore::storeid::StoreId;
+
+    use super::SearchIndex;
+    use query::Query;
+
+    fn entry(id: &str, content: &str) -> Entry {
+        let mut e = Entry::new(StoreId::new_baseless(PathBuf::from(id)).unwrap());
+        *e.get_content_mut() = String::from(content);
+        e
+    }
+
+    fn index() -> SearchIndex {
+        let mut index = SearchIndex::new(PathBuf::from("/tmp/imag-test.search"));
+        index.update(&entry("notes/a", "The quick brown fox jumps over the lazy dog")).unwrap();
+        index.update(&entry("notes/b", "A lazy afternoon, brown bread")).unwrap();
+        index.update(&entry("diary/c", "fox fox fox")).unwrap();
+        index
+    }
+
+    fn search(index: &SearchIndex, q: &str, module: Option<&str>) -> Vec<String> {
+        index.search(&Query::parse(q).unwrap(), module)
+            .into_iter()
+            .map(|r| format!("{}", r.id()))
+            .collect()
+    }
+
+    #[test]
+    fn test_boolean_queries() {
+        let index = index();
+        assert_eq!(search(&index, "lazy brown", None), vec!["notes/b", "notes/a"]);
+        assert_eq!(search(&index, "lazy -fox", None), vec!["notes/b"]);
+        assert_eq!(search(&index, "bread OR dog", None), vec!["notes/b", "notes/a"]);
+        assert!(search(&index, "unicorn", None).is_empty());
+    }
+
+    #[test]
+    fn test_phrase_query() {
+        let index = index();
+        assert_eq!(search(&index, "\"lazy dog\"", None), vec!["notes/a"]);
+        assert!(search(&index, "\"dog lazy\"", None).is_empty());
+    }
+
+    #[test]
+    fn test_ranking_and_module() {
+        let index = index();
+        assert_eq!(search(&index, "fox", None), vec!["diary/c", "notes/a"]);
+        assert_eq!(search(&index, "fox", Some("notes")), vec!["notes/a"]);
+    }
+
+    #[test]
+    fn test_update_remove_rename() {
+        let mut index = index();
+        index.update(&entry("notes/a", "nothing here")).unwrap();
+        assert!(search(&index, "fox", Some("notes")).is_empty());
+
+        index.rename(&StoreId::new_baseless(PathBuf::from("diary/c")).unwrap(),
+                     &StoreId::new_baseless(PathBuf::from("notes/c")).unwrap()).unwrap();
+        assert_eq!(search(&index, "fox", None), vec!["notes/c"]);
+
+        index.remove(&StoreId::new_baseless(PathBuf::from("notes/c")).unwrap()).unwrap();
+        assert!(search(&index, "fox", None).is_empty());
+        assert_eq!(index.len(), 2);
+    }
+
+    #[test]
+    fn test_save_merges_concurrent_changes() {
+        let backend  = InMemoryBackend::new();
+        let location = PathBuf::from("/search");
+        let mut idx  = index();
+        idx.location = location.clone();
+        idx.save(&backend).unwrap();
+        assert!(backend.is_file(&location));
+
+        // Two processes load the same index
+        let mut first  = SearchIndex::load(&backend, location.clone()).unwrap();
+        let mut second = SearchIndex::load(&backend, location.clone()).unwrap();
+
+        first.update(&entry("notes/d", "fox in the henhouse")).unwrap();
+        first.save(&backend).unwrap();
+
+        second.remove(&StoreId::new_baseless(PathBuf::from("diary/c")).unwrap()).unwrap();
+        second.save(&backend).unwrap();
+
+        let loaded = SearchIndex::load(&backend, location).unwrap();
+        assert_eq!(loaded.len(), 3);
+        assert_eq!(search(&loaded, "fox", None), vec!["notes/d", "notes/a"]);
+        assert_eq!(second.len(), 3);
+    }
+
+}
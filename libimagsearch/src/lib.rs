@@ -0,0 +1,60 @@
+//
+// imag - the personal information management suite for the commandline
+// Copyright (C) 2015, 2016 Matthias Beyer <mail@beyermatthias.de> and contributors
+//
+// This library is free software; you can redistribute it and/or
+// modify it under the terms of the GNU Lesser General Public
+// License as published by the Free Software Foundation; version
+// 2.1 of the License.
+//
+// This library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+// Lesser General Public License for more details.
+//
+// You should have received a copy of the GNU Lesser General Public
+// License along with this library; if not, write to the Free Software
+// Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA  02110-1301  USA
+//
+
+#![deny(
+    dead_code,
+    non_camel_case_types,
+    non_snake_case,
+    path_statements,
+    trivial_numeric_casts,
+    unstable_features,
+    unused_allocation,
+    unused_import_braces,
+    unused_imports,
+    unused_must_use,
+    unused_mut,
+    unused_qualifications,
+    while_true,
+)]
+
+//! Full-text search over the entries of the store
+//!
+//! This library maintains an inverted index over the content and the string header fields of all
+//! entries. If enabled in the configuration, the index is kept up to date by the index hooks which
+//! `hook::register_search_hooks()` registers and can be rebuilt from scratch with
+//! `SearchIndex::rebuild()`.
+//!
+//! Queries support boolean operators and phrases, see the `query` module for the syntax.
+//!
+
+#[macro_use] extern crate log;
+extern crate toml;
+
+extern crate libimagstore;
+extern crate libimagstorestdhook;
+#[macro_use] extern crate libimagerror;
+
+pub mod config;
+pub mod error;
+pub mod hook;
+pub mod index;
+pub mod query;
+pub mod result;
+pub mod tokenizer;
+
@@ -0,0 +1,260 @@
+//
+// imag - the personal information management suite for the commandline
+// Copyright (C) 2015, 2016 Matthias Beyer <mail@beyermatthias.de> and contributors
+//
+// This library is free software; you can redistribute it and/or
+// modify it under the terms of the GNU Lesser General Public
+// License as published by the Free Software Foundation; version
+// 2.1 of the License.
+//
+// This library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+// Lesser General Public License for more details.
+//
+// You should have received a copy of the GNU Lesser General Public
+// License along with this library; if not, write to the Free Software
+// Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA  02110-1301  USA
+//
+
+//! Search queries
+//!
+//! The query syntax is:
+//!
+//! * `foo bar` - entries which contain both "foo" and "bar"
+//! * `foo AND bar` - same as above
+//! * `foo OR bar` - entries which contain "foo" or "bar"
+//! * `NOT foo` or `-foo` - entries which do not contain "foo"
+//! * `"foo bar"` - entries which contain "foo" directly followed by "bar"
+//! * `(foo OR bar) baz` - parentheses group expressions
+//!
+//! `AND` binds stronger than `OR`. Terms are matched case-insensitively.
+//!
+
+use std::iter::Peekable;
+use std::vec::IntoIter;
+
+use libimagerror::into::IntoError;
+
+use error::SearchErrorKind as SEK;
+use result::Result;
+use tokenizer::tokenize;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Query {
+    Term(String),
+    Phrase(Vec<String>),
+    And(Box<Query>, Box<Query>),
+    Or(Box<Query>, Box<Query>),
+    Not(Box<Query>),
+}
+
+impl Query {
+
+    /// Parse a query string
+    pub fn parse(s: &str) -> Result<Query> {
+        let mut tokens = try!(lex(s)).into_iter().peekable();
+        if tokens.peek().is_none() {
+            return Err(SEK::EmptyQuery.into_error());
+        }
+
+        let query = try!(parse_or(&mut tokens));
+        match tokens.next() {
+            None    => Ok(query),
+            Some(t) => {
+                debug!("Unexpected token in query: {:?}", t);
+                Err(SEK::QueryParseError.into_error())
+            },
+        }
+    }
+
+    /// Get all terms which have to be present in a matching entry
+    ///
+    /// Terms below a `NOT` are not included. These terms are used for ranking the results.
+    pub fn positive_terms(&self) -> Vec<&String> {
+        match *self {
+            Query::Term(ref t)         => vec![t],
+            Query::Phrase(ref ts)      => ts.iter().collect(),
+            Query::And(ref a, ref b)   |
+            Query::Or(ref a, ref b)    => {
+                let mut v = a.positive_terms();
+                v.append(&mut b.positive_terms());
+                v
+            },
+            Query::Not(_)              => vec![],
+        }
+    }
+
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+    Word(String),
+    Phrase(String),
+    And,
+    Or,
+    Not,
+    Open,
+    Close,
+}
+
+fn lex(s: &str) -> Result<Vec<Token>> {
+    let mut tokens = vec![];
+    let mut chars  = s.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            c if c.is_whitespace() => continue,
+            '(' => tokens.push(Token::Open),
+            ')' => tokens.push(Token::Close),
+            '"' => {
+                let mut phrase = String::new();
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some(c)   => phrase.push(c),
+                        None      => return Err(SEK::QueryParseError.into_error()),
+                    }
+                }
+                tokens.push(Token::Phrase(phrase));
+            },
+            c => {
+                let mut word = String::new();
+                word.push(c);
+                loop {
+                    let ends_word = match chars.peek() {
+                        Some(&c) => c.is_whitespace() || c == '(' || c == ')' || c == '"',
+                        None     => true,
+                    };
+                    if ends_word {
+                        break;
+                    }
+                    word.push(chars.next().unwrap());
+                }
+
+                match &word[..] {
+                    "AND" => tokens.push(Token::And),
+                    "OR"  => tokens.push(Token::Or),
+                    "NOT" => tokens.push(Token::Not),
+                    _ => if word.starts_with('-') && word.len() > 1 {
+                        tokens.push(Token::Not);
+                        tokens.push(Token::Word(String::from(&word[1..])));
+                    } else {
+                        tokens.push(Token::Word(word));
+                    },
+                }
+            },
+        }
+    }
+
+    Ok(tokens)
+}
+
+type Tokens = Peekable<IntoIter<Token>>;
+
+fn parse_or(tokens: &mut Tokens) -> Result<Query> {
+    let mut query = try!(parse_and(tokens));
+    while tokens.peek() == Some(&Token::Or) {
+        tokens.next();
+        let rhs = try!(parse_and(tokens));
+        query = Query::Or(Box::new(query), Box::new(rhs));
+    }
+    Ok(query)
+}
+
+fn parse_and(tokens: &mut Tokens) -> Result<Query> {
+    let mut query = try!(parse_not(tokens));
+    loop {
+        let explicit = match tokens.peek() {
+            Some(&Token::And)       => true,
+            Some(&Token::Word(_))   |
+            Some(&Token::Phrase(_)) |
+            Some(&Token::Not)       |
+            Some(&Token::Open)      => false,
+            _ => return Ok(query),
+        };
+        if explicit {
+            tokens.next();
+        }
+        let rhs = try!(parse_not(tokens));
+        query = Query::And(Box::new(query), Box::new(rhs));
+    }
+}
+
+fn parse_not(tokens: &mut Tokens) -> Result<Query> {
+    if tokens.peek() == Some(&Token::Not) {
+        tokens.next();
+        parse_not(tokens).map(Box::new).map(Query::Not)
+    } else {
+        parse_primary(tokens)
+    }
+}
+
+fn parse_primary(tokens: &mut Tokens) -> Result<Query> {
+    match tokens.next() {
+        Some(Token::Open) => {
+            let query = try!(parse_or(tokens));
+            match tokens.next() {
+                Some(Token::Close) => Ok(query),
+                _ => Err(SEK::QueryParseError.into_error()),
+            }
+        },
+        Some(Token::Word(w))   |
+        Some(Token::Phrase(w)) => terms_to_query(tokenize(&w)),
+        t => {
+            debug!("Unexpected token in query: {:?}", t);
+            Err(SEK::QueryParseError.into_error())
+        },
+    }
+}
+
+/// A word like "foo-bar" results in more than one term and is handled like the phrase "foo bar"
+fn terms_to_query(mut terms: Vec<String>) -> Result<Query> {
+    match terms.len() {
+        0 => Err(SEK::QueryParseError.into_error()),
+        1 => Ok(Query::Term(terms.pop().unwrap())),
+        _ => Ok(Query::Phrase(terms)),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Query;
+    use super::Query::*;
+
+    fn term(s: &str) -> Box<Query> {
+        Box::new(Term(String::from(s)))
+    }
+
+    #[test]
+    fn test_parse_implicit_and() {
+        assert_eq!(Query::parse("foo Bar").unwrap(), And(term("foo"), term("bar")));
+        assert_eq!(Query::parse("foo AND bar").unwrap(), And(term("foo"), term("bar")));
+    }
+
+    #[test]
+    fn test_parse_precedence() {
+        assert_eq!(Query::parse("a b OR c").unwrap(),
+                   Or(Box::new(And(term("a"), term("b"))), term("c")));
+        assert_eq!(Query::parse("a (b OR c)").unwrap(),
+                   And(term("a"), Box::new(Or(term("b"), term("c")))));
+    }
+
+    #[test]
+    fn test_parse_not_and_phrase() {
+        assert_eq!(Query::parse("-foo").unwrap(), Not(term("foo")));
+        assert_eq!(Query::parse("NOT \"foo bar\"").unwrap(),
+                   Not(Box::new(Phrase(vec![String::from("foo"), String::from("bar")]))));
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert!(Query::parse("").is_err());
+        assert!(Query::parse("(foo").is_err());
+        assert!(Query::parse("\"foo").is_err());
+        assert!(Query::parse("foo OR").is_err());
+        assert!(Query::parse("foo )").is_err());
+    }
+
+}
+
@@ -0,0 +1,90 @@
+//
+// imag - the personal information management suite for the commandline
+// Copyright (C) 2015, 2016 Matthias Beyer <mail@beyermatthias.de> and contributors
+//
+// This library is free software; you can redistribute it and/or
+// modify it under the terms of the GNU Lesser General Public
+// License as published by the Free Software Foundation; version
+// 2.1 of the License.
+//
+// This library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+// Lesser General Public License for more details.
+//
+// You should have received a copy of the GNU Lesser General Public
+// License along with this library; if not, write to the Free Software
+// Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA  02110-1301  USA
+//
+
+//! Splitting text into the terms the search index is built from
+
+use toml::Value;
+
+use libimagstore::store::Entry;
+
+/// Marker which is put between the fields of an entry in its token stream
+///
+/// It is never produced by `tokenize()`, so a phrase can never match across two fields.
+pub const FIELD_SEPARATOR: &'static str = "|";
+
+/// Split `s` into lowercase terms
+///
+/// Everything which is not alphanumeric separates two terms.
+pub fn tokenize(s: &str) -> Vec<String> {
+    s.split(|c: char| !c.is_alphanumeric())
+        .filter(|t| !t.is_empty())
+        .map(|t| t.to_lowercase())
+        .collect()
+}
+
+/// Get the token stream of an entry
+///
+/// The stream contains the terms of all string header fields, followed by the terms of the
+/// content. The "imag" header section only holds bookkeeping data (version, links) and is not
+/// indexed.
+pub fn tokenize_entry(entry: &Entry) -> Vec<String> {
+    let mut tokens = vec![];
+
+    if let Value::Table(ref t) = *entry.get_header().header() {
+        for (key, value) in t.iter() {
+            if key != "imag" {
+                tokenize_value(value, &mut tokens);
+            }
+        }
+    }
+
+    tokens.push(String::from(FIELD_SEPARATOR));
+    tokens.append(&mut tokenize(entry.get_content()));
+    tokens
+}
+
+fn tokenize_value(value: &Value, tokens: &mut Vec<String>) {
+    match *value {
+        Value::String(ref s) => {
+            tokens.push(String::from(FIELD_SEPARATOR));
+            tokens.append(&mut tokenize(s));
+        },
+        Value::Array(ref a) => for elem in a {
+            tokenize_value(elem, tokens);
+        },
+        Value::Table(ref t) => for (_, elem) in t.iter() {
+            tokenize_value(elem, tokens);
+        },
+        _ => { /* only strings are indexed */ },
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::tokenize;
+
+    #[test]
+    fn test_tokenize() {
+        assert_eq!(tokenize("Hello, World!"), vec!["hello", "world"]);
+        assert_eq!(tokenize("  foo-bar_baz 42 "), vec!["foo", "bar", "baz", "42"]);
+        assert!(tokenize(" ,;. ").is_empty());
+    }
+
+}
+
@@ -17,11 +17,13 @@
 // Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA  02110-1301  USA
 //
 
+use std::path::PathBuf;
+
 use toml::Value;
 
 use libimagerror::into::IntoError;
 
-use store::Result;
+use store::{LockPolicy, Result};
 
 /// Check whether the configuration is valid for the store
 ///
@@ -212,6 +214,72 @@ pub fn config_implicit_store_create_allowed(config: Option<&Value>) -> bool {
     }).unwrap_or(false)
 }
 
+/// Checks whether the store configuration has a table "index" with a key "enabled" which maps
+/// to `true`. If not, the store index is disabled.
+pub fn config_index_enabled(config: Option<&Value>) -> bool {
+    match config.and_then(|c| c.lookup("index.enabled")) {
+        Some(&Value::Boolean(b)) => b,
+        Some(_) => {
+            warn!("Key 'index.enabled' does not contain a Boolean value");
+            false
+        },
+        None => false,
+    }
+}
+
+/// Get the path of the store index from the store configuration ("index.path"), if configured
+pub fn config_index_path(config: Option<&Value>) -> Option<PathBuf> {
+    match config.and_then(|c| c.lookup("index.path")) {
+        Some(&Value::String(ref s)) => Some(PathBuf::from(s)),
+        Some(_) => {
+            warn!("Key 'index.path' does not contain a String value");
+            None
+        },
+        None => None,
+    }
+}
+
+/// Get the policy for waiting on entries which are locked by another process
+///
+/// The policy is read from the "locking" table of the store configuration:
+///
+/// * `policy = "fail"` fails immediately
+/// * `policy = "wait"` waits for `timeout` milliseconds (default: 5000)
+/// * `policy = "block"` waits until the lock is released
+///
+/// If there is no such table, the store waits for the default timeout.
+pub fn config_lock_policy(config: Option<&Value>) -> LockPolicy {
+    use std::time::Duration;
+
+    let timeout = match config.and_then(|c| c.lookup("locking.timeout")) {
+        Some(&Value::Integer(i)) if i >= 0 => Duration::from_millis(i as u64),
+        Some(_) => {
+            warn!("Key 'locking.timeout' does not contain a positive Integer value");
+            Duration::from_millis(DEFAULT_LOCK_TIMEOUT_MS)
+        },
+        None => Duration::from_millis(DEFAULT_LOCK_TIMEOUT_MS),
+    };
+
+    match config.and_then(|c| c.lookup("locking.policy")) {
+        Some(&Value::String(ref s)) => match &s[..] {
+            "fail"  => LockPolicy::Fail,
+            "wait"  => LockPolicy::Wait(timeout),
+            "block" => LockPolicy::Block,
+            _ => {
+                warn!("Unknown locking policy '{}', waiting {:?} for locks", s, timeout);
+                LockPolicy::Wait(timeout)
+            },
+        },
+        Some(_) => {
+            warn!("Key 'locking.policy' does not contain a String value");
+            LockPolicy::Wait(timeout)
+        },
+        None => LockPolicy::Wait(timeout),
+    }
+}
+
+const DEFAULT_LOCK_TIMEOUT_MS : u64 = 5000;
+
 pub fn get_store_unload_aspect_names(value: &Option<Value>) -> Vec<String> {
     get_aspect_names_for_aspect_position("store-unload-hook-aspects", value)
 }
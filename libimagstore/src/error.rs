@@ -57,12 +57,17 @@ generate_custom_error_types!(StoreError, StoreErrorKind, CustomErrorData,
     LockError               => "Error locking datastructure",
     LockPoisoned            => "The internal Store Lock has been poisoned",
     EntryAlreadyBorrowed    => "Entry is already borrowed",
+    EntryLockedByOtherProcess => "Entry is locked by another process",
     EntryAlreadyExists      => "Entry already exists",
     MalformedEntry          => "Entry has invalid formatting, missing header",
     HeaderPathSyntaxError   => "Syntax error in accessor string",
     HeaderPathTypeFailure   => "Header has wrong type for path",
     HeaderKeyNotFound       => "Header Key not found",
     HeaderTypeFailure       => "Header type is wrong",
+    HeaderSchemaViolation   => "Header does not match the schema of its module",
+    SchemaAlreadyRegistered => "A schema for this header section is already registered",
+    MigrationError          => "Error while migrating an entry",
+    MigrationInvalid        => "A migration must go from a lower to a higher version",
     HookRegisterError       => "Hook register error",
     AspectNameNotFoundError => "Aspect name not found",
     HookExecutionError      => "Hook execution error",
@@ -77,6 +82,10 @@ generate_custom_error_types!(StoreError, StoreErrorKind, CustomErrorData,
     StoreIdLocalPartAbsoluteError => "StoreId 'id' part is absolute (starts with '/') which is not allowed",
     StoreIdBuildFromFullPathError => "Building StoreId from full file path failed",
     StoreIdHasNoBaseError   => "StoreId has no 'base' part",
+    IndexDisabled           => "The store index is not enabled",
+    IndexLoadError          => "Error while loading the store index",
+    IndexWriteError         => "Error while writing the store index",
+    JournalError            => "Error while handling the transaction journal",
 
     CreateCallError            => "Error when calling create()",
     RetrieveCallError          => "Error when calling retrieve()",
@@ -87,7 +96,11 @@ generate_custom_error_types!(StoreError, StoreErrorKind, CustomErrorData,
     RetrieveCopyCallError      => "Error when calling retrieve_copy()",
     DeleteCallError            => "Error when calling delete()",
     MoveCallError              => "Error when calling move()",
-    MoveByIdCallError          => "Error when calling move_by_id()"
+    MoveByIdCallError          => "Error when calling move_by_id()",
+    QueryCallError             => "Error when querying the store index",
+    ReindexCallError           => "Error when calling reindex()",
+    TransactionCommitError     => "Error when committing a transaction",
+    MigrateCallError           => "Error when calling migrate()"
 );
 
 generate_result_helper!(StoreError, StoreErrorKind);
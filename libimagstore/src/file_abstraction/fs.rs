@@ -0,0 +1,268 @@
+//
+// imag - the personal information management suite for the commandline
+// Copyright (C) 2015, 2016 Matthias Beyer <mail@beyermatthias.de> and contributors
+//
+// This library is free software; you can redistribute it and/or
+// modify it under the terms of the GNU Lesser General Public
+// License as published by the Free Software Foundation; version
+// 2.1 of the License.
+//
+// This library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+// Lesser General Public License for more details.
+//
+// You should have received a copy of the GNU Lesser General Public
+// License along with this library; if not, write to the Free Software
+// Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA  02110-1301  USA
+//
+
+use std::collections::HashMap;
+use std::io::{ErrorKind, Read, Write};
+use std::path::{Path, PathBuf};
+use std::fs::{File, OpenOptions, create_dir_all, remove_file, copy, rename};
+use std::sync::Mutex;
+
+use fs2::{FileExt, lock_contended_error};
+use walkdir::WalkDir;
+
+use libimagerror::into::IntoError;
+
+use error::StoreErrorKind as SEK;
+use error::MapErrInto;
+use store::Result;
+
+use super::StorageBackend;
+
+/// `StorageBackend` which stores the entries as files on the filesystem
+///
+/// Locks are advisory `flock()` locks on lock files, so they are visible to other processes.
+#[derive(Debug)]
+pub struct FileSystemBackend {
+    locks: Mutex<HashMap<PathBuf, File>>,
+}
+
+impl FileSystemBackend {
+
+    pub fn new() -> FileSystemBackend {
+        FileSystemBackend {
+            locks: Mutex::new(HashMap::new()),
+        }
+    }
+
+}
+
+fn create_file<A: AsRef<Path>>(p: A) -> ::std::io::Result<File> {
+    if let Some(parent) = p.as_ref().parent() {
+        debug!("Implicitely creating directory: {:?}", parent);
+        if let Err(e) = create_dir_all(parent) {
+            return Err(e);
+        }
+    }
+    OpenOptions::new().write(true).truncate(true).create(true).open(p)
+}
+
+impl StorageBackend for FileSystemBackend {
+
+    fn read(&self, path: &PathBuf) -> Result<Vec<u8>> {
+        debug!("Reading file: {:?}", path);
+        let mut file = try!(File::open(path).map_err_into(SEK::FileNotFound));
+        let mut buf = vec![];
+        file.read_to_end(&mut buf)
+            .map_err_into(SEK::IoError)
+            .map(|_| buf)
+    }
+
+    fn write(&self, path: &PathBuf, buf: &[u8]) -> Result<()> {
+        debug!("Writing file: {:?}", path);
+        create_file(path)
+            .map_err_into(SEK::FileNotCreated)
+            .and_then(|mut file| file.write_all(buf).map_err_into(SEK::FileNotWritten))
+    }
+
+    fn remove(&self, path: &PathBuf) -> Result<()> {
+        remove_file(path).map_err_into(SEK::FileNotRemoved)
+    }
+
+    fn copy(&self, from: &PathBuf, to: &PathBuf) -> Result<()> {
+        copy(from, to).map_err_into(SEK::FileNotCopied).map(|_| ())
+    }
+
+    fn rename(&self, from: &PathBuf, to: &PathBuf) -> Result<()> {
+        if let Some(parent) = to.parent() {
+            try!(create_dir_all(parent).map_err_into(SEK::DirNotCreated));
+        }
+        rename(from, to).map_err_into(SEK::FileNotRenamed)
+    }
+
+    fn create_dir_all(&self, path: &PathBuf) -> Result<()> {
+        create_dir_all(path).map_err_into(SEK::DirNotCreated)
+    }
+
+    fn list(&self, path: &PathBuf) -> Result<Vec<PathBuf>> {
+        let mut v = vec![];
+
+        if !path.exists() {
+            debug!("Nothing to list at {:?}", path);
+            return Ok(v);
+        }
+
+        for elem in WalkDir::new(path).into_iter() {
+            let dent = try!(elem.map_err_into(SEK::IoError));
+            if dent.file_type().is_file() {
+                v.push(dent.path().to_path_buf());
+            }
+        }
+
+        Ok(v)
+    }
+
+    fn is_file(&self, path: &PathBuf) -> bool {
+        path.is_file()
+    }
+
+    fn is_dir(&self, path: &PathBuf) -> bool {
+        path.is_dir()
+    }
+
+    fn try_lock(&self, path: &PathBuf) -> Result<bool> {
+        let mut locks = try!(self.locks.lock().map_err(|_| SEK::LockPoisoned.into_error()));
+        if locks.contains_key(path) {
+            return Ok(false);
+        }
+
+        loop {
+            if let Some(parent) = path.parent() {
+                try!(create_dir_all(parent).map_err_into(SEK::DirNotCreated));
+            }
+            let file = try!(OpenOptions::new()
+                            .write(true)
+                            .create(true)
+                            .open(path)
+                            .map_err_into(SEK::FileNotCreated));
+
+            match file.try_lock_exclusive() {
+                Ok(()) => {
+                    // The process which held the lock before removes the lock file when it
+                    // releases the lock. If that happened between opening and locking the file,
+                    // we locked a file nobody else sees and have to start over.
+                    if !try!(is_same_file(&file, path).map_err_into(SEK::LockError)) {
+                        debug!("Lock file {:?} was removed meanwhile, retrying", path);
+                        continue;
+                    }
+
+                    debug!("Locked {:?}", path);
+                    locks.insert(path.clone(), file);
+                    return Ok(true);
+                },
+                Err(ref e) if e.raw_os_error() == lock_contended_error().raw_os_error() => {
+                    debug!("Lock {:?} is held by another process", path);
+                    return Ok(false);
+                },
+                Err(e) => return Err(SEK::LockError.into_error_with_cause(Box::new(e))),
+            }
+        }
+    }
+
+    fn unlock(&self, path: &PathBuf) -> Result<()> {
+        let mut locks = try!(self.locks.lock().map_err(|_| SEK::LockPoisoned.into_error()));
+        match locks.remove(path) {
+            Some(file) => {
+                debug!("Unlocking {:?}", path);
+                // Remove the lock file while still holding the lock, so it cannot be locked by
+                // someone else in between
+                if let Err(e) = remove_file(path) {
+                    if e.kind() != ErrorKind::NotFound {
+                        warn!("Cannot remove lock file {:?}: {:?}", path, e);
+                    }
+                }
+                file.unlock().map_err_into(SEK::LockError)
+            },
+            None => Ok(()),
+        }
+    }
+
+}
+
+/// Check whether `file` is (still) the file at `path`
+#[cfg(unix)]
+fn is_same_file(file: &File, path: &PathBuf) -> ::std::io::Result<bool> {
+    use std::fs::metadata;
+    use std::os::unix::fs::MetadataExt;
+
+    let opened = try!(file.metadata());
+    match metadata(path) {
+        Ok(current) => Ok(opened.dev() == current.dev() && opened.ino() == current.ino()),
+        Err(ref e) if e.kind() == ErrorKind::NotFound => Ok(false),
+        Err(e) => Err(e),
+    }
+}
+
+#[cfg(not(unix))]
+fn is_same_file(_: &File, path: &PathBuf) -> ::std::io::Result<bool> {
+    Ok(path.is_file())
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::PathBuf;
+
+    use tempdir::TempDir;
+
+    use super::FileSystemBackend;
+    use file_abstraction::StorageBackend;
+
+    #[test]
+    fn test_write_read() {
+        let dir     = TempDir::new("imag-fs-backend").unwrap();
+        let backend = FileSystemBackend::new();
+        let mut pb  = PathBuf::from(dir.path());
+        pb.push("sub/test1");
+
+        assert!(backend.write(&pb, b"Hello World, this is long").is_ok());
+        assert!(backend.write(&pb, b"Hello World").is_ok());
+        assert_eq!(backend.read(&pb).unwrap(), b"Hello World");
+        assert!(backend.is_file(&pb));
+        assert!(backend.is_dir(&PathBuf::from(dir.path())));
+    }
+
+    #[test]
+    fn test_list() {
+        let dir     = TempDir::new("imag-fs-backend").unwrap();
+        let backend = FileSystemBackend::new();
+        let base    = PathBuf::from(dir.path());
+
+        for name in &["mod/a", "mod/b/c", "other/d"] {
+            let mut pb = base.clone();
+            pb.push(name);
+            assert!(backend.write(&pb, b"").is_ok());
+        }
+
+        let mut module = base.clone();
+        module.push("mod");
+        assert_eq!(backend.list(&module).unwrap().len(), 2);
+
+        let mut absent = base.clone();
+        absent.push("absent");
+        assert!(backend.list(&absent).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_lock() {
+        let dir     = TempDir::new("imag-fs-backend").unwrap();
+        let backend = FileSystemBackend::new();
+        let other   = FileSystemBackend::new();
+        let mut pb  = PathBuf::from(dir.path());
+        pb.push("locks/entry");
+
+        assert!(backend.try_lock(&pb).unwrap());
+        assert!(!backend.try_lock(&pb).unwrap());
+        assert!(!other.try_lock(&pb).unwrap());
+
+        assert!(backend.unlock(&pb).is_ok());
+        assert!(!pb.exists());
+        assert!(other.try_lock(&pb).unwrap());
+        assert!(pb.exists());
+    }
+
+}
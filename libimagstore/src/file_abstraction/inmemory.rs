@@ -0,0 +1,197 @@
+//
+// imag - the personal information management suite for the commandline
+// Copyright (C) 2015, 2016 Matthias Beyer <mail@beyermatthias.de> and contributors
+//
+// This library is free software; you can redistribute it and/or
+// modify it under the terms of the GNU Lesser General Public
+// License as published by the Free Software Foundation; version
+// 2.1 of the License.
+//
+// This library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+// Lesser General Public License for more details.
+//
+// You should have received a copy of the GNU Lesser General Public
+// License along with this library; if not, write to the Free Software
+// Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA  02110-1301  USA
+//
+
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::sync::MutexGuard;
+
+use libimagerror::into::IntoError;
+
+use error::StoreErrorKind as SEK;
+use store::Result;
+
+use super::StorageBackend;
+
+/// `StorageBackend` which holds all files in memory
+///
+/// Directories are mostly implicit in this backend: the root, the directories created with
+/// `create_dir_all()` and all directories which contain files exist.
+#[derive(Debug)]
+pub struct InMemoryBackend {
+    files: Mutex<HashMap<PathBuf, Vec<u8>>>,
+    dirs: Mutex<HashSet<PathBuf>>,
+    locks: Mutex<HashSet<PathBuf>>,
+}
+
+impl InMemoryBackend {
+
+    pub fn new() -> InMemoryBackend {
+        InMemoryBackend {
+            files: Mutex::new(HashMap::new()),
+            dirs: Mutex::new(HashSet::new()),
+            locks: Mutex::new(HashSet::new()),
+        }
+    }
+
+    fn files(&self) -> Result<MutexGuard<HashMap<PathBuf, Vec<u8>>>> {
+        self.files.lock().map_err(|_| SEK::LockPoisoned.into_error())
+    }
+
+}
+
+impl StorageBackend for InMemoryBackend {
+
+    fn read(&self, path: &PathBuf) -> Result<Vec<u8>> {
+        debug!("Reading in-memory file: {:?}", path);
+        self.files()
+            .and_then(|map| map.get(path).cloned().ok_or(SEK::FileNotFound.into_error()))
+    }
+
+    fn write(&self, path: &PathBuf, buf: &[u8]) -> Result<()> {
+        debug!("Writing in-memory file: {:?}", path);
+        self.files()
+            .map(|mut map| { map.insert(path.clone(), Vec::from(buf)); })
+    }
+
+    fn remove(&self, path: &PathBuf) -> Result<()> {
+        self.files()
+            .and_then(|mut map| {
+                map.remove(path).map(|_| ()).ok_or(SEK::FileNotFound.into_error())
+            })
+    }
+
+    fn copy(&self, from: &PathBuf, to: &PathBuf) -> Result<()> {
+        let mut map = try!(self.files());
+        let content = try!(map.get(from).cloned().ok_or(SEK::FileNotFound.into_error()));
+        map.insert(to.clone(), content);
+        Ok(())
+    }
+
+    fn rename(&self, from: &PathBuf, to: &PathBuf) -> Result<()> {
+        let mut map = try!(self.files());
+        let content = try!(map.remove(from).ok_or(SEK::FileNotFound.into_error()));
+        map.insert(to.clone(), content);
+        Ok(())
+    }
+
+    fn create_dir_all(&self, path: &PathBuf) -> Result<()> {
+        self.dirs
+            .lock()
+            .map_err(|_| SEK::LockPoisoned.into_error())
+            .map(|mut dirs| { dirs.insert(path.clone()); })
+    }
+
+    fn list(&self, path: &PathBuf) -> Result<Vec<PathBuf>> {
+        self.files()
+            .map(|map| map.keys().filter(|p| p.starts_with(path)).cloned().collect())
+    }
+
+    fn is_file(&self, path: &PathBuf) -> bool {
+        self.files().map(|map| map.contains_key(path)).unwrap_or(false)
+    }
+
+    fn is_dir(&self, path: &PathBuf) -> bool {
+        if path.parent().is_none() {
+            return true;
+        }
+
+        let created = self.dirs
+            .lock()
+            .map(|dirs| dirs.iter().any(|d| d.starts_with(path)))
+            .unwrap_or(false);
+
+        created || self.files()
+            .map(|map| map.keys().any(|p| p != path && p.starts_with(path)))
+            .unwrap_or(false)
+    }
+
+    fn try_lock(&self, path: &PathBuf) -> Result<bool> {
+        self.locks
+            .lock()
+            .map_err(|_| SEK::LockPoisoned.into_error())
+            .map(|mut locks| locks.insert(path.clone()))
+    }
+
+    fn unlock(&self, path: &PathBuf) -> Result<()> {
+        self.locks
+            .lock()
+            .map_err(|_| SEK::LockPoisoned.into_error())
+            .map(|mut locks| { locks.remove(path); })
+    }
+
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::PathBuf;
+
+    use super::InMemoryBackend;
+    use file_abstraction::StorageBackend;
+
+    #[test]
+    fn test_write_read() {
+        let backend = InMemoryBackend::new();
+        let path    = PathBuf::from("/tests/test1");
+
+        assert!(backend.write(&path, b"Hello World, this is long").is_ok());
+        assert!(backend.write(&path, b"Hello World").is_ok());
+        assert_eq!(backend.read(&path).unwrap(), b"Hello World");
+    }
+
+    #[test]
+    fn test_rename_removes_old() {
+        let backend = InMemoryBackend::new();
+        let from    = PathBuf::from("/tests/from");
+        let to      = PathBuf::from("/tests/to");
+
+        assert!(backend.write(&from, b"content").is_ok());
+        assert!(backend.rename(&from, &to).is_ok());
+        assert!(!backend.is_file(&from));
+        assert_eq!(backend.read(&to).unwrap(), b"content");
+    }
+
+    #[test]
+    fn test_list() {
+        let backend = InMemoryBackend::new();
+
+        for name in &["/mod/a", "/mod/b/c", "/module/d"] {
+            assert!(backend.write(&PathBuf::from(name), b"").is_ok());
+        }
+
+        assert_eq!(backend.list(&PathBuf::from("/mod")).unwrap().len(), 2);
+        assert!(backend.list(&PathBuf::from("/absent")).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_is_dir() {
+        let backend = InMemoryBackend::new();
+        assert!(backend.write(&PathBuf::from("/mod/b/c"), b"").is_ok());
+        assert!(backend.create_dir_all(&PathBuf::from("/empty/dir")).is_ok());
+
+        for dir in &["/", "/mod", "/mod/b", "/empty", "/empty/dir"] {
+            assert!(backend.is_dir(&PathBuf::from(dir)));
+        }
+        for no_dir in &["/mod/b/c", "/absent", "/mo"] {
+            assert!(!backend.is_dir(&PathBuf::from(no_dir)));
+        }
+    }
+
+}
@@ -0,0 +1,94 @@
+//
+// imag - the personal information management suite for the commandline
+// Copyright (C) 2015, 2016 Matthias Beyer <mail@beyermatthias.de> and contributors
+//
+// This library is free software; you can redistribute it and/or
+// modify it under the terms of the GNU Lesser General Public
+// License as published by the Free Software Foundation; version
+// 2.1 of the License.
+//
+// This library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+// Lesser General Public License for more details.
+//
+// You should have received a copy of the GNU Lesser General Public
+// License along with this library; if not, write to the Free Software
+// Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA  02110-1301  USA
+//
+
+//! Storage backends for the store
+//!
+//! The `Store` does not access the filesystem itself, but talks to a `StorageBackend` object which
+//! is passed to `Store::new`. Two backends are shipped with this library:
+//!
+//! * `FileSystemBackend`, which stores entries as files on disk. This is what the imag binaries
+//!   use.
+//! * `InMemoryBackend`, which holds all entries in a `HashMap`. This is useful for tests, both in
+//!   this library and in crates depending on it.
+//!
+
+use std::fmt::Debug;
+use std::path::PathBuf;
+
+use store::Result;
+
+mod fs;
+mod inmemory;
+
+pub use self::fs::FileSystemBackend;
+pub use self::inmemory::InMemoryBackend;
+
+/// The interface the `Store` uses to persist entries.
+///
+/// All paths passed to the backend are full paths, as in "store location + local part of the
+/// StoreId".
+pub trait StorageBackend : Debug + Send {
+
+    /// Read the content of the file at `path`
+    ///
+    /// Returns a `StoreErrorKind::FileNotFound` error if there is no such file.
+    fn read(&self, path: &PathBuf) -> Result<Vec<u8>>;
+
+    /// Write `buf` to the file at `path`, replacing the content which is already there.
+    ///
+    /// Parent directories are created implicitely.
+    fn write(&self, path: &PathBuf, buf: &[u8]) -> Result<()>;
+
+    /// Remove the file at `path`
+    fn remove(&self, path: &PathBuf) -> Result<()>;
+
+    /// Copy the file at `from` to `to`
+    fn copy(&self, from: &PathBuf, to: &PathBuf) -> Result<()>;
+
+    /// Move the file at `from` to `to`
+    fn rename(&self, from: &PathBuf, to: &PathBuf) -> Result<()>;
+
+    /// Create the directory `path` and all its parents
+    fn create_dir_all(&self, path: &PathBuf) -> Result<()>;
+
+    /// List all files below `path`, recursively
+    ///
+    /// Listing a path where nothing exists results in an empty list, not in an error.
+    fn list(&self, path: &PathBuf) -> Result<Vec<PathBuf>>;
+
+    /// Check whether there is a file at `path`
+    fn is_file(&self, path: &PathBuf) -> bool;
+
+    /// Check whether there is a directory at `path`
+    fn is_dir(&self, path: &PathBuf) -> bool;
+
+    /// Try to acquire the exclusive lock `path` without blocking
+    ///
+    /// Returns `Ok(false)` if the lock is already held, either by another process or by this
+    /// backend. An acquired lock is held until `unlock` is called with the same path or the
+    /// backend is dropped.
+    fn try_lock(&self, path: &PathBuf) -> Result<bool>;
+
+    /// Release the lock `path`
+    ///
+    /// Releasing a lock which is not held is not an error.
+    fn unlock(&self, path: &PathBuf) -> Result<()>;
+
+}
+
@@ -35,15 +35,38 @@ pub trait NonMutableHookDataAccessor : Debug + Send {
     fn access(&self, &FileLockEntry) -> HookResult<()>;
 }
 
+/// Accessor for hooks which get executed when an entry is moved.
+///
+/// The first `StoreId` is the id the entry is moved away from, the second one is the id the entry
+/// is moved to.
+pub trait StoreIdMoveAccessor : Debug + Send {
+    fn access_move(&self, &StoreId, &StoreId) -> HookResult<()>;
+}
+
+/// Accessor for hooks which get executed once for all entries of a transaction, instead of once
+/// for each entry.
+pub trait TransactionAccessor : Debug + Send {
+    fn access_transaction(&self, &[StoreId]) -> HookResult<()>;
+}
+
 #[derive(Debug)]
 pub enum HookDataAccessor<'a> {
     StoreIdAccess(&'a StoreIdAccessor),
     MutableAccess(&'a MutableHookDataAccessor),
     NonMutableAccess(&'a NonMutableHookDataAccessor),
+    MoveAccess(&'a StoreIdMoveAccessor),
 }
 
 pub trait HookDataAccessorProvider {
     fn accessor(&self) -> HookDataAccessor;
+
+    /// The accessor which is used when the hook is executed for a transaction
+    ///
+    /// Hooks which do not provide one are executed for each entry of the transaction, with the
+    /// accessor returned by `accessor()`.
+    fn transaction_accessor(&self) -> Option<&TransactionAccessor> {
+        None
+    }
 }
 
 
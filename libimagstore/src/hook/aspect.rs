@@ -17,6 +17,8 @@
 // Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA  02110-1301  USA
 //
 
+use std::cell::RefCell;
+
 use libimagerror::trace::trace_error;
 use libimagutil::iter::FoldResult;
 
@@ -25,6 +27,7 @@ use storeid::StoreId;
 use hook::Hook;
 use hook::result::HookResult;
 use hook::accessor::{StoreIdAccessor, MutableHookDataAccessor, NonMutableHookDataAccessor};
+use hook::accessor::StoreIdMoveAccessor;
 use hook::accessor::HookDataAccessor as HDA;
 
 use hook::error::HookError as HE;
@@ -73,12 +76,14 @@ impl StoreIdAccessor for Aspect {
             return Err(HE::new(HEK::AccessTypeViolation, None));
         }
 
-        accessors.iter().fold_defresult(|accessor| {
-            let res = match accessor {
-                &HDA::StoreIdAccess(accessor) => accessor.access(id),
-                _ => unreachable!(),
-            };
-            trace_hook_errors(res)
+        self.hooks.iter().zip(accessors.iter()).fold_defresult(|(hook, accessor)| {
+            without_recursion(&**hook, &[id.clone()], || {
+                let res = match accessor {
+                    &HDA::StoreIdAccess(accessor) => accessor.access(id),
+                    _ => unreachable!(),
+                };
+                trace_hook_errors(res)
+            })
         })
     }
 }
@@ -94,22 +99,59 @@ impl MutableHookDataAccessor for Aspect {
         // More sophisticated version would check whether there are _chunks_ of
         // NonMutableAccess accessors and execute these chunks in parallel. We do not have
         // performance concerns yet, so this is okay.
-        accessors.iter().fold_defresult(|accessor| {
-            let res = match accessor {
-                &HDA::StoreIdAccess(ref accessor)    => accessor.access(fle.get_location()),
-                &HDA::NonMutableAccess(ref accessor) => accessor.access(fle),
-                &HDA::MutableAccess(ref accessor)    => {
-                    if !self.cfg.as_ref().map(|c| c.allow_mutable_hooks()).unwrap_or(false) {
-                        debug!("Apparently mutable hooks are not allowed... failing now.");
-                        return Err(HE::new(HEK::MutableHooksNotAllowed, None));
-                    }
-
-                    accessor.access_mut(fle)
+        self.hooks.iter().zip(accessors.iter()).fold_defresult(|(hook, accessor)| {
+            let id = fle.get_location().clone();
+            without_recursion(&**hook, &[id], || self.access_mut_with(accessor, fle))
+        })
+    }
+}
+
+impl Aspect {
+
+    /// Execute the hooks of this aspect for all entries of a transaction
+    ///
+    /// Hooks which provide a `TransactionAccessor` are executed once for all entries, all other
+    /// hooks are executed once per entry.
+    pub fn access_transaction(&self, fles: &mut [FileLockEntry]) -> HookResult<()> {
+        let ids : Vec<StoreId> = fles.iter().map(|fle| fle.get_location().clone()).collect();
+
+        self.hooks.iter().fold_defresult(|hook| {
+            match hook.transaction_accessor() {
+                Some(accessor) => without_recursion(&**hook, &ids, || {
+                    trace_hook_errors(accessor.access_transaction(&ids))
+                }),
+                None => {
+                    let accessor = hook.accessor();
+                    fles.iter_mut().fold_defresult(|fle| {
+                        let id = fle.get_location().clone();
+                        without_recursion(&**hook, &[id], || self.access_mut_with(&accessor, fle))
+                    })
                 },
-            };
-            trace_hook_errors(res)
+            }
         })
     }
+
+    fn access_mut_with(&self, accessor: &HDA, fle: &mut FileLockEntry) -> HookResult<()> {
+        let res = match accessor {
+            &HDA::StoreIdAccess(ref accessor)    => accessor.access(fle.get_location()),
+            &HDA::NonMutableAccess(ref accessor) => accessor.access(fle),
+            &HDA::MutableAccess(ref accessor)    => {
+                if !self.cfg.as_ref().map(|c| c.allow_mutable_hooks()).unwrap_or(false) {
+                    debug!("Apparently mutable hooks are not allowed... failing now.");
+                    return Err(HE::new(HEK::MutableHooksNotAllowed, None));
+                }
+
+                accessor.access_mut(fle)
+            },
+            &HDA::MoveAccess(_) => {
+                warn!("Denied execution of Move-Accessing Hook");
+                debug!("in MutableAccess-Aspect execution: {:?}", self);
+                return Err(HE::new(HEK::AccessTypeViolation, None));
+            },
+        };
+        trace_hook_errors(res)
+    }
+
 }
 
 impl NonMutableHookDataAccessor for Aspect {
@@ -127,16 +169,81 @@ impl NonMutableHookDataAccessor for Aspect {
             return Err(HE::new(HEK::AccessTypeViolation, None));
         }
 
-        accessors.iter().fold_defresult(|accessor| {
-            let res = match accessor {
-                &HDA::NonMutableAccess(accessor) => accessor.access(fle),
-                _ => unreachable!(),
-            };
-            trace_hook_errors(res)
+        self.hooks.iter().zip(accessors.iter()).fold_defresult(|(hook, accessor)| {
+            without_recursion(&**hook, &[fle.get_location().clone()], || {
+                let res = match accessor {
+                    &HDA::NonMutableAccess(accessor) => accessor.access(fle),
+                    _ => unreachable!(),
+                };
+                trace_hook_errors(res)
+            })
         })
     }
 }
 
+impl StoreIdMoveAccessor for Aspect {
+    fn access_move(&self, from: &StoreId, to: &StoreId) -> HookResult<()> {
+        let accessors : Vec<HDA> = self.hooks.iter().map(|h| h.accessor()).collect();
+        if !accessors.iter().all(|a| {
+            let x = is_match!(*a, HDA::MoveAccess(_));
+            if !x {
+                warn!("Denied execution of None-Move-Accessing Hook");
+                debug!("Accessor: {:?}", a);
+                debug!("in MoveAccess-Aspect execution: {:?}", self);
+            }
+            x
+        }) {
+            return Err(HE::new(HEK::AccessTypeViolation, None));
+        }
+
+        self.hooks.iter().zip(accessors.iter()).fold_defresult(|(hook, accessor)| {
+            without_recursion(&**hook, &[from.clone(), to.clone()], || {
+                let res = match accessor {
+                    &HDA::MoveAccess(accessor) => accessor.access_move(from, to),
+                    _ => unreachable!(),
+                };
+                trace_hook_errors(res)
+            })
+        })
+    }
+}
+
+thread_local! {
+    /// The hooks this thread executes at the moment, identified by their address, together with
+    /// the entries they are executed for
+    static RUNNING_HOOKS: RefCell<Vec<(usize, StoreId)>> = RefCell::new(vec![]);
+}
+
+/// Execute `f`, which executes `hook` for the entries `ids`
+///
+/// Hooks may alter other entries, which executes the hooks for these entries as well. A hook is
+/// not executed again for an entry it is executed for already, though, otherwise a hook which
+/// alters the entry it is executed for would recurse endlessly.
+fn without_recursion<F>(hook: &Hook, ids: &[StoreId], f: F) -> HookResult<()>
+    where F: FnOnce() -> HookResult<()>
+{
+    let key = hook as *const Hook as *const () as usize;
+    let running = RUNNING_HOOKS.with(|running| {
+        running.borrow().iter().any(|&(k, ref id)| k == key && ids.contains(id))
+    });
+    if running {
+        debug!("Hook {} is executed for {:?} already, not executing it recursively",
+               hook.name(), ids);
+        return Ok(());
+    }
+
+    RUNNING_HOOKS.with(|running| {
+        running.borrow_mut().extend(ids.iter().map(|id| (key, id.clone())))
+    });
+    let res = f();
+    RUNNING_HOOKS.with(|running| {
+        let mut running = running.borrow_mut();
+        let len = running.len();
+        running.truncate(len - ids.len());
+    });
+    res
+}
+
 fn trace_hook_errors(res: HookResult<()>) -> HookResult<()> {
     res.or_else(|e| {
         if !e.is_aborting() {
@@ -29,4 +29,6 @@ pub enum HookPosition {
     PostUpdate,
     PreDelete,
     PostDelete,
+    PreMove,
+    PostMove,
 }
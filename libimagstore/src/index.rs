@@ -0,0 +1,463 @@
+//
+// imag - the personal information management suite for the commandline
+// Copyright (C) 2015, 2016 Matthias Beyer <mail@beyermatthias.de> and contributors
+//
+// This library is free software; you can redistribute it and/or
+// modify it under the terms of the GNU Lesser General Public
+// License as published by the Free Software Foundation; version
+// 2.1 of the License.
+//
+// This library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+// Lesser General Public License for more details.
+//
+// You should have received a copy of the GNU Lesser General Public
+// License along with this library; if not, write to the Free Software
+// Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA  02110-1301  USA
+//
+
+//! A persistent index over the headers of all entries in the store
+//!
+//! Walking the whole store and parsing each entry is expensive. The `Index` caches the header of
+//! every entry (which includes the tags in "imag.tags" and the links in "imag.links"), so the
+//! store can answer queries like "all entries with tag X" without touching the entries at all.
+//! Both are arrays of strings, so `query_header_contains()` works on them. The types of typed
+//! links live in the "imag.typed_links" array of tables, which can only be queried with
+//! `query_header_eq()` on the complete array.
+//!
+//! The index is optional. It is enabled via the store configuration:
+//!
+//! ```toml
+//! [store.index]
+//! enabled = true
+//! path    = "/home/user/.imag/store.index" # optional
+//! ```
+//!
+//! If no path is configured, the index lives next to the store directory. The index is kept up
+//! to date by the index hook from `libimagstorestdhook` and can be rebuilt from scratch with
+//! `Store::reindex()` (`imag-store reindex`).
+//!
+//! Several processes may use the store at the same time, so the index remembers which entries it
+//! altered. When it is saved, it locks the index file, applies these changes to the index which
+//! is on disk right now and writes the result, so changes of other processes are not lost.
+//!
+
+use std::collections::BTreeMap;
+use std::ffi::OsString;
+use std::fmt::Debug;
+use std::path::PathBuf;
+use std::thread::sleep;
+use std::time::{Duration, Instant};
+
+use toml::{Parser, Value};
+
+use libimagerror::into::IntoError;
+
+use error::StoreErrorKind as SEK;
+use error::MapErrInto;
+use file_abstraction::StorageBackend;
+use storeid::StoreId;
+use store::{Entry, Result};
+
+/// How long saving the index waits for another process to release the index lock
+const LOCK_TIMEOUT_MS : u64 = 10_000;
+const LOCK_RETRY_INTERVAL_MS : u64 = 50;
+
+/// An index over the entries of the store
+///
+/// Indexes are kept up to date by the index hook (see `libimagstorestdhook::index`) and written
+/// through the `StorageBackend` of the store when the store is dropped, see
+/// `Store::register_index()`.
+pub trait StoreIndex : Debug + Send {
+
+    /// Put `entry` into the index, replacing its old version if there is one
+    fn update(&mut self, entry: &Entry) -> Result<()>;
+
+    /// Remove the entry `id` from the index
+    fn remove(&mut self, id: &StoreId) -> Result<()>;
+
+    /// Move the index data of `from` to `to`
+    fn rename(&mut self, from: &StoreId, to: &StoreId) -> Result<()>;
+
+    /// Write the changes to the index
+    fn save(&mut self, backend: &StorageBackend) -> Result<()>;
+
+}
+
+/// The index
+///
+/// Maps the local part of each `StoreId` to the header of the entry.
+#[derive(Debug)]
+pub struct Index {
+    location: PathBuf,
+    entries: BTreeMap<String, Value>,
+
+    /// The changes since the index was loaded or saved: the new header of an entry, or `None` if
+    /// the entry was removed
+    changes: BTreeMap<String, Option<Value>>,
+
+    /// Whether the index was cleared since it was loaded or saved
+    cleared: bool,
+}
+
+impl Index {
+
+    /// Create a new, empty index which will be persisted at `location`
+    pub fn new(location: PathBuf) -> Index {
+        Index {
+            location: location,
+            entries: BTreeMap::new(),
+            changes: BTreeMap::new(),
+            cleared: false,
+        }
+    }
+
+    /// Load the index from `location`
+    ///
+    /// If there is no index at `location` yet, an empty one is returned.
+    pub fn load(backend: &StorageBackend, location: PathBuf) -> Result<Index> {
+        let mut index = Index::new(location);
+        index.entries = try!(read_entries(backend, &index.location));
+        Ok(index)
+    }
+
+    /// Write the changes to the index to its location, if it was altered since it was loaded
+    ///
+    /// The index file is locked meanwhile, and the changes are applied to the index as it is on
+    /// disk right now, so changes other processes saved in the meantime are kept.
+    pub fn save(&mut self, backend: &StorageBackend) -> Result<()> {
+        if !self.cleared && self.changes.is_empty() {
+            debug!("Index not altered, not writing it");
+            return Ok(());
+        }
+
+        let lock = lock_path(&self.location);
+        try!(lock_index(backend, &lock));
+        let res = self.merge_and_write(backend);
+        try!(backend.unlock(&lock).map_err_into(SEK::IndexWriteError));
+        res
+    }
+
+    fn merge_and_write(&mut self, backend: &StorageBackend) -> Result<()> {
+        let mut entries = if self.cleared {
+            BTreeMap::new()
+        } else {
+            try!(read_entries(backend, &self.location).map_err_into(SEK::IndexWriteError))
+        };
+
+        for (key, change) in self.changes.iter() {
+            match *change {
+                Some(ref header) => { entries.insert(key.clone(), header.clone()); },
+                None             => { entries.remove(key); },
+            }
+        }
+
+        let serialized = entries
+            .iter()
+            .map(|(id, header)| {
+                let mut t = BTreeMap::new();
+                t.insert(String::from("id"), Value::String(id.clone()));
+                t.insert(String::from("header"), header.clone());
+                Value::Table(t)
+            })
+            .collect();
+
+        let mut t = BTreeMap::new();
+        t.insert(String::from("entries"), Value::Array(serialized));
+
+        try!(backend.write(&self.location, ::toml::encode_str(&Value::Table(t)).as_bytes())
+             .map_err_into(SEK::IndexWriteError));
+
+        self.entries = entries;
+        self.changes.clear();
+        self.cleared = false;
+        Ok(())
+    }
+
+    /// Get the path where this index is persisted
+    pub fn location(&self) -> &PathBuf {
+        &self.location
+    }
+
+    /// Put the header of `entry` into the index, replacing the old one if there is one
+    pub fn update(&mut self, entry: &Entry) -> Result<()> {
+        let key    = try!(index_key(entry.get_location()));
+        let header = entry.get_header().header().clone();
+        debug!("Updating index for {}", key);
+        self.entries.insert(key.clone(), header.clone());
+        self.changes.insert(key, Some(header));
+        Ok(())
+    }
+
+    /// Remove the entry `id` from the index
+    pub fn remove(&mut self, id: &StoreId) -> Result<()> {
+        let key = try!(index_key(id));
+        debug!("Removing {} from index", key);
+        self.entries.remove(&key);
+        self.changes.insert(key, None);
+        Ok(())
+    }
+
+    /// Move the header indexed for `from` to `to`
+    pub fn rename(&mut self, from: &StoreId, to: &StoreId) -> Result<()> {
+        let from = try!(index_key(from));
+        let to   = try!(index_key(to));
+        debug!("Moving {} to {} in index", from, to);
+        if let Some(header) = self.entries.remove(&from) {
+            self.entries.insert(to.clone(), header.clone());
+            self.changes.insert(to, Some(header));
+        }
+        self.changes.insert(from, None);
+        Ok(())
+    }
+
+    /// Remove everything from the index
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.changes.clear();
+        self.cleared = true;
+    }
+
+    /// Number of entries in the index
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Get the ids of all entries where the header value at `path` equals `value`
+    ///
+    /// The returned `StoreId`s have no base.
+    pub fn query_header_eq(&self, path: &str, value: &Value) -> Result<Vec<StoreId>> {
+        self.query(path, |v| v == value)
+    }
+
+    /// Get the ids of all entries where the header value at `path` is an array which contains
+    /// `value`
+    ///
+    /// This is what is used for tags and links, for example `query_header_contains("imag.tags",
+    /// &Value::String("foo"))` finds all entries tagged with "foo".
+    ///
+    /// The returned `StoreId`s have no base.
+    pub fn query_header_contains(&self, path: &str, value: &Value) -> Result<Vec<StoreId>> {
+        self.query(path, |v| match *v {
+            Value::Array(ref a) => a.contains(value),
+            _ => false,
+        })
+    }
+
+    fn query<F>(&self, path: &str, f: F) -> Result<Vec<StoreId>>
+        where F: Fn(&Value) -> bool
+    {
+        self.entries
+            .iter()
+            .filter(|&(_, header)| header.lookup(path).map(|v| f(v)).unwrap_or(false))
+            .map(|(id, _)| StoreId::new_baseless(PathBuf::from(id)))
+            .collect()
+    }
+
+}
+
+impl StoreIndex for Index {
+
+    fn update(&mut self, entry: &Entry) -> Result<()> {
+        Index::update(self, entry)
+    }
+
+    fn remove(&mut self, id: &StoreId) -> Result<()> {
+        Index::remove(self, id)
+    }
+
+    fn rename(&mut self, from: &StoreId, to: &StoreId) -> Result<()> {
+        Index::rename(self, from, to)
+    }
+
+    fn save(&mut self, backend: &StorageBackend) -> Result<()> {
+        Index::save(self, backend)
+    }
+
+}
+
+/// Read the entries of the index at `location`, or no entries if there is no index yet
+fn read_entries(backend: &StorageBackend, location: &PathBuf) -> Result<BTreeMap<String, Value>> {
+    let mut entries = BTreeMap::new();
+    if !backend.is_file(location) {
+        debug!("No index at {:?}, starting with an empty one", location);
+        return Ok(entries);
+    }
+
+    let buf = try!(backend.read(location).map_err_into(SEK::IndexLoadError));
+    let s   = try!(String::from_utf8(buf).map_err_into(SEK::EncodingError)
+                   .map_err_into(SEK::IndexLoadError));
+    let table = try!(Parser::new(&s).parse().ok_or(SEK::IndexLoadError.into_error()));
+
+    match table.get("entries") {
+        Some(&Value::Array(ref elems)) => {
+            for elem in elems {
+                let (id, header) = try!(index_elem_parts(elem));
+                entries.insert(id, header);
+            }
+            Ok(entries)
+        },
+        None    => Ok(entries),
+        Some(_) => Err(SEK::IndexLoadError.into_error()),
+    }
+}
+
+/// Path of the lock file for the index at `location`
+pub fn lock_path(location: &PathBuf) -> PathBuf {
+    let mut s = OsString::from(location.as_os_str());
+    s.push(".lock");
+    PathBuf::from(s)
+}
+
+/// Lock the index file with the lock file `lock`, waiting for other processes to release it
+///
+/// Indexes are read, merged with the changes of this process and written while holding this
+/// lock, so changes from other processes are not lost. Fails with `StoreErrorKind::LockError` if
+/// the lock cannot be acquired within a few seconds.
+pub fn lock_index(backend: &StorageBackend, lock: &PathBuf) -> Result<()> {
+    let start   = Instant::now();
+    let timeout = Duration::from_millis(LOCK_TIMEOUT_MS);
+
+    while !try!(backend.try_lock(lock).map_err_into(SEK::IndexWriteError)) {
+        if start.elapsed() >= timeout {
+            warn!("Index is locked by another process");
+            return Err(SEK::LockError.into_error()).map_err_into(SEK::IndexWriteError);
+        }
+
+        debug!("Index is locked, retrying");
+        sleep(Duration::from_millis(LOCK_RETRY_INTERVAL_MS));
+    }
+
+    Ok(())
+}
+
+fn index_key(id: &StoreId) -> Result<String> {
+    id.local()
+        .to_str()
+        .map(String::from)
+        .ok_or(SEK::EncodingError.into_error())
+}
+
+fn index_elem_parts(elem: &Value) -> Result<(String, Value)> {
+    match *elem {
+        Value::Table(ref t) => match (t.get("id"), t.get("header")) {
+            (Some(&Value::String(ref id)), Some(&Value::Table(ref header))) => {
+                Ok((id.clone(), Value::Table(header.clone())))
+            },
+            _ => Err(SEK::IndexLoadError.into_error()),
+        },
+        _ => Err(SEK::IndexLoadError.into_error()),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::BTreeMap;
+    use std::path::PathBuf;
+
+    use toml::Value;
+
+    use super::Index;
+    use file_abstraction::{InMemoryBackend, StorageBackend};
+    use storeid::StoreId;
+    use store::Entry;
+
+    fn entry(id: &str, tags: Vec<&str>) -> Entry {
+        let mut entry = Entry::new(StoreId::new_baseless(PathBuf::from(id)).unwrap());
+        let tags = tags.into_iter().map(|t| Value::String(String::from(t))).collect();
+        entry.get_header_mut().set("imag.tags", Value::Array(tags)).unwrap();
+        entry.get_header_mut().set("test", Value::Table(BTreeMap::new())).unwrap();
+        entry.get_header_mut().set("test.id", Value::String(String::from(id))).unwrap();
+        entry
+    }
+
+    fn index() -> Index {
+        let mut index = Index::new(PathBuf::from("/index"));
+        index.update(&entry("test/a", vec!["foo"])).unwrap();
+        index.update(&entry("test/b", vec!["foo", "bar"])).unwrap();
+        index.update(&entry("test/c", vec![])).unwrap();
+        index
+    }
+
+    fn id(s: &str) -> StoreId {
+        StoreId::new_baseless(PathBuf::from(s)).unwrap()
+    }
+
+    #[test]
+    fn test_query_contains() {
+        let index = index();
+        let foo = Value::String(String::from("foo"));
+        let bar = Value::String(String::from("bar"));
+
+        assert_eq!(index.query_header_contains("imag.tags", &foo).unwrap(),
+                   vec![id("test/a"), id("test/b")]);
+        assert_eq!(index.query_header_contains("imag.tags", &bar).unwrap(), vec![id("test/b")]);
+        assert!(index.query_header_contains("imag.nothing", &bar).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_query_eq() {
+        let index = index();
+        let c = Value::String(String::from("test/c"));
+
+        assert_eq!(index.query_header_eq("test.id", &c).unwrap(), vec![id("test/c")]);
+    }
+
+    #[test]
+    fn test_remove_and_rename() {
+        let mut index = index();
+        let a = Value::String(String::from("test/a"));
+
+        index.remove(&id("test/b")).unwrap();
+        index.rename(&id("test/a"), &id("test/d")).unwrap();
+
+        assert_eq!(index.len(), 2);
+        assert_eq!(index.query_header_eq("test.id", &a).unwrap(), vec![id("test/d")]);
+    }
+
+    #[test]
+    fn test_save_load() {
+        let backend = InMemoryBackend::new();
+        let mut index = index();
+        index.save(&backend).unwrap();
+        assert!(backend.is_file(&PathBuf::from("/index")));
+
+        let loaded = Index::load(&backend, PathBuf::from("/index")).unwrap();
+        let foo = Value::String(String::from("foo"));
+        assert_eq!(loaded.len(), 3);
+        assert_eq!(loaded.query_header_contains("imag.tags", &foo).unwrap(),
+                   vec![id("test/a"), id("test/b")]);
+    }
+
+    #[test]
+    fn test_load_missing() {
+        let backend = InMemoryBackend::new();
+        let index   = Index::load(&backend, PathBuf::from("/index")).unwrap();
+        assert_eq!(index.len(), 0);
+    }
+
+    #[test]
+    fn test_save_merges_concurrent_changes() {
+        let backend = InMemoryBackend::new();
+        index().save(&backend).unwrap();
+
+        // Two processes load the same index
+        let mut first  = Index::load(&backend, PathBuf::from("/index")).unwrap();
+        let mut second = Index::load(&backend, PathBuf::from("/index")).unwrap();
+
+        first.update(&entry("test/d", vec!["foo"])).unwrap();
+        first.save(&backend).unwrap();
+
+        second.remove(&id("test/a")).unwrap();
+        second.save(&backend).unwrap();
+
+        let loaded = Index::load(&backend, PathBuf::from("/index")).unwrap();
+        let foo = Value::String(String::from("foo"));
+        assert_eq!(loaded.len(), 3);
+        assert_eq!(loaded.query_header_contains("imag.tags", &foo).unwrap(),
+                   vec![id("test/b"), id("test/d")]);
+        assert_eq!(second.len(), 3);
+    }
+
+}
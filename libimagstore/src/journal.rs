@@ -0,0 +1,357 @@
+//
+// imag - the personal information management suite for the commandline
+// Copyright (C) 2015, 2016 Matthias Beyer <mail@beyermatthias.de> and contributors
+//
+// This library is free software; you can redistribute it and/or
+// modify it under the terms of the GNU Lesser General Public
+// License as published by the Free Software Foundation; version
+// 2.1 of the License.
+//
+// This library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+// Lesser General Public License for more details.
+//
+// You should have received a copy of the GNU Lesser General Public
+// License along with this library; if not, write to the Free Software
+// Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA  02110-1301  USA
+//
+
+//! The journal used to commit transactions atomically
+//!
+//! A transaction is committed in these steps:
+//!
+//! 1. The journal is written in the "prepared" state, listing all entry files the transaction
+//!    writes to.
+//! 2. The new content of each entry is written to a temporary file next to the entry file.
+//! 3. The journal is rewritten in the "committed" state.
+//! 4. Each temporary file is renamed to the entry file.
+//! 5. The journal is removed.
+//!
+//! If the process dies before step 3, the temporary files are removed on recovery and the store
+//! is left as it was before the transaction. If it dies after step 3, the remaining renames are
+//! done on recovery, so the transaction is completed.
+//!
+//! Each transaction has its own journal file in the journal directory. The journal is locked
+//! while the transaction is in progress, so a process opening the store only recovers journals
+//! of transactions which were interrupted, never the ones another process is committing right now.
+//!
+
+use std::ffi::OsString;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering, ATOMIC_USIZE_INIT};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use toml::{Parser, Value};
+
+use libimagerror::into::IntoError;
+
+use error::StoreErrorKind as SEK;
+use error::MapErrInto;
+use file_abstraction::StorageBackend;
+use store::Result;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum JournalState {
+    Prepared,
+    Committed,
+}
+
+impl JournalState {
+
+    fn as_str(&self) -> &'static str {
+        match *self {
+            JournalState::Prepared  => "prepared",
+            JournalState::Committed => "committed",
+        }
+    }
+
+    fn from_str(s: &str) -> Option<JournalState> {
+        match s {
+            "prepared"  => Some(JournalState::Prepared),
+            "committed" => Some(JournalState::Committed),
+            _           => None,
+        }
+    }
+
+}
+
+/// The journal of one transaction
+#[derive(Debug)]
+pub struct Journal {
+    location: PathBuf,
+    targets: Vec<PathBuf>,
+}
+
+impl Journal {
+
+    /// Create a new, empty journal which is persisted at `location`
+    pub fn new(location: PathBuf) -> Journal {
+        Journal {
+            location: location,
+            targets: vec![],
+        }
+    }
+
+    /// Create a new, empty journal with a unique name in the journal directory `dir`
+    ///
+    /// The journal is locked until the transaction is committed or aborted.
+    pub fn create(backend: &StorageBackend, dir: &PathBuf) -> Result<Journal> {
+        loop {
+            let location = dir.join(unique_name());
+            let lock     = Journal::lock_path(&location);
+
+            if try!(backend.try_lock(&lock)) {
+                if !backend.is_file(&location) {
+                    debug!("Created journal {:?}", location);
+                    return Ok(Journal::new(location));
+                }
+                try!(backend.unlock(&lock));
+            }
+        }
+    }
+
+    /// Add the entry file `target` to the journal
+    pub fn push(&mut self, target: PathBuf) {
+        self.targets.push(target);
+    }
+
+    /// Get the temporary file the new content of `target` is written to
+    pub fn temp_path(target: &PathBuf) -> PathBuf {
+        let mut s = OsString::from(target.as_os_str());
+        s.push(".imag-transaction");
+        PathBuf::from(s)
+    }
+
+    /// Write the journal in "prepared" state
+    ///
+    /// This must happen before any temporary file is written.
+    pub fn prepare(&self, backend: &StorageBackend) -> Result<()> {
+        self.write(backend, JournalState::Prepared)
+    }
+
+    /// Write the journal in "committed" state and move all temporary files to their targets
+    ///
+    /// This must happen after all temporary files were written.
+    pub fn commit(&self, backend: &StorageBackend) -> Result<()> {
+        try!(self.write(backend, JournalState::Committed));
+        self.finish(backend, JournalState::Committed)
+    }
+
+    /// Remove all temporary files which were written so far and the journal itself
+    pub fn abort(&self, backend: &StorageBackend) -> Result<()> {
+        self.finish(backend, JournalState::Prepared)
+    }
+
+    /// Recover from all transactions in the journal directory `dir` which were interrupted
+    ///
+    /// Journals which are locked belong to a transaction another process is committing right
+    /// now and are left alone.
+    pub fn recover(backend: &StorageBackend, dir: &PathBuf) -> Result<()> {
+        let journals = try!(backend.list(dir)).into_iter().filter(|p| !Journal::is_lock_path(p));
+
+        for location in journals {
+            if !try!(backend.try_lock(&Journal::lock_path(&location))) {
+                debug!("Journal {:?} belongs to a running transaction", location);
+                continue;
+            }
+
+            if !backend.is_file(&location) {
+                debug!("Journal {:?} was finished meanwhile", location);
+                try!(backend.unlock(&Journal::lock_path(&location)));
+                continue;
+            }
+
+            warn!("Found transaction journal at {:?}, recovering", location);
+            let (journal, state) = try!(Journal::read(backend, location));
+            try!(journal.finish(backend, state));
+        }
+
+        Ok(())
+    }
+
+    fn lock_path(location: &PathBuf) -> PathBuf {
+        location.with_extension("lock")
+    }
+
+    fn is_lock_path(path: &PathBuf) -> bool {
+        path.extension().map(|e| e == "lock").unwrap_or(false)
+    }
+
+    fn finish(&self, backend: &StorageBackend, state: JournalState) -> Result<()> {
+        for target in &self.targets {
+            let temp = Journal::temp_path(target);
+            if !backend.is_file(&temp) {
+                continue;
+            }
+
+            match state {
+                JournalState::Committed => {
+                    debug!("Moving {:?} -> {:?}", temp, target);
+                    try!(backend.rename(&temp, target).map_err_into(SEK::JournalError));
+                },
+                JournalState::Prepared => {
+                    debug!("Removing {:?}", temp);
+                    try!(backend.remove(&temp).map_err_into(SEK::JournalError));
+                },
+            }
+        }
+
+        try!(backend.remove(&self.location).map_err_into(SEK::JournalError));
+        backend.unlock(&Journal::lock_path(&self.location))
+    }
+
+    fn write(&self, backend: &StorageBackend, state: JournalState) -> Result<()> {
+        let targets = try!(self.targets
+            .iter()
+            .map(|t| {
+                t.to_str()
+                    .map(|s| Value::String(String::from(s)))
+                    .ok_or(SEK::EncodingError.into_error())
+            })
+            .collect::<Result<Vec<Value>>>());
+
+        let mut table = ::std::collections::BTreeMap::new();
+        table.insert(String::from("state"), Value::String(String::from(state.as_str())));
+        table.insert(String::from("targets"), Value::Array(targets));
+
+        let s = ::toml::encode_str(&Value::Table(table));
+        backend.write(&self.location, s.as_bytes()).map_err_into(SEK::JournalError)
+    }
+
+    fn read(backend: &StorageBackend, location: PathBuf) -> Result<(Journal, JournalState)> {
+        let buf   = try!(backend.read(&location).map_err_into(SEK::JournalError));
+        let s     = try!(String::from_utf8(buf).map_err_into(SEK::JournalError));
+        let table = try!(Parser::new(&s).parse().ok_or(SEK::JournalError.into_error()));
+
+        let state = match table.get("state") {
+            Some(&Value::String(ref s)) => JournalState::from_str(s),
+            _ => None,
+        };
+        let state = try!(state.ok_or(SEK::JournalError.into_error()));
+
+        let mut journal = Journal::new(location);
+        match table.get("targets") {
+            Some(&Value::Array(ref targets)) => for target in targets {
+                match *target {
+                    Value::String(ref s) => journal.push(PathBuf::from(s)),
+                    _ => return Err(SEK::JournalError.into_error()),
+                }
+            },
+            _ => return Err(SEK::JournalError.into_error()),
+        }
+
+        Ok((journal, state))
+    }
+
+}
+
+/// A journal name which is unique for this process and, very likely, for all processes
+fn unique_name() -> String {
+    static COUNTER : AtomicUsize = ATOMIC_USIZE_INIT;
+
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| (d.as_secs(), d.subsec_nanos()))
+        .unwrap_or((0, 0));
+    format!("{}-{}-{}", now.0, now.1, COUNTER.fetch_add(1, Ordering::SeqCst))
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::PathBuf;
+
+    use super::Journal;
+    use super::JournalState;
+    use file_abstraction::{InMemoryBackend, StorageBackend};
+
+    fn setup(backend: &InMemoryBackend) -> Journal {
+        let mut journal = Journal::new(PathBuf::from("/journal/tx"));
+        for name in &["/store/a", "/store/b"] {
+            let target = PathBuf::from(name);
+            backend.write(&target, b"old").unwrap();
+            journal.push(target);
+        }
+        journal.prepare(backend).unwrap();
+        for name in &["/store/a", "/store/b"] {
+            backend.write(&Journal::temp_path(&PathBuf::from(name)), b"new").unwrap();
+        }
+        journal
+    }
+
+    #[test]
+    fn test_commit() {
+        let backend = InMemoryBackend::new();
+        let journal = setup(&backend);
+
+        journal.commit(&backend).unwrap();
+
+        assert!(!backend.is_file(&PathBuf::from("/journal/tx")));
+        assert_eq!(backend.read(&PathBuf::from("/store/a")).unwrap(), b"new");
+        assert_eq!(backend.read(&PathBuf::from("/store/b")).unwrap(), b"new");
+        assert_eq!(backend.list(&PathBuf::from("/store")).unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_recover_prepared() {
+        let backend = InMemoryBackend::new();
+        let _ = setup(&backend);
+
+        Journal::recover(&backend, &PathBuf::from("/journal")).unwrap();
+
+        assert!(!backend.is_file(&PathBuf::from("/journal/tx")));
+        assert_eq!(backend.read(&PathBuf::from("/store/a")).unwrap(), b"old");
+        assert_eq!(backend.read(&PathBuf::from("/store/b")).unwrap(), b"old");
+        assert_eq!(backend.list(&PathBuf::from("/store")).unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_recover_committed() {
+        let backend = InMemoryBackend::new();
+        let journal = setup(&backend);
+
+        // Simulate a crash after the first rename
+        journal.write(&backend, JournalState::Committed).unwrap();
+        let a = PathBuf::from("/store/a");
+        backend.rename(&Journal::temp_path(&a), &a).unwrap();
+
+        Journal::recover(&backend, &PathBuf::from("/journal")).unwrap();
+
+        assert!(!backend.is_file(&PathBuf::from("/journal/tx")));
+        assert_eq!(backend.read(&PathBuf::from("/store/a")).unwrap(), b"new");
+        assert_eq!(backend.read(&PathBuf::from("/store/b")).unwrap(), b"new");
+        assert_eq!(backend.list(&PathBuf::from("/store")).unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_recover_without_journal() {
+        let backend = InMemoryBackend::new();
+        assert!(Journal::recover(&backend, &PathBuf::from("/journal")).is_ok());
+    }
+
+    #[test]
+    fn test_recover_skips_locked_journal() {
+        let backend = InMemoryBackend::new();
+        let _ = setup(&backend);
+
+        // Simulate a transaction which is still in progress
+        assert!(backend.try_lock(&Journal::lock_path(&PathBuf::from("/journal/tx"))).unwrap());
+
+        Journal::recover(&backend, &PathBuf::from("/journal")).unwrap();
+
+        assert!(backend.is_file(&PathBuf::from("/journal/tx")));
+        assert_eq!(backend.list(&PathBuf::from("/store")).unwrap().len(), 4);
+    }
+
+    #[test]
+    fn test_create_unique() {
+        let backend = InMemoryBackend::new();
+        let dir     = PathBuf::from("/journal");
+
+        let a = Journal::create(&backend, &dir).unwrap();
+        let b = Journal::create(&backend, &dir).unwrap();
+        assert!(a.location != b.location);
+        assert!(a.location.starts_with(&dir));
+    }
+
+}
@@ -34,7 +34,6 @@
 #[macro_use] extern crate log;
 #[macro_use] extern crate version;
 extern crate fs2;
-extern crate glob;
 #[macro_use] extern crate lazy_static;
 extern crate regex;
 extern crate toml;
@@ -50,6 +49,10 @@ pub mod storeid;
 pub mod error;
 pub mod hook;
 pub mod store;
+pub mod file_abstraction;
+pub mod index;
+mod journal;
+pub mod migration;
+pub mod schema;
 mod configuration;
-mod file_abstraction;
 
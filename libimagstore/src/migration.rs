@@ -0,0 +1,249 @@
+//
+// imag - the personal information management suite for the commandline
+// Copyright (C) 2015, 2016 Matthias Beyer <mail@beyermatthias.de> and contributors
+//
+// This library is free software; you can redistribute it and/or
+// modify it under the terms of the GNU Lesser General Public
+// License as published by the Free Software Foundation; version
+// 2.1 of the License.
+//
+// This library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+// Lesser General Public License for more details.
+//
+// You should have received a copy of the GNU Lesser General Public
+// License along with this library; if not, write to the Free Software
+// Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA  02110-1301  USA
+//
+
+//! Migrations of the store format
+//!
+//! Every entry has the version of imag it was written with in its header ("imag.version"). If a
+//! module changes the layout of its entries, it can register a `Migration` with the store, which
+//! transforms entries from an older version to a newer one. Migrations are applied when an entry
+//! is retrieved from the store, or for all entries at once with `Store::migrate()`
+//! (`imag-store migrate`).
+//!
+//! A migration from version `from` to version `to` is applied to all entries with a version
+//! `from <= version < to`. If several migrations apply, the one with the lowest `to` runs first.
+//! After each migration, "imag.version" of the entry is set to `to`, so the migrations are
+//! chained until none applies anymore.
+//!
+
+use std::fmt::{Debug, Display, Formatter};
+use std::fmt::Error as FmtError;
+use std::result::Result as RResult;
+
+use semver::Version;
+use toml::Value;
+
+use libimagerror::into::IntoError;
+
+use error::StoreErrorKind as SEK;
+use error::MapErrInto;
+use storeid::StoreId;
+use store::{Entry, Result};
+
+/// A transformation of entries from one version to another
+pub struct Migration {
+    name: String,
+    from: Version,
+    to: Version,
+    transform: Box<Fn(&mut Entry) -> Result<()> + Send + Sync>,
+}
+
+impl Migration {
+
+    /// Create a new migration named `name`, which transforms entries from `from` to `to` with
+    /// `transform`
+    ///
+    /// Returns a `StoreErrorKind::MigrationInvalid` error if `from` is not lower than `to`.
+    pub fn new<S, F>(name: S, from: Version, to: Version, transform: F) -> Result<Migration>
+        where S: Into<String>,
+              F: Fn(&mut Entry) -> Result<()> + Send + Sync + 'static
+    {
+        if from >= to {
+            return Err(SEK::MigrationInvalid.into_error());
+        }
+
+        Ok(Migration {
+            name: name.into(),
+            from: from,
+            to: to,
+            transform: Box::new(transform),
+        })
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn from_version(&self) -> &Version {
+        &self.from
+    }
+
+    pub fn to_version(&self) -> &Version {
+        &self.to
+    }
+
+    fn applies_to(&self, version: &Version) -> bool {
+        self.from <= *version && *version < self.to
+    }
+
+}
+
+impl Debug for Migration {
+
+    fn fmt(&self, fmt: &mut Formatter) -> RResult<(), FmtError> {
+        write!(fmt, "Migration({}: {} -> {})", self.name, self.from, self.to)
+    }
+
+}
+
+/// A migration which was (or, in a dry run, would be) applied to an entry
+#[derive(Debug, Clone)]
+pub struct MigrationStep {
+    id: StoreId,
+    name: String,
+    from: Version,
+    to: Version,
+}
+
+impl MigrationStep {
+
+    pub fn id(&self) -> &StoreId {
+        &self.id
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn from_version(&self) -> &Version {
+        &self.from
+    }
+
+    pub fn to_version(&self) -> &Version {
+        &self.to
+    }
+
+}
+
+impl Display for MigrationStep {
+
+    fn fmt(&self, fmt: &mut Formatter) -> RResult<(), FmtError> {
+        write!(fmt, "{}: {} ({} -> {})", self.id, self.name, self.from, self.to)
+    }
+
+}
+
+/// Apply all `migrations` which apply to `entry`, in order
+///
+/// Returns the steps which were applied. If a migration fails, the entry might be partially
+/// migrated, so it must not be written in this case.
+pub fn apply_migrations(migrations: &[Migration], entry: &mut Entry) -> Result<Vec<MigrationStep>> {
+    let mut steps = vec![];
+
+    loop {
+        let version = try!(entry_version(entry));
+        let next = migrations
+            .iter()
+            .filter(|m| m.applies_to(&version))
+            .fold(None, |acc: Option<&Migration>, m| match acc {
+                Some(a) if a.to <= m.to => Some(a),
+                _ => Some(m),
+            });
+
+        let migration = match next {
+            None    => return Ok(steps),
+            Some(m) => m,
+        };
+
+        debug!("Migrating {} with {:?}", entry.get_location(), migration);
+        try!((migration.transform)(entry).map_err_into(SEK::MigrationError));
+        try!(entry.get_header_mut()
+             .set("imag.version", Value::String(format!("{}", migration.to)))
+             .map_err_into(SEK::MigrationError));
+
+        steps.push(MigrationStep {
+            id: entry.get_location().clone(),
+            name: migration.name.clone(),
+            from: version,
+            to: migration.to.clone(),
+        });
+    }
+}
+
+fn entry_version(entry: &Entry) -> Result<Version> {
+    match entry.get_header().read("imag.version") {
+        Ok(Some(Value::String(s))) => {
+            Version::parse(&s).map_err(|_| SEK::MigrationError.into_error())
+        },
+        Ok(_)  => Err(SEK::HeaderKeyNotFound.into_error()).map_err_into(SEK::MigrationError),
+        Err(e) => Err(e).map_err_into(SEK::MigrationError),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::BTreeMap;
+    use std::path::PathBuf;
+
+    use semver::Version;
+    use toml::Value;
+
+    use super::{Migration, apply_migrations};
+    use storeid::StoreId;
+    use store::Entry;
+
+    fn entry(version: &str) -> Entry {
+        let mut entry = Entry::new(StoreId::new_baseless(PathBuf::from("test")).unwrap());
+        entry.get_header_mut().set("imag.version", Value::String(String::from(version))).unwrap();
+        entry
+    }
+
+    fn migration(name: &str, from: &str, to: &str) -> Migration {
+        let key = format!("test.{}", name);
+        Migration::new(name, Version::parse(from).unwrap(), Version::parse(to).unwrap(), move |e| {
+            if try!(e.get_header().read("test")).is_none() {
+                try!(e.get_header_mut().set("test", Value::Table(BTreeMap::new())));
+            }
+            e.get_header_mut().set(&key, Value::Boolean(true)).map(|_| ())
+        }).unwrap()
+    }
+
+    #[test]
+    fn test_invalid_migration() {
+        let v = Version::parse("0.2.0").unwrap();
+        assert!(Migration::new("invalid", v.clone(), v, |_| Ok(())).is_err());
+    }
+
+    #[test]
+    fn test_migrations_are_chained() {
+        let migrations = vec![
+            migration("second", "0.2.0", "0.3.0"),
+            migration("first", "0.1.0", "0.2.0"),
+            migration("unrelated", "1.0.0", "2.0.0"),
+        ];
+        let mut entry = entry("0.1.5");
+
+        let steps = apply_migrations(&migrations, &mut entry).unwrap();
+        let names : Vec<&str> = steps.iter().map(|s| s.name()).collect();
+
+        assert_eq!(names, vec!["first", "second"]);
+        assert_eq!(entry.get_header().read("imag.version").unwrap(),
+                   Some(Value::String(String::from("0.3.0"))));
+        assert_eq!(entry.get_header().read("test.first").unwrap(), Some(Value::Boolean(true)));
+        assert_eq!(entry.get_header().read("test.unrelated").unwrap(), None);
+    }
+
+    #[test]
+    fn test_up_to_date_entry() {
+        let migrations = vec![migration("first", "0.1.0", "0.2.0")];
+        let mut entry  = entry("0.2.0");
+
+        assert!(apply_migrations(&migrations, &mut entry).unwrap().is_empty());
+    }
+
+}
@@ -0,0 +1,279 @@
+//
+// imag - the personal information management suite for the commandline
+// Copyright (C) 2015, 2016 Matthias Beyer <mail@beyermatthias.de> and contributors
+//
+// This library is free software; you can redistribute it and/or
+// modify it under the terms of the GNU Lesser General Public
+// License as published by the Free Software Foundation; version
+// 2.1 of the License.
+//
+// This library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+// Lesser General Public License for more details.
+//
+// You should have received a copy of the GNU Lesser General Public
+// License along with this library; if not, write to the Free Software
+// Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA  02110-1301  USA
+//
+
+//! Header schemas
+//!
+//! Modules own a section of the entry header (libimagcounter owns "counter", libimagtodo owns
+//! "todo", ...). A `Schema` describes which fields a module expects in its section and which type
+//! they have. Schemas are registered with `Store::register_schema()`, and every entry which has
+//! the section is validated against the schema when it is written to the store and by
+//! `Store::verify()`.
+//!
+//! ```ignore
+//!  let schema = Schema::new("counter")
+//!      .required("name", FieldType::String)
+//!      .required("value", FieldType::Integer)
+//!      .optional("unit", FieldType::String);
+//! ```
+//!
+
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use std::fmt::Error as FmtError;
+
+use toml::Value;
+
+use libimagerror::into::IntoError;
+
+use error::StoreErrorKind as SEK;
+use store::{EntryHeader, Result};
+
+/// The type a header field is expected to have
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FieldType {
+    String,
+    Integer,
+    Float,
+    Boolean,
+    Datetime,
+    Array,
+    Table,
+}
+
+impl FieldType {
+
+    fn matches(&self, v: &Value) -> bool {
+        match (*self, v) {
+            (FieldType::String,   &Value::String(_))   => true,
+            (FieldType::Integer,  &Value::Integer(_))  => true,
+            (FieldType::Float,    &Value::Float(_))    => true,
+            (FieldType::Boolean,  &Value::Boolean(_))  => true,
+            (FieldType::Datetime, &Value::Datetime(_)) => true,
+            (FieldType::Array,    &Value::Array(_))    => true,
+            (FieldType::Table,    &Value::Table(_))    => true,
+            _ => false,
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match *self {
+            FieldType::String   => "String",
+            FieldType::Integer  => "Integer",
+            FieldType::Float    => "Float",
+            FieldType::Boolean  => "Boolean",
+            FieldType::Datetime => "Datetime",
+            FieldType::Array    => "Array",
+            FieldType::Table    => "Table",
+        }
+    }
+
+}
+
+#[derive(Debug, Clone)]
+struct Field {
+    path: String,
+    kind: FieldType,
+    required: bool,
+}
+
+/// The expected shape of one section of the header
+#[derive(Debug, Clone)]
+pub struct Schema {
+    section: String,
+    fields: Vec<Field>,
+}
+
+impl Schema {
+
+    /// Create a schema for the header section `section`, without any fields
+    pub fn new<S: Into<String>>(section: S) -> Schema {
+        Schema {
+            section: section.into(),
+            fields: vec![],
+        }
+    }
+
+    /// The header section this schema describes
+    pub fn section(&self) -> &str {
+        &self.section
+    }
+
+    /// Expect the field `path` (relative to the section) to be present and of type `kind`
+    pub fn required<S: Into<String>>(self, path: S, kind: FieldType) -> Schema {
+        self.field(path.into(), kind, true)
+    }
+
+    /// Expect the field `path` (relative to the section) to be of type `kind`, if it is present
+    pub fn optional<S: Into<String>>(self, path: S, kind: FieldType) -> Schema {
+        self.field(path.into(), kind, false)
+    }
+
+    fn field(mut self, path: String, kind: FieldType, required: bool) -> Schema {
+        self.fields.push(Field {
+            path: path,
+            kind: kind,
+            required: required,
+        });
+        self
+    }
+
+    /// Validate `header` against this schema
+    ///
+    /// Headers without the section of this schema are always valid. Otherwise, the first field
+    /// which does not match the schema results in a `StoreErrorKind::HeaderSchemaViolation` error,
+    /// caused by a `SchemaViolation` naming the field.
+    pub fn validate(&self, header: &EntryHeader) -> Result<()> {
+        let section = match header.header().lookup(&self.section) {
+            None    => return Ok(()),
+            Some(s) => s,
+        };
+
+        if !FieldType::Table.matches(section) {
+            return Err(violation(self.section.clone(), FieldType::Table, false));
+        }
+
+        for field in &self.fields {
+            let path = format!("{}.{}", self.section, field.path);
+            match header.header().lookup(&path) {
+                None if field.required => return Err(violation(path, field.kind, true)),
+                Some(v) if !field.kind.matches(v) => return Err(violation(path, field.kind, false)),
+                _ => {},
+            }
+        }
+
+        Ok(())
+    }
+
+}
+
+fn violation(path: String, expected: FieldType, missing: bool) -> ::error::StoreError {
+    let v = SchemaViolation::new(path, expected, missing);
+    SEK::HeaderSchemaViolation.into_error_with_cause(Box::new(v))
+}
+
+/// A header field which does not match its schema
+#[derive(Debug, Clone)]
+pub struct SchemaViolation {
+    path: String,
+    msg: String,
+}
+
+impl SchemaViolation {
+
+    fn new(path: String, expected: FieldType, missing: bool) -> SchemaViolation {
+        let msg = if missing {
+            format!("Header field '{}' is missing, expected {}", path, expected.name())
+        } else {
+            format!("Header field '{}' has the wrong type, expected {}", path, expected.name())
+        };
+
+        SchemaViolation {
+            path: path,
+            msg: msg,
+        }
+    }
+
+    /// The path of the offending header field
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+}
+
+impl Display for SchemaViolation {
+
+    fn fmt(&self, fmt: &mut Formatter) -> ::std::result::Result<(), FmtError> {
+        write!(fmt, "{}", self.msg)
+    }
+
+}
+
+impl Error for SchemaViolation {
+
+    fn description(&self) -> &str {
+        &self.msg
+    }
+
+}
+
+#[cfg(test)]
+mod test {
+    use std::error::Error;
+
+    use toml::Value;
+
+    use super::{FieldType, Schema};
+    use error::StoreErrorKind as SEK;
+    use store::EntryHeader;
+
+    fn schema() -> Schema {
+        Schema::new("counter")
+            .required("name", FieldType::String)
+            .required("value", FieldType::Integer)
+            .optional("unit", FieldType::String)
+    }
+
+    fn header(name: Option<Value>, value: Option<Value>, unit: Option<Value>) -> EntryHeader {
+        let mut header = EntryHeader::new();
+        header.set("counter", Value::Table(::std::collections::BTreeMap::new())).unwrap();
+        for (k, v) in vec![("counter.name", name), ("counter.value", value), ("counter.unit", unit)] {
+            if let Some(v) = v {
+                header.set(k, v).unwrap();
+            }
+        }
+        header
+    }
+
+    fn violation_message(header: &EntryHeader) -> String {
+        let e = schema().validate(header).unwrap_err();
+        assert_eq!(e.err_type(), SEK::HeaderSchemaViolation);
+        String::from(e.cause().unwrap().description())
+    }
+
+    #[test]
+    fn test_valid() {
+        let name = Some(Value::String(String::from("c")));
+        let unit = Some(Value::String(String::from("km")));
+
+        assert!(schema().validate(&header(name.clone(), Some(Value::Integer(1)), None)).is_ok());
+        assert!(schema().validate(&header(name, Some(Value::Integer(1)), unit)).is_ok());
+    }
+
+    #[test]
+    fn test_section_absent() {
+        assert!(schema().validate(&EntryHeader::new()).is_ok());
+    }
+
+    #[test]
+    fn test_missing_field() {
+        let h = header(Some(Value::String(String::from("c"))), None, None);
+        assert_eq!(violation_message(&h),
+                   "Header field 'counter.value' is missing, expected Integer");
+    }
+
+    #[test]
+    fn test_wrong_type() {
+        let h = header(Some(Value::String(String::from("c"))),
+                       Some(Value::Integer(1)),
+                       Some(Value::Integer(2)));
+        assert_eq!(violation_message(&h),
+                   "Header field 'counter.unit' has the wrong type, expected String");
+    }
+
+}
@@ -24,6 +24,8 @@ use std::result::Result as RResult;
 use std::sync::Arc;
 use std::sync::RwLock;
 use std::collections::BTreeMap;
+use std::collections::BTreeSet;
+use std::collections::VecDeque;
 use std::io::Read;
 use std::convert::From;
 use std::convert::Into;
@@ -33,24 +35,28 @@ use std::ops::DerefMut;
 use std::fmt::Formatter;
 use std::fmt::Debug;
 use std::fmt::Error as FMTError;
+use std::thread::sleep;
+use std::time::{Duration, Instant};
 
 use toml::{Table, Value};
 use regex::Regex;
-use glob::glob;
-use walkdir::WalkDir;
-use walkdir::Iter as WalkDirIter;
 
 use error::{ParserErrorKind, ParserError};
 use error::{StoreError as SE, StoreErrorKind as SEK};
 use error::MapErrInto;
 use storeid::{IntoStoreId, StoreId, StoreIdIterator};
-use file_abstraction::FileAbstraction;
+use file_abstraction::StorageBackend;
+use index::{Index, StoreIndex};
+use journal::Journal;
+use schema::Schema;
+use migration::{Migration, MigrationStep, apply_migrations};
 
 use hook::aspect::Aspect;
 use hook::error::HookErrorKind;
 use hook::result::HookResult;
 use hook::accessor::{ MutableHookDataAccessor,
-            StoreIdAccessor};
+            StoreIdAccessor,
+            StoreIdMoveAccessor};
 use hook::position::HookPosition;
 use hook::Hook;
 
@@ -58,11 +64,25 @@ use libimagerror::into::IntoError;
 use libimagerror::trace::trace_error;
 use libimagutil::iter::FoldResult;
 
-use self::glob_store_iter::*;
-
 /// The Result Type returned by any interaction with the store that could fail
 pub type Result<T> = RResult<T, SE>;
 
+/// What to do if an entry is locked by another process
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LockPolicy {
+    /// Fail with `StoreErrorKind::EntryLockedByOtherProcess` immediately
+    Fail,
+
+    /// Wait for the lock, but fail if it was not released within the timeout
+    Wait(Duration),
+
+    /// Wait until the lock is released
+    Block,
+}
+
+/// Interval in which a lock held by another process is retried
+const LOCK_RETRY_INTERVAL_MS : u64 = 50;
+
 
 #[derive(Debug, PartialEq)]
 enum StoreEntryStatus {
@@ -75,7 +95,6 @@ enum StoreEntryStatus {
 #[derive(Debug)]
 struct StoreEntry {
     id: StoreId,
-    file: FileAbstraction,
     status: StoreEntryStatus,
 }
 
@@ -84,28 +103,42 @@ pub enum StoreObject {
     Collection(PathBuf),
 }
 
+/// Iterator over all collections and entries below a module
+///
+/// The listing is done through the `StorageBackend` of the store, so walking works for all
+/// backends. Each collection is yielded right before the first entry inside it. Hidden files and
+/// directories, like the `.git` directory of the store, are skipped.
 pub struct Walk {
     store_path: PathBuf,
-    dirwalker: WalkDirIter,
+    module_path: PathBuf,
+    files: ::std::vec::IntoIter<PathBuf>,
+    collections: BTreeSet<PathBuf>,
+    pending: VecDeque<StoreObject>,
 }
 
 impl Walk {
 
-    fn new(mut store_path: PathBuf, mod_name: &str) -> Walk {
-        let pb = store_path.clone();
-        store_path.push(mod_name);
-        Walk {
-            store_path: pb,
-            dirwalker: WalkDir::new(store_path).into_iter(),
-        }
-    }
-}
+    fn new(store_path: PathBuf, mod_name: &str, backend: &StorageBackend) -> Walk {
+        let mut module_path = store_path.clone();
+        module_path.push(mod_name);
 
-impl ::std::ops::Deref for Walk {
-    type Target = WalkDirIter;
+        let mut files = match backend.list(&module_path) {
+            Ok(files) => files,
+            Err(e) => {
+                warn!("Error in Walker");
+                trace_error(&e);
+                vec![]
+            },
+        };
+        files.sort();
 
-    fn deref(&self) -> &Self::Target {
-        &self.dirwalker
+        Walk {
+            store_path: store_path,
+            module_path: module_path,
+            files: files.into_iter(),
+            collections: BTreeSet::new(),
+            pending: VecDeque::new(),
+        }
     }
 }
 
@@ -113,41 +146,65 @@ impl Iterator for Walk {
     type Item = StoreObject;
 
     fn next(&mut self) -> Option<Self::Item> {
-        while let Some(something) = self.dirwalker.next() {
-            match something {
-                Ok(next) => if next.file_type().is_dir() {
-                                return Some(StoreObject::Collection(next.path().to_path_buf()))
-                            } else if next.file_type().is_file() {
-                                let n   = next.path().to_path_buf();
-                                let sid = match StoreId::new(Some(self.store_path.clone()), n) {
-                                    Err(e) => {
-                                        trace_error(&e);
-                                        continue;
-                                    },
-                                    Ok(o) => o,
-                                };
-                                return Some(StoreObject::Id(sid))
-                            },
-                Err(e) => {
-                    warn!("Error in Walker");
-                    debug!("{:?}", e);
-                    return None;
+        loop {
+            if let Some(object) = self.pending.pop_front() {
+                return Some(object);
+            }
+
+            let file = match self.files.next() {
+                Some(file) => file,
+                None       => return None,
+            };
+
+            if is_hidden(&self.store_path, &file) {
+                debug!("Skipping {:?} while walking", file);
+                continue;
+            }
+
+            {
+                let mut new_collections = vec![];
+                let mut parent = file.parent();
+                while let Some(p) = parent {
+                    if !p.starts_with(&self.module_path) || self.collections.contains(p) {
+                        break;
+                    }
+                    new_collections.push(p.to_path_buf());
+                    parent = p.parent();
+                }
+
+                for collection in new_collections.into_iter().rev() {
+                    self.collections.insert(collection.clone());
+                    self.pending.push_back(StoreObject::Collection(collection));
                 }
             }
-        }
 
-        return None;
+            match StoreId::from_full_path(&self.store_path, file) {
+                Ok(id) => self.pending.push_back(StoreObject::Id(id)),
+                Err(e) => trace_error(&e),
+            }
+        }
     }
 }
 
+/// Whether a file inside the store or one of its parent directories inside the store is hidden
+fn is_hidden(store_path: &PathBuf, file: &PathBuf) -> bool {
+    file.strip_prefix(store_path)
+        .map(|local| {
+            local.components().any(|c| {
+                c.as_os_str().to_str().map(|s| s.starts_with(".")).unwrap_or(false)
+            })
+        })
+        .unwrap_or(false)
+}
+
 
 impl StoreEntry {
 
     fn new(id: StoreId) -> Result<StoreEntry> {
-        let pb = try!(id.clone().into_pathbuf());
+        // Fail early if the id cannot be transformed into a path
+        let _ = try!(id.clone().into_pathbuf());
         Ok(StoreEntry {
             id: id,
-            file: FileAbstraction::Absent(pb),
             status: StoreEntryStatus::Present,
         })
     }
@@ -158,31 +215,30 @@ impl StoreEntry {
         self.status == StoreEntryStatus::Borrowed
     }
 
-    fn get_entry(&mut self) -> Result<Entry> {
+    fn get_entry(&mut self, backend: &StorageBackend) -> Result<Entry> {
+        use std::io::Cursor;
+
         if !self.is_borrowed() {
-            let file = self.file.get_file_content();
-            if let Err(err) = file {
-                if err.err_type() == SEK::FileNotFound {
+            let path = try!(self.id.clone().into_pathbuf());
+            match backend.read(&path) {
+                Err(err) => if err.err_type() == SEK::FileNotFound {
                     Ok(Entry::new(self.id.clone()))
                 } else {
                     Err(err)
-                }
-            } else {
-                // TODO:
-                let entry = Entry::from_reader(self.id.clone(), &mut file.unwrap());
-                entry
+                },
+                Ok(buf) => Entry::from_reader(self.id.clone(), &mut Cursor::new(buf)),
             }
         } else {
             Err(SE::new(SEK::EntryAlreadyBorrowed, None))
         }
     }
 
-    fn write_entry(&mut self, entry: &Entry) -> Result<()> {
+    fn write_entry(&mut self, backend: &StorageBackend, entry: &Entry) -> Result<()> {
         if self.is_borrowed() {
             assert_eq!(self.id, entry.location);
-            self.file.write_file_content(entry.to_str().as_bytes())
+            let path = try!(self.id.clone().into_pathbuf());
+            backend.write(&path, entry.to_str().as_bytes())
                 .map_err_into(SEK::FileError)
-                .map(|_| ())
         } else {
             Ok(())
         }
@@ -198,27 +254,58 @@ pub struct Store {
      */
     configuration: Option<Value>,
 
+    /**
+     * The backend the entries are persisted with
+     */
+    backend: Box<StorageBackend>,
+
+    /**
+     * The index over the entry headers, if enabled in the configuration
+     */
+    index: Option<Arc<Mutex<Index>>>,
+
+    /**
+     * Further indexes over the entries, see `Store::register_index()`
+     */
+    indices: Mutex<Vec<Arc<Mutex<StoreIndex>>>>,
+
+    /**
+     * What to do if an entry is locked by another process
+     */
+    lock_policy: LockPolicy,
+
+    /**
+     * Header schemas of the modules, see `Store::register_schema()`
+     */
+    schemas: RwLock<Vec<Schema>>,
+
+    /**
+     * Migrations of the store format, see `Store::register_migration()`
+     */
+    migrations: RwLock<Vec<Migration>>,
+
     /*
      * Registered hooks
      */
 
-    store_unload_aspects  : Arc<Mutex<Vec<Aspect>>>,
+    store_unload_aspects  : Arc<RwLock<Vec<Aspect>>>,
 
-    pre_create_aspects    : Arc<Mutex<Vec<Aspect>>>,
-    post_create_aspects   : Arc<Mutex<Vec<Aspect>>>,
-    pre_retrieve_aspects  : Arc<Mutex<Vec<Aspect>>>,
-    post_retrieve_aspects : Arc<Mutex<Vec<Aspect>>>,
-    pre_update_aspects    : Arc<Mutex<Vec<Aspect>>>,
-    post_update_aspects   : Arc<Mutex<Vec<Aspect>>>,
-    pre_delete_aspects    : Arc<Mutex<Vec<Aspect>>>,
-    post_delete_aspects   : Arc<Mutex<Vec<Aspect>>>,
-    pre_move_aspects      : Arc<Mutex<Vec<Aspect>>>,
-    post_move_aspects     : Arc<Mutex<Vec<Aspect>>>,
+    pre_create_aspects    : Arc<RwLock<Vec<Aspect>>>,
+    post_create_aspects   : Arc<RwLock<Vec<Aspect>>>,
+    pre_retrieve_aspects  : Arc<RwLock<Vec<Aspect>>>,
+    post_retrieve_aspects : Arc<RwLock<Vec<Aspect>>>,
+    pre_update_aspects    : Arc<RwLock<Vec<Aspect>>>,
+    post_update_aspects   : Arc<RwLock<Vec<Aspect>>>,
+    pre_delete_aspects    : Arc<RwLock<Vec<Aspect>>>,
+    post_delete_aspects   : Arc<RwLock<Vec<Aspect>>>,
+    pre_move_aspects      : Arc<RwLock<Vec<Aspect>>>,
+    post_move_aspects     : Arc<RwLock<Vec<Aspect>>>,
 
     /**
      * Internal Path->File cache map
      *
-     * Caches the files, so they remain flock()ed
+     * Caches which entries are borrowed. Locking against other processes is done with the
+     * lock functions of the `StorageBackend`.
      *
      * Could be optimized for a threadsafe HashMap
      */
@@ -228,14 +315,21 @@ pub struct Store {
 impl Store {
 
     /// Create a new Store object
-    pub fn new(location: PathBuf, store_config: Option<Value>) -> Result<Store> {
+    ///
+    /// The `backend` is used for all file operations of the store. Use
+    /// `file_abstraction::FileSystemBackend` for a store on disk and
+    /// `file_abstraction::InMemoryBackend` for a store which lives in memory only (for example
+    /// for testing).
+    pub fn new(location: PathBuf, store_config: Option<Value>, backend: Box<StorageBackend>)
+        -> Result<Store>
+    {
         use configuration::*;
 
         debug!("Validating Store configuration");
         let _ = try!(config_is_valid(&store_config).map_err_into(SEK::ConfigurationError));
 
         debug!("Building new Store object");
-        if !location.exists() {
+        if !backend.is_dir(&location) && !backend.is_file(&location) {
             if !config_implicit_store_create_allowed(store_config.as_ref()) {
                 warn!("Implicitely creating store directory is denied");
                 warn!(" -> Either because configuration does not allow it");
@@ -246,16 +340,33 @@ impl Store {
             }
 
             debug!("Creating store path");
-            let c = FileAbstraction::create_dir_all(&location);
+            let c = backend.create_dir_all(&location);
             if c.is_err() {
                 debug!("Failed");
                 return Err(SEK::StorePathCreate.into_error_with_cause(Box::new(c.unwrap_err())));
             }
-        } else if location.is_file() {
+        } else if backend.is_file(&location) {
             debug!("Store path exists as file");
             return Err(SEK::StorePathExists.into_error());
         }
 
+        debug!("Checking for interrupted transactions");
+        try!(Journal::recover(&*backend, &sibling_path(&location, "journal")));
+
+        let index = if config_index_enabled(store_config.as_ref()) {
+            let index_path = config_index_path(store_config.as_ref())
+                .unwrap_or_else(|| sibling_path(&location, "index"));
+            debug!("Loading store index from {:?}", index_path);
+            let index = try!(Index::load(&*backend, index_path));
+            Some(Arc::new(Mutex::new(index)))
+        } else {
+            debug!("Store index disabled");
+            None
+        };
+
+        let lock_policy = config_lock_policy(store_config.as_ref());
+        debug!("Lock policy: {:?}", lock_policy);
+
         let store_unload_aspects = get_store_unload_aspect_names(&store_config)
             .into_iter().map(|n| {
                 let cfg = AspectConfig::get_for(&store_config, n.clone());
@@ -325,19 +436,25 @@ impl Store {
         let store = Store {
             location: location.clone(),
             configuration: store_config,
-
-            store_unload_aspects  : Arc::new(Mutex::new(store_unload_aspects)),
-
-            pre_create_aspects    : Arc::new(Mutex::new(pre_create_aspects)),
-            post_create_aspects   : Arc::new(Mutex::new(post_create_aspects)),
-            pre_retrieve_aspects  : Arc::new(Mutex::new(pre_retrieve_aspects)),
-            post_retrieve_aspects : Arc::new(Mutex::new(post_retrieve_aspects)),
-            pre_update_aspects    : Arc::new(Mutex::new(pre_update_aspects)),
-            post_update_aspects   : Arc::new(Mutex::new(post_update_aspects)),
-            pre_delete_aspects    : Arc::new(Mutex::new(pre_delete_aspects)),
-            post_delete_aspects   : Arc::new(Mutex::new(post_delete_aspects)),
-            pre_move_aspects    : Arc::new(Mutex::new(pre_move_aspects)),
-            post_move_aspects   : Arc::new(Mutex::new(post_move_aspects)),
+            backend: backend,
+            index: index,
+            indices: Mutex::new(vec![]),
+            lock_policy: lock_policy,
+            schemas: RwLock::new(vec![]),
+            migrations: RwLock::new(vec![]),
+
+            store_unload_aspects  : Arc::new(RwLock::new(store_unload_aspects)),
+
+            pre_create_aspects    : Arc::new(RwLock::new(pre_create_aspects)),
+            post_create_aspects   : Arc::new(RwLock::new(post_create_aspects)),
+            pre_retrieve_aspects  : Arc::new(RwLock::new(pre_retrieve_aspects)),
+            post_retrieve_aspects : Arc::new(RwLock::new(post_retrieve_aspects)),
+            pre_update_aspects    : Arc::new(RwLock::new(pre_update_aspects)),
+            post_update_aspects   : Arc::new(RwLock::new(post_update_aspects)),
+            pre_delete_aspects    : Arc::new(RwLock::new(pre_delete_aspects)),
+            post_delete_aspects   : Arc::new(RwLock::new(post_delete_aspects)),
+            pre_move_aspects      : Arc::new(RwLock::new(pre_move_aspects)),
+            post_move_aspects     : Arc::new(RwLock::new(post_move_aspects)),
             entries: Arc::new(RwLock::new(HashMap::new())),
         };
 
@@ -359,49 +476,50 @@ impl Store {
     /// This function is not intended to be called by normal programs but only by `imag-store`.
     #[cfg(feature = "verify")]
     pub fn verify(&self) -> bool {
-        info!("Header | Content length | Path");
-        info!("-------+----------------+-----");
+        info!("Header | Schema | Content length | Path");
+        info!("-------+--------+----------------+-----");
 
-        WalkDir::new(self.location.clone())
-            .into_iter()
-            .map(|res| {
-                match res {
-                    Ok(dent) => {
-                        if dent.file_type().is_file() {
-                            match self.get(PathBuf::from(dent.path())) {
-                                Ok(Some(fle)) => {
-                                    let p           = fle.get_location();
-                                    let content_len = fle.get_content().len();
-                                    let header      = if fle.get_header().verify().is_ok() {
-                                        "ok"
-                                    } else {
-                                        "broken"
-                                    };
-
-                                    info!("{: >6} | {: >14} | {:?}", header, content_len, p.deref());
-                                },
-
-                                Ok(None) => {
-                                    info!("{: >6} | {: >14} | {:?}", "?", "couldn't load", dent.path());
-                                },
-
-                                Err(e) => {
-                                    debug!("{:?}", e);
-                                },
-                            }
-                        } else {
-                            info!("{: >6} | {: >14} | {:?}", "?", "<no file>", dent.path());
+        let paths = match self.backend.list(&self.location) {
+            Ok(paths) => paths,
+            Err(e) => {
+                trace_error(&e);
+                return false;
+            },
+        };
+
+        paths.into_iter()
+            .map(|path| {
+                let entry = StoreId::from_full_path(&self.location, path.clone())
+                    .and_then(|id| self.retrieve_copy(id));
+
+                match entry {
+                    Ok(entry) => {
+                        let content_len = entry.get_content().len();
+                        let header      = entry.get_header().verify();
+                        let schema      = self.validate_schemas(&entry);
+                        let ok          = header.is_ok() && schema.is_ok();
+
+                        info!("{: >6} | {: >6} | {: >14} | {:?}",
+                              if header.is_ok() { "ok" } else { "broken" },
+                              if schema.is_ok() { "ok" } else { "broken" },
+                              content_len,
+                              entry.get_location().deref());
+
+                        if let Err(e) = schema {
+                            trace_error(&e);
                         }
+
+                        ok
                     },
 
                     Err(e) => {
+                        info!("{: >6} | {: >6} | {: >14} | {:?}", "?", "?", "couldn't load", path);
                         debug!("{:?}", e);
+                        false
                     },
                 }
-
-                true
             })
-            .all(|b| b)
+            .fold(true, |acc, ok| acc && ok)
     }
 
     /// Creates the Entry at the given location (inside the entry)
@@ -415,7 +533,7 @@ impl Store {
         }
 
         {
-            let mut hsmap = match self.entries.write() {
+            let hsmap = match self.entries.read() {
                 Err(_) => return Err(SEK::LockPoisoned.into_error()).map_err_into(SEK::CreateCallError),
                 Ok(s) => s,
             };
@@ -423,11 +541,27 @@ impl Store {
             if hsmap.contains_key(&id) {
                 return Err(SEK::EntryAlreadyExists.into_error()).map_err_into(SEK::CreateCallError);
             }
-            hsmap.insert(id.clone(), {
+        }
+
+        // Waiting for the lock must not block the other threads of this process, so the entries
+        // are not locked meanwhile
+        try!(self.lock_entry(&id).map_err_into(SEK::CreateCallError));
+        let inserted = self.entries
+            .write()
+            .map_err(|_| SEK::LockPoisoned.into_error())
+            .and_then(|mut hsmap| {
+                if hsmap.contains_key(&id) {
+                    return Err(SEK::EntryAlreadyExists.into_error());
+                }
+
                 let mut se = try!(StoreEntry::new(id.clone()));
                 se.status = StoreEntryStatus::Borrowed;
-                se
+                hsmap.insert(id.clone(), se);
+                Ok(())
             });
+        if let Err(e) = inserted {
+            let _ = self.unlock_entry(&id).map_err(|e| trace_error(&e));
+            return Err(e).map_err_into(SEK::CreateCallError);
         }
 
         let mut fle = FileLockEntry::new(self, Entry::new(id));
@@ -452,19 +586,33 @@ impl Store {
                 .map_err_into(SEK::RetrieveCallError)
         }
 
-        let entry = try!({
-            self.entries
-                .write()
-                .map_err(|_| SE::new(SEK::LockPoisoned, None))
-                .and_then(|mut es| {
-                    let new_se = try!(StoreEntry::new(id.clone()));
-                    let mut se = es.entry(id.clone()).or_insert(new_se);
-                    let entry = se.get_entry();
+        // Waiting for the lock must not block the other threads of this process, so the entries
+        // are not locked meanwhile
+        try!(self.lock_entry(&id).map_err_into(SEK::RetrieveCallError));
+        let entry = self.entries
+            .write()
+            .map_err(|_| SE::new(SEK::LockPoisoned, None))
+            .and_then(|mut es| {
+                let new_se = try!(StoreEntry::new(id.clone()));
+                let mut se = es.entry(id.clone()).or_insert(new_se);
+                if se.is_borrowed() {
+                    return Err(SE::new(SEK::EntryAlreadyBorrowed, None));
+                }
+
+                let entry = se.get_entry(&*self.backend)
+                    .and_then(|mut e| self.migrate_entry(&mut e).map(|_| e));
+                if entry.is_ok() {
                     se.status = StoreEntryStatus::Borrowed;
-                    entry
-                })
-                .map_err_into(SEK::RetrieveCallError)
-        });
+                }
+                entry
+            });
+        let entry = match entry {
+            Ok(entry) => entry,
+            Err(e) => {
+                let _ = self.unlock_entry(&id).map_err(|e| trace_error(&e));
+                return Err(e).map_err_into(SEK::RetrieveCallError);
+            },
+        };
 
         let mut fle = FileLockEntry::new(self, entry);
         self.execute_hooks_for_mut_file(self.post_retrieve_aspects.clone(), &mut fle)
@@ -487,7 +635,7 @@ impl Store {
             .map_err_into(SEK::GetCallError)
         );
 
-        if !exists && !id.exists() {
+        if !exists && !self.backend.is_file(&try!(id.clone().into_pathbuf())) {
             debug!("Does not exist in internal cache or filesystem: {:?}", id);
             return Ok(None);
         }
@@ -500,21 +648,142 @@ impl Store {
         let mut path = self.path().clone();
         path.push(mod_name);
 
-        path.to_str()
-            .ok_or(SE::new(SEK::EncodingError, None))
-            .and_then(|path| {
-                let path = [ path, "/**/*" ].join("");
-                debug!("glob()ing with '{}'", path);
-                glob(&path[..]).map_err_into(SEK::GlobError)
+        debug!("Listing entries in '{:?}'", path);
+        let store_path = self.path().clone();
+        self.backend
+            .list(&path)
+            .map(|paths| {
+                let iter = paths
+                    .into_iter()
+                    .filter_map(move |p| {
+                        StoreId::from_full_path(&store_path, p)
+                            .map_err(|e| {
+                                debug!("Cannot build StoreId from listed path: {:?}", e);
+                                trace_error(&e);
+                            })
+                            .ok()
+                    });
+                StoreIdIterator::new(Box::new(iter))
             })
-            .map(|paths| GlobStoreIdIterator::new(paths, self.path().clone()).into())
-            .map_err_into(SEK::GlobError)
             .map_err_into(SEK::RetrieveForModuleCallError)
     }
 
-    // Walk the store tree for the module
+    /// Get the store index, if it is enabled
+    ///
+    /// This is used by the index hook to keep the index up to date.
+    pub fn index(&self) -> Option<Arc<Mutex<Index>>> {
+        self.index.clone()
+    }
+
+    /// Register a further index over the entries, which is written when the store is dropped
+    ///
+    /// The index is not kept up to date by the store itself, an index hook has to be registered
+    /// for it as well.
+    pub fn register_index(&self, index: Arc<Mutex<StoreIndex>>) -> Result<()> {
+        self.indices
+            .lock()
+            .map_err(|_| SEK::LockPoisoned.into_error())
+            .map(|mut indices| indices.push(index))
+    }
+
+    /// Get the backend the store is persisted with
+    ///
+    /// Indexes which live next to the store are read and written through the backend, too.
+    pub fn backend(&self) -> &StorageBackend {
+        &*self.backend
+    }
+
+    /// Get all entries where the header value at `path` equals `value`, using the store index
+    ///
+    /// Returns a `StoreErrorKind::IndexDisabled` error if the index is not enabled.
+    pub fn query_header_eq(&self, path: &str, value: &Value) -> Result<StoreIdIterator> {
+        self.query_index(|index| index.query_header_eq(path, value))
+    }
+
+    /// Get all entries where the header value at `path` is an array containing `value`, using the
+    /// store index
+    ///
+    /// For example, `query_header_contains("imag.tags", &Value::String(tag))` yields all entries
+    /// tagged with `tag`.
+    ///
+    /// Returns a `StoreErrorKind::IndexDisabled` error if the index is not enabled.
+    pub fn query_header_contains(&self, path: &str, value: &Value) -> Result<StoreIdIterator> {
+        self.query_index(|index| index.query_header_contains(path, value))
+    }
+
+    fn query_index<F>(&self, f: F) -> Result<StoreIdIterator>
+        where F: FnOnce(&Index) -> Result<Vec<StoreId>>
+    {
+        let index = try!(self.index.as_ref()
+                         .ok_or(SEK::IndexDisabled.into_error())
+                         .map_err_into(SEK::QueryCallError));
+        let index = try!(index.lock()
+                         .map_err(|_| SEK::LockPoisoned.into_error())
+                         .map_err_into(SEK::QueryCallError));
+
+        let base = self.path().clone();
+        f(&index)
+            .map(|ids| {
+                let iter = ids.into_iter().map(move |id| id.with_base(base.clone()));
+                StoreIdIterator::new(Box::new(iter))
+            })
+            .map_err_into(SEK::QueryCallError)
+    }
+
+    /// Rebuild the store index from scratch by reading all entries in the store
+    ///
+    /// Entries which cannot be read (because they are malformed or currently borrowed) are
+    /// reported and skipped.
+    ///
+    /// Returns a `StoreErrorKind::IndexDisabled` error if the index is not enabled.
+    pub fn reindex(&self) -> Result<()> {
+        let index = try!(self.index.as_ref()
+                         .ok_or(SEK::IndexDisabled.into_error())
+                         .map_err_into(SEK::ReindexCallError));
+        let mut index = try!(index.lock()
+                             .map_err(|_| SEK::LockPoisoned.into_error())
+                             .map_err_into(SEK::ReindexCallError));
+
+        let paths = try!(self.backend.list(&self.location).map_err_into(SEK::ReindexCallError));
+
+        index.clear();
+        for path in paths {
+            if path == *index.location() {
+                continue;
+            }
+
+            let entry = StoreId::from_full_path(&self.location, path)
+                .and_then(|id| self.retrieve_copy(id));
+
+            match entry {
+                Ok(entry) => try!(index.update(&entry).map_err_into(SEK::ReindexCallError)),
+                Err(e) => {
+                    warn!("Cannot index entry, skipping it");
+                    trace_error(&e);
+                },
+            }
+        }
+
+        index.save(&*self.backend).map_err_into(SEK::ReindexCallError)
+    }
+
+    /// Walk the store tree for the module
+    ///
+    /// Pass an empty module name to walk the whole store.
     pub fn walk<'a>(&'a self, mod_name: &str) -> Walk {
-        Walk::new(self.path().clone(), mod_name)
+        Walk::new(self.path().clone(), mod_name, &*self.backend)
+    }
+
+    /// Check whether the entry `id` exists, either in the backend or as an entry which was
+    /// created but not written yet
+    pub fn exists<S: IntoStoreId>(&self, id: S) -> Result<bool> {
+        let id = try!(id.into_storeid()).with_base(self.path().clone());
+        let borrowed = try!(self.entries
+            .read()
+            .map(|map| map.contains_key(&id))
+            .map_err(|_| SE::new(SEK::LockPoisoned, None)));
+
+        Ok(borrowed || id.exists(&*self.backend))
     }
 
     /// Return the `FileLockEntry` and write to disk
@@ -545,9 +814,10 @@ impl Store {
 
         debug!("Verifying Entry");
         try!(entry.entry.verify());
+        try!(self.validate_schemas(&entry.entry));
 
         debug!("Writing Entry");
-        try!(se.write_entry(&entry.entry));
+        try!(se.write_entry(&*self.backend, &entry.entry));
         if modify_presence {
             se.status = StoreEntryStatus::Present;
         }
@@ -559,6 +829,169 @@ impl Store {
             .map_err_into(SEK::UpdateCallError)
     }
 
+    /// Path of the lock file for the entry `id`
+    ///
+    /// The lock files live outside of the store directory, so they do not show up as entries.
+    fn lock_path(&self, id: &StoreId) -> PathBuf {
+        let mut path = sibling_path(&self.location, "locks");
+        path.push(id.local());
+        path
+    }
+
+    /// Lock the entry `id` against other processes, waiting as the `LockPolicy` says
+    ///
+    /// If the entry is borrowed by this process already, this fails with
+    /// `EntryAlreadyBorrowed` right away instead of waiting for a lock which is held by this
+    /// process itself.
+    fn lock_entry(&self, id: &StoreId) -> Result<()> {
+        let path  = self.lock_path(id);
+        let start = Instant::now();
+
+        loop {
+            if try!(self.is_borrowed(id)) {
+                debug!("Entry {:?} is borrowed by this process", id);
+                return Err(SEK::EntryAlreadyBorrowed.into_error());
+            }
+
+            if try!(self.backend.try_lock(&path)) {
+                return Ok(());
+            }
+
+            match self.lock_policy {
+                LockPolicy::Fail => break,
+                LockPolicy::Wait(timeout) if start.elapsed() >= timeout => break,
+                _ => {
+                    debug!("Entry {:?} is locked, retrying", id);
+                    sleep(Duration::from_millis(LOCK_RETRY_INTERVAL_MS));
+                },
+            }
+        }
+
+        warn!("Entry {} is locked by another process", id);
+        Err(SEK::EntryLockedByOtherProcess.into_error())
+    }
+
+    /// Whether the entry `id` is borrowed by this process
+    fn is_borrowed(&self, id: &StoreId) -> Result<bool> {
+        self.entries
+            .read()
+            .map_err(|_| SEK::LockPoisoned.into_error())
+            .map(|es| es.get(id).map(|se| se.is_borrowed()).unwrap_or(false))
+    }
+
+    /// Release the lock for the entry `id`
+    ///
+    /// The lock file is removed by the backend.
+    fn unlock_entry(&self, id: &StoreId) -> Result<()> {
+        self.backend.unlock(&self.lock_path(id))
+    }
+
+    /// Mark the entry `id` as not borrowed anymore and release its lock
+    ///
+    /// This is done when a `FileLockEntry` is dropped, even if writing it failed, so the entry
+    /// can be borrowed again.
+    fn release_entry(&self, id: &StoreId) -> Result<()> {
+        {
+            let mut hsmap = try!(self.entries.write().map_err(|_| SEK::LockPoisoned.into_error()));
+            if let Some(se) = hsmap.get_mut(id) {
+                se.status = StoreEntryStatus::Present;
+            }
+        }
+        self.unlock_entry(id)
+    }
+
+    /// Execute `f` while holding the lock for the entry `id`
+    fn with_entry_lock<T, F>(&self, id: &StoreId, f: F) -> Result<T>
+        where F: FnOnce() -> Result<T>
+    {
+        try!(self.lock_entry(id));
+        let res = f();
+        if let Err(e) = self.unlock_entry(id) {
+            warn!("Unlocking {} failed", id);
+            trace_error(&e);
+        }
+        res
+    }
+
+    /// Start a new transaction
+    ///
+    /// Entries which are added to the `Transaction` are written to the store atomically when the
+    /// transaction is committed. See `Transaction` for details.
+    pub fn transaction<'a>(&'a self) -> Transaction<'a> {
+        Transaction::new(self)
+    }
+
+    /// Write all `entries` atomically
+    ///
+    /// The update hooks are executed once for the complete transaction: the pre-update hooks
+    /// before anything is written, so a failing hook aborts the complete transaction, and the
+    /// post-update hooks after all entries were written. Hooks which do not know about
+    /// transactions are executed for each entry.
+    ///
+    /// `committed` is set as soon as the entries are written, so the transaction is not rolled
+    /// back if releasing the entries or a post-update hook fails afterwards.
+    fn commit_transaction<'a>(&'a self,
+                              entries: &mut Vec<FileLockEntry<'a>>,
+                              committed: &mut bool)
+        -> Result<()>
+    {
+        try!(self.execute_hooks_for_transaction(self.pre_update_aspects.clone(), entries)
+            .map_err_into(SEK::PreHookExecuteError)
+            .map_err_into(SEK::HookExecutionError));
+
+        for entry in entries.iter() {
+            debug!("Verifying Entry");
+            try!(entry.entry.verify());
+            try!(self.validate_schemas(&entry.entry));
+        }
+
+        let journal_dir = sibling_path(&self.location, "journal");
+        let mut journal = try!(Journal::create(&*self.backend, &journal_dir));
+        for entry in entries.iter() {
+            journal.push(try!(entry.get_location().clone().into_pathbuf()));
+        }
+
+        try!(journal.prepare(&*self.backend));
+        for entry in entries.iter() {
+            let temp = Journal::temp_path(&try!(entry.get_location().clone().into_pathbuf()));
+            debug!("Writing Entry to {:?}", temp);
+            if let Err(e) = self.backend.write(&temp, entry.to_str().as_bytes()) {
+                if let Err(e) = journal.abort(&*self.backend) {
+                    warn!("Aborting transaction failed, store will be recovered on next start");
+                    trace_error(&e);
+                }
+                return Err(e);
+            }
+        }
+        try!(journal.commit(&*self.backend));
+        *committed = true;
+
+        {
+            let mut hsmap = try!(self.entries.write().map_err(|_| SEK::LockPoisoned.into_error()));
+            for entry in entries.iter() {
+                if let Some(se) = hsmap.get_mut(entry.get_location()) {
+                    se.status = StoreEntryStatus::Present;
+                }
+                try!(self.unlock_entry(entry.get_location()));
+            }
+        }
+
+        self.execute_hooks_for_transaction(self.post_update_aspects.clone(), entries)
+            .map_err_into(SEK::PostHookExecuteError)
+            .map_err_into(SEK::HookExecutionError)
+    }
+
+    /// Release all `entries` without writing them
+    fn rollback_transaction<'a>(&'a self, entries: &[FileLockEntry<'a>]) -> Result<()> {
+        let mut hsmap = try!(self.entries.write().map_err(|_| SEK::LockPoisoned.into_error()));
+        for entry in entries {
+            debug!("Rolling back {:?}", entry.get_location());
+            hsmap.remove(entry.get_location());
+            try!(self.unlock_entry(entry.get_location()));
+        }
+        Ok(())
+    }
+
     /// Retrieve a copy of a given entry, this cannot be used to mutate
     /// the one on disk
     pub fn retrieve_copy<S: IntoStoreId>(&self, id: S) -> Result<Entry> {
@@ -576,7 +1009,9 @@ impl Store {
             return Err(SE::new(SEK::IdLocked, None)).map_err_into(SEK::RetrieveCopyCallError);
         }
 
-        try!(StoreEntry::new(id)).get_entry()
+        try!(StoreEntry::new(id))
+            .get_entry(&*self.backend)
+            .and_then(|mut e| self.migrate_entry(&mut e).map(|_| e))
     }
 
     /// Delete an entry
@@ -590,7 +1025,7 @@ impl Store {
         }
 
         {
-            let mut entries = match self.entries.write() {
+            let entries = match self.entries.read() {
                 Err(_) => return Err(SE::new(SEK::LockPoisoned, None))
                     .map_err_into(SEK::DeleteCallError),
                 Ok(e) => e,
@@ -605,15 +1040,24 @@ impl Store {
                     return Err(SE::new(SEK::IdLocked, None)).map_err_into(SEK::DeleteCallError)
                 }
             }
+        }
+
+        // remove the entry first, then the file
+        //
+        // The entries are not locked while waiting for the lock of the entry, so other threads
+        // of this process are not blocked meanwhile
+        let pb = try!(id.clone().with_base(self.path().clone()).into_pathbuf());
+        try!(self.with_entry_lock(&id, || {
+            let mut entries = try!(self.entries.write().map_err(|_| SEK::LockPoisoned.into_error()));
+            if entries.get(&id).map(|e| e.is_borrowed()).unwrap_or(false) {
+                return Err(SE::new(SEK::IdLocked, None));
+            }
 
-            // remove the entry first, then the file
             entries.remove(&id);
-            let pb = try!(id.clone().with_base(self.path().clone()).into_pathbuf());
-            if let Err(e) = FileAbstraction::remove_file(&pb) {
-                return Err(SEK::FileError.into_error_with_cause(Box::new(e)))
-                    .map_err_into(SEK::DeleteCallError);
-            }
-        }
+            self.backend
+                .remove(&pb)
+                .map_err(|e| SEK::FileError.into_error_with_cause(Box::new(e)))
+        }).map_err_into(SEK::DeleteCallError));
 
         self.execute_hooks_for_id(self.post_delete_aspects.clone(), &id)
             .map_err_into(SEK::PostHookExecuteError)
@@ -622,46 +1066,70 @@ impl Store {
     }
 
     /// Save a copy of the Entry in another place
-    /// Executes the post_move_aspects for the new id
+    ///
+    /// As the original entry stays where it is, no move hooks are executed. The copy is a new
+    /// entry, so the create and update hooks are executed for it.
     pub fn save_to(&self, entry: &FileLockEntry, new_id: StoreId) -> Result<()> {
-        self.save_to_other_location(entry, new_id, false)
+        let mut copy = try!(self.create(new_id).map_err_into(SEK::MoveCallError));
+        *copy.get_header_mut()  = entry.get_header().clone();
+        *copy.get_content_mut() = entry.get_content().clone();
+        self.update(copy).map_err_into(SEK::MoveCallError)
     }
 
     /// Save an Entry in another place
     /// Removes the original entry
-    /// Executes the post_move_aspects for the new id
+    /// Executes the pre_move_aspects and the post_move_aspects with the old and the new id
+    ///
+    /// The entry may have been altered before it is saved, so the update hooks are executed for
+    /// the entry at its new location afterwards, too.
     pub fn save_as(&self, entry: FileLockEntry, new_id: StoreId) -> Result<()> {
-        self.save_to_other_location(&entry, new_id, true)
-    }
-
-    fn save_to_other_location(&self, entry: &FileLockEntry, new_id: StoreId, remove_old: bool)
-        -> Result<()>
-    {
         let new_id = new_id.with_base(self.path().clone());
-        let hsmap = self.entries.write();
-        if hsmap.is_err() {
-            return Err(SE::new(SEK::LockPoisoned, None)).map_err_into(SEK::MoveCallError)
+        let old_id = entry.get_location().clone();
+
+        if let Err(e) = self.execute_hooks_for_move(self.pre_move_aspects.clone(), &old_id, &new_id) {
+            return Err(e)
+                .map_err_into(SEK::PreHookExecuteError)
+                .map_err_into(SEK::HookExecutionError)
+                .map_err_into(SEK::MoveCallError)
         }
-        if hsmap.unwrap().contains_key(&new_id) {
-            return Err(SE::new(SEK::EntryAlreadyExists, None)).map_err_into(SEK::MoveCallError)
+
+        {
+            let mut hsmap = match self.entries.write() {
+                Err(_) => return Err(SE::new(SEK::LockPoisoned, None)).map_err_into(SEK::MoveCallError),
+                Ok(m)  => m,
+            };
+
+            if hsmap.contains_key(&new_id) {
+                return Err(SE::new(SEK::EntryAlreadyExists, None)).map_err_into(SEK::MoveCallError)
+            }
+
+            let old_id_as_path = try!(old_id.clone().with_base(self.path().clone()).into_pathbuf());
+            let new_id_as_path = try!(new_id.clone().with_base(self.path().clone()).into_pathbuf());
+            try!(self.backend.write(&new_id_as_path, entry.to_str().as_bytes())
+                .and_then(|_| {
+                    if self.backend.is_file(&old_id_as_path) {
+                        self.backend.remove(&old_id_as_path)
+                    } else {
+                        Ok(())
+                    }
+                })
+                .map_err_into(SEK::FileError)
+                .map_err_into(SEK::MoveCallError));
+
+            // The old entry is gone now, so dropping the `FileLockEntry` must not write it back to
+            // its old location.
+            hsmap.remove(&old_id);
         }
 
-        let old_id = entry.get_location().clone();
+        try!(self.execute_hooks_for_move(self.post_move_aspects.clone(), &old_id, &new_id)
+            .map_err_into(SEK::PostHookExecuteError)
+            .map_err_into(SEK::HookExecutionError)
+            .map_err_into(SEK::MoveCallError));
 
-        let old_id_as_path = try!(old_id.clone().with_base(self.path().clone()).into_pathbuf());
-        let new_id_as_path = try!(new_id.clone().with_base(self.path().clone()).into_pathbuf());
-        FileAbstraction::copy(&old_id_as_path, &new_id_as_path)
-            .and_then(|_| {
-                if remove_old {
-                    FileAbstraction::remove_file(&old_id_as_path)
-                } else {
-                    Ok(())
-                }
-            })
-            .map_err_into(SEK::FileError)
-            .and_then(|_| self.execute_hooks_for_id(self.post_move_aspects.clone(), &new_id)
-                    .map_err_into(SEK::PostHookExecuteError)
-                    .map_err_into(SEK::HookExecutionError))
+        // The move hooks only know the ids, so the update hooks (like the index hook) get the
+        // header the entry was saved with
+        self.retrieve(new_id)
+            .and_then(|moved| self.update(moved))
             .map_err_into(SEK::MoveCallError)
     }
 
@@ -705,7 +1173,7 @@ impl Store {
         let new_id = new_id.with_base(self.path().clone());
         let old_id = old_id.with_base(self.path().clone());
 
-        if let Err(e) = self.execute_hooks_for_id(self.pre_move_aspects.clone(), &old_id) {
+        if let Err(e) = self.execute_hooks_for_move(self.pre_move_aspects.clone(), &old_id, &new_id) {
             return Err(e)
                 .map_err_into(SEK::PreHookExecuteError)
                 .map_err_into(SEK::HookExecutionError)
@@ -713,7 +1181,7 @@ impl Store {
         }
 
         {
-            let mut hsmap = match self.entries.write() {
+            let hsmap = match self.entries.read() {
                 Err(_) => return Err(SE::new(SEK::LockPoisoned, None)),
                 Ok(m)  => m,
             };
@@ -722,35 +1190,46 @@ impl Store {
                 return Err(SEK::EntryAlreadyExists.into_error());
             }
 
-            // if we do not have an entry here, we fail in `FileAbstraction::rename()` below.
+            // if we do not have an entry here, we fail in `StorageBackend::rename()` below.
             // if we have one, but it is borrowed, we really should not rename it, as this might
             // lead to strange errors
             if hsmap.get(&old_id).map(|e| e.is_borrowed()).unwrap_or(false) {
                 return Err(SEK::EntryAlreadyBorrowed.into_error());
             }
+        }
 
-            let old_id_pb = try!(old_id.clone().with_base(self.path().clone()).into_pathbuf());
-            let new_id_pb = try!(new_id.clone().with_base(self.path().clone()).into_pathbuf());
-
-            match FileAbstraction::rename(&old_id_pb, &new_id_pb) {
-                Err(e) => return Err(SEK::EntryRenameError.into_error_with_cause(Box::new(e))),
-                Ok(_) => {
-                    debug!("Rename worked on filesystem");
-
-                    // assert enforced through check hsmap.contains_key(&new_id) above.
-                    // Should therefor never fail
-                    assert!(hsmap
-                            .remove(&old_id)
-                            .and_then(|mut entry| {
-                                entry.id = new_id.clone();
-                                hsmap.insert(new_id.clone(), entry)
-                            }).is_none())
+        let old_id_pb = try!(old_id.clone().with_base(self.path().clone()).into_pathbuf());
+        let new_id_pb = try!(new_id.clone().with_base(self.path().clone()).into_pathbuf());
+
+        // The entries are not locked while waiting for the locks of the entries, so other
+        // threads of this process are not blocked meanwhile. The checks from above are repeated
+        // while holding the locks.
+        let renamed = self.with_entry_lock(&old_id, || {
+            self.with_entry_lock(&new_id, || {
+                let mut hsmap = try!(self.entries.write().map_err(|_| SEK::LockPoisoned.into_error()));
+                if hsmap.contains_key(&new_id) {
+                    return Err(SEK::EntryAlreadyExists.into_error());
+                }
+                if hsmap.get(&old_id).map(|e| e.is_borrowed()).unwrap_or(false) {
+                    return Err(SEK::EntryAlreadyBorrowed.into_error());
                 }
-            }
 
-        }
+                try!(self.backend
+                     .rename(&old_id_pb, &new_id_pb)
+                     .map_err(|e| SEK::EntryRenameError.into_error_with_cause(Box::new(e))));
+                debug!("Rename worked on filesystem");
 
-        self.execute_hooks_for_id(self.pre_move_aspects.clone(), &new_id)
+                if let Some(mut entry) = hsmap.remove(&old_id) {
+                    entry.id = new_id.clone();
+                    hsmap.insert(new_id.clone(), entry);
+                }
+                Ok(())
+            })
+        });
+
+        try!(renamed);
+
+        self.execute_hooks_for_move(self.post_move_aspects.clone(), &old_id, &new_id)
             .map_err_into(SEK::PostHookExecuteError)
             .map_err_into(SEK::HookExecutionError)
             .map_err_into(SEK::MoveByIdCallError)
@@ -761,6 +1240,94 @@ impl Store {
         &self.location
     }
 
+    /// Register the header schema of a module
+    ///
+    /// From now on, entries which have the section of the schema in their header are validated
+    /// against the schema before they are written. There can only be one schema per section.
+    pub fn register_schema(&self, schema: Schema) -> Result<()> {
+        let mut schemas = try!(self.schemas.write().map_err(|_| SEK::LockPoisoned.into_error()));
+        if schemas.iter().any(|s| s.section() == schema.section()) {
+            return Err(SEK::SchemaAlreadyRegistered.into_error());
+        }
+
+        debug!("Registering schema for section '{}'", schema.section());
+        schemas.push(schema);
+        Ok(())
+    }
+
+    /// Validate the header of `entry` against all registered schemas
+    pub fn validate_schemas(&self, entry: &Entry) -> Result<()> {
+        let schemas = try!(self.schemas.read().map_err(|_| SEK::LockPoisoned.into_error()));
+        schemas
+            .iter()
+            .fold(Ok(()), |acc, schema| acc.and_then(|_| schema.validate(entry.get_header())))
+    }
+
+    /// Register a migration of the store format
+    ///
+    /// From now on, entries are migrated when they are retrieved. See the `migration` module for
+    /// how migrations are applied.
+    pub fn register_migration(&self, migration: Migration) -> Result<()> {
+        debug!("Registering {:?}", migration);
+        self.migrations
+            .write()
+            .map_err(|_| SEK::LockPoisoned.into_error())
+            .map(|mut migrations| migrations.push(migration))
+    }
+
+    /// Apply all registered migrations which apply to `entry`
+    fn migrate_entry(&self, entry: &mut Entry) -> Result<Vec<MigrationStep>> {
+        let migrations = try!(self.migrations.read().map_err(|_| SEK::LockPoisoned.into_error()));
+        apply_migrations(&migrations, entry)
+    }
+
+    /// Migrate all entries in the store
+    ///
+    /// Returns the migration steps which were applied. If `dry_run` is true, the migrations are
+    /// only applied in memory, so the steps which would be applied are returned but nothing is
+    /// written. Entries which cannot be read are reported and skipped.
+    pub fn migrate(&self, dry_run: bool) -> Result<Vec<MigrationStep>> {
+        let paths = try!(self.backend.list(&self.location).map_err_into(SEK::MigrateCallError));
+        let mut steps = vec![];
+
+        for path in paths {
+            let id = match StoreId::from_full_path(&self.location, path) {
+                Ok(id) => id,
+                Err(e) => {
+                    trace_error(&e);
+                    continue;
+                },
+            };
+
+            // Read the entry without migrating it, to find out which migrations apply
+            let entry = StoreEntry::new(id.clone()).and_then(|mut se| se.get_entry(&*self.backend));
+            let mut entry = match entry {
+                Ok(entry) => entry,
+                Err(e) => {
+                    warn!("Cannot read entry {}, skipping it", id);
+                    trace_error(&e);
+                    continue;
+                },
+            };
+
+            let mut entry_steps = try!(self.migrate_entry(&mut entry)
+                                       .map_err_into(SEK::MigrateCallError));
+            if entry_steps.is_empty() {
+                continue;
+            }
+
+            if !dry_run {
+                // Retrieving migrates the entry, updating writes it back
+                let fle = try!(self.retrieve(id).map_err_into(SEK::MigrateCallError));
+                try!(self.update(fle).map_err_into(SEK::MigrateCallError));
+            }
+
+            steps.append(&mut entry_steps);
+        }
+
+        Ok(steps)
+    }
+
     pub fn register_hook(&mut self,
                          position: HookPosition,
                          aspect_name: &str,
@@ -782,9 +1349,11 @@ impl Store {
                 HookPosition::PostUpdate   => self.post_update_aspects.clone(),
                 HookPosition::PreDelete    => self.pre_delete_aspects.clone(),
                 HookPosition::PostDelete   => self.post_delete_aspects.clone(),
+                HookPosition::PreMove      => self.pre_move_aspects.clone(),
+                HookPosition::PostMove     => self.post_move_aspects.clone(),
             };
 
-        let mut guard = match guard.deref().lock().map_err(|_| SE::new(SEK::LockError, None)) {
+        let mut guard = match guard.deref().write().map_err(|_| SE::new(SEK::LockError, None)) {
             Err(e) => return Err(SEK::HookRegisterError.into_error_with_cause(Box::new(e))),
             Ok(g) => g,
         };
@@ -823,35 +1392,93 @@ impl Store {
     }
 
     fn execute_hooks_for_id(&self,
-                            aspects: Arc<Mutex<Vec<Aspect>>>,
+                            aspects: Arc<RwLock<Vec<Aspect>>>,
                             id: &StoreId)
         -> HookResult<()>
     {
-        match aspects.lock() {
-            Err(_) => return Err(HookErrorKind::HookExecutionError.into()),
-            Ok(g) => g
-        }.iter().fold_defresult(|aspect| {
-            debug!("[Aspect][exec]: {:?}", aspect);
-            (aspect as &StoreIdAccessor).access(id)
+        with_aspects(&aspects, |aspects| {
+            aspects.iter().fold_defresult(|aspect| {
+                debug!("[Aspect][exec]: {:?}", aspect);
+                (aspect as &StoreIdAccessor).access(id)
+            })
+        }).map_err(Box::new)
+            .map_err(|e| HookErrorKind::HookExecutionError.into_error_with_cause(e))
+    }
+
+    fn execute_hooks_for_move(&self,
+                              aspects: Arc<RwLock<Vec<Aspect>>>,
+                              old_id: &StoreId,
+                              new_id: &StoreId)
+        -> HookResult<()>
+    {
+        with_aspects(&aspects, |aspects| {
+            aspects.iter().fold_defresult(|aspect| {
+                debug!("[Aspect][exec]: {:?}", aspect);
+                (aspect as &StoreIdMoveAccessor).access_move(old_id, new_id)
+            })
         }).map_err(Box::new)
             .map_err(|e| HookErrorKind::HookExecutionError.into_error_with_cause(e))
     }
 
     fn execute_hooks_for_mut_file(&self,
-                                  aspects: Arc<Mutex<Vec<Aspect>>>,
+                                  aspects: Arc<RwLock<Vec<Aspect>>>,
                                   fle: &mut FileLockEntry)
         -> HookResult<()>
     {
-        match aspects.lock() {
-            Err(_) => return Err(HookErrorKind::HookExecutionError.into()),
-            Ok(g) => g
-        }.iter().fold_defresult(|aspect| {
-            debug!("[Aspect][exec]: {:?}", aspect);
-            aspect.access_mut(fle)
+        with_aspects(&aspects, |aspects| {
+            aspects.iter().fold_defresult(|aspect| {
+                debug!("[Aspect][exec]: {:?}", aspect);
+                aspect.access_mut(fle)
+            })
         }).map_err(Box::new)
             .map_err(|e| HookErrorKind::HookExecutionError.into_error_with_cause(e))
     }
 
+    fn execute_hooks_for_transaction(&self,
+                                     aspects: Arc<RwLock<Vec<Aspect>>>,
+                                     fles: &mut [FileLockEntry])
+        -> HookResult<()>
+    {
+        with_aspects(&aspects, |aspects| {
+            aspects.iter().fold_defresult(|aspect| {
+                debug!("[Aspect][exec]: {:?}", aspect);
+                aspect.access_transaction(fles)
+            })
+        }).map_err(Box::new)
+            .map_err(|e| HookErrorKind::HookExecutionError.into_error_with_cause(e))
+    }
+
+}
+
+/// Call `f` with the aspects of a hook position
+///
+/// The aspects are only read while the hooks are executed, so a hook may alter other entries,
+/// which executes the hooks of the same position for these entries, too. `Aspect` makes sure a
+/// hook is not executed recursively for the entry it is executed for already.
+fn with_aspects<F>(aspects: &Arc<RwLock<Vec<Aspect>>>, f: F) -> HookResult<()>
+    where F: FnOnce(&[Aspect]) -> HookResult<()>
+{
+    let aspects = match aspects.read() {
+        Err(_) => return Err(HookErrorKind::HookExecutionError.into()),
+        Ok(g) => g
+    };
+
+    f(&aspects)
+}
+
+/// Path of a file the store keeps for itself (like the index or the transaction journals): a file
+/// next to the store directory, or a hidden file inside the store if the store has no parent
+/// directory.
+fn sibling_path(location: &PathBuf, ext: &str) -> PathBuf {
+    match (location.parent(), location.file_name()) {
+        (Some(parent), Some(name)) => {
+            let mut name = name.to_os_string();
+            name.push(".");
+            name.push(ext);
+            parent.join(name)
+        },
+        _ => location.join(format!(".{}", ext)),
+    }
 }
 
 impl Debug for Store {
@@ -861,6 +1488,9 @@ impl Debug for Store {
         try!(write!(fmt, "\n"));
         try!(write!(fmt, " - location               : {:?}\n", self.location));
         try!(write!(fmt, " - configuration          : {:?}\n", self.configuration));
+        try!(write!(fmt, " - backend                : {:?}\n", self.backend));
+        try!(write!(fmt, " - index enabled          : {:?}\n", self.index.is_some()));
+        try!(write!(fmt, " - lock policy            : {:?}\n", self.lock_policy));
         try!(write!(fmt, " - pre_create_aspects     : {:?}\n", self.pre_create_aspects    ));
         try!(write!(fmt, " - post_create_aspects    : {:?}\n", self.post_create_aspects   ));
         try!(write!(fmt, " - pre_retrieve_aspects   : {:?}\n", self.pre_retrieve_aspects  ));
@@ -869,6 +1499,8 @@ impl Debug for Store {
         try!(write!(fmt, " - post_update_aspects    : {:?}\n", self.post_update_aspects   ));
         try!(write!(fmt, " - pre_delete_aspects     : {:?}\n", self.pre_delete_aspects    ));
         try!(write!(fmt, " - post_delete_aspects    : {:?}\n", self.post_delete_aspects   ));
+        try!(write!(fmt, " - pre_move_aspects       : {:?}\n", self.pre_move_aspects      ));
+        try!(write!(fmt, " - post_move_aspects      : {:?}\n", self.post_move_aspects     ));
         try!(write!(fmt, "\n"));
         try!(write!(fmt, "Entries:\n"));
         try!(write!(fmt, "{:?}", self.entries));
@@ -886,6 +1518,29 @@ impl Drop for Store {
      * TODO: Unlock them
      */
     fn drop(&mut self) {
+        if let Some(ref index) = self.index {
+            match index.lock() {
+                Ok(mut index) => if let Err(e) = index.save(&*self.backend) {
+                    warn!("Cannot write store index");
+                    trace_error(&e);
+                },
+                Err(_) => warn!("Store index lock poisoned, cannot write store index"),
+            }
+        }
+
+        match self.indices.lock() {
+            Ok(indices) => for index in indices.iter() {
+                match index.lock() {
+                    Ok(mut index) => if let Err(e) = index.save(&*self.backend) {
+                        warn!("Cannot write index");
+                        trace_error(&e);
+                    },
+                    Err(_) => warn!("Index lock poisoned, cannot write index"),
+                }
+            },
+            Err(_) => warn!("Index list lock poisoned, cannot write indexes"),
+        }
+
         match StoreId::new(Some(self.location.clone()), PathBuf::from(".")) {
             Err(e) => {
                 trace_error(&e);
@@ -909,6 +1564,9 @@ impl Drop for Store {
 pub struct FileLockEntry<'a> {
     store: &'a Store,
     entry: Entry,
+
+    /// Whether the entry is owned by a `Transaction`, which writes it instead of `Drop`
+    in_transaction: bool,
 }
 
 impl<'a> FileLockEntry<'a, > {
@@ -916,8 +1574,14 @@ impl<'a> FileLockEntry<'a, > {
         FileLockEntry {
             store: store,
             entry: entry,
+            in_transaction: false,
         }
     }
+
+    /// Get the store this entry belongs to
+    pub fn store(&self) -> &'a Store {
+        self.store
+    }
 }
 
 impl<'a> Debug for FileLockEntry<'a> {
@@ -943,9 +1607,18 @@ impl<'a> DerefMut for FileLockEntry<'a> {
 
 #[cfg(not(test))]
 impl<'a> Drop for FileLockEntry<'a> {
-    /// This will silently ignore errors, use `Store::update` if you want to catch the errors
+    /// Errors cannot be returned from here, use `Store::update` if you want to catch the errors
+    ///
+    /// If the entry cannot be written, for example because its header does not match a schema,
+    /// the error is reported, as the changes to the entry are lost.
     fn drop(&mut self) {
-        let _ = self.store._update(self, true);
+        if !self.in_transaction {
+            if let Err(e) = self.store._update(self, true) {
+                warn!("Could not write entry {}, changes are lost", self.get_location());
+                trace_error(&e);
+            }
+            let _ = self.store.release_entry(self.get_location());
+        }
     }
 }
 
@@ -953,7 +1626,86 @@ impl<'a> Drop for FileLockEntry<'a> {
 impl<'a> Drop for FileLockEntry<'a> {
     /// This will not silently ignore errors but prints the result of the _update() call for testing
     fn drop(&mut self) {
-        let _ = self.store._update(self, true).map_err(|e| trace_error(&e));
+        if !self.in_transaction {
+            let _ = self.store._update(self, true).map_err(|e| trace_error(&e));
+            let _ = self.store.release_entry(self.get_location()).map_err(|e| trace_error(&e));
+        }
+    }
+}
+
+/// A set of entries which are written to the store atomically
+///
+/// Entries are borrowed from the store as usual and then added to the transaction, which owns
+/// them from then on. `Transaction::commit()` writes all of them or none of them: the new
+/// contents are written to temporary files first and then moved in place, with a journal next
+/// to the store so an interrupted commit can be recovered when the store is opened the next time.
+///
+/// If the transaction is dropped without being committed, all changes to its entries are
+/// discarded.
+///
+/// ```ignore
+///  let mut a = try!(store.retrieve(id_a));
+///  let mut b = try!(store.retrieve(id_b));
+///  try!(a.add_internal_link(&mut b));
+///
+///  let mut tx = store.transaction();
+///  tx.add(a);
+///  tx.add(b);
+///  try!(tx.commit());
+/// ```
+pub struct Transaction<'a> {
+    store: &'a Store,
+    entries: Vec<FileLockEntry<'a>>,
+    committed: bool,
+}
+
+impl<'a> Transaction<'a> {
+
+    fn new(store: &'a Store) -> Transaction<'a> {
+        Transaction {
+            store: store,
+            entries: vec![],
+            committed: false,
+        }
+    }
+
+    /// Add an entry to the transaction
+    pub fn add(&mut self, mut entry: FileLockEntry<'a>) {
+        entry.in_transaction = true;
+        self.entries.push(entry);
+    }
+
+    /// Get an entry of the transaction for further modification
+    pub fn get_mut(&mut self, id: &StoreId) -> Option<&mut FileLockEntry<'a>> {
+        let id = id.clone().with_base(self.store.path().clone());
+        self.entries.iter_mut().find(|e| *e.get_location() == id)
+    }
+
+    /// Write all entries of the transaction to the store
+    pub fn commit(mut self) -> Result<()> {
+        let store = self.store;
+        store.commit_transaction(&mut self.entries, &mut self.committed)
+            .map_err_into(SEK::TransactionCommitError)
+    }
+
+}
+
+impl<'a> Debug for Transaction<'a> {
+    fn fmt(&self, fmt: &mut Formatter) -> RResult<(), FMTError> {
+        let ids : Vec<&StoreId> = self.entries.iter().map(|e| e.get_location()).collect();
+        write!(fmt, "Transaction({:?})", ids)
+    }
+}
+
+impl<'a> Drop for Transaction<'a> {
+    /// Discards the changes if the transaction was not committed
+    fn drop(&mut self) {
+        if !self.committed {
+            if let Err(e) = self.store.rollback_transaction(&self.entries) {
+                warn!("Rolling back transaction failed");
+                trace_error(&e);
+            }
+        }
     }
 }
 
@@ -1560,75 +2312,6 @@ impl Entry {
 
 }
 
-mod glob_store_iter {
-    use std::fmt::{Debug, Formatter};
-    use std::fmt::Error as FmtError;
-    use std::path::PathBuf;
-    use glob::Paths;
-    use storeid::StoreId;
-    use storeid::StoreIdIterator;
-
-    use error::StoreErrorKind as SEK;
-    use error::MapErrInto;
-
-    use libimagerror::trace::trace_error;
-
-    pub struct GlobStoreIdIterator {
-        store_path: PathBuf,
-        paths: Paths,
-    }
-
-    impl Debug for GlobStoreIdIterator {
-
-        fn fmt(&self, fmt: &mut Formatter) -> Result<(), FmtError> {
-            write!(fmt, "GlobStoreIdIterator")
-        }
-
-    }
-
-    impl Into<StoreIdIterator> for GlobStoreIdIterator {
-
-        fn into(self) -> StoreIdIterator {
-            StoreIdIterator::new(Box::new(self))
-        }
-
-    }
-
-    impl GlobStoreIdIterator {
-
-        pub fn new(paths: Paths, store_path: PathBuf) -> GlobStoreIdIterator {
-            debug!("Create a GlobStoreIdIterator(store_path = {:?}, /* ... */)", store_path);
-
-            GlobStoreIdIterator {
-                store_path: store_path,
-                paths: paths,
-            }
-        }
-
-    }
-
-    impl Iterator for GlobStoreIdIterator {
-        type Item = StoreId;
-
-        fn next(&mut self) -> Option<StoreId> {
-            self.paths
-                .next()
-                .and_then(|o| {
-                    debug!("GlobStoreIdIterator::next() => {:?}", o);
-                    o.map_err_into(SEK::StoreIdHandlingError)
-                        .and_then(|p| StoreId::from_full_path(&self.store_path, p))
-                        .map_err(|e| {
-                            debug!("GlobStoreIdIterator error: {:?}", e);
-                            trace_error(&e);
-                        }).ok()
-                })
-        }
-
-    }
-
-}
-
-
 #[cfg(test)]
 mod test {
     extern crate env_logger;
@@ -2244,7 +2927,39 @@ mod store_tests {
     use super::Store;
 
     pub fn get_store() -> Store {
-        Store::new(PathBuf::from("/"), None).unwrap()
+        use file_abstraction::InMemoryBackend;
+        Store::new(PathBuf::from("/"), None, Box::new(InMemoryBackend::new())).unwrap()
+    }
+
+    /// Get an in-memory store at `location` with a configuration without hooks, extended by
+    /// `extra`
+    fn get_store_with_extra_config(location: &str, extra: &str) -> Store {
+        use toml::{Parser, Value};
+        use file_abstraction::InMemoryBackend;
+
+        let cfg = format!(r#"
+implicit-create            = true
+store-unload-hook-aspects  = []
+pre-create-hook-aspects    = []
+post-create-hook-aspects   = []
+pre-move-hook-aspects      = []
+post-move-hook-aspects     = []
+pre-retrieve-hook-aspects  = []
+post-retrieve-hook-aspects = []
+pre-update-hook-aspects    = []
+post-update-hook-aspects   = []
+pre-delete-hook-aspects    = []
+post-delete-hook-aspects   = []
+
+[hooks]
+[aspects]
+
+{}
+"#, extra);
+        let cfg = Parser::new(&cfg).parse().unwrap();
+        Store::new(PathBuf::from(location),
+                   Some(Value::Table(cfg)),
+                   Box::new(InMemoryBackend::new())).unwrap()
     }
 
     #[test]
@@ -2254,7 +2969,7 @@ mod store_tests {
         assert_eq!(store.location, PathBuf::from("/"));
         assert!(store.entries.read().unwrap().is_empty());
 
-        assert!(store.store_unload_aspects.lock().unwrap().is_empty());
+        assert!(store.store_unload_aspects.read().unwrap().is_empty());
 
         assert!(store.pre_create_aspects.lock().unwrap().is_empty());
         assert!(store.post_create_aspects.lock().unwrap().is_empty());
@@ -2269,181 +2984,494 @@ mod store_tests {
     }
 
     #[test]
-    fn test_store_create() {
+    fn test_store_create() {
+        let store = get_store();
+
+        for n in 1..100 {
+            let s = format!("test-{}", n);
+            let entry = store.create(PathBuf::from(s.clone())).unwrap();
+            assert!(entry.verify().is_ok());
+            let loc = entry.get_location().clone().into_pathbuf().unwrap();
+            assert!(loc.starts_with("/"));
+            assert!(loc.ends_with(s));
+        }
+    }
+
+    #[test]
+    fn test_store_get_create_get_delete_get() {
+        let store = get_store();
+
+        for n in 1..100 {
+            let res = store.get(PathBuf::from(format!("test-{}", n)));
+            assert!(match res { Ok(None) => true, _ => false, })
+        }
+
+        for n in 1..100 {
+            let s = format!("test-{}", n);
+            let entry = store.create(PathBuf::from(s.clone())).unwrap();
+
+            assert!(entry.verify().is_ok());
+
+            let loc = entry.get_location().clone().into_pathbuf().unwrap();
+
+            assert!(loc.starts_with("/"));
+            assert!(loc.ends_with(s));
+        }
+
+        for n in 1..100 {
+            let res = store.get(PathBuf::from(format!("test-{}", n)));
+            assert!(match res { Ok(Some(_)) => true, _ => false, })
+        }
+
+        for n in 1..100 {
+            assert!(store.delete(PathBuf::from(format!("test-{}", n))).is_ok())
+        }
+
+        for n in 1..100 {
+            let res = store.get(PathBuf::from(format!("test-{}", n)));
+            assert!(match res { Ok(None) => true, _ => false, })
+        }
+    }
+
+    #[test]
+    fn test_store_create_twice() {
+        use error::StoreErrorKind as SEK;
+
+        let store = get_store();
+
+        for n in 1..100 {
+            let s = format!("test-{}", n % 50);
+            store.create(PathBuf::from(s.clone()))
+                .map_err(|e| assert!(is_match!(e.err_type(), SEK::CreateCallError) && n >= 50))
+                .ok()
+                .map(|entry| {
+                    assert!(entry.verify().is_ok());
+                    let loc = entry.get_location().clone().into_pathbuf().unwrap();
+                    assert!(loc.starts_with("/"));
+                    assert!(loc.ends_with(s));
+                });
+        }
+    }
+
+    #[test]
+    fn test_store_create_in_hm() {
+        use storeid::StoreId;
+
+        let store = get_store();
+
+        for n in 1..100 {
+            let pb = StoreId::new_baseless(PathBuf::from(format!("test-{}", n))).unwrap();
+
+            assert!(store.entries.read().unwrap().get(&pb).is_none());
+            assert!(store.create(pb.clone()).is_ok());
+
+            let pb = pb.with_base(store.path().clone());
+            assert!(store.entries.read().unwrap().get(&pb).is_some());
+        }
+    }
+
+    #[test]
+    fn test_store_retrieve_in_hm() {
+        use storeid::StoreId;
+
+        let store = get_store();
+
+        for n in 1..100 {
+            let pb = StoreId::new_baseless(PathBuf::from(format!("test-{}", n))).unwrap();
+
+            assert!(store.entries.read().unwrap().get(&pb).is_none());
+            assert!(store.retrieve(pb.clone()).is_ok());
+
+            let pb = pb.with_base(store.path().clone());
+            assert!(store.entries.read().unwrap().get(&pb).is_some());
+        }
+    }
+
+    #[test]
+    fn test_get_none() {
+        let store = get_store();
+
+        for n in 1..100 {
+            match store.get(PathBuf::from(format!("test-{}", n))) {
+                Ok(None) => assert!(true),
+                _        => assert!(false),
+            }
+        }
+    }
+
+    #[test]
+    fn test_delete_none() {
+        let store = get_store();
+
+        for n in 1..100 {
+            match store.delete(PathBuf::from(format!("test-{}", n))) {
+                Err(_) => assert!(true),
+                _      => assert!(false),
+            }
+        }
+    }
+
+    #[test]
+    fn test_retrieve_for_module() {
+        let pathes = vec![
+            "foo/1", "foo/2", "foo/3", "foo/4", "foo/5",
+            "bar/1", "bar/2", "bar/3", "bar/4", "bar/5",
+            "bla/1", "bla/2", "bla/3", "bla/4", "bla/5",
+            "boo/1", "boo/2", "boo/3", "boo/4", "boo/5",
+            "glu/1", "glu/2", "glu/3", "glu/4", "glu/5",
+        ];
+
+        fn test(store: &Store, modulename: &str) {
+            use std::path::Component;
+            use storeid::StoreId;
+
+            let retrieved = store.retrieve_for_module(modulename);
+            assert!(retrieved.is_ok());
+            let v : Vec<StoreId> = retrieved.unwrap().collect();
+            assert!(v.len() == 5);
+
+            let retrieved = store.retrieve_for_module(modulename);
+            assert!(retrieved.is_ok());
+
+            assert!(retrieved.unwrap().all(|e| {
+                let first = e.components().next();
+                assert!(first.is_some());
+                match first.unwrap() {
+                    Component::Normal(s) => s == modulename,
+                    _                    => false,
+                }
+            }))
+        }
+
+        let store = get_store();
+        for path in pathes {
+            assert!(store.create(PathBuf::from(path)).is_ok());
+        }
+
+        test(&store, "foo");
+        test(&store, "bar");
+        test(&store, "bla");
+        test(&store, "boo");
+        test(&store, "glu");
+    }
+
+    #[test]
+    fn test_walk() {
+        use super::StoreObject;
+
+        let store = get_store();
+        for path in &["foo/a", "foo/sub/b", "bar/c"] {
+            assert!(store.create(PathBuf::from(path)).is_ok());
+        }
+
+        let objects = store.walk("")
+            .map(|object| match object {
+                StoreObject::Id(id)           => format!("id {}", id),
+                StoreObject::Collection(path) => format!("collection {}", path.display()),
+            })
+            .collect::<Vec<_>>();
+
+        assert_eq!(objects, vec![
+            "collection /",
+            "collection /bar",
+            "id bar/c",
+            "collection /foo",
+            "id foo/a",
+            "collection /foo/sub",
+            "id foo/sub/b",
+        ]);
+
+        let foo = store.walk("foo").filter(|o| is_match!(*o, StoreObject::Id(_))).count();
+        assert_eq!(foo, 2);
+    }
+
+    #[test]
+    fn test_walk_skips_hidden_files() {
+        use super::StoreObject;
+
+        let store = get_store();
+        for path in &["foo/a", ".git/config", "foo/.b", "bar/.hidden/c"] {
+            assert!(store.create(PathBuf::from(path)).is_ok());
+        }
+
+        let ids = store.walk("")
+            .filter_map(|object| match object {
+                StoreObject::Id(id) => Some(format!("{}", id)),
+                _                   => None,
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(ids, vec!["foo/a"]);
+
+        let collections = store.walk("")
+            .filter_map(|object| match object {
+                StoreObject::Collection(path) => Some(path),
+                _                             => None,
+            })
+            .filter(|path| path.to_str().map(|s| s.contains("/.")).unwrap_or(false))
+            .count();
+        assert_eq!(collections, 0);
+    }
+
+    #[test]
+    fn test_exists() {
+        let store = get_store();
+        assert!(!store.exists(PathBuf::from("test/exists")).unwrap());
+
+        {
+            let _entry = store.create(PathBuf::from("test/exists")).unwrap();
+            assert!(store.exists(PathBuf::from("test/exists")).unwrap());
+        }
+
+        assert!(store.exists(PathBuf::from("test/exists")).unwrap());
+        assert!(store.delete(PathBuf::from("test/exists")).is_ok());
+        assert!(!store.exists(PathBuf::from("test/exists")).unwrap());
+    }
+
+    #[test]
+    fn test_store_index_disabled() {
+        use toml::Value;
+        use error::StoreErrorKind as SEK;
+
+        let store = get_store();
+        let query = store.query_header_eq("imag.version", &Value::String(String::from("0")));
+
+        assert!(store.index().is_none());
+        assert!(query.is_err());
+        assert!(is_match!(store.reindex().unwrap_err().err_type(), SEK::ReindexCallError));
+    }
+
+    #[test]
+    fn test_store_reindex() {
+        use toml::Value;
+        use storeid::StoreId;
+
+        let store = get_store_with_extra_config("/store", r#"
+[index]
+enabled = true
+path = "/index"
+"#);
+
+        for n in 1..10 {
+            let mut entry = store.create(PathBuf::from(format!("test-{}", n))).unwrap();
+            let tags = if n % 3 == 0 { vec![Value::String(String::from("three"))] } else { vec![] };
+            entry.get_header_mut().set("imag.tags", Value::Array(tags)).unwrap();
+        }
+
+        assert!(store.reindex().is_ok());
+
+        let three = Value::String(String::from("three"));
+        let mut ids : Vec<StoreId> = store.query_header_contains("imag.tags", &three)
+            .unwrap()
+            .collect();
+        ids.sort();
+
+        let expected : Vec<StoreId> = vec!["test-3", "test-6", "test-9"]
+            .into_iter()
+            .map(|s| StoreId::new(Some(PathBuf::from("/store")), PathBuf::from(s)).unwrap())
+            .collect();
+        assert_eq!(ids, expected);
+        assert_eq!(store.index().unwrap().lock().unwrap().len(), 9);
+    }
+
+    #[test]
+    fn test_store_transaction_commit() {
+        use storeid::StoreId;
+
         let store = get_store();
+        let ids : Vec<StoreId> = vec!["tx-a", "tx-b"]
+            .into_iter()
+            .map(|s| StoreId::new_baseless(PathBuf::from(s)).unwrap())
+            .collect();
 
-        for n in 1..100 {
-            let s = format!("test-{}", n);
-            let entry = store.create(PathBuf::from(s.clone())).unwrap();
-            assert!(entry.verify().is_ok());
-            let loc = entry.get_location().clone().into_pathbuf().unwrap();
-            assert!(loc.starts_with("/"));
-            assert!(loc.ends_with(s));
+        for id in ids.iter() {
+            let _ = store.create(id.clone()).unwrap();
         }
+
+        {
+            let mut tx = store.transaction();
+            for id in ids.iter() {
+                let mut entry = store.retrieve(id.clone()).unwrap();
+                *entry.get_content_mut() = String::from("committed");
+                tx.add(entry);
+            }
+
+            assert!(tx.get_mut(&ids[0]).is_some());
+            assert!(tx.commit().is_ok());
+        }
+
+        for id in ids.iter() {
+            let entry = store.retrieve_copy(id.clone()).unwrap();
+            assert_eq!(entry.get_content(), "committed");
+        }
+        assert!(store.backend.list(&PathBuf::from("/.journal")).unwrap().is_empty());
+        assert_eq!(store.backend.list(&PathBuf::from("/")).unwrap().len(), 2);
     }
 
     #[test]
-    fn test_store_get_create_get_delete_get() {
+    fn test_store_transaction_rollback() {
+        use storeid::StoreId;
+
         let store = get_store();
+        let id    = StoreId::new_baseless(PathBuf::from("tx-rollback")).unwrap();
 
-        for n in 1..100 {
-            let res = store.get(PathBuf::from(format!("test-{}", n)));
-            assert!(match res { Ok(None) => true, _ => false, })
+        {
+            let mut entry = store.create(id.clone()).unwrap();
+            *entry.get_content_mut() = String::from("original");
         }
 
-        for n in 1..100 {
-            let s = format!("test-{}", n);
-            let entry = store.create(PathBuf::from(s.clone())).unwrap();
+        {
+            let mut tx = store.transaction();
+            let mut entry = store.retrieve(id.clone()).unwrap();
+            *entry.get_content_mut() = String::from("discarded");
+            tx.add(entry);
+        }
 
-            assert!(entry.verify().is_ok());
+        let entry = store.retrieve(id.clone()).unwrap();
+        assert_eq!(entry.get_content(), "original");
+    }
 
-            let loc = entry.get_location().clone().into_pathbuf().unwrap();
+    #[test]
+    fn test_store_entry_locked_by_other_process() {
+        use error::StoreErrorKind as SEK;
+        use storeid::StoreId;
 
-            assert!(loc.starts_with("/"));
-            assert!(loc.ends_with(s));
-        }
+        let store = get_store_with_extra_config("/", r#"
+[locking]
+policy = "fail"
+"#);
+        let id = StoreId::new_baseless(PathBuf::from("locked")).unwrap();
 
-        for n in 1..100 {
-            let res = store.get(PathBuf::from(format!("test-{}", n)));
-            assert!(match res { Ok(Some(_)) => true, _ => false, })
-        }
+        // Simulate another process holding the lock
+        assert!(store.backend.try_lock(&store.lock_path(&id)).unwrap());
 
-        for n in 1..100 {
-            assert!(store.delete(PathBuf::from(format!("test-{}", n))).is_ok())
-        }
+        let err = store.retrieve(id.clone()).unwrap_err();
+        assert_eq!(err.err_type(), SEK::RetrieveCallError);
+        assert!(store.delete(id.clone()).is_err());
 
-        for n in 1..100 {
-            let res = store.get(PathBuf::from(format!("test-{}", n)));
-            assert!(match res { Ok(None) => true, _ => false, })
-        }
+        assert!(store.backend.unlock(&store.lock_path(&id)).is_ok());
+        assert!(store.retrieve(id.clone()).is_ok());
     }
 
     #[test]
-    fn test_store_create_twice() {
+    fn test_store_entry_borrowed_by_this_process() {
+        use std::error::Error;
         use error::StoreErrorKind as SEK;
+        use storeid::StoreId;
 
-        let store = get_store();
+        // With a blocking policy, waiting for our own lock would never return
+        let store = get_store_with_extra_config("/", r#"
+[locking]
+policy = "block"
+"#);
+        let id = StoreId::new_baseless(PathBuf::from("borrowed")).unwrap();
 
-        for n in 1..100 {
-            let s = format!("test-{}", n % 50);
-            store.create(PathBuf::from(s.clone()))
-                .map_err(|e| assert!(is_match!(e.err_type(), SEK::CreateCallError) && n >= 50))
-                .ok()
-                .map(|entry| {
-                    assert!(entry.verify().is_ok());
-                    let loc = entry.get_location().clone().into_pathbuf().unwrap();
-                    assert!(loc.starts_with("/"));
-                    assert!(loc.ends_with(s));
-                });
-        }
+        let _entry = store.create(id.clone()).unwrap();
+        let err    = store.retrieve(id.clone()).unwrap_err();
+        assert_eq!(err.err_type(), SEK::RetrieveCallError);
+        assert!(err.cause().map(|c| c.to_string().contains("borrowed")).unwrap_or(false));
     }
 
     #[test]
-    fn test_store_create_in_hm() {
+    fn test_store_lock_released_on_drop() {
         use storeid::StoreId;
 
         let store = get_store();
+        let id    = StoreId::new_baseless(PathBuf::from("lock-release")).unwrap();
+        let path  = store.lock_path(&id.clone().with_base(store.path().clone()));
 
-        for n in 1..100 {
-            let pb = StoreId::new_baseless(PathBuf::from(format!("test-{}", n))).unwrap();
-
-            assert!(store.entries.read().unwrap().get(&pb).is_none());
-            assert!(store.create(pb.clone()).is_ok());
-
-            let pb = pb.with_base(store.path().clone());
-            assert!(store.entries.read().unwrap().get(&pb).is_some());
+        {
+            let _entry = store.create(id.clone()).unwrap();
+            assert!(!store.backend.try_lock(&path).unwrap());
         }
+
+        assert!(store.backend.try_lock(&path).unwrap());
     }
 
     #[test]
-    fn test_store_retrieve_in_hm() {
-        use storeid::StoreId;
+    fn test_store_update_validates_schema() {
+        use std::collections::BTreeMap;
+        use toml::Value;
+        use error::StoreErrorKind as SEK;
+        use schema::{FieldType, Schema};
 
-        let store = get_store();
+        let store  = get_store();
+        let schema = Schema::new("test").required("value", FieldType::Integer);
+        assert!(store.register_schema(schema.clone()).is_ok());
+        assert!(store.register_schema(schema).is_err());
 
-        for n in 1..100 {
-            let pb = StoreId::new_baseless(PathBuf::from(format!("test-{}", n))).unwrap();
+        let mut entry = store.create(PathBuf::from("schema-test")).unwrap();
+        entry.get_header_mut().set("test", Value::Table(BTreeMap::new())).unwrap();
+        entry.get_header_mut().set("test.value", Value::String(String::from("1"))).unwrap();
 
-            assert!(store.entries.read().unwrap().get(&pb).is_none());
-            assert!(store.retrieve(pb.clone()).is_ok());
+        let err = store.update(entry).unwrap_err();
+        assert_eq!(err.err_type(), SEK::UpdateCallError);
 
-            let pb = pb.with_base(store.path().clone());
-            assert!(store.entries.read().unwrap().get(&pb).is_some());
-        }
+        let mut entry = store.retrieve(PathBuf::from("schema-test")).unwrap();
+        entry.get_header_mut().set("test", Value::Table(BTreeMap::new())).unwrap();
+        entry.get_header_mut().set("test.value", Value::Integer(1)).unwrap();
+        assert!(store.update(entry).is_ok());
     }
 
     #[test]
-    fn test_get_none() {
-        let store = get_store();
+    fn test_store_entry_released_if_write_fails() {
+        use std::collections::BTreeMap;
+        use toml::Value;
+        use schema::{FieldType, Schema};
 
-        for n in 1..100 {
-            match store.get(PathBuf::from(format!("test-{}", n))) {
-                Ok(None) => assert!(true),
-                _        => assert!(false),
-            }
+        let store  = get_store();
+        let schema = Schema::new("test").required("value", FieldType::Integer);
+        assert!(store.register_schema(schema).is_ok());
+
+        {
+            let _ = store.create(PathBuf::from("release-test")).unwrap();
+        }
+
+        {
+            let mut entry = store.retrieve(PathBuf::from("release-test")).unwrap();
+            entry.get_header_mut().set("test", Value::Table(BTreeMap::new())).unwrap();
+            entry.get_header_mut().set("test.value", Value::String(String::from("1"))).unwrap();
         }
+
+        let entry = store.retrieve(PathBuf::from("release-test")).unwrap();
+        assert!(entry.get_header().read("test").unwrap().is_none());
     }
 
     #[test]
-    fn test_delete_none() {
-        let store = get_store();
+    fn test_store_migrate() {
+        use semver::Version;
+        use toml::Value;
+        use migration::Migration;
 
-        for n in 1..100 {
-            match store.delete(PathBuf::from(format!("test-{}", n))) {
-                Err(_) => assert!(true),
-                _      => assert!(false),
-            }
+        let store = get_store();
+        {
+            let mut entry = store.create(PathBuf::from("migrate-test")).unwrap();
+            entry.get_header_mut().set("imag.version", Value::String(String::from("0.0.1"))).unwrap();
         }
-    }
 
-    // Disabled because we cannot test this by now, as we rely on glob() in
-    // Store::retieve_for_module(), which accesses the filesystem and tests run in-memory, so there
-    // are no files on the filesystem in this test after Store::create().
-    //
-    // #[test]
-    // fn test_retrieve_for_module() {
-    //     let pathes = vec![
-    //         "foo/1", "foo/2", "foo/3", "foo/4", "foo/5",
-    //         "bar/1", "bar/2", "bar/3", "bar/4", "bar/5",
-    //         "bla/1", "bla/2", "bla/3", "bla/4", "bla/5",
-    //         "boo/1", "boo/2", "boo/3", "boo/4", "boo/5",
-    //         "glu/1", "glu/2", "glu/3", "glu/4", "glu/5",
-    //     ];
-
-    //     fn test(store: &Store, modulename: &str) {
-    //         use std::path::Component;
-    //         use storeid::StoreId;
-
-    //         let retrieved = store.retrieve_for_module(modulename);
-    //         assert!(retrieved.is_ok());
-    //         let v : Vec<StoreId> = retrieved.unwrap().collect();
-    //         println!("v = {:?}", v);
-    //         assert!(v.len() == 5);
-
-    //         let retrieved = store.retrieve_for_module(modulename);
-    //         assert!(retrieved.is_ok());
-
-    //         assert!(retrieved.unwrap().all(|e| {
-    //             let first = e.components().next();
-    //             assert!(first.is_some());
-    //             match first.unwrap() {
-    //                 Component::Normal(s) => s == modulename,
-    //                 _                    => false,
-    //             }
-    //         }))
-    //     }
-
-    //     let store = get_store();
-    //     for path in pathes {
-    //         assert!(store.create(PathBuf::from(path)).is_ok());
-    //     }
-
-    //     test(&store, "foo");
-    //     test(&store, "bar");
-    //     test(&store, "bla");
-    //     test(&store, "boo");
-    //     test(&store, "glu");
-    // }
+        let migration = Migration::new("test",
+                                       Version::parse("0.0.1").unwrap(),
+                                       Version::parse("0.0.2").unwrap(),
+                                       |e| {
+                                           *e.get_content_mut() = String::from("migrated");
+                                           Ok(())
+                                       }).unwrap();
+        assert!(store.register_migration(migration).is_ok());
+
+        let steps = store.migrate(true).unwrap();
+        assert_eq!(steps.len(), 1);
+        assert_eq!(store.backend.read(&PathBuf::from("/migrate-test")).map(|buf| {
+            String::from_utf8(buf).unwrap().contains("migrated")
+        }).unwrap(), false);
+
+        let steps = store.migrate(false).unwrap();
+        assert_eq!(steps.len(), 1);
+        assert!(store.migrate(true).unwrap().is_empty());
+
+        let entry = store.retrieve_copy(PathBuf::from("migrate-test")).unwrap();
+        assert_eq!(entry.get_content(), "migrated");
+        assert_eq!(entry.get_header().read("imag.version").unwrap(),
+                   Some(Value::String(String::from("0.0.2"))));
+    }
 
     #[test]
     fn test_store_move_moves_in_hm() {
@@ -2535,6 +3563,8 @@ mod store_hook_tests {
                     HP::PostRetrieve |
                     HP::PreUpdate    |
                     HP::PostUpdate   => HDA::MutableAccess(&self.accessor),
+                    HP::PreMove      |
+                    HP::PostMove     => HDA::MoveAccess(&self.accessor),
                 }
             }
 
@@ -2545,6 +3575,7 @@ mod store_hook_tests {
             use hook::accessor::MutableHookDataAccessor;
             use hook::accessor::NonMutableHookDataAccessor;
             use hook::accessor::StoreIdAccessor;
+            use hook::accessor::StoreIdMoveAccessor;
             use hook::position::HookPosition;
             use store::FileLockEntry;
             use storeid::StoreId;
@@ -2614,24 +3645,40 @@ mod store_hook_tests {
 
             }
 
+            impl StoreIdMoveAccessor for TestHookAccessor {
+
+                fn access_move(&self, from: &StoreId, to: &StoreId) -> HookResult<()> {
+                    get_result(self.succeed, self.error_aborting)
+                }
+
+            }
+
         }
 
     }
 
     use std::path::PathBuf;
+    use std::sync::{Arc, Mutex};
 
+    use toml::Value;
+
+    use hook::Hook;
+    use hook::accessor::{HookDataAccessor, HookDataAccessorProvider, NonMutableHookDataAccessor};
     use hook::position::HookPosition as HP;
+    use hook::result::HookResult;
     use storeid::StoreId;
-    use store::Store;
+    use store::{FileLockEntry, Store};
 
     use self::test_hook::TestHook;
 
     fn get_store_with_config() -> Store {
         use toml::Parser;
+        use file_abstraction::InMemoryBackend;
 
         let cfg = Parser::new(mini_config()).parse().unwrap();
         println!("Config parsed: {:?}", cfg);
-        Store::new(PathBuf::from("/"), Some(cfg.get("store").cloned().unwrap())).unwrap()
+        let backend = Box::new(InMemoryBackend::new());
+        Store::new(PathBuf::from("/"), Some(cfg.get("store").cloned().unwrap()), backend).unwrap()
     }
 
     fn mini_config() -> &'static str {
@@ -2774,10 +3821,21 @@ aspect = "test"
         test_hook_execution(&[HP::PostDelete], "test_postdelete");
     }
 
+    #[test]
+    fn test_premove() {
+        test_hook_execution(&[HP::PreMove], "test_premove");
+    }
+
+    #[test]
+    fn test_postmove() {
+        test_hook_execution(&[HP::PostMove], "test_postmove");
+    }
+
     #[test]
     fn test_multiple_same_position() {
         let positions = [ HP::StoreUnload, HP::PreCreate, HP::PostCreate, HP::PreRetrieve,
-            HP::PostRetrieve, HP::PreUpdate, HP::PostUpdate, HP::PreDelete, HP::PostDelete ];
+            HP::PostRetrieve, HP::PreUpdate, HP::PostUpdate, HP::PreDelete, HP::PostDelete,
+            HP::PreMove, HP::PostMove ];
 
         for position in positions.iter() {
             for n in 2..10 {
@@ -2832,6 +3890,20 @@ aspect = "test"
         assert!(store.update(fle).is_err());
     }
 
+    #[test]
+    fn test_pre_move_error() {
+        let storeid = StoreId::new_baseless(PathBuf::from("test_pre_move_error")).unwrap();
+        let moved   = StoreId::new_baseless(PathBuf::from("test_pre_move_error-moved")).unwrap();
+        let store   = get_store_with_aborting_hook_at_pos(HP::PreMove);
+
+        assert!(store.create(storeid.clone()).is_ok());
+        assert!(store.move_by_id(storeid.clone(), moved.clone()).is_err());
+
+        // The entry was not moved, as the hook fails pre-move
+        assert!(store.entries.read().unwrap().get(&storeid.with_base(store.path().clone())).is_some());
+        assert!(store.entries.read().unwrap().get(&moved.with_base(store.path().clone())).is_none());
+    }
+
     #[test]
     fn test_post_create_error() {
         let store   = get_store_with_aborting_hook_at_pos(HP::PostCreate);
@@ -2868,6 +3940,20 @@ aspect = "test"
         assert!(store.entries.read().unwrap().get(&pb).is_none());
     }
 
+    #[test]
+    fn test_post_move_error() {
+        let store   = get_store_with_aborting_hook_at_pos(HP::PostMove);
+        let pb      = StoreId::new_baseless(PathBuf::from("test_post_move_error")).unwrap();
+        let moved   = StoreId::new_baseless(PathBuf::from("test_post_move_error-moved")).unwrap();
+
+        assert!(store.create(pb.clone()).is_ok());
+        assert!(store.move_by_id(pb.clone(), moved.clone()).is_err());
+
+        // But the entry is moved, as we fail post-move
+        assert!(store.entries.read().unwrap().get(&pb.with_base(store.path().clone())).is_none());
+        assert!(store.entries.read().unwrap().get(&moved.with_base(store.path().clone())).is_some());
+    }
+
     #[test]
     fn test_post_update_error() {
         let store   = get_store_with_aborting_hook_at_pos(HP::PostUpdate);
@@ -2964,5 +4050,249 @@ aspect = "test"
         assert!(store.entries.read().unwrap().get(&pb).is_some());
         assert!(store.update(fle).is_ok());
     }
-}
 
+    #[test]
+    fn test_transaction_hook_executed_once() {
+        use std::sync::Arc;
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        use toml::Value;
+
+        use hook::Hook;
+        use hook::accessor::{HookDataAccessor, HookDataAccessorProvider};
+        use hook::accessor::{StoreIdAccessor, TransactionAccessor};
+        use hook::result::HookResult;
+
+        #[derive(Debug)]
+        struct CountingHook {
+            per_entry: Arc<AtomicUsize>,
+            per_transaction: Arc<AtomicUsize>,
+        }
+
+        impl Hook for CountingHook {
+            fn name(&self) -> &'static str { "testhook_counting" }
+            fn set_config(&mut self, _: &Value) { }
+        }
+
+        impl HookDataAccessorProvider for CountingHook {
+            fn accessor(&self) -> HookDataAccessor {
+                HookDataAccessor::StoreIdAccess(self)
+            }
+
+            fn transaction_accessor(&self) -> Option<&TransactionAccessor> {
+                Some(self)
+            }
+        }
+
+        impl StoreIdAccessor for CountingHook {
+            fn access(&self, _: &StoreId) -> HookResult<()> {
+                self.per_entry.fetch_add(1, Ordering::SeqCst);
+                Ok(())
+            }
+        }
+
+        impl TransactionAccessor for CountingHook {
+            fn access_transaction(&self, ids: &[StoreId]) -> HookResult<()> {
+                assert_eq!(ids.len(), 2);
+                self.per_transaction.fetch_add(1, Ordering::SeqCst);
+                Ok(())
+            }
+        }
+
+        let per_entry       = Arc::new(AtomicUsize::new(0));
+        let per_transaction = Arc::new(AtomicUsize::new(0));
+        let mut store       = get_store_with_config();
+        let hook            = CountingHook {
+            per_entry: per_entry.clone(),
+            per_transaction: per_transaction.clone(),
+        };
+        assert!(store.register_hook(HP::PostUpdate, "test", Box::new(hook)).is_ok());
+
+        let a = StoreId::new_baseless(PathBuf::from("test_transaction_hook_a")).unwrap();
+        let b = StoreId::new_baseless(PathBuf::from("test_transaction_hook_b")).unwrap();
+        let mut tx = store.transaction();
+        tx.add(store.create(a).unwrap());
+        tx.add(store.create(b).unwrap());
+        assert!(tx.commit().is_ok());
+
+        assert_eq!(per_transaction.load(Ordering::SeqCst), 1);
+        assert_eq!(per_entry.load(Ordering::SeqCst), 0);
+    }
+
+    #[derive(Debug)]
+    struct RecordingHook {
+        seen: Arc<Mutex<Vec<(PathBuf, String)>>>,
+    }
+
+    impl Hook for RecordingHook {
+        fn name(&self) -> &'static str { "testhook_recording_content" }
+        fn set_config(&mut self, _: &Value) { }
+    }
+
+    impl HookDataAccessorProvider for RecordingHook {
+        fn accessor(&self) -> HookDataAccessor {
+            HookDataAccessor::NonMutableAccess(self)
+        }
+    }
+
+    impl NonMutableHookDataAccessor for RecordingHook {
+        fn access(&self, fle: &FileLockEntry) -> HookResult<()> {
+            let id = fle.get_location().local().clone();
+            self.seen.lock().unwrap().push((id, fle.get_content().clone()));
+            Ok(())
+        }
+    }
+
+    fn get_store_with_recording_hook_at_pos(pos: HP)
+        -> (Store, Arc<Mutex<Vec<(PathBuf, String)>>>)
+    {
+        let seen      = Arc::new(Mutex::new(vec![]));
+        let mut store = get_store_with_config();
+        let hook      = RecordingHook { seen: seen.clone() };
+        assert!(store.register_hook(pos, "test", Box::new(hook)).is_ok());
+        (store, seen)
+    }
+
+    #[test]
+    fn test_save_to_executes_create_hooks() {
+        let (store, seen) = get_store_with_recording_hook_at_pos(HP::PostCreate);
+
+        {
+            let mut entry = store.create(PathBuf::from("test_save_to_original")).unwrap();
+            *entry.get_content_mut() = String::from("content");
+            let new_id = StoreId::new_baseless(PathBuf::from("test_save_to_copy")).unwrap();
+            assert!(store.save_to(&entry, new_id).is_ok());
+        }
+
+        let seen = seen.lock().unwrap();
+        assert_eq!(seen.len(), 2);
+        assert_eq!(seen[1].0, PathBuf::from("test_save_to_copy"));
+
+        let copy = store.get(PathBuf::from("test_save_to_copy")).unwrap().unwrap();
+        assert_eq!(copy.get_content(), "content");
+    }
+
+    #[test]
+    fn test_save_as_executes_update_hooks_with_saved_entry() {
+        let (store, seen) = get_store_with_recording_hook_at_pos(HP::PostUpdate);
+
+        {
+            let mut entry = store.create(PathBuf::from("test_save_as_original")).unwrap();
+            *entry.get_content_mut() = String::from("altered");
+            let new_id = StoreId::new_baseless(PathBuf::from("test_save_as_moved")).unwrap();
+            assert!(store.save_as(entry, new_id).is_ok());
+        }
+
+        let moved = (PathBuf::from("test_save_as_moved"), String::from("altered"));
+        let seen  = seen.lock().unwrap();
+        assert!(!seen.is_empty());
+        assert!(seen.iter().all(|s| *s == moved));
+    }
+
+    #[test]
+    fn test_transaction_failing_post_update_hook_keeps_changes() {
+        let store = get_store_with_aborting_hook_at_pos(HP::PostUpdate);
+        let id    = StoreId::new_baseless(PathBuf::from("test_transaction_post_update")).unwrap();
+
+        {
+            let mut tx    = store.transaction();
+            let mut entry = store.create(id.clone()).unwrap();
+            *entry.get_content_mut() = String::from("committed");
+            tx.add(entry);
+            assert!(tx.commit().is_err());
+        }
+
+        // The entries were written before the hook failed, so they are not rolled back
+        let pb = id.clone().with_base(store.path().clone());
+        assert!(store.entries.read().unwrap().get(&pb).is_some());
+        let entry = store.get(id).unwrap().unwrap();
+        assert_eq!(entry.get_content(), "committed");
+    }
+
+    #[test]
+    fn test_hook_updating_other_entries_executes_hooks_for_them() {
+        use std::sync::{Arc, Mutex};
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        use toml::Value;
+
+        use hook::Hook;
+        use hook::accessor::{HookDataAccessor, HookDataAccessorProvider, MutableHookDataAccessor};
+        use hook::accessor::NonMutableHookDataAccessor;
+        use hook::result::HookResult;
+        use store::FileLockEntry;
+
+        #[derive(Debug)]
+        struct TouchingHook {
+            calls: Arc<AtomicUsize>,
+        }
+
+        impl Hook for TouchingHook {
+            fn name(&self) -> &'static str { "testhook_touching" }
+            fn set_config(&mut self, _: &Value) { }
+        }
+
+        impl HookDataAccessorProvider for TouchingHook {
+            fn accessor(&self) -> HookDataAccessor {
+                HookDataAccessor::MutableAccess(self)
+            }
+        }
+
+        impl MutableHookDataAccessor for TouchingHook {
+            fn access_mut(&self, fle: &mut FileLockEntry) -> HookResult<()> {
+                self.calls.fetch_add(1, Ordering::SeqCst);
+                if fle.get_location().local() == &PathBuf::from("test_touching_a") {
+                    let id    = PathBuf::from("test_touching_b");
+                    let mut b = fle.store().get(id).unwrap().unwrap();
+                    b.get_content_mut().push_str("touched");
+                }
+                Ok(())
+            }
+        }
+
+        #[derive(Debug)]
+        struct RecordingHook {
+            seen: Arc<Mutex<Vec<PathBuf>>>,
+        }
+
+        impl Hook for RecordingHook {
+            fn name(&self) -> &'static str { "testhook_recording" }
+            fn set_config(&mut self, _: &Value) { }
+        }
+
+        impl HookDataAccessorProvider for RecordingHook {
+            fn accessor(&self) -> HookDataAccessor {
+                HookDataAccessor::NonMutableAccess(self)
+            }
+        }
+
+        impl NonMutableHookDataAccessor for RecordingHook {
+            fn access(&self, fle: &FileLockEntry) -> HookResult<()> {
+                let id = fle.get_location().local().clone();
+                self.seen.lock().unwrap().push(id);
+                Ok(())
+            }
+        }
+
+        let calls     = Arc::new(AtomicUsize::new(0));
+        let seen      = Arc::new(Mutex::new(vec![]));
+        let mut store = get_store_with_config();
+        let _         = store.create(PathBuf::from("test_touching_b")).unwrap();
+        let touching  = TouchingHook { calls: calls.clone() };
+        let recording = RecordingHook { seen: seen.clone() };
+        assert!(store.register_hook(HP::PreUpdate, "test", Box::new(touching)).is_ok());
+        assert!(store.register_hook(HP::PreUpdate, "test", Box::new(recording)).is_ok());
+
+        {
+            let _ = store.create(PathBuf::from("test_touching_a")).unwrap();
+        }
+
+        // Both hooks ran for "test_touching_a" and for the entry the first hook updated
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+        assert_eq!(*seen.lock().unwrap(), vec![PathBuf::from("test_touching_b"),
+                                               PathBuf::from("test_touching_a")]);
+        let b = store.get(PathBuf::from("test_touching_b")).unwrap().unwrap();
+        assert_eq!(b.get_content(), "touched");
+    }
+
+}
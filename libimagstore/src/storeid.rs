@@ -30,6 +30,7 @@ use libimagerror::into::IntoError;
 
 use error::StoreErrorKind as SEK;
 use error::MapErrInto;
+use file_abstraction::StorageBackend;
 use store::Result;
 
 /// The Index into the Store
@@ -89,9 +90,12 @@ impl StoreId {
         Ok(base)
     }
 
-    pub fn exists(&self) -> bool {
+    /// Check whether there is a file for this StoreId in `backend`
+    ///
+    /// Returns false if the StoreId has no base.
+    pub fn exists(&self, backend: &StorageBackend) -> bool {
         // TODO: hiding error here.
-        self.clone().into_pathbuf().map(|pb| pb.exists()).unwrap_or(false)
+        self.clone().into_pathbuf().map(|pb| backend.is_file(&pb)).unwrap_or(false)
     }
 
     pub fn to_str(&self) -> Result<String> {
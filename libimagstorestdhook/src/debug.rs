@@ -72,6 +72,8 @@ impl HookDataAccessorProvider for DebugHook {
             HP::PostRetrieve |
             HP::PreUpdate    |
             HP::PostUpdate   => HDA::MutableAccess(&self.accessor),
+            HP::PreMove      |
+            HP::PostMove     => HDA::MoveAccess(&self.accessor),
         }
     }
 
@@ -86,6 +88,7 @@ pub mod accessor {
     use libimagstore::hook::accessor::MutableHookDataAccessor;
     use libimagstore::hook::accessor::NonMutableHookDataAccessor;
     use libimagstore::hook::accessor::StoreIdAccessor;
+    use libimagstore::hook::accessor::StoreIdMoveAccessor;
     use libimagstore::hook::position::HookPosition;
 
     #[derive(Debug)]
@@ -112,6 +115,15 @@ pub mod accessor {
 
     }
 
+    impl StoreIdMoveAccessor for DebugHookAccessor {
+
+        fn access_move(&self, from: &StoreId, to: &StoreId) -> HookResult<()> {
+            debug!("[DEBUG HOOK]: {:?} -> {:?}", from, to);
+            Ok(())
+        }
+
+    }
+
     impl MutableHookDataAccessor for DebugHookAccessor {
 
         fn access_mut(&self, fle: &mut FileLockEntry) -> HookResult<()> {
@@ -0,0 +1,137 @@
+//
+// imag - the personal information management suite for the commandline
+// Copyright (C) 2015, 2016 Matthias Beyer <mail@beyermatthias.de> and contributors
+//
+// This library is free software; you can redistribute it and/or
+// modify it under the terms of the GNU Lesser General Public
+// License as published by the Free Software Foundation; version
+// 2.1 of the License.
+//
+// This library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+// Lesser General Public License for more details.
+//
+// You should have received a copy of the GNU Lesser General Public
+// License along with this library; if not, write to the Free Software
+// Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA  02110-1301  USA
+//
+
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use toml::Value;
+
+use libimagerror::into::IntoError;
+use libimagstore::error::StoreError;
+use libimagstore::hook::Hook;
+use libimagstore::hook::accessor::HookDataAccessor as HDA;
+use libimagstore::hook::accessor::HookDataAccessorProvider;
+use libimagstore::hook::accessor::NonMutableHookDataAccessor;
+use libimagstore::hook::accessor::StoreIdAccessor;
+use libimagstore::hook::accessor::StoreIdMoveAccessor;
+use libimagstore::hook::error::CustomData;
+use libimagstore::hook::error::HookErrorKind as HEK;
+use libimagstore::hook::position::HookPosition;
+use libimagstore::hook::result::HookResult;
+use libimagstore::index::StoreIndex;
+use libimagstore::store::FileLockEntry;
+use libimagstore::storeid::StoreId;
+
+/// Hook which keeps an index over the store up to date
+///
+/// Register it for `PostCreate`, `PostUpdate`, `PostDelete` and `PostMove`, with the index from
+/// `Store::index()` or any other index registered with `Store::register_index()`.
+///
+/// Errors from this hook are never aborting. A stale index can be rebuilt (with `imag-store
+/// reindex` or `imag-search rebuild`), so there is no need to abort the store operation.
+#[derive(Debug)]
+pub struct IndexHook {
+    position: HookPosition,
+    index: Arc<Mutex<StoreIndex>>,
+}
+
+impl IndexHook {
+
+    pub fn new(position: HookPosition, index: Arc<Mutex<StoreIndex>>) -> IndexHook {
+        IndexHook {
+            position: position,
+            index: index,
+        }
+    }
+
+    fn with_index<F>(&self, f: F) -> HookResult<()>
+        where F: FnOnce(&mut MutexGuard<StoreIndex>) -> Result<(), StoreError>
+    {
+        let mut index = try!(self.index
+            .lock()
+            .map_err(|_| HEK::HookExecutionError.into_error())
+            .map_err(|e| e.with_custom_data(CustomData::default().aborting(false))));
+
+        f(&mut index)
+            .map_err(Box::new)
+            .map_err(|e| HEK::HookExecutionError.into_error_with_cause(e))
+            .map_err(|e| e.with_custom_data(CustomData::default().aborting(false)))
+    }
+
+}
+
+impl Hook for IndexHook {
+
+    fn name(&self) -> &'static str {
+        "stdhook_index"
+    }
+
+    fn set_config(&mut self, _: &Value) {
+        () // We are not configurable here.
+    }
+
+}
+
+impl HookDataAccessorProvider for IndexHook {
+
+    fn accessor(&self) -> HDA {
+        use libimagstore::hook::position::HookPosition as HP;
+
+        match self.position {
+            HP::PreMove      |
+            HP::PostMove     => HDA::MoveAccess(self),
+            HP::StoreUnload  |
+            HP::PreCreate    |
+            HP::PreRetrieve  |
+            HP::PreDelete    |
+            HP::PostDelete   => HDA::StoreIdAccess(self),
+            HP::PostCreate   |
+            HP::PostRetrieve |
+            HP::PreUpdate    |
+            HP::PostUpdate   => HDA::NonMutableAccess(self),
+        }
+    }
+
+}
+
+impl NonMutableHookDataAccessor for IndexHook {
+
+    fn access(&self, fle: &FileLockEntry) -> HookResult<()> {
+        debug!("[INDEX HOOK] Updating {:?}", fle.get_location());
+        self.with_index(|index| index.update(fle))
+    }
+
+}
+
+impl StoreIdAccessor for IndexHook {
+
+    fn access(&self, id: &StoreId) -> HookResult<()> {
+        debug!("[INDEX HOOK] Removing {:?}", id);
+        self.with_index(|index| index.remove(id))
+    }
+
+}
+
+impl StoreIdMoveAccessor for IndexHook {
+
+    fn access_move(&self, from: &StoreId, to: &StoreId) -> HookResult<()> {
+        debug!("[INDEX HOOK] Moving {:?} -> {:?}", from, to);
+        self.with_index(|index| index.rename(from, to))
+    }
+
+}
@@ -35,7 +35,6 @@
 
 #[macro_use] extern crate log;
 extern crate toml;
-extern crate fs2;
 extern crate git2;
 
 extern crate libimagstore;
@@ -45,7 +44,7 @@ extern crate libimaginteraction;
 extern crate libimagutil;
 
 pub mod debug;
-pub mod flock;
+pub mod index;
 pub mod linkverify;
 pub mod vcs;
 
@@ -21,6 +21,7 @@ use std::path::PathBuf;
 
 use toml::Value;
 
+use libimagstore::file_abstraction::StorageBackend;
 use libimagstore::hook::Hook;
 use libimagstore::hook::accessor::HookDataAccessor as HDA;
 use libimagstore::hook::accessor::HookDataAccessorProvider;
@@ -30,16 +31,19 @@ use libimagstore::store::FileLockEntry;
 use libimagentrylink::internal::InternalLinker;
 use libimagerror::trace::trace_error;
 
-#[derive(Debug, Clone)]
+#[derive(Debug)]
 pub struct LinkedEntriesExistHook {
     store_location: PathBuf,
+    backend: Box<StorageBackend>,
 }
 
 impl LinkedEntriesExistHook {
 
-    pub fn new(store_location: PathBuf) -> LinkedEntriesExistHook {
+    /// Create the hook for the store at `store_location`, which is persisted with `backend`
+    pub fn new(store_location: PathBuf, backend: Box<StorageBackend>) -> LinkedEntriesExistHook {
         LinkedEntriesExistHook {
             store_location: store_location,
+            backend: backend,
         }
     }
 
@@ -72,7 +76,7 @@ impl NonMutableHookDataAccessor for LinkedEntriesExistHook {
         let _ = fle.get_internal_links()
             .map(|links| {
                 for link in links {
-                    if !link.exists() {
+                    if !link.clone().with_base(self.store_location.clone()).exists(&*self.backend) {
                         warn!("File link does not exist: {:?} -> {:?}", fle.get_location(), link);
                     }
                 }
@@ -26,6 +26,7 @@ pub enum StoreAction {
     Retrieve,
     Update,
     Delete,
+    Move,
     StoreUnload,
 }
 
@@ -37,6 +38,7 @@ impl StoreAction {
             StoreAction::Retrieve    => "RETRIEVE",
             StoreAction::Update      => "UPDATE",
             StoreAction::Delete      => "DELETE",
+            StoreAction::Move        => "MOVE",
             StoreAction::StoreUnload => "STORE UNLOAD",
         }
     }
@@ -47,6 +49,7 @@ impl StoreAction {
             StoreAction::Retrieve    => "Retrieve",
             StoreAction::Update      => "Update",
             StoreAction::Delete      => "Delete",
+            StoreAction::Move        => "Move",
             StoreAction::StoreUnload => "Store Unload",
         }
     }
@@ -61,6 +64,7 @@ impl Display for StoreAction {
                     StoreAction::Retrieve    => "retrieve",
                     StoreAction::Update      => "update",
                     StoreAction::Delete      => "delete",
+                    StoreAction::Move        => "move",
                     StoreAction::StoreUnload => "store unload",
                 })
     }
@@ -38,6 +38,7 @@ generate_error_module!(
         RepositoryIndexFetchingError      => "Error while fetching Repository Index",
         RepositoryIndexWritingError       => "Error while writing Repository Index",
         RepositoryPathAddingError         => "Error while adding Path to Index",
+        RepositoryPathRemovingError       => "Error while removing Path from Index",
         RepositoryCommittingError         => "Error while committing",
         RepositoryParentFetchingError     => "Error while fetching parent of commit",
         RepositoryStatusFetchError        => "Error while fetching repository status",
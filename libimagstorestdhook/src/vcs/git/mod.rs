@@ -21,6 +21,7 @@ mod action;
 mod config;
 pub mod delete;
 mod error;
+pub mod move_entry;
 mod result;
 mod runtime;
 pub mod store_unload;
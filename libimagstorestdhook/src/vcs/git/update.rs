@@ -26,7 +26,7 @@ use toml::Value;
 
 use libimagerror::trace::trace_error;
 use libimagstore::hook::Hook;
-use libimagstore::hook::accessor::StoreIdAccessor;
+use libimagstore::hook::accessor::{StoreIdAccessor, TransactionAccessor};
 use libimagstore::hook::accessor::{HookDataAccessor, HookDataAccessorProvider};
 use libimagstore::hook::position::HookPosition;
 use libimagstore::hook::result::HookResult;
@@ -100,6 +100,10 @@ impl HookDataAccessorProvider for UpdateHook {
     fn accessor(&self) -> HookDataAccessor {
         HookDataAccessor::StoreIdAccess(self)
     }
+
+    fn transaction_accessor(&self) -> Option<&TransactionAccessor> {
+        Some(self)
+    }
 }
 
 impl StoreIdAccessor for UpdateHook {
@@ -112,6 +116,23 @@ impl StoreIdAccessor for UpdateHook {
     /// if there is no configuration for an interactive commit.
     ///
     fn access(&self, id: &StoreId) -> HookResult<()> {
+        self.commit(&[id.clone()])
+    }
+
+}
+
+impl TransactionAccessor for UpdateHook {
+
+    /// Commit all entries of a transaction in one commit
+    fn access_transaction(&self, ids: &[StoreId]) -> HookResult<()> {
+        self.commit(ids)
+    }
+
+}
+
+impl UpdateHook {
+
+    fn commit(&self, ids: &[StoreId]) -> HookResult<()> {
         use libimagerror::into::IntoError;
         use vcs::git::action::StoreAction;
         use vcs::git::config::commit_message;
@@ -122,7 +143,7 @@ impl StoreIdAccessor for UpdateHook {
         use vcs::git::config::committing_is_enabled;
         use git2::{ADD_DEFAULT, STATUS_WT_NEW, STATUS_WT_MODIFIED, IndexMatchedPath};
 
-        debug!("[GIT UPDATE HOOK]: {:?}", id);
+        debug!("[GIT UPDATE HOOK]: {:?}", ids);
 
         let action = StoreAction::Update;
         let cfg    = try!(self.runtime.config_value_or_err(&action));
@@ -150,21 +171,28 @@ impl StoreIdAccessor for UpdateHook {
         let _         = try!(self.runtime.ensure_cfg_branch_is_checked_out(&action));
         let repo      = try!(self.runtime.repository(&action));
 
-        let file_status = try!(
-            repo
-                .status_file(id.local())
-                .map_dbg_err_str("Failed to fetch file status")
-                .map_dbg_err(|e| format!("\t->  {:?}", e))
-                .map_dbg_str("[GIT UPDATE HOOK]: Fetched file status")
-                .map_err_into(GHEK::RepositoryFileStatusError)
-                .map_into_hook_error()
-        );
+        let mut changed = vec![];
+        for id in ids {
+            let file_status = try!(
+                repo
+                    .status_file(id.local())
+                    .map_dbg_err_str("Failed to fetch file status")
+                    .map_dbg_err(|e| format!("\t->  {:?}", e))
+                    .map_dbg_str("[GIT UPDATE HOOK]: Fetched file status")
+                    .map_err_into(GHEK::RepositoryFileStatusError)
+                    .map_into_hook_error()
+            );
 
-        debug!("File status: STATUS_WT_NEW      = {}", file_status.contains(STATUS_WT_NEW));
-        debug!("File status: STATUS_WT_MODIFIED = {}", file_status.contains(STATUS_WT_MODIFIED));
+            debug!("File status: STATUS_WT_NEW      = {}", file_status.contains(STATUS_WT_NEW));
+            debug!("File status: STATUS_WT_MODIFIED = {}", file_status.contains(STATUS_WT_MODIFIED));
 
-        if !file_status.contains(STATUS_WT_NEW) && !file_status.contains(STATUS_WT_MODIFIED) {
-            // File seems to be unmodified and not new. This means that the file is already
+            if file_status.contains(STATUS_WT_NEW) || file_status.contains(STATUS_WT_MODIFIED) {
+                changed.push(id);
+            }
+        }
+
+        if changed.is_empty() {
+            // The files seem to be unmodified and not new. This means that they are already
             // committed and we can return here.
             return Ok(())
         }
@@ -187,8 +215,10 @@ impl StoreIdAccessor for UpdateHook {
                 .map_into_hook_error()
         );
 
+        let paths : Vec<&Path> = changed.iter().map(|id| id.local().as_path()).collect();
+
         let cb = &mut |path: &Path, _matched_spec: &[u8]| -> i32 {
-            if file_status.contains(STATUS_WT_NEW) || file_status.contains(STATUS_WT_MODIFIED) {
+            if paths.iter().any(|p| *p == path) {
                 debug!("[GIT CREATE HOOK]: File is modified/new: {}", path.display());
                 0
             } else {
@@ -198,10 +228,10 @@ impl StoreIdAccessor for UpdateHook {
         };
 
         try!(
-            index.add_all(&[id.local()], ADD_DEFAULT, Some(cb as &mut IndexMatchedPath))
+            index.add_all(&paths, ADD_DEFAULT, Some(cb as &mut IndexMatchedPath))
                 .map_err_into(GHEK::RepositoryPathAddingError)
                 .map_dbg_err_str("Failed to add to index")
-                .map_dbg(|_| format!("[GIT UPDATE HOOK]: Added id ({:?}) to index", id))
+                .map_dbg(|_| format!("[GIT UPDATE HOOK]: Added ids ({:?}) to index", changed))
                 .map_into_hook_error()
         );
 
@@ -241,7 +271,7 @@ impl StoreIdAccessor for UpdateHook {
                 .map_into_hook_error()
         );
 
-        let message = try!(commit_message(&repo, cfg, StoreAction::Update, &id)
+        let message = try!(commit_message(&repo, cfg, StoreAction::Update, changed[0])
                 .map_dbg_err_str("Failed to get commit message")
                 .map_dbg_str("[GIT UPDATE HOOK]: Fetched commit message"));
 
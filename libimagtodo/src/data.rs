@@ -0,0 +1,440 @@
+//
+// imag - the personal information management suite for the commandline
+// Copyright (C) 2015, 2016 Matthias Beyer <mail@beyermatthias.de> and contributors
+//
+// This library is free software; you can redistribute it and/or
+// modify it under the terms of the GNU Lesser General Public
+// License as published by the Free Software Foundation; version
+// 2.1 of the License.
+//
+// This library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+// Lesser General Public License for more details.
+//
+// You should have received a copy of the GNU Lesser General Public
+// License along with this library; if not, write to the Free Software
+// Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA  02110-1301  USA
+//
+
+//! The task model
+//!
+//! `TaskData` holds the attributes of a task, which are kept in the `todo` header section of an
+//! entry. The model follows the one of taskwarrior, so tasks can be exchanged with taskwarrior
+//! without losing information, but it does not depend on taskwarrior in any way.
+
+use std::collections::BTreeMap;
+use std::fmt::{Display, Formatter, Error as FmtError};
+use std::str::FromStr;
+
+use chrono::offset::utc::UTC;
+use chrono::naive::date::NaiveDate;
+use chrono::naive::datetime::NaiveDateTime;
+use toml::Value;
+use uuid::Uuid;
+
+use libimagstore::store::EntryHeader;
+use libimagerror::into::IntoError;
+
+use error::{TodoError, TodoErrorKind as TEK, MapErrInto};
+use result::Result;
+
+/// The format used for all timestamps of a task, for example `20161018T120000Z`.
+///
+/// This is the format taskwarrior uses. Timestamps in this format compare correctly as strings.
+pub const TIMESTAMP_FORMAT : &'static str = "%Y%m%dT%H%M%SZ";
+
+/// The current time, formatted as timestamp
+pub fn now() -> String {
+    UTC::now().format(TIMESTAMP_FORMAT).to_string()
+}
+
+/// Parse a date as passed by the user into a timestamp.
+///
+/// Both timestamps and plain dates (`2016-10-31`) are understood.
+pub fn parse_date(s: &str) -> Option<String> {
+    if NaiveDateTime::parse_from_str(s, TIMESTAMP_FORMAT).is_ok() {
+        return Some(String::from(s));
+    }
+
+    NaiveDate::parse_from_str(s, "%Y-%m-%d")
+        .ok()
+        .map(|d| d.and_hms(0, 0, 0).format(TIMESTAMP_FORMAT).to_string())
+}
+
+/// The status of a task
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Status {
+    Pending,
+    Waiting,
+    Recurring,
+    Completed,
+    Deleted,
+}
+
+impl Status {
+
+    /// Whether the task still has to be done
+    pub fn is_open(&self) -> bool {
+        match *self {
+            Status::Pending | Status::Waiting | Status::Recurring => true,
+            Status::Completed | Status::Deleted                   => false,
+        }
+    }
+
+}
+
+impl FromStr for Status {
+    type Err = TodoError;
+
+    fn from_str(s: &str) -> Result<Status> {
+        match s {
+            "pending"   => Ok(Status::Pending),
+            "waiting"   => Ok(Status::Waiting),
+            "recurring" => Ok(Status::Recurring),
+            "completed" => Ok(Status::Completed),
+            "deleted"   => Ok(Status::Deleted),
+            _           => Err(TEK::InvalidStatus.into_error()),
+        }
+    }
+
+}
+
+impl Display for Status {
+
+    fn fmt(&self, fmt: &mut Formatter) -> ::std::result::Result<(), FmtError> {
+        let s = match *self {
+            Status::Pending   => "pending",
+            Status::Waiting   => "waiting",
+            Status::Recurring => "recurring",
+            Status::Completed => "completed",
+            Status::Deleted   => "deleted",
+        };
+        write!(fmt, "{}", s)
+    }
+
+}
+
+/// The priority of a task
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Priority {
+    High,
+    Medium,
+    Low,
+}
+
+impl FromStr for Priority {
+    type Err = TodoError;
+
+    fn from_str(s: &str) -> Result<Priority> {
+        match s {
+            "H" | "h" | "high"   => Ok(Priority::High),
+            "M" | "m" | "medium" => Ok(Priority::Medium),
+            "L" | "l" | "low"    => Ok(Priority::Low),
+            _                    => Err(TEK::InvalidPriority.into_error()),
+        }
+    }
+
+}
+
+impl Display for Priority {
+
+    fn fmt(&self, fmt: &mut Formatter) -> ::std::result::Result<(), FmtError> {
+        let s = match *self {
+            Priority::High   => "H",
+            Priority::Medium => "M",
+            Priority::Low    => "L",
+        };
+        write!(fmt, "{}", s)
+    }
+
+}
+
+/// An annotation of a task
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Annotation {
+    pub entry: String,
+    pub description: String,
+}
+
+/// The attributes of a task which are kept in the `todo` header section.
+///
+/// All timestamps are in the `TIMESTAMP_FORMAT`. `depends` holds the UUIDs of the tasks this task
+/// depends on, `recur` a recurrence as understood by `recurrence::Recurrence`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TaskData {
+    pub uuid: String,
+    pub description: String,
+    pub status: Status,
+    pub entry: String,
+    pub modified: Option<String>,
+    pub end: Option<String>,
+    pub due: Option<String>,
+    pub scheduled: Option<String>,
+    pub wait: Option<String>,
+    pub project: Option<String>,
+    pub priority: Option<Priority>,
+    pub recur: Option<String>,
+    pub depends: Vec<String>,
+    pub tags: Vec<String>,
+    pub annotations: Vec<Annotation>,
+}
+
+impl TaskData {
+
+    /// Create a new pending task with a fresh UUID
+    pub fn new(description: String) -> TaskData {
+        let now = now();
+        TaskData {
+            uuid: format!("{}", Uuid::new_v4()),
+            description: description,
+            status: Status::Pending,
+            entry: now.clone(),
+            modified: Some(now),
+            end: None,
+            due: None,
+            scheduled: None,
+            wait: None,
+            project: None,
+            priority: None,
+            recur: None,
+            depends: vec![],
+            tags: vec![],
+            annotations: vec![],
+        }
+    }
+
+    /// Check whether two tasks have the same attributes, ignoring the modification timestamp
+    pub fn same_attributes(&self, other: &TaskData) -> bool {
+        let mut other = other.clone();
+        other.modified = self.modified.clone();
+        *self == other
+    }
+
+    /// Mark the task as modified now
+    pub fn touch(&mut self) {
+        self.modified = Some(now());
+    }
+
+    /// The status of the task at the moment.
+    ///
+    /// A waiting task becomes pending as soon as its `wait` date has passed.
+    pub fn current_status(&self) -> Status {
+        match (self.status, self.wait.as_ref()) {
+            (Status::Waiting, Some(w)) if *w <= now() => Status::Pending,
+            (Status::Waiting, None)                   => Status::Pending,
+            (s, _)                                    => s,
+        }
+    }
+
+    /// Read the task from the `todo` header section of an entry
+    pub fn from_header(hdr: &EntryHeader) -> Result<TaskData> {
+        let read_str = |path: &str| -> Result<Option<String>> {
+            match try!(hdr.read(path).map_err_into(TEK::HeaderReadError)) {
+                Some(Value::String(s)) => Ok(Some(s)),
+                Some(_)                => Err(TEK::HeaderTypeError.into_error()),
+                None                   => Ok(None),
+            }
+        };
+
+        let read_str_array = |path: &str| -> Result<Vec<String>> {
+            match try!(hdr.read(path).map_err_into(TEK::HeaderReadError)) {
+                Some(Value::Array(a)) => Ok(a.into_iter()
+                    .filter_map(|v| match v {
+                        Value::String(s) => Some(s),
+                        _                => None,
+                    })
+                    .collect()),
+                Some(_) => Err(TEK::HeaderTypeError.into_error()),
+                None    => Ok(vec![]),
+            }
+        };
+
+        let uuid        = try!(try!(read_str("todo.uuid")).ok_or(TEK::HeaderFieldMissing.into_error()));
+        let description = try!(read_str("todo.description")).unwrap_or(String::new());
+        let entry       = try!(read_str("todo.entry")).unwrap_or(String::new());
+
+        let status = match try!(read_str("todo.status")) {
+            Some(s) => try!(Status::from_str(&s)),
+            None    => Status::Pending,
+        };
+
+        let priority = match try!(read_str("todo.priority")) {
+            Some(p) => Some(try!(Priority::from_str(&p))),
+            None    => None,
+        };
+
+        let annotations = match try!(hdr.read("todo.annotations").map_err_into(TEK::HeaderReadError)) {
+            Some(Value::Array(a)) => a.into_iter()
+                .filter_map(|v| match v {
+                    Value::Table(mut t) => match (t.remove("entry"), t.remove("description")) {
+                        (Some(Value::String(e)), Some(Value::String(d))) => {
+                            Some(Annotation { entry: e, description: d })
+                        },
+                        _ => None,
+                    },
+                    _ => None,
+                })
+                .collect(),
+            Some(_) => return Err(TEK::HeaderTypeError.into_error()),
+            None    => vec![],
+        };
+
+        Ok(TaskData {
+            uuid: uuid,
+            description: description,
+            status: status,
+            entry: entry,
+            modified: try!(read_str("todo.modified")),
+            end: try!(read_str("todo.end")),
+            due: try!(read_str("todo.due")),
+            scheduled: try!(read_str("todo.scheduled")),
+            wait: try!(read_str("todo.wait")),
+            project: try!(read_str("todo.project")),
+            priority: priority,
+            recur: try!(read_str("todo.recur")),
+            depends: try!(read_str_array("todo.depends")),
+            tags: try!(read_str_array("todo.tags")),
+            annotations: annotations,
+        })
+    }
+
+    /// Write the task into the `todo` header section of an entry.
+    ///
+    /// Other fields in the section (for example `todo.synced`) are left untouched.
+    pub fn write_to_header(&self, hdr: &mut EntryHeader) -> Result<()> {
+        if try!(hdr.read("todo").map_err_into(TEK::HeaderReadError)).is_none() {
+            try!(hdr.set("todo", Value::Table(BTreeMap::new())).map_err_into(TEK::HeaderWriteError));
+        }
+
+        {
+            let mut set = |path: &str, v: Value| -> Result<()> {
+                hdr.set(path, v).map(|_| ()).map_err_into(TEK::HeaderWriteError)
+            };
+
+            let str_array = |v: &Vec<String>| Value::Array(v.iter().cloned().map(Value::String).collect());
+
+            try!(set("todo.uuid", Value::String(self.uuid.clone())));
+            try!(set("todo.description", Value::String(self.description.clone())));
+            try!(set("todo.status", Value::String(format!("{}", self.status))));
+            try!(set("todo.entry", Value::String(self.entry.clone())));
+            try!(set("todo.depends", str_array(&self.depends)));
+            try!(set("todo.tags", str_array(&self.tags)));
+
+            let anns = self.annotations
+                .iter()
+                .map(|a| {
+                    let mut t = BTreeMap::new();
+                    t.insert(String::from("entry"), Value::String(a.entry.clone()));
+                    t.insert(String::from("description"), Value::String(a.description.clone()));
+                    Value::Table(t)
+                })
+                .collect();
+            try!(set("todo.annotations", Value::Array(anns)));
+        }
+
+        let priority = self.priority.map(|p| format!("{}", p));
+        let optionals = [
+            ("todo.modified", &self.modified),
+            ("todo.end", &self.end),
+            ("todo.due", &self.due),
+            ("todo.scheduled", &self.scheduled),
+            ("todo.wait", &self.wait),
+            ("todo.project", &self.project),
+            ("todo.priority", &priority),
+            ("todo.recur", &self.recur),
+        ];
+
+        for &(path, value) in optionals.iter() {
+            match *value {
+                Some(ref s) => {
+                    try!(hdr.set(path, Value::String(s.clone())).map_err_into(TEK::HeaderWriteError));
+                },
+                None => {
+                    try!(hdr.delete(path).map_err_into(TEK::HeaderWriteError));
+                },
+            }
+        }
+
+        Ok(())
+    }
+
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::PathBuf;
+    use std::str::FromStr;
+
+    use libimagstore::store::Entry;
+    use libimagstore::storeid::StoreId;
+
+    use super::*;
+
+    fn example() -> TaskData {
+        TaskData {
+            uuid: String::from("a0e4e6c4-1b7a-4a56-8f4f-e7dc3a7d34a1"),
+            description: String::from("Write the sync code"),
+            status: Status::Pending,
+            entry: String::from("20161001T100000Z"),
+            modified: Some(String::from("20161002T100000Z")),
+            end: None,
+            due: Some(String::from("20161031T000000Z")),
+            scheduled: None,
+            wait: None,
+            project: Some(String::from("imag")),
+            priority: Some(Priority::High),
+            recur: Some(String::from("weekly")),
+            depends: vec![String::from("4c2f1b7e-3d4a-4fbb-9b3c-0d7f3b7e2a11")],
+            tags: vec![String::from("rust"), String::from("todo")],
+            annotations: vec![Annotation {
+                entry: String::from("20161002T100000Z"),
+                description: String::from("started"),
+            }],
+        }
+    }
+
+    #[test]
+    fn test_parse_date() {
+        assert_eq!(Some(String::from("20161031T000000Z")), parse_date("2016-10-31"));
+        assert_eq!(Some(String::from("20161031T120000Z")), parse_date("20161031T120000Z"));
+        assert_eq!(None, parse_date("tomorrow"));
+    }
+
+    #[test]
+    fn test_status_roundtrip() {
+        for s in &["pending", "waiting", "recurring", "completed", "deleted"] {
+            assert_eq!(*s, format!("{}", Status::from_str(s).unwrap()));
+        }
+        assert!(Status::from_str("done").is_err());
+    }
+
+    #[test]
+    fn test_current_status() {
+        let mut t  = example();
+        t.status   = Status::Waiting;
+        t.wait     = Some(String::from("20000101T000000Z"));
+        assert_eq!(Status::Pending, t.current_status());
+
+        t.wait     = Some(String::from("99991231T000000Z"));
+        assert_eq!(Status::Waiting, t.current_status());
+    }
+
+    #[test]
+    fn test_header_roundtrip() {
+        let mut entry = Entry::new(StoreId::new_baseless(PathBuf::from("todo/taskwarrior/x")).unwrap());
+        let mut t     = example();
+
+        t.write_to_header(entry.get_header_mut()).unwrap();
+        assert_eq!(t, TaskData::from_header(entry.get_header()).unwrap());
+
+        t.due      = None;
+        t.priority = None;
+        t.write_to_header(entry.get_header_mut()).unwrap();
+        assert!(entry.get_header().read("todo.due").unwrap().is_none());
+        assert!(entry.get_header().read("todo.priority").unwrap().is_none());
+        assert_eq!(t, TaskData::from_header(entry.get_header()).unwrap());
+    }
+
+}
+
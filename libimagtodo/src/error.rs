@@ -19,10 +19,23 @@
 
 generate_error_module!(
     generate_error_types!(TodoError, TodoErrorKind,
-        ConversionError     => "Conversion Error",
-        StoreError          => "Store Error",
-        StoreIdError        => "Store Id handling error",
-        ImportError         => "Error importing"
+        ConversionError      => "Conversion Error",
+        StoreError           => "Store Error",
+        StoreIdError         => "Store Id handling error",
+        ImportError          => "Error importing",
+        ExportError          => "Error exporting from taskwarrior",
+        HeaderReadError      => "Error reading header",
+        HeaderWriteError     => "Error writing header",
+        HeaderTypeError      => "Header type error",
+        HeaderFieldMissing   => "Header field missing",
+        TaskwarriorCallError => "Error calling taskwarrior",
+        SyncError            => "Error synchronizing with taskwarrior",
+        LinkError            => "Error linking tasks",
+        AmbiguousId          => "The ID matches more than one task",
+        InvalidDependency    => "A task cannot depend on itself",
+        InvalidStatus        => "Invalid task status",
+        InvalidPriority      => "Invalid task priority",
+        InvalidRecurrence    => "Invalid recurrence"
     );
 );
 
@@ -31,20 +31,28 @@
     while_true,
 )]
 
+extern crate chrono;
 extern crate semver;
 extern crate uuid;
 extern crate toml;
 #[macro_use] extern crate log;
-extern crate serde_json;
 
 #[macro_use] extern crate libimagstore;
 #[macro_use] extern crate libimagerror;
 extern crate libimagutil;
-extern crate task_hookrs;
+extern crate libimagentrylink;
+
+#[cfg(feature = "taskwarrior")] extern crate crypto;
+#[cfg(feature = "taskwarrior")] extern crate serde_json;
+#[cfg(feature = "taskwarrior")] extern crate task_hookrs;
 
 module_entry_path_mod!("todo");
 
+pub mod data;
 pub mod error;
+pub mod recurrence;
 pub mod result;
+#[cfg(feature = "taskwarrior")] pub mod sync;
 pub mod task;
+#[cfg(feature = "taskwarrior")] pub mod taskwarrior;
 
@@ -0,0 +1,173 @@
+//
+// imag - the personal information management suite for the commandline
+// Copyright (C) 2015, 2016 Matthias Beyer <mail@beyermatthias.de> and contributors
+//
+// This library is free software; you can redistribute it and/or
+// modify it under the terms of the GNU Lesser General Public
+// License as published by the Free Software Foundation; version
+// 2.1 of the License.
+//
+// This library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+// Lesser General Public License for more details.
+//
+// You should have received a copy of the GNU Lesser General Public
+// License along with this library; if not, write to the Free Software
+// Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA  02110-1301  USA
+//
+
+//! Recurring tasks
+//!
+//! A recurring task has a `recur` attribute and a due date. When it is done, a new instance of the
+//! task is created, with its dates moved by the recurrence interval.
+
+use std::fmt::{Display, Formatter, Error as FmtError};
+use std::str::FromStr;
+
+use chrono::Datelike;
+use chrono::Duration;
+use chrono::naive::date::NaiveDate;
+use chrono::naive::datetime::NaiveDateTime;
+
+use libimagerror::into::IntoError;
+
+use data::TIMESTAMP_FORMAT;
+use error::{TodoError, TodoErrorKind as TEK};
+use result::Result;
+
+/// The interval of a recurring task
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Recurrence {
+    Days(u32),
+    Weeks(u32),
+    Months(u32),
+    Years(u32),
+}
+
+impl Recurrence {
+
+    /// Move a timestamp by the interval.
+    ///
+    /// Moving by months or years keeps the day of the month, if possible. Otherwise the last day
+    /// of the month is used (January 31st plus one month is February 28th or 29th).
+    pub fn next(&self, timestamp: &str) -> Option<String> {
+        let dt = match NaiveDateTime::parse_from_str(timestamp, TIMESTAMP_FORMAT) {
+            Ok(dt) => dt,
+            Err(_) => return None,
+        };
+
+        let next = match *self {
+            Recurrence::Days(n)   => Some(dt + Duration::days(n as i64)),
+            Recurrence::Weeks(n)  => Some(dt + Duration::weeks(n as i64)),
+            Recurrence::Months(n) => add_months(dt, n),
+            Recurrence::Years(n)  => add_months(dt, n * 12),
+        };
+
+        next.map(|dt| dt.format(TIMESTAMP_FORMAT).to_string())
+    }
+
+}
+
+fn add_months(dt: NaiveDateTime, months: u32) -> Option<NaiveDateTime> {
+    let date   = dt.date();
+    let months = date.month0() + months;
+    let year   = date.year() + (months / 12) as i32;
+    let month  = months % 12 + 1;
+
+    let mut day = date.day();
+    loop {
+        if let Some(d) = NaiveDate::from_ymd_opt(year, month, day) {
+            return Some(d.and_time(dt.time()));
+        }
+        if day <= 28 {
+            return None;
+        }
+        day -= 1;
+    }
+}
+
+impl FromStr for Recurrence {
+    type Err = TodoError;
+
+    /// Parse a recurrence.
+    ///
+    /// Understood are `daily`, `weekly`, `monthly`, `yearly` and a number followed by `d`, `w`,
+    /// `mo` or `y`, like taskwarrior uses them (`2w` for every other week).
+    fn from_str(s: &str) -> Result<Recurrence> {
+        match s {
+            "daily"             => return Ok(Recurrence::Days(1)),
+            "weekly"            => return Ok(Recurrence::Weeks(1)),
+            "monthly"           => return Ok(Recurrence::Months(1)),
+            "yearly" | "annual" => return Ok(Recurrence::Years(1)),
+            _                   => { },
+        }
+
+        let split = s.find(|c: char| !c.is_digit(10)).unwrap_or(s.len());
+        let (num, unit) = s.split_at(split);
+
+        let num = try!(num.parse::<u32>().map_err(|_| TEK::InvalidRecurrence.into_error()));
+        if num == 0 {
+            return Err(TEK::InvalidRecurrence.into_error());
+        }
+
+        match unit {
+            "d"  => Ok(Recurrence::Days(num)),
+            "w"  => Ok(Recurrence::Weeks(num)),
+            "mo" => Ok(Recurrence::Months(num)),
+            "y"  => Ok(Recurrence::Years(num)),
+            _    => Err(TEK::InvalidRecurrence.into_error()),
+        }
+    }
+
+}
+
+impl Display for Recurrence {
+
+    fn fmt(&self, fmt: &mut Formatter) -> ::std::result::Result<(), FmtError> {
+        match *self {
+            Recurrence::Days(1)   => write!(fmt, "daily"),
+            Recurrence::Weeks(1)  => write!(fmt, "weekly"),
+            Recurrence::Months(1) => write!(fmt, "monthly"),
+            Recurrence::Years(1)  => write!(fmt, "yearly"),
+            Recurrence::Days(n)   => write!(fmt, "{}d", n),
+            Recurrence::Weeks(n)  => write!(fmt, "{}w", n),
+            Recurrence::Months(n) => write!(fmt, "{}mo", n),
+            Recurrence::Years(n)  => write!(fmt, "{}y", n),
+        }
+    }
+
+}
+
+#[cfg(test)]
+mod test {
+    use std::str::FromStr;
+
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(Recurrence::Days(1), Recurrence::from_str("daily").unwrap());
+        assert_eq!(Recurrence::Weeks(2), Recurrence::from_str("2w").unwrap());
+        assert_eq!(Recurrence::Months(3), Recurrence::from_str("3mo").unwrap());
+        assert!(Recurrence::from_str("0d").is_err());
+        assert!(Recurrence::from_str("sometimes").is_err());
+        assert_eq!("2w", format!("{}", Recurrence::Weeks(2)));
+    }
+
+    #[test]
+    fn test_next() {
+        let r = Recurrence::Weeks(1);
+        assert_eq!(Some(String::from("20161107T120000Z")), r.next("20161031T120000Z"));
+
+        let r = Recurrence::Months(1);
+        assert_eq!(Some(String::from("20170228T000000Z")), r.next("20170131T000000Z"));
+        assert_eq!(Some(String::from("20170115T000000Z")), r.next("20161215T000000Z"));
+
+        let r = Recurrence::Years(1);
+        assert_eq!(Some(String::from("20170228T000000Z")), r.next("20160229T000000Z"));
+        assert_eq!(None, r.next("garbage"));
+    }
+
+}
+
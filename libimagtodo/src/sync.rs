@@ -0,0 +1,384 @@
+//
+// imag - the personal information management suite for the commandline
+// Copyright (C) 2015, 2016 Matthias Beyer <mail@beyermatthias.de> and contributors
+//
+// This library is free software; you can redistribute it and/or
+// modify it under the terms of the GNU Lesser General Public
+// License as published by the Free Software Foundation; version
+// 2.1 of the License.
+//
+// This library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+// Lesser General Public License for more details.
+//
+// You should have received a copy of the GNU Lesser General Public
+// License along with this library; if not, write to the Free Software
+// Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA  02110-1301  USA
+//
+
+//! Bidirectional synchronization between the store and taskwarrior
+//!
+//! The store keeps, for each task, the taskwarrior `modified` timestamp which was seen when the
+//! task was last synchronized (`todo.synced`), and a hash of the task attributes at that time
+//! (`todo.synced_hash`). A task changed in taskwarrior if its `modified` timestamp differs from
+//! `todo.synced`. A task changed in imag if its attributes do not match the hash anymore, so
+//! edits which do not touch `todo.modified` (for example with `imag-edit`) are noticed as well.
+//! This tells which side changed a task since the last synchronization:
+//!
+//! * changed only in taskwarrior: the task is pulled into the store
+//! * changed only in imag: the task is pushed to taskwarrior
+//! * changed on both sides: the task is reported as conflict, or resolved by a `Resolution`
+//!
+//! Tasks which exist only in taskwarrior are pulled, tasks created for taskwarrior in imag (see
+//! `Task::create_for_taskwarrior()`) which were never synchronized are pushed. A task which was
+//! synchronized before but vanished from taskwarrior is reported as conflict as well. Tasks which
+//! only live in the store are not synchronized at all.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use crypto::digest::Digest;
+use crypto::sha1::Sha1;
+use serde_json;
+use uuid::Uuid;
+
+use libimagstore::store::Store;
+use libimagerror::into::IntoError;
+
+use data::TaskData;
+use error::{TodoErrorKind as TEK, MapErrInto};
+use result::Result;
+use task::Task;
+use taskwarrior;
+
+/// What to do with a single task
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Action {
+    /// Nothing changed
+    Nothing,
+
+    /// The attributes are equal on both sides, but the timestamps in the store are outdated
+    Align,
+
+    /// Copy the task from taskwarrior into the store
+    Pull,
+
+    /// Copy the task from the store into taskwarrior
+    Push,
+
+    /// Remove the task from the store
+    Remove,
+
+    /// The task cannot be synchronized automatically
+    Conflict(ConflictKind),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConflictKind {
+    /// The task was modified in imag and in taskwarrior since the last synchronization
+    BothModified,
+
+    /// The task was synchronized before, but does not exist in taskwarrior anymore
+    MissingInTaskwarrior,
+}
+
+/// How conflicts should be resolved
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Resolution {
+    /// Do not touch conflicting tasks, only report them
+    Report,
+
+    /// The version in the store wins
+    PreferImag,
+
+    /// The version in taskwarrior wins
+    PreferTaskwarrior,
+}
+
+/// A conflict found while synchronizing
+#[derive(Debug, Clone)]
+pub struct Conflict {
+    pub uuid: String,
+    pub description: String,
+    pub kind: ConflictKind,
+}
+
+/// The outcome of a synchronization run
+#[derive(Debug, Clone, Default)]
+pub struct SyncReport {
+    /// UUIDs of the tasks which were copied from taskwarrior into the store
+    pub pulled: Vec<String>,
+
+    /// UUIDs of the tasks which were copied from the store into taskwarrior
+    pub pushed: Vec<String>,
+
+    /// UUIDs of the tasks which were removed from the store
+    pub removed: Vec<String>,
+
+    /// All conflicts, whether they were resolved or not
+    pub conflicts: Vec<Conflict>,
+}
+
+/// The state of a task in the store at its last synchronization
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Synced<'a> {
+    /// The taskwarrior `modified` timestamp (`todo.synced`)
+    pub modified: Option<&'a str>,
+
+    /// The hash of the task attributes (`todo.synced_hash`), see `attributes_hash()`
+    pub hash: Option<&'a str>,
+}
+
+/// The hash of the attributes of a task, ignoring the modification timestamp
+pub fn attributes_hash(data: &TaskData) -> String {
+    let mut data = data.clone();
+    data.modified = None;
+
+    let mut hasher = Sha1::new();
+    hasher.input_str(&serde_json::to_string(&data.to_json()).unwrap_or(String::new()));
+    hasher.result_str()
+}
+
+/// Decide what to do with a task, given the version from the store, the state of the task in the
+/// store at the last synchronization and the version from taskwarrior.
+///
+/// Tasks which were synchronized before the hash was recorded are considered changed in imag if
+/// their `modified` timestamp differs from `todo.synced`.
+pub fn decide(imag: Option<&TaskData>, synced: Option<Synced>, tw: Option<&TaskData>) -> Action {
+    let synced_modified = synced.and_then(|s| s.modified);
+
+    match (imag, tw) {
+        (None, None)    => Action::Nothing,
+        (None, Some(_)) => Action::Pull,
+        (Some(_), None) => if synced_modified.is_none() {
+            Action::Push
+        } else {
+            Action::Conflict(ConflictKind::MissingInTaskwarrior)
+        },
+        (Some(i), Some(t)) => {
+            let imag_changed = match synced.and_then(|s| s.hash) {
+                Some(hash) => attributes_hash(i) != hash,
+                None       => i.modified.as_ref().map(|s| &s[..]) != synced_modified,
+            };
+            let tw_changed = t.modified.as_ref().map(|s| &s[..]) != synced_modified;
+
+            if i.same_attributes(t) {
+                if imag_changed || tw_changed {
+                    Action::Align
+                } else {
+                    Action::Nothing
+                }
+            } else {
+                match (imag_changed, tw_changed) {
+                    (true, true)  => Action::Conflict(ConflictKind::BothModified),
+                    (true, false) => Action::Push,
+                    (false, _)    => Action::Pull,
+                }
+            }
+        },
+    }
+}
+
+/// Turn a conflict into an action, according to the `Resolution`
+pub fn resolve(kind: ConflictKind, resolution: Resolution) -> Action {
+    match (kind, resolution) {
+        (_, Resolution::Report)                                             => Action::Conflict(kind),
+        (_, Resolution::PreferImag)                                         => Action::Push,
+        (ConflictKind::BothModified, Resolution::PreferTaskwarrior)         => Action::Pull,
+        (ConflictKind::MissingInTaskwarrior, Resolution::PreferTaskwarrior) => Action::Remove,
+    }
+}
+
+/// Synchronize the store with taskwarrior.
+///
+/// `binary` is the taskwarrior executable to call. If `dry_run` is set, the report is computed but
+/// neither the store nor taskwarrior is modified.
+pub fn sync(store: &Store, binary: &str, resolution: Resolution, dry_run: bool)
+    -> Result<SyncReport>
+{
+    let tw : BTreeMap<String, TaskData> = try!(taskwarrior::export(binary))
+        .into_iter()
+        .map(|t| (t.uuid.clone(), t))
+        .collect();
+
+    let mut imag : BTreeMap<String, (TaskData, Option<String>, Option<String>)> = BTreeMap::new();
+    for task in try!(Task::all_taskwarrior(store)) {
+        let task   = try!(task);
+        let data   = try!(task.data());
+        let synced = try!(task.synced());
+        let hash   = try!(task.synced_hash());
+        imag.insert(data.uuid.clone(), (data, synced, hash));
+    }
+
+    let uuids : BTreeSet<String> = imag.keys().chain(tw.keys()).cloned().collect();
+
+    let mut report    = SyncReport::default();
+    let mut to_pull   = vec![];
+    let mut to_push   = vec![];
+    let mut to_remove = vec![];
+
+    for uuid in uuids {
+        let i = imag.get(&uuid);
+        let t = tw.get(&uuid);
+
+        let synced = i.map(|&(_, ref modified, ref hash)| Synced {
+            modified: modified.as_ref().map(|s| &s[..]),
+            hash: hash.as_ref().map(|s| &s[..]),
+        });
+
+        let action = match decide(i.map(|&(ref d, _, _)| d), synced, t) {
+            Action::Conflict(kind) => {
+                let description = i.map(|&(ref d, _, _)| d)
+                    .or(t)
+                    .map(|d| d.description.clone())
+                    .unwrap_or(String::new());
+
+                report.conflicts.push(Conflict {
+                    uuid: uuid.clone(),
+                    description: description,
+                    kind: kind,
+                });
+
+                resolve(kind, resolution)
+            },
+            other => other,
+        };
+
+        match action {
+            Action::Nothing | Action::Conflict(_) => { },
+            Action::Align => to_pull.push(t.unwrap().clone()), // safe by decide()
+            Action::Pull  => {
+                report.pulled.push(uuid.clone());
+                to_pull.push(t.unwrap().clone()); // safe by decide() and resolve()
+            },
+            Action::Push  => {
+                report.pushed.push(uuid.clone());
+                to_push.push(i.unwrap().0.clone()); // safe by decide() and resolve()
+            },
+            Action::Remove => {
+                report.removed.push(uuid.clone());
+                to_remove.push(uuid.clone());
+            },
+        }
+    }
+
+    if dry_run {
+        return Ok(report);
+    }
+
+    for data in to_pull {
+        try!(Task::store_synced(store, &data));
+    }
+
+    for uuid in to_remove {
+        let uuid = try!(Uuid::parse_str(&uuid).map_err_into(TEK::ConversionError));
+        try!(Task::delete_by_uuid(store, uuid));
+    }
+
+    if !to_push.is_empty() {
+        try!(taskwarrior::import(binary, &to_push));
+
+        // Taskwarrior may adjust the tasks while importing them (for example the `modified`
+        // timestamp), so the store is aligned with what taskwarrior holds afterwards.
+        let exported = try!(taskwarrior::export(binary));
+        for pushed in to_push {
+            match exported.iter().find(|t| t.uuid == pushed.uuid) {
+                Some(data) => { try!(Task::store_synced(store, data)); },
+                None       => return Err(TEK::SyncError.into_error()),
+            }
+        }
+    }
+
+    Ok(report)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use data::TaskData;
+
+    fn task(description: &str, modified: &str) -> TaskData {
+        let mut t   = TaskData::new(String::from(description));
+        t.uuid      = String::from("6b1c7c55-6f0e-4b3c-9a57-0c8d6d2d8f11");
+        t.entry     = String::from("20161001T100000Z");
+        t.modified  = Some(String::from(modified));
+        t
+    }
+
+    fn synced<'a>(modified: &'a str, hash: Option<&'a str>) -> Option<Synced<'a>> {
+        Some(Synced { modified: Some(modified), hash: hash })
+    }
+
+    #[test]
+    fn test_decide_one_sided() {
+        let t = task("a", "20161001T100000Z");
+
+        assert_eq!(Action::Pull, decide(None, None, Some(&t)));
+        assert_eq!(Action::Push, decide(Some(&t), None, None));
+        assert_eq!(Action::Conflict(ConflictKind::MissingInTaskwarrior),
+                   decide(Some(&t), synced("20161001T100000Z", None), None));
+    }
+
+    #[test]
+    fn test_decide_both_sides() {
+        let old    = task("a", "20161001T100000Z");
+        let hash   = attributes_hash(&old);
+        let imag   = task("a from imag", "20161002T100000Z");
+        let tw     = task("a from taskwarrior", "20161003T100000Z");
+
+        for s in vec![synced("20161001T100000Z", None), synced("20161001T100000Z", Some(&hash))] {
+            assert_eq!(Action::Nothing, decide(Some(&old), s, Some(&old)));
+            assert_eq!(Action::Push, decide(Some(&imag), s, Some(&old)));
+            assert_eq!(Action::Pull, decide(Some(&old), s, Some(&tw)));
+            assert_eq!(Action::Conflict(ConflictKind::BothModified),
+                       decide(Some(&imag), s, Some(&tw)));
+        }
+    }
+
+    #[test]
+    fn test_decide_edit_without_modified() {
+        let old  = task("a", "20161001T100000Z");
+        let hash = attributes_hash(&old);
+        let s    = synced("20161001T100000Z", Some(&hash));
+
+        // Edited in imag, but `modified` was not updated
+        let edited = task("a, edited", "20161001T100000Z");
+        let tw     = task("a from taskwarrior", "20161003T100000Z");
+
+        assert_eq!(Action::Push, decide(Some(&edited), s, Some(&old)));
+        assert_eq!(Action::Conflict(ConflictKind::BothModified),
+                   decide(Some(&edited), s, Some(&tw)));
+    }
+
+    #[test]
+    fn test_decide_align() {
+        let old = task("a", "20161001T100000Z");
+        let new = task("a", "20161002T100000Z");
+
+        assert_eq!(Action::Align, decide(Some(&old), synced("20161001T100000Z", None), Some(&new)));
+    }
+
+    #[test]
+    fn test_attributes_hash_ignores_modified() {
+        let a = task("a", "20161001T100000Z");
+        let b = task("a", "20161002T100000Z");
+        let c = task("b", "20161001T100000Z");
+
+        assert_eq!(attributes_hash(&a), attributes_hash(&b));
+        assert!(attributes_hash(&a) != attributes_hash(&c));
+    }
+
+    #[test]
+    fn test_resolve() {
+        let both    = ConflictKind::BothModified;
+        let missing = ConflictKind::MissingInTaskwarrior;
+
+        assert_eq!(Action::Conflict(both), resolve(both, Resolution::Report));
+        assert_eq!(Action::Push, resolve(both, Resolution::PreferImag));
+        assert_eq!(Action::Pull, resolve(both, Resolution::PreferTaskwarrior));
+        assert_eq!(Action::Push, resolve(missing, Resolution::PreferImag));
+        assert_eq!(Action::Remove, resolve(missing, Resolution::PreferTaskwarrior));
+    }
+
+}
+
@@ -17,25 +17,39 @@
 // Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA  02110-1301  USA
 //
 
-use std::collections::BTreeMap;
 use std::ops::{Deref, DerefMut};
-use std::io::BufRead;
-use std::result::Result as RResult;
+#[cfg(feature = "taskwarrior")] use std::io::BufRead;
+#[cfg(feature = "taskwarrior")] use std::result::Result as RResult;
+use std::str::FromStr;
 
+#[cfg(feature = "taskwarrior")] use serde_json;
+#[cfg(feature = "taskwarrior")] use serde_json::Value as JsonValue;
 use toml::Value;
 use uuid::Uuid;
 
-use task_hookrs::task::Task as TTask;
-use task_hookrs::import::{import_task, import_tasks};
+#[cfg(feature = "taskwarrior")] use task_hookrs::task::Task as TTask;
+#[cfg(feature = "taskwarrior")] use task_hookrs::import::{import_task, import_tasks};
 
 use libimagstore::store::{FileLockEntry, Store};
 use libimagstore::storeid::{IntoStoreId, StoreIdIterator, StoreId};
-use libimagerror::trace::MapErrTrace;
-use libimagutil::debug_result::DebugResult;
+use libimagstore::schema::{FieldType, Schema};
+#[cfg(feature = "taskwarrior")] use libimagerror::trace::MapErrTrace;
+use libimagerror::into::IntoError;
+#[cfg(feature = "taskwarrior")] use libimagutil::debug_result::DebugResult;
+use libimagentrylink::internal::InternalLinker;
 use module_path::ModuleEntryPath;
 
 use error::{TodoError, TodoErrorKind, MapErrInto};
 use result::Result;
+use data::{now, Status, TaskData};
+use recurrence::Recurrence;
+#[cfg(feature = "taskwarrior")] use sync::attributes_hash;
+
+/// Tasks which only live in the store are kept in `todo/tasks/<uuid>`
+const NATIVE_DIR : &'static str = "tasks";
+
+/// Tasks which are synchronized with taskwarrior are kept in `todo/taskwarrior/<uuid>`
+const TASKWARRIOR_DIR : &'static str = "taskwarrior";
 
 /// Task struct containing a `FileLockEntry`
 #[derive(Debug)]
@@ -48,6 +62,268 @@ impl<'a> Task<'a> {
         Task(fle)
     }
 
+    /// Create a new task which only lives in the store.
+    ///
+    /// The task is never passed to taskwarrior, see `Task::create_for_taskwarrior()` for tasks
+    /// which are.
+    pub fn create(store: &'a Store, data: &TaskData) -> Result<Task<'a>> {
+        Task::create_in(store, NATIVE_DIR, data)
+    }
+
+    /// Create a new task in the store, which is synchronized with taskwarrior but was not passed
+    /// to taskwarrior yet.
+    ///
+    /// The task is pushed to taskwarrior by the next `sync::sync()`.
+    pub fn create_for_taskwarrior(store: &'a Store, data: &TaskData) -> Result<Task<'a>> {
+        Task::create_in(store, TASKWARRIOR_DIR, data)
+    }
+
+    fn create_in(store: &'a Store, dir: &str, data: &TaskData) -> Result<Task<'a>> {
+        ModuleEntryPath::new(format!("{}/{}", dir, data.uuid))
+            .into_storeid()
+            .and_then(|id| store.create(id))
+            .map_err_into(TodoErrorKind::StoreError)
+            .and_then(|mut fle| {
+                try!(data.write_to_header(fle.get_header_mut()));
+                Ok(Task::new(fle))
+            })
+    }
+
+    /// Whether the task is synchronized with taskwarrior
+    pub fn is_taskwarrior_task(&self) -> bool {
+        self.get_location()
+            .local()
+            .parent()
+            .and_then(|p| p.file_name())
+            .and_then(|n| n.to_str())
+            .map(|n| n == TASKWARRIOR_DIR)
+            .unwrap_or(false)
+    }
+
+    /// Get a task which is synchronized with taskwarrior by its UUID.
+    ///
+    /// If there is no such task with this UUID, this returns `Ok(None)`.
+    pub fn get_from_uuid(store: &'a Store, uuid: Uuid) -> Result<Option<Task<'a>>> {
+        ModuleEntryPath::new(format!("{}/{}", TASKWARRIOR_DIR, uuid))
+            .into_storeid()
+            .and_then(|store_id| store.get(store_id))
+            .map(|o| o.map(Task::new))
+            .map_err_into(TodoErrorKind::StoreError)
+    }
+
+    /// Get the task attributes from the header
+    pub fn data(&self) -> Result<TaskData> {
+        TaskData::from_header(self.get_header())
+    }
+
+    /// Write the task attributes to the header
+    pub fn set_data(&mut self, data: &TaskData) -> Result<()> {
+        data.write_to_header(self.get_header_mut())
+    }
+
+    /// Find a task by its UUID or by a unique prefix of its UUID.
+    ///
+    /// If the prefix matches more than one task, an error is returned.
+    pub fn find(store: &'a Store, id: &str) -> Result<Option<Task<'a>>> {
+        let mut matching = try!(Task::all_as_ids(store))
+            .filter(|sid| {
+                sid.local()
+                    .file_name()
+                    .and_then(|n| n.to_str())
+                    .map(|n| n.starts_with(id))
+                    .unwrap_or(false)
+            })
+            .collect::<Vec<_>>();
+
+        match matching.len() {
+            0 => Ok(None),
+            1 => store.get(matching.pop().unwrap()) // safe by len() == 1
+                .map(|o| o.map(Task::new))
+                .map_err_into(TodoErrorKind::StoreError),
+            _ => Err(TodoErrorKind::AmbiguousId.into_error()),
+        }
+    }
+
+    /// Change the attributes of the task with `f` and mark the task as modified
+    pub fn modify<F: FnOnce(&mut TaskData)>(&mut self, f: F) -> Result<()> {
+        let mut data = try!(self.data());
+        f(&mut data);
+        data.touch();
+        self.set_data(&data)
+    }
+
+    /// Mark the task as completed.
+    ///
+    /// If the task is recurring and has a due date, the next instance of the task is created and
+    /// returned.
+    pub fn done(&mut self, store: &'a Store) -> Result<Option<Task<'a>>> {
+        try!(self.modify(|data| {
+            data.status = Status::Completed;
+            data.end    = Some(now());
+        }));
+
+        let data = try!(self.data());
+        let recurrence = match data.recur {
+            Some(ref r) => try!(Recurrence::from_str(r)),
+            None        => return Ok(None),
+        };
+
+        let due = match data.due.as_ref().and_then(|d| recurrence.next(d)) {
+            Some(due) => due,
+            None      => return Ok(None),
+        };
+
+        let mut next       = TaskData::new(data.description.clone());
+        next.due           = Some(due);
+        next.scheduled     = data.scheduled.as_ref().and_then(|d| recurrence.next(d));
+        next.wait          = data.wait.as_ref().and_then(|d| recurrence.next(d));
+        next.project       = data.project.clone();
+        next.priority      = data.priority;
+        next.recur         = data.recur.clone();
+        next.tags          = data.tags.clone();
+        if next.wait.is_some() {
+            next.status = Status::Waiting;
+        }
+
+        if self.is_taskwarrior_task() {
+            Task::create_for_taskwarrior(store, &next).map(Some)
+        } else {
+            Task::create(store, &next).map(Some)
+        }
+    }
+
+    /// Make this task depend on `other`.
+    ///
+    /// The dependency is recorded in the `todo.depends` header field and the entries of both
+    /// tasks are linked with each other.
+    pub fn add_dependency(&mut self, other: &mut Task) -> Result<()> {
+        let own_uuid   = try!(self.data()).uuid;
+        let other_uuid = try!(other.data()).uuid;
+
+        if own_uuid == other_uuid {
+            return Err(TodoErrorKind::InvalidDependency.into_error());
+        }
+
+        if try!(self.data()).depends.contains(&other_uuid) {
+            return Ok(());
+        }
+
+        try!(self.modify(|data| data.depends.push(other_uuid)));
+        self.0.add_internal_link(&mut other.0).map_err_into(TodoErrorKind::LinkError)
+    }
+
+    /// Remove the dependency of this task on `other`, including the link between the entries.
+    pub fn remove_dependency(&mut self, other: &mut Task) -> Result<()> {
+        let other_uuid = try!(other.data()).uuid;
+
+        if !try!(self.data()).depends.contains(&other_uuid) {
+            return Ok(());
+        }
+
+        try!(self.modify(|data| data.depends.retain(|u| *u != other_uuid)));
+        self.0.remove_internal_link(&mut other.0).map_err_into(TodoErrorKind::LinkError)
+    }
+
+    /// Get the taskwarrior `modified` timestamp of the last synchronization, if the task was ever
+    /// synchronized
+    pub fn synced(&self) -> Result<Option<String>> {
+        match try!(self.get_header().read("todo.synced").map_err_into(TodoErrorKind::HeaderReadError)) {
+            Some(Value::String(s)) => Ok(Some(s)),
+            Some(_)                => Err(TodoErrorKind::HeaderTypeError.into_error()),
+            None                   => Ok(None),
+        }
+    }
+
+    /// Set the timestamp of the last synchronization
+    pub fn set_synced(&mut self, synced: Option<String>) -> Result<()> {
+        let hdr = self.get_header_mut();
+        let res = match synced {
+            Some(s) => hdr.set("todo.synced", Value::String(s)).map(|_| ()),
+            None    => hdr.delete("todo.synced").map(|_| ()),
+        };
+        res.map_err_into(TodoErrorKind::HeaderWriteError)
+    }
+
+    /// Get the hash of the task attributes at the last synchronization, see
+    /// `sync::attributes_hash()`
+    pub fn synced_hash(&self) -> Result<Option<String>> {
+        match try!(self.get_header().read("todo.synced_hash").map_err_into(TodoErrorKind::HeaderReadError)) {
+            Some(Value::String(s)) => Ok(Some(s)),
+            Some(_)                => Err(TodoErrorKind::HeaderTypeError.into_error()),
+            None                   => Ok(None),
+        }
+    }
+
+    /// Set the hash of the task attributes at the last synchronization
+    pub fn set_synced_hash(&mut self, hash: Option<String>) -> Result<()> {
+        let hdr = self.get_header_mut();
+        let res = match hash {
+            Some(h) => hdr.set("todo.synced_hash", Value::String(h)).map(|_| ()),
+            None    => hdr.delete("todo.synced_hash").map(|_| ()),
+        };
+        res.map_err_into(TodoErrorKind::HeaderWriteError)
+    }
+
+    /// Delete the task `uuid` which is synchronized with taskwarrior
+    pub fn delete_by_uuid(store: &Store, uuid: Uuid) -> Result<()> {
+        ModuleEntryPath::new(format!("{}/{}", TASKWARRIOR_DIR, uuid))
+            .into_storeid()
+            .and_then(|id| store.delete(id))
+            .map_err(|e| TodoError::new(TodoErrorKind::StoreError, Some(Box::new(e))))
+    }
+
+    /// The ids of all tasks, whether they are synchronized with taskwarrior or not
+    pub fn all_as_ids(store: &Store) -> Result<StoreIdIterator> {
+        store.retrieve_for_module("todo")
+            .map_err(|e| TodoError::new(TodoErrorKind::StoreError, Some(Box::new(e))))
+    }
+
+    pub fn all(store: &Store) -> Result<TaskIterator> {
+        Task::all_as_ids(store)
+            .map(|iter| TaskIterator::new(store, iter))
+    }
+
+    /// The ids of the tasks which are synchronized with taskwarrior
+    pub fn all_taskwarrior_as_ids(store: &Store) -> Result<StoreIdIterator> {
+        store.retrieve_for_module(&format!("todo/{}", TASKWARRIOR_DIR))
+            .map_err(|e| TodoError::new(TodoErrorKind::StoreError, Some(Box::new(e))))
+    }
+
+    /// All tasks which are synchronized with taskwarrior
+    pub fn all_taskwarrior(store: &Store) -> Result<TaskIterator> {
+        Task::all_taskwarrior_as_ids(store)
+            .map(|iter| TaskIterator::new(store, iter))
+    }
+
+    /// The schema of the "todo" header section, to be registered with
+    /// `Store::register_schema()`
+    pub fn header_schema() -> Schema {
+        Schema::new("todo")
+            .required("uuid", FieldType::String)
+            .optional("description", FieldType::String)
+            .optional("status", FieldType::String)
+            .optional("entry", FieldType::String)
+            .optional("modified", FieldType::String)
+            .optional("end", FieldType::String)
+            .optional("due", FieldType::String)
+            .optional("scheduled", FieldType::String)
+            .optional("wait", FieldType::String)
+            .optional("project", FieldType::String)
+            .optional("priority", FieldType::String)
+            .optional("recur", FieldType::String)
+            .optional("depends", FieldType::Array)
+            .optional("tags", FieldType::Array)
+            .optional("annotations", FieldType::Array)
+            .optional("synced", FieldType::String)
+            .optional("synced_hash", FieldType::String)
+    }
+
+}
+
+/// Functions for the taskwarrior hooks and the synchronization with taskwarrior
+#[cfg(feature = "taskwarrior")]
+impl<'a> Task<'a> {
+
     pub fn import<R: BufRead>(store: &'a Store, mut r: R) -> Result<(Task<'a>, String, Uuid)> {
         let mut line = String::new();
         r.read_line(&mut line);
@@ -94,17 +370,6 @@ impl<'a> Task<'a> {
             })
     }
 
-    /// Get a task from an UUID.
-    ///
-    /// If there is no task with this UUID, this returns `Ok(None)`.
-    pub fn get_from_uuid(store: &'a Store, uuid: Uuid) -> Result<Option<Task<'a>>> {
-        ModuleEntryPath::new(format!("taskwarrior/{}", uuid))
-            .into_storeid()
-            .and_then(|store_id| store.get(store_id))
-            .map(|o| o.map(Task::new))
-            .map_err_into(TodoErrorKind::StoreError)
-    }
-
     /// Same as Task::get_from_import() but uses Store::retrieve() rather than Store::get(), to
     /// implicitely create the task if it does not exist.
     pub fn retrieve_from_import<R: BufRead>(store: &'a Store, mut r: R) -> Result<Task<'a>> {
@@ -164,21 +429,59 @@ impl<'a> Task<'a> {
         Ok(())
     }
 
-    pub fn delete_by_uuid(store: &Store, uuid: Uuid) -> Result<()> {
-        ModuleEntryPath::new(format!("taskwarrior/{}", uuid))
-            .into_storeid()
-            .and_then(|id| store.delete(id))
-            .map_err(|e| TodoError::new(TodoErrorKind::StoreError, Some(Box::new(e))))
-    }
+    /// Mirror the modifications passed to a taskwarrior `on-modify` hook into the store.
+    ///
+    /// Like `Task::delete_by_imports()`, deleted tasks are removed from the store. All other tasks
+    /// get their attributes updated.
+    pub fn modify_by_imports<R: BufRead>(store: &Store, r: R) -> Result<()> {
+        use serde_json::ser::to_string as serde_to_string;
+        use task_hookrs::status::TaskStatus;
 
-    pub fn all_as_ids(store: &Store) -> Result<StoreIdIterator> {
-        store.retrieve_for_module("todo/taskwarrior")
-            .map_err(|e| TodoError::new(TodoErrorKind::StoreError, Some(Box::new(e))))
+        for (counter, res_ttask) in import_tasks(r).into_iter().enumerate() {
+            match res_ttask {
+                Ok(ttask) => {
+                    if counter % 2 == 1 {
+                        // Only every second task is needed, see `Task::delete_by_imports()`
+                        match serde_to_string(&ttask).map_err_into(TodoErrorKind::ImportError) {
+                            // use println!() here, as we talk with TW
+                            Ok(val) => println!("{}", val),
+                            Err(e)  => return Err(e),
+                        }
+
+                        if *ttask.status() == TaskStatus::Deleted {
+                            match Task::delete_by_uuid(store, *ttask.uuid()) {
+                                Ok(_)  => info!("Deleted task {}", *ttask.uuid()),
+                                Err(e) => return Err(e),
+                            }
+                        } else {
+                            let uuid = *ttask.uuid();
+                            match ttask.into_task(store) {
+                                Ok(_)  => info!("Updated task {}", uuid),
+                                Err(e) => return Err(e),
+                            }
+                        }
+                    } // end if c % 2
+                },
+                Err(e) => return Err(e).map_err_into(TodoErrorKind::ImportError),
+            }
+        }
+        Ok(())
     }
 
-    pub fn all(store: &Store) -> Result<TaskIterator> {
-        Task::all_as_ids(store)
-            .map(|iter| TaskIterator::new(store, iter))
+    /// Write a task as it is known to taskwarrior into the store, creating the entry if
+    /// necessary, and mark it as synchronized.
+    pub fn store_synced(store: &'a Store, data: &TaskData) -> Result<Task<'a>> {
+        ModuleEntryPath::new(format!("{}/{}", TASKWARRIOR_DIR, data.uuid))
+            .into_storeid()
+            .and_then(|id| store.retrieve(id))
+            .map_err_into(TodoErrorKind::StoreError)
+            .and_then(|fle| {
+                let mut task = Task::new(fle);
+                try!(task.set_data(data));
+                try!(task.set_synced(data.modified.clone()));
+                try!(task.set_synced_hash(Some(attributes_hash(data))));
+                Ok(task)
+            })
     }
 
 }
@@ -201,6 +504,7 @@ impl<'a> DerefMut for Task<'a> {
 }
 
 /// A trait to get a `libimagtodo::task::Task` out of the implementing object.
+#[cfg(feature = "taskwarrior")]
 pub trait IntoTask<'a> {
 
     /// # Usage
@@ -221,34 +525,16 @@ pub trait IntoTask<'a> {
 
 }
 
+#[cfg(feature = "taskwarrior")]
 impl<'a> IntoTask<'a> for TTask {
 
     fn into_task(self, store : &'a Store) -> Result<Task<'a>> {
-        let uuid     = self.uuid();
-        ModuleEntryPath::new(format!("taskwarrior/{}", uuid))
-            .into_storeid()
-            .map_err_into(TodoErrorKind::StoreIdError)
-            .and_then(|id| {
-                store.retrieve(id)
-                    .map_err_into(TodoErrorKind::StoreError)
-                    .and_then(|mut fle| {
-                        {
-                            let mut hdr = fle.get_header_mut();
-                            let read = hdr.read("todo").map_err_into(TodoErrorKind::StoreError);
-                            if try!(read).is_none() {
-                                try!(hdr
-                                    .set("todo", Value::Table(BTreeMap::new()))
-                                    .map_err_into(TodoErrorKind::StoreError));
-                            }
-
-                            try!(hdr.set("todo.uuid", Value::String(format!("{}",uuid)))
-                                 .map_err_into(TodoErrorKind::StoreError));
-                        }
-
-                        // If none of the errors above have returned the function, everything is fine
-                        Ok(Task::new(fle))
-                    })
-            })
+        // Go through the JSON representation, so all attributes taskwarrior passed are kept
+        serde_json::to_string(&self)
+            .map_err_into(TodoErrorKind::ConversionError)
+            .and_then(|s| serde_json::from_str(&s).map_err_into(TodoErrorKind::ConversionError))
+            .and_then(|json: JsonValue| TaskData::from_json(&json))
+            .and_then(|data| Task::store_synced(store, &data))
     }
 
 }
@@ -0,0 +1,282 @@
+//
+// imag - the personal information management suite for the commandline
+// Copyright (C) 2015, 2016 Matthias Beyer <mail@beyermatthias.de> and contributors
+//
+// This library is free software; you can redistribute it and/or
+// modify it under the terms of the GNU Lesser General Public
+// License as published by the Free Software Foundation; version
+// 2.1 of the License.
+//
+// This library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+// Lesser General Public License for more details.
+//
+// You should have received a copy of the GNU Lesser General Public
+// License along with this library; if not, write to the Free Software
+// Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA  02110-1301  USA
+//
+
+//! Data exchange with taskwarrior
+//!
+//! Taskwarrior is an optional backend of the todo module. This module contains the conversion of
+//! `TaskData` from and to the JSON representation taskwarrior uses, and the functions to talk to
+//! the `task` binary (`task export` and `task import`).
+
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::process::{Command, Stdio};
+use std::str::FromStr;
+
+use serde_json::Value as JsonValue;
+use serde_json;
+
+use libimagerror::into::IntoError;
+
+use data::{now, Annotation, Priority, Status, TaskData};
+use error::{TodoErrorKind as TEK, MapErrInto};
+use result::Result;
+
+impl TaskData {
+
+    /// Read a task from the JSON representation taskwarrior uses for `task export`
+    pub fn from_json(json: &JsonValue) -> Result<TaskData> {
+        let obj = match json.as_object() {
+            Some(o) => o,
+            None    => return Err(TEK::ConversionError.into_error()),
+        };
+
+        let get_str = |key: &str| -> Option<String> {
+            obj.get(key).and_then(|v| v.as_str()).map(String::from)
+        };
+
+        let get_str_array = |key: &str| -> Vec<String> {
+            obj.get(key)
+                .and_then(|v| v.as_array())
+                .map(|a| a.iter().filter_map(|t| t.as_str().map(String::from)).collect())
+                .unwrap_or(vec![])
+        };
+
+        let uuid        = try!(get_str("uuid").ok_or(TEK::ConversionError.into_error()));
+        let description = try!(get_str("description").ok_or(TEK::ConversionError.into_error()));
+        let entry       = get_str("entry").unwrap_or_else(now);
+
+        let status = match get_str("status") {
+            Some(s) => try!(Status::from_str(&s)),
+            None    => Status::Pending,
+        };
+
+        let priority = match get_str("priority") {
+            Some(p) => Some(try!(Priority::from_str(&p))),
+            None    => None,
+        };
+
+        // Older taskwarrior versions export the dependencies as comma separated string
+        let depends = match get_str("depends") {
+            Some(s) => s.split(',').map(String::from).filter(|s| !s.is_empty()).collect(),
+            None    => get_str_array("depends"),
+        };
+
+        let annotations = obj.get("annotations")
+            .and_then(|v| v.as_array())
+            .map(|a| {
+                a.iter()
+                    .filter_map(|ann| {
+                        let get = |key: &str| -> Option<String> {
+                            ann.as_object()
+                                .and_then(|o| o.get(key))
+                                .and_then(|v| v.as_str())
+                                .map(String::from)
+                        };
+                        let (e, d) = (get("entry"), get("description"));
+                        match (e, d) {
+                            (Some(e), Some(d)) => Some(Annotation { entry: e, description: d }),
+                            _ => None,
+                        }
+                    })
+                    .collect()
+            })
+            .unwrap_or(vec![]);
+
+        Ok(TaskData {
+            uuid: uuid,
+            description: description,
+            status: status,
+            entry: entry,
+            modified: get_str("modified"),
+            end: get_str("end"),
+            due: get_str("due"),
+            scheduled: get_str("scheduled"),
+            wait: get_str("wait"),
+            project: get_str("project"),
+            priority: priority,
+            recur: get_str("recur"),
+            depends: depends,
+            tags: get_str_array("tags"),
+            annotations: annotations,
+        })
+    }
+
+    /// Get the JSON representation of the task, as understood by `task import`
+    pub fn to_json(&self) -> JsonValue {
+        let mut obj = BTreeMap::new();
+        obj.insert(String::from("uuid"), JsonValue::String(self.uuid.clone()));
+        obj.insert(String::from("description"), JsonValue::String(self.description.clone()));
+        obj.insert(String::from("status"), JsonValue::String(format!("{}", self.status)));
+        obj.insert(String::from("entry"), JsonValue::String(self.entry.clone()));
+
+        let priority = self.priority.map(|p| format!("{}", p));
+        let optionals = [
+            ("modified", &self.modified),
+            ("end", &self.end),
+            ("due", &self.due),
+            ("scheduled", &self.scheduled),
+            ("wait", &self.wait),
+            ("project", &self.project),
+            ("priority", &priority),
+            ("recur", &self.recur),
+        ];
+
+        for &(key, value) in optionals.iter() {
+            if let Some(ref s) = *value {
+                obj.insert(String::from(key), JsonValue::String(s.clone()));
+            }
+        }
+
+        if !self.depends.is_empty() {
+            obj.insert(String::from("depends"), JsonValue::String(self.depends.join(",")));
+        }
+        if !self.tags.is_empty() {
+            let tags = self.tags.iter().cloned().map(JsonValue::String).collect();
+            obj.insert(String::from("tags"), JsonValue::Array(tags));
+        }
+        if !self.annotations.is_empty() {
+            let anns = self.annotations
+                .iter()
+                .map(|a| {
+                    let mut ann = BTreeMap::new();
+                    ann.insert(String::from("entry"), JsonValue::String(a.entry.clone()));
+                    ann.insert(String::from("description"), JsonValue::String(a.description.clone()));
+                    JsonValue::Object(ann)
+                })
+                .collect();
+            obj.insert(String::from("annotations"), JsonValue::Array(anns));
+        }
+
+        JsonValue::Object(obj)
+    }
+
+}
+
+/// Get all tasks from taskwarrior by calling `<binary> export`
+pub fn export(binary: &str) -> Result<Vec<TaskData>> {
+    let output = try!(Command::new(binary)
+        .arg("rc.hooks=off")
+        .arg("export")
+        .stdin(Stdio::null())
+        .stderr(Stdio::inherit())
+        .output()
+        .map_err_into(TEK::TaskwarriorCallError));
+
+    if !output.status.success() {
+        return Err(TEK::TaskwarriorCallError.into_error());
+    }
+
+    let json : JsonValue = try!(String::from_utf8(output.stdout)
+        .map_err_into(TEK::ExportError)
+        .and_then(|s| serde_json::from_str(&s).map_err_into(TEK::ExportError)));
+
+    match json {
+        JsonValue::Array(tasks) => tasks.iter().map(TaskData::from_json).collect(),
+        _ => Err(TEK::ExportError.into_error()),
+    }
+}
+
+/// Pass tasks to taskwarrior by calling `<binary> import`.
+///
+/// Hooks are disabled for the call, so the `on-add` and `on-modify` hooks of imag-todo do not
+/// write back into the store while it is being synchronized.
+pub fn import(binary: &str, tasks: &[TaskData]) -> Result<()> {
+    if tasks.is_empty() {
+        return Ok(());
+    }
+
+    let mut child = try!(Command::new(binary)
+        .arg("rc.hooks=off")
+        .arg("import")
+        .arg("-")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::inherit())
+        .spawn()
+        .map_err_into(TEK::TaskwarriorCallError));
+
+    {
+        let stdin = try!(child.stdin.as_mut().ok_or(TEK::TaskwarriorCallError.into_error()));
+        for task in tasks {
+            let line = try!(serde_json::to_string(&task.to_json()).map_err_into(TEK::ConversionError));
+            try!(writeln!(stdin, "{}", line).map_err_into(TEK::TaskwarriorCallError));
+        }
+    }
+
+    let status = try!(child.wait().map_err_into(TEK::TaskwarriorCallError));
+    if status.success() {
+        Ok(())
+    } else {
+        Err(TEK::TaskwarriorCallError.into_error())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json;
+    use serde_json::Value as JsonValue;
+
+    use data::{Annotation, Priority, Status, TaskData};
+
+    fn example() -> TaskData {
+        TaskData {
+            uuid: String::from("a0e4e6c4-1b7a-4a56-8f4f-e7dc3a7d34a1"),
+            description: String::from("Write the sync code"),
+            status: Status::Pending,
+            entry: String::from("20161001T100000Z"),
+            modified: Some(String::from("20161002T100000Z")),
+            end: None,
+            due: Some(String::from("20161031T000000Z")),
+            scheduled: Some(String::from("20161020T000000Z")),
+            wait: None,
+            project: Some(String::from("imag")),
+            priority: Some(Priority::Medium),
+            recur: None,
+            depends: vec![String::from("4c2f1b7e-3d4a-4fbb-9b3c-0d7f3b7e2a11")],
+            tags: vec![String::from("rust"), String::from("todo")],
+            annotations: vec![Annotation {
+                entry: String::from("20161002T100000Z"),
+                description: String::from("started"),
+            }],
+        }
+    }
+
+    #[test]
+    fn test_json_roundtrip() {
+        let t    = example();
+        let json = serde_json::to_string(&t.to_json()).unwrap();
+        let back : JsonValue = serde_json::from_str(&json).unwrap();
+        assert_eq!(t, TaskData::from_json(&back).unwrap());
+    }
+
+    #[test]
+    fn test_json_from_taskwarrior() {
+        let s = r#"{"id":1,"description":"Buy milk","entry":"20161001T100000Z","modified":"20161001T100000Z","status":"pending","uuid":"4c2f1b7e-3d4a-4fbb-9b3c-0d7f3b7e2a11","depends":["a0e4e6c4-1b7a-4a56-8f4f-e7dc3a7d34a1"],"urgency":0.8}"#;
+        let json : JsonValue = serde_json::from_str(s).unwrap();
+        let t = TaskData::from_json(&json).unwrap();
+
+        assert_eq!(t.description, "Buy milk");
+        assert_eq!(t.status, Status::Pending);
+        assert_eq!(t.depends, vec![String::from("a0e4e6c4-1b7a-4a56-8f4f-e7dc3a7d34a1")]);
+        assert!(t.due.is_none());
+        assert!(t.tags.is_empty());
+    }
+
+}
+
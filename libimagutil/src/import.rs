@@ -0,0 +1,100 @@
+//
+// imag - the personal information management suite for the commandline
+// Copyright (C) 2015, 2016 Matthias Beyer <mail@beyermatthias.de> and contributors
+//
+// This library is free software; you can redistribute it and/or
+// modify it under the terms of the GNU Lesser General Public
+// License as published by the Free Software Foundation; version
+// 2.1 of the License.
+//
+// This library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+// Lesser General Public License for more details.
+//
+// You should have received a copy of the GNU Lesser General Public
+// License along with this library; if not, write to the Free Software
+// Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA  02110-1301  USA
+//
+
+//! Helpers for modules which import entries from external files
+//!
+//! Importing the same file twice must not create duplicates, so an importer reports for every
+//! imported entry whether it was created, updated or left untouched. The `ImportSummary` counts
+//! these actions for the report printed by the commandline tools.
+
+use std::fmt::{Display, Formatter, Error as FmtError};
+
+/// What happened when an entry was imported
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImportAction {
+    Created,
+    Updated,
+    Unchanged,
+}
+
+/// Counts of the actions of one or more imports
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct ImportSummary {
+    pub created: usize,
+    pub updated: usize,
+    pub unchanged: usize,
+}
+
+impl ImportSummary {
+
+    pub fn new() -> ImportSummary {
+        ImportSummary::default()
+    }
+
+    /// Count one action
+    pub fn add(&mut self, action: ImportAction) {
+        match action {
+            ImportAction::Created   => self.created   += 1,
+            ImportAction::Updated   => self.updated   += 1,
+            ImportAction::Unchanged => self.unchanged += 1,
+        }
+    }
+
+    /// Count all actions of an import, logging each imported id on the debug level
+    pub fn add_all<I, T>(&mut self, imported: I)
+        where I: IntoIterator<Item = (T, ImportAction)>,
+              T: Display
+    {
+        for (id, action) in imported {
+            debug!("{:?}: {}", action, id);
+            self.add(action);
+        }
+    }
+
+}
+
+impl Display for ImportSummary {
+
+    fn fmt(&self, fmt: &mut Formatter) -> Result<(), FmtError> {
+        write!(fmt, "{} created, {} updated, {} unchanged", self.created, self.updated, self.unchanged)
+    }
+
+}
+
+#[cfg(test)]
+mod test {
+    use super::{ImportAction, ImportSummary};
+
+    #[test]
+    fn test_summary_counts_actions() {
+        let mut summary = ImportSummary::new();
+        summary.add_all(vec![
+            ("a", ImportAction::Created),
+            ("b", ImportAction::Unchanged),
+            ("c", ImportAction::Created),
+            ("d", ImportAction::Updated),
+        ]);
+
+        assert_eq!(2, summary.created);
+        assert_eq!(1, summary.updated);
+        assert_eq!(1, summary.unchanged);
+        assert_eq!("2 created, 1 updated, 1 unchanged", format!("{}", summary));
+    }
+
+}
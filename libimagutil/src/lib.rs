@@ -43,10 +43,12 @@ extern crate tempfile;
 pub mod cli_validators;
 pub mod debug_result;
 pub mod edit;
+pub mod import;
 pub mod info_result;
 pub mod ismatch;
 pub mod iter;
 pub mod key_value_split;
+pub mod quoted;
 pub mod variants;
 pub mod warn_exit;
 pub mod warn_result;
@@ -0,0 +1,72 @@
+//
+// imag - the personal information management suite for the commandline
+// Copyright (C) 2015, 2016 Matthias Beyer <mail@beyermatthias.de> and contributors
+//
+// This library is free software; you can redistribute it and/or
+// modify it under the terms of the GNU Lesser General Public
+// License as published by the Free Software Foundation; version
+// 2.1 of the License.
+//
+// This library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+// Lesser General Public License for more details.
+//
+// You should have received a copy of the GNU Lesser General Public
+// License along with this library; if not, write to the Free Software
+// Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA  02110-1301  USA
+//
+
+//! Helpers for splitting text where double quoted parts must be left alone
+//!
+//! Content lines of vCard and iCalendar files, like `DTSTART;TZID="Europe/Berlin":20161017T090000`,
+//! may contain separators inside quoted parameter values.
+
+/// Split at `sep`, but not inside double quotes
+pub fn split_unquoted(s: &str, sep: char) -> Vec<String> {
+    let mut parts  = vec![String::new()];
+    let mut quoted = false;
+    for c in s.chars() {
+        if c == '"' {
+            quoted = !quoted;
+        }
+
+        if c == sep && !quoted {
+            parts.push(String::new());
+        } else {
+            parts.last_mut().unwrap().push(c); // there is always at least one element
+        }
+    }
+    parts
+}
+
+/// Find the first `c` which is not inside double quotes
+pub fn find_unquoted(s: &str, c: char) -> Option<usize> {
+    let mut quoted = false;
+    for (i, x) in s.char_indices() {
+        if x == '"' {
+            quoted = !quoted;
+        } else if x == c && !quoted {
+            return Some(i);
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_split_unquoted() {
+        assert_eq!(split_unquoted("a;b=\"c;d\";e", ';'), vec!["a", "b=\"c;d\"", "e"]);
+        assert_eq!(split_unquoted("", ';'), vec![""]);
+    }
+
+    #[test]
+    fn test_find_unquoted() {
+        assert_eq!(find_unquoted("A;X=\"a:b\":value", ':'), Some(9));
+        assert_eq!(find_unquoted("\"a:b\"", ':'), None);
+    }
+
+}
@@ -0,0 +1,132 @@
+//
+// imag - the personal information management suite for the commandline
+// Copyright (C) 2015, 2016 Matthias Beyer <mail@beyermatthias.de> and contributors
+//
+// This library is free software; you can redistribute it and/or
+// modify it under the terms of the GNU Lesser General Public
+// License as published by the Free Software Foundation; version
+// 2.1 of the License.
+//
+// This library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+// Lesser General Public License for more details.
+//
+// You should have received a copy of the GNU Lesser General Public
+// License along with this library; if not, write to the Free Software
+// Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA  02110-1301  USA
+//
+
+//! Hook which mirrors the links between wiki pages on every update of a page
+//!
+//! The `WikiLinkHook` updates the links whenever a wiki page is written. It is not registered by
+//! the runtime, `imag-wiki` registers it with `register_wiki_hook()`. Pages which were altered with
+//! other tools, for example with `imag-edit` or `imag-store`, are updated with `imag-wiki
+//! update-links`.
+
+use toml::Value;
+
+use libimagerror::into::IntoError;
+use libimagerror::trace::{trace_error, trace_error_dbg};
+use libimagstore::error::StoreErrorKind;
+use libimagstore::hook::Hook;
+use libimagstore::hook::accessor::HookDataAccessor as HDA;
+use libimagstore::hook::accessor::HookDataAccessorProvider;
+use libimagstore::hook::accessor::MutableHookDataAccessor;
+use libimagstore::hook::error::CustomData;
+use libimagstore::hook::error::HookErrorKind as HEK;
+use libimagstore::hook::position::HookPosition as HP;
+use libimagstore::hook::result::HookResult;
+use libimagstore::store::FileLockEntry;
+use libimagstore::store::Store;
+
+use page::{is_wiki_page, link_mentions, mirror_links};
+use result::Result;
+
+/// Hook which mirrors the markdown links of a wiki page as internal links
+///
+/// Register it for `PreUpdate`, in an aspect which allows mutable hooks, so the links of the page
+/// itself are written together with its text. When a page is written for the first time, the
+/// pages which link to it are linked, too.
+///
+/// Errors from this hook are never aborting, the links can be updated later with `imag-wiki
+/// update-links`.
+///
+/// The pages which are linked are written while the hook runs, so the hook is executed for them,
+/// too. The store does not execute it again for a page it is executed for already.
+#[derive(Debug)]
+pub struct WikiLinkHook;
+
+impl WikiLinkHook {
+
+    pub fn new() -> WikiLinkHook {
+        WikiLinkHook
+    }
+
+    fn update(&self, fle: &mut FileLockEntry) -> Result<()> {
+        let store  = fle.store();
+        let is_new = !fle.get_location().exists(store.backend());
+
+        if is_new {
+            try!(link_mentions(fle, store));
+        }
+        mirror_links(fle, store)
+    }
+
+}
+
+impl Hook for WikiLinkHook {
+
+    fn name(&self) -> &'static str {
+        "wiki_links"
+    }
+
+    fn set_config(&mut self, _: &Value) {
+        () // We are not configurable here.
+    }
+
+}
+
+impl HookDataAccessorProvider for WikiLinkHook {
+
+    fn accessor(&self) -> HDA {
+        HDA::MutableAccess(self)
+    }
+
+}
+
+impl MutableHookDataAccessor for WikiLinkHook {
+
+    fn access_mut(&self, fle: &mut FileLockEntry) -> HookResult<()> {
+        if !is_wiki_page(fle.get_location()) {
+            return Ok(());
+        }
+
+        let id = fle.get_location().clone();
+        debug!("[WIKI HOOK] Updating links of {:?}", id);
+        self.update(fle).map_err(|e| {
+            warn!("Cannot update the links of wiki page {}", id);
+            trace_error(&e);
+            HEK::HookExecutionError
+                .into_error_with_cause(Box::new(e))
+                .with_custom_data(CustomData::default().aborting(false))
+        })
+    }
+
+}
+
+/// Register the `WikiLinkHook` with the store, in the "wiki" aspect at `PreUpdate`
+///
+/// If the registration fails, the error is traced and a warning is printed, the links can still be
+/// updated with `imag-wiki update-links`.
+pub fn register_wiki_hook(store: &mut Store) {
+    let hook = Box::new(WikiLinkHook::new());
+    if let Err(e) = store.register_hook(HP::PreUpdate, &String::from("wiki"), hook) {
+        if e.err_type() == StoreErrorKind::HookRegisterError {
+            trace_error_dbg(&e);
+            warn!("Registering wiki hook with store failed");
+        } else {
+            trace_error(&e);
+        };
+    }
+}
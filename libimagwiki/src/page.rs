@@ -0,0 +1,448 @@
+//
+// imag - the personal information management suite for the commandline
+// Copyright (C) 2015, 2016 Matthias Beyer <mail@beyermatthias.de> and contributors
+//
+// This library is free software; you can redistribute it and/or
+// modify it under the terms of the GNU Lesser General Public
+// License as published by the Free Software Foundation; version
+// 2.1 of the License.
+//
+// This library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+// Lesser General Public License for more details.
+//
+// You should have received a copy of the GNU Lesser General Public
+// License along with this library; if not, write to the Free Software
+// Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA  02110-1301  USA
+//
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::ops::{Deref, DerefMut};
+use std::path::{Component, Path};
+
+use toml::Value;
+
+use libimagentrylink::internal::InternalLinker;
+use libimagentrymarkdown::link::extract_links;
+use libimagstore::storeid::IntoStoreId;
+use libimagstore::storeid::StoreId;
+use libimagstore::storeid::StoreIdIterator;
+use libimagstore::store::Entry;
+use libimagstore::store::FileLockEntry;
+use libimagstore::store::Store;
+use libimagerror::into::IntoError;
+use libimagerror::trace::trace_error;
+
+use module_path::ModuleEntryPath;
+use result::Result;
+use error::WikiErrorKind as WEK;
+use error::MapErrInto;
+
+/// The type of the internal links which mirror the markdown links between wiki pages
+pub const LINK_TYPE : &'static str = "wiki";
+
+/// Get the name of the wiki page a markdown link points to
+///
+/// Links to URLs, anchors and absolute paths do not point to wiki pages. A link may be prefixed
+/// with `wiki/` and may have an anchor, so `Foo`, `wiki/Foo` and `Foo#section` all point to the
+/// page "Foo".
+pub fn page_name_from_link(link: &str) -> Option<String> {
+    if link.is_empty() || link.starts_with("#") || link.starts_with("/") || link.contains("://") ||
+        link.starts_with("mailto:")
+    {
+        return None;
+    }
+
+    let name = link.split('#').next().unwrap_or("");
+    let name = if name.starts_with("wiki/") { &name["wiki/".len()..] } else { name };
+    let name = name.trim_right_matches('/');
+
+    let valid = Path::new(name).components().all(|c| match c {
+        Component::Normal(_) => true,
+        _                    => false,
+    });
+
+    if !name.is_empty() && valid {
+        Some(String::from(name))
+    } else {
+        None
+    }
+}
+
+/// Get the ids of all wiki pages the markdown links in `text` point to
+///
+/// The pages are not required to exist.
+pub fn linked_page_ids(text: &str) -> BTreeSet<StoreId> {
+    extract_links(text)
+        .into_iter()
+        .filter_map(|link| page_name_from_link(&link.link))
+        .filter_map(|name| ModuleEntryPath::new(name).into_storeid().ok())
+        .collect()
+}
+
+/// Whether the entry `id` is a wiki page
+pub fn is_wiki_page(id: &StoreId) -> bool {
+    id.clone().without_base().local().starts_with("wiki")
+}
+
+/// Synchronize the internal links of the wiki page `entry` with the markdown links in its text
+///
+/// Pages which are linked in the text get linked with a link of the type `LINK_TYPE`, a link of
+/// another type between the pages becomes a `LINK_TYPE` link. Links to pages which are not linked
+/// in the text anymore are removed, unless the other page links back to this one. Links to pages
+/// which do not exist are ignored.
+pub fn mirror_links(entry: &mut Entry, store: &Store) -> Result<()> {
+    let own    = entry.get_location().clone().without_base();
+    let wanted = linked_page_ids(entry.get_content());
+    let links  = try!(entry.get_internal_links_by_type(LINK_TYPE).map_err_into(WEK::LinkError));
+
+    let linked = links.iter().map(|l| l.get_link().clone()).collect::<BTreeSet<_>>();
+    let outgoing = links
+        .iter()
+        .filter(|l| !l.is_incoming())
+        .map(|l| l.get_link().clone())
+        .collect::<BTreeSet<_>>();
+
+    for id in wanted.iter().filter(|id| **id != own && !linked.contains(*id)) {
+        match try!(store.get(id.clone()).map_err_into(WEK::StoreReadError)) {
+            Some(mut target) => {
+                debug!("Linking wiki page {} to {}", own, id);
+                try!(entry
+                     .add_typed_internal_link(&mut target, LINK_TYPE, BTreeMap::new())
+                     .map_err_into(WEK::LinkError));
+            },
+            None => debug!("Wiki page {} does not exist, not linking it", id),
+        }
+    }
+
+    for id in outgoing.iter().filter(|id| !wanted.contains(*id)) {
+        let mut target = match try!(store.get(id.clone()).map_err_into(WEK::StoreReadError)) {
+            Some(target) => target,
+            None         => continue,
+        };
+
+        if linked_page_ids(target.get_content()).contains(&own) {
+            debug!("Wiki page {} still links to {}, keeping the link", id, own);
+            try!(target
+                 .add_typed_internal_link(entry, LINK_TYPE, BTreeMap::new())
+                 .map_err_into(WEK::LinkError));
+        } else {
+            debug!("Unlinking wiki page {} from {}", own, id);
+            try!(entry.remove_internal_link(&mut target).map_err_into(WEK::LinkError));
+        }
+    }
+
+    Ok(())
+}
+
+/// Link the wiki pages which link to the page `entry` in their text to it
+///
+/// Links to pages which do not exist are ignored by `mirror_links()`, so they have to be added
+/// when the page is created.
+pub fn link_mentions(entry: &mut Entry, store: &Store) -> Result<()> {
+    let own = entry.get_location().clone().without_base();
+
+    for id in try!(store.retrieve_for_module("wiki").map_err_into(WEK::StoreReadError)) {
+        let id = id.without_base();
+        if id == own {
+            continue;
+        }
+
+        // Only the pages which link to this page are borrowed from the store
+        let mentions = match store.retrieve_copy(id.clone()) {
+            Ok(page) => linked_page_ids(page.get_content()).contains(&own),
+            Err(e) => {
+                warn!("Cannot read wiki page {}, not checking it for links", id);
+                trace_error(&e);
+                continue;
+            },
+        };
+
+        if mentions {
+            if let Some(mut page) = try!(store.get(id.clone()).map_err_into(WEK::StoreReadError)) {
+                debug!("Linking wiki page {} to {}", id, own);
+                try!(page
+                     .add_typed_internal_link(entry, LINK_TYPE, BTreeMap::new())
+                     .map_err_into(WEK::LinkError));
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// A wiki page
+///
+/// Wiki pages are stored in the `wiki` collection, the name of a page is its path in there.
+/// Markdown links between pages are mirrored as internal links of the type `LINK_TYPE` by
+/// `WikiPage::update_links()`, and on every update of a page by the `WikiLinkHook`.
+#[derive(Debug)]
+pub struct WikiPage<'a> {
+    entry: FileLockEntry<'a>,
+}
+
+impl<'a> WikiPage<'a> {
+
+    /// Create a new wiki page and link it to the pages its text links to and the pages which link
+    /// to it
+    pub fn new(store: &'a Store, name: String, text: String) -> Result<WikiPage<'a>> {
+        debug!("Creating new wiki page: '{}'", name);
+        let mut entry = try!(ModuleEntryPath::new(name.clone())
+            .into_storeid()
+            .and_then(|id| store.create(id))
+            .map_err_into(WEK::StoreWriteError));
+
+        {
+            let header = entry.get_header_mut();
+            try!(header.set("wiki", Value::Table(BTreeMap::new())).map_err_into(WEK::StoreWriteError));
+            try!(header.set("wiki.name", Value::String(name)).map_err_into(WEK::StoreWriteError));
+        }
+        *entry.get_content_mut() = text;
+
+        try!(link_mentions(&mut entry, store));
+        let mut page = WikiPage { entry: entry };
+        try!(page.update_links(store));
+        Ok(page)
+    }
+
+    pub fn get(store: &'a Store, name: String) -> Result<Option<WikiPage<'a>>> {
+        ModuleEntryPath::new(name)
+            .into_storeid()
+            .and_then(|id| store.get(id))
+            .map_err_into(WEK::StoreReadError)
+            .map(|o| o.map(|entry| WikiPage { entry: entry }))
+    }
+
+    /// Delete a wiki page, removing all links to it
+    pub fn delete(store: &Store, name: String) -> Result<()> {
+        let id = try!(ModuleEntryPath::new(name).into_storeid().map_err_into(WEK::StoreReadError));
+
+        {
+            let mut entry = match try!(store.get(id.clone()).map_err_into(WEK::StoreReadError)) {
+                Some(entry) => entry,
+                None        => return Err(WEK::PageNotFound.into_error()),
+            };
+
+            let links = try!(entry.get_internal_links().map_err_into(WEK::LinkError));
+            for link in links {
+                if let Some(mut other) = try!(store.get(link).map_err_into(WEK::StoreReadError)) {
+                    try!(entry.remove_internal_link(&mut other).map_err_into(WEK::LinkError));
+                }
+            }
+        }
+
+        store.delete(id).map_err_into(WEK::StoreWriteError)
+    }
+
+    pub fn all_pages(store: &'a Store) -> Result<WikiPageIterator<'a>> {
+        store.retrieve_for_module("wiki")
+            .map(|iter| WikiPageIterator::new(store, iter))
+            .map_err_into(WEK::StoreReadError)
+    }
+
+    pub fn get_name(&self) -> Result<String> {
+        match self.entry.get_header().read("wiki.name") {
+            Ok(Some(Value::String(s))) => Ok(s),
+            Ok(_)  => Err(WEK::HeaderTypeError.into_error()),
+            Err(e) => Err(WEK::StoreReadError.into_error_with_cause(Box::new(e))),
+        }
+    }
+
+    pub fn get_text(&self) -> &String {
+        self.entry.get_content()
+    }
+
+    /// Set the text of the page, call `update_links()` afterwards to update the links
+    pub fn set_text(&mut self, text: String) {
+        *self.entry.get_content_mut() = text
+    }
+
+    /// Synchronize the internal links of the page with the markdown links in its text
+    ///
+    /// See `mirror_links()`.
+    pub fn update_links(&mut self, store: &Store) -> Result<()> {
+        mirror_links(&mut self.entry, store)
+    }
+
+    /// Get the ids of all pages which link to this page in their text
+    pub fn backlinks(&self, store: &Store) -> Result<Vec<StoreId>> {
+        let own   = self.entry.get_location().clone().without_base();
+        let links = try!(self.entry.get_internal_links_by_type(LINK_TYPE).map_err_into(WEK::LinkError));
+
+        let mut backlinks = vec![];
+        for link in links {
+            if link.is_incoming() {
+                backlinks.push(link.get_link().clone());
+                continue;
+            }
+
+            // Both pages might link to each other, but the link is recorded in one direction only
+            if let Some(page) = try!(store.get(link.get_link().clone()).map_err_into(WEK::StoreReadError)) {
+                if linked_page_ids(page.get_content()).contains(&own) {
+                    backlinks.push(link.get_link().clone());
+                }
+            }
+        }
+
+        Ok(backlinks)
+    }
+
+}
+
+impl<'a> Deref for WikiPage<'a> {
+
+    type Target = FileLockEntry<'a>;
+
+    fn deref(&self) -> &FileLockEntry<'a> {
+        &self.entry
+    }
+
+}
+
+impl<'a> DerefMut for WikiPage<'a> {
+
+    fn deref_mut(&mut self) -> &mut FileLockEntry<'a> {
+        &mut self.entry
+    }
+
+}
+
+#[derive(Debug)]
+pub struct WikiPageIterator<'a> {
+    store: &'a Store,
+    iditer: StoreIdIterator,
+}
+
+impl<'a> WikiPageIterator<'a> {
+
+    pub fn new(store: &'a Store, iditer: StoreIdIterator) -> WikiPageIterator<'a> {
+        WikiPageIterator {
+            store: store,
+            iditer: iditer,
+        }
+    }
+
+}
+
+impl<'a> Iterator for WikiPageIterator<'a> {
+    type Item = Result<WikiPage<'a>>;
+
+    fn next(&mut self) -> Option<Result<WikiPage<'a>>> {
+        self.iditer
+            .next()
+            .map(|id| {
+                self.store
+                    .retrieve(id)
+                    .map(|entry| WikiPage { entry: entry })
+                    .map_err_into(WEK::StoreReadError)
+            })
+    }
+
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::PathBuf;
+
+    use libimagstore::store::Store;
+    use libimagstore::file_abstraction::InMemoryBackend;
+    use libimagstore::storeid::StoreId;
+    use libimagentrylink::internal::InternalLinker;
+
+    use super::*;
+
+    fn get_store() -> Store {
+        Store::new(PathBuf::from("/"), None, Box::new(InMemoryBackend::new())).unwrap()
+    }
+
+    fn id(name: &str) -> StoreId {
+        StoreId::new_baseless(PathBuf::from(format!("wiki/{}", name))).unwrap()
+    }
+
+    fn set_text(store: &Store, name: &str, text: &str) {
+        let mut page = WikiPage::get(store, String::from(name)).unwrap().unwrap();
+        page.set_text(String::from(text));
+        page.update_links(store).unwrap();
+    }
+
+    fn backlinks(store: &Store, name: &str) -> Vec<StoreId> {
+        WikiPage::get(store, String::from(name)).unwrap().unwrap().backlinks(store).unwrap()
+    }
+
+    #[test]
+    fn test_page_name_from_link() {
+        assert_eq!(page_name_from_link("Foo"), Some(String::from("Foo")));
+        assert_eq!(page_name_from_link("wiki/Foo#bar"), Some(String::from("Foo")));
+        assert_eq!(page_name_from_link("projects/x"), Some(String::from("projects/x")));
+        assert_eq!(page_name_from_link("http://example.com"), None);
+        assert_eq!(page_name_from_link("#anchor"), None);
+        assert_eq!(page_name_from_link("../etc"), None);
+    }
+
+    #[test]
+    fn test_links_follow_text() {
+        let store = get_store();
+        let _ = WikiPage::new(&store, String::from("B"), String::new()).unwrap();
+        let _ = WikiPage::new(&store, String::from("A"), String::from("See [B](B) and [C](C)")).unwrap();
+
+        assert_eq!(backlinks(&store, "B"), vec![id("A")]);
+        assert!(backlinks(&store, "A").is_empty());
+
+        set_text(&store, "A", "Nothing here");
+        assert!(backlinks(&store, "B").is_empty());
+        let a = WikiPage::get(&store, String::from("A")).unwrap().unwrap();
+        assert_eq!(a.get_internal_links().unwrap().count(), 0);
+    }
+
+    #[test]
+    fn test_mutual_links() {
+        let store = get_store();
+        let _ = WikiPage::new(&store, String::from("A"), String::new()).unwrap();
+        let _ = WikiPage::new(&store, String::from("B"), String::from("[A](A)")).unwrap();
+        set_text(&store, "A", "[B](wiki/B)");
+
+        assert_eq!(backlinks(&store, "A"), vec![id("B")]);
+        assert_eq!(backlinks(&store, "B"), vec![id("A")]);
+
+        // B still links to A, so the link is kept
+        set_text(&store, "A", "");
+        assert_eq!(backlinks(&store, "A"), vec![id("B")]);
+        assert!(backlinks(&store, "B").is_empty());
+
+        set_text(&store, "B", "");
+        assert!(backlinks(&store, "A").is_empty());
+    }
+
+    #[test]
+    fn test_mentions_linked_on_create() {
+        let store = get_store();
+        let _ = WikiPage::new(&store, String::from("A"), String::from("See [B](B)")).unwrap();
+        assert!(WikiPage::get(&store, String::from("A")).unwrap().unwrap()
+                .get_internal_links().unwrap().next().is_none());
+
+        let _ = WikiPage::new(&store, String::from("B"), String::new()).unwrap();
+        assert_eq!(backlinks(&store, "B"), vec![id("A")]);
+    }
+
+    #[test]
+    fn test_wiki_link_replaces_plain_link() {
+        let store = get_store();
+        let _ = WikiPage::new(&store, String::from("A"), String::new()).unwrap();
+        let _ = WikiPage::new(&store, String::from("B"), String::new()).unwrap();
+        {
+            let mut a = WikiPage::get(&store, String::from("A")).unwrap().unwrap();
+            let mut b = WikiPage::get(&store, String::from("B")).unwrap().unwrap();
+            a.add_internal_link(&mut b).unwrap();
+        }
+
+        set_text(&store, "A", "[B](B)");
+        assert_eq!(backlinks(&store, "B"), vec![id("A")]);
+
+        let a     = WikiPage::get(&store, String::from("A")).unwrap().unwrap();
+        let links = a.get_typed_internal_links().unwrap();
+        assert_eq!(links.len(), 1);
+        assert_eq!(links[0].get_kind(), Some(LINK_TYPE));
+    }
+
+}